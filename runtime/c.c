@@ -0,0 +1,444 @@
+// The runtime for programs compiled with coolc --target=c
+// coolc copies this file to the start of every program that it writes, so a
+// program is built with nothing but a C compiler:
+//     cc name.c -o name
+//
+// It takes the place of trap.handler: the methods of the basic classes and the
+// routines that the generated code calls when a program fails are here.
+// Messages match those of trap.handler (and of coolc run). Memory is never reclaimed.
+
+#define _XOPEN_SOURCE 700 // For sigaltstack and setrlimit
+
+#include <signal.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#if defined(__unix__) || defined(__APPLE__)
+#define COOL_UNIX
+#include <sys/resource.h>
+#endif
+
+// Methods have different types, so dispatch tables hold them as this type
+// and they are converted back to their real type when called.
+typedef void (*cool_method)(void);
+
+// Every object starts with these fields
+typedef struct cool_object {
+    intptr_t tag;
+    intptr_t size; // In bytes
+    const cool_method *disp_tab;
+} object;
+
+// Int and Bool
+struct cool_int {
+    object header;
+    int32_t val;
+};
+
+struct cool_string {
+    object header;
+    struct cool_int *len;
+    char chars[]; // Followed by a null
+};
+
+// A string constant of n - 1 characters (laid out as struct cool_string)
+#define COOL_STRING(n)         \
+    struct {                   \
+        object header;         \
+        struct cool_int *len;  \
+        char chars[n];         \
+    }
+
+// An entry of class_objTab (used by new SELF_TYPE)
+struct cool_class {
+    object *proto;
+    object *(*init)(object *);
+};
+
+// Defined by the generated code
+extern struct cool_int Int_protObj;
+extern struct cool_string String_protObj;
+extern struct cool_int bool_const0;
+extern struct cool_int bool_const1;
+extern object *const class_nameTab[];
+
+static void cool_main(void);
+
+#define MAX_STACK_SIZE (64 << 20) // Bytes
+
+// Printed when a program finishes (as trap.handler does)
+static const char TERM_MSG[] = "\nCOOL program successfully executed\n";
+
+// An error that COOL programs cannot catch (written to standard error like coolc run)
+static void fail(const char *msg) {
+    fflush(stdout);
+    fprintf(stderr, "%s\n", msg);
+    exit(1);
+}
+
+// A runtime error reported by the program itself
+static void halt(void) {
+    fflush(stdout);
+    exit(1);
+}
+
+static void *alloc(size_t size) {
+    void *obj = malloc(size);
+
+    if (obj == NULL) {
+        fail("Out of memory");
+    }
+
+    return obj;
+}
+
+static object *copy(object *obj) {
+    if (obj == NULL) {
+        fail("Object.copy called on void");
+    }
+
+    object *new_obj = alloc(obj->size);
+    memcpy(new_obj, obj, obj->size);
+
+    return new_obj;
+}
+
+static object *new_int(int32_t val) {
+    struct cool_int *obj = (struct cool_int *)copy(&Int_protObj.header);
+    obj->val = val;
+
+    return &obj->header;
+}
+
+static object *new_string(const char *val, size_t len) {
+    // The characters are followed by a null
+    size_t size = offsetof(struct cool_string, chars) + len + 1;
+
+    struct cool_string *obj = alloc(size);
+    obj->header = String_protObj.header;
+    obj->header.size = size;
+    obj->len = (struct cool_int *)new_int(len);
+    memcpy(obj->chars, val, len);
+    obj->chars[len] = '\0';
+
+    return &obj->header;
+}
+
+// The value of an Int or Bool
+int32_t cool_int_val(object *obj) {
+    return ((struct cool_int *)obj)->val;
+}
+
+static struct cool_string *string_val(object *obj) {
+    return (struct cool_string *)obj;
+}
+
+static struct cool_string *class_name(object *obj) {
+    return string_val(class_nameTab[obj->tag]);
+}
+
+static void write_string(struct cool_string *s) {
+    fwrite(s->chars, 1, s->len->val, stdout);
+}
+
+// Read a line without its newline (the result is null terminated)
+static char *read_line(size_t *len) {
+    // Prompts must appear before the program waits for input
+    fflush(stdout);
+
+    size_t cap = 64;
+    size_t n = 0;
+    char *line = alloc(cap);
+    int c;
+
+    while ((c = getchar()) != EOF && c != '\n') {
+        if (n + 1 == cap) {
+            cap *= 2;
+            line = realloc(line, cap);
+
+            if (line == NULL) {
+                fail("Out of memory");
+            }
+        }
+
+        line[n++] = (char)c;
+    }
+
+    line[n] = '\0';
+    *len = n;
+
+    return line;
+}
+
+object *Object__abort(object *self) {
+    printf("Abort called from class ");
+    write_string(class_name(self));
+    printf("\n");
+    halt();
+
+    return self;
+}
+
+object *Object__type_name(object *self) {
+    return &class_name(self)->header;
+}
+
+object *Object__copy(object *self) {
+    return copy(self);
+}
+
+object *IO__out_string(object *self, object *arg) {
+    write_string(string_val(arg));
+
+    return self;
+}
+
+object *IO__out_int(object *self, object *arg) {
+    printf("%ld", (long)cool_int_val(arg));
+
+    return self;
+}
+
+object *IO__in_string(object *self) {
+    (void)self;
+
+    size_t len;
+    char *line = read_line(&len);
+
+    // A string with a null character is read as the empty string
+    if (memchr(line, '\0', len) != NULL) {
+        len = 0;
+    }
+
+    object *obj = new_string(line, len);
+    free(line);
+
+    return obj;
+}
+
+object *IO__in_int(object *self) {
+    (void)self;
+
+    size_t len;
+    char *line = read_line(&len);
+    char *p = line;
+
+    while (*p == ' ' || (*p >= '\t' && *p <= '\r')) {
+        p++;
+    }
+
+    // Like atoi, leading digits are read and the rest is ignored
+    // A number too big for 64 bits is read as 0.
+    int negative = *p == '-';
+    if (*p == '-' || *p == '+') {
+        p++;
+    }
+
+    int64_t val = 0;
+    for (; *p >= '0' && *p <= '9'; p++) {
+        int64_t digit = *p - '0';
+
+        if (negative ? val < (INT64_MIN + digit) / 10 : val > (INT64_MAX - digit) / 10) {
+            val = 0;
+            break;
+        }
+
+        val = negative ? val * 10 - digit : val * 10 + digit;
+    }
+
+    free(line);
+
+    // Only the low 32 bits are kept
+    return new_int((int32_t)(uint32_t)val);
+}
+
+object *String__length(object *self) {
+    return new_int(string_val(self)->len->val);
+}
+
+object *String__concat(object *self, object *arg) {
+    struct cool_string *s = string_val(self);
+    struct cool_string *t = string_val(arg);
+    size_t len = s->len->val;
+    size_t arg_len = t->len->val;
+
+    char *val = alloc(len + arg_len + 1);
+    memcpy(val, s->chars, len);
+    memcpy(val + len, t->chars, arg_len);
+
+    object *obj = new_string(val, len + arg_len);
+    free(val);
+
+    return obj;
+}
+
+object *String__substr(object *self, object *index, object *len) {
+    struct cool_string *s = string_val(self);
+    int64_t i = cool_int_val(index);
+    int64_t l = cool_int_val(len);
+    int64_t self_len = s->len->val;
+
+    if (i < 0) {
+        printf("Index to substr is negative\n");
+    } else if (i > self_len) {
+        printf("Index to substr is too big\n");
+    } else if (l < 0) {
+        printf("Length to substr is negative\n");
+    } else if (i + l > self_len) {
+        printf("Length to substr too long\n");
+    } else {
+        return new_string(s->chars + i, l);
+    }
+
+    printf("Execution aborted.\n");
+    halt();
+
+    return NULL;
+}
+
+// The routines below are called by the generated code
+
+object *cool_bool(int val) {
+    return val ? &bool_const1.header : &bool_const0.header;
+}
+
+// Compare two objects that are known to be different objects
+// Returns true (1) if they have the same basic value.
+int cool_equality_test(object *lhs, object *rhs) {
+    if (lhs == NULL || rhs == NULL || lhs->tag != rhs->tag) {
+        return 0;
+    }
+
+    if (lhs->tag == Int_protObj.header.tag || lhs->tag == bool_const0.header.tag) {
+        return cool_int_val(lhs) == cool_int_val(rhs);
+    }
+
+    if (lhs->tag == String_protObj.header.tag) {
+        struct cool_string *lhs_str = string_val(lhs);
+        struct cool_string *rhs_str = string_val(rhs);
+
+        return lhs_str->len->val == rhs_str->len->val &&
+               memcmp(lhs_str->chars, rhs_str->chars, lhs_str->len->val) == 0;
+    }
+
+    return 0;
+}
+
+// Int operations (each returns a new Int)
+// Addition, subtraction and negation stop the program on overflow, but
+// multiplication wraps around (as MIPS mul does).
+
+object *cool_add(object *lhs, object *rhs) {
+    int64_t val = (int64_t)cool_int_val(lhs) + cool_int_val(rhs);
+
+    if (val < INT32_MIN || val > INT32_MAX) {
+        fail("Arithmetic overflow");
+    }
+
+    return new_int((int32_t)val);
+}
+
+object *cool_sub(object *lhs, object *rhs) {
+    int64_t val = (int64_t)cool_int_val(lhs) - cool_int_val(rhs);
+
+    if (val < INT32_MIN || val > INT32_MAX) {
+        fail("Arithmetic overflow");
+    }
+
+    return new_int((int32_t)val);
+}
+
+object *cool_mul(object *lhs, object *rhs) {
+    return new_int((int32_t)((uint32_t)cool_int_val(lhs) * (uint32_t)cool_int_val(rhs)));
+}
+
+object *cool_div(object *lhs, object *rhs) {
+    int32_t divisor = cool_int_val(rhs);
+
+    if (divisor == 0) {
+        fail("Division by zero");
+    }
+
+    // Dividing the smallest Int by -1 wraps around
+    if (divisor == -1) {
+        return new_int((int32_t)(0 - (uint32_t)cool_int_val(lhs)));
+    }
+
+    return new_int(cool_int_val(lhs) / divisor);
+}
+
+object *cool_neg(object *obj) {
+    if (cool_int_val(obj) == INT32_MIN) {
+        fail("Arithmetic overflow");
+    }
+
+    return new_int(-cool_int_val(obj));
+}
+
+void cool_dispatch_abort(object *file_name, int line_no) {
+    write_string(string_val(file_name));
+    printf(":%d: Dispatch to void.\n", line_no);
+    halt();
+}
+
+// obj is the object that did not match
+void cool_case_abort(object *obj) {
+    printf("No match in case statement for Class ");
+    write_string(class_name(obj));
+    printf("\n");
+    halt();
+}
+
+void cool_case_abort2(object *file_name, int line_no) {
+    write_string(string_val(file_name));
+    printf(":%d: Match on void in case statement.\n", line_no);
+    halt();
+}
+
+#ifdef COOL_UNIX
+// The only memory fault that a COOL program can cause is running out of stack
+static void stack_overflow(int sig) {
+    (void)sig;
+
+    fail("Stack overflow");
+}
+#endif
+
+int main(void) {
+#ifdef COOL_UNIX
+    // Allow as deep a recursion as coolc run does (if the hard limit permits)
+    struct rlimit rl;
+    if (getrlimit(RLIMIT_STACK, &rl) == 0 && rl.rlim_cur != RLIM_INFINITY &&
+        rl.rlim_cur < MAX_STACK_SIZE) {
+        rl.rlim_cur = rl.rlim_max == RLIM_INFINITY || rl.rlim_max > MAX_STACK_SIZE
+                          ? MAX_STACK_SIZE
+                          : rl.rlim_max;
+        setrlimit(RLIMIT_STACK, &rl);
+    }
+
+    // The handler needs a stack of its own
+    static char handler_stack[1 << 16];
+    stack_t ss;
+    memset(&ss, 0, sizeof(ss));
+    ss.ss_sp = handler_stack;
+    ss.ss_size = sizeof(handler_stack);
+    sigaltstack(&ss, NULL);
+
+    struct sigaction sa;
+    memset(&sa, 0, sizeof(sa));
+    sa.sa_handler = stack_overflow;
+    sa.sa_flags = SA_ONSTACK;
+    sigaction(SIGSEGV, &sa, NULL);
+#endif
+
+    cool_main();
+
+    printf("%s", TERM_MSG);
+
+    return 0;
+}
+
+// The program
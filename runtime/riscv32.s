@@ -0,0 +1,738 @@
+# The runtime for programs compiled with coolc --target=riscv32
+# It takes the place of trap.handler: the program starts here, and the methods of
+# the basic classes and the routines that the generated code jumps to when a
+# program fails are here. Messages match those of trap.handler (and of coolc run).
+#
+# It runs on RV32IM Linux (only the write, read, exit and brk system calls are used):
+#     riscv64-unknown-elf-gcc -march=rv32im -mabi=ilp32 -nostdlib name.s runtime/riscv32.s -o name
+#     qemu-riscv32 name
+# coolc run --target=riscv32 runs the program with this runtime without either.
+#
+# The conventions are those of trap.handler: self and the result are in a0, and
+# arguments are passed on the stack (the last argument is at 4(sp)) and are
+# removed by the routine. s0 (the frame pointer), s1 (self) and sp are preserved;
+# the other registers may be changed. Memory is never reclaimed.
+
+	.data
+	.align 2
+
+# The heap is the memory between the end of the program and the break
+heap_ptr:
+	.word 0
+heap_end:
+	.word 0
+
+# Standard input is read in blocks
+input_buf:
+	.word 0
+input_pos:
+	.word 0
+input_end:
+	.word 0
+
+term_msg:
+	.asciz "\nCOOL program successfully executed\n"
+abort_msg:
+	.asciz "Abort called from class "
+dispatch_abort_msg:
+	.asciz ": Dispatch to void.\n"
+case_abort_msg:
+	.asciz "No match in case statement for Class "
+case_abort2_msg:
+	.asciz ": Match on void in case statement.\n"
+substr_index_negative_msg:
+	.asciz "Index to substr is negative\n"
+substr_index_too_big_msg:
+	.asciz "Index to substr is too big\n"
+substr_length_negative_msg:
+	.asciz "Length to substr is negative\n"
+substr_length_too_long_msg:
+	.asciz "Length to substr too long\n"
+execution_aborted_msg:
+	.asciz "Execution aborted.\n"
+arithmetic_overflow_msg:
+	.asciz "Arithmetic overflow\n"
+division_by_zero_msg:
+	.asciz "Division by zero\n"
+out_of_memory_msg:
+	.asciz "Out of memory\n"
+newline_msg:
+	.asciz "\n"
+colon_msg:
+	.asciz ":"
+
+	.align 2
+
+	.text
+	.globl _start
+	.globl Object.abort
+	.globl Object.type_name
+	.globl Object.copy
+	.globl IO.out_string
+	.globl IO.out_int
+	.globl IO.in_string
+	.globl IO.in_int
+	.globl String.length
+	.globl String.concat
+	.globl String.substr
+	.globl equality_test
+	.globl _dispatch_abort
+	.globl _case_abort
+	.globl _case_abort2
+	.globl _arithmetic_overflow
+	.globl _division_by_zero
+
+# Create a Main object and call its main method
+_start:
+	li a0, 0
+	li a7, 214 # brk
+	ecall
+	la t0, heap_ptr
+	sw a0, 0(t0)
+	la t0, heap_end
+	sw a0, 0(t0)
+
+	li a0, 4096
+	call _alloc
+	la t0, input_buf
+	sw a0, 0(t0)
+	la t0, input_pos
+	sw a0, 0(t0)
+	la t0, input_end
+	sw a0, 0(t0)
+
+	la a0, Main_protObj
+	call Object.copy
+	call Main_init
+	call Main.main
+
+	la a0, term_msg
+	call _write_cstr
+	li a0, 0
+	j _exit
+
+# Helpers (these take their arguments in a0-a2)
+
+# Stop the program with the status in a0
+_exit:
+	li a7, 93 # exit
+	ecall
+
+# Write a1 bytes at a0 to the file descriptor in a2
+_write_fd:
+	mv t0, a0
+	mv t1, a1
+	mv t2, a2
+_write_fd_loop:
+	beqz t1, _write_fd_done
+	mv a0, t2
+	mv a1, t0
+	mv a2, t1
+	li a7, 64 # write
+	ecall
+	blez a0, _write_fd_done # Output that cannot be written is lost
+	add t0, t0, a0
+	sub t1, t1, a0
+	j _write_fd_loop
+_write_fd_done:
+	ret
+
+# Write a1 bytes at a0 to standard output
+_write:
+	li a2, 1
+	j _write_fd
+
+# Write the null-terminated string at a0 to the file descriptor in a1
+_write_cstr_fd:
+	mv t0, a0
+_write_cstr_fd_loop:
+	lbu t1, 0(t0)
+	beqz t1, _write_cstr_fd_end
+	addi t0, t0, 1
+	j _write_cstr_fd_loop
+_write_cstr_fd_end:
+	mv a2, a1
+	sub a1, t0, a0
+	j _write_fd
+
+# Write the null-terminated string at a0 to standard output
+_write_cstr:
+	li a1, 1
+	j _write_cstr_fd
+
+# Write the String object in a0 to standard output
+_write_string:
+	lw a1, 12(a0)
+	lw a1, 12(a1)
+	addi a0, a0, 16
+	j _write
+
+# Write the number in a0 to standard output
+_write_int:
+	addi sp, sp, -20
+	sw ra, 16(sp)
+	addi t0, sp, 16 # The digits are written backwards from here
+	li t2, 10
+	mv t1, a0
+	bgez a0, _write_int_loop
+	neg t1, a0 # Unsigned, so the smallest Int is negated correctly
+_write_int_loop:
+	remu t3, t1, t2
+	divu t1, t1, t2
+	addi t3, t3, 48 # '0'
+	addi t0, t0, -1
+	sb t3, 0(t0)
+	bnez t1, _write_int_loop
+	bgez a0, _write_int_digits
+	li t3, 45 # '-'
+	addi t0, t0, -1
+	sb t3, 0(t0)
+_write_int_digits:
+	mv a0, t0
+	addi a1, sp, 16
+	sub a1, a1, t0
+	call _write
+	lw ra, 16(sp)
+	addi sp, sp, 20
+	ret
+
+# The name of the class of the object in a0
+_class_name:
+	lw t0, 0(a0)
+	slli t0, t0, 2
+	la t1, class_nameTab
+	add t1, t1, t0
+	lw a0, 0(t1)
+	ret
+
+# Move the break so that the heap ends at or after a0 (which is not changed)
+_grow:
+	la t0, heap_end
+	lw t1, 0(t0)
+	bleu a0, t1, _grow_done
+	mv t2, a0
+	li t1, 65536 # Grow in large steps
+	add a0, a0, t1
+	li a7, 214 # brk
+	ecall
+	bltu a0, t2, _out_of_memory
+	la t0, heap_end
+	sw a0, 0(t0)
+	mv a0, t2
+_grow_done:
+	ret
+
+_out_of_memory:
+	la a0, out_of_memory_msg
+	li a1, 2
+	call _write_cstr_fd
+	li a0, 1
+	j _exit
+
+# Allocate a0 bytes (word aligned)
+_alloc:
+	addi sp, sp, -8
+	sw ra, 4(sp)
+	la t0, heap_ptr
+	lw t1, 0(t0)
+	addi t1, t1, 3
+	andi t1, t1, -4
+	sw t1, 0(sp)
+	add a0, t1, a0
+	call _grow
+	la t0, heap_ptr
+	sw a0, 0(t0)
+	lw a0, 0(sp)
+	lw ra, 4(sp)
+	addi sp, sp, 8
+	ret
+
+# Copy a2 bytes from a1 to a0
+_memcpy:
+	beqz a2, _memcpy_done
+	lbu t0, 0(a1)
+	sb t0, 0(a0)
+	addi a0, a0, 1
+	addi a1, a1, 1
+	addi a2, a2, -1
+	j _memcpy
+_memcpy_done:
+	ret
+
+# A new Int object with the value in a0
+_new_int:
+	addi sp, sp, -8
+	sw ra, 4(sp)
+	sw a0, 0(sp)
+	la a0, Int_protObj
+	call Object.copy
+	lw t0, 0(sp)
+	sw t0, 12(a0)
+	lw ra, 4(sp)
+	addi sp, sp, 8
+	ret
+
+# A new String object of length a0 (the characters are not set)
+_alloc_string:
+	addi sp, sp, -12
+	sw ra, 8(sp)
+	sw a0, 4(sp)
+	call _new_int
+	sw a0, 0(sp)
+
+	# The characters are followed by a null
+	lw t0, 4(sp)
+	srli t0, t0, 2
+	addi t0, t0, 5
+	sw t0, 4(sp) # The size (in words)
+	addi a0, t0, 1 # The object is preceded by an eye catcher (for the garbage collector)
+	slli a0, a0, 2
+	call _alloc
+
+	li t0, -1
+	sw t0, 0(a0)
+	addi a0, a0, 4
+	la t1, String_protObj
+	lw t0, 0(t1)
+	sw t0, 0(a0)
+	lw t0, 4(sp)
+	sw t0, 4(a0)
+	lw t0, 8(t1)
+	sw t0, 8(a0)
+	lw t0, 0(sp)
+	sw t0, 12(a0)
+	lw t0, 12(t0)
+	add t0, a0, t0
+	sb zero, 16(t0)
+
+	lw ra, 8(sp)
+	addi sp, sp, 12
+	ret
+
+# A new String object with the a1 characters at a0
+_new_string:
+	addi sp, sp, -16
+	sw ra, 12(sp)
+	sw a0, 8(sp)
+	sw a1, 4(sp)
+	mv a0, a1
+	call _alloc_string
+	sw a0, 0(sp)
+	addi a0, a0, 16
+	lw a1, 8(sp)
+	lw a2, 4(sp)
+	call _memcpy
+	lw a0, 0(sp)
+	lw ra, 12(sp)
+	addi sp, sp, 16
+	ret
+
+# The next byte of standard input (-1 at the end)
+_getc:
+	la t0, input_pos
+	lw t1, 0(t0)
+	la t2, input_end
+	lw t3, 0(t2)
+	bltu t1, t3, _getc_buffered
+	li a0, 0 # stdin
+	la t1, input_buf
+	lw a1, 0(t1)
+	li a2, 4096
+	li a7, 63 # read
+	ecall
+	blez a0, _getc_eof
+	lw t1, 0(t1)
+	add t3, t1, a0
+	sw t3, 0(t2)
+_getc_buffered:
+	lbu a0, 0(t1)
+	addi t1, t1, 1
+	sw t1, 0(t0)
+	ret
+_getc_eof:
+	li a0, -1
+	ret
+
+# Read a line without its newline
+# The characters are placed on the heap (a0 is their address and a1 is their
+# number) but are not part of an object.
+_read_line:
+	addi sp, sp, -12
+	sw ra, 8(sp)
+	la t0, heap_ptr
+	lw t1, 0(t0)
+	sw t1, 4(sp) # The start of the line
+	sw t1, 0(sp) # The end of the line
+_read_line_loop:
+	call _getc
+	li t0, -1
+	beq a0, t0, _read_line_done
+	li t0, 10 # '\n'
+	beq a0, t0, _read_line_done
+	mv t4, a0 # _grow does not change t4
+	lw a0, 0(sp)
+	addi a0, a0, 1
+	sw a0, 0(sp)
+	call _grow
+	lw t0, 0(sp)
+	sb t4, -1(t0)
+	j _read_line_loop
+_read_line_done:
+	lw a0, 4(sp)
+	lw a1, 0(sp)
+	la t0, heap_ptr
+	sw a1, 0(t0)
+	sub a1, a1, a0
+	lw ra, 8(sp)
+	addi sp, sp, 12
+	ret
+
+# Stop the program after a runtime error (the message has been written)
+_halt:
+	li a0, 1
+	j _exit
+
+# The methods of the basic classes
+
+Object.abort:
+	mv s1, a0
+	la a0, abort_msg
+	call _write_cstr
+	mv a0, s1
+	call _class_name
+	call _write_string
+	la a0, newline_msg
+	call _write_cstr
+	j _halt
+
+Object.type_name:
+	j _class_name
+
+Object.copy:
+	addi sp, sp, -8
+	sw ra, 4(sp)
+	sw a0, 0(sp)
+	lw a0, 4(a0)
+	addi a0, a0, 1 # The object is preceded by an eye catcher (for the garbage collector)
+	slli a0, a0, 2
+	call _alloc
+	li t0, -1
+	sw t0, 0(a0)
+	addi a0, a0, 4
+	lw t0, 0(sp)
+	lw t1, 4(t0)
+	slli t1, t1, 2
+	add t1, t0, t1 # The end of the object
+	mv t2, a0
+_copy_loop:
+	lw t3, 0(t0)
+	sw t3, 0(t2)
+	addi t0, t0, 4
+	addi t2, t2, 4
+	bltu t0, t1, _copy_loop
+	lw ra, 4(sp)
+	addi sp, sp, 8
+	ret
+
+IO.out_string:
+	addi sp, sp, -8
+	sw ra, 4(sp)
+	sw a0, 0(sp)
+	lw a0, 12(sp)
+	call _write_string
+	lw a0, 0(sp)
+	lw ra, 4(sp)
+	addi sp, sp, 12
+	ret
+
+IO.out_int:
+	addi sp, sp, -8
+	sw ra, 4(sp)
+	sw a0, 0(sp)
+	lw a0, 12(sp)
+	lw a0, 12(a0)
+	call _write_int
+	lw a0, 0(sp)
+	lw ra, 4(sp)
+	addi sp, sp, 12
+	ret
+
+IO.in_string:
+	addi sp, sp, -12
+	sw ra, 8(sp)
+	call _read_line
+	sw a0, 4(sp)
+
+	# A string with a null character is read as the empty string
+	mv t0, a0
+	add t1, a0, a1
+_in_string_loop:
+	beq t0, t1, _in_string_done
+	lbu t2, 0(t0)
+	addi t0, t0, 1
+	bnez t2, _in_string_loop
+	li a1, 0
+_in_string_done:
+	call _new_string
+	lw ra, 8(sp)
+	addi sp, sp, 12
+	ret
+
+# Like atoi, leading digits are read and the rest is ignored
+# The value is computed with 64 bits (in t4 and t3) and its low 32 bits are used.
+# A number too big for 64 bits is read as 0.
+IO.in_int:
+	addi sp, sp, -8
+	sw ra, 4(sp)
+	call _read_line
+	sw a0, 0(sp)
+	add t1, a0, a1 # The end of the line
+	mv t0, a0
+
+	# Skip white space
+	li t5, 32 # ' '
+	li t6, 9 # '\t'
+	li a3, 13 # '\r'
+_in_int_space:
+	beq t0, t1, _in_int_sign
+	lbu t2, 0(t0)
+	beq t2, t5, _in_int_next_space
+	blt t2, t6, _in_int_sign
+	bgt t2, a3, _in_int_sign
+_in_int_next_space:
+	addi t0, t0, 1
+	j _in_int_space
+
+_in_int_sign:
+	li a2, 0 # Negative
+	beq t0, t1, _in_int_digits
+	lbu t2, 0(t0)
+	li t5, 45 # '-'
+	beq t2, t5, _in_int_negative
+	li t5, 43 # '+'
+	bne t2, t5, _in_int_digits
+	addi t0, t0, 1
+	j _in_int_digits
+_in_int_negative:
+	li a2, 1
+	addi t0, t0, 1
+
+_in_int_digits:
+	li t3, 0 # The low word of the magnitude
+	li t4, 0 # The high word
+	li t6, 10
+_in_int_loop:
+	beq t0, t1, _in_int_done
+	lbu t2, 0(t0)
+	addi t2, t2, -48 # '0'
+	bgeu t2, t6, _in_int_done
+
+	# Multiply by 10 (the high word must not overflow)
+	mulhu t5, t4, t6
+	bnez t5, _in_int_overflow
+	mul t4, t4, t6
+	mulhu t5, t3, t6
+	mul t3, t3, t6
+	add t5, t4, t5
+	bltu t5, t4, _in_int_overflow
+	mv t4, t5
+
+	# Add the digit
+	add t3, t3, t2
+	sltu t5, t3, t2
+	add t5, t4, t5
+	bltu t5, t4, _in_int_overflow
+	mv t4, t5
+
+	# The magnitude may be 2^63 only for a negative number
+	bgez t4, _in_int_next
+	beqz a2, _in_int_overflow
+	li t5, 0x80000000
+	bne t4, t5, _in_int_overflow
+	bnez t3, _in_int_overflow
+_in_int_next:
+	addi t0, t0, 1
+	j _in_int_loop
+
+_in_int_overflow:
+	li t3, 0
+	li a2, 0
+_in_int_done:
+	mv a0, t3
+	beqz a2, _in_int_value
+	neg a0, t3
+_in_int_value:
+	# The line is not needed anymore
+	lw t0, 0(sp)
+	la t1, heap_ptr
+	sw t0, 0(t1)
+
+	call _new_int
+	lw ra, 4(sp)
+	addi sp, sp, 8
+	ret
+
+String.length:
+	lw a0, 12(a0)
+	lw a0, 12(a0)
+	j _new_int
+
+String.concat:
+	addi sp, sp, -12
+	sw ra, 8(sp)
+	sw a0, 4(sp)
+	lw t0, 12(a0)
+	lw t0, 12(t0)
+	lw t1, 16(sp)
+	lw t1, 12(t1)
+	lw t1, 12(t1)
+	add a0, t0, t1
+	call _alloc_string
+	sw a0, 0(sp)
+
+	addi a0, a0, 16
+	lw a1, 4(sp)
+	lw a2, 12(a1)
+	lw a2, 12(a2)
+	addi a1, a1, 16
+	call _memcpy
+	lw a1, 16(sp)
+	lw a2, 12(a1)
+	lw a2, 12(a2)
+	addi a1, a1, 16
+	call _memcpy
+
+	lw a0, 0(sp)
+	lw ra, 8(sp)
+	addi sp, sp, 16
+	ret
+
+String.substr:
+	addi sp, sp, -4
+	sw ra, 0(sp)
+	lw t0, 12(a0)
+	lw t0, 12(t0) # The length of self
+	lw t1, 12(sp)
+	lw t1, 12(t1) # The index
+	lw t2, 8(sp)
+	lw t2, 12(t2) # The length of the substring
+
+	la a1, substr_index_negative_msg
+	bltz t1, _substr_abort
+	la a1, substr_index_too_big_msg
+	bgt t1, t0, _substr_abort
+	la a1, substr_length_negative_msg
+	bltz t2, _substr_abort
+	la a1, substr_length_too_long_msg
+	add t3, t1, t2 # Both are not negative, so this does not overflow (unsigned)
+	bgtu t3, t0, _substr_abort
+
+	addi a0, a0, 16
+	add a0, a0, t1
+	mv a1, t2
+	call _new_string
+	lw ra, 0(sp)
+	addi sp, sp, 12
+	ret
+
+_substr_abort:
+	mv a0, a1
+	call _write_cstr
+	la a0, execution_aborted_msg
+	call _write_cstr
+	j _halt
+
+# Compare the objects in t1 and t2 (which are known to be different objects)
+# Returns a0 if they are equal and a1 if they are not.
+equality_test:
+	beqz t1, _equality_false
+	beqz t2, _equality_false
+	lw t3, 0(t1)
+	lw t4, 0(t2)
+	bne t3, t4, _equality_false
+
+	la t0, _int_tag
+	lw t0, 0(t0)
+	beq t3, t0, _equality_int
+	la t0, _bool_tag
+	lw t0, 0(t0)
+	beq t3, t0, _equality_int
+	la t0, _string_tag
+	lw t0, 0(t0)
+	bne t3, t0, _equality_false
+
+	lw t3, 12(t1)
+	lw t3, 12(t3)
+	lw t4, 12(t2)
+	lw t4, 12(t4)
+	bne t3, t4, _equality_false
+	addi t1, t1, 16
+	addi t2, t2, 16
+_equality_string_loop:
+	beqz t3, _equality_true
+	lbu t4, 0(t1)
+	lbu t5, 0(t2)
+	bne t4, t5, _equality_false
+	addi t1, t1, 1
+	addi t2, t2, 1
+	addi t3, t3, -1
+	j _equality_string_loop
+
+_equality_int:
+	lw t3, 12(t1)
+	lw t4, 12(t2)
+	bne t3, t4, _equality_false
+_equality_true:
+	ret
+_equality_false:
+	mv a0, a1
+	ret
+
+# a0 is the name of the file and t1 is the line number
+_dispatch_abort:
+	la a1, dispatch_abort_msg
+	j _abort_at_line
+
+# a0 is the object that did not match
+_case_abort:
+	mv s1, a0
+	la a0, case_abort_msg
+	call _write_cstr
+	mv a0, s1
+	call _class_name
+	call _write_string
+	la a0, newline_msg
+	call _write_cstr
+	j _halt
+
+# a0 is the name of the file and t1 is the line number
+_case_abort2:
+	la a1, case_abort2_msg
+	j _abort_at_line
+
+# Write the file name in a0, the line number in t1 and the message in a1
+_abort_at_line:
+	mv s1, t1
+	mv s0, a1
+	call _write_string
+	la a0, colon_msg
+	call _write_cstr
+	mv a0, s1
+	call _write_int
+	mv a0, s0
+	call _write_cstr
+	j _halt
+
+# Int operations that fail (written to standard error like coolc run)
+_arithmetic_overflow:
+	la a0, arithmetic_overflow_msg
+	j _fail
+
+_division_by_zero:
+	la a0, division_by_zero_msg
+	j _fail
+
+_fail:
+	li a1, 2
+	call _write_cstr_fd
+	j _halt
@@ -0,0 +1,111 @@
+// The host for programs compiled with coolc --target=wasm
+// It provides the imports of runtime/wasm.wat and works in a browser or in Node.js.
+// In a page:
+//     import { runCool } from "./wasm.mjs";
+//     const { output, errors, status } = await runCool(wasmBytes, inputText);
+// From the command line (standard input is read before the program starts):
+//     node runtime/wasm.mjs name.wasm
+
+// Thrown to stop the program
+class Exit {
+    constructor(status) {
+        this.status = status;
+    }
+}
+
+// Run a compiled module (the bytes of a .wasm file)
+// Input is a string or bytes. Output is returned as bytes and errors as a string.
+// The status is 0 if the program finished and 1 if it was stopped by an error.
+export async function runCool(wasm, input = "") {
+    if (typeof input === "string") {
+        input = new TextEncoder().encode(input);
+    }
+
+    let memory = null;
+    let inputPos = 0;
+    let line = new Uint8Array(0); // The line that was read last
+    const output = [];
+    let errors = "";
+
+    const bytes = (ptr, len) => new Uint8Array(memory.buffer, ptr, len);
+
+    const imports = {
+        cool: {
+            out(ptr, len) {
+                output.push(bytes(ptr, len).slice());
+            },
+            fail(ptr, len) {
+                errors += new TextDecoder().decode(bytes(ptr, len)) + "\n";
+                throw new Exit(1);
+            },
+            halt() {
+                throw new Exit(1);
+            },
+            read_line() {
+                let end = input.indexOf(10, inputPos);
+                if (end < 0) {
+                    end = input.length;
+                }
+
+                line = input.subarray(inputPos, end);
+                inputPos = Math.min(end + 1, input.length);
+
+                return line.length;
+            },
+            copy_line(ptr) {
+                bytes(ptr, line.length).set(line);
+            },
+        },
+    };
+
+    const { instance } = await WebAssembly.instantiate(wasm, imports);
+    memory = instance.exports.memory;
+
+    let status = 0;
+    try {
+        instance.exports.main();
+    } catch (e) {
+        if (e instanceof Exit) {
+            status = e.status;
+        } else if (e instanceof RangeError || e.name === "InternalError") {
+            // The engine ran out of stack ("too much recursion" in Firefox)
+            errors += "Stack overflow\n";
+            status = 1;
+        } else {
+            throw e;
+        }
+    }
+
+    // Output written before an error is kept
+    const all = new Uint8Array(output.reduce((len, chunk) => len + chunk.length, 0));
+    let pos = 0;
+    for (const chunk of output) {
+        all.set(chunk, pos);
+        pos += chunk.length;
+    }
+
+    return { output: all, errors, status };
+}
+
+// Run from the command line
+if (typeof process !== "undefined" && process.versions && process.versions.node) {
+    const { pathToFileURL } = await import("node:url");
+
+    if (process.argv[1] && import.meta.url === pathToFileURL(process.argv[1]).href) {
+        const fs = await import("node:fs");
+
+        if (process.argv.length !== 3) {
+            process.stderr.write("Usage: node wasm.mjs name.wasm\n");
+            process.exit(2);
+        }
+
+        const { output, errors, status } = await runCool(
+            fs.readFileSync(process.argv[2]),
+            fs.readFileSync(0),
+        );
+
+        fs.writeSync(1, output);
+        process.stderr.write(errors);
+        process.exitCode = status;
+    }
+}
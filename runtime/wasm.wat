@@ -0,0 +1,431 @@
+;; The runtime for programs compiled with coolc --target=wasm
+;; coolc copies this file to the start of every module that it writes (the
+;; generated code follows and closes the module), so a program is converted with
+;;     wat2wasm name.wat -o name.wasm
+;; and run by a host that provides the imports below (see runtime/wasm.mjs).
+;;
+;; It takes the place of trap.handler: the methods of the basic classes and the
+;; routines that the generated code calls when a program fails are here.
+;; Objects have the MIPS layout with 4-byte words (tag, size in words, dispatch
+;; table, then attributes), and void is address 0. A dispatch table holds
+;; indexes into the function table. Memory is never reclaimed.
+;;
+;; Addresses below 512 belong to the runtime; the generated data starts there.
+;; The generated code defines $heap (the first free address) and the addresses
+;; $Int_protObj, $String_protObj, $bool_const0, $bool_const1 and $class_nameTab.
+
+(module
+  ;; Write bytes to standard output
+  (import "cool" "out" (func $out (param $ptr i32) (param $len i32)))
+  ;; Write a message and a newline to standard error and stop (exit status 1)
+  (import "cool" "fail" (func $fail (param $ptr i32) (param $len i32)))
+  ;; Stop after a runtime error that the program has reported (exit status 1)
+  (import "cool" "halt" (func $halt))
+  ;; Read a line of standard input (without its newline) and return its length
+  (import "cool" "read_line" (func $read_line (result i32)))
+  ;; Copy the line that was read last to memory
+  (import "cool" "copy_line" (func $copy_line (param $ptr i32)))
+
+  ;; Messages (as trap.handler writes them)
+  (data (i32.const 16) "Abort called from class ")                ;; 24 bytes
+  (data (i32.const 40) "\n")                                      ;; 1
+  (data (i32.const 41) ": Dispatch to void.\n")                   ;; 20
+  (data (i32.const 61) ": Match on void in case statement.\n")    ;; 35
+  (data (i32.const 96) "No match in case statement for Class ")   ;; 37
+  (data (i32.const 133) "Index to substr is negative\n")          ;; 28
+  (data (i32.const 161) "Index to substr is too big\n")           ;; 27
+  (data (i32.const 188) "Length to substr is negative\n")         ;; 29
+  (data (i32.const 217) "Length to substr too long\n")            ;; 26
+  (data (i32.const 243) "Execution aborted.\n")                   ;; 19
+  (data (i32.const 262) "Arithmetic overflow")                    ;; 19
+  (data (i32.const 281) "Division by zero")                       ;; 16
+  (data (i32.const 297) "Out of memory")                          ;; 13
+  (data (i32.const 310) "\nCOOL program successfully executed\n") ;; 36
+
+  ;; Room for the digits of an Int (written backwards from the end)
+  (global $digits_end i32 (i32.const 368))
+
+  ;; Called by the host
+  ;; Create a Main object and call its main method (see $cool_main).
+  (func (export "main")
+    (call $cool_main)
+    (call $out (i32.const 310) (i32.const 36)))
+
+  ;; Allocate bytes (a multiple of 4) on the heap
+  (func $alloc (param $size i32) (result i32)
+    (local $obj i32)
+    (local $end i32)
+    (local.set $obj (global.get $heap))
+    (local.set $end (i32.add (local.get $obj) (local.get $size)))
+    (if (i32.gt_u (local.get $end) (i32.shl (memory.size) (i32.const 16)))
+      (then
+        ;; Grow by at least a quarter so that growing is rare
+        (if (i32.lt_s
+              (memory.grow
+                (i32.add
+                  (i32.shr_u
+                    (i32.sub (local.get $end) (i32.shl (memory.size) (i32.const 16)))
+                    (i32.const 16))
+                  (i32.add (i32.shr_u (memory.size) (i32.const 2)) (i32.const 1))))
+              (i32.const 0))
+          (then
+            (call $fail (i32.const 297) (i32.const 13))))))
+    (global.set $heap (local.get $end))
+    (local.get $obj))
+
+  (func $copy (param $obj i32) (result i32)
+    (local $new i32)
+    (local $i i32)
+    (local $size i32)
+    (local.set $size (i32.shl (i32.load offset=4 (local.get $obj)) (i32.const 2)))
+    (local.set $new (call $alloc (local.get $size)))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (local.get $size)))
+        (i32.store
+          (i32.add (local.get $new) (local.get $i))
+          (i32.load (i32.add (local.get $obj) (local.get $i))))
+        (local.set $i (i32.add (local.get $i) (i32.const 4)))
+        (br $next)))
+    (local.get $new))
+
+  (func $copy_bytes (param $to i32) (param $from i32) (param $len i32)
+    (local $i i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (local.get $len)))
+        (i32.store8
+          (i32.add (local.get $to) (local.get $i))
+          (i32.load8_u (i32.add (local.get $from) (local.get $i))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next))))
+
+  (func $new_int (param $val i32) (result i32)
+    (local $obj i32)
+    (local.set $obj (call $copy (global.get $Int_protObj)))
+    (i32.store offset=12 (local.get $obj) (local.get $val))
+    (local.get $obj))
+
+  ;; A string of len characters that are not yet set (followed by a null)
+  (func $alloc_string (param $len i32) (result i32)
+    (local $obj i32)
+    (local $size i32)
+    (local.set $size
+      (i32.add (i32.const 4) (i32.shr_u (i32.add (local.get $len) (i32.const 4)) (i32.const 2))))
+    (local.set $obj (call $alloc (i32.shl (local.get $size) (i32.const 2))))
+    (i32.store (local.get $obj) (i32.load (global.get $String_protObj)))
+    (i32.store offset=4 (local.get $obj) (local.get $size))
+    (i32.store offset=8 (local.get $obj) (i32.load offset=8 (global.get $String_protObj)))
+    (i32.store offset=12 (local.get $obj) (call $new_int (local.get $len)))
+    (i32.store8 (i32.add (local.get $obj) (i32.add (i32.const 16) (local.get $len))) (i32.const 0))
+    (local.get $obj))
+
+  (func $new_string (param $chars i32) (param $len i32) (result i32)
+    (local $obj i32)
+    (local.set $obj (call $alloc_string (local.get $len)))
+    (call $copy_bytes
+      (i32.add (local.get $obj) (i32.const 16)) (local.get $chars) (local.get $len))
+    (local.get $obj))
+
+  ;; The value of an Int or Bool
+  (func $int_val (param $obj i32) (result i32)
+    (i32.load offset=12 (local.get $obj)))
+
+  (func $string_len (param $obj i32) (result i32)
+    (call $int_val (i32.load offset=12 (local.get $obj))))
+
+  (func $class_name (param $obj i32) (result i32)
+    (i32.load
+      (i32.add (global.get $class_nameTab) (i32.shl (i32.load (local.get $obj)) (i32.const 2)))))
+
+  (func $write_string (param $obj i32)
+    (call $out (i32.add (local.get $obj) (i32.const 16)) (call $string_len (local.get $obj))))
+
+  (func $write_int (param $val i32)
+    (local $n i64)
+    (local $ptr i32)
+    (local.set $n (i64.extend_i32_s (local.get $val)))
+    (if (i64.lt_s (local.get $n) (i64.const 0))
+      (then
+        (local.set $n (i64.sub (i64.const 0) (local.get $n)))))
+    (local.set $ptr (global.get $digits_end))
+    (loop $next
+      (local.set $ptr (i32.sub (local.get $ptr) (i32.const 1)))
+      (i64.store8
+        (local.get $ptr)
+        (i64.add (i64.const 48) (i64.rem_u (local.get $n) (i64.const 10))))
+      (local.set $n (i64.div_u (local.get $n) (i64.const 10)))
+      (br_if $next (i64.ne (local.get $n) (i64.const 0))))
+    (if (i32.lt_s (local.get $val) (i32.const 0))
+      (then
+        (local.set $ptr (i32.sub (local.get $ptr) (i32.const 1)))
+        (i32.store8 (local.get $ptr) (i32.const 45))))
+    (call $out (local.get $ptr) (i32.sub (global.get $digits_end) (local.get $ptr))))
+
+  ;; Read a line into a new string
+  (func $read_string (result i32)
+    (local $obj i32)
+    (local.set $obj (call $alloc_string (call $read_line)))
+    (call $copy_line (i32.add (local.get $obj) (i32.const 16)))
+    (local.get $obj))
+
+  (func $Object.abort (param $self i32) (result i32)
+    (call $out (i32.const 16) (i32.const 24))
+    (call $write_string (call $class_name (local.get $self)))
+    (call $out (i32.const 40) (i32.const 1))
+    (call $halt)
+    (local.get $self))
+
+  (func $Object.type_name (param $self i32) (result i32)
+    (call $class_name (local.get $self)))
+
+  (func $Object.copy (param $self i32) (result i32)
+    (call $copy (local.get $self)))
+
+  (func $IO.out_string (param $self i32) (param $arg i32) (result i32)
+    (call $write_string (local.get $arg))
+    (local.get $self))
+
+  (func $IO.out_int (param $self i32) (param $arg i32) (result i32)
+    (call $write_int (call $int_val (local.get $arg)))
+    (local.get $self))
+
+  (func $IO.in_string (param $self i32) (result i32)
+    (local $obj i32)
+    (local $i i32)
+    (local $len i32)
+    (local.set $obj (call $read_string))
+    (local.set $len (call $string_len (local.get $obj)))
+    ;; A string with a null character is read as the empty string
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (local.get $len)))
+        (if (i32.eqz (i32.load8_u offset=16 (i32.add (local.get $obj) (local.get $i))))
+          (then
+            (return (call $alloc_string (i32.const 0)))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (local.get $obj))
+
+  (func $IO.in_int (param $self i32) (result i32)
+    (local $p i32)
+    (local $end i32)
+    (local $c i32)
+    (local $negative i32)
+    (local $val i64)
+    (local $digit i64)
+    (local.set $p (i32.add (call $read_string) (i32.const 16)))
+    ;; The line is followed by a null
+    (local.set $c (i32.load8_u (local.get $p)))
+    (block $done
+      (loop $next
+        (br_if $done
+          (i32.eqz
+            (i32.or
+              (i32.eq (local.get $c) (i32.const 32))
+              (i32.le_u (i32.sub (local.get $c) (i32.const 9)) (i32.const 4)))))
+        (local.set $p (i32.add (local.get $p) (i32.const 1)))
+        (local.set $c (i32.load8_u (local.get $p)))
+        (br $next)))
+    ;; Like atoi, leading digits are read and the rest is ignored
+    ;; A number too big for 64 bits is read as 0.
+    (local.set $negative (i32.eq (local.get $c) (i32.const 45)))
+    (if (i32.or (local.get $negative) (i32.eq (local.get $c) (i32.const 43)))
+      (then
+        (local.set $p (i32.add (local.get $p) (i32.const 1)))))
+    (block $done
+      (loop $next
+        (local.set $c (i32.sub (i32.load8_u (local.get $p)) (i32.const 48)))
+        (br_if $done (i32.gt_u (local.get $c) (i32.const 9)))
+        (local.set $digit (i64.extend_i32_u (local.get $c)))
+        (if (local.get $negative)
+          (then
+            (if (i64.lt_s
+                  (local.get $val)
+                  (i64.div_s
+                    (i64.add (i64.const 0x8000000000000000) (local.get $digit))
+                    (i64.const 10)))
+              (then
+                (local.set $val (i64.const 0))
+                (br $done)))
+            (local.set $val
+              (i64.sub (i64.mul (local.get $val) (i64.const 10)) (local.get $digit))))
+          (else
+            (if (i64.gt_s
+                  (local.get $val)
+                  (i64.div_s
+                    (i64.sub (i64.const 0x7fffffffffffffff) (local.get $digit))
+                    (i64.const 10)))
+              (then
+                (local.set $val (i64.const 0))
+                (br $done)))
+            (local.set $val
+              (i64.add (i64.mul (local.get $val) (i64.const 10)) (local.get $digit)))))
+        (local.set $p (i32.add (local.get $p) (i32.const 1)))
+        (br $next)))
+    ;; Only the low 32 bits are kept
+    (call $new_int (i32.wrap_i64 (local.get $val))))
+
+  (func $String.length (param $self i32) (result i32)
+    (call $new_int (call $string_len (local.get $self))))
+
+  (func $String.concat (param $self i32) (param $arg i32) (result i32)
+    (local $obj i32)
+    (local $len i32)
+    (local.set $len (call $string_len (local.get $self)))
+    (local.set $obj
+      (call $alloc_string (i32.add (local.get $len) (call $string_len (local.get $arg)))))
+    (call $copy_bytes
+      (i32.add (local.get $obj) (i32.const 16))
+      (i32.add (local.get $self) (i32.const 16))
+      (local.get $len))
+    (call $copy_bytes
+      (i32.add (local.get $obj) (i32.add (i32.const 16) (local.get $len)))
+      (i32.add (local.get $arg) (i32.const 16))
+      (call $string_len (local.get $arg)))
+    (local.get $obj))
+
+  (func $String.substr (param $self i32) (param $index i32) (param $len i32) (result i32)
+    (local $i i64)
+    (local $l i64)
+    (local $self_len i64)
+    (local.set $i (i64.extend_i32_s (call $int_val (local.get $index))))
+    (local.set $l (i64.extend_i32_s (call $int_val (local.get $len))))
+    (local.set $self_len (i64.extend_i32_s (call $string_len (local.get $self))))
+    (if (i64.lt_s (local.get $i) (i64.const 0))
+      (then
+        (call $out (i32.const 133) (i32.const 28)))
+      (else
+        (if (i64.gt_s (local.get $i) (local.get $self_len))
+          (then
+            (call $out (i32.const 161) (i32.const 27)))
+          (else
+            (if (i64.lt_s (local.get $l) (i64.const 0))
+              (then
+                (call $out (i32.const 188) (i32.const 29)))
+              (else
+                (if (i64.gt_s (i64.add (local.get $i) (local.get $l)) (local.get $self_len))
+                  (then
+                    (call $out (i32.const 217) (i32.const 26)))
+                  (else
+                    (return
+                      (call $new_string
+                        (i32.add
+                          (i32.add (local.get $self) (i32.const 16))
+                          (i32.wrap_i64 (local.get $i)))
+                        (i32.wrap_i64 (local.get $l))))))))))))
+    (call $out (i32.const 243) (i32.const 19))
+    (call $halt)
+    (i32.const 0))
+
+  ;; The routines below are called by the generated code
+
+  (func $cool_bool (param $val i32) (result i32)
+    (select (global.get $bool_const1) (global.get $bool_const0) (local.get $val)))
+
+  ;; lhs = rhs (the same object, or basic objects with the same value)
+  (func $cool_equal (param $lhs i32) (param $rhs i32) (result i32)
+    (local $tag i32)
+    (if (i32.eq (local.get $lhs) (local.get $rhs))
+      (then
+        (return (global.get $bool_const1))))
+    (if (i32.or (i32.eqz (local.get $lhs)) (i32.eqz (local.get $rhs)))
+      (then
+        (return (global.get $bool_const0))))
+    (local.set $tag (i32.load (local.get $lhs)))
+    (if (i32.ne (local.get $tag) (i32.load (local.get $rhs)))
+      (then
+        (return (global.get $bool_const0))))
+    (if (i32.or
+          (i32.eq (local.get $tag) (i32.load (global.get $Int_protObj)))
+          (i32.eq (local.get $tag) (i32.load (global.get $bool_const0))))
+      (then
+        (return
+          (call $cool_bool
+            (i32.eq (call $int_val (local.get $lhs)) (call $int_val (local.get $rhs)))))))
+    (if (i32.eq (local.get $tag) (i32.load (global.get $String_protObj)))
+      (then
+        (return (call $cool_bool (call $string_equal (local.get $lhs) (local.get $rhs))))))
+    (global.get $bool_const0))
+
+  (func $string_equal (param $lhs i32) (param $rhs i32) (result i32)
+    (local $i i32)
+    (local $len i32)
+    (local.set $len (call $string_len (local.get $lhs)))
+    (if (i32.ne (local.get $len) (call $string_len (local.get $rhs)))
+      (then
+        (return (i32.const 0))))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (local.get $len)))
+        (if (i32.ne
+              (i32.load8_u offset=16 (i32.add (local.get $lhs) (local.get $i)))
+              (i32.load8_u offset=16 (i32.add (local.get $rhs) (local.get $i))))
+          (then
+            (return (i32.const 0))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (i32.const 1))
+
+  ;; Int operations (each returns a new Int)
+  ;; Addition, subtraction and negation stop the program on overflow, but
+  ;; multiplication wraps around (as MIPS mul does).
+
+  (func $overflow (param $val i64) (result i32)
+    (if (i64.ne (local.get $val) (i64.extend_i32_s (i32.wrap_i64 (local.get $val))))
+      (then
+        (call $fail (i32.const 262) (i32.const 19))))
+    (call $new_int (i32.wrap_i64 (local.get $val))))
+
+  (func $cool_add (param $lhs i32) (param $rhs i32) (result i32)
+    (call $overflow
+      (i64.add
+        (i64.extend_i32_s (call $int_val (local.get $lhs)))
+        (i64.extend_i32_s (call $int_val (local.get $rhs))))))
+
+  (func $cool_sub (param $lhs i32) (param $rhs i32) (result i32)
+    (call $overflow
+      (i64.sub
+        (i64.extend_i32_s (call $int_val (local.get $lhs)))
+        (i64.extend_i32_s (call $int_val (local.get $rhs))))))
+
+  (func $cool_mul (param $lhs i32) (param $rhs i32) (result i32)
+    (call $new_int (i32.mul (call $int_val (local.get $lhs)) (call $int_val (local.get $rhs)))))
+
+  (func $cool_div (param $lhs i32) (param $rhs i32) (result i32)
+    (local $divisor i32)
+    (local.set $divisor (call $int_val (local.get $rhs)))
+    (if (i32.eqz (local.get $divisor))
+      (then
+        (call $fail (i32.const 281) (i32.const 16))))
+    ;; Dividing the smallest Int by -1 wraps around
+    (if (i32.eq (local.get $divisor) (i32.const -1))
+      (then
+        (return (call $new_int (i32.sub (i32.const 0) (call $int_val (local.get $lhs)))))))
+    (call $new_int (i32.div_s (call $int_val (local.get $lhs)) (local.get $divisor))))
+
+  (func $cool_neg (param $obj i32) (result i32)
+    (call $overflow (i64.sub (i64.const 0) (i64.extend_i32_s (call $int_val (local.get $obj))))))
+
+  (func $cool_dispatch_abort (param $file_name i32) (param $line_no i32)
+    (call $write_string (local.get $file_name))
+    (call $out (i32.const 41) (i32.const 1))
+    (call $write_int (local.get $line_no))
+    (call $out (i32.const 41) (i32.const 20))
+    (call $halt))
+
+  ;; obj is the object that did not match
+  (func $cool_case_abort (param $obj i32)
+    (call $out (i32.const 96) (i32.const 37))
+    (call $write_string (call $class_name (local.get $obj)))
+    (call $out (i32.const 40) (i32.const 1))
+    (call $halt))
+
+  (func $cool_case_abort2 (param $file_name i32) (param $line_no i32)
+    (call $write_string (local.get $file_name))
+    (call $out (i32.const 61) (i32.const 1))
+    (call $write_int (local.get $line_no))
+    (call $out (i32.const 61) (i32.const 35))
+    (call $halt))
+
+  ;; The program
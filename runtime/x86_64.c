@@ -0,0 +1,336 @@
+// The runtime for programs compiled with coolc --target=x86-64 (or --emit=llvm-ir)
+// It takes the place of trap.handler: the methods of the basic classes and the
+// routines that the generated code jumps to when a program fails are here.
+// Messages match those of trap.handler (and of coolc run).
+//
+// Build a program with
+//     cc name.s runtime/x86_64.c -o name
+//
+// The generated code calls these functions through small stubs that follow the
+// System V calling convention, so they are plain C. Memory is never reclaimed.
+
+#define _GNU_SOURCE
+
+#include <signal.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+
+// Every object starts with these fields (each 8 bytes)
+typedef struct object {
+    int64_t tag;
+    int64_t size; // In words, including these fields
+    void **disp_tab;
+} object;
+
+// Int and Bool
+typedef struct int_object {
+    object header;
+    int32_t val; // The generated code only uses the low 32 bits
+    int32_t pad;
+} int_object;
+
+typedef struct string_object {
+    object header;
+    int_object *len;
+    char chars[]; // Followed by a null
+} string_object;
+
+#define WORD_SIZE 8
+#define HEADER_WORDS 4 // The words of a String before its characters
+
+// Defined by the generated code
+extern int64_t _int_tag;
+extern int64_t _bool_tag;
+extern int64_t _string_tag;
+extern string_object *class_nameTab[];
+extern int_object Int_protObj;
+extern string_object String_protObj;
+
+void cool_main(void);
+
+// Printed when a program finishes (as trap.handler does)
+static const char TERM_MSG[] = "\nCOOL program successfully executed\n";
+
+// An error that COOL programs cannot catch (written to standard error like coolc run)
+static void fail(const char *msg) {
+    fflush(stdout);
+    fprintf(stderr, "%s\n", msg);
+    exit(1);
+}
+
+// A runtime error reported by the program itself
+static void halt(void) {
+    fflush(stdout);
+    exit(1);
+}
+
+static void *alloc(size_t words) {
+    void *obj = malloc(words * WORD_SIZE);
+
+    if (obj == NULL) {
+        fail("Out of memory");
+    }
+
+    return obj;
+}
+
+static object *copy(object *obj) {
+    if (obj == NULL) {
+        fail("Object.copy called on void");
+    }
+
+    object *new_obj = alloc(obj->size);
+    memcpy(new_obj, obj, obj->size * WORD_SIZE);
+
+    return new_obj;
+}
+
+static int_object *new_int(int32_t val) {
+    int_object *obj = (int_object *)copy(&Int_protObj.header);
+    obj->val = val;
+
+    return obj;
+}
+
+static string_object *new_string(const char *val, size_t len) {
+    // The characters are followed by a null
+    size_t size = HEADER_WORDS + len / WORD_SIZE + 1;
+
+    string_object *obj = alloc(size);
+    obj->header = String_protObj.header;
+    obj->header.size = size;
+    obj->len = new_int(len);
+    memcpy(obj->chars, val, len);
+    obj->chars[len] = '\0';
+
+    return obj;
+}
+
+static string_object *class_name(object *obj) {
+    return class_nameTab[obj->tag];
+}
+
+static void write_string(string_object *s) {
+    fwrite(s->chars, 1, s->len->val, stdout);
+}
+
+// Read a line without its newline (the result is null terminated)
+static char *read_line(size_t *len) {
+    // Prompts must appear before the program waits for input
+    fflush(stdout);
+
+    char *line = NULL;
+    size_t cap = 0;
+    ssize_t n = getline(&line, &cap, stdin);
+
+    if (n < 0) {
+        n = 0;
+        line = realloc(line, 1);
+    } else if (n > 0 && line[n - 1] == '\n') {
+        n--;
+    }
+
+    line[n] = '\0';
+    *len = n;
+
+    return line;
+}
+
+object *Object_abort(object *self) {
+    printf("Abort called from class ");
+    write_string(class_name(self));
+    printf("\n");
+    halt();
+
+    return self;
+}
+
+object *Object_type_name(object *self) {
+    return &class_name(self)->header;
+}
+
+object *Object_copy(object *self) {
+    return copy(self);
+}
+
+object *IO_out_string(object *self, string_object *arg) {
+    write_string(arg);
+
+    return self;
+}
+
+object *IO_out_int(object *self, int_object *arg) {
+    printf("%d", arg->val);
+
+    return self;
+}
+
+object *IO_in_string(object *self) {
+    (void)self;
+
+    size_t len;
+    char *line = read_line(&len);
+
+    // A string with a null character is read as the empty string
+    if (memchr(line, '\0', len) != NULL) {
+        len = 0;
+    }
+
+    string_object *obj = new_string(line, len);
+    free(line);
+
+    return &obj->header;
+}
+
+object *IO_in_int(object *self) {
+    (void)self;
+
+    size_t len;
+    char *line = read_line(&len);
+    char *p = line;
+
+    while (*p == ' ' || (*p >= '\t' && *p <= '\r')) {
+        p++;
+    }
+
+    // Like atoi, leading digits are read and the rest is ignored
+    // A number too big for 64 bits is read as 0.
+    int negative = *p == '-';
+    if (*p == '-' || *p == '+') {
+        p++;
+    }
+
+    int64_t val = 0;
+    for (; *p >= '0' && *p <= '9'; p++) {
+        int64_t digit = negative ? -(*p - '0') : *p - '0';
+
+        if (__builtin_mul_overflow(val, 10, &val) || __builtin_add_overflow(val, digit, &val)) {
+            val = 0;
+            break;
+        }
+    }
+
+    free(line);
+
+    return &new_int((int32_t)val)->header;
+}
+
+object *String_length(string_object *self) {
+    return &new_int(self->len->val)->header;
+}
+
+object *String_concat(string_object *self, string_object *arg) {
+    size_t len = self->len->val;
+    size_t arg_len = arg->len->val;
+
+    char *val = malloc(len + arg_len);
+    memcpy(val, self->chars, len);
+    memcpy(val + len, arg->chars, arg_len);
+
+    string_object *obj = new_string(val, len + arg_len);
+    free(val);
+
+    return &obj->header;
+}
+
+object *String_substr(string_object *self, int_object *index, int_object *len) {
+    int64_t i = index->val;
+    int64_t l = len->val;
+    int64_t self_len = self->len->val;
+
+    if (i < 0) {
+        printf("Index to substr is negative\n");
+    } else if (i > self_len) {
+        printf("Index to substr is too big\n");
+    } else if (l < 0) {
+        printf("Length to substr is negative\n");
+    } else if (i + l > self_len) {
+        printf("Length to substr too long\n");
+    } else {
+        return &new_string(self->chars + i, l)->header;
+    }
+
+    printf("Execution aborted.\n");
+    halt();
+
+    return NULL;
+}
+
+// Compare two objects that are known to be different objects
+// Returns true (1) if they have the same basic value.
+int64_t cool_equality_test(object *lhs, object *rhs) {
+    if (lhs == NULL || rhs == NULL || lhs->tag != rhs->tag) {
+        return 0;
+    }
+
+    if (lhs->tag == _int_tag || lhs->tag == _bool_tag) {
+        return ((int_object *)lhs)->val == ((int_object *)rhs)->val;
+    }
+
+    if (lhs->tag == _string_tag) {
+        string_object *lhs_str = (string_object *)lhs;
+        string_object *rhs_str = (string_object *)rhs;
+
+        return lhs_str->len->val == rhs_str->len->val &&
+               memcmp(lhs_str->chars, rhs_str->chars, lhs_str->len->val) == 0;
+    }
+
+    return 0;
+}
+
+void cool_dispatch_abort(string_object *file_name, int64_t line_no) {
+    write_string(file_name);
+    printf(":%ld: Dispatch to void.\n", (long)line_no);
+    halt();
+}
+
+// obj is the object that did not match
+void cool_case_abort(object *obj) {
+    printf("No match in case statement for Class ");
+    write_string(class_name(obj));
+    printf("\n");
+    halt();
+}
+
+void cool_case_abort2(string_object *file_name, int64_t line_no) {
+    write_string(file_name);
+    printf(":%ld: Match on void in case statement.\n", (long)line_no);
+    halt();
+}
+
+void cool_arithmetic_overflow(void) {
+    fail("Arithmetic overflow");
+}
+
+void cool_division_by_zero(void) {
+    fail("Division by zero");
+}
+
+// The only memory fault that a COOL program can cause is running out of stack
+static void stack_overflow(int sig) {
+    (void)sig;
+
+    fail("Stack overflow");
+}
+
+int main(void) {
+    // The handler needs a stack of its own
+    static char handler_stack[1 << 16];
+    stack_t ss = {.ss_sp = handler_stack, .ss_size = sizeof(handler_stack)};
+    sigaltstack(&ss, NULL);
+
+    struct sigaction sa;
+    memset(&sa, 0, sizeof(sa));
+    sa.sa_handler = stack_overflow;
+    sa.sa_flags = SA_ONSTACK;
+    sigaction(SIGSEGV, &sa, NULL);
+
+    cool_main();
+
+    printf("%s", TERM_MSG);
+
+    return 0;
+}
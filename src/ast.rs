@@ -7,6 +7,8 @@ use std::fmt;
 use std::hash::Hash;
 use std::ops::Range;
 
+use crate::span::Span;
+
 // Each class has a 'tag' - a unique integer value assign in DFS order
 // Object class has a tag of 0
 // The family field is the range of the tags in all of the class's descendants (including itself).
@@ -15,7 +17,7 @@ pub struct Class {
     pub file_name: String,
     pub file_no: u32,
     pub basic: bool, // Is this class provided by the runtime environment? (i.e. Object, Int, String, Bool, IO)
-    pub line_no: i16,
+    pub span: Span,
     pub parent_name: Option<TypeID>,
     pub attrs: Vec<Attr>,                    // Attributes of the class
     pub methods: IndexMap<ObjectID, Method>, // Methods of this class indexed by their names
@@ -34,7 +36,7 @@ impl Class {
 
 // Class attributes
 pub struct Attr {
-    pub line_no: i16,
+    pub span: Span,
     pub name: ObjectID,
     pub type_decl: TypeID,
     pub init: Expression,
@@ -43,7 +45,7 @@ pub struct Attr {
 
 // Class methods
 pub struct Method {
-    pub line_no: i16,
+    pub span: Span,
     pub formals: Vec<Formal>, // Arguments and their types
     pub return_type: TypeID,
     pub expr: Expression,
@@ -53,14 +55,14 @@ pub struct Method {
 impl Method {
     pub fn get_sig(&self) -> Method {
         let Method {
-            line_no,
+            span,
             formals,
             return_type,
             ..
         } = self;
 
         Method {
-            line_no: *line_no,
+            span: *span,
             formals: formals.clone(),
             return_type: return_type.clone(),
             expr: Expression::NoExpr, // No need to copy the contents
@@ -71,14 +73,14 @@ impl Method {
 // Used to represent the arguments of a method declaration
 #[derive(Clone)]
 pub struct Formal {
-    pub line_no: i16,
+    pub span: Span,
     pub name: ObjectID,
     pub type_decl: TypeID,
 }
 
 // One branch of a TypeCase statement
 pub struct Branch {
-    pub line_no: i16,
+    pub span: Span,
     pub name: ObjectID,
     pub type_decl: TypeID,
     pub expr: Expression,
@@ -150,12 +152,12 @@ impl fmt::Display for CompType {
 
 pub enum Expression {
     Assign {
-        line_no: i16,
+        span: Span,
         name: ObjectID,
         expr: Box<Expression>,
     },
     Dispatch {
-        line_no: i16,
+        span: Span,
         expr: Box<Expression>,
         type_name: Option<TypeID>, // static dispatch if Some
         name: ObjectID,
@@ -163,113 +165,153 @@ pub enum Expression {
         static_type: TypeID,
     },
     Cond {
-        line_no: i16,
+        span: Span,
         pred: Box<Expression>,
         then_expr: Box<Expression>,
         else_expr: Box<Expression>,
         static_type: TypeID,
     },
     Loop {
-        line_no: i16,
+        span: Span,
         pred: Box<Expression>,
         body: Box<Expression>,
     },
     TypeCase {
-        line_no: i16,
+        span: Span,
         expr: Box<Expression>,
         branches: Vec<Branch>,
         static_type: TypeID,
     },
     Block {
-        line_no: i16,
+        span: Span,
         body: Vec<Expression>,
     },
     Let {
-        line_no: i16,
+        span: Span,
         identifier: ObjectID,
         type_decl: TypeID,
         init: Box<Expression>,
         body: Box<Expression>,
     },
     ArithOp {
-        line_no: i16,
+        span: Span,
         expr_lhs: Box<Expression>,
         expr_rhs: Box<Expression>,
         arith_op_type: ArithOpType,
     },
     Neg {
-        line_no: i16,
+        span: Span,
         expr: Box<Expression>,
     },
     Comp {
-        line_no: i16,
+        span: Span,
         expr_lhs: Box<Expression>,
         expr_rhs: Box<Expression>,
         comp_type: CompType,
     },
     Eq {
-        line_no: i16,
+        span: Span,
         expr_lhs: Box<Expression>,
         expr_rhs: Box<Expression>,
     },
     Not {
-        line_no: i16,
+        span: Span,
         expr: Box<Expression>,
     },
     IntConst {
-        line_no: i16,
+        span: Span,
         val: u32,
     },
     BoolConst {
-        line_no: i16,
+        span: Span,
         val: bool,
     },
     StringConst {
-        line_no: i16,
+        span: Span,
         val: String,
         val_id: u32, // A unique value used to reference the string constant in the assembly output
     },
     New {
-        line_no: i16,
+        span: Span,
         type_name: TypeID,
     },
     IsVoid {
-        line_no: i16,
+        span: Span,
         expr: Box<Expression>,
     },
     NoExpr,
     VarByName {
-        line_no: i16,
+        span: Span,
         name: ObjectID,
         static_type: TypeID,
     },
 }
 
 impl Expression {
-    pub fn line_no(&self) -> i16 {
+    pub fn span(&self) -> Span {
         match self {
-            Expression::Assign { line_no, .. }
-            | Expression::Dispatch { line_no, .. }
-            | Expression::Cond { line_no, .. }
-            | Expression::Loop { line_no, .. }
-            | Expression::TypeCase { line_no, .. }
-            | Expression::Block { line_no, .. }
-            | Expression::Let { line_no, .. }
-            | Expression::ArithOp { line_no, .. }
-            | Expression::Neg { line_no, .. }
-            | Expression::Comp { line_no, .. }
-            | Expression::Eq { line_no, .. }
-            | Expression::Not { line_no, .. }
-            | Expression::IntConst { line_no, .. }
-            | Expression::BoolConst { line_no, .. }
-            | Expression::StringConst { line_no, .. }
-            | Expression::New { line_no, .. }
-            | Expression::IsVoid { line_no, .. }
-            | Expression::VarByName { line_no, .. } => *line_no,
+            Expression::Assign { span, .. }
+            | Expression::Dispatch { span, .. }
+            | Expression::Cond { span, .. }
+            | Expression::Loop { span, .. }
+            | Expression::TypeCase { span, .. }
+            | Expression::Block { span, .. }
+            | Expression::Let { span, .. }
+            | Expression::ArithOp { span, .. }
+            | Expression::Neg { span, .. }
+            | Expression::Comp { span, .. }
+            | Expression::Eq { span, .. }
+            | Expression::Not { span, .. }
+            | Expression::IntConst { span, .. }
+            | Expression::BoolConst { span, .. }
+            | Expression::StringConst { span, .. }
+            | Expression::New { span, .. }
+            | Expression::IsVoid { span, .. }
+            | Expression::VarByName { span, .. } => *span,
             _ => panic!("Bad expr type"),
         }
     }
 
+    // The expressions directly inside the expression
+    pub fn children(&self) -> Vec<&Expression> {
+        match self {
+            Expression::Assign { expr, .. }
+            | Expression::Neg { expr, .. }
+            | Expression::Not { expr, .. }
+            | Expression::IsVoid { expr, .. } => vec![expr],
+            Expression::Dispatch { expr, args, .. } => {
+                std::iter::once(&**expr).chain(args.iter()).collect()
+            }
+            Expression::Cond {
+                pred,
+                then_expr,
+                else_expr,
+                ..
+            } => vec![pred, then_expr, else_expr],
+            Expression::Loop { pred, body, .. } => vec![pred, body],
+            Expression::TypeCase { expr, branches, .. } => std::iter::once(&**expr)
+                .chain(branches.iter().map(|branch| &branch.expr))
+                .collect(),
+            Expression::Block { body, .. } => body.iter().collect(),
+            Expression::Let { init, body, .. } => vec![init, body],
+            Expression::ArithOp {
+                expr_lhs, expr_rhs, ..
+            }
+            | Expression::Comp {
+                expr_lhs, expr_rhs, ..
+            }
+            | Expression::Eq {
+                expr_lhs, expr_rhs, ..
+            } => vec![expr_lhs, expr_rhs],
+            Expression::IntConst { .. }
+            | Expression::BoolConst { .. }
+            | Expression::StringConst { .. }
+            | Expression::New { .. }
+            | Expression::VarByName { .. }
+            | Expression::NoExpr => Vec::new(),
+        }
+    }
+
     // Static type of the expression
     // Some expression types need to be set during semantic analysis
     pub fn static_type(&self) -> TypeID {
@@ -398,6 +440,14 @@ impl TypeID {
         }
     }
 
+    // Assigned to expressions that fail to type check
+    // It conforms to every type so that one error does not cause others
+    pub fn new_error_type() -> TypeID {
+        TypeID {
+            type_name: "_error".to_string(),
+        }
+    }
+
     pub fn is_system_type(&self) -> bool {
         self.type_name.as_bytes()[0] == b'_'
     }
@@ -410,6 +460,10 @@ impl TypeID {
         self.type_name == "_no_type"
     }
 
+    pub fn is_error_type(&self) -> bool {
+        self.type_name == "_error"
+    }
+
     pub fn is_int(&self) -> bool {
         self.type_name == "Int"
     }
@@ -425,6 +479,10 @@ impl TypeID {
     pub fn len(&self) -> usize {
         self.type_name.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.type_name.is_empty()
+    }
 }
 
 impl fmt::Display for TypeID {
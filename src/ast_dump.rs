@@ -0,0 +1,377 @@
+// Writes classes in the format of the Stanford reference parser and semantic analyzer:
+//
+// #1
+// _program
+//   #1
+//   _class
+//     Main
+//     IO
+//     "test.cl"
+//     (
+//     ...
+//     )
+//
+// Every expression is followed by its static type (_no_type if the types are not shown).
+
+use indexmap::IndexMap;
+use std::fmt::Display;
+use std::io;
+
+use crate::ast::{
+    ArithOpType, Attr, Branch, Class, CompType, Expression, Formal, Method, ObjectID, TypeID,
+};
+use crate::token::escape_str;
+
+const INDENT: usize = 2; // Each level of the tree is indented by this many spaces
+
+// Attributes and methods are kept separately in a Class
+// They are merged back into source order for the dump.
+enum Feature<'a> {
+    Attr(&'a Attr),
+    Method(&'a ObjectID, &'a Method),
+}
+
+fn emit_line(
+    out_file: &mut dyn io::Write,
+    indent: usize,
+    text: impl Display,
+) -> Result<(), io::Error> {
+    writeln!(out_file, "{:indent$}{}", "", text, indent = indent)
+}
+
+fn emit_line_no(
+    out_file: &mut dyn io::Write,
+    indent: usize,
+    line_no: u32,
+) -> Result<(), io::Error> {
+    emit_line(out_file, indent, format!("#{}", line_no))
+}
+
+// with_types selects whether the static types set by semant are shown
+pub fn dump_ast(
+    out_file: &mut dyn io::Write,
+    classes: &IndexMap<TypeID, Class>,
+    with_types: bool,
+) -> Result<(), io::Error> {
+    // The basic classes are not part of the program
+    let mut program: Vec<(&TypeID, &Class)> =
+        classes.iter().filter(|(_, class)| !class.basic).collect();
+    program.sort_by_key(|(_, class)| (class.file_no, class.span.lo));
+
+    // The program is placed at the first class
+    let line_no = match program.first() {
+        Some((_, class)) => class.span.line_no,
+        None => 1,
+    };
+
+    emit_line_no(out_file, 0, line_no)?;
+    emit_line(out_file, 0, "_program")?;
+
+    for (class_name, class) in program {
+        dump_class(out_file, INDENT, class_name, class, with_types)?;
+    }
+
+    Ok(())
+}
+
+fn dump_class(
+    out_file: &mut dyn io::Write,
+    indent: usize,
+    class_name: &TypeID,
+    class: &Class,
+    with_types: bool,
+) -> Result<(), io::Error> {
+    let inner = indent + INDENT;
+
+    let parent_name = match &class.parent_name {
+        Some(parent_name) => parent_name.clone(),
+        None => TypeID::root_class_name(),
+    };
+
+    let mut features: Vec<Feature> = class.attrs.iter().map(Feature::Attr).collect();
+    features.extend(
+        class
+            .methods
+            .iter()
+            .map(|(method_name, method)| Feature::Method(method_name, method)),
+    );
+    features.sort_by_key(|feature| match feature {
+        Feature::Attr(attr) => attr.span.lo,
+        Feature::Method(_, method) => method.span.lo,
+    });
+
+    emit_line_no(out_file, indent, class.span.line_no)?;
+    emit_line(out_file, indent, "_class")?;
+    emit_line(out_file, inner, class_name)?;
+    emit_line(out_file, inner, parent_name)?;
+    emit_line(out_file, inner, escape_str(&class.file_name))?;
+    emit_line(out_file, inner, "(")?;
+
+    for feature in features {
+        match feature {
+            Feature::Attr(attr) => dump_attr(out_file, inner, attr, with_types)?,
+            Feature::Method(method_name, method) => {
+                dump_method(out_file, inner, method_name, method, with_types)?
+            }
+        }
+    }
+
+    emit_line(out_file, inner, ")")
+}
+
+fn dump_attr(
+    out_file: &mut dyn io::Write,
+    indent: usize,
+    attr: &Attr,
+    with_types: bool,
+) -> Result<(), io::Error> {
+    let inner = indent + INDENT;
+
+    emit_line_no(out_file, indent, attr.span.line_no)?;
+    emit_line(out_file, indent, "_attr")?;
+    emit_line(out_file, inner, &attr.name)?;
+    emit_line(out_file, inner, &attr.type_decl)?;
+    dump_expr(out_file, inner, &attr.init, attr.span.line_no, with_types)
+}
+
+fn dump_method(
+    out_file: &mut dyn io::Write,
+    indent: usize,
+    method_name: &ObjectID,
+    method: &Method,
+    with_types: bool,
+) -> Result<(), io::Error> {
+    let inner = indent + INDENT;
+
+    emit_line_no(out_file, indent, method.span.line_no)?;
+    emit_line(out_file, indent, "_method")?;
+    emit_line(out_file, inner, method_name)?;
+
+    for formal in method.formals.iter() {
+        dump_formal(out_file, inner, formal)?;
+    }
+
+    emit_line(out_file, inner, &method.return_type)?;
+    dump_expr(
+        out_file,
+        inner,
+        &method.expr,
+        method.span.line_no,
+        with_types,
+    )
+}
+
+fn dump_formal(
+    out_file: &mut dyn io::Write,
+    indent: usize,
+    formal: &Formal,
+) -> Result<(), io::Error> {
+    let inner = indent + INDENT;
+
+    emit_line_no(out_file, indent, formal.span.line_no)?;
+    emit_line(out_file, indent, "_formal")?;
+    emit_line(out_file, inner, &formal.name)?;
+    emit_line(out_file, inner, &formal.type_decl)
+}
+
+fn dump_branch(
+    out_file: &mut dyn io::Write,
+    indent: usize,
+    branch: &Branch,
+    with_types: bool,
+) -> Result<(), io::Error> {
+    let inner = indent + INDENT;
+
+    emit_line_no(out_file, indent, branch.span.line_no)?;
+    emit_line(out_file, indent, "_branch")?;
+    emit_line(out_file, inner, &branch.name)?;
+    emit_line(out_file, inner, &branch.type_decl)?;
+    dump_expr(
+        out_file,
+        inner,
+        &branch.expr,
+        branch.span.line_no,
+        with_types,
+    )
+}
+
+// line_no is used for a missing expression, which has no position of its own
+fn dump_expr(
+    out_file: &mut dyn io::Write,
+    indent: usize,
+    expr: &Expression,
+    line_no: u32,
+    with_types: bool,
+) -> Result<(), io::Error> {
+    let inner = indent + INDENT;
+
+    let line_no = match expr {
+        Expression::NoExpr => line_no,
+        _ => expr.span().line_no,
+    };
+
+    emit_line_no(out_file, indent, line_no)?;
+
+    match expr {
+        Expression::Assign { name, expr, .. } => {
+            emit_line(out_file, indent, "_assign")?;
+            emit_line(out_file, inner, name)?;
+            dump_expr(out_file, inner, expr, line_no, with_types)?;
+        }
+        Expression::Dispatch {
+            expr,
+            type_name,
+            name,
+            args,
+            ..
+        } => {
+            match type_name {
+                Some(type_name) => {
+                    emit_line(out_file, indent, "_static_dispatch")?;
+                    dump_expr(out_file, inner, expr, line_no, with_types)?;
+                    emit_line(out_file, inner, type_name)?;
+                }
+                None => {
+                    emit_line(out_file, indent, "_dispatch")?;
+                    dump_expr(out_file, inner, expr, line_no, with_types)?;
+                }
+            }
+
+            emit_line(out_file, inner, name)?;
+            emit_line(out_file, inner, "(")?;
+
+            for arg in args.iter() {
+                dump_expr(out_file, inner, arg, line_no, with_types)?;
+            }
+
+            emit_line(out_file, inner, ")")?;
+        }
+        Expression::Cond {
+            pred,
+            then_expr,
+            else_expr,
+            ..
+        } => {
+            emit_line(out_file, indent, "_cond")?;
+            dump_expr(out_file, inner, pred, line_no, with_types)?;
+            dump_expr(out_file, inner, then_expr, line_no, with_types)?;
+            dump_expr(out_file, inner, else_expr, line_no, with_types)?;
+        }
+        Expression::Loop { pred, body, .. } => {
+            emit_line(out_file, indent, "_loop")?;
+            dump_expr(out_file, inner, pred, line_no, with_types)?;
+            dump_expr(out_file, inner, body, line_no, with_types)?;
+        }
+        Expression::TypeCase { expr, branches, .. } => {
+            emit_line(out_file, indent, "_typcase")?;
+            dump_expr(out_file, inner, expr, line_no, with_types)?;
+
+            // semant sorts the branches for code generation
+            let mut branches: Vec<&Branch> = branches.iter().collect();
+            branches.sort_by_key(|branch| branch.span.lo);
+
+            for branch in branches {
+                dump_branch(out_file, inner, branch, with_types)?;
+            }
+        }
+        Expression::Block { body, .. } => {
+            emit_line(out_file, indent, "_block")?;
+
+            for expr in body.iter() {
+                dump_expr(out_file, inner, expr, line_no, with_types)?;
+            }
+        }
+        Expression::Let {
+            identifier,
+            type_decl,
+            init,
+            body,
+            ..
+        } => {
+            emit_line(out_file, indent, "_let")?;
+            emit_line(out_file, inner, identifier)?;
+            emit_line(out_file, inner, type_decl)?;
+            dump_expr(out_file, inner, init, line_no, with_types)?;
+            dump_expr(out_file, inner, body, line_no, with_types)?;
+        }
+        Expression::ArithOp {
+            expr_lhs,
+            expr_rhs,
+            arith_op_type,
+            ..
+        } => {
+            let node_name = match arith_op_type {
+                ArithOpType::Add => "_plus",
+                ArithOpType::Sub => "_sub",
+                ArithOpType::Mul => "_mul",
+                ArithOpType::Div => "_divide",
+            };
+
+            emit_line(out_file, indent, node_name)?;
+            dump_expr(out_file, inner, expr_lhs, line_no, with_types)?;
+            dump_expr(out_file, inner, expr_rhs, line_no, with_types)?;
+        }
+        Expression::Neg { expr, .. } => {
+            emit_line(out_file, indent, "_neg")?;
+            dump_expr(out_file, inner, expr, line_no, with_types)?;
+        }
+        Expression::Comp {
+            expr_lhs,
+            expr_rhs,
+            comp_type,
+            ..
+        } => {
+            let node_name = match comp_type {
+                CompType::LT => "_lt",
+                CompType::LEq => "_leq",
+            };
+
+            emit_line(out_file, indent, node_name)?;
+            dump_expr(out_file, inner, expr_lhs, line_no, with_types)?;
+            dump_expr(out_file, inner, expr_rhs, line_no, with_types)?;
+        }
+        Expression::Eq {
+            expr_lhs, expr_rhs, ..
+        } => {
+            emit_line(out_file, indent, "_eq")?;
+            dump_expr(out_file, inner, expr_lhs, line_no, with_types)?;
+            dump_expr(out_file, inner, expr_rhs, line_no, with_types)?;
+        }
+        Expression::Not { expr, .. } => {
+            // The reference calls 'not' a complement
+            emit_line(out_file, indent, "_comp")?;
+            dump_expr(out_file, inner, expr, line_no, with_types)?;
+        }
+        Expression::IntConst { val, .. } => {
+            emit_line(out_file, indent, "_int")?;
+            emit_line(out_file, inner, val)?;
+        }
+        Expression::BoolConst { val, .. } => {
+            emit_line(out_file, indent, "_bool")?;
+            emit_line(out_file, inner, if *val { 1 } else { 0 })?;
+        }
+        Expression::StringConst { val, .. } => {
+            emit_line(out_file, indent, "_string")?;
+            emit_line(out_file, inner, escape_str(val))?;
+        }
+        Expression::New { type_name, .. } => {
+            emit_line(out_file, indent, "_new")?;
+            emit_line(out_file, inner, type_name)?;
+        }
+        Expression::IsVoid { expr, .. } => {
+            emit_line(out_file, indent, "_isvoid")?;
+            dump_expr(out_file, inner, expr, line_no, with_types)?;
+        }
+        Expression::VarByName { name, .. } => {
+            emit_line(out_file, indent, "_object")?;
+            emit_line(out_file, inner, name)?;
+        }
+        Expression::NoExpr => emit_line(out_file, indent, "_no_expr")?,
+    }
+
+    if with_types {
+        emit_line(out_file, indent, format!(": {}", expr.static_type()))
+    } else {
+        emit_line(out_file, indent, ": _no_type")
+    }
+}
@@ -0,0 +1,514 @@
+// Reads programs in the format written by the Stanford reference parser and
+// semantic analyzer (see ast_dump.rs for an example).
+// This lets semant and cgen be run on the output of another front end.
+//
+// Indentation is not significant. Static types (the ": Type" lines) are
+// ignored because semant sets them again.
+// Only line numbers are known, so each span covers the "#line" of its node in the input.
+// Input that is not in the AST format is reported at the line where reading stopped.
+
+use indexmap::IndexMap;
+
+use crate::ast::{
+    ArithOpType, Attr, Branch, Class, CompType, Expression, Formal, Method, ObjectID, TypeID,
+};
+use crate::diagnostics::{Diagnostics, ErrorCode};
+use crate::parser::{add_class, add_method};
+use crate::span::Span;
+
+struct Line<'a> {
+    lo: u32,       // Byte offset of the line in the input
+    input_no: u32, // Line number in the input (for errors in the input itself)
+    col: u32,      // Column of the text in the input
+    text: &'a str, // Without indentation
+}
+
+struct Reader<'a> {
+    lines: Vec<Line<'a>>, // Blank lines are left out
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(in_file_text: &'a str) -> Reader<'a> {
+        let mut lines = Vec::new();
+        let mut lo = 0;
+
+        for (line, input_no) in in_file_text.split_inclusive('\n').zip(1..) {
+            let text = line.trim();
+
+            if !text.is_empty() {
+                let indent = line.find(text).unwrap();
+
+                lines.push(Line {
+                    lo: (lo + indent) as u32,
+                    input_no,
+                    col: indent as u32 + 1,
+                    text,
+                });
+            }
+
+            lo += line.len();
+        }
+
+        Reader { lines, pos: 0 }
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.lines.get(self.pos).map(|line| line.text)
+    }
+
+    // Look past the next line (e.g. to see the kind of node that follows a "#line")
+    fn peek_second(&self) -> Option<&'a str> {
+        self.lines.get(self.pos + 1).map(|line| line.text)
+    }
+
+    fn error(&self, msg: &str) -> String {
+        format!("Malformed AST: {}", msg)
+    }
+
+    // The line where reading stopped (or the end of the input)
+    fn error_span(&self, in_file_text: &str) -> Span {
+        match self.lines.get(self.pos) {
+            Some(line) => Span::new(
+                line.lo,
+                line.lo + line.text.len() as u32,
+                line.input_no,
+                line.col,
+            ),
+            None => {
+                let end = in_file_text.len() as u32;
+                let line_no = in_file_text.lines().count().max(1) as u32;
+                let col = in_file_text.lines().last().unwrap_or_default().len() as u32 + 1;
+
+                Span::new(end, end, line_no, col)
+            }
+        }
+    }
+
+    fn next(&mut self, expected: &str) -> Result<&'a str, String> {
+        match self.peek() {
+            Some(text) => {
+                self.pos += 1;
+                Ok(text)
+            }
+            None => Err(self.error(&format!("expected {}", expected))),
+        }
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        match self.peek() {
+            Some(text) if text == expected => {
+                self.pos += 1;
+                Ok(())
+            }
+            _ => Err(self.error(&format!("expected {}", expected))),
+        }
+    }
+
+    // #line
+    fn read_span(&mut self) -> Result<Span, String> {
+        let line = match self.lines.get(self.pos) {
+            Some(line) => line,
+            None => return Err(self.error("expected a line number")),
+        };
+
+        let line_no = match line.text.strip_prefix('#').map(str::parse::<u32>) {
+            Some(Ok(line_no)) => line_no,
+            _ => return Err(self.error("expected a line number")),
+        };
+
+        let span = Span::new(line.lo, line.lo + line.text.len() as u32, line_no, 1);
+
+        self.pos += 1;
+        Ok(span)
+    }
+
+    fn read_type_id(&mut self) -> Result<TypeID, String> {
+        Ok(TypeID::new(self.next("a type")?.to_string()))
+    }
+
+    fn read_object_id(&mut self) -> Result<ObjectID, String> {
+        Ok(ObjectID::new(self.next("an identifier")?.to_string()))
+    }
+
+    fn read_str(&mut self) -> Result<String, String> {
+        let text = self.next("a string")?;
+
+        match unescape_str(text) {
+            Some(val) => Ok(val),
+            None => {
+                self.pos -= 1;
+                Err(self.error("bad string"))
+            }
+        }
+    }
+
+    fn at_node(&self) -> bool {
+        match self.peek() {
+            Some(text) => text.starts_with('#'),
+            None => false,
+        }
+    }
+
+    // Read nodes of one kind until a line that is not a node
+    fn read_list<T>(
+        &mut self,
+        node_name: &str,
+        read: fn(&mut Reader<'a>) -> Result<T, String>,
+    ) -> Result<Vec<T>, String> {
+        let mut nodes = Vec::new();
+
+        while self.at_node() && self.peek_second() == Some(node_name) {
+            nodes.push(read(self)?);
+        }
+
+        Ok(nodes)
+    }
+
+    fn read_program(
+        &mut self,
+        classes: &mut IndexMap<TypeID, Class>,
+        file_nos: &mut IndexMap<String, u32>,
+        diagnostics: &mut Diagnostics,
+    ) -> Result<(), String> {
+        self.read_span()?;
+        self.expect("_program")?;
+
+        while self.at_node() {
+            self.read_class(classes, file_nos, diagnostics)?;
+
+            if diagnostics.limit_reached() {
+                break;
+            }
+        }
+
+        match self.peek() {
+            None => Ok(()),
+            Some(_) => Err(self.error("expected a class")),
+        }
+    }
+
+    fn read_class(
+        &mut self,
+        classes: &mut IndexMap<TypeID, Class>,
+        file_nos: &mut IndexMap<String, u32>,
+        diagnostics: &mut Diagnostics,
+    ) -> Result<(), String> {
+        let span = self.read_span()?;
+        self.expect("_class")?;
+        let name = self.read_type_id()?;
+        let parent_name = self.read_type_id()?;
+        let file_name = self.read_str()?;
+        self.expect("(")?;
+
+        let mut attrs: Vec<Attr> = Vec::new();
+        let mut methods: IndexMap<ObjectID, Method> = IndexMap::new();
+
+        while self.at_node() {
+            match self.peek_second() {
+                Some("_attr") => attrs.push(self.read_attr()?),
+                Some("_method") => {
+                    let (method_name, method) = self.read_method()?;
+
+                    add_method(&mut methods, method_name, method, &file_name, diagnostics);
+                }
+                _ => {
+                    self.pos += 1;
+                    return Err(self.error("expected a feature"));
+                }
+            }
+        }
+
+        self.expect(")")?;
+
+        // Files are numbered in the order they are first seen
+        let next_file_no = file_nos.len() as u32 + 1;
+        let file_no = *file_nos.entry(file_name.clone()).or_insert(next_file_no);
+
+        let class = Class {
+            file_name: file_name.clone(),
+            file_no,
+            basic: false,
+            span,
+            parent_name: Some(parent_name),
+            attrs,
+            methods,
+            family: 0..0,
+            child_names: Vec::new(),
+            method_name_to_pos: IndexMap::new(),
+            dispatch_table: Vec::new(),
+        };
+
+        add_class(classes, name, class, &file_name, diagnostics);
+
+        Ok(())
+    }
+
+    fn read_attr(&mut self) -> Result<Attr, String> {
+        let span = self.read_span()?;
+        self.expect("_attr")?;
+
+        Ok(Attr {
+            span,
+            name: self.read_object_id()?,
+            type_decl: self.read_type_id()?,
+            init: self.read_expr()?,
+            self_offset: 0,
+        })
+    }
+
+    fn read_method(&mut self) -> Result<(ObjectID, Method), String> {
+        let span = self.read_span()?;
+        self.expect("_method")?;
+        let name = self.read_object_id()?;
+
+        Ok((
+            name,
+            Method {
+                span,
+                formals: self.read_list("_formal", Reader::read_formal)?,
+                return_type: self.read_type_id()?,
+                expr: self.read_expr()?,
+            },
+        ))
+    }
+
+    fn read_formal(&mut self) -> Result<Formal, String> {
+        let span = self.read_span()?;
+        self.expect("_formal")?;
+
+        Ok(Formal {
+            span,
+            name: self.read_object_id()?,
+            type_decl: self.read_type_id()?,
+        })
+    }
+
+    fn read_branch(&mut self) -> Result<Branch, String> {
+        let span = self.read_span()?;
+        self.expect("_branch")?;
+
+        Ok(Branch {
+            span,
+            name: self.read_object_id()?,
+            type_decl: self.read_type_id()?,
+            expr: self.read_expr()?,
+            family: 0..0,
+        })
+    }
+
+    fn read_boxed_expr(&mut self) -> Result<Box<Expression>, String> {
+        Ok(Box::new(self.read_expr()?))
+    }
+
+    fn read_expr(&mut self) -> Result<Expression, String> {
+        let span = self.read_span()?;
+        let static_type = TypeID::new_no_type(); // Set by semant
+
+        let node_name = self.next("an expression")?;
+
+        let expr = match node_name {
+            "_assign" => Expression::Assign {
+                span,
+                name: self.read_object_id()?,
+                expr: self.read_boxed_expr()?,
+            },
+            "_static_dispatch" | "_dispatch" => {
+                let expr = self.read_boxed_expr()?;
+
+                let type_name = if node_name == "_static_dispatch" {
+                    Some(self.read_type_id()?)
+                } else {
+                    None
+                };
+
+                let name = self.read_object_id()?;
+
+                self.expect("(")?;
+                let args = self.read_list_of_exprs()?;
+                self.expect(")")?;
+
+                Expression::Dispatch {
+                    span,
+                    expr,
+                    type_name,
+                    name,
+                    args,
+                    static_type,
+                }
+            }
+            "_cond" => Expression::Cond {
+                span,
+                pred: self.read_boxed_expr()?,
+                then_expr: self.read_boxed_expr()?,
+                else_expr: self.read_boxed_expr()?,
+                static_type,
+            },
+            "_loop" => Expression::Loop {
+                span,
+                pred: self.read_boxed_expr()?,
+                body: self.read_boxed_expr()?,
+            },
+            "_typcase" => Expression::TypeCase {
+                span,
+                expr: self.read_boxed_expr()?,
+                branches: self.read_list("_branch", Reader::read_branch)?,
+                static_type,
+            },
+            "_block" => Expression::Block {
+                span,
+                body: self.read_list_of_exprs()?,
+            },
+            "_let" => Expression::Let {
+                span,
+                identifier: self.read_object_id()?,
+                type_decl: self.read_type_id()?,
+                init: self.read_boxed_expr()?,
+                body: self.read_boxed_expr()?,
+            },
+            "_plus" | "_sub" | "_mul" | "_divide" => Expression::ArithOp {
+                span,
+                expr_lhs: self.read_boxed_expr()?,
+                expr_rhs: self.read_boxed_expr()?,
+                arith_op_type: match node_name {
+                    "_plus" => ArithOpType::Add,
+                    "_sub" => ArithOpType::Sub,
+                    "_mul" => ArithOpType::Mul,
+                    _ => ArithOpType::Div,
+                },
+            },
+            "_neg" => Expression::Neg {
+                span,
+                expr: self.read_boxed_expr()?,
+            },
+            "_lt" | "_leq" => Expression::Comp {
+                span,
+                expr_lhs: self.read_boxed_expr()?,
+                expr_rhs: self.read_boxed_expr()?,
+                comp_type: if node_name == "_lt" {
+                    CompType::LT
+                } else {
+                    CompType::LEq
+                },
+            },
+            "_eq" => Expression::Eq {
+                span,
+                expr_lhs: self.read_boxed_expr()?,
+                expr_rhs: self.read_boxed_expr()?,
+            },
+            "_comp" => Expression::Not {
+                span,
+                expr: self.read_boxed_expr()?,
+            },
+            "_int" => match self.next("an integer")?.parse::<u32>() {
+                Ok(val) => Expression::IntConst { span, val },
+                Err(_) => {
+                    self.pos -= 1;
+                    return Err(self.error("bad integer"));
+                }
+            },
+            "_bool" => match self.next("a boolean")? {
+                "1" => Expression::BoolConst { span, val: true },
+                "0" => Expression::BoolConst { span, val: false },
+                _ => {
+                    self.pos -= 1;
+                    return Err(self.error("bad boolean"));
+                }
+            },
+            "_string" => Expression::StringConst {
+                span,
+                val: self.read_str()?,
+                val_id: 0, // This is set later
+            },
+            "_new" => Expression::New {
+                span,
+                type_name: self.read_type_id()?,
+            },
+            "_isvoid" => Expression::IsVoid {
+                span,
+                expr: self.read_boxed_expr()?,
+            },
+            "_no_expr" => Expression::NoExpr,
+            "_object" => Expression::VarByName {
+                span,
+                name: self.read_object_id()?,
+                static_type,
+            },
+            _ => {
+                self.pos -= 1;
+                return Err(self.error("unknown expression"));
+            }
+        };
+
+        // Every expression ends with its static type
+        match self.peek() {
+            Some(text) if text.starts_with(':') => {
+                self.pos += 1;
+                Ok(expr)
+            }
+            _ => Err(self.error("expected the type of an expression")),
+        }
+    }
+
+    fn read_list_of_exprs(&mut self) -> Result<Vec<Expression>, String> {
+        let mut exprs = Vec::new();
+
+        while self.at_node() {
+            exprs.push(self.read_expr()?);
+        }
+
+        Ok(exprs)
+    }
+}
+
+// Undo the escapes of a quoted string (see token::escape_str)
+fn unescape_str(text: &str) -> Option<String> {
+    let inner = text.strip_prefix('"')?.strip_suffix('"')?;
+    let mut val = String::new();
+    let mut chars = inner.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            val.push(c);
+            continue;
+        }
+
+        match chars.next()? {
+            'n' => val.push('\n'),
+            't' => val.push('\t'),
+            'b' => val.push('\x08'),
+            'f' => val.push('\x0c'),
+            c @ '0'..='7' => {
+                // Octal escape of three digits
+                let digits: String = std::iter::once(c).chain(chars.by_ref().take(2)).collect();
+                val.push(u8::from_str_radix(&digits, 8).ok()? as char);
+            }
+            c => val.push(c),
+        }
+    }
+
+    Some(val)
+}
+
+// Read a program in the reference AST format into classes
+// Errors in the program and input that is not in the AST format are recorded in
+// diagnostics.
+// file_nos numbers the source files named in the AST (starting at 1).
+pub fn read_ast(
+    in_file_text: &str,
+    in_file_name: &str,
+    classes: &mut IndexMap<TypeID, Class>,
+    file_nos: &mut IndexMap<String, u32>,
+    diagnostics: &mut Diagnostics,
+) {
+    let mut reader = Reader::new(in_file_text);
+
+    if let Err(msg) = reader.read_program(classes, file_nos, diagnostics) {
+        diagnostics.error(
+            ErrorCode::MalformedAst,
+            in_file_name,
+            reader.error_span(in_file_text),
+            msg,
+        );
+    }
+}
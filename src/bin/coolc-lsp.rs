@@ -0,0 +1,15 @@
+// A language server for COOL that speaks LSP over standard input and output
+// See src/lsp/mod.rs for more detail.
+
+use std::io;
+use std::process::exit;
+
+fn main() {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+
+    if let Err(msg) = coolc::lsp::serve(stdin.lock(), stdout.lock()) {
+        eprintln!("{}", msg);
+        exit(1);
+    }
+}
@@ -3,6 +3,7 @@
 use indexmap::IndexMap;
 
 use crate::ast::{Attr, Class, Expression, Formal, Method, ObjectID, TypeID};
+use crate::span::Span;
 use std::ops::Range;
 
 // The classes defined here are automatically included.
@@ -12,7 +13,12 @@ use std::ops::Range;
 const file_name: String = String::new();
 const file_no: u32 = 0;
 const basic: bool = true;
-const line_no: i16 = 0;
+const span: Span = Span {
+    lo: 0,
+    hi: 0,
+    line_no: 0,
+    col: 0,
+};
 const attrs: Vec<Attr> = Vec::new();
 const family: Range<u32> = 0..0;
 const child_names: Vec<TypeID> = Vec::new();
@@ -24,21 +30,21 @@ const expr: Expression = Expression::NoExpr;
 pub fn initialize_classes() -> IndexMap<TypeID, Class> {
     let class_object = {
         let abort = Method {
-            line_no,
+            span,
             formals,
             return_type: TypeID::new_object(),
             expr,
         };
 
         let type_name = Method {
-            line_no,
+            span,
             formals,
             return_type: TypeID::new_string(),
             expr,
         };
 
         let copy = Method {
-            line_no,
+            span,
             formals,
             return_type: TypeID::new_self_type(),
             expr,
@@ -53,7 +59,7 @@ pub fn initialize_classes() -> IndexMap<TypeID, Class> {
             file_name,
             file_no,
             basic,
-            line_no,
+            span,
             parent_name: None,
             attrs,
             methods,
@@ -66,9 +72,9 @@ pub fn initialize_classes() -> IndexMap<TypeID, Class> {
 
     let class_io = {
         let out_string = Method {
-            line_no,
+            span,
             formals: vec![Formal {
-                line_no,
+                span,
                 name: ObjectID::new("arg".to_string()),
                 type_decl: TypeID::new_string(),
             }],
@@ -77,9 +83,9 @@ pub fn initialize_classes() -> IndexMap<TypeID, Class> {
         };
 
         let out_int = Method {
-            line_no,
+            span,
             formals: vec![Formal {
-                line_no,
+                span,
                 name: ObjectID::new("arg".to_string()),
                 type_decl: TypeID::new_int(),
             }],
@@ -88,14 +94,14 @@ pub fn initialize_classes() -> IndexMap<TypeID, Class> {
         };
 
         let in_string = Method {
-            line_no,
+            span,
             formals,
             return_type: TypeID::new_string(),
             expr,
         };
 
         let in_int = Method {
-            line_no,
+            span,
             formals,
             return_type: TypeID::new_int(),
             expr,
@@ -111,7 +117,7 @@ pub fn initialize_classes() -> IndexMap<TypeID, Class> {
             file_name,
             file_no,
             basic,
-            line_no,
+            span,
             parent_name: Some(TypeID::new_object()),
             attrs,
             methods,
@@ -124,7 +130,7 @@ pub fn initialize_classes() -> IndexMap<TypeID, Class> {
 
     let class_int = {
         let val = Attr {
-            line_no,
+            span,
             name: ObjectID::new("_val".to_string()),
             type_decl: TypeID::new("_prim_slot".to_string()),
             init: Expression::NoExpr,
@@ -137,7 +143,7 @@ pub fn initialize_classes() -> IndexMap<TypeID, Class> {
             file_name,
             file_no,
             basic,
-            line_no,
+            span,
             parent_name: Some(TypeID::new_object()),
             attrs: vec![val],
             methods,
@@ -150,7 +156,7 @@ pub fn initialize_classes() -> IndexMap<TypeID, Class> {
 
     let class_bool = {
         let val = Attr {
-            line_no,
+            span,
             name: ObjectID::new("_val".to_string()),
             type_decl: TypeID::new("_prim_slot".to_string()),
             init: Expression::NoExpr,
@@ -163,7 +169,7 @@ pub fn initialize_classes() -> IndexMap<TypeID, Class> {
             file_name,
             file_no,
             basic,
-            line_no,
+            span,
             parent_name: Some(TypeID::new_object()),
             attrs: vec![val],
             methods,
@@ -176,7 +182,7 @@ pub fn initialize_classes() -> IndexMap<TypeID, Class> {
 
     let class_string = {
         let val = Attr {
-            line_no,
+            span,
             name: ObjectID::new("_val".to_string()),
             type_decl: TypeID::new_int(),
             init: Expression::NoExpr,
@@ -184,7 +190,7 @@ pub fn initialize_classes() -> IndexMap<TypeID, Class> {
         };
 
         let str_field = Attr {
-            line_no,
+            span,
             name: ObjectID::new("_str_field".to_string()),
             type_decl: TypeID::new("_prim_slot".to_string()),
             init: Expression::NoExpr,
@@ -192,16 +198,16 @@ pub fn initialize_classes() -> IndexMap<TypeID, Class> {
         };
 
         let length = Method {
-            line_no,
+            span,
             formals,
             return_type: TypeID::new_int(),
             expr,
         };
 
         let concat = Method {
-            line_no,
+            span,
             formals: vec![Formal {
-                line_no,
+                span,
                 name: ObjectID::new("arg".to_string()),
                 type_decl: TypeID::new_string(),
             }],
@@ -210,15 +216,15 @@ pub fn initialize_classes() -> IndexMap<TypeID, Class> {
         };
 
         let substr = Method {
-            line_no,
+            span,
             formals: vec![
                 Formal {
-                    line_no,
+                    span,
                     name: ObjectID::new("arg1".to_string()),
                     type_decl: TypeID::new_int(),
                 },
                 Formal {
-                    line_no,
+                    span,
                     name: ObjectID::new("arg2".to_string()),
                     type_decl: TypeID::new_int(),
                 },
@@ -236,7 +242,7 @@ pub fn initialize_classes() -> IndexMap<TypeID, Class> {
             file_name,
             file_no,
             basic,
-            line_no,
+            span,
             parent_name: Some(TypeID::new_object()),
             attrs: vec![val, str_field],
             methods,
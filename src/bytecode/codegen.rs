@@ -0,0 +1,726 @@
+// Generate bytecode from the typed classes
+// The classes must have passed semantic analysis (tags, dispatch tables, attribute
+// offsets and case branch order are set by semant).
+
+use indexmap::IndexMap;
+
+use super::{AttrType, Builtin, ClassInfo, Function, Instr, Program};
+use crate::ast::{ArithOpType, Class, CompType, Expression, ObjectID, TypeID};
+use crate::scoped_collections::ScopedIndexMap;
+use crate::semant::ATTR_OFFSET_START;
+
+// Where a variable is kept
+#[derive(Clone, Copy)]
+enum Location {
+    Local(u32),
+    Attr(u32),
+}
+
+// The code of the function being generated
+struct Code {
+    instrs: Vec<Instr>,
+    locals: u32, // The most locals in use at once (beyond the arguments)
+}
+
+impl Code {
+    fn emit(&mut self, instr: Instr) {
+        self.instrs.push(instr);
+    }
+
+    // The position of the next instruction
+    fn pos(&self) -> u32 {
+        self.instrs.len() as u32
+    }
+
+    // Set the target of a jump once it is known
+    fn patch(&mut self, at: u32, target: u32) {
+        match &mut self.instrs[at as usize] {
+            Instr::Jump(t) | Instr::JumpIfFalse(t) => *t = target,
+            Instr::JumpUnlessTag { target: t, .. } => *t = target,
+            _ => panic!("Only jumps can be patched"),
+        }
+    }
+}
+
+// The builtin function for a method of a basic class
+fn builtin(class_name: &TypeID, method_name: &ObjectID) -> Builtin {
+    match (
+        class_name.to_string().as_str(),
+        method_name.to_string().as_str(),
+    ) {
+        ("Object", "abort") => Builtin::Abort,
+        ("Object", "type_name") => Builtin::TypeName,
+        ("Object", "copy") => Builtin::Copy,
+        ("IO", "out_string") => Builtin::OutString,
+        ("IO", "out_int") => Builtin::OutInt,
+        ("IO", "in_string") => Builtin::InString,
+        ("IO", "in_int") => Builtin::InInt,
+        ("String", "length") => Builtin::Length,
+        ("String", "concat") => Builtin::Concat,
+        ("String", "substr") => Builtin::Substr,
+        _ => panic!("Unknown method {}.{}", class_name, method_name),
+    }
+}
+
+fn attr_type(type_decl: &TypeID) -> AttrType {
+    if type_decl.is_int() {
+        AttrType::Int
+    } else if type_decl.is_bool() {
+        AttrType::Bool
+    } else if type_decl.is_string() {
+        AttrType::String
+    } else {
+        AttrType::Object
+    }
+}
+
+// Generate the program
+// The strings are the string constants (numbered as in str_table), then the file names
+// (numbered from 1, after an empty name for the basic classes) and the class names.
+pub fn generate(
+    classes: &IndexMap<TypeID, Class>,
+    in_file_names: &[String],
+    str_table: &[String],
+) -> Program {
+    let mut strings: Vec<Vec<u8>> = str_table.iter().map(|s| s.as_bytes().to_vec()).collect();
+
+    let first_file_name = strings.len() as u32;
+    strings.push(Vec::new());
+    for in_file_name in in_file_names.iter() {
+        strings.push(in_file_name.as_bytes().to_vec());
+    }
+
+    // The classes in tag order
+    let mut tagged: Vec<(&TypeID, &Class)> = classes.iter().collect();
+    tagged.sort_by_key(|(_, class)| class.tag());
+
+    // Number the functions: the init function of each class, then its methods
+    let mut functions: IndexMap<(TypeID, ObjectID), u32> = IndexMap::new();
+    let mut inits: IndexMap<TypeID, u32> = IndexMap::new();
+    let mut count: u32 = 0;
+
+    for (class_name, class) in classes.iter() {
+        inits.insert(class_name.clone(), count);
+        count += 1;
+
+        for method_name in class.methods.keys() {
+            functions.insert((class_name.clone(), method_name.clone()), count);
+            count += 1;
+        }
+    }
+
+    let mut program_classes = Vec::with_capacity(tagged.len());
+    let mut program_functions = Vec::with_capacity(count as usize);
+
+    for (class_name, class) in tagged {
+        strings.push(class_name.to_string().into_bytes());
+
+        let mut attrs = Vec::new();
+        if !class.basic {
+            attr_types(&mut attrs, classes, class_name);
+        }
+
+        program_classes.push(ClassInfo {
+            name: strings.len() as u32 - 1,
+            attrs,
+            init: inits[class_name],
+            dispatch_table: class
+                .dispatch_table
+                .iter()
+                .map(|(class_name, method_name)| {
+                    functions[&(class_name.clone(), method_name.clone())]
+                })
+                .collect(),
+        });
+    }
+
+    for (class_name, class) in classes.iter() {
+        let file_name = first_file_name + class.file_no;
+
+        program_functions.push(init_function(
+            class_name, class, classes, &inits, &functions, file_name,
+        ));
+
+        for (method_name, method) in class.methods.iter() {
+            if class.basic {
+                program_functions.push(Function::Builtin(builtin(class_name, method_name)));
+                continue;
+            }
+
+            let mut variables = class_variables(classes, class_name);
+            variables.enter_scope(); // Scope for arguments (formals)
+
+            for (formal, i) in method.formals.iter().zip(0..) {
+                variables.insert(formal.name.clone(), Location::Local(i));
+            }
+
+            let arity = method.formals.len() as u32;
+            let mut code = Code {
+                instrs: Vec::new(),
+                locals: 0,
+            };
+
+            code_expr(
+                &mut code,
+                &method.expr,
+                class,
+                classes,
+                &functions,
+                &mut variables,
+                arity,
+            );
+            code.emit(Instr::Return);
+
+            program_functions.push(Function::Code {
+                file_name,
+                arity,
+                locals: code.locals,
+                code: code.instrs,
+            });
+        }
+    }
+
+    let main_class = &classes[&TypeID::new_main()];
+    let main_pos = main_class.method_name_to_pos[&ObjectID::new_main()];
+    let tag = |class_name: TypeID| classes[&class_name].tag();
+
+    Program {
+        strings,
+        classes: program_classes,
+        functions: program_functions,
+        int_tag: tag(TypeID::new_int()),
+        bool_tag: tag(TypeID::new_bool()),
+        string_tag: tag(TypeID::new_string()),
+        main_tag: main_class.tag(),
+        main_method: {
+            let (class_name, method_name) = &main_class.dispatch_table[main_pos as usize];
+            functions[&(class_name.clone(), method_name.clone())]
+        },
+    }
+}
+
+// The types of the attributes of a class (inherited attributes first)
+fn attr_types(attrs: &mut Vec<AttrType>, classes: &IndexMap<TypeID, Class>, class_name: &TypeID) {
+    let class: &Class = classes.get(class_name).unwrap();
+
+    if let Some(parent_name) = &class.parent_name {
+        attr_types(attrs, classes, parent_name);
+    }
+
+    for attr in class.attrs.iter() {
+        attrs.push(attr_type(&attr.type_decl));
+    }
+}
+
+// The attributes that code in a class can name (including inherited attributes)
+fn class_variables(
+    classes: &IndexMap<TypeID, Class>,
+    class_name: &TypeID,
+) -> ScopedIndexMap<ObjectID, Location> {
+    let class: &Class = classes.get(class_name).unwrap();
+
+    let mut variables = match &class.parent_name {
+        Some(parent_name) => class_variables(classes, parent_name),
+        None => ScopedIndexMap::new(),
+    };
+
+    variables.enter_scope(); // Scope for attributes
+
+    if !class.basic {
+        for attr in class.attrs.iter() {
+            variables.insert(
+                attr.name.clone(),
+                Location::Attr((attr.self_offset - ATTR_OFFSET_START) as u32),
+            );
+        }
+    }
+
+    variables
+}
+
+// Initialization method
+// The parent's attributes are initialized first. Returns self.
+fn init_function(
+    class_name: &TypeID,
+    class: &Class,
+    classes: &IndexMap<TypeID, Class>,
+    inits: &IndexMap<TypeID, u32>,
+    functions: &IndexMap<(TypeID, ObjectID), u32>,
+    file_name: u32,
+) -> Function {
+    let mut code = Code {
+        instrs: Vec::new(),
+        locals: 0,
+    };
+
+    if let Some(parent_name) = &class.parent_name {
+        code.emit(Instr::PushSelf);
+        code.emit(Instr::StaticDispatch {
+            function: inits[parent_name],
+            args: 0,
+            line: 0, // self is never void
+        });
+        code.emit(Instr::Pop);
+    }
+
+    if !class.basic {
+        let mut variables = class_variables(classes, class_name);
+
+        for attr in class.attrs.iter() {
+            if let Expression::NoExpr = attr.init {
+            } else {
+                code_expr(
+                    &mut code,
+                    &attr.init,
+                    class,
+                    classes,
+                    functions,
+                    &mut variables,
+                    0,
+                );
+                code.emit(Instr::StoreAttr(
+                    (attr.self_offset - ATTR_OFFSET_START) as u32,
+                ));
+            }
+        }
+    }
+
+    code.emit(Instr::PushSelf);
+    code.emit(Instr::Return);
+
+    Function::Code {
+        file_name,
+        arity: 0,
+        locals: code.locals,
+        code: code.instrs,
+    }
+}
+
+// Output code for an expression
+// The code leaves the value on top of the stack.
+fn code_expr(
+    code: &mut Code,
+    expr: &Expression,
+    current_class: &Class,
+    classes: &IndexMap<TypeID, Class>,
+    functions: &IndexMap<(TypeID, ObjectID), u32>,
+    variables: &mut ScopedIndexMap<ObjectID, Location>,
+    next_local: u32, // The first local that is not in use
+) {
+    match expr {
+        Expression::Assign { name, expr, .. } => {
+            code_expr(
+                code,
+                expr,
+                current_class,
+                classes,
+                functions,
+                variables,
+                next_local,
+            );
+
+            // The value is also the result
+            code.emit(Instr::Dup);
+            code.emit(match variables.get(name).unwrap() {
+                Location::Local(i) => Instr::StoreLocal(*i),
+                Location::Attr(i) => Instr::StoreAttr(*i),
+            });
+        }
+        Expression::Dispatch {
+            span,
+            expr,
+            type_name,
+            name,
+            args,
+            ..
+        } => {
+            // Push arguments onto stack
+            for arg in args.iter() {
+                code_expr(
+                    code,
+                    arg,
+                    current_class,
+                    classes,
+                    functions,
+                    variables,
+                    next_local,
+                );
+            }
+
+            // Then the receiver
+            code_expr(
+                code,
+                expr,
+                current_class,
+                classes,
+                functions,
+                variables,
+                next_local,
+            );
+
+            let args = args.len() as u32;
+            let line = span.line_no;
+
+            match type_name {
+                Some(type_name) => {
+                    // Static dispatch calls the method found in the class's dispatch table
+                    let class = &classes[type_name];
+                    let pos = class.method_name_to_pos[name];
+                    let (class_name, method_name) = &class.dispatch_table[pos as usize];
+
+                    code.emit(Instr::StaticDispatch {
+                        function: functions[&(class_name.clone(), method_name.clone())],
+                        args,
+                        line,
+                    });
+                }
+                None => {
+                    // "Dynamic" dispatch
+                    let class = if expr.static_type().is_self_type() {
+                        current_class
+                    } else {
+                        &classes[&expr.static_type()]
+                    };
+
+                    code.emit(Instr::Dispatch {
+                        pos: class.method_name_to_pos[name] as u32,
+                        args,
+                        line,
+                    });
+                }
+            }
+        }
+        Expression::Cond {
+            pred,
+            then_expr,
+            else_expr,
+            ..
+        } => {
+            code_expr(
+                code,
+                pred,
+                current_class,
+                classes,
+                functions,
+                variables,
+                next_local,
+            );
+
+            let jump_else = code.pos();
+            code.emit(Instr::JumpIfFalse(0));
+
+            code_expr(
+                code,
+                then_expr,
+                current_class,
+                classes,
+                functions,
+                variables,
+                next_local,
+            );
+
+            let jump_fi = code.pos();
+            code.emit(Instr::Jump(0));
+
+            let else_pos = code.pos();
+            code.patch(jump_else, else_pos);
+
+            code_expr(
+                code,
+                else_expr,
+                current_class,
+                classes,
+                functions,
+                variables,
+                next_local,
+            );
+
+            let fi_pos = code.pos();
+            code.patch(jump_fi, fi_pos);
+        }
+        Expression::Loop { pred, body, .. } => {
+            let start = code.pos();
+
+            code_expr(
+                code,
+                pred,
+                current_class,
+                classes,
+                functions,
+                variables,
+                next_local,
+            );
+
+            let jump_end = code.pos();
+            code.emit(Instr::JumpIfFalse(0));
+
+            code_expr(
+                code,
+                body,
+                current_class,
+                classes,
+                functions,
+                variables,
+                next_local,
+            );
+
+            code.emit(Instr::Pop);
+            code.emit(Instr::Jump(start));
+
+            let end = code.pos();
+            code.patch(jump_end, end);
+
+            code.emit(Instr::PushVoid);
+        }
+        Expression::TypeCase {
+            span,
+            expr,
+            branches,
+            ..
+        } => {
+            code_expr(
+                code,
+                expr,
+                current_class,
+                classes,
+                functions,
+                variables,
+                next_local,
+            );
+
+            code.emit(Instr::CaseVoid(span.line_no));
+
+            let mut jumps_end = Vec::new();
+
+            // Code each branch
+            // Note that the order is already correct
+            for branch in branches.iter() {
+                let jump_next = code.pos();
+                code.emit(Instr::JumpUnlessTag {
+                    start: branch.family.start,
+                    end: branch.family.end,
+                    target: 0,
+                });
+
+                variables.enter_scope(); // Scope for new variable
+
+                // The matched value is kept in a local
+                code.emit(Instr::StoreLocal(next_local));
+                code.locals = code.locals.max(next_local + 1);
+                variables.insert(branch.name.clone(), Location::Local(next_local));
+
+                code_expr(
+                    code,
+                    &branch.expr,
+                    current_class,
+                    classes,
+                    functions,
+                    variables,
+                    next_local + 1,
+                );
+
+                variables.exit_scope();
+
+                jumps_end.push(code.pos());
+                code.emit(Instr::Jump(0));
+
+                let next = code.pos();
+                code.patch(jump_next, next);
+            }
+
+            // If no branch succeeds
+            code.emit(Instr::CaseAbort);
+
+            let end = code.pos();
+            for jump in jumps_end {
+                code.patch(jump, end);
+            }
+        }
+        Expression::Block { body, .. } => {
+            // Evaluate each expression in body (only the last value is kept)
+            for (i, expr) in body.iter().enumerate() {
+                if i > 0 {
+                    code.emit(Instr::Pop);
+                }
+
+                code_expr(
+                    code,
+                    expr,
+                    current_class,
+                    classes,
+                    functions,
+                    variables,
+                    next_local,
+                );
+            }
+        }
+        Expression::Let {
+            identifier,
+            type_decl,
+            init,
+            body,
+            ..
+        } => {
+            // Evaluate init expression
+            if let Expression::NoExpr = **init {
+                code.emit(match attr_type(type_decl) {
+                    AttrType::Int => Instr::PushInt(0),
+                    AttrType::Bool => Instr::PushBool(false),
+                    AttrType::String => Instr::PushString(0), // Empty String ("")
+                    AttrType::Object => Instr::PushVoid,
+                });
+            } else {
+                code_expr(
+                    code,
+                    init,
+                    current_class,
+                    classes,
+                    functions,
+                    variables,
+                    next_local,
+                );
+            }
+
+            variables.enter_scope(); // Scope for new variable
+
+            code.emit(Instr::StoreLocal(next_local));
+            code.locals = code.locals.max(next_local + 1);
+            variables.insert(identifier.clone(), Location::Local(next_local));
+
+            // Evaluate body
+            code_expr(
+                code,
+                body,
+                current_class,
+                classes,
+                functions,
+                variables,
+                next_local + 1,
+            );
+
+            variables.exit_scope();
+        }
+        Expression::ArithOp {
+            expr_lhs,
+            expr_rhs,
+            arith_op_type,
+            ..
+        } => {
+            for expr in [expr_lhs, expr_rhs].iter() {
+                code_expr(
+                    code,
+                    expr,
+                    current_class,
+                    classes,
+                    functions,
+                    variables,
+                    next_local,
+                );
+            }
+
+            code.emit(match arith_op_type {
+                ArithOpType::Add => Instr::Add,
+                ArithOpType::Sub => Instr::Sub,
+                ArithOpType::Mul => Instr::Mul,
+                ArithOpType::Div => Instr::Div,
+            });
+        }
+        Expression::Neg { expr, .. } => {
+            code_expr(
+                code,
+                expr,
+                current_class,
+                classes,
+                functions,
+                variables,
+                next_local,
+            );
+            code.emit(Instr::Neg);
+        }
+        Expression::Comp {
+            expr_lhs,
+            expr_rhs,
+            comp_type,
+            ..
+        } => {
+            for expr in [expr_lhs, expr_rhs].iter() {
+                code_expr(
+                    code,
+                    expr,
+                    current_class,
+                    classes,
+                    functions,
+                    variables,
+                    next_local,
+                );
+            }
+
+            code.emit(match comp_type {
+                CompType::LT => Instr::Lt,
+                CompType::LEq => Instr::Le,
+            });
+        }
+        Expression::Eq {
+            expr_lhs, expr_rhs, ..
+        } => {
+            for expr in [expr_lhs, expr_rhs].iter() {
+                code_expr(
+                    code,
+                    expr,
+                    current_class,
+                    classes,
+                    functions,
+                    variables,
+                    next_local,
+                );
+            }
+
+            code.emit(Instr::Eq);
+        }
+        Expression::Not { expr, .. } => {
+            code_expr(
+                code,
+                expr,
+                current_class,
+                classes,
+                functions,
+                variables,
+                next_local,
+            );
+            code.emit(Instr::Not);
+        }
+        Expression::IntConst { val, .. } => code.emit(Instr::PushInt(*val as i32)),
+        Expression::BoolConst { val, .. } => code.emit(Instr::PushBool(*val)),
+        Expression::StringConst { val_id, .. } => code.emit(Instr::PushString(*val_id)),
+        Expression::New { type_name, .. } => {
+            if type_name.is_self_type() {
+                code.emit(Instr::NewSelfType);
+            } else {
+                code.emit(Instr::New(classes[type_name].tag()));
+            }
+        }
+        Expression::IsVoid { expr, .. } => {
+            code_expr(
+                code,
+                expr,
+                current_class,
+                classes,
+                functions,
+                variables,
+                next_local,
+            );
+            code.emit(Instr::IsVoid);
+        }
+        Expression::NoExpr => code.emit(Instr::PushVoid), // Never used
+        Expression::VarByName { name, .. } => {
+            if name.is_self() {
+                code.emit(Instr::PushSelf);
+            } else {
+                code.emit(match variables.get(name).unwrap() {
+                    Location::Local(i) => Instr::LoadLocal(*i),
+                    Location::Attr(i) => Instr::LoadAttr(*i),
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,452 @@
+// The .coolbc file format
+// A file starts with the magic bytes "COOLBC" and a version byte. Everything after
+// that is a sequence of numbers, each written in LEB128 (7 bits per byte, the low
+// bits first, with the high bit set on every byte but the last). Signed numbers are
+// zigzag encoded first, so that small negative numbers are short as well.
+//
+//     header     int_tag bool_tag string_tag main_tag main_method
+//     strings    count, then each string as its length and its bytes
+//     classes    count, then for each class (in tag order):
+//                name init attr_count attr_type... dispatch_count function...
+//     functions  count, then for each function:
+//                0 file_name arity locals instr_count instr...  (code)
+//                1 builtin                                      (a method of a basic class)
+//
+// Each instruction is an opcode followed by its operands (see write_instr). A file is
+// checked as it is read, so the machine never sees a bad string, class, function,
+// local or jump target.
+
+use super::{AttrType, Builtin, ClassInfo, Function, Instr, Program};
+
+const MAGIC: &[u8] = b"COOLBC";
+const VERSION: u8 = 1;
+
+const ATTR_TYPES: [AttrType; 4] = [
+    AttrType::Object,
+    AttrType::Int,
+    AttrType::Bool,
+    AttrType::String,
+];
+
+impl Program {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        out.push(VERSION);
+
+        for val in [
+            self.int_tag,
+            self.bool_tag,
+            self.string_tag,
+            self.main_tag,
+            self.main_method,
+        ]
+        .iter()
+        {
+            write_uint(&mut out, *val);
+        }
+
+        write_uint(&mut out, self.strings.len() as u32);
+        for string in self.strings.iter() {
+            write_uint(&mut out, string.len() as u32);
+            out.extend(string);
+        }
+
+        write_uint(&mut out, self.classes.len() as u32);
+        for class in self.classes.iter() {
+            write_uint(&mut out, class.name);
+            write_uint(&mut out, class.init);
+
+            write_uint(&mut out, class.attrs.len() as u32);
+            for attr in class.attrs.iter() {
+                let pos = ATTR_TYPES.iter().position(|t| t == attr).unwrap();
+                out.push(pos as u8);
+            }
+
+            write_uint(&mut out, class.dispatch_table.len() as u32);
+            for function in class.dispatch_table.iter() {
+                write_uint(&mut out, *function);
+            }
+        }
+
+        write_uint(&mut out, self.functions.len() as u32);
+        for function in self.functions.iter() {
+            match function {
+                Function::Code {
+                    file_name,
+                    arity,
+                    locals,
+                    code,
+                } => {
+                    out.push(0);
+                    write_uint(&mut out, *file_name);
+                    write_uint(&mut out, *arity);
+                    write_uint(&mut out, *locals);
+
+                    write_uint(&mut out, code.len() as u32);
+                    for instr in code.iter() {
+                        write_instr(&mut out, *instr);
+                    }
+                }
+                Function::Builtin(builtin) => {
+                    out.push(1);
+                    let pos = Builtin::ALL.iter().position(|b| b == builtin).unwrap();
+                    out.push(pos as u8);
+                }
+            }
+        }
+
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Program, String> {
+        if !bytes.starts_with(MAGIC) {
+            return Err("Not a COOL bytecode file".to_string());
+        }
+
+        let mut reader = Reader {
+            bytes,
+            pos: MAGIC.len(),
+        };
+
+        let version = reader.byte()?;
+        if version != VERSION {
+            return Err(format!("Unsupported bytecode version {}", version));
+        }
+
+        let int_tag = reader.uint()?;
+        let bool_tag = reader.uint()?;
+        let string_tag = reader.uint()?;
+        let main_tag = reader.uint()?;
+        let main_method = reader.uint()?;
+
+        let mut strings = Vec::new();
+        for _ in 0..reader.uint()? {
+            let len = reader.uint()? as usize;
+            strings.push(reader.take(len)?.to_vec());
+        }
+
+        let mut classes = Vec::new();
+        for _ in 0..reader.uint()? {
+            let name = reader.uint()?;
+            let init = reader.uint()?;
+
+            let mut attrs = Vec::new();
+            for _ in 0..reader.uint()? {
+                match ATTR_TYPES.get(reader.byte()? as usize) {
+                    Some(attr) => attrs.push(*attr),
+                    None => return Err(bad("attribute type")),
+                }
+            }
+
+            let mut dispatch_table = Vec::new();
+            for _ in 0..reader.uint()? {
+                dispatch_table.push(reader.uint()?);
+            }
+
+            classes.push(ClassInfo {
+                name,
+                attrs,
+                init,
+                dispatch_table,
+            });
+        }
+
+        let mut functions = Vec::new();
+        for _ in 0..reader.uint()? {
+            functions.push(match reader.byte()? {
+                0 => {
+                    let file_name = reader.uint()?;
+                    let arity = reader.uint()?;
+                    let locals = reader.uint()?;
+
+                    let mut code = Vec::new();
+                    for _ in 0..reader.uint()? {
+                        code.push(reader.instr()?);
+                    }
+
+                    Function::Code {
+                        file_name,
+                        arity,
+                        locals,
+                        code,
+                    }
+                }
+                1 => match Builtin::ALL.get(reader.byte()? as usize) {
+                    Some(builtin) => Function::Builtin(*builtin),
+                    None => return Err(bad("builtin method")),
+                },
+                _ => return Err(bad("function")),
+            });
+        }
+
+        if reader.pos != bytes.len() {
+            return Err(bad("end of file"));
+        }
+
+        let program = Program {
+            strings,
+            classes,
+            functions,
+            int_tag,
+            bool_tag,
+            string_tag,
+            main_tag,
+            main_method,
+        };
+
+        check(&program)?;
+
+        Ok(program)
+    }
+}
+
+fn bad(what: &str) -> String {
+    format!("Bad {} in bytecode file", what)
+}
+
+// Check that every number that refers to something is in range
+fn check(program: &Program) -> Result<(), String> {
+    let string = |i: u32| (i as usize) < program.strings.len();
+    let class = |i: u32| (i as usize) < program.classes.len();
+    let function = |i: u32| (i as usize) < program.functions.len();
+
+    let tags = [
+        program.int_tag,
+        program.bool_tag,
+        program.string_tag,
+        program.main_tag,
+    ];
+
+    if !tags.iter().all(|tag| class(*tag)) {
+        return Err(bad("class"));
+    }
+
+    if !function(program.main_method) {
+        return Err(bad("function"));
+    }
+
+    for info in program.classes.iter() {
+        if !string(info.name) {
+            return Err(bad("string"));
+        }
+
+        if !function(info.init) || !info.dispatch_table.iter().all(|f| function(*f)) {
+            return Err(bad("function"));
+        }
+    }
+
+    for f in program.functions.iter() {
+        let (file_name, frame_size, code) = match f {
+            Function::Code {
+                file_name,
+                arity,
+                locals,
+                code,
+            } => (*file_name, *arity as u64 + *locals as u64, code),
+            Function::Builtin(_) => continue,
+        };
+
+        if !string(file_name) {
+            return Err(bad("string"));
+        }
+
+        let target = |i: u32| (i as usize) < code.len();
+
+        for instr in code.iter() {
+            let ok = match *instr {
+                Instr::PushString(i) => string(i),
+                Instr::LoadLocal(i) | Instr::StoreLocal(i) => (i as u64) < frame_size,
+                Instr::Jump(i) | Instr::JumpIfFalse(i) => target(i),
+                Instr::JumpUnlessTag { target: i, .. } => target(i),
+                Instr::New(i) => class(i),
+                Instr::StaticDispatch { function: i, .. } => function(i),
+                _ => true,
+            };
+
+            if !ok {
+                return Err(bad("instruction"));
+            }
+        }
+
+        // The machine must not run past the end of the code
+        if code.last() != Some(&Instr::Return) {
+            return Err(bad("function"));
+        }
+    }
+
+    Ok(())
+}
+
+// The opcodes in order (each operand follows as a number)
+//     0 PushVoid        1 PushInt n     2 PushBool b      3 PushString s
+//     4 PushSelf        5 LoadLocal i   6 StoreLocal i    7 LoadAttr i
+//     8 StoreAttr i     9 Dup          10 Pop            11 Jump t
+//    12 JumpIfFalse t  13 Add          14 Sub            15 Mul
+//    16 Div            17 Neg          18 Lt             19 Le
+//    20 Eq             21 Not          22 IsVoid         23 New c
+//    24 NewSelfType    25 Dispatch pos args line         26 StaticDispatch f args line
+//    27 CaseVoid line  28 JumpUnlessTag start end t      29 CaseAbort
+//    30 Return
+fn write_instr(out: &mut Vec<u8>, instr: Instr) {
+    let (opcode, operands): (u8, Vec<u32>) = match instr {
+        Instr::PushVoid => (0, vec![]),
+        Instr::PushInt(val) => {
+            out.push(1);
+            write_int(out, val);
+            return;
+        }
+        Instr::PushBool(val) => (2, vec![val as u32]),
+        Instr::PushString(i) => (3, vec![i]),
+        Instr::PushSelf => (4, vec![]),
+        Instr::LoadLocal(i) => (5, vec![i]),
+        Instr::StoreLocal(i) => (6, vec![i]),
+        Instr::LoadAttr(i) => (7, vec![i]),
+        Instr::StoreAttr(i) => (8, vec![i]),
+        Instr::Dup => (9, vec![]),
+        Instr::Pop => (10, vec![]),
+        Instr::Jump(t) => (11, vec![t]),
+        Instr::JumpIfFalse(t) => (12, vec![t]),
+        Instr::Add => (13, vec![]),
+        Instr::Sub => (14, vec![]),
+        Instr::Mul => (15, vec![]),
+        Instr::Div => (16, vec![]),
+        Instr::Neg => (17, vec![]),
+        Instr::Lt => (18, vec![]),
+        Instr::Le => (19, vec![]),
+        Instr::Eq => (20, vec![]),
+        Instr::Not => (21, vec![]),
+        Instr::IsVoid => (22, vec![]),
+        Instr::New(c) => (23, vec![c]),
+        Instr::NewSelfType => (24, vec![]),
+        Instr::Dispatch { pos, args, line } => (25, vec![pos, args, line]),
+        Instr::StaticDispatch {
+            function,
+            args,
+            line,
+        } => (26, vec![function, args, line]),
+        Instr::CaseVoid(line) => (27, vec![line]),
+        Instr::JumpUnlessTag { start, end, target } => (28, vec![start, end, target]),
+        Instr::CaseAbort => (29, vec![]),
+        Instr::Return => (30, vec![]),
+    };
+
+    out.push(opcode);
+
+    for operand in operands {
+        write_uint(out, operand);
+    }
+}
+
+fn write_uint(out: &mut Vec<u8>, mut val: u32) {
+    while val >= 0x80 {
+        out.push((val as u8 & 0x7f) | 0x80);
+        val >>= 7;
+    }
+
+    out.push(val as u8);
+}
+
+fn write_int(out: &mut Vec<u8>, val: i32) {
+    write_uint(out, ((val << 1) ^ (val >> 31)) as u32);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        match self.bytes.get(self.pos..self.pos.saturating_add(len)) {
+            Some(bytes) => {
+                self.pos += len;
+                Ok(bytes)
+            }
+            None => Err("Unexpected end of bytecode file".to_string()),
+        }
+    }
+
+    fn uint(&mut self) -> Result<u32, String> {
+        let mut val: u32 = 0;
+
+        for shift in (0..35).step_by(7) {
+            let byte = self.byte()?;
+
+            // The fifth byte holds only the top 4 bits
+            if shift == 28 && byte > 0x0f {
+                return Err(bad("number"));
+            }
+
+            val |= ((byte & 0x7f) as u32) << shift;
+
+            if byte & 0x80 == 0 {
+                return Ok(val);
+            }
+        }
+
+        Err(bad("number"))
+    }
+
+    fn int(&mut self) -> Result<i32, String> {
+        let val = self.uint()?;
+
+        Ok((val >> 1) as i32 ^ -((val & 1) as i32))
+    }
+
+    fn instr(&mut self) -> Result<Instr, String> {
+        Ok(match self.byte()? {
+            0 => Instr::PushVoid,
+            1 => Instr::PushInt(self.int()?),
+            2 => match self.uint()? {
+                0 => Instr::PushBool(false),
+                1 => Instr::PushBool(true),
+                _ => return Err(bad("instruction")),
+            },
+            3 => Instr::PushString(self.uint()?),
+            4 => Instr::PushSelf,
+            5 => Instr::LoadLocal(self.uint()?),
+            6 => Instr::StoreLocal(self.uint()?),
+            7 => Instr::LoadAttr(self.uint()?),
+            8 => Instr::StoreAttr(self.uint()?),
+            9 => Instr::Dup,
+            10 => Instr::Pop,
+            11 => Instr::Jump(self.uint()?),
+            12 => Instr::JumpIfFalse(self.uint()?),
+            13 => Instr::Add,
+            14 => Instr::Sub,
+            15 => Instr::Mul,
+            16 => Instr::Div,
+            17 => Instr::Neg,
+            18 => Instr::Lt,
+            19 => Instr::Le,
+            20 => Instr::Eq,
+            21 => Instr::Not,
+            22 => Instr::IsVoid,
+            23 => Instr::New(self.uint()?),
+            24 => Instr::NewSelfType,
+            25 => Instr::Dispatch {
+                pos: self.uint()?,
+                args: self.uint()?,
+                line: self.uint()?,
+            },
+            26 => Instr::StaticDispatch {
+                function: self.uint()?,
+                args: self.uint()?,
+                line: self.uint()?,
+            },
+            27 => Instr::CaseVoid(self.uint()?),
+            28 => Instr::JumpUnlessTag {
+                start: self.uint()?,
+                end: self.uint()?,
+                target: self.uint()?,
+            },
+            29 => Instr::CaseAbort,
+            30 => Instr::Return,
+            _ => return Err(bad("instruction")),
+        })
+    }
+}
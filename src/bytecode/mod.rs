@@ -0,0 +1,146 @@
+// A compact bytecode for COOL programs and a virtual machine that runs it
+// The bytecode is generated from the typed classes (see codegen.rs) and can be saved
+// as a .coolbc file (see file.rs). The machine (see vm.rs) is a stack machine that
+// writes exactly what the MIPS program writes under mips::run, including the
+// messages of runtime errors.
+//
+// Classes are numbered by their tags, so a case branch matches a range of classes
+// (see Branch::family). Each class has a dispatch table of function numbers in the
+// order of Class::dispatch_table.
+
+mod codegen;
+mod file;
+mod vm;
+
+pub use self::codegen::generate;
+pub use self::vm::run;
+
+pub const FILE_EXTENSION: &str = "coolbc";
+
+pub struct Program {
+    pub strings: Vec<Vec<u8>>, // String constants, then file names and class names
+    pub classes: Vec<ClassInfo>, // In tag order
+    pub functions: Vec<Function>,
+    pub int_tag: u32,
+    pub bool_tag: u32,
+    pub string_tag: u32,
+    pub main_tag: u32,
+    pub main_method: u32, // The function for Main.main
+}
+
+pub struct ClassInfo {
+    pub name: u32,                // A string
+    pub attrs: Vec<AttrType>,     // All attributes (inherited attributes first)
+    pub init: u32,                // The function that initializes an object (self is returned)
+    pub dispatch_table: Vec<u32>, // Functions
+}
+
+// What an attribute holds before it is initialized
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttrType {
+    Object, // Void
+    Int,    // 0
+    Bool,   // false
+    String, // ""
+}
+
+pub enum Function {
+    Code {
+        file_name: u32, // A string (for the messages of runtime errors)
+        arity: u32,     // The arguments are the first locals
+        locals: u32,    // How many let and case variables may be in use at once
+        code: Vec<Instr>,
+    },
+    Builtin(Builtin),
+}
+
+// The methods of the basic classes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Builtin {
+    Abort,
+    TypeName,
+    Copy,
+    OutString,
+    OutInt,
+    InString,
+    InInt,
+    Length,
+    Concat,
+    Substr,
+}
+
+impl Builtin {
+    pub const ALL: [Builtin; 10] = [
+        Builtin::Abort,
+        Builtin::TypeName,
+        Builtin::Copy,
+        Builtin::OutString,
+        Builtin::OutInt,
+        Builtin::InString,
+        Builtin::InInt,
+        Builtin::Length,
+        Builtin::Concat,
+        Builtin::Substr,
+    ];
+
+    pub fn arity(self) -> u32 {
+        match self {
+            Builtin::OutString | Builtin::OutInt | Builtin::Concat => 1,
+            Builtin::Substr => 2,
+            _ => 0,
+        }
+    }
+}
+
+// An instruction of the stack machine
+// Each method call has a frame of locals (the arguments, then let and case
+// variables) and works on the values above them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instr {
+    PushVoid,
+    PushInt(i32),
+    PushBool(bool),
+    PushString(u32),
+    PushSelf,
+    LoadLocal(u32),
+    StoreLocal(u32), // Pops the value
+    LoadAttr(u32),   // Of self
+    StoreAttr(u32),  // Pops the value
+    Dup,
+    Pop,
+    Jump(u32),        // To an instruction of the same function
+    JumpIfFalse(u32), // Pops a Bool
+    Add,              // The Int operations pop two values (the right one on top)
+    Sub,
+    Mul,
+    Div,
+    Neg,
+    Lt,
+    Le,
+    Eq,
+    Not,
+    IsVoid,
+    New(u32), // Creates and initializes an object of a class
+    NewSelfType,
+    // The arguments are pushed in order, then the receiver
+    // Both stop the program if the receiver is void.
+    Dispatch {
+        pos: u32, // In the dispatch table of the receiver's class
+        args: u32,
+        line: u32,
+    },
+    StaticDispatch {
+        function: u32,
+        args: u32,
+        line: u32,
+    },
+    CaseVoid(u32), // Stops the program if the value being matched is void (the line)
+    // Jumps unless the tag of the value being matched is in start..end
+    JumpUnlessTag {
+        start: u32,
+        end: u32,
+        target: u32,
+    },
+    CaseAbort, // No branch matched
+    Return,
+}
@@ -0,0 +1,582 @@
+// The virtual machine that runs bytecode
+// Calls do not use the Rust stack, so deep recursion only needs memory.
+
+use std::cell::RefCell;
+use std::io::{BufRead, Write};
+use std::rc::Rc;
+
+use super::{AttrType, Builtin, Function, Instr, Program};
+use crate::mips::TERM_MSG;
+
+const MAX_FRAMES: usize = 1 << 20; // Deeper recursion is a stack overflow
+
+#[derive(Clone)]
+enum Value {
+    Void,
+    Int(i32),
+    Bool(bool),
+    Str(Rc<[u8]>),
+    Object(Rc<Object>),
+}
+
+// An object of a class other than Int, Bool and String
+struct Object {
+    tag: u32,
+    attrs: RefCell<Vec<Value>>,
+}
+
+// Why the program stopped early
+enum Stop {
+    Halt,          // A runtime error (the message has been written)
+    Error(String), // An error that the MIPS program reports as an exception
+}
+
+// The state of a method call
+struct Frame<'a> {
+    code: &'a [Instr],
+    file_name: u32,
+    pc: usize,
+    base: usize, // Where the locals start on the stack
+    self_val: Value,
+}
+
+struct Machine<'a> {
+    program: &'a Program,
+    strings: Vec<Rc<[u8]>>,
+    stack: Vec<Value>,
+    frames: Vec<Frame<'a>>,
+    input: &'a mut dyn BufRead,
+    output: &'a mut dyn Write,
+}
+
+// Run a program
+// Returns false if the program was stopped by a runtime error (e.g. a dispatch to void).
+pub fn run(
+    program: &Program,
+    input: &mut dyn BufRead,
+    output: &mut dyn Write,
+) -> Result<bool, String> {
+    let mut machine = Machine {
+        program,
+        strings: program
+            .strings
+            .iter()
+            .map(|string| Rc::from(string.as_slice()))
+            .collect(),
+        stack: Vec::new(),
+        frames: Vec::new(),
+        input,
+        output,
+    };
+
+    let result = match machine.run_main() {
+        Ok(()) => machine.write(TERM_MSG.as_bytes()).map(|_| true),
+        Err(Stop::Halt) => Ok(false),
+        Err(Stop::Error(msg)) => Err(msg),
+    };
+
+    // Output written before an error is kept
+    if let Err(why) = machine.output.flush() {
+        return Err(format!("Cannot write output: {}", why));
+    }
+
+    result
+}
+
+fn bad_code() -> Stop {
+    Stop::Error("Bad bytecode".to_string())
+}
+
+impl<'a> Machine<'a> {
+    // Create a Main object and call its main method
+    fn run_main(&mut self) -> Result<(), Stop> {
+        self.new_object(self.program.main_tag)?;
+        self.finish()?;
+
+        let main = self.pop()?;
+        self.call(self.program.main_method, main, 0)?;
+        self.finish()?;
+
+        Ok(())
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.output
+            .write_all(bytes)
+            .map_err(|why| format!("Cannot write output: {}", why))
+    }
+
+    // Write the message of a runtime error and stop
+    fn halt(&mut self, msg: &[u8]) -> Result<(), Stop> {
+        self.write(msg).map_err(Stop::Error)?;
+
+        Err(Stop::Halt)
+    }
+
+    fn read_line(&mut self) -> Result<Vec<u8>, Stop> {
+        // Prompts must appear before the program waits for input
+        if let Err(why) = self.output.flush() {
+            return Err(Stop::Error(format!("Cannot write output: {}", why)));
+        }
+
+        let mut line = Vec::new();
+
+        if let Err(why) = self.input.read_until(b'\n', &mut line) {
+            return Err(Stop::Error(format!("Cannot read input: {}", why)));
+        }
+
+        if line.last() == Some(&b'\n') {
+            line.pop();
+        }
+
+        Ok(line)
+    }
+
+    fn push(&mut self, val: Value) {
+        self.stack.push(val);
+    }
+
+    fn pop(&mut self) -> Result<Value, Stop> {
+        self.stack.pop().ok_or_else(bad_code)
+    }
+
+    fn pop_int(&mut self) -> Result<i32, Stop> {
+        match self.pop()? {
+            Value::Int(val) => Ok(val),
+            _ => Err(bad_code()),
+        }
+    }
+
+    fn pop_bool(&mut self) -> Result<bool, Stop> {
+        match self.pop()? {
+            Value::Bool(val) => Ok(val),
+            _ => Err(bad_code()),
+        }
+    }
+
+    fn top(&self) -> Result<&Value, Stop> {
+        self.stack.last().ok_or_else(bad_code)
+    }
+
+    fn frame(&self) -> &Frame<'a> {
+        self.frames.last().unwrap()
+    }
+
+    fn tag(&self, val: &Value) -> Result<u32, Stop> {
+        match val {
+            Value::Void => Err(bad_code()),
+            Value::Int(_) => Ok(self.program.int_tag),
+            Value::Bool(_) => Ok(self.program.bool_tag),
+            Value::Str(_) => Ok(self.program.string_tag),
+            Value::Object(obj) => Ok(obj.tag),
+        }
+    }
+
+    // The name of the class of a value
+    fn class_name(&self, val: &Value) -> Result<Rc<[u8]>, Stop> {
+        let tag = self.tag(val)?;
+
+        Ok(self.strings[self.program.classes[tag as usize].name as usize].clone())
+    }
+
+    // Write a runtime error message that names the current file and a line
+    fn halt_at(&mut self, line: u32, msg: &str) -> Result<(), Stop> {
+        let mut text = self.strings[self.frame().file_name as usize].to_vec();
+        text.extend(format!(":{}: {}\n", line, msg).bytes());
+
+        self.halt(&text)
+    }
+
+    // Push a new object of a class and call its init method (which returns it)
+    fn new_object(&mut self, tag: u32) -> Result<(), Stop> {
+        let program = self.program;
+
+        if tag == program.int_tag {
+            self.push(Value::Int(0));
+        } else if tag == program.bool_tag {
+            self.push(Value::Bool(false));
+        } else if tag == program.string_tag {
+            self.push(Value::Str(Rc::from(&[][..])));
+        } else {
+            let class = &program.classes[tag as usize];
+
+            // Attributes start with the default value of their type
+            let attrs = class
+                .attrs
+                .iter()
+                .map(|attr| match attr {
+                    AttrType::Object => Value::Void,
+                    AttrType::Int => Value::Int(0),
+                    AttrType::Bool => Value::Bool(false),
+                    AttrType::String => Value::Str(Rc::from(&[][..])),
+                })
+                .collect();
+
+            let obj = Value::Object(Rc::new(Object {
+                tag,
+                attrs: RefCell::new(attrs),
+            }));
+
+            self.call(class.init, obj, 0)?;
+        }
+
+        Ok(())
+    }
+
+    // Call a function with arguments that are on the stack
+    // A builtin method pushes its result at once. Otherwise, a frame is pushed
+    // and the result is pushed when it returns.
+    fn call(&mut self, function: u32, receiver: Value, args: u32) -> Result<(), Stop> {
+        let args = args as usize;
+
+        if args > self.stack.len() {
+            return Err(bad_code());
+        }
+
+        match &self.program.functions[function as usize] {
+            Function::Builtin(builtin) => {
+                if builtin.arity() as usize != args {
+                    return Err(bad_code());
+                }
+
+                let arg_vals = self.stack.split_off(self.stack.len() - args);
+                let result = self.builtin(*builtin, receiver, arg_vals)?;
+                self.push(result);
+            }
+            Function::Code {
+                file_name,
+                arity,
+                locals,
+                code,
+            } => {
+                if *arity as usize != args {
+                    return Err(bad_code());
+                }
+
+                if self.frames.len() == MAX_FRAMES {
+                    return Err(Stop::Error("Stack overflow".to_string()));
+                }
+
+                let base = self.stack.len() - args;
+                self.stack
+                    .resize(base + args + *locals as usize, Value::Void);
+
+                self.frames.push(Frame {
+                    code,
+                    file_name: *file_name,
+                    pc: 0,
+                    base,
+                    self_val: receiver,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn builtin(
+        &mut self,
+        builtin: Builtin,
+        receiver: Value,
+        args: Vec<Value>,
+    ) -> Result<Value, Stop> {
+        match builtin {
+            Builtin::Abort => {
+                let mut msg = b"Abort called from class ".to_vec();
+                msg.extend(self.class_name(&receiver)?.iter());
+                msg.push(b'\n');
+
+                self.halt(&msg)?;
+
+                Ok(Value::Void)
+            }
+            Builtin::TypeName => Ok(Value::Str(self.class_name(&receiver)?)),
+            Builtin::Copy => Ok(match receiver {
+                Value::Object(obj) => Value::Object(Rc::new(Object {
+                    tag: obj.tag,
+                    attrs: RefCell::new(obj.attrs.borrow().clone()),
+                })),
+                val => val,
+            }),
+            Builtin::OutString => {
+                if let Some(Value::Str(val)) = args.first() {
+                    self.write(val).map_err(Stop::Error)?;
+                }
+
+                Ok(receiver)
+            }
+            Builtin::OutInt => {
+                if let Some(Value::Int(val)) = args.first() {
+                    self.write(val.to_string().as_bytes())
+                        .map_err(Stop::Error)?;
+                }
+
+                Ok(receiver)
+            }
+            Builtin::InString => {
+                let line = self.read_line()?;
+
+                // A string with a null character is read as the empty string
+                if line.contains(&0) {
+                    Ok(Value::Str(Rc::from(&[][..])))
+                } else {
+                    Ok(Value::Str(Rc::from(line)))
+                }
+            }
+            Builtin::InInt => {
+                let line = self.read_line()?;
+                let line = String::from_utf8_lossy(&line);
+                let line = line.trim_start();
+
+                // Like atoi, leading digits are read and the rest is ignored
+                let end = line
+                    .char_indices()
+                    .find(|(i, c)| !(c.is_ascii_digit() || (*i == 0 && (*c == '-' || *c == '+'))))
+                    .map_or(line.len(), |(i, _)| i);
+
+                Ok(Value::Int(line[..end].parse::<i64>().unwrap_or(0) as i32))
+            }
+            Builtin::Length => match receiver {
+                Value::Str(val) => Ok(Value::Int(val.len() as i32)),
+                _ => Err(bad_code()),
+            },
+            Builtin::Concat => match (receiver, args.first()) {
+                (Value::Str(val), Some(Value::Str(arg))) => {
+                    let mut val = val.to_vec();
+                    val.extend(arg.iter());
+
+                    Ok(Value::Str(Rc::from(val)))
+                }
+                _ => Err(bad_code()),
+            },
+            Builtin::Substr => match (receiver, args.first(), args.get(1)) {
+                (Value::Str(val), Some(Value::Int(index)), Some(Value::Int(len))) => {
+                    let (index, len) = (*index, *len);
+
+                    let msg: &[u8] = if index < 0 {
+                        b"Index to substr is negative\n"
+                    } else if index as usize > val.len() {
+                        b"Index to substr is too big\n"
+                    } else if len < 0 {
+                        b"Length to substr is negative\n"
+                    } else if (index as usize + len as usize) > val.len() {
+                        b"Length to substr too long\n"
+                    } else {
+                        let start = index as usize;
+
+                        return Ok(Value::Str(Rc::from(&val[start..start + len as usize])));
+                    };
+
+                    let mut msg = msg.to_vec();
+                    msg.extend(b"Execution aborted.\n");
+
+                    self.halt(&msg)?;
+
+                    Ok(Value::Void)
+                }
+                _ => Err(bad_code()),
+            },
+        }
+    }
+
+    // Run until the current frame returns
+    // Its result is left on the stack.
+    fn finish(&mut self) -> Result<(), Stop> {
+        let depth = self.frames.len();
+
+        while self.frames.len() >= depth && depth > 0 {
+            self.step()?;
+        }
+
+        Ok(())
+    }
+
+    // Execute one instruction
+    fn step(&mut self) -> Result<(), Stop> {
+        let frame = self.frames.last_mut().unwrap();
+        let instr = frame.code[frame.pc];
+        frame.pc += 1;
+
+        match instr {
+            Instr::PushVoid => self.push(Value::Void),
+            Instr::PushInt(val) => self.push(Value::Int(val)),
+            Instr::PushBool(val) => self.push(Value::Bool(val)),
+            Instr::PushString(i) => self.push(Value::Str(self.strings[i as usize].clone())),
+            Instr::PushSelf => self.push(self.frame().self_val.clone()),
+            Instr::LoadLocal(i) => {
+                let slot = self.frame().base + i as usize;
+                let val = self.stack.get(slot).cloned().ok_or_else(bad_code)?;
+                self.push(val);
+            }
+            Instr::StoreLocal(i) => {
+                let val = self.pop()?;
+                let slot = self.frame().base + i as usize;
+
+                match self.stack.get_mut(slot) {
+                    Some(local) => *local = val,
+                    None => return Err(bad_code()),
+                }
+            }
+            Instr::LoadAttr(i) => {
+                let val = match &self.frame().self_val {
+                    Value::Object(obj) => obj.attrs.borrow().get(i as usize).cloned(),
+                    _ => None,
+                };
+
+                self.push(val.ok_or_else(bad_code)?);
+            }
+            Instr::StoreAttr(i) => {
+                let val = self.pop()?;
+
+                let stored = match &self.frame().self_val {
+                    Value::Object(obj) => match obj.attrs.borrow_mut().get_mut(i as usize) {
+                        Some(attr) => {
+                            *attr = val;
+                            true
+                        }
+                        None => false,
+                    },
+                    _ => false,
+                };
+
+                if !stored {
+                    return Err(bad_code());
+                }
+            }
+            Instr::Dup => {
+                let val = self.top()?.clone();
+                self.push(val);
+            }
+            Instr::Pop => {
+                self.pop()?;
+            }
+            Instr::Jump(target) => self.frames.last_mut().unwrap().pc = target as usize,
+            Instr::JumpIfFalse(target) => {
+                if !self.pop_bool()? {
+                    self.frames.last_mut().unwrap().pc = target as usize;
+                }
+            }
+            Instr::Add | Instr::Sub | Instr::Mul | Instr::Div => {
+                let rhs = self.pop_int()?;
+                let lhs = self.pop_int()?;
+
+                // The same as the MIPS instructions used by cgen
+                let val = match instr {
+                    Instr::Add => lhs.checked_add(rhs),
+                    Instr::Sub => lhs.checked_sub(rhs),
+                    Instr::Mul => Some(lhs.wrapping_mul(rhs)),
+                    _ => {
+                        if rhs == 0 {
+                            return Err(Stop::Error("Division by zero".to_string()));
+                        }
+
+                        Some(lhs.wrapping_div(rhs))
+                    }
+                };
+
+                match val {
+                    Some(val) => self.push(Value::Int(val)),
+                    None => return Err(Stop::Error("Arithmetic overflow".to_string())),
+                }
+            }
+            Instr::Neg => match self.pop_int()?.checked_neg() {
+                Some(val) => self.push(Value::Int(val)),
+                None => return Err(Stop::Error("Arithmetic overflow".to_string())),
+            },
+            Instr::Lt | Instr::Le => {
+                let rhs = self.pop_int()?;
+                let lhs = self.pop_int()?;
+
+                self.push(Value::Bool(if instr == Instr::Lt {
+                    lhs < rhs
+                } else {
+                    lhs <= rhs
+                }));
+            }
+            Instr::Eq => {
+                let rhs = self.pop()?;
+                let lhs = self.pop()?;
+
+                // Basic values are compared by value and other objects by identity
+                self.push(Value::Bool(match (lhs, rhs) {
+                    (Value::Void, Value::Void) => true,
+                    (Value::Int(lhs), Value::Int(rhs)) => lhs == rhs,
+                    (Value::Bool(lhs), Value::Bool(rhs)) => lhs == rhs,
+                    (Value::Str(lhs), Value::Str(rhs)) => lhs == rhs,
+                    (Value::Object(lhs), Value::Object(rhs)) => Rc::ptr_eq(&lhs, &rhs),
+                    _ => false,
+                }));
+            }
+            Instr::Not => {
+                let val = self.pop_bool()?;
+                self.push(Value::Bool(!val));
+            }
+            Instr::IsVoid => {
+                let val = self.pop()?;
+                self.push(Value::Bool(matches!(val, Value::Void)));
+            }
+            Instr::New(tag) => self.new_object(tag)?,
+            Instr::NewSelfType => {
+                let tag = self.tag(&self.frame().self_val)?;
+                self.new_object(tag)?;
+            }
+            Instr::Dispatch { pos, args, line } => {
+                let receiver = self.pop()?;
+
+                if let Value::Void = receiver {
+                    return self.halt_at(line, "Dispatch to void.");
+                }
+
+                let class = &self.program.classes[self.tag(&receiver)? as usize];
+
+                match class.dispatch_table.get(pos as usize) {
+                    Some(function) => self.call(*function, receiver, args)?,
+                    None => return Err(bad_code()),
+                }
+            }
+            Instr::StaticDispatch {
+                function,
+                args,
+                line,
+            } => {
+                let receiver = self.pop()?;
+
+                if let Value::Void = receiver {
+                    return self.halt_at(line, "Dispatch to void.");
+                }
+
+                self.call(function, receiver, args)?;
+            }
+            Instr::CaseVoid(line) => {
+                if let Value::Void = self.top()? {
+                    return self.halt_at(line, "Match on void in case statement.");
+                }
+            }
+            Instr::JumpUnlessTag { start, end, target } => {
+                let tag = self.tag(self.top()?)?;
+
+                if !(start..end).contains(&tag) {
+                    self.frames.last_mut().unwrap().pc = target as usize;
+                }
+            }
+            Instr::CaseAbort => {
+                let val = self.pop()?;
+
+                let mut msg = b"No match in case statement for Class ".to_vec();
+                msg.extend(self.class_name(&val)?.iter());
+                msg.push(b'\n');
+
+                return self.halt(&msg);
+            }
+            Instr::Return => {
+                let result = self.pop()?;
+                let frame = self.frames.pop().unwrap();
+
+                self.stack.truncate(frame.base);
+                self.push(result);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -6,13 +6,15 @@ use super::emit::*;
 use crate::ast::{ArithOpType, Branch, Class, CompType, Expression, ObjectID, TypeID};
 use crate::scoped_collections::ScopedIndexMap;
 
-use super::{Label, MemLocation};
-use super::{DISPTABLE_LOCATION, TAG_LOCATION};
+use super::{GarbageCollector, Label, MemLocation};
+use super::{DISPTABLE_LOCATION, TAG_LOCATION, WORD_SIZE};
 
 // Initialization method
 pub fn code_methods(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
     classes: &IndexMap<TypeID, Class>,
+    isa: Isa,
+    gc: GarbageCollector,
     class_name: &TypeID,
     object_locations: &mut ScopedIndexMap<ObjectID, MemLocation>, // Locations of variables
 ) -> Result<(), io::Error> {
@@ -33,34 +35,42 @@ pub fn code_methods(
 
     writeln!(out_file, "{}_init:", class_name)?;
 
-    emit_method_start(out_file)?;
+    emit_method_start(out_file, isa)?;
 
     if let Some(parent_name) = &class.parent_name {
         // Use parent's initialization first
-        emit_jal(out_file, &format!("{}_init", parent_name))?;
+        emit_jal(out_file, isa, &format!("{}_init", parent_name))?;
     }
 
     for attr in class.attrs.iter() {
         if let Expression::NoExpr = attr.init {
         } else {
             // Evaluate init expression
-            code_expr(out_file, &attr.init, class, classes, object_locations, -1)?;
-
-            emit_store_word(
-                // Move to location relative to object (ACC)
+            code_expr(
                 out_file,
-                Register::ACC,
-                MemLocation {
-                    reg: Register::SELF,
-                    offset: attr.self_offset,
-                },
+                &attr.init,
+                class,
+                classes,
+                isa,
+                gc,
+                object_locations,
+                -1,
             )?;
+
+            let location = MemLocation {
+                reg: Register::SELF,
+                offset: attr.self_offset,
+            };
+
+            // Move to location relative to object (ACC)
+            emit_store_word(out_file, isa, Register::ACC, location)?;
+            code_gc_assign(out_file, isa, gc, location)?;
         }
     }
 
-    emit_move(out_file, Register::ACC, Register::SELF)?;
+    emit_move(out_file, isa, Register::ACC, Register::SELF)?;
 
-    emit_method_end(out_file, 0)?;
+    emit_method_end(out_file, isa, 0)?;
 
     if !class.basic {
         // Code for methods of basic objects is in trap.handler
@@ -85,19 +95,35 @@ pub fn code_methods(
             // Label method
             writeln!(out_file, "{}.{}:", class_name, method_name)?;
 
-            emit_method_start(out_file)?;
+            emit_method_start(out_file, isa)?;
 
             // Evaluate
-            code_expr(out_file, &method.expr, class, classes, object_locations, -1)?;
+            code_expr(
+                out_file,
+                &method.expr,
+                class,
+                classes,
+                isa,
+                gc,
+                object_locations,
+                -1,
+            )?;
 
-            emit_method_end(out_file, method.formals.len() as i16)?;
+            emit_method_end(out_file, isa, method.formals.len() as i16)?;
 
             object_locations.exit_scope();
         }
     }
 
     for child_class_name in &class.child_names {
-        code_methods(out_file, classes, &child_class_name, object_locations)?;
+        code_methods(
+            out_file,
+            classes,
+            isa,
+            gc,
+            child_class_name,
+            object_locations,
+        )?;
     }
 
     object_locations.exit_scope();
@@ -105,12 +131,37 @@ pub fn code_methods(
     Ok(())
 }
 
+// Tell the generational collector that a pointer was stored in an attribute
+// Variables on the stack are roots anyway. ACC is preserved.
+fn code_gc_assign(
+    out_file: &mut dyn io::Write,
+    isa: Isa,
+    gc: GarbageCollector,
+    location: MemLocation,
+) -> Result<(), io::Error> {
+    if gc == GarbageCollector::Generational && location.reg == Register::SELF {
+        emit_addiu(
+            out_file,
+            isa,
+            Register::A1,
+            Register::SELF,
+            location.offset * WORD_SIZE,
+        )?;
+        emit_jal(out_file, isa, "_GenGC_Assign")?;
+    }
+
+    Ok(())
+}
+
 // Output code for an expression
+#[allow(clippy::too_many_arguments)]
 fn code_expr(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
     expr: &Expression,
     current_class: &Class,
     classes: &IndexMap<TypeID, Class>,
+    isa: Isa,
+    gc: GarbageCollector,
     object_locations: &mut ScopedIndexMap<ObjectID, MemLocation>,
     fp_offset: i16,
 ) -> Result<(), io::Error> {
@@ -122,18 +173,19 @@ fn code_expr(
                 expr,
                 current_class,
                 classes,
+                isa,
+                gc,
                 object_locations,
                 fp_offset,
             )?;
 
-            emit_store_word(
-                out_file,
-                Register::ACC,
-                *object_locations.get(name).unwrap(),
-            )?;
+            let location = *object_locations.get(name).unwrap();
+
+            emit_store_word(out_file, isa, Register::ACC, location)?;
+            code_gc_assign(out_file, isa, gc, location)?;
         }
         Expression::Dispatch {
-            line_no,
+            span,
             expr,
             type_name,
             name,
@@ -147,11 +199,13 @@ fn code_expr(
                     arg,
                     current_class,
                     classes,
+                    isa,
+                    gc,
                     object_locations,
                     fp_offset,
                 )?;
 
-                emit_push(out_file, Register::ACC)?;
+                emit_push(out_file, isa, Register::ACC)?;
             }
 
             // Evaluate expression and keep at ACC
@@ -160,6 +214,8 @@ fn code_expr(
                 expr,
                 current_class,
                 classes,
+                isa,
+                gc,
                 object_locations,
                 fp_offset,
             )?;
@@ -167,12 +223,18 @@ fn code_expr(
             let l = Label::new();
 
             // Dispatch on void
-            emit_bnez(out_file, Register::ACC, l)?;
+            emit_bnez(out_file, isa, Register::ACC, l)?;
 
             // Load filename and line number for crash
-            emit_load_string(out_file, Register::ACC, "file_name", current_class.file_no)?;
-            emit_load_imm(out_file, Register::T1, *line_no)?;
-            emit_jal(out_file, "_dispatch_abort")?;
+            emit_load_string(
+                out_file,
+                isa,
+                Register::ACC,
+                "file_name",
+                current_class.file_no,
+            )?;
+            emit_load_imm(out_file, isa, Register::T1, span.line_no)?;
+            emit_jal(out_file, isa, "_dispatch_abort")?;
 
             emit_label_def(out_file, l)?;
 
@@ -181,7 +243,12 @@ fn code_expr(
                 Some(type_name) => {
                     // Static dispatch
 
-                    emit_load_address(out_file, Register::T1, format!("{}_dispTab", type_name))?;
+                    emit_load_address(
+                        out_file,
+                        isa,
+                        Register::T1,
+                        format!("{}_dispTab", type_name),
+                    )?;
 
                     classes.get(type_name).unwrap()
                 }
@@ -189,7 +256,7 @@ fn code_expr(
                 None => {
                     // "Dynamic" dispatch
 
-                    emit_load_word(out_file, Register::T1, DISPTABLE_LOCATION)?;
+                    emit_load_word(out_file, isa, Register::T1, DISPTABLE_LOCATION)?;
 
                     if expr.static_type().is_self_type() {
                         current_class
@@ -205,6 +272,7 @@ fn code_expr(
             // Load method added from dispatch table to T1
             emit_load_word(
                 out_file,
+                isa,
                 Register::T1,
                 MemLocation {
                     reg: Register::T1,
@@ -213,7 +281,7 @@ fn code_expr(
             )?;
 
             // jump to method
-            emit_jalr(out_file, Register::T1)?;
+            emit_jalr(out_file, isa, Register::T1)?;
         }
         Expression::Cond {
             pred,
@@ -230,15 +298,17 @@ fn code_expr(
                 pred,
                 current_class,
                 classes,
+                isa,
+                gc,
                 object_locations,
                 fp_offset,
             )?;
 
             // Get boolean value returned
-            emit_fetch_int(out_file, Register::T1, Register::ACC)?;
+            emit_fetch_int(out_file, isa, Register::T1, Register::ACC)?;
 
             // If false (zero) jump to else clause
-            emit_beqz(out_file, Register::T1, label_else)?;
+            emit_beqz(out_file, isa, Register::T1, label_else)?;
 
             // Evaluate then_expr
             code_expr(
@@ -246,12 +316,14 @@ fn code_expr(
                 then_expr,
                 current_class,
                 classes,
+                isa,
+                gc,
                 object_locations,
                 fp_offset,
             )?;
 
             // Unconditional branch to end of if statement
-            emit_branch(out_file, label_fi)?;
+            emit_branch(out_file, isa, label_fi)?;
             emit_label_def(out_file, label_else)?;
 
             // Evaluate else_expr
@@ -260,6 +332,8 @@ fn code_expr(
                 else_expr,
                 current_class,
                 classes,
+                isa,
+                gc,
                 object_locations,
                 fp_offset,
             )?;
@@ -278,15 +352,17 @@ fn code_expr(
                 pred,
                 current_class,
                 classes,
+                isa,
+                gc,
                 object_locations,
                 fp_offset,
             )?;
 
             // Get boolean value returned
-            emit_fetch_int(out_file, Register::T1, Register::ACC)?;
+            emit_fetch_int(out_file, isa, Register::T1, Register::ACC)?;
 
             // if false (zero) jump to end
-            emit_beqz(out_file, Register::T1, label_end)?;
+            emit_beqz(out_file, isa, Register::T1, label_end)?;
 
             // Evaluate contents
             code_expr(
@@ -294,20 +370,22 @@ fn code_expr(
                 body,
                 current_class,
                 classes,
+                isa,
+                gc,
                 object_locations,
                 fp_offset,
             )?;
 
             // Unconditional branch to beginning
-            emit_branch(out_file, label_start)?;
+            emit_branch(out_file, isa, label_start)?;
 
             emit_label_def(out_file, label_end)?;
 
             // Set ACC to void
-            emit_move(out_file, Register::ACC, Register::ZERO)?;
+            emit_move(out_file, isa, Register::ACC, Register::ZERO)?;
         }
         Expression::TypeCase {
-            line_no,
+            span,
             expr,
             branches,
             ..
@@ -318,22 +396,30 @@ fn code_expr(
                 expr,
                 current_class,
                 classes,
+                isa,
+                gc,
                 object_locations,
                 fp_offset,
             )?;
 
             let label_notvoid = Label::new();
-            emit_bnez(out_file, Register::ACC, label_notvoid)?; // jump over fail if not void
+            emit_bnez(out_file, isa, Register::ACC, label_notvoid)?; // jump over fail if not void
 
             // Load filename and line number for crash
-            emit_load_string(out_file, Register::ACC, "file_name", current_class.file_no)?;
-            emit_load_imm(out_file, Register::T1, *line_no)?;
-            emit_jal(out_file, "_case_abort2")?;
+            emit_load_string(
+                out_file,
+                isa,
+                Register::ACC,
+                "file_name",
+                current_class.file_no,
+            )?;
+            emit_load_imm(out_file, isa, Register::T1, span.line_no)?;
+            emit_jal(out_file, isa, "_case_abort2")?;
 
             emit_label_def(out_file, label_notvoid)?;
 
             // Load tag to T2
-            emit_load_word(out_file, Register::T2, TAG_LOCATION)?;
+            emit_load_word(out_file, isa, Register::T2, TAG_LOCATION)?;
 
             let label_end = Label::new();
 
@@ -345,6 +431,8 @@ fn code_expr(
                     branch,
                     current_class,
                     classes,
+                    isa,
+                    gc,
                     object_locations,
                     fp_offset,
                     label_end,
@@ -352,7 +440,7 @@ fn code_expr(
             }
 
             // If no branch succeeds
-            emit_jal(out_file, "_case_abort")?;
+            emit_jal(out_file, isa, "_case_abort")?;
 
             emit_label_def(out_file, label_end)?;
         }
@@ -364,6 +452,8 @@ fn code_expr(
                     expr,
                     current_class,
                     classes,
+                    isa,
+                    gc,
                     object_locations,
                     fp_offset,
                 )?;
@@ -382,13 +472,14 @@ fn code_expr(
 
             if let Expression::NoExpr = **init {
                 if type_decl.is_int() {
-                    emit_load_int(out_file, Register::ACC, 0)?; // Zero
+                    emit_load_int(out_file, isa, Register::ACC, 0)?; // Zero
                 } else if type_decl.is_bool() {
-                    emit_load_bool(out_file, Register::ACC, false)?; // False
+                    emit_load_bool(out_file, isa, Register::ACC, false)?; // False
                 } else if type_decl.is_string() {
-                    emit_load_string(out_file, Register::ACC, "str_const", 0)?; // Empty String ("")
+                    emit_load_string(out_file, isa, Register::ACC, "str_const", 0)?;
+                // Empty String ("")
                 } else {
-                    emit_load_imm(out_file, Register::ACC, 0)?; // Void
+                    emit_load_imm(out_file, isa, Register::ACC, 0)?; // Void
                 }
             } else {
                 code_expr(
@@ -396,13 +487,15 @@ fn code_expr(
                     init,
                     current_class,
                     classes,
+                    isa,
+                    gc,
                     object_locations,
                     fp_offset,
                 )?;
             }
 
             // Push to stack and save location relative to FP
-            emit_push(out_file, Register::ACC)?;
+            emit_push(out_file, isa, Register::ACC)?;
             object_locations.insert(
                 identifier.clone(),
                 MemLocation {
@@ -417,12 +510,14 @@ fn code_expr(
                 body,
                 current_class,
                 classes,
+                isa,
+                gc,
                 object_locations,
                 fp_offset - 1,
             )?;
 
             // Pop the result of init to nowhere
-            emit_pop(out_file, Register::ZERO)?;
+            emit_pop(out_file, isa, Register::ZERO)?;
 
             object_locations.exit_scope();
         }
@@ -438,11 +533,13 @@ fn code_expr(
                 expr_lhs,
                 current_class,
                 classes,
+                isa,
+                gc,
                 object_locations,
                 fp_offset,
             )?;
 
-            emit_push(out_file, Register::ACC)?;
+            emit_push(out_file, isa, Register::ACC)?;
 
             // Evaluate RHS
             code_expr(
@@ -450,43 +547,45 @@ fn code_expr(
                 expr_rhs,
                 current_class,
                 classes,
+                isa,
+                gc,
                 object_locations,
                 fp_offset,
             )?;
 
             // New integer (same value as RHS)
-            emit_jal(out_file, "Object.copy")?;
+            emit_jal(out_file, isa, "Object.copy")?;
 
             // Get the RHS value and put it in T1
-            emit_fetch_int(out_file, Register::T1, Register::ACC)?;
+            emit_fetch_int(out_file, isa, Register::T1, Register::ACC)?;
 
             // Get the LHS from the top of the stack and put it in T2
-            emit_load_word(out_file, Register::T2, STACK_NEXT_LOCATION)?;
+            emit_load_word(out_file, isa, Register::T2, STACK_NEXT_LOCATION)?;
 
             // Put the LHS value in T3
-            emit_fetch_int(out_file, Register::T3, Register::T2)?;
+            emit_fetch_int(out_file, isa, Register::T3, Register::T2)?;
 
             // Execute the operation and put to result in T1
             match arith_op_type {
                 ArithOpType::Add => {
-                    emit_add(out_file, Register::T1, Register::T3, Register::T1)?;
+                    emit_add(out_file, isa, Register::T1, Register::T3, Register::T1)?;
                 }
                 ArithOpType::Sub => {
-                    emit_sub(out_file, Register::T1, Register::T3, Register::T1)?;
+                    emit_sub(out_file, isa, Register::T1, Register::T3, Register::T1)?;
                 }
                 ArithOpType::Mul => {
-                    emit_mul(out_file, Register::T1, Register::T3, Register::T1)?;
+                    emit_mul(out_file, isa, Register::T1, Register::T3, Register::T1)?;
                 }
                 ArithOpType::Div => {
-                    emit_div(out_file, Register::T1, Register::T3, Register::T1)?;
+                    emit_div(out_file, isa, Register::T1, Register::T3, Register::T1)?;
                 }
             }
 
             // Put the result from T1 into the new integer
-            emit_store_int(out_file, Register::T1, Register::ACC)?;
+            emit_store_int(out_file, isa, Register::T1, Register::ACC)?;
 
             // Pop LHS to nowhere
-            emit_pop(out_file, Register::ZERO)?;
+            emit_pop(out_file, isa, Register::ZERO)?;
         }
         Expression::Neg { expr, .. } => {
             // Evaluate
@@ -495,21 +594,23 @@ fn code_expr(
                 expr,
                 current_class,
                 classes,
+                isa,
+                gc,
                 object_locations,
                 fp_offset,
             )?;
 
             // New integer (same value)
-            emit_jal(out_file, "Object.copy")?;
+            emit_jal(out_file, isa, "Object.copy")?;
 
             // Get value
-            emit_fetch_int(out_file, Register::T1, Register::ACC)?;
+            emit_fetch_int(out_file, isa, Register::T1, Register::ACC)?;
 
             // Negate current value
-            emit_neg(out_file, Register::T1, Register::T1)?;
+            emit_neg(out_file, isa, Register::T1, Register::T1)?;
 
             // Store value
-            emit_store_int(out_file, Register::T1, Register::ACC)?;
+            emit_store_int(out_file, isa, Register::T1, Register::ACC)?;
         }
         Expression::Comp {
             expr_lhs,
@@ -525,11 +626,13 @@ fn code_expr(
                 expr_lhs,
                 current_class,
                 classes,
+                isa,
+                gc,
                 object_locations,
                 fp_offset,
             )?;
 
-            emit_push(out_file, Register::ACC)?;
+            emit_push(out_file, isa, Register::ACC)?;
 
             // Evaluate RHS
             code_expr(
@@ -537,43 +640,45 @@ fn code_expr(
                 expr_rhs,
                 current_class,
                 classes,
+                isa,
+                gc,
                 object_locations,
                 fp_offset,
             )?;
 
             // New integer (same value as RHS)
-            emit_jal(out_file, "Object.copy")?;
+            emit_jal(out_file, isa, "Object.copy")?;
 
             // Get the RHS value and put it in T1
-            emit_fetch_int(out_file, Register::T1, Register::ACC)?;
+            emit_fetch_int(out_file, isa, Register::T1, Register::ACC)?;
 
             // Get the LHS from the top of the stack and put it in T2
-            emit_load_word(out_file, Register::T2, STACK_NEXT_LOCATION)?;
+            emit_load_word(out_file, isa, Register::T2, STACK_NEXT_LOCATION)?;
 
             // Put the LHS value in T3
-            emit_fetch_int(out_file, Register::T3, Register::T2)?;
+            emit_fetch_int(out_file, isa, Register::T3, Register::T2)?;
 
             // Point ACC to true constant
-            emit_load_bool(out_file, Register::ACC, true)?;
+            emit_load_bool(out_file, isa, Register::ACC, true)?;
 
             // Execute the comparison
             // If true branch to end
             match comp_type {
                 CompType::LT => {
-                    emit_blt(out_file, Register::T3, Register::T1, label)?;
+                    emit_blt(out_file, isa, Register::T3, Register::T1, label)?;
                 }
                 CompType::LEq => {
-                    emit_bleq(out_file, Register::T3, Register::T1, label)?;
+                    emit_bleq(out_file, isa, Register::T3, Register::T1, label)?;
                 }
             }
 
             // Point ACC to false constant
-            emit_load_bool(out_file, Register::ACC, false)?;
+            emit_load_bool(out_file, isa, Register::ACC, false)?;
 
             emit_label_def(out_file, label)?;
 
             // Pop LHS to nowhere
-            emit_pop(out_file, Register::ZERO)?;
+            emit_pop(out_file, isa, Register::ZERO)?;
         }
         Expression::Eq {
             expr_lhs, expr_rhs, ..
@@ -586,11 +691,13 @@ fn code_expr(
                 expr_lhs,
                 current_class,
                 classes,
+                isa,
+                gc,
                 object_locations,
                 fp_offset,
             )?;
 
-            emit_push(out_file, Register::ACC)?;
+            emit_push(out_file, isa, Register::ACC)?;
 
             // Evaluate RHS
             code_expr(
@@ -598,23 +705,25 @@ fn code_expr(
                 expr_rhs,
                 current_class,
                 classes,
+                isa,
+                gc,
                 object_locations,
                 fp_offset,
             )?;
 
             // RHS to T2
-            emit_move(out_file, Register::T2, Register::ACC)?;
+            emit_move(out_file, isa, Register::T2, Register::ACC)?;
 
             // LHS to T1
-            emit_pop(out_file, Register::T1)?;
+            emit_pop(out_file, isa, Register::T1)?;
 
-            emit_load_bool(out_file, Register::ACC, true)?;
+            emit_load_bool(out_file, isa, Register::ACC, true)?;
 
             // Test for structural equality
-            emit_beq(out_file, Register::T1, Register::T2, label)?;
+            emit_beq(out_file, isa, Register::T1, Register::T2, label)?;
 
-            emit_load_bool(out_file, Register::A1, false)?;
-            emit_jal(out_file, "equality_test")?; // jump to equality test
+            emit_load_bool(out_file, isa, Register::A1, false)?;
+            emit_jal(out_file, isa, "equality_test")?; // jump to equality test
             emit_label_def(out_file, label)?;
         }
         Expression::Not { expr, .. } => {
@@ -624,74 +733,91 @@ fn code_expr(
                 expr,
                 current_class,
                 classes,
+                isa,
+                gc,
                 object_locations,
                 fp_offset,
             )?;
 
             let label = Label::new();
-            emit_fetch_int(out_file, Register::T1, Register::ACC)?;
-            emit_load_bool(out_file, Register::ACC, true)?;
+            emit_fetch_int(out_file, isa, Register::T1, Register::ACC)?;
+            emit_load_bool(out_file, isa, Register::ACC, true)?;
 
             // if value is zero (false) jump to end
-            emit_beqz(out_file, Register::T1, label)?;
-            emit_load_bool(out_file, Register::ACC, false)?;
+            emit_beqz(out_file, isa, Register::T1, label)?;
+            emit_load_bool(out_file, isa, Register::ACC, false)?;
             emit_label_def(out_file, label)?;
         }
         Expression::IntConst { val, .. } => {
-            emit_load_int(out_file, Register::ACC, *val)?;
+            emit_load_int(out_file, isa, Register::ACC, *val)?;
         }
         Expression::BoolConst { val, .. } => {
-            emit_load_bool(out_file, Register::ACC, *val)?;
+            emit_load_bool(out_file, isa, Register::ACC, *val)?;
         }
         Expression::StringConst { val_id, .. } => {
-            emit_load_string(out_file, Register::ACC, "str_const", *val_id)?;
+            emit_load_string(out_file, isa, Register::ACC, "str_const", *val_id)?;
         }
         Expression::New { type_name, .. } => {
             if type_name.is_self_type() {
                 // address of class_objTab to T1
-                emit_load_address(out_file, Register::T1, "class_objTab".to_string())?;
+                emit_load_address(out_file, isa, Register::T1, "class_objTab".to_string())?;
 
-                // set T2 to current class tag
-                emit_load_word(out_file, Register::T2, TAG_LOCATION)?;
+                // set T2 to the tag of self's class
+                emit_load_word(
+                    out_file,
+                    isa,
+                    Register::T2,
+                    MemLocation {
+                        reg: Register::SELF,
+                        offset: 0,
+                    },
+                )?;
 
                 // Multiply tag by 8 (2 WORDs)
-                emit_sll(out_file, Register::T2, Register::T2, 3)?;
+                emit_sll(out_file, isa, Register::T2, Register::T2, 3)?;
 
                 // Get prototype and init method
-                emit_addu(out_file, Register::T1, Register::T1, Register::T2)?;
-                emit_move(out_file, Register::T3, Register::T1)?;
-                emit_push(out_file, Register::T3)?;
+                emit_addu(out_file, isa, Register::T1, Register::T1, Register::T2)?;
+                emit_move(out_file, isa, Register::T3, Register::T1)?;
+                emit_push(out_file, isa, Register::T3)?;
 
                 // Copy prototype
                 emit_load_word(
                     out_file,
+                    isa,
                     Register::ACC,
                     MemLocation {
                         reg: Register::T1,
                         offset: 0,
                     },
                 )?;
-                emit_jal(out_file, "Object.copy")?;
+                emit_jal(out_file, isa, "Object.copy")?;
 
-                emit_pop(out_file, Register::T3)?;
+                emit_pop(out_file, isa, Register::T3)?;
 
                 // Call init method
                 emit_load_word(
                     out_file,
+                    isa,
                     Register::T1,
                     MemLocation {
                         reg: Register::T3,
                         offset: 1,
                     },
                 )?;
-                emit_jalr(out_file, Register::T1)?;
+                emit_jalr(out_file, isa, Register::T1)?;
             } else {
                 // Copy prototype object
-                emit_load_address(out_file, Register::ACC, format!("{}_protObj", type_name))?;
-                emit_jal(out_file, "Object.copy")?;
+                emit_load_address(
+                    out_file,
+                    isa,
+                    Register::ACC,
+                    format!("{}_protObj", type_name),
+                )?;
+                emit_jal(out_file, isa, "Object.copy")?;
 
                 // Initialize object
-                emit_jal(out_file, &format!("{}_init", type_name))?;
+                emit_jal(out_file, isa, &format!("{}_init", type_name))?;
             }
         }
         Expression::IsVoid { expr, .. } => {
@@ -702,29 +828,32 @@ fn code_expr(
                 expr,
                 current_class,
                 classes,
+                isa,
+                gc,
                 object_locations,
                 fp_offset,
             )?;
 
-            emit_move(out_file, Register::T1, Register::ACC)?;
-            emit_load_bool(out_file, Register::ACC, true)?;
+            emit_move(out_file, isa, Register::T1, Register::ACC)?;
+            emit_load_bool(out_file, isa, Register::ACC, true)?;
 
             // Test if equal to zero (Void)
-            emit_beqz(out_file, Register::T1, label)?;
+            emit_beqz(out_file, isa, Register::T1, label)?;
 
-            emit_load_bool(out_file, Register::ACC, false)?;
+            emit_load_bool(out_file, isa, Register::ACC, false)?;
             emit_label_def(out_file, label)?;
         }
         Expression::NoExpr => {} // Never used
         Expression::VarByName { name, .. } => {
             if name.is_self() {
                 // self always refers to SELF register
-                emit_move(out_file, Register::ACC, Register::SELF)?;
+                emit_move(out_file, isa, Register::ACC, Register::SELF)?;
             } else {
                 // Lookup location
 
                 emit_load_word(
                     out_file,
+                    isa,
                     Register::ACC,
                     *object_locations.get(name).unwrap(),
                 )?;
@@ -736,13 +865,16 @@ fn code_expr(
 }
 
 // Code one branch of a TypeCase
+#[allow(clippy::too_many_arguments)]
 fn code_branch(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
     Branch {
         name, expr, family, ..
     }: &Branch,
     current_class: &Class,
     classes: &IndexMap<TypeID, Class>,
+    isa: Isa,
+    gc: GarbageCollector,
     object_locations: &mut ScopedIndexMap<ObjectID, MemLocation>,
     fp_offset: i16,
     label_end: Label,
@@ -750,13 +882,13 @@ fn code_branch(
     let label_end_branch = Label::new();
 
     // If tag is outside family, this branch doesn't match
-    emit_blti(out_file, Register::T2, family.start, label_end_branch)?;
-    emit_bgei(out_file, Register::T2, family.end, label_end_branch)?;
+    emit_blti(out_file, isa, Register::T2, family.start, label_end_branch)?;
+    emit_bgei(out_file, isa, Register::T2, family.end, label_end_branch)?;
 
     object_locations.enter_scope(); // Scope for new variable
 
     // Save expression to name
-    emit_push(out_file, Register::ACC)?;
+    emit_push(out_file, isa, Register::ACC)?;
     object_locations.insert(
         name.clone(),
         MemLocation {
@@ -771,15 +903,17 @@ fn code_branch(
         expr,
         current_class,
         classes,
+        isa,
+        gc,
         object_locations,
         fp_offset - 1,
     )?;
 
-    emit_pop(out_file, Register::ZERO)?;
+    emit_pop(out_file, isa, Register::ZERO)?;
 
     object_locations.exit_scope();
 
-    emit_branch(out_file, label_end)?;
+    emit_branch(out_file, isa, label_end)?;
     emit_label_def(out_file, label_end_branch)?;
 
     Ok(())
@@ -1,9 +1,12 @@
 use super::{Label, MemLocation};
 use super::{DEFAULT_OBJFIELDS, LG_WORD_SIZE, WORD_SIZE};
 
-use std::{fmt, io};
+use std::io;
 
-// This module contains functions used to output MIPS code.
+// This module contains functions used to output MIPS or RISC-V (RV32IM) code.
+// The two are close enough that code.rs is written for both: each function
+// writes the instructions of the chosen instruction set. Where RISC-V has no
+// equivalent of a MIPS (pseudo-)instruction, a short sequence is written instead.
 
 const FP_CALL_LOCATION: MemLocation = MemLocation {
     reg: Register::SP,
@@ -30,8 +33,16 @@ pub const STACK_NEXT_LOCATION: MemLocation = MemLocation {
     offset: 1,
 };
 
+// The instruction set that code is written for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Isa {
+    Mips,    // SPIM and trap.handler
+    Riscv32, // RV32IM and runtime/riscv32.s
+}
+
 // Registers used by the assembly code
-#[derive(Copy, Clone)]
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Copy, Clone, PartialEq, Eq)]
 pub enum Register {
     ZERO,
     ACC,
@@ -45,31 +56,73 @@ pub enum Register {
     RA,
 }
 
-impl fmt::Display for Register {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Register::ZERO => write!(f, "$zero"),
-            Register::ACC => write!(f, "$a0"),
-            Register::A1 => write!(f, "$a1"),
-            Register::SELF => write!(f, "$s0"),
-            Register::T1 => write!(f, "$t1"),
-            Register::T2 => write!(f, "$t2"),
-            Register::T3 => write!(f, "$t3"),
-            Register::SP => write!(f, "$sp"),
-            Register::FP => write!(f, "$fp"),
-            Register::RA => write!(f, "$ra"),
+impl Register {
+    fn name(self, isa: Isa) -> &'static str {
+        match isa {
+            Isa::Mips => match self {
+                Register::ZERO => "$zero",
+                Register::ACC => "$a0",
+                Register::A1 => "$a1",
+                Register::SELF => "$s0",
+                Register::T1 => "$t1",
+                Register::T2 => "$t2",
+                Register::T3 => "$t3",
+                Register::SP => "$sp",
+                Register::FP => "$fp",
+                Register::RA => "$ra",
+            },
+            // SELF and FP are callee-saved registers in the RISC-V calling convention
+            Isa::Riscv32 => match self {
+                Register::ZERO => "zero",
+                Register::ACC => "a0",
+                Register::A1 => "a1",
+                Register::SELF => "s1",
+                Register::T1 => "t1",
+                Register::T2 => "t2",
+                Register::T3 => "t3",
+                Register::SP => "sp",
+                Register::FP => "s0",
+                Register::RA => "ra",
+            },
         }
     }
 }
 
-pub fn emit_global(out_file: &mut Box<dyn io::Write>, sym: &str) -> Result<(), io::Error> {
+// Registers that only the sequences written for RISC-V use (like MIPS's $at)
+const RISCV_SCRATCH: &str = "t0";
+const RISCV_SCRATCH2: &str = "t4";
+const RISCV_SCRATCH3: &str = "t5";
+
+// RISC-V immediates are 12 bits
+fn fits_riscv_imm(imm: i32) -> bool {
+    (-2048..2048).contains(&imm)
+}
+
+// Write an instruction
+// MIPS operands are separated by spaces (as SPIM's examples are) and RISC-V operands by commas.
+fn emit_instr(
+    out_file: &mut dyn io::Write,
+    isa: Isa,
+    op: &str,
+    operands: &[&str],
+) -> Result<(), io::Error> {
+    let separator = match isa {
+        Isa::Mips => " ",
+        Isa::Riscv32 => ", ",
+    };
+
+    writeln!(out_file, "\t{} {}", op, operands.join(separator))
+}
+
+pub fn emit_global(out_file: &mut dyn io::Write, sym: &str) -> Result<(), io::Error> {
     writeln!(out_file, "\t.globl {}", sym)
 }
 
-pub fn emit_align(out_file: &mut Box<dyn io::Write>, n: i16) -> Result<(), io::Error> {
+pub fn emit_align(out_file: &mut dyn io::Write, n: i16) -> Result<(), io::Error> {
     writeln!(out_file, "\t.align {}", n)
 }
 
+#[allow(clippy::wrong_self_convention)]
 pub trait AsWord {
     fn as_word(self) -> String;
 }
@@ -92,17 +145,18 @@ impl AsWord for &str {
     }
 }
 
-pub fn emit_word(out_file: &mut Box<dyn io::Write>, w: impl AsWord) -> Result<(), io::Error> {
+pub fn emit_word(out_file: &mut dyn io::Write, w: impl AsWord) -> Result<(), io::Error> {
     writeln!(out_file, "\t.word {}", w.as_word())
 }
 
 // Needed before an object for the garbage collector
-pub fn emit_gc_tag(out_file: &mut Box<dyn io::Write>) -> Result<(), io::Error> {
+pub fn emit_gc_tag(out_file: &mut dyn io::Write) -> Result<(), io::Error> {
     writeln!(out_file, "\t.word -1")
 }
 
 pub fn emit_string_const(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
+    isa: Isa,
     base_name: &str,
     string_tag: u32,
     val: &str,
@@ -115,11 +169,19 @@ pub fn emit_string_const(
     emit_word(out_file, "String_dispTab")?;
     emit_word(out_file, format!("int_const_{:08x}", val.len()))?;
 
-    write!(out_file, "\t.byte")?;
-    for b in val.as_bytes().iter() {
-        write!(out_file, " {:#04x}", b)?;
-    }
-    writeln!(out_file, " 0x00")?; // Strings are null terminated
+    let bytes: Vec<String> = val
+        .as_bytes()
+        .iter()
+        .map(|b| format!("{:#04x}", b))
+        .chain(Some("0x00".to_string())) // Strings are null terminated
+        .collect();
+
+    let separator = match isa {
+        Isa::Mips => " ",
+        Isa::Riscv32 => ", ",
+    };
+
+    writeln!(out_file, "\t.byte {}", bytes.join(separator))?;
 
     emit_align(out_file, LG_WORD_SIZE)?;
 
@@ -127,7 +189,7 @@ pub fn emit_string_const(
 }
 
 pub fn emit_int_const(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
     int_tag: u32,
     val: u32,
 ) -> Result<(), io::Error> {
@@ -142,7 +204,7 @@ pub fn emit_int_const(
 }
 
 pub fn emit_bool_const(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
     bool_tag: u32,
     val: bool,
 ) -> Result<(), io::Error> {
@@ -167,206 +229,490 @@ pub fn emit_bool_const(
     Ok(())
 }
 
-pub fn emit_jalr(out_file: &mut Box<dyn io::Write>, dest: Register) -> Result<(), io::Error> {
-    writeln!(out_file, "\tjalr {}", dest)
+pub fn emit_jalr(out_file: &mut dyn io::Write, isa: Isa, dest: Register) -> Result<(), io::Error> {
+    emit_instr(out_file, isa, "jalr", &[dest.name(isa)])
 }
 
-pub fn emit_jal(out_file: &mut Box<dyn io::Write>, target: &str) -> Result<(), io::Error> {
-    writeln!(out_file, "\tjal {}", target)
+pub fn emit_jal(out_file: &mut dyn io::Write, isa: Isa, target: &str) -> Result<(), io::Error> {
+    match isa {
+        Isa::Mips => emit_instr(out_file, isa, "jal", &[target]),
+        Isa::Riscv32 => emit_instr(out_file, isa, "call", &[target]), // Reaches any address
+    }
+}
+
+pub fn emit_return(out_file: &mut dyn io::Write, isa: Isa) -> Result<(), io::Error> {
+    emit_instr(out_file, isa, "jr", &[Register::RA.name(isa)])
 }
 
-pub fn emit_return(out_file: &mut Box<dyn io::Write>) -> Result<(), io::Error> {
-    writeln!(out_file, "\tjr {}", Register::RA)
+// The operand of a load or store (i.e. 12($s0))
+// A RISC-V offset that does not fit in an immediate is added to the base in a scratch register.
+fn mem_operand(
+    out_file: &mut dyn io::Write,
+    isa: Isa,
+    MemLocation { reg, offset }: MemLocation,
+) -> Result<String, io::Error> {
+    let offset = (WORD_SIZE * offset) as i32;
+
+    if isa == Isa::Riscv32 && !fits_riscv_imm(offset) {
+        emit_instr(out_file, isa, "li", &[RISCV_SCRATCH, &offset.to_string()])?;
+        emit_instr(
+            out_file,
+            isa,
+            "add",
+            &[RISCV_SCRATCH, RISCV_SCRATCH, reg.name(isa)],
+        )?;
+
+        return Ok(format!("0({})", RISCV_SCRATCH));
+    }
+
+    Ok(format!("{}({})", offset, reg.name(isa)))
 }
 
 pub fn emit_store_word(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
+    isa: Isa,
     src: Register,
-    MemLocation { reg: dest, offset }: MemLocation,
+    dest: MemLocation,
 ) -> Result<(), io::Error> {
-    writeln!(out_file, "\tsw {} {}({})", src, WORD_SIZE * offset, dest)
+    let dest = mem_operand(out_file, isa, dest)?;
+    emit_instr(out_file, isa, "sw", &[src.name(isa), &dest])
 }
 
 pub fn emit_load_word(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
+    isa: Isa,
     dest: Register,
-    MemLocation { reg: src, offset }: MemLocation,
+    src: MemLocation,
 ) -> Result<(), io::Error> {
-    writeln!(out_file, "\tlw {} {}({})", dest, WORD_SIZE * offset, src)
+    let src = mem_operand(out_file, isa, src)?;
+    emit_instr(out_file, isa, "lw", &[dest.name(isa), &src])
 }
 
+// li is a pseudo-instruction that accepts a full 32-bit immediate
 pub fn emit_load_imm(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
+    isa: Isa,
     dest: Register,
-    imm: i16,
+    imm: u32,
 ) -> Result<(), io::Error> {
-    writeln!(out_file, "\tli {} {:#06x}", dest, imm)
+    emit_instr(
+        out_file,
+        isa,
+        "li",
+        &[dest.name(isa), &format!("{:#010x}", imm)],
+    )
 }
 
 pub fn emit_load_address(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
+    isa: Isa,
     dest: Register,
     addr: String,
 ) -> Result<(), io::Error> {
-    writeln!(out_file, "\tla {} {}", dest, addr)
+    emit_instr(out_file, isa, "la", &[dest.name(isa), &addr])
 }
 
 pub fn emit_move(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
+    isa: Isa,
     dest: Register,
     src: Register,
 ) -> Result<(), io::Error> {
-    writeln!(out_file, "\tmove {} {}", dest, src)
+    let op = match isa {
+        Isa::Mips => "move",
+        Isa::Riscv32 => "mv",
+    };
+
+    emit_instr(out_file, isa, op, &[dest.name(isa), src.name(isa)])
+}
+
+// Jump to a runtime routine that stops the program unless the branch op is taken
+// (MIPS traps instead; see emit_add, emit_sub, emit_neg and emit_div)
+fn emit_riscv_check(
+    out_file: &mut dyn io::Write,
+    op: &str,
+    src1: &str,
+    src2: &str,
+    routine: &str,
+) -> Result<(), io::Error> {
+    let label_ok = Label::new();
+
+    emit_instr(
+        out_file,
+        Isa::Riscv32,
+        op,
+        &[src1, src2, &label_ok.to_string()],
+    )?;
+    emit_instr(out_file, Isa::Riscv32, "j", &[routine])?;
+    emit_label_def(out_file, label_ok)?;
+
+    Ok(())
 }
 
+// Traps on overflow (as MIPS neg does)
 pub fn emit_neg(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
+    isa: Isa,
     dest: Register,
     src: Register,
 ) -> Result<(), io::Error> {
-    writeln!(out_file, "\tneg {} {}", dest, src)
+    if isa == Isa::Riscv32 {
+        // Only the smallest Int cannot be negated
+        emit_instr(out_file, isa, "li", &[RISCV_SCRATCH, "0x80000000"])?;
+        emit_riscv_check(
+            out_file,
+            "bne",
+            src.name(isa),
+            RISCV_SCRATCH,
+            "_arithmetic_overflow",
+        )?;
+    }
+
+    emit_instr(out_file, isa, "neg", &[dest.name(isa), src.name(isa)])
 }
 
+// Traps on overflow
 pub fn emit_add(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
+    isa: Isa,
     dest: Register,
     src1: Register,
     src2: Register,
 ) -> Result<(), io::Error> {
-    writeln!(out_file, "\tadd {} {} {}", dest, src1, src2)
+    match isa {
+        Isa::Mips => emit_instr(
+            out_file,
+            isa,
+            "add",
+            &[dest.name(isa), src1.name(isa), src2.name(isa)],
+        ),
+        Isa::Riscv32 => {
+            // The sum overflowed if it is less than src1 but src2 is not negative (or the reverse)
+            emit_instr(
+                out_file,
+                isa,
+                "add",
+                &[RISCV_SCRATCH, src1.name(isa), src2.name(isa)],
+            )?;
+            emit_instr(
+                out_file,
+                isa,
+                "slt",
+                &[RISCV_SCRATCH2, RISCV_SCRATCH, src1.name(isa)],
+            )?;
+            emit_instr(
+                out_file,
+                isa,
+                "slti",
+                &[RISCV_SCRATCH3, src2.name(isa), "0"],
+            )?;
+            emit_riscv_check(
+                out_file,
+                "beq",
+                RISCV_SCRATCH2,
+                RISCV_SCRATCH3,
+                "_arithmetic_overflow",
+            )?;
+            emit_instr(out_file, isa, "mv", &[dest.name(isa), RISCV_SCRATCH])
+        }
+    }
 }
 
 pub fn emit_addu(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
+    isa: Isa,
     dest: Register,
     src1: Register,
     src2: Register,
 ) -> Result<(), io::Error> {
-    writeln!(out_file, "\taddu {} {} {}", dest, src1, src2)
+    let op = match isa {
+        Isa::Mips => "addu",
+        Isa::Riscv32 => "add",
+    };
+
+    emit_instr(
+        out_file,
+        isa,
+        op,
+        &[dest.name(isa), src1.name(isa), src2.name(isa)],
+    )
 }
 
 pub fn emit_addiu(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
+    isa: Isa,
     dest: Register,
     src: Register,
     imm: i16,
 ) -> Result<(), io::Error> {
-    writeln!(out_file, "\taddiu {} {} {}", dest, src, imm)
+    match isa {
+        Isa::Mips => emit_instr(
+            out_file,
+            isa,
+            "addiu",
+            &[dest.name(isa), src.name(isa), &imm.to_string()],
+        ),
+        Isa::Riscv32 if fits_riscv_imm(imm as i32) => emit_instr(
+            out_file,
+            isa,
+            "addi",
+            &[dest.name(isa), src.name(isa), &imm.to_string()],
+        ),
+        Isa::Riscv32 => {
+            emit_instr(out_file, isa, "li", &[RISCV_SCRATCH, &imm.to_string()])?;
+            emit_instr(
+                out_file,
+                isa,
+                "add",
+                &[dest.name(isa), src.name(isa), RISCV_SCRATCH],
+            )
+        }
+    }
 }
 
+// Traps on division by zero
 pub fn emit_div(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
+    isa: Isa,
     dest: Register,
     src1: Register,
     src2: Register,
 ) -> Result<(), io::Error> {
-    writeln!(out_file, "\tdiv {} {} {}", dest, src1, src2)
+    if isa == Isa::Riscv32 {
+        emit_riscv_check(
+            out_file,
+            "bne",
+            src2.name(isa),
+            Register::ZERO.name(isa),
+            "_division_by_zero",
+        )?;
+    }
+
+    emit_instr(
+        out_file,
+        isa,
+        "div",
+        &[dest.name(isa), src1.name(isa), src2.name(isa)],
+    )
 }
 
 pub fn emit_mul(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
+    isa: Isa,
     dest: Register,
     src1: Register,
     src2: Register,
 ) -> Result<(), io::Error> {
-    writeln!(out_file, "\tmul {} {} {}", dest, src1, src2)
+    emit_instr(
+        out_file,
+        isa,
+        "mul",
+        &[dest.name(isa), src1.name(isa), src2.name(isa)],
+    )
 }
 
+// Traps on overflow
 pub fn emit_sub(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
+    isa: Isa,
     dest: Register,
     src1: Register,
     src2: Register,
 ) -> Result<(), io::Error> {
-    writeln!(out_file, "\tsub {} {} {}", dest, src1, src2)
+    match isa {
+        Isa::Mips => emit_instr(
+            out_file,
+            isa,
+            "sub",
+            &[dest.name(isa), src1.name(isa), src2.name(isa)],
+        ),
+        Isa::Riscv32 => {
+            // The difference overflowed if it is less than src1 but src2 is not positive (or the reverse)
+            emit_instr(
+                out_file,
+                isa,
+                "sub",
+                &[RISCV_SCRATCH, src1.name(isa), src2.name(isa)],
+            )?;
+            emit_instr(
+                out_file,
+                isa,
+                "slt",
+                &[RISCV_SCRATCH2, RISCV_SCRATCH, src1.name(isa)],
+            )?;
+            emit_instr(
+                out_file,
+                isa,
+                "slt",
+                &[RISCV_SCRATCH3, Register::ZERO.name(isa), src2.name(isa)],
+            )?;
+            emit_riscv_check(
+                out_file,
+                "beq",
+                RISCV_SCRATCH2,
+                RISCV_SCRATCH3,
+                "_arithmetic_overflow",
+            )?;
+            emit_instr(out_file, isa, "mv", &[dest.name(isa), RISCV_SCRATCH])
+        }
+    }
 }
 
 pub fn emit_sll(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
+    isa: Isa,
     dest: Register,
     src: Register,
     shift: u8,
 ) -> Result<(), io::Error> {
-    writeln!(out_file, "\tsll {} {} {}", dest, src, shift)
+    let op = match isa {
+        Isa::Mips => "sll",
+        Isa::Riscv32 => "slli",
+    };
+
+    emit_instr(
+        out_file,
+        isa,
+        op,
+        &[dest.name(isa), src.name(isa), &shift.to_string()],
+    )
 }
 
-pub fn emit_branch(out_file: &mut Box<dyn io::Write>, label: Label) -> Result<(), io::Error> {
-    writeln!(out_file, "\tb {}", label)
+pub fn emit_branch(out_file: &mut dyn io::Write, isa: Isa, label: Label) -> Result<(), io::Error> {
+    let op = match isa {
+        Isa::Mips => "b",
+        Isa::Riscv32 => "j",
+    };
+
+    emit_instr(out_file, isa, op, &[&label.to_string()])
 }
 
 pub fn emit_beqz(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
+    isa: Isa,
     src: Register,
     label: Label,
 ) -> Result<(), io::Error> {
-    writeln!(out_file, "\tbeqz {} {}", src, label)
+    emit_instr(out_file, isa, "beqz", &[src.name(isa), &label.to_string()])
 }
 
 pub fn emit_bnez(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
+    isa: Isa,
     src: Register,
     label: Label,
 ) -> Result<(), io::Error> {
-    writeln!(out_file, "\tbnez {} {}", src, label)
+    emit_instr(out_file, isa, "bnez", &[src.name(isa), &label.to_string()])
 }
 
 pub fn emit_beq(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
+    isa: Isa,
     src1: Register,
     src2: Register,
     label: Label,
 ) -> Result<(), io::Error> {
-    writeln!(out_file, "\tbeq {} {} {}", src1, src2, label)
+    emit_instr(
+        out_file,
+        isa,
+        "beq",
+        &[src1.name(isa), src2.name(isa), &label.to_string()],
+    )
 }
 
 pub fn emit_bleq(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
+    isa: Isa,
     src1: Register,
     src2: Register,
     label: Label,
 ) -> Result<(), io::Error> {
-    writeln!(out_file, "\tble {} {} {}", src1, src2, label)
+    emit_instr(
+        out_file,
+        isa,
+        "ble",
+        &[src1.name(isa), src2.name(isa), &label.to_string()],
+    )
 }
 
 pub fn emit_blt(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
+    isa: Isa,
     src1: Register,
     src2: Register,
     label: Label,
 ) -> Result<(), io::Error> {
-    writeln!(out_file, "\tblt {} {} {}", src1, src2, label)
+    emit_instr(
+        out_file,
+        isa,
+        "blt",
+        &[src1.name(isa), src2.name(isa), &label.to_string()],
+    )
+}
+
+// A branch that compares with an immediate
+// RISC-V branches only compare registers, so the immediate is loaded into a scratch register.
+fn emit_branch_imm(
+    out_file: &mut dyn io::Write,
+    isa: Isa,
+    op: &str,
+    src: Register,
+    imm: u32,
+    label: Label,
+) -> Result<(), io::Error> {
+    match isa {
+        Isa::Mips => emit_instr(
+            out_file,
+            isa,
+            op,
+            &[src.name(isa), &imm.to_string(), &label.to_string()],
+        ),
+        Isa::Riscv32 => {
+            emit_instr(out_file, isa, "li", &[RISCV_SCRATCH, &imm.to_string()])?;
+            emit_instr(
+                out_file,
+                isa,
+                op,
+                &[src.name(isa), RISCV_SCRATCH, &label.to_string()],
+            )
+        }
+    }
 }
 
 pub fn emit_blti(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
+    isa: Isa,
     src: Register,
     imm: u32,
     label: Label,
 ) -> Result<(), io::Error> {
-    writeln!(out_file, "\tblt {} {} {}", src, imm, label)
+    emit_branch_imm(out_file, isa, "blt", src, imm, label)
 }
 
 pub fn emit_bgei(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
+    isa: Isa,
     src: Register,
     imm: u32,
     label: Label,
 ) -> Result<(), io::Error> {
-    writeln!(out_file, "\tbge {} {} {}", src, imm, label)
+    emit_branch_imm(out_file, isa, "bge", src, imm, label)
 }
 
-pub fn emit_label_def(out_file: &mut Box<dyn io::Write>, label: Label) -> Result<(), io::Error> {
+pub fn emit_label_def(out_file: &mut dyn io::Write, label: Label) -> Result<(), io::Error> {
     writeln!(out_file, "{}:", label)
 }
 
 // Instructions to load an Int type's value pointed to by src into dest
 pub fn emit_fetch_int(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
+    isa: Isa,
     dest: Register,
     src: Register,
 ) -> Result<(), io::Error> {
     emit_load_word(
         out_file,
+        isa,
         dest,
         MemLocation {
             reg: src,
@@ -377,12 +723,14 @@ pub fn emit_fetch_int(
 
 // Instructions to load src's value in to the Int object pointed to by dest
 pub fn emit_store_int(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
+    isa: Isa,
     src: Register,
     dest: Register,
 ) -> Result<(), io::Error> {
     emit_store_word(
         out_file,
+        isa,
         src,
         MemLocation {
             reg: dest,
@@ -392,31 +740,32 @@ pub fn emit_store_int(
 }
 
 // Add the value in src to the stack
-pub fn emit_push(out_file: &mut Box<dyn io::Write>, src: Register) -> Result<(), io::Error> {
-    emit_store_word(out_file, src, STACK_TOP_LOCATION)?;
-    emit_addiu(out_file, Register::SP, Register::SP, -WORD_SIZE)?;
+pub fn emit_push(out_file: &mut dyn io::Write, isa: Isa, src: Register) -> Result<(), io::Error> {
+    emit_store_word(out_file, isa, src, STACK_TOP_LOCATION)?;
+    emit_addiu(out_file, isa, Register::SP, Register::SP, -WORD_SIZE)?;
 
     Ok(())
 }
 
 // Remove the top value on the stack and set dest to that value
-pub fn emit_pop(out_file: &mut Box<dyn io::Write>, dest: Register) -> Result<(), io::Error> {
-    emit_addiu(out_file, Register::SP, Register::SP, WORD_SIZE)?;
-    emit_load_word(out_file, dest, STACK_TOP_LOCATION)?;
+pub fn emit_pop(out_file: &mut dyn io::Write, isa: Isa, dest: Register) -> Result<(), io::Error> {
+    emit_addiu(out_file, isa, Register::SP, Register::SP, WORD_SIZE)?;
+    emit_load_word(out_file, isa, dest, STACK_TOP_LOCATION)?;
 
     Ok(())
 }
 
 // Set dest to point to the specified Bool constant
 pub fn emit_load_bool(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
+    isa: Isa,
     dest: Register,
     val: bool,
 ) -> Result<(), io::Error> {
     if val {
-        emit_load_address(out_file, dest, "bool_const1".to_string())?;
+        emit_load_address(out_file, isa, dest, "bool_const1".to_string())?;
     } else {
-        emit_load_address(out_file, dest, "bool_const0".to_string())?;
+        emit_load_address(out_file, isa, dest, "bool_const0".to_string())?;
     }
 
     Ok(())
@@ -424,55 +773,59 @@ pub fn emit_load_bool(
 
 // Set dest to point to the specified Int constant
 pub fn emit_load_int(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
+    isa: Isa,
     dest: Register,
     val: u32,
 ) -> Result<(), io::Error> {
-    emit_load_address(out_file, dest, format!("int_const_{:08x}", val))
+    emit_load_address(out_file, isa, dest, format!("int_const_{:08x}", val))
 }
 
 // Set dest to point to the specified String constant
 pub fn emit_load_string(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
+    isa: Isa,
     dest: Register,
     base_name: &str,
     val_id: u32,
 ) -> Result<(), io::Error> {
-    emit_load_address(out_file, dest, format!("{}_{:08x}", base_name, val_id))
+    emit_load_address(out_file, isa, dest, format!("{}_{:08x}", base_name, val_id))
 }
 
-pub fn emit_method_start(out_file: &mut Box<dyn io::Write>) -> Result<(), io::Error> {
+pub fn emit_method_start(out_file: &mut dyn io::Write, isa: Isa) -> Result<(), io::Error> {
     // Push values onto stack
-    emit_addiu(out_file, Register::SP, Register::SP, -3 * WORD_SIZE)?;
-    emit_store_word(out_file, Register::FP, FP_CALL_LOCATION)?;
-    emit_store_word(out_file, Register::SELF, SELF_CALL_LOCATION)?;
-    emit_store_word(out_file, Register::RA, RA_CALL_LOCATION)?;
+    emit_addiu(out_file, isa, Register::SP, Register::SP, -3 * WORD_SIZE)?;
+    emit_store_word(out_file, isa, Register::FP, FP_CALL_LOCATION)?;
+    emit_store_word(out_file, isa, Register::SELF, SELF_CALL_LOCATION)?;
+    emit_store_word(out_file, isa, Register::RA, RA_CALL_LOCATION)?;
 
     // Set FP to start of new frame
-    emit_addiu(out_file, Register::FP, Register::SP, WORD_SIZE)?;
+    emit_addiu(out_file, isa, Register::FP, Register::SP, WORD_SIZE)?;
 
     // value passed in ACC is new SELF
-    emit_move(out_file, Register::SELF, Register::ACC)?;
+    emit_move(out_file, isa, Register::SELF, Register::ACC)?;
 
     Ok(())
 }
 
 // Undo emit_method_start and remove all arguments that were pushed onto stack
 pub fn emit_method_end(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
+    isa: Isa,
     formal_count: i16,
 ) -> Result<(), io::Error> {
-    emit_load_word(out_file, Register::FP, FP_CALL_LOCATION)?;
-    emit_load_word(out_file, Register::SELF, SELF_CALL_LOCATION)?;
-    emit_load_word(out_file, Register::RA, RA_CALL_LOCATION)?;
+    emit_load_word(out_file, isa, Register::FP, FP_CALL_LOCATION)?;
+    emit_load_word(out_file, isa, Register::SELF, SELF_CALL_LOCATION)?;
+    emit_load_word(out_file, isa, Register::RA, RA_CALL_LOCATION)?;
 
     emit_addiu(
         out_file,
+        isa,
         Register::SP,
         Register::SP,
         (3 + formal_count) * WORD_SIZE,
     )?;
-    emit_return(out_file)?;
+    emit_return(out_file, isa)?;
 
     Ok(())
 }
@@ -1,6 +1,7 @@
 mod code;
 mod emit;
 
+use std::str::FromStr;
 use std::{fmt, io};
 
 use crate::ast::{Class, ObjectID, TypeID};
@@ -11,6 +12,8 @@ use indexmap::IndexMap;
 use self::code::code_methods;
 use self::emit::*;
 
+pub use self::emit::Isa;
+
 const WORD_SIZE: i16 = 4;
 const LG_WORD_SIZE: i16 = 2;
 
@@ -39,7 +42,7 @@ pub struct MemLocation {
 pub struct Label(u32);
 
 impl Label {
-    fn new() -> Label {
+    pub(crate) fn new() -> Label {
         unsafe {
             LABEL_VAL += 1;
 
@@ -54,27 +57,67 @@ impl fmt::Display for Label {
     }
 }
 
+// The garbage collector of the runtime (trap.handler) that the program uses
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GarbageCollector {
+    None,         // Memory is never reclaimed
+    Generational, // Needs a call to _GenGC_Assign after every attribute write
+    StopAndCopy,
+}
+
+impl FromStr for GarbageCollector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<GarbageCollector, String> {
+        match s {
+            "none" => Ok(GarbageCollector::None),
+            "generational" => Ok(GarbageCollector::Generational),
+            "stop-and-copy" => Ok(GarbageCollector::StopAndCopy),
+            _ => Err(format!("Unknown garbage collector {}", s)),
+        }
+    }
+}
+
 // Translate an IO error if necessary
+// If gc_test is set, the runtime collects garbage at every allocation (to find bugs).
+// Only the MIPS runtime (trap.handler) has a garbage collector.
+#[allow(clippy::too_many_arguments)]
 pub fn cgen(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
     in_file_names: &[String],
     classes: &IndexMap<TypeID, Class>,
     int_table: &[u32],
     str_table: &[String],
+    isa: Isa,
+    gc: GarbageCollector,
+    gc_test: bool,
 ) -> Result<(), String> {
-    match write_code(out_file, in_file_names, classes, int_table, str_table) {
+    match write_code(
+        out_file,
+        in_file_names,
+        classes,
+        int_table,
+        str_table,
+        isa,
+        gc,
+        gc_test,
+    ) {
         Err(s) => Err(s.to_string()),
         Ok(()) => Ok(()),
     }
 }
 
-// output MIPS assembly for entire program to out_file
+// output MIPS or RISC-V assembly for entire program to out_file
+#[allow(clippy::too_many_arguments)]
 fn write_code(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
     in_file_names: &[String],
     classes: &IndexMap<TypeID, Class>,
     int_table: &[u32],
     str_table: &[String],
+    isa: Isa,
+    gc: GarbageCollector,
+    gc_test: bool,
 ) -> Result<(), io::Error> {
     let root_class_name: TypeID = TypeID::root_class_name();
 
@@ -104,21 +147,23 @@ fn write_code(
     writeln!(out_file, "_string_tag:")?;
     emit_word(out_file, string_tag)?;
 
-    gc_settings(out_file)?; // Garbage Collection initialization
+    if isa == Isa::Mips {
+        gc_settings(out_file, gc, gc_test)?; // Garbage Collection initialization
+    }
 
     // Write out all string constants
     for (val, val_id) in str_table.iter().zip(0..) {
-        emit_string_const(out_file, "str_const", string_tag, val, val_id)?;
+        emit_string_const(out_file, isa, "str_const", string_tag, val, val_id)?;
     }
 
     // Write out all file names as string constants
-    emit_string_const(out_file, "file_name", string_tag, &String::new(), 0)?;
+    emit_string_const(out_file, isa, "file_name", string_tag, "", 0)?;
     for (val, val_id) in in_file_names.iter().zip(1..) {
-        emit_string_const(out_file, "file_name", string_tag, val, val_id)?;
+        emit_string_const(out_file, isa, "file_name", string_tag, val, val_id)?;
     }
 
     // Write out all class names (in tag order)
-    class_name_consts(out_file, classes, &root_class_name, string_tag)?;
+    class_name_consts(out_file, classes, &root_class_name, isa, string_tag)?;
 
     // Write integer constants
     for val in int_table.iter() {
@@ -153,39 +198,59 @@ fn write_code(
 
     // methods for all objects
     let mut object_locations: ScopedIndexMap<ObjectID, MemLocation> = ScopedIndexMap::new();
-    code_methods(out_file, classes, &root_class_name, &mut object_locations)?;
+    code_methods(
+        out_file,
+        classes,
+        isa,
+        gc,
+        &root_class_name,
+        &mut object_locations,
+    )?;
 
     Ok(())
 }
 
 // Output garbage collection settings
-fn gc_settings(out_file: &mut Box<dyn io::Write>) -> Result<(), io::Error> {
+// The runtime calls the initializer at startup and the collector when the heap is full.
+fn gc_settings(
+    out_file: &mut dyn io::Write,
+    gc: GarbageCollector,
+    gc_test: bool,
+) -> Result<(), io::Error> {
+    let (initializer, collector) = match gc {
+        GarbageCollector::None => ("_NoGC_Init", "_NoGC_Collect"),
+        GarbageCollector::Generational => ("_GenGC_Init", "_GenGC_Collect"),
+        GarbageCollector::StopAndCopy => ("_SCC_Init", "_SCC_Collect"),
+    };
+
     emit_global(out_file, "_MemMgr_INITIALIZER")?;
     writeln!(out_file, "_MemMgr_INITIALIZER:")?;
-    emit_word(out_file, "_NoGC_Init")?;
+    emit_word(out_file, initializer)?;
 
     emit_global(out_file, "_MemMgr_COLLECTOR")?;
     writeln!(out_file, "_MemMgr_COLLECTOR:")?;
-    emit_word(out_file, "_NoGC_Collect")?;
+    emit_word(out_file, collector)?;
 
     emit_global(out_file, "_MemMgr_TEST")?;
     writeln!(out_file, "_MemMgr_TEST:")?;
-    emit_word(out_file, 0)?;
+    emit_word(out_file, gc_test as u32)?;
 
     Ok(())
 }
 
 // Write out all class names (in tag order)
 fn class_name_consts(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
     classes: &IndexMap<TypeID, Class>,
     class_name: &TypeID,
+    isa: Isa,
     string_tag: u32,
 ) -> Result<(), io::Error> {
     let class: &Class = classes.get(class_name).unwrap();
 
     emit_string_const(
         out_file,
+        isa,
         "class_name",
         string_tag,
         &class_name.to_string(),
@@ -193,7 +258,7 @@ fn class_name_consts(
     )?;
 
     for child_class_name in &class.child_names {
-        class_name_consts(out_file, classes, &child_class_name, string_tag)?;
+        class_name_consts(out_file, classes, child_class_name, isa, string_tag)?;
     }
 
     Ok(())
@@ -201,7 +266,7 @@ fn class_name_consts(
 
 // Class Object tab
 fn class_obj_tab(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
     classes: &IndexMap<TypeID, Class>,
     class_name: &TypeID,
 ) -> Result<(), io::Error> {
@@ -211,7 +276,7 @@ fn class_obj_tab(
     emit_word(out_file, format!("{}_init", class_name))?;
 
     for child_class_name in &class.child_names {
-        class_obj_tab(out_file, classes, &child_class_name)?;
+        class_obj_tab(out_file, classes, child_class_name)?;
     }
 
     Ok(())
@@ -219,7 +284,7 @@ fn class_obj_tab(
 
 // list each class's methods in order
 fn dispatch_table(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
     classes: &IndexMap<TypeID, Class>,
     class_name: &TypeID,
 ) -> Result<(), io::Error> {
@@ -232,7 +297,7 @@ fn dispatch_table(
     }
 
     for child_class_name in &class.child_names {
-        dispatch_table(out_file, classes, &child_class_name)?;
+        dispatch_table(out_file, classes, child_class_name)?;
     }
 
     Ok(())
@@ -241,7 +306,7 @@ fn dispatch_table(
 // Create a prototype object for each class
 // This object has the structure of an object in this class but its attributes are not set
 fn proto_obj(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
     classes: &IndexMap<TypeID, Class>,
     class_name: &TypeID,
     mut size: i16,
@@ -260,7 +325,7 @@ fn proto_obj(
     proto_attrs(out_file, classes, class_name)?;
 
     for child_class_name in &class.child_names {
-        proto_obj(out_file, classes, &child_class_name, size)?;
+        proto_obj(out_file, classes, child_class_name, size)?;
     }
 
     Ok(())
@@ -268,7 +333,7 @@ fn proto_obj(
 
 // Attributes for a prototype object
 fn proto_attrs(
-    out_file: &mut Box<dyn io::Write>,
+    out_file: &mut dyn io::Write,
     classes: &IndexMap<TypeID, Class>,
     class_name: &TypeID,
 ) -> Result<(), io::Error> {
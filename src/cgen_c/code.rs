@@ -0,0 +1,711 @@
+use std::fmt::Display;
+use std::io;
+
+use indexmap::IndexMap;
+
+use super::{default_value, struct_name};
+use crate::ast::{ArithOpType, Class, CompType, Expression, ObjectID, TypeID};
+use crate::scoped_collections::ScopedIndexMap;
+
+// The function that implements a method for objects of a class
+pub fn method_function(class: &Class, method_name: &ObjectID) -> String {
+    let pos = *class.method_name_to_pos.get(method_name).unwrap();
+    let (class_name, method_name) = &class.dispatch_table[pos as usize];
+
+    format!("{}__{}", class_name, method_name)
+}
+
+// Write a line of C at a depth of nesting
+fn emit(out_file: &mut dyn io::Write, depth: usize, line: impl Display) -> Result<(), io::Error> {
+    writeln!(out_file, "{:width$}{}", "", line, width = 4 * depth)
+}
+
+// A new variable for the function being written
+// Each has a different number (which also keeps let variables apart).
+fn new_var(vars: &mut u32, name: impl Display) -> String {
+    *vars += 1;
+    format!("{}{}", name, vars)
+}
+
+// Can a value from code_expr be void?
+// Constants and self are never void.
+fn may_be_void(val: &str) -> bool {
+    !val.starts_with('&') && val != "self"
+}
+
+// Initialization method
+pub fn code_methods(
+    out_file: &mut dyn io::Write,
+    classes: &IndexMap<TypeID, Class>,
+    class_name: &TypeID,
+    variables: &mut ScopedIndexMap<ObjectID, String>, // The C lvalue of each variable
+) -> Result<(), io::Error> {
+    let class: &Class = classes.get(class_name).unwrap();
+
+    variables.enter_scope(); // Scope for attributes
+
+    // Add attributes to var list (the attributes of basic classes are never named)
+    if !class.basic {
+        for attr in class.attrs.iter() {
+            variables.insert(
+                attr.name.clone(),
+                format!("(({} *)self)->a_{}", struct_name(class_name), attr.name),
+            );
+        }
+    }
+
+    let mut vars: u32 = 0;
+
+    writeln!(out_file)?;
+    writeln!(
+        out_file,
+        "static object *{}_init(object *self) {{",
+        class_name
+    )?;
+
+    if let Some(parent_name) = &class.parent_name {
+        // Use parent's initialization first
+        emit(out_file, 1, format!("{}_init(self);", parent_name))?;
+    }
+
+    for attr in class.attrs.iter() {
+        if let Expression::NoExpr = attr.init {
+        } else {
+            // Evaluate init expression
+            let val = code_expr(
+                out_file, &attr.init, class, classes, variables, &mut vars, 1,
+            )?;
+
+            emit(
+                out_file,
+                1,
+                format!("{} = {};", variables.get(&attr.name).unwrap(), val),
+            )?;
+        }
+    }
+
+    emit(out_file, 1, "return self;")?;
+    writeln!(out_file, "}}")?;
+
+    if !class.basic {
+        // Code for methods of basic objects is in the runtime
+        // Code methods of class
+        for (method_name, method) in class.methods.iter() {
+            variables.enter_scope(); // Scope for arguments (formals)
+
+            let mut vars: u32 = 0;
+            let mut params = String::new();
+
+            for formal in method.formals.iter() {
+                let var = new_var(&mut vars, format!("v_{}_", formal.name));
+                params.push_str(&format!(", object *{}", var));
+                variables.insert(formal.name.clone(), var);
+            }
+
+            writeln!(out_file)?;
+            writeln!(
+                out_file,
+                "static object *{}__{}(object *self{}) {{",
+                class_name, method_name, params
+            )?;
+
+            // Evaluate
+            let val = code_expr(
+                out_file,
+                &method.expr,
+                class,
+                classes,
+                variables,
+                &mut vars,
+                1,
+            )?;
+
+            emit(out_file, 1, format!("return {};", val))?;
+            writeln!(out_file, "}}")?;
+
+            variables.exit_scope();
+        }
+    }
+
+    for child_class_name in &class.child_names {
+        code_methods(out_file, classes, child_class_name, variables)?;
+    }
+
+    variables.exit_scope();
+
+    Ok(())
+}
+
+// Output statements that evaluate an expression
+// Returns a C expression for the value that later code cannot change (a new
+// variable, a constant, self or void).
+fn code_expr(
+    out_file: &mut dyn io::Write,
+    expr: &Expression,
+    current_class: &Class,
+    classes: &IndexMap<TypeID, Class>,
+    variables: &mut ScopedIndexMap<ObjectID, String>,
+    vars: &mut u32,
+    depth: usize,
+) -> Result<String, io::Error> {
+    let val = match expr {
+        Expression::Assign { name, expr, .. } => {
+            let val = code_expr(
+                out_file,
+                expr,
+                current_class,
+                classes,
+                variables,
+                vars,
+                depth,
+            )?;
+
+            emit(
+                out_file,
+                depth,
+                format!("{} = {};", variables.get(name).unwrap(), val),
+            )?;
+
+            val
+        }
+        Expression::Dispatch {
+            span,
+            expr,
+            type_name,
+            name,
+            args,
+            ..
+        } => {
+            // Evaluate the arguments first
+            let mut arg_vals = Vec::new();
+            for arg in args.iter() {
+                arg_vals.push(code_expr(
+                    out_file,
+                    arg,
+                    current_class,
+                    classes,
+                    variables,
+                    vars,
+                    depth,
+                )?);
+            }
+
+            let receiver = code_expr(
+                out_file,
+                expr,
+                current_class,
+                classes,
+                variables,
+                vars,
+                depth,
+            )?;
+
+            // Dispatch on void
+            if may_be_void(&receiver) {
+                emit(out_file, depth, format!("if ({} == NULL) {{", receiver))?;
+                emit(
+                    out_file,
+                    depth + 1,
+                    format!(
+                        "cool_dispatch_abort(&file_name_{:08x}.header, {});",
+                        current_class.file_no, span.line_no
+                    ),
+                )?;
+                emit(out_file, depth, "}")?;
+            }
+
+            let function = match type_name {
+                // Static dispatch calls the method directly
+                Some(type_name) => method_function(classes.get(type_name).unwrap(), name),
+
+                // "Dynamic" dispatch calls the method in the receiver's dispatch table
+                None => {
+                    let method_pos = if expr.static_type().is_self_type() {
+                        current_class
+                    } else {
+                        classes.get(&expr.static_type()).unwrap()
+                    }
+                    .method_name_to_pos
+                    .get(name)
+                    .unwrap();
+
+                    format!(
+                        "((object *(*)(object *{}))(({})->disp_tab[{}]))",
+                        ", object *".repeat(args.len()),
+                        receiver,
+                        method_pos
+                    )
+                }
+            };
+
+            let mut call_args = receiver;
+            for arg_val in arg_vals.iter() {
+                call_args.push_str(", ");
+                call_args.push_str(arg_val);
+            }
+
+            let val = new_var(vars, "t");
+            emit(
+                out_file,
+                depth,
+                format!("object *{} = {}({});", val, function, call_args),
+            )?;
+
+            val
+        }
+        Expression::Cond {
+            pred,
+            then_expr,
+            else_expr,
+            ..
+        } => {
+            let pred_val = code_expr(
+                out_file,
+                pred,
+                current_class,
+                classes,
+                variables,
+                vars,
+                depth,
+            )?;
+
+            let val = new_var(vars, "t");
+            emit(out_file, depth, format!("object *{};", val))?;
+
+            emit(
+                out_file,
+                depth,
+                format!("if (cool_int_val({})) {{", pred_val),
+            )?;
+            let then_val = code_expr(
+                out_file,
+                then_expr,
+                current_class,
+                classes,
+                variables,
+                vars,
+                depth + 1,
+            )?;
+            emit(out_file, depth + 1, format!("{} = {};", val, then_val))?;
+
+            emit(out_file, depth, "} else {")?;
+            let else_val = code_expr(
+                out_file,
+                else_expr,
+                current_class,
+                classes,
+                variables,
+                vars,
+                depth + 1,
+            )?;
+            emit(out_file, depth + 1, format!("{} = {};", val, else_val))?;
+            emit(out_file, depth, "}")?;
+
+            val
+        }
+        Expression::Loop { pred, body, .. } => {
+            emit(out_file, depth, "for (;;) {")?;
+
+            let pred_val = code_expr(
+                out_file,
+                pred,
+                current_class,
+                classes,
+                variables,
+                vars,
+                depth + 1,
+            )?;
+
+            emit(
+                out_file,
+                depth + 1,
+                format!("if (!cool_int_val({})) {{", pred_val),
+            )?;
+            emit(out_file, depth + 2, "break;")?;
+            emit(out_file, depth + 1, "}")?;
+
+            code_expr(
+                out_file,
+                body,
+                current_class,
+                classes,
+                variables,
+                vars,
+                depth + 1,
+            )?;
+
+            emit(out_file, depth, "}")?;
+
+            "((object *)NULL)".to_string() // Void
+        }
+        Expression::TypeCase {
+            span,
+            expr,
+            branches,
+            ..
+        } => {
+            let obj = code_expr(
+                out_file,
+                expr,
+                current_class,
+                classes,
+                variables,
+                vars,
+                depth,
+            )?;
+
+            if may_be_void(&obj) {
+                emit(out_file, depth, format!("if ({} == NULL) {{", obj))?;
+                emit(
+                    out_file,
+                    depth + 1,
+                    format!(
+                        "cool_case_abort2(&file_name_{:08x}.header, {});",
+                        current_class.file_no, span.line_no
+                    ),
+                )?;
+                emit(out_file, depth, "}")?;
+            }
+
+            let val = new_var(vars, "t");
+            emit(out_file, depth, format!("object *{} = NULL;", val))?;
+
+            // Code each branch
+            // Note that the order is already correct
+            let mut keyword = "if";
+            for branch in branches.iter() {
+                // The branch matches if the tag is in its family
+                emit(
+                    out_file,
+                    depth,
+                    format!(
+                        "{} (({})->tag >= {} && ({})->tag < {}) {{",
+                        keyword, obj, branch.family.start, obj, branch.family.end
+                    ),
+                )?;
+                keyword = "} else if";
+
+                variables.enter_scope(); // Scope for new variable
+
+                let var = new_var(vars, format!("v_{}_", branch.name));
+                emit(out_file, depth + 1, format!("object *{} = {};", var, obj))?;
+                variables.insert(branch.name.clone(), var);
+
+                let branch_val = code_expr(
+                    out_file,
+                    &branch.expr,
+                    current_class,
+                    classes,
+                    variables,
+                    vars,
+                    depth + 1,
+                )?;
+                emit(out_file, depth + 1, format!("{} = {};", val, branch_val))?;
+
+                variables.exit_scope();
+            }
+
+            // If no branch succeeds
+            emit(out_file, depth, "} else {")?;
+            emit(out_file, depth + 1, format!("cool_case_abort({});", obj))?;
+            emit(out_file, depth, "}")?;
+
+            val
+        }
+        Expression::Block { body, .. } => {
+            // Evaluate each expression in body
+            let mut val = String::new();
+            for expr in body.iter() {
+                val = code_expr(
+                    out_file,
+                    expr,
+                    current_class,
+                    classes,
+                    variables,
+                    vars,
+                    depth,
+                )?;
+            }
+
+            val
+        }
+        Expression::Let {
+            identifier,
+            type_decl,
+            init,
+            body,
+            ..
+        } => {
+            // Evaluate init expression
+            let init_val = if let Expression::NoExpr = **init {
+                default_value(type_decl)
+            } else {
+                code_expr(
+                    out_file,
+                    init,
+                    current_class,
+                    classes,
+                    variables,
+                    vars,
+                    depth,
+                )?
+            };
+
+            variables.enter_scope(); // Scope for new variable
+
+            let var = new_var(vars, format!("v_{}_", identifier));
+            emit(out_file, depth, format!("object *{} = {};", var, init_val))?;
+            variables.insert(identifier.clone(), var);
+
+            // Evaluate body
+            let val = code_expr(
+                out_file,
+                body,
+                current_class,
+                classes,
+                variables,
+                vars,
+                depth,
+            )?;
+
+            variables.exit_scope();
+
+            val
+        }
+        Expression::ArithOp {
+            expr_lhs,
+            expr_rhs,
+            arith_op_type,
+            ..
+        } => {
+            let lhs = code_expr(
+                out_file,
+                expr_lhs,
+                current_class,
+                classes,
+                variables,
+                vars,
+                depth,
+            )?;
+            let rhs = code_expr(
+                out_file,
+                expr_rhs,
+                current_class,
+                classes,
+                variables,
+                vars,
+                depth,
+            )?;
+
+            // The runtime checks for overflow and division by zero
+            let function = match arith_op_type {
+                ArithOpType::Add => "cool_add",
+                ArithOpType::Sub => "cool_sub",
+                ArithOpType::Mul => "cool_mul",
+                ArithOpType::Div => "cool_div",
+            };
+
+            let val = new_var(vars, "t");
+            emit(
+                out_file,
+                depth,
+                format!("object *{} = {}({}, {});", val, function, lhs, rhs),
+            )?;
+
+            val
+        }
+        Expression::Neg { expr, .. } => {
+            let obj = code_expr(
+                out_file,
+                expr,
+                current_class,
+                classes,
+                variables,
+                vars,
+                depth,
+            )?;
+
+            let val = new_var(vars, "t");
+            emit(
+                out_file,
+                depth,
+                format!("object *{} = cool_neg({});", val, obj),
+            )?;
+
+            val
+        }
+        Expression::Comp {
+            expr_lhs,
+            expr_rhs,
+            comp_type,
+            ..
+        } => {
+            let lhs = code_expr(
+                out_file,
+                expr_lhs,
+                current_class,
+                classes,
+                variables,
+                vars,
+                depth,
+            )?;
+            let rhs = code_expr(
+                out_file,
+                expr_rhs,
+                current_class,
+                classes,
+                variables,
+                vars,
+                depth,
+            )?;
+
+            let op = match comp_type {
+                CompType::LT => "<",
+                CompType::LEq => "<=",
+            };
+
+            let val = new_var(vars, "t");
+            emit(
+                out_file,
+                depth,
+                format!(
+                    "object *{} = cool_bool(cool_int_val({}) {} cool_int_val({}));",
+                    val, lhs, op, rhs
+                ),
+            )?;
+
+            val
+        }
+        Expression::Eq {
+            expr_lhs, expr_rhs, ..
+        } => {
+            let lhs = code_expr(
+                out_file,
+                expr_lhs,
+                current_class,
+                classes,
+                variables,
+                vars,
+                depth,
+            )?;
+            let rhs = code_expr(
+                out_file,
+                expr_rhs,
+                current_class,
+                classes,
+                variables,
+                vars,
+                depth,
+            )?;
+
+            // The same object is always equal (otherwise, test for structural equality)
+            if lhs == rhs {
+                "&bool_const1.header".to_string() // True
+            } else {
+                let val = new_var(vars, "t");
+                emit(
+                    out_file,
+                    depth,
+                    format!(
+                        "object *{} = cool_bool({} == {} || cool_equality_test({}, {}));",
+                        val, lhs, rhs, lhs, rhs
+                    ),
+                )?;
+
+                val
+            }
+        }
+        Expression::Not { expr, .. } => {
+            let obj = code_expr(
+                out_file,
+                expr,
+                current_class,
+                classes,
+                variables,
+                vars,
+                depth,
+            )?;
+
+            let val = new_var(vars, "t");
+            emit(
+                out_file,
+                depth,
+                format!("object *{} = cool_bool(!cool_int_val({}));", val, obj),
+            )?;
+
+            val
+        }
+        Expression::IntConst { val, .. } => format!("&int_const_{:08x}.header", val),
+        Expression::BoolConst { val, .. } => format!("&bool_const{}.header", *val as u8),
+        Expression::StringConst { val_id, .. } => format!("&str_const_{:08x}.header", val_id),
+        Expression::New { type_name, .. } => {
+            let val = new_var(vars, "t");
+
+            if type_name.is_self_type() {
+                // The class of self is found by its tag
+                emit(
+                    out_file,
+                    depth,
+                    format!(
+                        "object *{} = class_objTab[self->tag].init(Object__copy(class_objTab[self->tag].proto));",
+                        val
+                    ),
+                )?;
+            } else {
+                // Copy prototype object and initialize it
+                emit(
+                    out_file,
+                    depth,
+                    format!(
+                        "object *{} = {}_init(Object__copy(&{}_protObj.header));",
+                        val, type_name, type_name
+                    ),
+                )?;
+            }
+
+            val
+        }
+        Expression::IsVoid { expr, .. } => {
+            let obj = code_expr(
+                out_file,
+                expr,
+                current_class,
+                classes,
+                variables,
+                vars,
+                depth,
+            )?;
+
+            if may_be_void(&obj) {
+                let val = new_var(vars, "t");
+                emit(
+                    out_file,
+                    depth,
+                    format!("object *{} = cool_bool({} == NULL);", val, obj),
+                )?;
+
+                val
+            } else {
+                "&bool_const0.header".to_string() // False
+            }
+        }
+        Expression::NoExpr => "NULL".to_string(), // Never used
+        Expression::VarByName { name, .. } => {
+            if name.is_self() {
+                "self".to_string()
+            } else {
+                // Later code may assign to the variable, so its value is copied
+                let val = new_var(vars, "t");
+                emit(
+                    out_file,
+                    depth,
+                    format!("object *{} = {};", val, variables.get(name).unwrap()),
+                )?;
+
+                val
+            }
+        }
+    };
+
+    Ok(val)
+}
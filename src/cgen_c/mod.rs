@@ -0,0 +1,408 @@
+// A code generator for C
+// Each class becomes a struct that starts with the tag, size and dispatch table of
+// the MIPS layout (see cgen), and each method becomes a function. The runtime
+// (runtime/c.c) is copied to the start of the output, so a program is built with
+//     cc name.c -o name
+
+mod code;
+
+use std::io;
+
+use crate::ast::{Class, ObjectID, TypeID};
+use crate::scoped_collections::ScopedIndexMap;
+
+use indexmap::IndexMap;
+
+use self::code::{code_methods, method_function};
+
+const RUNTIME: &str = include_str!("../../runtime/c.c");
+
+// Translate an IO error if necessary
+pub fn cgen(
+    out_file: &mut dyn io::Write,
+    in_file_names: &[String],
+    classes: &IndexMap<TypeID, Class>,
+    int_table: &[u32],
+    str_table: &[String],
+) -> Result<(), String> {
+    match write_code(out_file, in_file_names, classes, int_table, str_table) {
+        Err(s) => Err(s.to_string()),
+        Ok(()) => Ok(()),
+    }
+}
+
+// output a C program for entire program to out_file
+fn write_code(
+    out_file: &mut dyn io::Write,
+    in_file_names: &[String],
+    classes: &IndexMap<TypeID, Class>,
+    int_table: &[u32],
+    str_table: &[String],
+) -> Result<(), io::Error> {
+    let root_class_name: TypeID = TypeID::root_class_name();
+
+    let int_tag = classes.get(&TypeID::new_int()).unwrap().tag();
+    let bool_tag = classes.get(&TypeID::new_bool()).unwrap().tag();
+    let string_tag = classes.get(&TypeID::new_string()).unwrap().tag();
+
+    write!(out_file, "{}", RUNTIME)?;
+
+    // Not every value that the generated code computes is used
+    writeln!(
+        out_file,
+        "#pragma GCC diagnostic ignored \"-Wunused-variable\""
+    )?;
+    writeln!(
+        out_file,
+        "#pragma GCC diagnostic ignored \"-Wunused-but-set-variable\""
+    )?;
+    writeln!(
+        out_file,
+        "#pragma GCC diagnostic ignored \"-Wunused-parameter\""
+    )?;
+    writeln!(out_file)?;
+
+    class_structs(out_file, classes, &root_class_name)?;
+    writeln!(out_file)?;
+
+    // Methods are used (in the dispatch tables) before they are defined
+    method_prototypes(out_file, classes, &root_class_name)?;
+    writeln!(out_file)?;
+
+    dispatch_table(out_file, classes, &root_class_name)?;
+    writeln!(out_file)?;
+
+    // Write integer constants
+    for val in int_table.iter() {
+        writeln!(
+            out_file,
+            "static struct cool_int int_const_{:08x} = {{{{{}, sizeof(struct cool_int), Int_dispTab}}, {}}};",
+            val, int_tag, *val as i32
+        )?;
+    }
+
+    // Write bool constants
+    for val in 0..2 {
+        writeln!(
+            out_file,
+            "struct cool_int bool_const{} = {{{{{}, sizeof(struct cool_int), Bool_dispTab}}, {}}};",
+            val, bool_tag, val
+        )?;
+    }
+
+    // Write out all string constants
+    for (val, val_id) in str_table.iter().zip(0..) {
+        string_const(out_file, "str_const", string_tag, val, val_id)?;
+    }
+
+    // Write out all file names as string constants
+    string_const(out_file, "file_name", string_tag, "", 0)?;
+    for (val, val_id) in in_file_names.iter().zip(1..) {
+        string_const(out_file, "file_name", string_tag, val, val_id)?;
+    }
+
+    // Write out all class names (in tag order)
+    class_name_consts(out_file, classes, &root_class_name, string_tag)?;
+    writeln!(out_file)?;
+
+    // Write class name table (in tag order)
+    writeln!(out_file, "object *const class_nameTab[] = {{")?;
+    for tag in 0..classes.len() {
+        writeln!(out_file, "    &class_name_{:08x}.header,", tag)?;
+    }
+    writeln!(out_file, "}};")?;
+    writeln!(out_file)?;
+
+    proto_obj(out_file, classes, &root_class_name)?;
+    writeln!(out_file)?;
+
+    writeln!(out_file, "const struct cool_class class_objTab[] = {{")?;
+    class_obj_tab(out_file, classes, &root_class_name)?;
+    writeln!(out_file, "}};")?;
+
+    // methods for all objects
+    let mut variables: ScopedIndexMap<ObjectID, String> = ScopedIndexMap::new();
+    code_methods(out_file, classes, &root_class_name, &mut variables)?;
+
+    // Called by main() in the runtime
+    // Create a Main object and call its main method.
+    let main_class = classes.get(&TypeID::new_main()).unwrap();
+    writeln!(out_file)?;
+    writeln!(out_file, "static void cool_main(void) {{")?;
+    writeln!(
+        out_file,
+        "    {}(Main_init(Object__copy(&Main_protObj.header)));",
+        method_function(main_class, &ObjectID::new_main())
+    )?;
+    writeln!(out_file, "}}")?;
+
+    Ok(())
+}
+
+// The C type of an object of a class
+fn struct_name(class_name: &TypeID) -> String {
+    if class_name.is_int() || class_name.is_bool() {
+        "struct cool_int".to_string()
+    } else if class_name.is_string() {
+        "struct cool_string".to_string()
+    } else {
+        format!("struct {}_object", class_name)
+    }
+}
+
+// Declare a struct for each class (Int, Bool and String are in the runtime)
+fn class_structs(
+    out_file: &mut dyn io::Write,
+    classes: &IndexMap<TypeID, Class>,
+    class_name: &TypeID,
+) -> Result<(), io::Error> {
+    let class: &Class = classes.get(class_name).unwrap();
+
+    if !class_name.is_int() && !class_name.is_bool() && !class_name.is_string() {
+        writeln!(out_file, "{} {{", struct_name(class_name))?;
+        writeln!(out_file, "    object header;")?;
+        struct_attrs(out_file, classes, class_name)?;
+        writeln!(out_file, "}};")?;
+    }
+
+    for child_class_name in &class.child_names {
+        class_structs(out_file, classes, child_class_name)?;
+    }
+
+    Ok(())
+}
+
+// Fields for the attributes of a class (inherited attributes first)
+fn struct_attrs(
+    out_file: &mut dyn io::Write,
+    classes: &IndexMap<TypeID, Class>,
+    class_name: &TypeID,
+) -> Result<(), io::Error> {
+    let class: &Class = classes.get(class_name).unwrap();
+
+    if let Some(parent_name) = &class.parent_name {
+        struct_attrs(out_file, classes, parent_name)?;
+    }
+
+    for attr in class.attrs.iter() {
+        writeln!(out_file, "    object *a_{};", attr.name)?;
+    }
+
+    Ok(())
+}
+
+// Declare the init function and the methods of each class
+// The methods of the basic classes are defined in the runtime.
+fn method_prototypes(
+    out_file: &mut dyn io::Write,
+    classes: &IndexMap<TypeID, Class>,
+    class_name: &TypeID,
+) -> Result<(), io::Error> {
+    let class: &Class = classes.get(class_name).unwrap();
+
+    writeln!(
+        out_file,
+        "static object *{}_init(object *self);",
+        class_name
+    )?;
+
+    if !class.basic {
+        for (method_name, method) in class.methods.iter() {
+            writeln!(
+                out_file,
+                "static object *{}__{}(object *self{});",
+                class_name,
+                method_name,
+                ", object *".repeat(method.formals.len())
+            )?;
+        }
+    }
+
+    for child_class_name in &class.child_names {
+        method_prototypes(out_file, classes, child_class_name)?;
+    }
+
+    Ok(())
+}
+
+// list each class's methods in order
+fn dispatch_table(
+    out_file: &mut dyn io::Write,
+    classes: &IndexMap<TypeID, Class>,
+    class_name: &TypeID,
+) -> Result<(), io::Error> {
+    let class: &Class = classes.get(class_name).unwrap();
+
+    writeln!(
+        out_file,
+        "static const cool_method {}_dispTab[] = {{",
+        class_name
+    )?;
+
+    for (class_name, method_name) in &class.dispatch_table {
+        writeln!(
+            out_file,
+            "    (cool_method){}__{},",
+            class_name, method_name
+        )?;
+    }
+
+    writeln!(out_file, "}};")?;
+
+    for child_class_name in &class.child_names {
+        dispatch_table(out_file, classes, child_class_name)?;
+    }
+
+    Ok(())
+}
+
+// A string constant (its length is one of the int constants)
+fn string_const(
+    out_file: &mut dyn io::Write,
+    base_name: &str,
+    string_tag: u32,
+    val: &str,
+    val_id: u32,
+) -> Result<(), io::Error> {
+    // Every byte that is not plainly printable is escaped (? because of trigraphs)
+    let mut chars = String::new();
+    for b in val.bytes() {
+        if (b.is_ascii_graphic() && b != b'"' && b != b'\\' && b != b'?') || b == b' ' {
+            chars.push(b as char);
+        } else {
+            chars.push_str(&format!("\\{:03o}", b));
+        }
+    }
+
+    writeln!(
+        out_file,
+        "static COOL_STRING({}) {}_{:08x} = {{{{{}, offsetof(struct cool_string, chars) + {}, String_dispTab}}, &int_const_{:08x}, \"{}\"}};",
+        val.len() + 1,
+        base_name,
+        val_id,
+        string_tag,
+        val.len() + 1,
+        val.len(),
+        chars
+    )?;
+
+    Ok(())
+}
+
+// Write out all class names (in tag order)
+fn class_name_consts(
+    out_file: &mut dyn io::Write,
+    classes: &IndexMap<TypeID, Class>,
+    class_name: &TypeID,
+    string_tag: u32,
+) -> Result<(), io::Error> {
+    let class: &Class = classes.get(class_name).unwrap();
+
+    string_const(
+        out_file,
+        "class_name",
+        string_tag,
+        &class_name.to_string(),
+        class.tag(),
+    )?;
+
+    for child_class_name in &class.child_names {
+        class_name_consts(out_file, classes, child_class_name, string_tag)?;
+    }
+
+    Ok(())
+}
+
+// Class Object tab
+fn class_obj_tab(
+    out_file: &mut dyn io::Write,
+    classes: &IndexMap<TypeID, Class>,
+    class_name: &TypeID,
+) -> Result<(), io::Error> {
+    let class: &Class = classes.get(class_name).unwrap();
+
+    writeln!(
+        out_file,
+        "    {{&{}_protObj.header, {}_init}},",
+        class_name, class_name
+    )?;
+
+    for child_class_name in &class.child_names {
+        class_obj_tab(out_file, classes, child_class_name)?;
+    }
+
+    Ok(())
+}
+
+// Create a prototype object for each class
+// This object has the structure of an object in this class but its attributes are not set
+fn proto_obj(
+    out_file: &mut dyn io::Write,
+    classes: &IndexMap<TypeID, Class>,
+    class_name: &TypeID,
+) -> Result<(), io::Error> {
+    let class: &Class = classes.get(class_name).unwrap();
+
+    let (size, attrs) = if class_name.is_int() || class_name.is_bool() {
+        ("sizeof(struct cool_int)".to_string(), vec!["0".to_string()])
+    } else if class_name.is_string() {
+        // The empty string (it has no room for characters)
+        (
+            "offsetof(struct cool_string, chars)".to_string(),
+            vec!["&int_const_00000000".to_string()],
+        )
+    } else {
+        let mut attrs = Vec::new();
+        proto_attrs(&mut attrs, classes, class_name);
+
+        (format!("sizeof({})", struct_name(class_name)), attrs)
+    };
+
+    write!(
+        out_file,
+        "{} {}_protObj = {{{{{}, {}, {}_dispTab}}",
+        struct_name(class_name),
+        class_name,
+        class.tag(),
+        size,
+        class_name
+    )?;
+
+    for attr in attrs.iter() {
+        write!(out_file, ", {}", attr)?;
+    }
+
+    writeln!(out_file, "}};")?;
+
+    for child_class_name in &class.child_names {
+        proto_obj(out_file, classes, child_class_name)?;
+    }
+
+    Ok(())
+}
+
+// Attributes for a prototype object
+fn proto_attrs(attrs: &mut Vec<String>, classes: &IndexMap<TypeID, Class>, class_name: &TypeID) {
+    let class: &Class = classes.get(class_name).unwrap();
+
+    if let Some(parent_name) = &class.parent_name {
+        proto_attrs(attrs, classes, parent_name);
+    }
+
+    for attr in class.attrs.iter() {
+        attrs.push(default_value(&attr.type_decl));
+    }
+}
+
+// The initial value of a variable of a type
+pub fn default_value(type_decl: &TypeID) -> String {
+    if type_decl.is_int() {
+        "&int_const_00000000.header".to_string() // 0
+    } else if type_decl.is_bool() {
+        "&bool_const0.header".to_string() // False
+    } else if type_decl.is_string() {
+        "&str_const_00000000.header".to_string() // Empty String ("")
+    } else {
+        "NULL".to_string() // Void
+    }
+}
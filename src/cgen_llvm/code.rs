@@ -0,0 +1,956 @@
+use std::fmt::Display;
+use std::io;
+
+use indexmap::IndexMap;
+
+use super::{default_value, struct_name};
+use crate::ast::{ArithOpType, Class, CompType, Expression, ObjectID, TypeID};
+use crate::scoped_collections::ScopedIndexMap;
+
+const VAL_FIELD: u32 = 3; // The value of an Int or Bool
+const DISPTAB_FIELD: u32 = 2;
+
+// Where a variable is kept
+#[derive(Clone)]
+pub enum Location {
+    Local(String),     // A pointer to memory on the stack
+    Attr(String, u32), // The struct type of self and the field
+}
+
+// The function being written
+struct Function {
+    allocas: Vec<String>, // Variables (allocated in the entry block)
+    next: u32,            // Numbers the values and labels
+    block: String,        // The label of the current basic block (for phi)
+}
+
+impl Function {
+    fn new() -> Function {
+        Function {
+            allocas: Vec::new(),
+            next: 0,
+            block: "entry".to_string(),
+        }
+    }
+
+    // A new value or label
+    // Each has a different number (which also keeps let variables apart).
+    fn new_name(&mut self, name: impl Display) -> String {
+        self.next += 1;
+        format!("{}{}", name, self.next)
+    }
+
+    // A new variable on the stack
+    fn new_var(&mut self, name: impl Display) -> String {
+        let var = format!("%{}", self.new_name(format!("v_{}_", name)));
+        self.allocas.push(var.clone());
+
+        var
+    }
+}
+
+// The function that implements a method of a class
+// The methods of the basic classes are in the runtime (with C names).
+pub fn function_name(
+    classes: &IndexMap<TypeID, Class>,
+    class_name: &TypeID,
+    method_name: &ObjectID,
+) -> String {
+    if classes.get(class_name).unwrap().basic {
+        format!("@{}_{}", class_name, method_name)
+    } else {
+        format!("@{}.{}", class_name, method_name)
+    }
+}
+
+// The function that implements a method for objects of a class
+pub fn method_function(
+    classes: &IndexMap<TypeID, Class>,
+    class: &Class,
+    method_name: &ObjectID,
+) -> String {
+    let pos = *class.method_name_to_pos.get(method_name).unwrap();
+    let (class_name, method_name) = &class.dispatch_table[pos as usize];
+
+    function_name(classes, class_name, method_name)
+}
+
+// Write an instruction
+fn emit(out_file: &mut dyn io::Write, line: impl Display) -> Result<(), io::Error> {
+    writeln!(out_file, "  {}", line)
+}
+
+// Start a basic block
+fn emit_label(
+    out_file: &mut dyn io::Write,
+    function: &mut Function,
+    label: &str,
+) -> Result<(), io::Error> {
+    writeln!(out_file, "{}:", label)?;
+    function.block = label.to_string();
+
+    Ok(())
+}
+
+// Write an instruction that has a value
+// Returns the value.
+fn emit_val(
+    out_file: &mut dyn io::Write,
+    function: &mut Function,
+    instr: impl Display,
+) -> Result<String, io::Error> {
+    let val = format!("%{}", function.new_name("t"));
+    emit(out_file, format!("{} = {}", val, instr))?;
+
+    Ok(val)
+}
+
+// Can a value from code_expr be void?
+// Constants and self are never void.
+fn may_be_void(val: &str) -> bool {
+    !val.starts_with('@') && val != "%self"
+}
+
+// Stop the program (with a runtime error) if a condition is true
+fn emit_check(
+    out_file: &mut dyn io::Write,
+    function: &mut Function,
+    cond: &str,
+    fail: impl Display,
+) -> Result<(), io::Error> {
+    let fail_label = function.new_name("L");
+    let ok_label = function.new_name("L");
+
+    emit(
+        out_file,
+        format!("br i1 {}, label %{}, label %{}", cond, fail_label, ok_label),
+    )?;
+
+    emit_label(out_file, function, &fail_label)?;
+    emit(out_file, format!("call void {}", fail))?;
+    emit(out_file, "unreachable")?;
+
+    emit_label(out_file, function, &ok_label)
+}
+
+// The value of an Int or Bool
+fn int_val(
+    out_file: &mut dyn io::Write,
+    function: &mut Function,
+    obj: &str,
+) -> Result<String, io::Error> {
+    let field = emit_val(
+        out_file,
+        function,
+        format!(
+            "getelementptr %Int_object, ptr {}, i32 0, i32 {}",
+            obj, VAL_FIELD
+        ),
+    )?;
+
+    emit_val(out_file, function, format!("load i32, ptr {}", field))
+}
+
+// Is a Bool true?
+fn truth(
+    out_file: &mut dyn io::Write,
+    function: &mut Function,
+    obj: &str,
+) -> Result<String, io::Error> {
+    let val = int_val(out_file, function, obj)?;
+
+    emit_val(out_file, function, format!("icmp ne i32 {}, 0", val))
+}
+
+// A new Int
+fn new_int(
+    out_file: &mut dyn io::Write,
+    function: &mut Function,
+    val: &str,
+) -> Result<String, io::Error> {
+    let obj = emit_val(
+        out_file,
+        function,
+        "call ptr @Object_copy(ptr @Int_protObj)",
+    )?;
+    let field = emit_val(
+        out_file,
+        function,
+        format!(
+            "getelementptr %Int_object, ptr {}, i32 0, i32 {}",
+            obj, VAL_FIELD
+        ),
+    )?;
+    emit(out_file, format!("store i32 {}, ptr {}", val, field))?;
+
+    Ok(obj)
+}
+
+// The Bool constant for an i1
+fn new_bool(
+    out_file: &mut dyn io::Write,
+    function: &mut Function,
+    cond: &str,
+) -> Result<String, io::Error> {
+    emit_val(
+        out_file,
+        function,
+        format!("select i1 {}, ptr @bool_const1, ptr @bool_const0", cond),
+    )
+}
+
+// Write a function whose body has been generated
+fn write_function(
+    out_file: &mut dyn io::Write,
+    name: &str,
+    params: &[String],
+    function: &Function,
+    body: &[u8],
+) -> Result<(), io::Error> {
+    writeln!(out_file)?;
+    write!(out_file, "define ptr {}(ptr %self", name)?;
+
+    for param in params.iter() {
+        write!(out_file, ", ptr {}", param)?;
+    }
+
+    writeln!(out_file, ") {{")?;
+    writeln!(out_file, "entry:")?;
+
+    for var in function.allocas.iter() {
+        emit(out_file, format!("{} = alloca ptr", var))?;
+    }
+
+    out_file.write_all(body)?;
+    writeln!(out_file, "}}")?;
+
+    Ok(())
+}
+
+// Initialization method
+pub fn code_methods(
+    out_file: &mut dyn io::Write,
+    classes: &IndexMap<TypeID, Class>,
+    class_name: &TypeID,
+    variables: &mut ScopedIndexMap<ObjectID, Location>,
+) -> Result<(), io::Error> {
+    let class: &Class = classes.get(class_name).unwrap();
+
+    variables.enter_scope(); // Scope for attributes
+
+    // Add attributes to var list (the attributes of basic classes are never named)
+    if !class.basic {
+        for attr in class.attrs.iter() {
+            variables.insert(
+                attr.name.clone(),
+                Location::Attr(struct_name(class_name), attr.self_offset as u32),
+            );
+        }
+    }
+
+    let mut function = Function::new();
+    let mut body: Vec<u8> = Vec::new();
+
+    if let Some(parent_name) = &class.parent_name {
+        // Use parent's initialization first
+        emit(
+            &mut body,
+            format!("call ptr @{}_init(ptr %self)", parent_name),
+        )?;
+    }
+
+    for attr in class.attrs.iter() {
+        if let Expression::NoExpr = attr.init {
+        } else {
+            // Evaluate init expression
+            let val = code_expr(
+                &mut body,
+                &attr.init,
+                class,
+                classes,
+                variables,
+                &mut function,
+            )?;
+
+            store(
+                &mut body,
+                &mut function,
+                variables.get(&attr.name).unwrap(),
+                &val,
+            )?;
+        }
+    }
+
+    emit(&mut body, "ret ptr %self")?;
+
+    write_function(
+        out_file,
+        &format!("@{}_init", class_name),
+        &[],
+        &function,
+        &body,
+    )?;
+
+    if !class.basic {
+        // Code for methods of basic objects is in the runtime
+        // Code methods of class
+        for (method_name, method) in class.methods.iter() {
+            variables.enter_scope(); // Scope for arguments (formals)
+
+            let mut function = Function::new();
+            let mut body: Vec<u8> = Vec::new();
+            let mut params: Vec<String> = Vec::new();
+
+            // Arguments can be assigned to, so each is kept on the stack
+            for formal in method.formals.iter() {
+                let param = format!("%p_{}", formal.name);
+                let var = function.new_var(&formal.name);
+
+                emit(&mut body, format!("store ptr {}, ptr {}", param, var))?;
+                variables.insert(formal.name.clone(), Location::Local(var));
+                params.push(param);
+            }
+
+            // Evaluate
+            let val = code_expr(
+                &mut body,
+                &method.expr,
+                class,
+                classes,
+                variables,
+                &mut function,
+            )?;
+
+            emit(&mut body, format!("ret ptr {}", val))?;
+
+            write_function(
+                out_file,
+                &function_name(classes, class_name, method_name),
+                &params,
+                &function,
+                &body,
+            )?;
+
+            variables.exit_scope();
+        }
+    }
+
+    for child_class_name in &class.child_names {
+        code_methods(out_file, classes, child_class_name, variables)?;
+    }
+
+    variables.exit_scope();
+
+    Ok(())
+}
+
+// Store a value in a variable
+fn store(
+    out_file: &mut dyn io::Write,
+    function: &mut Function,
+    location: &Location,
+    val: &str,
+) -> Result<(), io::Error> {
+    let ptr = match location {
+        Location::Local(var) => var.clone(),
+        Location::Attr(struct_name, field) => emit_val(
+            out_file,
+            function,
+            format!(
+                "getelementptr {}, ptr %self, i32 0, i32 {}",
+                struct_name, field
+            ),
+        )?,
+    };
+
+    emit(out_file, format!("store ptr {}, ptr {}", val, ptr))
+}
+
+// Output instructions that evaluate an expression
+// Returns the value (a value of an instruction, a constant, self or null).
+fn code_expr(
+    out_file: &mut dyn io::Write,
+    expr: &Expression,
+    current_class: &Class,
+    classes: &IndexMap<TypeID, Class>,
+    variables: &mut ScopedIndexMap<ObjectID, Location>,
+    function: &mut Function,
+) -> Result<String, io::Error> {
+    let val = match expr {
+        Expression::Assign { name, expr, .. } => {
+            let val = code_expr(out_file, expr, current_class, classes, variables, function)?;
+
+            let location = variables.get(name).unwrap().clone();
+            store(out_file, function, &location, &val)?;
+
+            val
+        }
+        Expression::Dispatch {
+            span,
+            expr,
+            type_name,
+            name,
+            args,
+            ..
+        } => {
+            // Evaluate the arguments first
+            let mut arg_vals = Vec::new();
+            for arg in args.iter() {
+                arg_vals.push(code_expr(
+                    out_file,
+                    arg,
+                    current_class,
+                    classes,
+                    variables,
+                    function,
+                )?);
+            }
+
+            let receiver = code_expr(out_file, expr, current_class, classes, variables, function)?;
+
+            // Dispatch on void
+            if may_be_void(&receiver) {
+                let is_void = emit_val(
+                    out_file,
+                    function,
+                    format!("icmp eq ptr {}, null", receiver),
+                )?;
+
+                emit_check(
+                    out_file,
+                    function,
+                    &is_void,
+                    format!(
+                        "@cool_dispatch_abort(ptr @file_name_{:08x}, i64 {})",
+                        current_class.file_no, span.line_no
+                    ),
+                )?;
+            }
+
+            let callee = match type_name {
+                // Static dispatch calls the method directly
+                Some(type_name) => method_function(classes, classes.get(type_name).unwrap(), name),
+
+                // "Dynamic" dispatch calls the method in the receiver's dispatch table
+                None => {
+                    let method_pos = if expr.static_type().is_self_type() {
+                        current_class
+                    } else {
+                        classes.get(&expr.static_type()).unwrap()
+                    }
+                    .method_name_to_pos
+                    .get(name)
+                    .unwrap();
+
+                    let field = emit_val(
+                        out_file,
+                        function,
+                        format!(
+                            "getelementptr %object, ptr {}, i32 0, i32 {}",
+                            receiver, DISPTAB_FIELD
+                        ),
+                    )?;
+                    let disp_tab =
+                        emit_val(out_file, function, format!("load ptr, ptr {}", field))?;
+                    let entry = emit_val(
+                        out_file,
+                        function,
+                        format!("getelementptr ptr, ptr {}, i64 {}", disp_tab, method_pos),
+                    )?;
+
+                    emit_val(out_file, function, format!("load ptr, ptr {}", entry))?
+                }
+            };
+
+            let mut call_args = format!("ptr {}", receiver);
+            for arg_val in arg_vals.iter() {
+                call_args.push_str(&format!(", ptr {}", arg_val));
+            }
+
+            emit_val(
+                out_file,
+                function,
+                format!("call ptr {}({})", callee, call_args),
+            )?
+        }
+        Expression::Cond {
+            pred,
+            then_expr,
+            else_expr,
+            ..
+        } => {
+            let pred_val = code_expr(out_file, pred, current_class, classes, variables, function)?;
+            let cond = truth(out_file, function, &pred_val)?;
+
+            let then_label = function.new_name("L");
+            let else_label = function.new_name("L");
+            let end_label = function.new_name("L");
+
+            emit(
+                out_file,
+                format!(
+                    "br i1 {}, label %{}, label %{}",
+                    cond, then_label, else_label
+                ),
+            )?;
+
+            emit_label(out_file, function, &then_label)?;
+            let then_val = code_expr(
+                out_file,
+                then_expr,
+                current_class,
+                classes,
+                variables,
+                function,
+            )?;
+            let then_end = function.block.clone();
+            emit(out_file, format!("br label %{}", end_label))?;
+
+            emit_label(out_file, function, &else_label)?;
+            let else_val = code_expr(
+                out_file,
+                else_expr,
+                current_class,
+                classes,
+                variables,
+                function,
+            )?;
+            let else_end = function.block.clone();
+            emit(out_file, format!("br label %{}", end_label))?;
+
+            emit_label(out_file, function, &end_label)?;
+            emit_val(
+                out_file,
+                function,
+                format!(
+                    "phi ptr [ {}, %{} ], [ {}, %{} ]",
+                    then_val, then_end, else_val, else_end
+                ),
+            )?
+        }
+        Expression::Loop { pred, body, .. } => {
+            let cond_label = function.new_name("L");
+            let body_label = function.new_name("L");
+            let end_label = function.new_name("L");
+
+            emit(out_file, format!("br label %{}", cond_label))?;
+
+            emit_label(out_file, function, &cond_label)?;
+            let pred_val = code_expr(out_file, pred, current_class, classes, variables, function)?;
+            let cond = truth(out_file, function, &pred_val)?;
+            emit(
+                out_file,
+                format!(
+                    "br i1 {}, label %{}, label %{}",
+                    cond, body_label, end_label
+                ),
+            )?;
+
+            emit_label(out_file, function, &body_label)?;
+            code_expr(out_file, body, current_class, classes, variables, function)?;
+            emit(out_file, format!("br label %{}", cond_label))?;
+
+            emit_label(out_file, function, &end_label)?;
+
+            "null".to_string() // Void
+        }
+        Expression::TypeCase {
+            span,
+            expr,
+            branches,
+            ..
+        } => {
+            let obj = code_expr(out_file, expr, current_class, classes, variables, function)?;
+
+            if may_be_void(&obj) {
+                let is_void = emit_val(out_file, function, format!("icmp eq ptr {}, null", obj))?;
+
+                emit_check(
+                    out_file,
+                    function,
+                    &is_void,
+                    format!(
+                        "@cool_case_abort2(ptr @file_name_{:08x}, i64 {})",
+                        current_class.file_no, span.line_no
+                    ),
+                )?;
+            }
+
+            let tag = emit_val(out_file, function, format!("load i64, ptr {}", obj))?;
+
+            let branch_labels: Vec<String> =
+                branches.iter().map(|_| function.new_name("L")).collect();
+            let no_match_label = function.new_name("L");
+            let end_label = function.new_name("L");
+
+            // Each tag goes to the first branch whose family has it
+            // Note that the order is already correct
+            emit(
+                out_file,
+                format!("switch i64 {}, label %{} [", tag, no_match_label),
+            )?;
+            for tag in 0..classes.len() as u32 {
+                if let Some(i) = branches
+                    .iter()
+                    .position(|branch| branch.family.contains(&tag))
+                {
+                    emit(
+                        out_file,
+                        format!("  i64 {}, label %{}", tag, branch_labels[i]),
+                    )?;
+                }
+            }
+            emit(out_file, "]")?;
+
+            // Code each branch
+            let mut incoming: Vec<String> = Vec::new();
+            for (branch, label) in branches.iter().zip(branch_labels.iter()) {
+                emit_label(out_file, function, label)?;
+
+                variables.enter_scope(); // Scope for new variable
+
+                let var = function.new_var(&branch.name);
+                emit(out_file, format!("store ptr {}, ptr {}", obj, var))?;
+                variables.insert(branch.name.clone(), Location::Local(var));
+
+                let branch_val = code_expr(
+                    out_file,
+                    &branch.expr,
+                    current_class,
+                    classes,
+                    variables,
+                    function,
+                )?;
+                incoming.push(format!("[ {}, %{} ]", branch_val, function.block));
+                emit(out_file, format!("br label %{}", end_label))?;
+
+                variables.exit_scope();
+            }
+
+            // If no branch succeeds
+            emit_label(out_file, function, &no_match_label)?;
+            emit(out_file, format!("call void @cool_case_abort(ptr {})", obj))?;
+            emit(out_file, "unreachable")?;
+
+            emit_label(out_file, function, &end_label)?;
+            emit_val(
+                out_file,
+                function,
+                format!("phi ptr {}", incoming.join(", ")),
+            )?
+        }
+        Expression::Block { body, .. } => {
+            // Evaluate each expression in body
+            let mut val = String::new();
+            for expr in body.iter() {
+                val = code_expr(out_file, expr, current_class, classes, variables, function)?;
+            }
+
+            val
+        }
+        Expression::Let {
+            identifier,
+            type_decl,
+            init,
+            body,
+            ..
+        } => {
+            // Evaluate init expression
+            let init_val = if let Expression::NoExpr = **init {
+                default_value(type_decl).to_string()
+            } else {
+                code_expr(out_file, init, current_class, classes, variables, function)?
+            };
+
+            variables.enter_scope(); // Scope for new variable
+
+            let var = function.new_var(identifier);
+            emit(out_file, format!("store ptr {}, ptr {}", init_val, var))?;
+            variables.insert(identifier.clone(), Location::Local(var));
+
+            // Evaluate body
+            let val = code_expr(out_file, body, current_class, classes, variables, function)?;
+
+            variables.exit_scope();
+
+            val
+        }
+        Expression::ArithOp {
+            expr_lhs,
+            expr_rhs,
+            arith_op_type,
+            ..
+        } => {
+            let lhs_obj = code_expr(
+                out_file,
+                expr_lhs,
+                current_class,
+                classes,
+                variables,
+                function,
+            )?;
+            let rhs_obj = code_expr(
+                out_file,
+                expr_rhs,
+                current_class,
+                classes,
+                variables,
+                function,
+            )?;
+
+            let lhs = int_val(out_file, function, &lhs_obj)?;
+            let rhs = int_val(out_file, function, &rhs_obj)?;
+
+            let val = match arith_op_type {
+                ArithOpType::Add | ArithOpType::Sub => {
+                    // Addition and subtraction stop the program on overflow
+                    let intrinsic = match arith_op_type {
+                        ArithOpType::Add => "@llvm.sadd.with.overflow.i32",
+                        _ => "@llvm.ssub.with.overflow.i32",
+                    };
+
+                    let result = emit_val(
+                        out_file,
+                        function,
+                        format!("call {{ i32, i1 }} {}(i32 {}, i32 {})", intrinsic, lhs, rhs),
+                    )?;
+                    let overflow = emit_val(
+                        out_file,
+                        function,
+                        format!("extractvalue {{ i32, i1 }} {}, 1", result),
+                    )?;
+                    emit_check(out_file, function, &overflow, "@cool_arithmetic_overflow()")?;
+
+                    emit_val(
+                        out_file,
+                        function,
+                        format!("extractvalue {{ i32, i1 }} {}, 0", result),
+                    )?
+                }
+
+                // Multiplication wraps around (as MIPS mul does)
+                ArithOpType::Mul => {
+                    emit_val(out_file, function, format!("mul i32 {}, {}", lhs, rhs))?
+                }
+                ArithOpType::Div => {
+                    let is_zero = emit_val(out_file, function, format!("icmp eq i32 {}, 0", rhs))?;
+                    emit_check(out_file, function, &is_zero, "@cool_division_by_zero()")?;
+
+                    // Dividing the smallest Int by -1 wraps around (sdiv would not)
+                    let is_minus_one =
+                        emit_val(out_file, function, format!("icmp eq i32 {}, -1", rhs))?;
+                    let divisor = emit_val(
+                        out_file,
+                        function,
+                        format!("select i1 {}, i32 1, i32 {}", is_minus_one, rhs),
+                    )?;
+                    let quotient =
+                        emit_val(out_file, function, format!("sdiv i32 {}, {}", lhs, divisor))?;
+                    let negated = emit_val(out_file, function, format!("sub i32 0, {}", lhs))?;
+
+                    emit_val(
+                        out_file,
+                        function,
+                        format!(
+                            "select i1 {}, i32 {}, i32 {}",
+                            is_minus_one, negated, quotient
+                        ),
+                    )?
+                }
+            };
+
+            new_int(out_file, function, &val)?
+        }
+        Expression::Neg { expr, .. } => {
+            let obj = code_expr(out_file, expr, current_class, classes, variables, function)?;
+            let val = int_val(out_file, function, &obj)?;
+
+            let result = emit_val(
+                out_file,
+                function,
+                format!(
+                    "call {{ i32, i1 }} @llvm.ssub.with.overflow.i32(i32 0, i32 {})",
+                    val
+                ),
+            )?;
+            let overflow = emit_val(
+                out_file,
+                function,
+                format!("extractvalue {{ i32, i1 }} {}, 1", result),
+            )?;
+            emit_check(out_file, function, &overflow, "@cool_arithmetic_overflow()")?;
+
+            let negated = emit_val(
+                out_file,
+                function,
+                format!("extractvalue {{ i32, i1 }} {}, 0", result),
+            )?;
+
+            new_int(out_file, function, &negated)?
+        }
+        Expression::Comp {
+            expr_lhs,
+            expr_rhs,
+            comp_type,
+            ..
+        } => {
+            let lhs_obj = code_expr(
+                out_file,
+                expr_lhs,
+                current_class,
+                classes,
+                variables,
+                function,
+            )?;
+            let rhs_obj = code_expr(
+                out_file,
+                expr_rhs,
+                current_class,
+                classes,
+                variables,
+                function,
+            )?;
+
+            let lhs = int_val(out_file, function, &lhs_obj)?;
+            let rhs = int_val(out_file, function, &rhs_obj)?;
+
+            let op = match comp_type {
+                CompType::LT => "slt",
+                CompType::LEq => "sle",
+            };
+
+            let cond = emit_val(
+                out_file,
+                function,
+                format!("icmp {} i32 {}, {}", op, lhs, rhs),
+            )?;
+
+            new_bool(out_file, function, &cond)?
+        }
+        Expression::Eq {
+            expr_lhs, expr_rhs, ..
+        } => {
+            let lhs = code_expr(
+                out_file,
+                expr_lhs,
+                current_class,
+                classes,
+                variables,
+                function,
+            )?;
+            let rhs = code_expr(
+                out_file,
+                expr_rhs,
+                current_class,
+                classes,
+                variables,
+                function,
+            )?;
+
+            // The same object is always equal (otherwise, test for structural equality)
+            if lhs == rhs {
+                "@bool_const1".to_string() // True
+            } else {
+                let same = emit_val(out_file, function, format!("icmp eq ptr {}, {}", lhs, rhs))?;
+                let test = emit_val(
+                    out_file,
+                    function,
+                    format!("call i64 @cool_equality_test(ptr {}, ptr {})", lhs, rhs),
+                )?;
+                let equal = emit_val(out_file, function, format!("icmp ne i64 {}, 0", test))?;
+                let cond = emit_val(out_file, function, format!("or i1 {}, {}", same, equal))?;
+
+                new_bool(out_file, function, &cond)?
+            }
+        }
+        Expression::Not { expr, .. } => {
+            let obj = code_expr(out_file, expr, current_class, classes, variables, function)?;
+            let val = int_val(out_file, function, &obj)?;
+            let cond = emit_val(out_file, function, format!("icmp eq i32 {}, 0", val))?;
+
+            new_bool(out_file, function, &cond)?
+        }
+        Expression::IntConst { val, .. } => format!("@int_const_{:08x}", val),
+        Expression::BoolConst { val, .. } => format!("@bool_const{}", *val as u8),
+        Expression::StringConst { val_id, .. } => format!("@str_const_{:08x}", val_id),
+        Expression::New { type_name, .. } => {
+            let (proto, init) = if type_name.is_self_type() {
+                // The class of self is found by its tag
+                let tag = emit_val(out_file, function, "load i64, ptr %self")?;
+
+                let proto_field = emit_val(
+                    out_file,
+                    function,
+                    format!(
+                        "getelementptr {{ ptr, ptr }}, ptr @class_objTab, i64 {}, i32 0",
+                        tag
+                    ),
+                )?;
+                let init_field = emit_val(
+                    out_file,
+                    function,
+                    format!(
+                        "getelementptr {{ ptr, ptr }}, ptr @class_objTab, i64 {}, i32 1",
+                        tag
+                    ),
+                )?;
+
+                (
+                    emit_val(out_file, function, format!("load ptr, ptr {}", proto_field))?,
+                    emit_val(out_file, function, format!("load ptr, ptr {}", init_field))?,
+                )
+            } else {
+                (
+                    format!("@{}_protObj", type_name),
+                    format!("@{}_init", type_name),
+                )
+            };
+
+            // Copy prototype object and initialize it
+            let obj = emit_val(
+                out_file,
+                function,
+                format!("call ptr @Object_copy(ptr {})", proto),
+            )?;
+
+            emit_val(
+                out_file,
+                function,
+                format!("call ptr {}(ptr {})", init, obj),
+            )?
+        }
+        Expression::IsVoid { expr, .. } => {
+            let obj = code_expr(out_file, expr, current_class, classes, variables, function)?;
+
+            if may_be_void(&obj) {
+                let cond = emit_val(out_file, function, format!("icmp eq ptr {}, null", obj))?;
+
+                new_bool(out_file, function, &cond)?
+            } else {
+                "@bool_const0".to_string() // False
+            }
+        }
+        Expression::NoExpr => "null".to_string(), // Never used
+        Expression::VarByName { name, .. } => {
+            if name.is_self() {
+                "%self".to_string()
+            } else {
+                let ptr = match variables.get(name).unwrap() {
+                    Location::Local(var) => var.clone(),
+                    Location::Attr(struct_name, field) => emit_val(
+                        out_file,
+                        function,
+                        format!(
+                            "getelementptr {}, ptr %self, i32 0, i32 {}",
+                            struct_name, field
+                        ),
+                    )?,
+                };
+
+                emit_val(out_file, function, format!("load ptr, ptr {}", ptr))?
+            }
+        }
+    };
+
+    Ok(val)
+}
@@ -0,0 +1,433 @@
+// A code generator for LLVM IR (the text format)
+// Objects have the layout of the x86-64 code (the MIPS layout with 8-byte words)
+// and are opaque pointers. Each class has a struct type, and its dispatch table
+// is a global array of method pointers. The program is linked with the runtime
+// of the x86-64 code, which implements the basic classes:
+//     llc -relocation-model=pic name.ll -o name.s     (LLVM 14 also needs -opaque-pointers)
+//     cc name.s runtime/x86_64.c -o name
+// or, with clang,
+//     clang name.ll runtime/x86_64.c -o name
+
+mod code;
+
+use std::io;
+
+use crate::ast::{Class, ObjectID, TypeID};
+use crate::scoped_collections::ScopedIndexMap;
+
+use indexmap::IndexMap;
+
+use self::code::{code_methods, function_name, method_function, Location};
+
+const WORD_SIZE: usize = 8;
+const DEFAULT_OBJFIELDS: u32 = 3;
+
+// The functions of the runtime that the generated code calls
+// The methods of the basic classes are declared with the classes.
+const RUNTIME_DECLARATIONS: [&str; 8] = [
+    "declare i64 @cool_equality_test(ptr, ptr)",
+    "declare void @cool_dispatch_abort(ptr, i64) noreturn",
+    "declare void @cool_case_abort(ptr) noreturn",
+    "declare void @cool_case_abort2(ptr, i64) noreturn",
+    "declare void @cool_arithmetic_overflow() noreturn",
+    "declare void @cool_division_by_zero() noreturn",
+    "declare { i32, i1 } @llvm.sadd.with.overflow.i32(i32, i32)",
+    "declare { i32, i1 } @llvm.ssub.with.overflow.i32(i32, i32)",
+];
+
+// Translate an IO error if necessary
+pub fn cgen(
+    out_file: &mut dyn io::Write,
+    in_file_names: &[String],
+    classes: &IndexMap<TypeID, Class>,
+    int_table: &[u32],
+    str_table: &[String],
+) -> Result<(), String> {
+    match write_code(out_file, in_file_names, classes, int_table, str_table) {
+        Err(s) => Err(s.to_string()),
+        Ok(()) => Ok(()),
+    }
+}
+
+// output an LLVM module for entire program to out_file
+fn write_code(
+    out_file: &mut dyn io::Write,
+    in_file_names: &[String],
+    classes: &IndexMap<TypeID, Class>,
+    int_table: &[u32],
+    str_table: &[String],
+) -> Result<(), io::Error> {
+    let root_class_name: TypeID = TypeID::root_class_name();
+
+    let int_tag = classes.get(&TypeID::new_int()).unwrap().tag();
+    let bool_tag = classes.get(&TypeID::new_bool()).unwrap().tag();
+    let string_tag = classes.get(&TypeID::new_string()).unwrap().tag();
+
+    // Every object starts with its tag, its size (in words) and its dispatch table
+    writeln!(out_file, "%object = type {{ i64, i64, ptr }}")?;
+    writeln!(out_file, "%Int_object = type {{ i64, i64, ptr, i32, i32 }}")?; // Also Bool
+    class_types(out_file, classes, &root_class_name, 0)?;
+    writeln!(out_file)?;
+
+    // The runtime needs the tags of the basic classes
+    writeln!(out_file, "@_int_tag = constant i64 {}", int_tag)?;
+    writeln!(out_file, "@_bool_tag = constant i64 {}", bool_tag)?;
+    writeln!(out_file, "@_string_tag = constant i64 {}", string_tag)?;
+    writeln!(out_file)?;
+
+    // Write out all string constants
+    for (val, val_id) in str_table.iter().zip(0..) {
+        string_const(out_file, "str_const", string_tag, val, val_id)?;
+    }
+
+    // Write out all file names as string constants
+    string_const(out_file, "file_name", string_tag, "", 0)?;
+    for (val, val_id) in in_file_names.iter().zip(1..) {
+        string_const(out_file, "file_name", string_tag, val, val_id)?;
+    }
+
+    // Write out all class names (in tag order)
+    class_name_consts(out_file, classes, &root_class_name, string_tag)?;
+    writeln!(out_file)?;
+
+    // Write integer constants
+    for val in int_table.iter() {
+        writeln!(
+            out_file,
+            "@int_const_{:08x} = constant %Int_object {{ i64 {}, i64 {}, ptr @Int_dispTab, i32 {}, i32 0 }}",
+            val,
+            int_tag,
+            DEFAULT_OBJFIELDS + 1,
+            *val as i32
+        )?;
+    }
+
+    // Write bool constants
+    for val in 0..2 {
+        writeln!(
+            out_file,
+            "@bool_const{} = constant %Int_object {{ i64 {}, i64 {}, ptr @Bool_dispTab, i32 {}, i32 0 }}",
+            val,
+            bool_tag,
+            DEFAULT_OBJFIELDS + 1,
+            val
+        )?;
+    }
+    writeln!(out_file)?;
+
+    // Write class name table (in tag order)
+    let class_names: Vec<String> = (0..classes.len())
+        .map(|tag| format!("ptr @class_name_{:08x}", tag))
+        .collect();
+    writeln!(
+        out_file,
+        "@class_nameTab = constant [{} x ptr] [{}]",
+        classes.len(),
+        class_names.join(", ")
+    )?;
+
+    // The prototype object and init function of each class (for new SELF_TYPE)
+    let mut entries: Vec<String> = Vec::new();
+    class_obj_tab(&mut entries, classes, &root_class_name);
+    writeln!(
+        out_file,
+        "@class_objTab = constant [{} x {{ ptr, ptr }}] [{}]",
+        classes.len(),
+        entries.join(", ")
+    )?;
+    writeln!(out_file)?;
+
+    dispatch_table(out_file, classes, &root_class_name)?;
+    writeln!(out_file)?;
+
+    proto_obj(out_file, classes, &root_class_name, DEFAULT_OBJFIELDS)?;
+    writeln!(out_file)?;
+
+    for declaration in RUNTIME_DECLARATIONS.iter() {
+        writeln!(out_file, "{}", declaration)?;
+    }
+
+    // The methods of the basic classes are in the runtime
+    method_declarations(out_file, classes, &root_class_name)?;
+
+    // methods for all objects
+    let mut variables: ScopedIndexMap<ObjectID, Location> = ScopedIndexMap::new();
+    code_methods(out_file, classes, &root_class_name, &mut variables)?;
+
+    // Called by main() in the runtime
+    // Create a Main object and call its main method.
+    let main_class = classes.get(&TypeID::new_main()).unwrap();
+    writeln!(out_file)?;
+    writeln!(out_file, "define void @cool_main() {{")?;
+    writeln!(out_file, "entry:")?;
+    writeln!(
+        out_file,
+        "  %obj = call ptr @Object_copy(ptr @Main_protObj)"
+    )?;
+    writeln!(out_file, "  %main = call ptr @Main_init(ptr %obj)")?;
+    writeln!(
+        out_file,
+        "  call ptr {}(ptr %main)",
+        method_function(classes, main_class, &ObjectID::new_main())
+    )?;
+    writeln!(out_file, "  ret void")?;
+    writeln!(out_file, "}}")?;
+
+    Ok(())
+}
+
+// The struct type of an object of a class
+fn struct_name(class_name: &TypeID) -> String {
+    if class_name.is_int() || class_name.is_bool() {
+        "%Int_object".to_string()
+    } else {
+        format!("%{}_object", class_name)
+    }
+}
+
+// Declare a struct type for each class
+// Int and Bool share one, and each string constant has a type of its own.
+fn class_types(
+    out_file: &mut dyn io::Write,
+    classes: &IndexMap<TypeID, Class>,
+    class_name: &TypeID,
+    mut attr_count: usize, // Including inherited attributes
+) -> Result<(), io::Error> {
+    let class: &Class = classes.get(class_name).unwrap();
+
+    attr_count += class.attrs.len();
+
+    if !class_name.is_int() && !class_name.is_bool() && !class_name.is_string() {
+        writeln!(
+            out_file,
+            "{} = type {{ i64, i64, ptr{} }}",
+            struct_name(class_name),
+            ", ptr".repeat(attr_count)
+        )?;
+    }
+
+    for child_class_name in &class.child_names {
+        class_types(out_file, classes, child_class_name, attr_count)?;
+    }
+
+    Ok(())
+}
+
+// A string constant (its length is one of the int constants)
+// The characters are followed by a null and padded to a whole word.
+fn string_const(
+    out_file: &mut dyn io::Write,
+    base_name: &str,
+    string_tag: u32,
+    val: &str,
+    val_id: u32,
+) -> Result<(), io::Error> {
+    let chars_len = (val.len() / WORD_SIZE + 1) * WORD_SIZE;
+
+    // Every byte that is not plainly printable is escaped
+    let mut chars = String::new();
+    for b in val.bytes() {
+        if (b.is_ascii_graphic() && b != b'"' && b != b'\\') || b == b' ' {
+            chars.push(b as char);
+        } else {
+            chars.push_str(&format!("\\{:02X}", b));
+        }
+    }
+    chars.push_str(&"\\00".repeat(chars_len - val.len()));
+
+    writeln!(
+        out_file,
+        "@{}_{:08x} = constant {{ i64, i64, ptr, ptr, [{} x i8] }} {{ i64 {}, i64 {}, ptr @String_dispTab, ptr @int_const_{:08x}, [{} x i8] c\"{}\" }}",
+        base_name,
+        val_id,
+        chars_len,
+        string_tag,
+        DEFAULT_OBJFIELDS as usize + 1 + chars_len / WORD_SIZE,
+        val.len(),
+        chars_len,
+        chars
+    )?;
+
+    Ok(())
+}
+
+// Write out all class names (in tag order)
+fn class_name_consts(
+    out_file: &mut dyn io::Write,
+    classes: &IndexMap<TypeID, Class>,
+    class_name: &TypeID,
+    string_tag: u32,
+) -> Result<(), io::Error> {
+    let class: &Class = classes.get(class_name).unwrap();
+
+    string_const(
+        out_file,
+        "class_name",
+        string_tag,
+        &class_name.to_string(),
+        class.tag(),
+    )?;
+
+    for child_class_name in &class.child_names {
+        class_name_consts(out_file, classes, child_class_name, string_tag)?;
+    }
+
+    Ok(())
+}
+
+// Class Object tab
+fn class_obj_tab(
+    entries: &mut Vec<String>,
+    classes: &IndexMap<TypeID, Class>,
+    class_name: &TypeID,
+) {
+    let class: &Class = classes.get(class_name).unwrap();
+
+    entries.push(format!(
+        "{{ ptr, ptr }} {{ ptr @{}_protObj, ptr @{}_init }}",
+        class_name, class_name
+    ));
+
+    for child_class_name in &class.child_names {
+        class_obj_tab(entries, classes, child_class_name);
+    }
+}
+
+// list each class's methods in order
+fn dispatch_table(
+    out_file: &mut dyn io::Write,
+    classes: &IndexMap<TypeID, Class>,
+    class_name: &TypeID,
+) -> Result<(), io::Error> {
+    let class: &Class = classes.get(class_name).unwrap();
+
+    let methods: Vec<String> = class
+        .dispatch_table
+        .iter()
+        .map(|(class_name, method_name)| {
+            format!("ptr {}", function_name(classes, class_name, method_name))
+        })
+        .collect();
+
+    writeln!(
+        out_file,
+        "@{}_dispTab = constant [{} x ptr] [{}]",
+        class_name,
+        methods.len(),
+        methods.join(", ")
+    )?;
+
+    for child_class_name in &class.child_names {
+        dispatch_table(out_file, classes, child_class_name)?;
+    }
+
+    Ok(())
+}
+
+// Create a prototype object for each class
+// This object has the structure of an object in this class but its attributes are not set
+fn proto_obj(
+    out_file: &mut dyn io::Write,
+    classes: &IndexMap<TypeID, Class>,
+    class_name: &TypeID,
+    mut size: u32,
+) -> Result<(), io::Error> {
+    let class: &Class = classes.get(class_name).unwrap();
+
+    size += class.attrs.len() as u32;
+
+    let header = format!(
+        "i64 {}, i64 {}, ptr @{}_dispTab",
+        class.tag(),
+        size,
+        class_name
+    );
+
+    if class_name.is_int() || class_name.is_bool() {
+        writeln!(
+            out_file,
+            "@{}_protObj = constant %Int_object {{ {}, i32 0, i32 0 }}",
+            class_name, header
+        )?;
+    } else if class_name.is_string() {
+        // The empty string
+        writeln!(
+            out_file,
+            "@String_protObj = constant {{ i64, i64, ptr, ptr, [{} x i8] }} {{ {}, ptr @int_const_00000000, [{} x i8] zeroinitializer }}",
+            WORD_SIZE, header, WORD_SIZE
+        )?;
+    } else {
+        let mut attrs = Vec::new();
+        proto_attrs(&mut attrs, classes, class_name);
+
+        writeln!(
+            out_file,
+            "@{}_protObj = constant {} {{ {}{} }}",
+            class_name,
+            struct_name(class_name),
+            header,
+            attrs
+                .iter()
+                .map(|attr| format!(", ptr {}", attr))
+                .collect::<String>()
+        )?;
+    }
+
+    for child_class_name in &class.child_names {
+        proto_obj(out_file, classes, child_class_name, size)?;
+    }
+
+    Ok(())
+}
+
+// Attributes for a prototype object
+fn proto_attrs(attrs: &mut Vec<String>, classes: &IndexMap<TypeID, Class>, class_name: &TypeID) {
+    let class: &Class = classes.get(class_name).unwrap();
+
+    if let Some(parent_name) = &class.parent_name {
+        proto_attrs(attrs, classes, parent_name);
+    }
+
+    for attr in class.attrs.iter() {
+        attrs.push(default_value(&attr.type_decl).to_string());
+    }
+}
+
+// Declare the methods of the basic classes (they are in the runtime)
+fn method_declarations(
+    out_file: &mut dyn io::Write,
+    classes: &IndexMap<TypeID, Class>,
+    class_name: &TypeID,
+) -> Result<(), io::Error> {
+    let class: &Class = classes.get(class_name).unwrap();
+
+    if class.basic {
+        for (method_name, method) in class.methods.iter() {
+            writeln!(
+                out_file,
+                "declare ptr {}(ptr{})",
+                function_name(classes, class_name, method_name),
+                ", ptr".repeat(method.formals.len())
+            )?;
+        }
+    }
+
+    for child_class_name in &class.child_names {
+        method_declarations(out_file, classes, child_class_name)?;
+    }
+
+    Ok(())
+}
+
+// The initial value of a variable of a type
+pub fn default_value(type_decl: &TypeID) -> &'static str {
+    if type_decl.is_int() {
+        "@int_const_00000000" // 0
+    } else if type_decl.is_bool() {
+        "@bool_const0" // False
+    } else if type_decl.is_string() {
+        "@str_const_00000000" // Empty String ("")
+    } else {
+        "null" // Void
+    }
+}
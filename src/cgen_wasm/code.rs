@@ -0,0 +1,770 @@
+use std::fmt::Display;
+use std::io;
+
+use indexmap::IndexMap;
+
+use super::{default_value, WORD_SIZE};
+use crate::ast::{ArithOpType, Class, CompType, Expression, ObjectID, TypeID};
+use crate::scoped_collections::ScopedIndexMap;
+
+const VAL_OFFSET: u32 = 12; // The value of an Int or Bool (in bytes)
+
+// Where a variable is kept
+#[derive(Clone)]
+pub enum Location {
+    Local(String),
+    Attr(u32), // The offset in self (in bytes)
+}
+
+// The function that implements a method for objects of a class
+pub fn method_function(class: &Class, method_name: &ObjectID) -> String {
+    let pos = *class.method_name_to_pos.get(method_name).unwrap();
+    let (class_name, method_name) = &class.dispatch_table[pos as usize];
+
+    format!("${}.{}", class_name, method_name)
+}
+
+// Write an instruction at a depth of nesting
+fn emit(out_file: &mut dyn io::Write, depth: usize, line: impl Display) -> Result<(), io::Error> {
+    writeln!(out_file, "{:width$}{}", "", line, width = 2 * depth)
+}
+
+// A new local for the function being written
+// Each has a different number (which also keeps let variables apart).
+fn new_var(locals: &mut Vec<String>, name: impl Display) -> String {
+    let var = format!("${}{}", name, locals.len() + 1);
+    locals.push(var.clone());
+
+    var
+}
+
+// Can the value of an expression be void?
+// Constants, new objects and self are never void.
+fn may_be_void(expr: &Expression) -> bool {
+    match expr {
+        Expression::IntConst { .. }
+        | Expression::BoolConst { .. }
+        | Expression::StringConst { .. }
+        | Expression::New { .. } => false,
+        Expression::VarByName { name, .. } => !name.is_self(),
+        _ => true,
+    }
+}
+
+// Write a function whose body has been generated
+fn write_function(
+    out_file: &mut dyn io::Write,
+    name: &str,
+    params: &[String],
+    locals: &[String],
+    body: &[u8],
+) -> Result<(), io::Error> {
+    writeln!(out_file)?;
+    write!(
+        out_file,
+        "  (func {} (type $method{}) (param $self i32)",
+        name,
+        params.len()
+    )?;
+
+    for param in params.iter() {
+        write!(out_file, " (param {} i32)", param)?;
+    }
+
+    writeln!(out_file, " (result i32)")?;
+
+    for local in locals.iter() {
+        emit(out_file, 2, format!("(local {} i32)", local))?;
+    }
+
+    out_file.write_all(body)?;
+    emit(out_file, 1, ")")?;
+
+    Ok(())
+}
+
+// Store the value on top of the stack in a variable (the value is left on the stack)
+fn store(
+    out_file: &mut dyn io::Write,
+    location: &Location,
+    locals: &mut Vec<String>,
+    depth: usize,
+) -> Result<(), io::Error> {
+    match location {
+        Location::Local(var) => emit(out_file, depth, format!("local.tee {}", var))?,
+        Location::Attr(offset) => {
+            let val = new_var(locals, "t");
+            emit(out_file, depth, format!("local.set {}", val))?;
+            emit(out_file, depth, "local.get $self")?;
+            emit(out_file, depth, format!("local.get {}", val))?;
+            emit(out_file, depth, format!("i32.store offset={}", offset))?;
+            emit(out_file, depth, format!("local.get {}", val))?;
+        }
+    }
+
+    Ok(())
+}
+
+// Initialization method
+pub fn code_methods(
+    out_file: &mut dyn io::Write,
+    classes: &IndexMap<TypeID, Class>,
+    class_name: &TypeID,
+    variables: &mut ScopedIndexMap<ObjectID, Location>,
+) -> Result<(), io::Error> {
+    let class: &Class = classes.get(class_name).unwrap();
+
+    variables.enter_scope(); // Scope for attributes
+
+    // Add attributes to var list (the attributes of basic classes are never named)
+    if !class.basic {
+        for attr in class.attrs.iter() {
+            variables.insert(
+                attr.name.clone(),
+                Location::Attr(attr.self_offset as u32 * WORD_SIZE),
+            );
+        }
+    }
+
+    let mut locals: Vec<String> = Vec::new();
+    let mut body: Vec<u8> = Vec::new();
+
+    if let Some(parent_name) = &class.parent_name {
+        // Use parent's initialization first
+        emit(&mut body, 2, "local.get $self")?;
+        emit(&mut body, 2, format!("call ${}_init", parent_name))?;
+        emit(&mut body, 2, "drop")?;
+    }
+
+    for attr in class.attrs.iter() {
+        if let Expression::NoExpr = attr.init {
+        } else {
+            // Evaluate init expression
+            code_expr(
+                &mut body,
+                &attr.init,
+                class,
+                classes,
+                variables,
+                &mut locals,
+                2,
+            )?;
+
+            store(
+                &mut body,
+                variables.get(&attr.name).unwrap(),
+                &mut locals,
+                2,
+            )?;
+            emit(&mut body, 2, "drop")?;
+        }
+    }
+
+    emit(&mut body, 2, "local.get $self")?;
+
+    write_function(
+        out_file,
+        &format!("${}_init", class_name),
+        &[],
+        &locals,
+        &body,
+    )?;
+
+    if !class.basic {
+        // Code for methods of basic objects is in the runtime
+        // Code methods of class
+        for (method_name, method) in class.methods.iter() {
+            variables.enter_scope(); // Scope for arguments (formals)
+
+            let mut locals: Vec<String> = Vec::new();
+            let mut params: Vec<String> = Vec::new();
+
+            // The arguments are numbered as the locals are (but not declared with them)
+            for (formal, param_no) in method.formals.iter().zip(1..) {
+                let param = format!("$v_{}_p{}", formal.name, param_no);
+                variables.insert(formal.name.clone(), Location::Local(param.clone()));
+                params.push(param);
+            }
+
+            // Evaluate
+            let mut body: Vec<u8> = Vec::new();
+            code_expr(
+                &mut body,
+                &method.expr,
+                class,
+                classes,
+                variables,
+                &mut locals,
+                2,
+            )?;
+
+            write_function(
+                out_file,
+                &format!("${}.{}", class_name, method_name),
+                &params,
+                &locals,
+                &body,
+            )?;
+
+            variables.exit_scope();
+        }
+    }
+
+    for child_class_name in &class.child_names {
+        code_methods(out_file, classes, child_class_name, variables)?;
+    }
+
+    variables.exit_scope();
+
+    Ok(())
+}
+
+// Output instructions that push the value of an expression
+fn code_expr(
+    out_file: &mut dyn io::Write,
+    expr: &Expression,
+    current_class: &Class,
+    classes: &IndexMap<TypeID, Class>,
+    variables: &mut ScopedIndexMap<ObjectID, Location>,
+    locals: &mut Vec<String>,
+    depth: usize,
+) -> Result<(), io::Error> {
+    match expr {
+        Expression::Assign { name, expr, .. } => {
+            code_expr(
+                out_file,
+                expr,
+                current_class,
+                classes,
+                variables,
+                locals,
+                depth,
+            )?;
+
+            let location = variables.get(name).unwrap().clone();
+            store(out_file, &location, locals, depth)?;
+        }
+        Expression::Dispatch {
+            span,
+            expr,
+            type_name,
+            name,
+            args,
+            ..
+        } => {
+            // Evaluate the arguments first
+            let mut arg_vals = Vec::new();
+            for arg in args.iter() {
+                code_expr(
+                    out_file,
+                    arg,
+                    current_class,
+                    classes,
+                    variables,
+                    locals,
+                    depth,
+                )?;
+
+                let arg_val = new_var(locals, "t");
+                emit(out_file, depth, format!("local.set {}", arg_val))?;
+                arg_vals.push(arg_val);
+            }
+
+            code_expr(
+                out_file,
+                expr,
+                current_class,
+                classes,
+                variables,
+                locals,
+                depth,
+            )?;
+
+            let receiver = new_var(locals, "t");
+            emit(out_file, depth, format!("local.set {}", receiver))?;
+
+            // Dispatch on void
+            if may_be_void(expr) {
+                emit(out_file, depth, format!("local.get {}", receiver))?;
+                emit(out_file, depth, "i32.eqz")?;
+                emit(out_file, depth, "if")?;
+                emit(
+                    out_file,
+                    depth + 1,
+                    format!("global.get $file_name_{:08x}", current_class.file_no),
+                )?;
+                emit(out_file, depth + 1, format!("i32.const {}", span.line_no))?;
+                emit(out_file, depth + 1, "call $cool_dispatch_abort")?;
+                emit(out_file, depth, "end")?;
+            }
+
+            emit(out_file, depth, format!("local.get {}", receiver))?;
+            for arg_val in arg_vals.iter() {
+                emit(out_file, depth, format!("local.get {}", arg_val))?;
+            }
+
+            match type_name {
+                // Static dispatch calls the method directly
+                Some(type_name) => emit(
+                    out_file,
+                    depth,
+                    format!(
+                        "call {}",
+                        method_function(classes.get(type_name).unwrap(), name)
+                    ),
+                )?,
+
+                // "Dynamic" dispatch calls the method in the receiver's dispatch table
+                None => {
+                    let method_pos = if expr.static_type().is_self_type() {
+                        current_class
+                    } else {
+                        classes.get(&expr.static_type()).unwrap()
+                    }
+                    .method_name_to_pos
+                    .get(name)
+                    .unwrap();
+
+                    emit(out_file, depth, format!("local.get {}", receiver))?;
+                    emit(out_file, depth, "i32.load offset=8")?;
+                    emit(
+                        out_file,
+                        depth,
+                        format!("i32.load offset={}", *method_pos as u32 * WORD_SIZE),
+                    )?;
+                    emit(
+                        out_file,
+                        depth,
+                        format!("call_indirect (type $method{})", args.len()),
+                    )?;
+                }
+            }
+        }
+        Expression::Cond {
+            pred,
+            then_expr,
+            else_expr,
+            ..
+        } => {
+            code_expr(
+                out_file,
+                pred,
+                current_class,
+                classes,
+                variables,
+                locals,
+                depth,
+            )?;
+            emit(out_file, depth, format!("i32.load offset={}", VAL_OFFSET))?;
+
+            emit(out_file, depth, "if (result i32)")?;
+            code_expr(
+                out_file,
+                then_expr,
+                current_class,
+                classes,
+                variables,
+                locals,
+                depth + 1,
+            )?;
+
+            emit(out_file, depth, "else")?;
+            code_expr(
+                out_file,
+                else_expr,
+                current_class,
+                classes,
+                variables,
+                locals,
+                depth + 1,
+            )?;
+            emit(out_file, depth, "end")?;
+        }
+        Expression::Loop { pred, body, .. } => {
+            emit(out_file, depth, "block")?;
+            emit(out_file, depth + 1, "loop")?;
+
+            code_expr(
+                out_file,
+                pred,
+                current_class,
+                classes,
+                variables,
+                locals,
+                depth + 2,
+            )?;
+
+            // Leave the block when the predicate is false
+            emit(
+                out_file,
+                depth + 2,
+                format!("i32.load offset={}", VAL_OFFSET),
+            )?;
+            emit(out_file, depth + 2, "i32.eqz")?;
+            emit(out_file, depth + 2, "br_if 1")?;
+
+            code_expr(
+                out_file,
+                body,
+                current_class,
+                classes,
+                variables,
+                locals,
+                depth + 2,
+            )?;
+
+            emit(out_file, depth + 2, "drop")?;
+            emit(out_file, depth + 2, "br 0")?;
+            emit(out_file, depth + 1, "end")?;
+            emit(out_file, depth, "end")?;
+
+            emit(out_file, depth, "i32.const 0")?; // Void
+        }
+        Expression::TypeCase {
+            span,
+            expr,
+            branches,
+            ..
+        } => {
+            code_expr(
+                out_file,
+                expr,
+                current_class,
+                classes,
+                variables,
+                locals,
+                depth,
+            )?;
+
+            let obj = new_var(locals, "t");
+            emit(out_file, depth, format!("local.set {}", obj))?;
+
+            if may_be_void(expr) {
+                emit(out_file, depth, format!("local.get {}", obj))?;
+                emit(out_file, depth, "i32.eqz")?;
+                emit(out_file, depth, "if")?;
+                emit(
+                    out_file,
+                    depth + 1,
+                    format!("global.get $file_name_{:08x}", current_class.file_no),
+                )?;
+                emit(out_file, depth + 1, format!("i32.const {}", span.line_no))?;
+                emit(out_file, depth + 1, "call $cool_case_abort2")?;
+                emit(out_file, depth, "end")?;
+            }
+
+            let tag = new_var(locals, "t");
+            emit(out_file, depth, format!("local.get {}", obj))?;
+            emit(out_file, depth, "i32.load")?;
+            emit(out_file, depth, format!("local.set {}", tag))?;
+
+            // Code each branch (each is in the else of the one before)
+            // Note that the order is already correct
+            let mut branch_depth = depth;
+            for branch in branches.iter() {
+                // The branch matches if the tag is in its family
+                emit(out_file, branch_depth, format!("local.get {}", tag))?;
+                emit(
+                    out_file,
+                    branch_depth,
+                    format!("i32.const {}", branch.family.start),
+                )?;
+                emit(out_file, branch_depth, "i32.sub")?;
+                emit(
+                    out_file,
+                    branch_depth,
+                    format!("i32.const {}", branch.family.end - branch.family.start),
+                )?;
+                emit(out_file, branch_depth, "i32.lt_u")?;
+                emit(out_file, branch_depth, "if (result i32)")?;
+
+                variables.enter_scope(); // Scope for new variable
+
+                let var = new_var(locals, format!("v_{}_", branch.name));
+                emit(out_file, branch_depth + 1, format!("local.get {}", obj))?;
+                emit(out_file, branch_depth + 1, format!("local.set {}", var))?;
+                variables.insert(branch.name.clone(), Location::Local(var));
+
+                code_expr(
+                    out_file,
+                    &branch.expr,
+                    current_class,
+                    classes,
+                    variables,
+                    locals,
+                    branch_depth + 1,
+                )?;
+
+                variables.exit_scope();
+
+                emit(out_file, branch_depth, "else")?;
+                branch_depth += 1;
+            }
+
+            // If no branch succeeds
+            emit(out_file, branch_depth, format!("local.get {}", obj))?;
+            emit(out_file, branch_depth, "call $cool_case_abort")?;
+            emit(out_file, branch_depth, "unreachable")?;
+
+            while branch_depth > depth {
+                branch_depth -= 1;
+                emit(out_file, branch_depth, "end")?;
+            }
+        }
+        Expression::Block { body, .. } => {
+            // Evaluate each expression in body (only the last value is kept)
+            for (i, expr) in body.iter().enumerate() {
+                if i > 0 {
+                    emit(out_file, depth, "drop")?;
+                }
+
+                code_expr(
+                    out_file,
+                    expr,
+                    current_class,
+                    classes,
+                    variables,
+                    locals,
+                    depth,
+                )?;
+            }
+        }
+        Expression::Let {
+            identifier,
+            type_decl,
+            init,
+            body,
+            ..
+        } => {
+            // Evaluate init expression
+            if let Expression::NoExpr = **init {
+                emit(out_file, depth, default_value(type_decl))?;
+            } else {
+                code_expr(
+                    out_file,
+                    init,
+                    current_class,
+                    classes,
+                    variables,
+                    locals,
+                    depth,
+                )?;
+            }
+
+            variables.enter_scope(); // Scope for new variable
+
+            let var = new_var(locals, format!("v_{}_", identifier));
+            emit(out_file, depth, format!("local.set {}", var))?;
+            variables.insert(identifier.clone(), Location::Local(var));
+
+            // Evaluate body
+            code_expr(
+                out_file,
+                body,
+                current_class,
+                classes,
+                variables,
+                locals,
+                depth,
+            )?;
+
+            variables.exit_scope();
+        }
+        Expression::ArithOp {
+            expr_lhs,
+            expr_rhs,
+            arith_op_type,
+            ..
+        } => {
+            code_expr(
+                out_file,
+                expr_lhs,
+                current_class,
+                classes,
+                variables,
+                locals,
+                depth,
+            )?;
+            code_expr(
+                out_file,
+                expr_rhs,
+                current_class,
+                classes,
+                variables,
+                locals,
+                depth,
+            )?;
+
+            // The runtime checks for overflow and division by zero
+            let function = match arith_op_type {
+                ArithOpType::Add => "$cool_add",
+                ArithOpType::Sub => "$cool_sub",
+                ArithOpType::Mul => "$cool_mul",
+                ArithOpType::Div => "$cool_div",
+            };
+
+            emit(out_file, depth, format!("call {}", function))?;
+        }
+        Expression::Neg { expr, .. } => {
+            code_expr(
+                out_file,
+                expr,
+                current_class,
+                classes,
+                variables,
+                locals,
+                depth,
+            )?;
+
+            emit(out_file, depth, "call $cool_neg")?;
+        }
+        Expression::Comp {
+            expr_lhs,
+            expr_rhs,
+            comp_type,
+            ..
+        } => {
+            code_expr(
+                out_file,
+                expr_lhs,
+                current_class,
+                classes,
+                variables,
+                locals,
+                depth,
+            )?;
+            emit(out_file, depth, format!("i32.load offset={}", VAL_OFFSET))?;
+
+            code_expr(
+                out_file,
+                expr_rhs,
+                current_class,
+                classes,
+                variables,
+                locals,
+                depth,
+            )?;
+            emit(out_file, depth, format!("i32.load offset={}", VAL_OFFSET))?;
+
+            let op = match comp_type {
+                CompType::LT => "i32.lt_s",
+                CompType::LEq => "i32.le_s",
+            };
+
+            emit(out_file, depth, op)?;
+            emit(out_file, depth, "call $cool_bool")?;
+        }
+        Expression::Eq {
+            expr_lhs, expr_rhs, ..
+        } => {
+            code_expr(
+                out_file,
+                expr_lhs,
+                current_class,
+                classes,
+                variables,
+                locals,
+                depth,
+            )?;
+            code_expr(
+                out_file,
+                expr_rhs,
+                current_class,
+                classes,
+                variables,
+                locals,
+                depth,
+            )?;
+
+            // The same object is always equal (otherwise, test for structural equality)
+            emit(out_file, depth, "call $cool_equal")?;
+        }
+        Expression::Not { expr, .. } => {
+            code_expr(
+                out_file,
+                expr,
+                current_class,
+                classes,
+                variables,
+                locals,
+                depth,
+            )?;
+
+            emit(out_file, depth, format!("i32.load offset={}", VAL_OFFSET))?;
+            emit(out_file, depth, "i32.eqz")?;
+            emit(out_file, depth, "call $cool_bool")?;
+        }
+        Expression::IntConst { val, .. } => emit(
+            out_file,
+            depth,
+            format!("global.get $int_const_{:08x}", val),
+        )?,
+        Expression::BoolConst { val, .. } => emit(
+            out_file,
+            depth,
+            format!("global.get $bool_const{}", *val as u8),
+        )?,
+        Expression::StringConst { val_id, .. } => emit(
+            out_file,
+            depth,
+            format!("global.get $str_const_{:08x}", val_id),
+        )?,
+        Expression::New { type_name, .. } => {
+            if type_name.is_self_type() {
+                // The class of self is found by its tag (each entry is two words)
+                let entry = new_var(locals, "t");
+                emit(out_file, depth, "global.get $class_objTab")?;
+                emit(out_file, depth, "local.get $self")?;
+                emit(out_file, depth, "i32.load")?;
+                emit(out_file, depth, "i32.const 3")?;
+                emit(out_file, depth, "i32.shl")?;
+                emit(out_file, depth, "i32.add")?;
+                emit(out_file, depth, format!("local.tee {}", entry))?;
+                emit(out_file, depth, "i32.load")?;
+                emit(out_file, depth, "call $Object.copy")?;
+                emit(out_file, depth, format!("local.get {}", entry))?;
+                emit(out_file, depth, "i32.load offset=4")?;
+                emit(out_file, depth, "call_indirect (type $method0)")?;
+            } else {
+                // Copy prototype object and initialize it
+                emit(
+                    out_file,
+                    depth,
+                    format!("global.get ${}_protObj", type_name),
+                )?;
+                emit(out_file, depth, "call $Object.copy")?;
+                emit(out_file, depth, format!("call ${}_init", type_name))?;
+            }
+        }
+        Expression::IsVoid { expr, .. } => {
+            code_expr(
+                out_file,
+                expr,
+                current_class,
+                classes,
+                variables,
+                locals,
+                depth,
+            )?;
+
+            emit(out_file, depth, "i32.eqz")?;
+            emit(out_file, depth, "call $cool_bool")?;
+        }
+        Expression::NoExpr => emit(out_file, depth, "i32.const 0")?, // Never used
+        Expression::VarByName { name, .. } => {
+            if name.is_self() {
+                emit(out_file, depth, "local.get $self")?;
+            } else {
+                match variables.get(name).unwrap() {
+                    Location::Local(var) => emit(out_file, depth, format!("local.get {}", var))?,
+                    Location::Attr(offset) => {
+                        emit(out_file, depth, "local.get $self")?;
+                        emit(out_file, depth, format!("i32.load offset={}", offset))?;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
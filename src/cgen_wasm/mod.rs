@@ -0,0 +1,423 @@
+// A code generator for WebAssembly (the text format)
+// Objects are kept in linear memory with the MIPS layout (see cgen) and 4-byte
+// words. Each method becomes a function in the function table, and a dispatch
+// table holds the indexes of its methods, so dynamic dispatch is a call_indirect.
+// The runtime (runtime/wasm.wat) is copied to the start of the output, and the
+// host provides the imports for input and output (see runtime/wasm.mjs):
+//     wat2wasm name.wat -o name.wasm
+//     node runtime/wasm.mjs name.wasm
+
+mod code;
+
+use std::io;
+
+use crate::ast::{Class, ObjectID, TypeID};
+use crate::scoped_collections::ScopedIndexMap;
+
+use indexmap::{IndexMap, IndexSet};
+
+use self::code::{code_methods, method_function, Location};
+
+const RUNTIME: &str = include_str!("../../runtime/wasm.wat");
+
+const WORD_SIZE: u32 = 4;
+const DEFAULT_OBJFIELDS: u32 = 3;
+const DATA_START: u32 = 512; // Lower addresses belong to the runtime
+const PAGE_SIZE: u32 = 1 << 16;
+
+// A word of static data
+enum Word {
+    Val(u32),
+    Addr(String), // The address of an object
+    Func(String), // The index of a function in the function table
+}
+
+// An object of static data
+// The characters of a string follow its words.
+struct StaticObj {
+    label: String,
+    words: Vec<Word>,
+    chars: Vec<u8>,
+}
+
+impl StaticObj {
+    fn new(label: impl Into<String>, words: Vec<Word>) -> StaticObj {
+        StaticObj {
+            label: label.into(),
+            words,
+            chars: Vec::new(),
+        }
+    }
+
+    // In bytes
+    fn size(&self) -> u32 {
+        WORD_SIZE * self.words.len() as u32 + self.chars.len() as u32
+    }
+}
+
+// Translate an IO error if necessary
+pub fn cgen(
+    out_file: &mut dyn io::Write,
+    in_file_names: &[String],
+    classes: &IndexMap<TypeID, Class>,
+    int_table: &[u32],
+    str_table: &[String],
+) -> Result<(), String> {
+    match write_code(out_file, in_file_names, classes, int_table, str_table) {
+        Err(s) => Err(s.to_string()),
+        Ok(()) => Ok(()),
+    }
+}
+
+// output a WebAssembly module for entire program to out_file
+fn write_code(
+    out_file: &mut dyn io::Write,
+    in_file_names: &[String],
+    classes: &IndexMap<TypeID, Class>,
+    int_table: &[u32],
+    str_table: &[String],
+) -> Result<(), io::Error> {
+    let root_class_name: TypeID = TypeID::root_class_name();
+
+    let int_tag = classes.get(&TypeID::new_int()).unwrap().tag();
+    let bool_tag = classes.get(&TypeID::new_bool()).unwrap().tag();
+    let string_tag = classes.get(&TypeID::new_string()).unwrap().tag();
+
+    write!(out_file, "{}", RUNTIME)?;
+
+    // A function type for each number of arguments
+    let max_args = classes
+        .values()
+        .flat_map(|class| class.methods.values())
+        .map(|method| method.formals.len())
+        .max()
+        .unwrap_or(0);
+
+    for args in 0..=max_args {
+        writeln!(
+            out_file,
+            "  (type $method{} (func{} (result i32)))",
+            args,
+            " (param i32)".repeat(args + 1)
+        )?;
+    }
+    writeln!(out_file)?;
+
+    // Every init function and method is in the table (in tag order)
+    let mut functions: IndexSet<String> = IndexSet::new();
+    function_table(&mut functions, classes, &root_class_name);
+
+    let mut data: Vec<StaticObj> = Vec::new();
+
+    // Write out all string constants
+    for (val, val_id) in str_table.iter().zip(0..) {
+        data.push(string_const("str_const", string_tag, val, val_id));
+    }
+
+    // Write out all file names as string constants
+    data.push(string_const("file_name", string_tag, "", 0));
+    for (val, val_id) in in_file_names.iter().zip(1..) {
+        data.push(string_const("file_name", string_tag, val, val_id));
+    }
+
+    // Write out all class names (in tag order)
+    class_name_consts(&mut data, classes, &root_class_name, string_tag);
+
+    // Write integer constants
+    for val in int_table.iter() {
+        data.push(StaticObj::new(
+            format!("int_const_{:08x}", val),
+            vec![
+                Word::Val(int_tag),
+                Word::Val(DEFAULT_OBJFIELDS + 1),
+                Word::Addr("Int_dispTab".to_string()),
+                Word::Val(*val),
+            ],
+        ));
+    }
+
+    // Write bool constants
+    for val in 0..2 {
+        data.push(StaticObj::new(
+            format!("bool_const{}", val),
+            vec![
+                Word::Val(bool_tag),
+                Word::Val(DEFAULT_OBJFIELDS + 1),
+                Word::Addr("Bool_dispTab".to_string()),
+                Word::Val(val),
+            ],
+        ));
+    }
+
+    // Write class name table (in tag order)
+    data.push(StaticObj::new(
+        "class_nameTab",
+        (0..classes.len())
+            .map(|tag| Word::Addr(format!("class_name_{:08x}", tag)))
+            .collect(),
+    ));
+
+    let mut class_obj_words = Vec::new();
+    class_obj_tab(&mut class_obj_words, classes, &root_class_name);
+    data.push(StaticObj::new("class_objTab", class_obj_words));
+
+    dispatch_table(&mut data, classes, &root_class_name);
+
+    proto_obj(&mut data, classes, &root_class_name, DEFAULT_OBJFIELDS);
+
+    // Lay out static data
+    let mut addrs: IndexMap<String, u32> = IndexMap::new();
+    let mut addr = DATA_START;
+    for obj in data.iter() {
+        addrs.insert(obj.label.clone(), addr);
+        addr += obj.size();
+    }
+
+    // The heap follows (it grows as needed)
+    let heap_start = (addr + 7) & !7;
+    writeln!(
+        out_file,
+        "  (memory (export \"memory\") {})",
+        heap_start / PAGE_SIZE + 1
+    )?;
+    writeln!(
+        out_file,
+        "  (global $heap (mut i32) (i32.const {}))",
+        heap_start
+    )?;
+    writeln!(out_file)?;
+
+    for obj in data.iter() {
+        static_obj(out_file, obj, &addrs, &functions)?;
+    }
+    writeln!(out_file)?;
+
+    writeln!(out_file, "  (table {} funcref)", functions.len())?;
+    writeln!(out_file, "  (elem (i32.const 0) func")?;
+    for function in functions.iter() {
+        writeln!(out_file, "    ${}", function)?;
+    }
+    writeln!(out_file, "  )")?;
+
+    // methods for all objects
+    let mut variables: ScopedIndexMap<ObjectID, Location> = ScopedIndexMap::new();
+    code_methods(out_file, classes, &root_class_name, &mut variables)?;
+
+    // Called by main in the runtime
+    // Create a Main object and call its main method.
+    let main_class = classes.get(&TypeID::new_main()).unwrap();
+    writeln!(out_file)?;
+    writeln!(out_file, "  (func $cool_main")?;
+    writeln!(out_file, "    global.get $Main_protObj")?;
+    writeln!(out_file, "    call $Object.copy")?;
+    writeln!(out_file, "    call $Main_init")?;
+    writeln!(
+        out_file,
+        "    call {}",
+        method_function(main_class, &ObjectID::new_main())
+    )?;
+    writeln!(out_file, "    drop)")?;
+
+    // The module was opened by the runtime
+    writeln!(out_file, ")")?;
+
+    Ok(())
+}
+
+// Define the address of an object of static data and its contents
+fn static_obj(
+    out_file: &mut dyn io::Write,
+    obj: &StaticObj,
+    addrs: &IndexMap<String, u32>,
+    functions: &IndexSet<String>,
+) -> Result<(), io::Error> {
+    let addr = *addrs.get(&obj.label).unwrap();
+
+    let mut bytes: Vec<u8> = Vec::new();
+    for word in obj.words.iter() {
+        let val = match word {
+            Word::Val(val) => *val,
+            Word::Addr(label) => *addrs.get(label).unwrap(),
+            Word::Func(function) => functions.get_index_of(function).unwrap() as u32,
+        };
+
+        bytes.extend(val.to_le_bytes().iter());
+    }
+    bytes.extend(obj.chars.iter());
+
+    // Every byte that is not plainly printable is escaped
+    let mut text = String::new();
+    for b in bytes.iter() {
+        if (b.is_ascii_graphic() && *b != b'"' && *b != b'\\') || *b == b' ' {
+            text.push(*b as char);
+        } else {
+            text.push_str(&format!("\\{:02x}", b));
+        }
+    }
+
+    writeln!(
+        out_file,
+        "  (global ${} i32 (i32.const {}))",
+        obj.label, addr
+    )?;
+    writeln!(out_file, "  (data (i32.const {}) \"{}\")", addr, text)?;
+
+    Ok(())
+}
+
+// The functions of the table (in tag order)
+// The methods of the basic classes are in the runtime.
+fn function_table(
+    functions: &mut IndexSet<String>,
+    classes: &IndexMap<TypeID, Class>,
+    class_name: &TypeID,
+) {
+    let class: &Class = classes.get(class_name).unwrap();
+
+    functions.insert(format!("{}_init", class_name));
+
+    for method_name in class.methods.keys() {
+        functions.insert(format!("{}.{}", class_name, method_name));
+    }
+
+    for child_class_name in &class.child_names {
+        function_table(functions, classes, child_class_name);
+    }
+}
+
+// A string constant (its length is one of the int constants)
+// The characters are followed by a null and padded to a whole word.
+fn string_const(base_name: &str, string_tag: u32, val: &str, val_id: u32) -> StaticObj {
+    let mut chars = val.as_bytes().to_vec();
+    chars.resize((val.len() / WORD_SIZE as usize + 1) * WORD_SIZE as usize, 0);
+
+    let size = DEFAULT_OBJFIELDS + 1 + chars.len() as u32 / WORD_SIZE;
+
+    let mut obj = StaticObj::new(
+        format!("{}_{:08x}", base_name, val_id),
+        vec![
+            Word::Val(string_tag),
+            Word::Val(size),
+            Word::Addr("String_dispTab".to_string()),
+            Word::Addr(format!("int_const_{:08x}", val.len())),
+        ],
+    );
+    obj.chars = chars;
+
+    obj
+}
+
+// Write out all class names (in tag order)
+fn class_name_consts(
+    data: &mut Vec<StaticObj>,
+    classes: &IndexMap<TypeID, Class>,
+    class_name: &TypeID,
+    string_tag: u32,
+) {
+    let class: &Class = classes.get(class_name).unwrap();
+
+    data.push(string_const(
+        "class_name",
+        string_tag,
+        &class_name.to_string(),
+        class.tag(),
+    ));
+
+    for child_class_name in &class.child_names {
+        class_name_consts(data, classes, child_class_name, string_tag);
+    }
+}
+
+// Class Object tab
+fn class_obj_tab(words: &mut Vec<Word>, classes: &IndexMap<TypeID, Class>, class_name: &TypeID) {
+    let class: &Class = classes.get(class_name).unwrap();
+
+    words.push(Word::Addr(format!("{}_protObj", class_name)));
+    words.push(Word::Func(format!("{}_init", class_name)));
+
+    for child_class_name in &class.child_names {
+        class_obj_tab(words, classes, child_class_name);
+    }
+}
+
+// list each class's methods in order
+fn dispatch_table(
+    data: &mut Vec<StaticObj>,
+    classes: &IndexMap<TypeID, Class>,
+    class_name: &TypeID,
+) {
+    let class: &Class = classes.get(class_name).unwrap();
+
+    data.push(StaticObj::new(
+        format!("{}_dispTab", class_name),
+        class
+            .dispatch_table
+            .iter()
+            .map(|(class_name, method_name)| Word::Func(format!("{}.{}", class_name, method_name)))
+            .collect(),
+    ));
+
+    for child_class_name in &class.child_names {
+        dispatch_table(data, classes, child_class_name);
+    }
+}
+
+// Create a prototype object for each class
+// This object has the structure of an object in this class but its attributes are not set
+fn proto_obj(
+    data: &mut Vec<StaticObj>,
+    classes: &IndexMap<TypeID, Class>,
+    class_name: &TypeID,
+    mut size: u32,
+) {
+    let class: &Class = classes.get(class_name).unwrap();
+
+    size += class.attrs.len() as u32;
+
+    let mut words = vec![
+        Word::Val(class.tag()),
+        Word::Val(size),
+        Word::Addr(format!("{}_dispTab", class_name)),
+    ];
+    proto_attrs(&mut words, classes, class_name);
+
+    data.push(StaticObj::new(format!("{}_protObj", class_name), words));
+
+    for child_class_name in &class.child_names {
+        proto_obj(data, classes, child_class_name, size);
+    }
+}
+
+// Attributes for a prototype object
+fn proto_attrs(words: &mut Vec<Word>, classes: &IndexMap<TypeID, Class>, class_name: &TypeID) {
+    let class: &Class = classes.get(class_name).unwrap();
+
+    if let Some(parent_name) = &class.parent_name {
+        proto_attrs(words, classes, parent_name);
+    }
+
+    for attr in class.attrs.iter() {
+        words.push(if attr.type_decl.is_int() {
+            Word::Addr("int_const_00000000".to_string()) // 0
+        } else if attr.type_decl.is_bool() {
+            Word::Addr("bool_const0".to_string()) // False
+        } else if attr.type_decl.is_string() {
+            Word::Addr("str_const_00000000".to_string()) // Empty String ("")
+        } else {
+            Word::Val(0) // Void
+        });
+    }
+}
+
+// An instruction that pushes the initial value of a variable of a type
+pub fn default_value(type_decl: &TypeID) -> &'static str {
+    if type_decl.is_int() {
+        "global.get $int_const_00000000" // 0
+    } else if type_decl.is_bool() {
+        "global.get $bool_const0" // False
+    } else if type_decl.is_string() {
+        "global.get $str_const_00000000" // Empty String ("")
+    } else {
+        "i32.const 0" // Void
+    }
+}
@@ -0,0 +1,819 @@
+use std::io;
+
+use indexmap::IndexMap;
+
+use super::emit::*;
+use crate::ast::{ArithOpType, Branch, Class, CompType, Expression, ObjectID, TypeID};
+use crate::scoped_collections::ScopedIndexMap;
+
+use super::{Label, MemLocation};
+use super::{DISPTABLE_LOCATION, TAG_LOCATION};
+
+// Initialization method
+pub fn code_methods(
+    out_file: &mut dyn io::Write,
+    classes: &IndexMap<TypeID, Class>,
+    class_name: &TypeID,
+    object_locations: &mut ScopedIndexMap<ObjectID, MemLocation>, // Locations of variables
+) -> Result<(), io::Error> {
+    let class: &Class = classes.get(class_name).unwrap();
+
+    object_locations.enter_scope(); // Scope for attributes
+
+    // Add attributes to var list
+    for attr in class.attrs.iter() {
+        object_locations.insert(
+            attr.name.clone(),
+            MemLocation {
+                reg: Register::SELF,
+                offset: attr.self_offset,
+            },
+        );
+    }
+
+    writeln!(out_file, "{}_init:", class_name)?;
+
+    emit_method_start(out_file)?;
+
+    if let Some(parent_name) = &class.parent_name {
+        // Use parent's initialization first
+        emit_call(out_file, &format!("{}_init", parent_name))?;
+    }
+
+    for attr in class.attrs.iter() {
+        if let Expression::NoExpr = attr.init {
+        } else {
+            // Evaluate init expression
+            code_expr(out_file, &attr.init, class, classes, object_locations, -1)?;
+
+            // Move to location relative to object
+            emit_store_word(
+                out_file,
+                Register::ACC,
+                MemLocation {
+                    reg: Register::SELF,
+                    offset: attr.self_offset,
+                },
+            )?;
+        }
+    }
+
+    emit_move(out_file, Register::ACC, Register::SELF)?;
+
+    emit_method_end(out_file, 0)?;
+
+    if !class.basic {
+        // Code for methods of basic objects is in the runtime
+        // Code methods of class
+        for (method_name, method) in class.methods.iter() {
+            object_locations.enter_scope(); // Scope for arguments (formals)
+
+            // Set locations relative to frame pointer (FP) to values of arguments
+            let mut fp_offset: i16 = (method.formals.len() + 2) as i16;
+            for formal in method.formals.iter() {
+                object_locations.insert(
+                    formal.name.clone(),
+                    MemLocation {
+                        reg: Register::FP,
+                        offset: fp_offset,
+                    },
+                );
+
+                fp_offset -= 1;
+            }
+
+            // Label method
+            writeln!(out_file, "{}.{}:", class_name, method_name)?;
+
+            emit_method_start(out_file)?;
+
+            // Evaluate
+            code_expr(out_file, &method.expr, class, classes, object_locations, -1)?;
+
+            emit_method_end(out_file, method.formals.len() as i16)?;
+
+            object_locations.exit_scope();
+        }
+    }
+
+    for child_class_name in &class.child_names {
+        code_methods(out_file, classes, child_class_name, object_locations)?;
+    }
+
+    object_locations.exit_scope();
+
+    Ok(())
+}
+
+// Output code for an expression
+// The result is left in ACC. SELF and FP are preserved.
+fn code_expr(
+    out_file: &mut dyn io::Write,
+    expr: &Expression,
+    current_class: &Class,
+    classes: &IndexMap<TypeID, Class>,
+    object_locations: &mut ScopedIndexMap<ObjectID, MemLocation>,
+    fp_offset: i16,
+) -> Result<(), io::Error> {
+    match expr {
+        Expression::Assign { name, expr, .. } => {
+            // Set ACC to point to the expr's value
+            code_expr(
+                out_file,
+                expr,
+                current_class,
+                classes,
+                object_locations,
+                fp_offset,
+            )?;
+
+            emit_store_word(
+                out_file,
+                Register::ACC,
+                *object_locations.get(name).unwrap(),
+            )?;
+        }
+        Expression::Dispatch {
+            span,
+            expr,
+            type_name,
+            name,
+            args,
+            ..
+        } => {
+            // Push arguments onto stack
+            for arg in args.iter() {
+                code_expr(
+                    out_file,
+                    arg,
+                    current_class,
+                    classes,
+                    object_locations,
+                    fp_offset,
+                )?;
+
+                emit_push(out_file, Register::ACC)?;
+            }
+
+            // Evaluate expression and keep at ACC
+            code_expr(
+                out_file,
+                expr,
+                current_class,
+                classes,
+                object_locations,
+                fp_offset,
+            )?;
+
+            let l = Label::new();
+
+            // Dispatch on void
+            emit_testq(out_file, Register::ACC)?;
+            emit_jump_if(out_file, "nz", l)?;
+
+            // Pass filename and line number for crash
+            emit_load_string(out_file, Register::A1, "file_name", current_class.file_no)?;
+            emit_load_imm(out_file, Register::A2, span.line_no)?;
+            emit_c_call(out_file, "cool_dispatch_abort")?;
+
+            emit_label_def(out_file, l)?;
+
+            // Find the position of this method
+            let method_pos = match type_name {
+                Some(type_name) => {
+                    // Static dispatch
+
+                    emit_load_address(out_file, Register::T1, format!("{}_dispTab", type_name))?;
+
+                    classes.get(type_name).unwrap()
+                }
+
+                None => {
+                    // "Dynamic" dispatch
+
+                    emit_load_word(out_file, Register::T1, DISPTABLE_LOCATION)?;
+
+                    if expr.static_type().is_self_type() {
+                        current_class
+                    } else {
+                        classes.get(&expr.static_type()).unwrap()
+                    }
+                }
+            }
+            .method_name_to_pos
+            .get(name)
+            .unwrap();
+
+            // Call the method whose address is in the dispatch table
+            emit_call_indirect(
+                out_file,
+                MemLocation {
+                    reg: Register::T1,
+                    offset: *method_pos,
+                },
+            )?;
+        }
+        Expression::Cond {
+            pred,
+            then_expr,
+            else_expr,
+            ..
+        } => {
+            let label_else = Label::new();
+            let label_fi = Label::new();
+
+            // Evaluate predicate
+            code_expr(
+                out_file,
+                pred,
+                current_class,
+                classes,
+                object_locations,
+                fp_offset,
+            )?;
+
+            // Get boolean value returned
+            emit_fetch_int(out_file, Register::T1, Register::ACC)?;
+
+            // If false (zero) jump to else clause
+            emit_cmpl_imm(out_file, Register::T1, 0)?;
+            emit_jump_if(out_file, "e", label_else)?;
+
+            // Evaluate then_expr
+            code_expr(
+                out_file,
+                then_expr,
+                current_class,
+                classes,
+                object_locations,
+                fp_offset,
+            )?;
+
+            // Unconditional branch to end of if statement
+            emit_branch(out_file, label_fi)?;
+            emit_label_def(out_file, label_else)?;
+
+            // Evaluate else_expr
+            code_expr(
+                out_file,
+                else_expr,
+                current_class,
+                classes,
+                object_locations,
+                fp_offset,
+            )?;
+
+            emit_label_def(out_file, label_fi)?;
+        }
+        Expression::Loop { pred, body, .. } => {
+            let label_start = Label::new();
+            let label_end = Label::new();
+
+            emit_label_def(out_file, label_start)?;
+
+            // Evaluate predicate
+            code_expr(
+                out_file,
+                pred,
+                current_class,
+                classes,
+                object_locations,
+                fp_offset,
+            )?;
+
+            // Get boolean value returned
+            emit_fetch_int(out_file, Register::T1, Register::ACC)?;
+
+            // if false (zero) jump to end
+            emit_cmpl_imm(out_file, Register::T1, 0)?;
+            emit_jump_if(out_file, "e", label_end)?;
+
+            // Evaluate contents
+            code_expr(
+                out_file,
+                body,
+                current_class,
+                classes,
+                object_locations,
+                fp_offset,
+            )?;
+
+            // Unconditional branch to beginning
+            emit_branch(out_file, label_start)?;
+
+            emit_label_def(out_file, label_end)?;
+
+            // Set ACC to void
+            emit_load_imm(out_file, Register::ACC, 0)?;
+        }
+        Expression::TypeCase {
+            span,
+            expr,
+            branches,
+            ..
+        } => {
+            // Evaluate expression
+            code_expr(
+                out_file,
+                expr,
+                current_class,
+                classes,
+                object_locations,
+                fp_offset,
+            )?;
+
+            let label_notvoid = Label::new();
+            emit_testq(out_file, Register::ACC)?;
+            emit_jump_if(out_file, "nz", label_notvoid)?; // jump over fail if not void
+
+            // Pass filename and line number for crash
+            emit_load_string(out_file, Register::A1, "file_name", current_class.file_no)?;
+            emit_load_imm(out_file, Register::A2, span.line_no)?;
+            emit_c_call(out_file, "cool_case_abort2")?;
+
+            emit_label_def(out_file, label_notvoid)?;
+
+            // Load tag to T2
+            emit_load_word(out_file, Register::T2, TAG_LOCATION)?;
+
+            let label_end = Label::new();
+
+            // Code each branch
+            // Note that the order is already correct
+            for branch in branches.iter() {
+                code_branch(
+                    out_file,
+                    branch,
+                    current_class,
+                    classes,
+                    object_locations,
+                    fp_offset,
+                    label_end,
+                )?;
+            }
+
+            // If no branch succeeds
+            emit_move(out_file, Register::A1, Register::ACC)?;
+            emit_c_call(out_file, "cool_case_abort")?;
+
+            emit_label_def(out_file, label_end)?;
+        }
+        Expression::Block { body, .. } => {
+            // Evaluate each expression in body
+            for expr in body.iter() {
+                code_expr(
+                    out_file,
+                    expr,
+                    current_class,
+                    classes,
+                    object_locations,
+                    fp_offset,
+                )?;
+            }
+        }
+        Expression::Let {
+            identifier,
+            type_decl,
+            init,
+            body,
+            ..
+        } => {
+            object_locations.enter_scope(); // Scope for new variable
+
+            // Evaluate init expression
+
+            if let Expression::NoExpr = **init {
+                if type_decl.is_int() {
+                    emit_load_int(out_file, Register::ACC, 0)?; // Zero
+                } else if type_decl.is_bool() {
+                    emit_load_bool(out_file, Register::ACC, false)?; // False
+                } else if type_decl.is_string() {
+                    emit_load_string(out_file, Register::ACC, "str_const", 0)?; // Empty String ("")
+                } else {
+                    emit_load_imm(out_file, Register::ACC, 0)?; // Void
+                }
+            } else {
+                code_expr(
+                    out_file,
+                    init,
+                    current_class,
+                    classes,
+                    object_locations,
+                    fp_offset,
+                )?;
+            }
+
+            // Push to stack and save location relative to FP
+            emit_push(out_file, Register::ACC)?;
+            object_locations.insert(
+                identifier.clone(),
+                MemLocation {
+                    reg: Register::FP,
+                    offset: fp_offset,
+                },
+            );
+
+            // Evaluate body
+            code_expr(
+                out_file,
+                body,
+                current_class,
+                classes,
+                object_locations,
+                fp_offset - 1,
+            )?;
+
+            // Pop the result of init to nowhere
+            emit_drop(out_file)?;
+
+            object_locations.exit_scope();
+        }
+        Expression::ArithOp {
+            expr_lhs,
+            expr_rhs,
+            arith_op_type,
+            ..
+        } => {
+            // Push LHS to stack
+            code_expr(
+                out_file,
+                expr_lhs,
+                current_class,
+                classes,
+                object_locations,
+                fp_offset,
+            )?;
+
+            emit_push(out_file, Register::ACC)?;
+
+            // Evaluate RHS
+            code_expr(
+                out_file,
+                expr_rhs,
+                current_class,
+                classes,
+                object_locations,
+                fp_offset,
+            )?;
+
+            // New integer (same value as RHS)
+            emit_call(out_file, "Object.copy")?;
+
+            // Get the RHS value and put it in T1
+            emit_fetch_int(out_file, Register::T1, Register::ACC)?;
+
+            // Get the LHS from the top of the stack and put its value in T2
+            emit_load_word(out_file, Register::T2, STACK_TOP_LOCATION)?;
+            emit_fetch_int(out_file, Register::T2, Register::T2)?;
+
+            // Execute the operation and put to result in T2
+            match arith_op_type {
+                ArithOpType::Add => {
+                    emit_arith(out_file, "addl", Register::T2, Register::T1)?;
+                    emit_jump_overflow(out_file)?;
+                }
+                ArithOpType::Sub => {
+                    emit_arith(out_file, "subl", Register::T2, Register::T1)?;
+                    emit_jump_overflow(out_file)?;
+                }
+                ArithOpType::Mul => {
+                    // Overflow wraps around
+                    emit_arith(out_file, "imull", Register::T2, Register::T1)?;
+                }
+                ArithOpType::Div => {
+                    let label_divide = Label::new();
+                    let label_done = Label::new();
+
+                    emit_cmpl_imm(out_file, Register::T1, 0)?;
+                    emit_jump_if(out_file, "e", "_division_by_zero")?;
+
+                    // Dividing the smallest Int by -1 wraps around (idiv would fault)
+                    emit_cmpl_imm(out_file, Register::T1, -1)?;
+                    emit_jump_if(out_file, "ne", label_divide)?;
+                    emit_negl(out_file, Register::T2)?;
+                    emit_branch(out_file, label_done)?;
+
+                    // idiv divides ACC, so the new integer is kept in A1
+                    emit_label_def(out_file, label_divide)?;
+                    emit_move(out_file, Register::A1, Register::ACC)?;
+                    emit_move(out_file, Register::ACC, Register::T2)?;
+                    emit_idivl(out_file, Register::T1)?;
+                    emit_move(out_file, Register::T2, Register::ACC)?;
+                    emit_move(out_file, Register::ACC, Register::A1)?;
+
+                    emit_label_def(out_file, label_done)?;
+                }
+            }
+
+            // Put the result from T2 into the new integer
+            emit_store_int(out_file, Register::T2, Register::ACC)?;
+
+            // Pop LHS to nowhere
+            emit_drop(out_file)?;
+        }
+        Expression::Neg { expr, .. } => {
+            // Evaluate
+            code_expr(
+                out_file,
+                expr,
+                current_class,
+                classes,
+                object_locations,
+                fp_offset,
+            )?;
+
+            // New integer (same value)
+            emit_call(out_file, "Object.copy")?;
+
+            // Get value
+            emit_fetch_int(out_file, Register::T1, Register::ACC)?;
+
+            // Negate current value
+            emit_negl(out_file, Register::T1)?;
+            emit_jump_overflow(out_file)?;
+
+            // Store value
+            emit_store_int(out_file, Register::T1, Register::ACC)?;
+        }
+        Expression::Comp {
+            expr_lhs,
+            expr_rhs,
+            comp_type,
+            ..
+        } => {
+            let label = Label::new();
+
+            // Push LHS to stack
+            code_expr(
+                out_file,
+                expr_lhs,
+                current_class,
+                classes,
+                object_locations,
+                fp_offset,
+            )?;
+
+            emit_push(out_file, Register::ACC)?;
+
+            // Evaluate RHS
+            code_expr(
+                out_file,
+                expr_rhs,
+                current_class,
+                classes,
+                object_locations,
+                fp_offset,
+            )?;
+
+            // Get the RHS value and put it in T1
+            emit_fetch_int(out_file, Register::T1, Register::ACC)?;
+
+            // Get the LHS from the top of the stack and put its value in T2
+            emit_pop(out_file, Register::T2)?;
+            emit_fetch_int(out_file, Register::T2, Register::T2)?;
+
+            // Point ACC to true constant (lea does not change the flags)
+            emit_cmpl(out_file, Register::T2, Register::T1)?;
+            emit_load_bool(out_file, Register::ACC, true)?;
+
+            // Execute the comparison
+            // If true branch to end
+            match comp_type {
+                CompType::LT => {
+                    emit_jump_if(out_file, "l", label)?;
+                }
+                CompType::LEq => {
+                    emit_jump_if(out_file, "le", label)?;
+                }
+            }
+
+            // Point ACC to false constant
+            emit_load_bool(out_file, Register::ACC, false)?;
+
+            emit_label_def(out_file, label)?;
+        }
+        Expression::Eq {
+            expr_lhs, expr_rhs, ..
+        } => {
+            let label_true = Label::new();
+            let label_end = Label::new();
+
+            // Push LHS to stack
+            code_expr(
+                out_file,
+                expr_lhs,
+                current_class,
+                classes,
+                object_locations,
+                fp_offset,
+            )?;
+
+            emit_push(out_file, Register::ACC)?;
+
+            // Evaluate RHS
+            code_expr(
+                out_file,
+                expr_rhs,
+                current_class,
+                classes,
+                object_locations,
+                fp_offset,
+            )?;
+
+            // RHS to A2
+            emit_move(out_file, Register::A2, Register::ACC)?;
+
+            // LHS to A1
+            emit_pop(out_file, Register::A1)?;
+
+            // The same object is always equal
+            emit_cmpq(out_file, Register::A1, Register::A2)?;
+            emit_jump_if(out_file, "e", label_true)?;
+
+            // Test for structural equality
+            emit_c_call(out_file, "cool_equality_test")?;
+            emit_testq(out_file, Register::ACC)?;
+            emit_jump_if(out_file, "nz", label_true)?;
+
+            emit_load_bool(out_file, Register::ACC, false)?;
+            emit_branch(out_file, label_end)?;
+
+            emit_label_def(out_file, label_true)?;
+            emit_load_bool(out_file, Register::ACC, true)?;
+            emit_label_def(out_file, label_end)?;
+        }
+        Expression::Not { expr, .. } => {
+            // Evaluate expression
+            code_expr(
+                out_file,
+                expr,
+                current_class,
+                classes,
+                object_locations,
+                fp_offset,
+            )?;
+
+            let label = Label::new();
+            emit_fetch_int(out_file, Register::T1, Register::ACC)?;
+            emit_load_bool(out_file, Register::ACC, true)?;
+
+            // if value is zero (false) jump to end
+            emit_cmpl_imm(out_file, Register::T1, 0)?;
+            emit_jump_if(out_file, "e", label)?;
+            emit_load_bool(out_file, Register::ACC, false)?;
+            emit_label_def(out_file, label)?;
+        }
+        Expression::IntConst { val, .. } => {
+            emit_load_int(out_file, Register::ACC, *val)?;
+        }
+        Expression::BoolConst { val, .. } => {
+            emit_load_bool(out_file, Register::ACC, *val)?;
+        }
+        Expression::StringConst { val_id, .. } => {
+            emit_load_string(out_file, Register::ACC, "str_const", *val_id)?;
+        }
+        Expression::New { type_name, .. } => {
+            if type_name.is_self_type() {
+                // address of class_objTab to T1
+                emit_load_address(out_file, Register::T1, "class_objTab".to_string())?;
+
+                // set T2 to the tag of self's class
+                emit_load_word(
+                    out_file,
+                    Register::T2,
+                    MemLocation {
+                        reg: Register::SELF,
+                        offset: 0,
+                    },
+                )?;
+
+                // Multiply tag by 16 (2 WORDs)
+                emit_shlq(out_file, Register::T2, 4)?;
+
+                // Get prototype and init method
+                emit_addq(out_file, Register::T1, Register::T2)?;
+                emit_push(out_file, Register::T1)?;
+
+                // Copy prototype
+                emit_load_word(
+                    out_file,
+                    Register::ACC,
+                    MemLocation {
+                        reg: Register::T1,
+                        offset: 0,
+                    },
+                )?;
+                emit_call(out_file, "Object.copy")?;
+
+                emit_pop(out_file, Register::T1)?;
+
+                // Call init method
+                emit_call_indirect(
+                    out_file,
+                    MemLocation {
+                        reg: Register::T1,
+                        offset: 1,
+                    },
+                )?;
+            } else {
+                // Copy prototype object
+                emit_load_address(out_file, Register::ACC, format!("{}_protObj", type_name))?;
+                emit_call(out_file, "Object.copy")?;
+
+                // Initialize object
+                emit_call(out_file, &format!("{}_init", type_name))?;
+            }
+        }
+        Expression::IsVoid { expr, .. } => {
+            let label = Label::new();
+
+            code_expr(
+                out_file,
+                expr,
+                current_class,
+                classes,
+                object_locations,
+                fp_offset,
+            )?;
+
+            emit_move(out_file, Register::T1, Register::ACC)?;
+            emit_load_bool(out_file, Register::ACC, true)?;
+
+            // Test if equal to zero (Void)
+            emit_testq(out_file, Register::T1)?;
+            emit_jump_if(out_file, "z", label)?;
+
+            emit_load_bool(out_file, Register::ACC, false)?;
+            emit_label_def(out_file, label)?;
+        }
+        Expression::NoExpr => {} // Never used
+        Expression::VarByName { name, .. } => {
+            if name.is_self() {
+                // self always refers to SELF register
+                emit_move(out_file, Register::ACC, Register::SELF)?;
+            } else {
+                // Lookup location
+
+                emit_load_word(
+                    out_file,
+                    Register::ACC,
+                    *object_locations.get(name).unwrap(),
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Code one branch of a TypeCase
+fn code_branch(
+    out_file: &mut dyn io::Write,
+    Branch {
+        name, expr, family, ..
+    }: &Branch,
+    current_class: &Class,
+    classes: &IndexMap<TypeID, Class>,
+    object_locations: &mut ScopedIndexMap<ObjectID, MemLocation>,
+    fp_offset: i16,
+    label_end: Label,
+) -> Result<(), io::Error> {
+    let label_end_branch = Label::new();
+
+    // If tag is outside family, this branch doesn't match
+    emit_cmpq_imm(out_file, Register::T2, family.start)?;
+    emit_jump_if(out_file, "l", label_end_branch)?;
+    emit_cmpq_imm(out_file, Register::T2, family.end)?;
+    emit_jump_if(out_file, "ge", label_end_branch)?;
+
+    object_locations.enter_scope(); // Scope for new variable
+
+    // Save expression to name
+    emit_push(out_file, Register::ACC)?;
+    object_locations.insert(
+        name.clone(),
+        MemLocation {
+            reg: Register::FP,
+            offset: fp_offset,
+        },
+    );
+
+    // Evaluate expression
+    code_expr(
+        out_file,
+        expr,
+        current_class,
+        classes,
+        object_locations,
+        fp_offset - 1,
+    )?;
+
+    emit_drop(out_file)?;
+
+    object_locations.exit_scope();
+
+    emit_branch(out_file, label_end)?;
+    emit_label_def(out_file, label_end_branch)?;
+
+    Ok(())
+}
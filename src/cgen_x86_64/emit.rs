@@ -0,0 +1,427 @@
+use super::{Label, MemLocation};
+use super::{DEFAULT_OBJFIELDS, LG_WORD_SIZE, WORD_SIZE};
+
+use std::{fmt, io};
+
+// This module contains functions used to output x86-64 code (GNU as, AT&T syntax).
+
+pub const STACK_TOP_LOCATION: MemLocation = MemLocation {
+    reg: Register::SP,
+    offset: 0,
+};
+
+// Registers used by the assembly code
+// SELF and FP are callee-saved in the System V ABI, so calls to the runtime keep them.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Register {
+    ACC,  // %rax
+    A1,   // %rdi (first argument of a C function)
+    A2,   // %rsi (second argument of a C function)
+    SELF, // %rbx
+    T1,   // %rcx
+    T2,   // %rdx
+    SP,   // %rsp
+    FP,   // %rbp
+    SAVE, // %r12 (the stack pointer while a C function is called)
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Register::ACC => write!(f, "%rax"),
+            Register::A1 => write!(f, "%rdi"),
+            Register::A2 => write!(f, "%rsi"),
+            Register::SELF => write!(f, "%rbx"),
+            Register::T1 => write!(f, "%rcx"),
+            Register::T2 => write!(f, "%rdx"),
+            Register::SP => write!(f, "%rsp"),
+            Register::FP => write!(f, "%rbp"),
+            Register::SAVE => write!(f, "%r12"),
+        }
+    }
+}
+
+impl Register {
+    // The low 32 bits of the register (Int values are 32 bits)
+    fn low(self) -> &'static str {
+        match self {
+            Register::ACC => "%eax",
+            Register::A1 => "%edi",
+            Register::A2 => "%esi",
+            Register::SELF => "%ebx",
+            Register::T1 => "%ecx",
+            Register::T2 => "%edx",
+            Register::SP => "%esp",
+            Register::FP => "%ebp",
+            Register::SAVE => "%r12d",
+        }
+    }
+}
+
+impl fmt::Display for MemLocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}({})", WORD_SIZE * self.offset, self.reg)
+    }
+}
+
+pub fn emit_global(out_file: &mut dyn io::Write, sym: &str) -> Result<(), io::Error> {
+    writeln!(out_file, "\t.globl {}", sym)
+}
+
+pub fn emit_align(out_file: &mut dyn io::Write, n: i16) -> Result<(), io::Error> {
+    writeln!(out_file, "\t.p2align {}", n)
+}
+
+#[allow(clippy::wrong_self_convention)]
+pub trait AsWord {
+    fn as_word(self) -> String;
+}
+
+impl AsWord for u32 {
+    fn as_word(self) -> String {
+        format!("{:#010x}", self)
+    }
+}
+
+impl AsWord for String {
+    fn as_word(self) -> String {
+        self
+    }
+}
+
+impl AsWord for &str {
+    fn as_word(self) -> String {
+        self.to_string()
+    }
+}
+
+// A word is 8 bytes (a pointer)
+pub fn emit_word(out_file: &mut dyn io::Write, w: impl AsWord) -> Result<(), io::Error> {
+    writeln!(out_file, "\t.quad {}", w.as_word())
+}
+
+pub fn emit_string_const(
+    out_file: &mut dyn io::Write,
+    base_name: &str,
+    string_tag: u32,
+    val: &str,
+    val_id: u32,
+) -> Result<(), io::Error> {
+    writeln!(out_file, "{}_{:08x}:", base_name, val_id)?;
+    emit_word(out_file, string_tag)?;
+    emit_word(out_file, (val.len() as u32) / (WORD_SIZE as u32) + 5)?;
+    emit_word(out_file, "String_dispTab")?;
+    emit_word(out_file, format!("int_const_{:08x}", val.len()))?;
+
+    write!(out_file, "\t.byte")?;
+    for b in val.as_bytes().iter() {
+        write!(out_file, " {:#04x},", b)?;
+    }
+    writeln!(out_file, " 0x00")?; // Strings are null terminated
+
+    emit_align(out_file, LG_WORD_SIZE)?;
+
+    Ok(())
+}
+
+pub fn emit_int_const(
+    out_file: &mut dyn io::Write,
+    int_tag: u32,
+    val: u32,
+) -> Result<(), io::Error> {
+    writeln!(out_file, "int_const_{:08x}:", val)?;
+    emit_word(out_file, int_tag)?;
+    emit_word(out_file, 4)?; // Length
+    emit_word(out_file, "Int_dispTab")?;
+    emit_word(out_file, val)?;
+
+    Ok(())
+}
+
+pub fn emit_bool_const(
+    out_file: &mut dyn io::Write,
+    bool_tag: u32,
+    val: bool,
+) -> Result<(), io::Error> {
+    if val {
+        writeln!(out_file, "bool_const1:")?;
+    } else {
+        writeln!(out_file, "bool_const0:")?;
+    }
+
+    emit_word(out_file, bool_tag)?;
+    emit_word(out_file, 4)?; // Length
+    emit_word(out_file, "Bool_dispTab")?;
+
+    if val {
+        emit_word(out_file, 1)?;
+    } else {
+        emit_word(out_file, 0)?;
+    }
+
+    Ok(())
+}
+
+pub fn emit_call(out_file: &mut dyn io::Write, target: &str) -> Result<(), io::Error> {
+    writeln!(out_file, "\tcall {}", target)
+}
+
+// Call the address stored at src
+pub fn emit_call_indirect(out_file: &mut dyn io::Write, src: MemLocation) -> Result<(), io::Error> {
+    writeln!(out_file, "\tcall *{}", src)
+}
+
+// Call a function of the runtime (written in C)
+// The System V ABI needs the stack to be aligned to 16 bytes.
+pub fn emit_c_call(out_file: &mut dyn io::Write, target: &str) -> Result<(), io::Error> {
+    emit_move(out_file, Register::SAVE, Register::SP)?;
+    writeln!(out_file, "\tandq $-16, {}", Register::SP)?;
+    emit_call(out_file, target)?;
+    emit_move(out_file, Register::SP, Register::SAVE)?;
+
+    Ok(())
+}
+
+// Return and remove arg_count words of arguments from the stack
+pub fn emit_return(out_file: &mut dyn io::Write, arg_count: i16) -> Result<(), io::Error> {
+    if arg_count == 0 {
+        writeln!(out_file, "\tret")
+    } else {
+        writeln!(out_file, "\tret ${}", arg_count * WORD_SIZE)
+    }
+}
+
+pub fn emit_store_word(
+    out_file: &mut dyn io::Write,
+    src: Register,
+    dest: MemLocation,
+) -> Result<(), io::Error> {
+    writeln!(out_file, "\tmovq {}, {}", src, dest)
+}
+
+pub fn emit_load_word(
+    out_file: &mut dyn io::Write,
+    dest: Register,
+    src: MemLocation,
+) -> Result<(), io::Error> {
+    writeln!(out_file, "\tmovq {}, {}", src, dest)
+}
+
+pub fn emit_load_imm(
+    out_file: &mut dyn io::Write,
+    dest: Register,
+    imm: u32,
+) -> Result<(), io::Error> {
+    writeln!(out_file, "\tmovq ${}, {}", imm, dest)
+}
+
+// Addresses are relative to the instruction so that programs can be position independent
+pub fn emit_load_address(
+    out_file: &mut dyn io::Write,
+    dest: Register,
+    addr: String,
+) -> Result<(), io::Error> {
+    writeln!(out_file, "\tleaq {}(%rip), {}", addr, dest)
+}
+
+pub fn emit_move(
+    out_file: &mut dyn io::Write,
+    dest: Register,
+    src: Register,
+) -> Result<(), io::Error> {
+    writeln!(out_file, "\tmovq {}, {}", src, dest)
+}
+
+pub fn emit_addq(
+    out_file: &mut dyn io::Write,
+    dest: Register,
+    src: Register,
+) -> Result<(), io::Error> {
+    writeln!(out_file, "\taddq {}, {}", src, dest)
+}
+
+pub fn emit_shlq(out_file: &mut dyn io::Write, dest: Register, shift: u8) -> Result<(), io::Error> {
+    writeln!(out_file, "\tshlq ${}, {}", shift, dest)
+}
+
+// 32-bit arithmetic on Int values (dest = dest op src)
+// Signed overflow sets the overflow flag (see emit_jump_overflow).
+pub fn emit_arith(
+    out_file: &mut dyn io::Write,
+    op: &str,
+    dest: Register,
+    src: Register,
+) -> Result<(), io::Error> {
+    writeln!(out_file, "\t{} {}, {}", op, src.low(), dest.low())
+}
+
+pub fn emit_negl(out_file: &mut dyn io::Write, dest: Register) -> Result<(), io::Error> {
+    writeln!(out_file, "\tnegl {}", dest.low())
+}
+
+// Divide ACC (32 bits) by src and put the quotient in ACC
+// T2 is overwritten.
+pub fn emit_idivl(out_file: &mut dyn io::Write, src: Register) -> Result<(), io::Error> {
+    writeln!(out_file, "\tcltd")?;
+    writeln!(out_file, "\tidivl {}", src.low())
+}
+
+// Compare the 32-bit values of two registers
+pub fn emit_cmpl(
+    out_file: &mut dyn io::Write,
+    src1: Register,
+    src2: Register,
+) -> Result<(), io::Error> {
+    writeln!(out_file, "\tcmpl {}, {}", src2.low(), src1.low())
+}
+
+pub fn emit_cmpl_imm(
+    out_file: &mut dyn io::Write,
+    src: Register,
+    imm: i32,
+) -> Result<(), io::Error> {
+    writeln!(out_file, "\tcmpl ${}, {}", imm, src.low())
+}
+
+pub fn emit_cmpq(
+    out_file: &mut dyn io::Write,
+    src1: Register,
+    src2: Register,
+) -> Result<(), io::Error> {
+    writeln!(out_file, "\tcmpq {}, {}", src2, src1)
+}
+
+pub fn emit_cmpq_imm(
+    out_file: &mut dyn io::Write,
+    src: Register,
+    imm: u32,
+) -> Result<(), io::Error> {
+    writeln!(out_file, "\tcmpq ${}, {}", imm, src)
+}
+
+pub fn emit_testq(out_file: &mut dyn io::Write, src: Register) -> Result<(), io::Error> {
+    writeln!(out_file, "\ttestq {}, {}", src, src)
+}
+
+pub fn emit_branch(out_file: &mut dyn io::Write, label: Label) -> Result<(), io::Error> {
+    writeln!(out_file, "\tjmp {}", label)
+}
+
+// Conditional jump after a comparison (cond is e (equal), ne, l (less), le, ge, z or nz)
+// The target may be a Label or the name of a routine (see runtime_stubs)
+pub fn emit_jump_if(
+    out_file: &mut dyn io::Write,
+    cond: &str,
+    target: impl fmt::Display,
+) -> Result<(), io::Error> {
+    writeln!(out_file, "\tj{} {}", cond, target)
+}
+
+// Stop the program if the last arithmetic instruction overflowed
+pub fn emit_jump_overflow(out_file: &mut dyn io::Write) -> Result<(), io::Error> {
+    emit_jump_if(out_file, "o", "_arithmetic_overflow")
+}
+
+pub fn emit_label_def(out_file: &mut dyn io::Write, label: Label) -> Result<(), io::Error> {
+    writeln!(out_file, "{}:", label)
+}
+
+// The location of the value of the Int (or Bool) object pointed to by reg
+pub fn int_location(reg: Register) -> MemLocation {
+    MemLocation {
+        reg,
+        offset: DEFAULT_OBJFIELDS,
+    }
+}
+
+// Instructions to load an Int type's value pointed to by src into dest
+pub fn emit_fetch_int(
+    out_file: &mut dyn io::Write,
+    dest: Register,
+    src: Register,
+) -> Result<(), io::Error> {
+    writeln!(out_file, "\tmovl {}, {}", int_location(src), dest.low())
+}
+
+// Instructions to load src's value in to the Int object pointed to by dest
+pub fn emit_store_int(
+    out_file: &mut dyn io::Write,
+    src: Register,
+    dest: Register,
+) -> Result<(), io::Error> {
+    writeln!(out_file, "\tmovl {}, {}", src.low(), int_location(dest))
+}
+
+// Add the value in src to the stack
+pub fn emit_push(out_file: &mut dyn io::Write, src: Register) -> Result<(), io::Error> {
+    writeln!(out_file, "\tpushq {}", src)
+}
+
+// Remove the top value on the stack and set dest to that value
+pub fn emit_pop(out_file: &mut dyn io::Write, dest: Register) -> Result<(), io::Error> {
+    writeln!(out_file, "\tpopq {}", dest)
+}
+
+// Remove the top value on the stack
+pub fn emit_drop(out_file: &mut dyn io::Write) -> Result<(), io::Error> {
+    writeln!(out_file, "\taddq ${}, {}", WORD_SIZE, Register::SP)
+}
+
+// Set dest to point to the specified Bool constant
+pub fn emit_load_bool(
+    out_file: &mut dyn io::Write,
+    dest: Register,
+    val: bool,
+) -> Result<(), io::Error> {
+    if val {
+        emit_load_address(out_file, dest, "bool_const1".to_string())?;
+    } else {
+        emit_load_address(out_file, dest, "bool_const0".to_string())?;
+    }
+
+    Ok(())
+}
+
+// Set dest to point to the specified Int constant
+pub fn emit_load_int(
+    out_file: &mut dyn io::Write,
+    dest: Register,
+    val: u32,
+) -> Result<(), io::Error> {
+    emit_load_address(out_file, dest, format!("int_const_{:08x}", val))
+}
+
+// Set dest to point to the specified String constant
+pub fn emit_load_string(
+    out_file: &mut dyn io::Write,
+    dest: Register,
+    base_name: &str,
+    val_id: u32,
+) -> Result<(), io::Error> {
+    emit_load_address(out_file, dest, format!("{}_{:08x}", base_name, val_id))
+}
+
+// The frame is the same as that of the MIPS code:
+// arguments, return address, saved FP, saved SELF (where FP points) and then locals.
+pub fn emit_method_start(out_file: &mut dyn io::Write) -> Result<(), io::Error> {
+    emit_push(out_file, Register::FP)?;
+    emit_push(out_file, Register::SELF)?;
+
+    // Set FP to start of new frame
+    emit_move(out_file, Register::FP, Register::SP)?;
+
+    // value passed in ACC is new SELF
+    emit_move(out_file, Register::SELF, Register::ACC)?;
+
+    Ok(())
+}
+
+// Undo emit_method_start and remove all arguments that were pushed onto stack
+pub fn emit_method_end(out_file: &mut dyn io::Write, formal_count: i16) -> Result<(), io::Error> {
+    emit_pop(out_file, Register::SELF)?;
+    emit_pop(out_file, Register::FP)?;
+    emit_return(out_file, formal_count)?;
+
+    Ok(())
+}
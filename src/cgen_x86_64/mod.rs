@@ -0,0 +1,329 @@
+// A code generator for x86-64 (System V, GNU assembler syntax)
+// The object layout and the calling convention are those of the MIPS code (see cgen)
+// with 8-byte words. The basic classes are implemented by a C runtime
+// (runtime/x86_64.c), which the program is linked with:
+//     cc name.s runtime/x86_64.c -o name
+
+mod code;
+mod emit;
+
+use std::io;
+
+use crate::ast::{Class, ObjectID, TypeID};
+use crate::cgen::Label;
+use crate::scoped_collections::ScopedIndexMap;
+
+use indexmap::IndexMap;
+
+use self::code::code_methods;
+use self::emit::*;
+
+const WORD_SIZE: i16 = 8;
+const LG_WORD_SIZE: i16 = 3;
+
+const DEFAULT_OBJFIELDS: i16 = 3;
+
+const TAG_LOCATION: MemLocation = MemLocation {
+    reg: Register::ACC,
+    offset: 0,
+};
+
+const DISPTABLE_LOCATION: MemLocation = MemLocation {
+    reg: Register::ACC,
+    offset: 2,
+};
+
+#[derive(Copy, Clone)]
+pub struct MemLocation {
+    pub reg: Register,
+    pub offset: i16,
+}
+
+// The methods of the basic classes that are implemented in C
+// Each is called through a stub that moves self and the arguments to the
+// registers of the System V convention.
+const RUNTIME_METHODS: [(&str, i16); 10] = [
+    ("Object.abort", 0),
+    ("Object.type_name", 0),
+    ("Object.copy", 0),
+    ("IO.out_string", 1),
+    ("IO.out_int", 1),
+    ("IO.in_string", 0),
+    ("IO.in_int", 0),
+    ("String.length", 0),
+    ("String.concat", 1),
+    ("String.substr", 2),
+];
+
+// Translate an IO error if necessary
+pub fn cgen(
+    out_file: &mut dyn io::Write,
+    in_file_names: &[String],
+    classes: &IndexMap<TypeID, Class>,
+    int_table: &[u32],
+    str_table: &[String],
+) -> Result<(), String> {
+    match write_code(out_file, in_file_names, classes, int_table, str_table) {
+        Err(s) => Err(s.to_string()),
+        Ok(()) => Ok(()),
+    }
+}
+
+// output x86-64 assembly for entire program to out_file
+fn write_code(
+    out_file: &mut dyn io::Write,
+    in_file_names: &[String],
+    classes: &IndexMap<TypeID, Class>,
+    int_table: &[u32],
+    str_table: &[String],
+) -> Result<(), io::Error> {
+    let root_class_name: TypeID = TypeID::root_class_name();
+
+    let int_tag = classes.get(&TypeID::new_int()).unwrap().tag();
+    let bool_tag = classes.get(&TypeID::new_bool()).unwrap().tag();
+    let string_tag = classes.get(&TypeID::new_string()).unwrap().tag();
+
+    writeln!(out_file, "\t.data")?;
+    emit_align(out_file, LG_WORD_SIZE)?;
+
+    emit_global(out_file, "class_nameTab")?; // allow the runtime to access these addresses
+    emit_global(out_file, "Int_protObj")?;
+    emit_global(out_file, "String_protObj")?;
+    emit_global(out_file, "_int_tag")?;
+    emit_global(out_file, "_bool_tag")?;
+    emit_global(out_file, "_string_tag")?;
+
+    writeln!(out_file, "_int_tag:")?;
+    emit_word(out_file, int_tag)?;
+
+    writeln!(out_file, "_bool_tag:")?;
+    emit_word(out_file, bool_tag)?;
+
+    writeln!(out_file, "_string_tag:")?;
+    emit_word(out_file, string_tag)?;
+
+    // Write out all string constants
+    for (val, val_id) in str_table.iter().zip(0..) {
+        emit_string_const(out_file, "str_const", string_tag, val, val_id)?;
+    }
+
+    // Write out all file names as string constants
+    emit_string_const(out_file, "file_name", string_tag, "", 0)?;
+    for (val, val_id) in in_file_names.iter().zip(1..) {
+        emit_string_const(out_file, "file_name", string_tag, val, val_id)?;
+    }
+
+    // Write out all class names (in tag order)
+    class_name_consts(out_file, classes, &root_class_name, string_tag)?;
+
+    // Write integer constants
+    for val in int_table.iter() {
+        emit_int_const(out_file, int_tag, *val)?;
+    }
+
+    // Write bool constants
+    emit_bool_const(out_file, bool_tag, false)?;
+    emit_bool_const(out_file, bool_tag, true)?;
+
+    // Write class name table (in tag order)
+    writeln!(out_file, "class_nameTab:")?;
+    for tag in 0..classes.len() {
+        emit_word(out_file, format!("class_name_{:08x}", tag))?;
+    }
+
+    writeln!(out_file, "class_objTab:")?;
+    class_obj_tab(out_file, classes, &root_class_name)?;
+
+    dispatch_table(out_file, classes, &root_class_name)?;
+
+    proto_obj(out_file, classes, &root_class_name, DEFAULT_OBJFIELDS)?;
+
+    writeln!(out_file, "\t.text")?;
+
+    runtime_stubs(out_file)?;
+
+    // methods for all objects
+    let mut object_locations: ScopedIndexMap<ObjectID, MemLocation> = ScopedIndexMap::new();
+    code_methods(out_file, classes, &root_class_name, &mut object_locations)?;
+
+    // The program must not need an executable stack
+    writeln!(out_file, "\t.section .note.GNU-stack,\"\",@progbits")?;
+
+    Ok(())
+}
+
+// Code that connects the program to the runtime
+fn runtime_stubs(out_file: &mut dyn io::Write) -> Result<(), io::Error> {
+    // Called by main() in the runtime
+    // Create a Main object and call its main method.
+    emit_global(out_file, "cool_main")?;
+    writeln!(out_file, "cool_main:")?;
+
+    // Keep the registers that C code expects to be preserved
+    emit_push(out_file, Register::SELF)?;
+    emit_push(out_file, Register::FP)?;
+    emit_push(out_file, Register::SAVE)?;
+
+    emit_load_address(out_file, Register::ACC, "Main_protObj".to_string())?;
+    emit_call(out_file, "Object.copy")?;
+    emit_call(out_file, "Main_init")?;
+    emit_call(out_file, "Main.main")?;
+
+    emit_pop(out_file, Register::SAVE)?;
+    emit_pop(out_file, Register::FP)?;
+    emit_pop(out_file, Register::SELF)?;
+    emit_return(out_file, 0)?;
+
+    // Pass self and the arguments (the last is on top of the stack) to the C function
+    for (method, arg_count) in RUNTIME_METHODS.iter() {
+        writeln!(out_file, "{}:", method)?;
+
+        emit_move(out_file, Register::A1, Register::ACC)?;
+
+        for (arg, reg) in [Register::A2, Register::T2]
+            .iter()
+            .take(*arg_count as usize)
+            .enumerate()
+        {
+            // Above the return address
+            let offset = *arg_count - arg as i16;
+            emit_load_word(
+                out_file,
+                *reg,
+                MemLocation {
+                    reg: Register::SP,
+                    offset,
+                },
+            )?;
+        }
+
+        emit_c_call(out_file, &method.replace('.', "_"))?;
+        emit_return(out_file, *arg_count)?;
+    }
+
+    // Int operations that fail
+    writeln!(out_file, "_arithmetic_overflow:")?;
+    emit_c_call(out_file, "cool_arithmetic_overflow")?;
+
+    writeln!(out_file, "_division_by_zero:")?;
+    emit_c_call(out_file, "cool_division_by_zero")?;
+
+    Ok(())
+}
+
+// Write out all class names (in tag order)
+fn class_name_consts(
+    out_file: &mut dyn io::Write,
+    classes: &IndexMap<TypeID, Class>,
+    class_name: &TypeID,
+    string_tag: u32,
+) -> Result<(), io::Error> {
+    let class: &Class = classes.get(class_name).unwrap();
+
+    emit_string_const(
+        out_file,
+        "class_name",
+        string_tag,
+        &class_name.to_string(),
+        class.tag(),
+    )?;
+
+    for child_class_name in &class.child_names {
+        class_name_consts(out_file, classes, child_class_name, string_tag)?;
+    }
+
+    Ok(())
+}
+
+// Class Object tab
+fn class_obj_tab(
+    out_file: &mut dyn io::Write,
+    classes: &IndexMap<TypeID, Class>,
+    class_name: &TypeID,
+) -> Result<(), io::Error> {
+    let class: &Class = classes.get(class_name).unwrap();
+
+    emit_word(out_file, format!("{}_protObj", class_name))?;
+    emit_word(out_file, format!("{}_init", class_name))?;
+
+    for child_class_name in &class.child_names {
+        class_obj_tab(out_file, classes, child_class_name)?;
+    }
+
+    Ok(())
+}
+
+// list each class's methods in order
+fn dispatch_table(
+    out_file: &mut dyn io::Write,
+    classes: &IndexMap<TypeID, Class>,
+    class_name: &TypeID,
+) -> Result<(), io::Error> {
+    let class: &Class = classes.get(class_name).unwrap();
+
+    writeln!(out_file, "{}_dispTab:", class_name)?;
+
+    for (class_name, method_name) in &class.dispatch_table {
+        emit_word(out_file, format!("{}.{}", class_name, method_name))?;
+    }
+
+    for child_class_name in &class.child_names {
+        dispatch_table(out_file, classes, child_class_name)?;
+    }
+
+    Ok(())
+}
+
+// Create a prototype object for each class
+// This object has the structure of an object in this class but its attributes are not set
+fn proto_obj(
+    out_file: &mut dyn io::Write,
+    classes: &IndexMap<TypeID, Class>,
+    class_name: &TypeID,
+    mut size: i16,
+) -> Result<(), io::Error> {
+    let class: &Class = classes.get(class_name).unwrap();
+
+    size += class.attrs.len() as i16;
+
+    writeln!(out_file, "{}_protObj:", class_name)?;
+    emit_word(out_file, class.tag())?;
+    emit_word(out_file, size as u32)?;
+
+    emit_word(out_file, format!("{}_dispTab", class_name))?;
+
+    proto_attrs(out_file, classes, class_name)?;
+
+    for child_class_name in &class.child_names {
+        proto_obj(out_file, classes, child_class_name, size)?;
+    }
+
+    Ok(())
+}
+
+// Attributes for a prototype object
+fn proto_attrs(
+    out_file: &mut dyn io::Write,
+    classes: &IndexMap<TypeID, Class>,
+    class_name: &TypeID,
+) -> Result<(), io::Error> {
+    let class: &Class = classes.get(class_name).unwrap();
+
+    if let Some(parent_name) = &class.parent_name {
+        proto_attrs(out_file, classes, parent_name)?;
+    }
+
+    for attr in class.attrs.iter() {
+        if attr.type_decl.is_int() {
+            emit_word(out_file, "int_const_00000000")?; // 0
+        } else if attr.type_decl.is_bool() {
+            emit_word(out_file, "bool_const0")?; // False
+        } else if attr.type_decl.is_string() {
+            emit_word(out_file, "str_const_00000000")?; // Empty String ("")
+        } else {
+            emit_word(out_file, 0)?; // Void
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,234 @@
+// Collects the errors found while compiling.
+// Each stage records its errors here and keeps going so that
+// a single run can report as many problems as possible.
+
+use std::fmt;
+
+use crate::span::Span;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    // Nothing is reported as a warning yet
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+// Stable codes for every kind of error
+// Tools should match on these rather than on the message text.
+// E00xx - lexical, E005x - syntax, E01xx - classes, E02xx - features, E03xx - expressions
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorCode {
+    IntOverflow,          // E0001
+    UnmatchedComment,     // E0002
+    UnexpectedChar,       // E0003
+    NullInString,         // E0004
+    UnterminatedString,   // E0005
+    NonAsciiChar,         // E0006
+    EofInComment,         // E0007
+    EofInString,          // E0008
+    SyntaxError,          // E0050
+    EmptyFile,            // E0051
+    MalformedAst,         // E0052
+    ClassRedefined,       // E0100
+    BadInheritance,       // E0101
+    UndefinedClass,       // E0102
+    InheritanceCycle,     // E0103
+    MissingMain,          // E0104
+    MainHasArgs,          // E0105
+    MethodRedefined,      // E0200
+    AttrRedefined,        // E0201
+    FormalRedefined,      // E0202
+    BadOverride,          // E0203
+    BadSelfType,          // E0204
+    BadSelf,              // E0205
+    TypeMismatch,         // E0300
+    UndeclaredIdentifier, // E0301
+    UndefinedMethod,      // E0302
+    WrongArgCount,        // E0303
+    NonBoolOperand,       // E0304
+    NonIntOperand,        // E0305
+    IllegalComparison,    // E0306
+    DuplicateBranch,      // E0307
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::IntOverflow => "E0001",
+            ErrorCode::UnmatchedComment => "E0002",
+            ErrorCode::UnexpectedChar => "E0003",
+            ErrorCode::NullInString => "E0004",
+            ErrorCode::UnterminatedString => "E0005",
+            ErrorCode::NonAsciiChar => "E0006",
+            ErrorCode::EofInComment => "E0007",
+            ErrorCode::EofInString => "E0008",
+            ErrorCode::SyntaxError => "E0050",
+            ErrorCode::EmptyFile => "E0051",
+            ErrorCode::MalformedAst => "E0052",
+            ErrorCode::ClassRedefined => "E0100",
+            ErrorCode::BadInheritance => "E0101",
+            ErrorCode::UndefinedClass => "E0102",
+            ErrorCode::InheritanceCycle => "E0103",
+            ErrorCode::MissingMain => "E0104",
+            ErrorCode::MainHasArgs => "E0105",
+            ErrorCode::MethodRedefined => "E0200",
+            ErrorCode::AttrRedefined => "E0201",
+            ErrorCode::FormalRedefined => "E0202",
+            ErrorCode::BadOverride => "E0203",
+            ErrorCode::BadSelfType => "E0204",
+            ErrorCode::BadSelf => "E0205",
+            ErrorCode::TypeMismatch => "E0300",
+            ErrorCode::UndeclaredIdentifier => "E0301",
+            ErrorCode::UndefinedMethod => "E0302",
+            ErrorCode::WrongArgCount => "E0303",
+            ErrorCode::NonBoolOperand => "E0304",
+            ErrorCode::NonIntOperand => "E0305",
+            ErrorCode::IllegalComparison => "E0306",
+            ErrorCode::DuplicateBranch => "E0307",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+// A region of a source file
+#[derive(Clone, Debug)]
+pub struct Location {
+    pub file_name: String,
+    pub span: Span,
+}
+
+impl Location {
+    pub fn new(file_name: &str, span: Span) -> Location {
+        Location {
+            file_name: file_name.to_string(),
+            span,
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} : {}", self.file_name, self.span.line_no)
+    }
+}
+
+// Extra information attached to a diagnostic (e.g. where something was previously defined)
+#[derive(Clone, Debug)]
+pub struct Note {
+    pub loc: Option<Location>,
+    pub msg: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: ErrorCode,
+    pub loc: Option<Location>, // None for errors that concern the whole program
+    pub msg: String,
+    pub notes: Vec<Note>,
+}
+
+impl Diagnostic {
+    pub fn error(code: ErrorCode, loc: Option<Location>, msg: String) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            code,
+            loc,
+            msg,
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn with_note(mut self, loc: Option<Location>, msg: String) -> Diagnostic {
+        self.notes.push(Note { loc, msg });
+        self
+    }
+}
+
+// file : line - error[E0102]: message
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(loc) = &self.loc {
+            write!(f, "{} - ", loc)?;
+        }
+
+        write!(f, "{}[{}]: {}", self.severity, self.code, self.msg)?;
+
+        for note in self.notes.iter() {
+            writeln!(f)?;
+
+            if let Some(loc) = &note.loc {
+                write!(f, "{} - ", loc)?;
+            }
+
+            write!(f, "note: {}", note.msg)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub struct Diagnostics {
+    diagnostics: Vec<Diagnostic>,
+    max_errors: usize, // Maximum number of errors to record (0 means no limit)
+}
+
+impl Diagnostics {
+    pub fn new(max_errors: usize) -> Diagnostics {
+        Diagnostics {
+            diagnostics: Vec::new(),
+            max_errors,
+        }
+    }
+
+    // Record a diagnostic
+    // Errors beyond the limit are discarded
+    pub fn report(&mut self, diagnostic: Diagnostic) {
+        if diagnostic.severity != Severity::Error || !self.limit_reached() {
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    // Record an error at a region of a source file
+    pub fn error(&mut self, code: ErrorCode, file_name: &str, span: Span, msg: String) {
+        self.report(Diagnostic::error(
+            code,
+            Some(Location::new(file_name, span)),
+            msg,
+        ));
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+            .count()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.error_count() != 0
+    }
+
+    // Once the limit is reached, there is no point in continuing
+    pub fn limit_reached(&self) -> bool {
+        self.max_errors != 0 && self.error_count() >= self.max_errors
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+}
@@ -0,0 +1,841 @@
+// Rewrites COOL source in a canonical layout:
+//
+// class Main inherits IO {
+//     x : Int <- 3;
+//
+//     main() : Object {
+//         let y : Int <- x,
+//             z : Bool
+//         in
+//             if z then
+//                 out_int(y)
+//             else
+//                 self
+//             fi
+//     };
+// };
+//
+// The layout comes from the AST. Comments are not part of the AST, so each one is
+// written before the first class, feature or statement that follows it in the source
+// (or at the end of the line if it follows code on the same line and comes before
+// the next part that is written).
+//
+// Comments are kept in a list beside the AST rather than attached to tokens, because
+// the parser does not keep the tokens: their spans are enough to place each comment.
+// The formatted source is parsed again to check that every comment is still between
+// the same classes, features, statements and branches.
+
+use indexmap::IndexMap;
+
+use crate::ast::{
+    ArithOpType, Attr, Branch, Class, CompType, Expression, Method, ObjectID, TypeID,
+};
+use crate::ast_dump::dump_ast;
+use crate::builtins::initialize_classes;
+use crate::diagnostics::Diagnostics;
+use crate::lexer::tokenize_with_comments;
+use crate::parser::parse;
+use crate::span::Span;
+use crate::token::Comment;
+use crate::Source;
+
+const INDENT: &str = "    ";
+
+// Binding strength of each kind of expression (see section 11.1 of the COOL manual)
+// An operand that binds less tightly than its position requires is put in parentheses.
+const PREC_LET: u8 = 0;
+const PREC_ASSIGN: u8 = 1;
+const PREC_NOT: u8 = 2;
+const PREC_COMP: u8 = 3;
+const PREC_ADD: u8 = 4;
+const PREC_MUL: u8 = 5;
+const PREC_ISVOID: u8 = 6;
+const PREC_NEG: u8 = 7;
+const PREC_ATOM: u8 = 8; // Constants, names, dispatches and expressions with closing keywords
+
+fn prec(expr: &Expression) -> u8 {
+    match expr {
+        Expression::Let { .. } => PREC_LET,
+        Expression::Assign { .. } => PREC_ASSIGN,
+        Expression::Not { .. } => PREC_NOT,
+        Expression::Comp { .. } | Expression::Eq { .. } => PREC_COMP,
+        Expression::ArithOp {
+            arith_op_type: ArithOpType::Add | ArithOpType::Sub,
+            ..
+        } => PREC_ADD,
+        Expression::ArithOp { .. } => PREC_MUL,
+        Expression::IsVoid { .. } => PREC_ISVOID,
+        // 'new A.f()' is a dispatch on 'new A', but it reads better with parentheses
+        Expression::Neg { .. } | Expression::New { .. } => PREC_NEG,
+        _ => PREC_ATOM,
+    }
+}
+
+// Quote a string constant with COOL escapes
+fn escape_cool_str(s: &str) -> String {
+    let mut escaped = String::from("\"");
+
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\x08' => escaped.push_str("\\b"),
+            '\x0c' => escaped.push_str("\\f"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped.push('"');
+    escaped
+}
+
+enum Feature<'a> {
+    Attr(&'a Attr),
+    Method(&'a ObjectID, &'a Method),
+}
+
+impl Feature<'_> {
+    fn lo(&self) -> u32 {
+        match self {
+            Feature::Attr(attr) => attr.span.lo,
+            Feature::Method(_, method) => method.span.lo,
+        }
+    }
+}
+
+// Whether an expression fits on one line
+// It must not contain blocks, cases, or a condition, loop or let that is laid out over several lines.
+fn is_simple(expr: &Expression) -> bool {
+    match expr {
+        Expression::Block { .. } | Expression::TypeCase { .. } => false,
+        Expression::Cond {
+            pred,
+            then_expr,
+            else_expr,
+            ..
+        } => is_simple(pred) && is_simple(then_expr) && is_simple(else_expr),
+        Expression::Loop { pred, body, .. } => is_simple(pred) && is_simple(body),
+        Expression::Let { init, body, .. } => is_simple(init) && is_simple(body),
+        Expression::Assign { expr, .. }
+        | Expression::Neg { expr, .. }
+        | Expression::Not { expr, .. }
+        | Expression::IsVoid { expr, .. } => is_simple(expr),
+        Expression::Dispatch { expr, args, .. } => is_simple(expr) && args.iter().all(is_simple),
+        Expression::ArithOp {
+            expr_lhs, expr_rhs, ..
+        }
+        | Expression::Comp {
+            expr_lhs, expr_rhs, ..
+        }
+        | Expression::Eq {
+            expr_lhs, expr_rhs, ..
+        } => is_simple(expr_lhs) && is_simple(expr_rhs),
+        _ => true,
+    }
+}
+
+struct Printer<'a> {
+    text: &'a str,         // The original source
+    line_starts: Vec<u32>, // Byte offset of the start of each line of the source
+    comments: &'a [Comment],
+    next_comment: usize, // The first comment that has not been written
+    out: String,
+    indent: usize,
+    at_line_start: bool,
+    at_statement: bool, // The next expression starts a statement
+}
+
+impl<'a> Printer<'a> {
+    fn new(text: &'a str, comments: &'a [Comment]) -> Printer<'a> {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i as u32 + 1))
+            .collect();
+
+        Printer {
+            text,
+            line_starts,
+            comments,
+            next_comment: 0,
+            out: String::new(),
+            indent: 0,
+            at_line_start: true,
+            at_statement: false,
+        }
+    }
+
+    // Source line (starting at 1) of a byte offset
+    fn line_of(&self, pos: u32) -> u32 {
+        self.line_starts.partition_point(|&start| start <= pos) as u32
+    }
+
+    // The source text of a span
+    fn source(&self, span: Span) -> &'a str {
+        self.text
+            .get(span.lo as usize..span.hi as usize)
+            .unwrap_or_default()
+    }
+
+    // Whether a comment starts inside span
+    fn has_comment(&self, span: Span) -> bool {
+        let i = self
+            .comments
+            .partition_point(|comment| comment.span.lo < span.lo);
+
+        matches!(self.comments.get(i), Some(comment) if comment.span.lo < span.hi)
+    }
+
+    // Whether an expression can be written on one line
+    // An expression that contains a comment is not, so that the comment stays in place.
+    fn fits_on_line(&self, expr: &Expression) -> bool {
+        is_simple(expr) && !self.has_comment(expr.span())
+    }
+
+    // Position of the last keyword (e.g. "else") between from and to in the source
+    // Keywords are not case sensitive, and keywords in comments are skipped.
+    // If there is none, to is returned.
+    fn keyword_pos(&self, from: u32, to: u32, keyword: &str) -> u32 {
+        let text = self.text.as_bytes();
+        let is_name_char = |pos: usize| {
+            text.get(pos)
+                .is_some_and(|c| c.is_ascii_alphanumeric() || *c == b'_')
+        };
+
+        (from as usize..(to as usize).saturating_sub(keyword.len()) + 1)
+            .rev()
+            .find(|&pos| {
+                text.get(pos..pos + keyword.len())
+                    .is_some_and(|word| word.eq_ignore_ascii_case(keyword.as_bytes()))
+                    && (pos == 0 || !is_name_char(pos - 1))
+                    && !is_name_char(pos + keyword.len())
+                    && !self.comments.iter().any(|comment| {
+                        comment.span.lo as usize <= pos && pos < comment.span.hi as usize
+                    })
+            })
+            .map_or(to, |pos| pos as u32)
+    }
+
+    fn write(&mut self, s: &str) {
+        if self.at_line_start && !s.is_empty() {
+            for _ in 0..self.indent {
+                self.out.push_str(INDENT);
+            }
+
+            self.at_line_start = false;
+        }
+
+        self.out.push_str(s);
+    }
+
+    fn newline(&mut self) {
+        self.out.push('\n');
+        self.at_line_start = true;
+    }
+
+    fn blank_line(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.newline();
+        }
+    }
+
+    // Write the comments that come before pos in the source, each on a line of its own
+    fn comments_before(&mut self, pos: u32) {
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            if comment.span.lo >= pos {
+                break;
+            }
+
+            self.next_comment += 1;
+
+            if !self.at_line_start {
+                self.newline();
+            }
+
+            self.write(&comment.text);
+            self.newline();
+        }
+    }
+
+    // End the line that contains the end of span (next is where the next part written starts)
+    fn end_line(&mut self, span: Span, next: u32) {
+        self.end_line_at(span.hi.saturating_sub(1), next);
+    }
+
+    // End the line that contains pos in the source
+    // The comments on the same line of the source are kept at the end of the line,
+    // unless they come after next (where the next part that is written starts).
+    fn end_line_at(&mut self, pos: u32, next: u32) {
+        let line_no = self.line_of(pos);
+
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            if self.line_of(comment.span.lo) != line_no || comment.span.lo >= next {
+                break;
+            }
+
+            self.next_comment += 1;
+            self.write(" ");
+            self.write(&comment.text);
+        }
+
+        self.newline();
+    }
+
+    // next is where the next class starts
+    fn class(&mut self, class_name: &TypeID, class: &Class, next: u32) {
+        self.comments_before(class.span.lo);
+
+        self.write(&format!("class {}", class_name));
+
+        if let Some(parent_name) = &class.parent_name {
+            if *parent_name != TypeID::root_class_name() {
+                self.write(&format!(" inherits {}", parent_name));
+            }
+        }
+
+        // Attributes and methods are kept separately in a Class
+        let mut features: Vec<Feature> = class.attrs.iter().map(Feature::Attr).collect();
+        features.extend(
+            class
+                .methods
+                .iter()
+                .map(|(method_name, method)| Feature::Method(method_name, method)),
+        );
+        features.sort_by_key(Feature::lo);
+
+        // Each feature is followed by the next one or the closing brace
+        let close = class.span.hi.saturating_sub(1);
+        let nexts: Vec<u32> = features
+            .iter()
+            .skip(1)
+            .map(Feature::lo)
+            .chain(std::iter::once(close))
+            .collect();
+
+        self.write(" {");
+        self.end_line_at(class.span.lo, nexts[0].min(close));
+        self.indent += 1;
+
+        for (i, feature) in features.iter().enumerate() {
+            // Methods are separated by a blank line, but consecutive attributes are not
+            if i > 0
+                && !matches!(
+                    (&features[i - 1], feature),
+                    (Feature::Attr(_), Feature::Attr(_))
+                )
+            {
+                self.blank_line();
+            }
+
+            match feature {
+                Feature::Attr(attr) => self.attr(attr, nexts[i]),
+                Feature::Method(method_name, method) => self.method(method_name, method, nexts[i]),
+            }
+        }
+
+        self.comments_before(close);
+        self.indent -= 1;
+        self.write("};");
+        self.end_line(class.span, next);
+    }
+
+    fn attr(&mut self, attr: &Attr, next: u32) {
+        self.comments_before(attr.span.lo);
+
+        self.write(&format!("{} : {}", attr.name, attr.type_decl));
+
+        if !matches!(attr.init, Expression::NoExpr) {
+            self.write(" <- ");
+            self.expr(&attr.init, PREC_LET);
+        }
+
+        self.write(";");
+        self.end_line(attr.span, next);
+    }
+
+    fn method(&mut self, method_name: &ObjectID, method: &Method, next: u32) {
+        self.comments_before(method.span.lo);
+
+        let formals: Vec<String> = method
+            .formals
+            .iter()
+            .map(|formal| format!("{} : {}", formal.name, formal.type_decl))
+            .collect();
+
+        self.write(&format!(
+            "{}({}) : {} {{",
+            method_name,
+            formals.join(", "),
+            method.return_type
+        ));
+        self.end_line_at(method.span.lo, method.expr.span().lo);
+        self.indent += 1;
+
+        let close = method.span.hi.saturating_sub(1);
+
+        self.statement(&method.expr);
+        self.end_line(method.expr.span(), close);
+        self.comments_before(close);
+        self.indent -= 1;
+        self.write("};");
+        self.end_line(method.span, next);
+    }
+
+    // An expression that starts on a line of its own
+    fn statement(&mut self, expr: &Expression) {
+        self.comments_before(expr.span().lo);
+        self.at_statement = true;
+        self.expr(expr, PREC_LET);
+    }
+
+    fn expr(&mut self, expr: &Expression, min_prec: u8) {
+        // Only the outermost expression of a statement is laid out over several lines
+        // if it would fit on one.
+        let at_statement = std::mem::replace(&mut self.at_statement, false);
+
+        if prec(expr) < min_prec {
+            self.write("(");
+            self.expr(expr, PREC_LET);
+            self.write(")");
+            return;
+        }
+
+        match expr {
+            Expression::Assign { name, expr, .. } => {
+                self.write(&format!("{} <- ", name));
+                self.expr(expr, PREC_LET);
+            }
+            Expression::Dispatch {
+                expr,
+                type_name,
+                name,
+                args,
+                ..
+            } => {
+                // f(x) is a dispatch on self that is not written in the source
+                let implicit_self = match &**expr {
+                    Expression::VarByName { span, name, .. } => {
+                        name.is_self() && self.source(*span) != "self"
+                    }
+                    _ => false,
+                };
+
+                if !implicit_self {
+                    self.expr(expr, PREC_ATOM);
+
+                    if let Some(type_name) = type_name {
+                        self.write(&format!("@{}", type_name));
+                    }
+
+                    self.write(".");
+                }
+
+                self.write(&format!("{}(", name));
+
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        self.write(", ");
+                    }
+
+                    self.expr(arg, PREC_LET);
+                }
+
+                self.write(")");
+            }
+            Expression::Cond {
+                span,
+                pred,
+                then_expr,
+                else_expr,
+                ..
+            } => {
+                self.write("if ");
+                self.expr(pred, PREC_LET);
+
+                if !at_statement && self.fits_on_line(expr) {
+                    self.write(" then ");
+                    self.expr(then_expr, PREC_LET);
+                    self.write(" else ");
+                    self.expr(else_expr, PREC_LET);
+                    self.write(" fi");
+                } else {
+                    let then_pos = self.keyword_pos(pred.span().hi, then_expr.span().lo, "then");
+                    let else_pos =
+                        self.keyword_pos(then_expr.span().hi, else_expr.span().lo, "else");
+                    let fi_pos = span.hi.saturating_sub(2);
+
+                    self.write(" then");
+                    self.indented(then_expr, then_pos, else_pos);
+                    self.comments_before(else_pos);
+                    self.write("else");
+                    self.indented(else_expr, else_pos, fi_pos);
+                    self.comments_before(fi_pos);
+                    self.write("fi");
+                }
+            }
+            Expression::Loop { span, pred, body } => {
+                self.write("while ");
+                self.expr(pred, PREC_LET);
+
+                if !at_statement && self.fits_on_line(expr) {
+                    self.write(" loop ");
+                    self.expr(body, PREC_LET);
+                    self.write(" pool");
+                } else {
+                    let loop_pos = self.keyword_pos(pred.span().hi, body.span().lo, "loop");
+                    let pool_pos = span.hi.saturating_sub(4);
+
+                    self.write(" loop");
+                    self.indented(body, loop_pos, pool_pos);
+                    self.comments_before(pool_pos);
+                    self.write("pool");
+                }
+            }
+            Expression::TypeCase {
+                span,
+                expr,
+                branches,
+                ..
+            } => {
+                let of_pos = self.keyword_pos(expr.span().hi, branches[0].span.lo, "of");
+                let esac_pos = span.hi.saturating_sub(4);
+
+                self.write("case ");
+                self.expr(expr, PREC_LET);
+                self.write(" of");
+                self.end_line_at(of_pos, branches[0].span.lo);
+                self.indent += 1;
+
+                // The arrows of the branches line up
+                let width = branches
+                    .iter()
+                    .map(|branch| format!("{} : {}", branch.name, branch.type_decl).len())
+                    .max()
+                    .unwrap_or(0);
+
+                for (i, branch) in branches.iter().enumerate() {
+                    let next = branches.get(i + 1).map_or(esac_pos, |next| next.span.lo);
+
+                    self.branch(branch, width, next);
+                }
+
+                self.comments_before(esac_pos);
+                self.indent -= 1;
+                self.write("esac");
+            }
+            Expression::Block { span, body } => {
+                let close = span.hi.saturating_sub(1);
+
+                self.write("{");
+                self.end_line_at(span.lo, body[0].span().lo);
+                self.indent += 1;
+
+                for (i, expr) in body.iter().enumerate() {
+                    let next = body.get(i + 1).map_or(close, |next| next.span().lo);
+
+                    self.statement(expr);
+                    self.write(";");
+                    self.end_line(expr.span(), next);
+                }
+
+                self.comments_before(close);
+                self.indent -= 1;
+                self.write("}");
+            }
+            Expression::Let { .. } => self.let_expr(expr, at_statement),
+            Expression::ArithOp {
+                expr_lhs,
+                expr_rhs,
+                arith_op_type,
+                ..
+            } => {
+                let prec = prec(expr);
+
+                self.expr(expr_lhs, prec);
+                self.write(&format!(" {} ", arith_op_type));
+                self.expr(expr_rhs, prec + 1);
+            }
+            Expression::Neg { expr, .. } => {
+                self.write("~");
+                self.expr(expr, PREC_NEG);
+            }
+            Expression::Comp {
+                expr_lhs,
+                expr_rhs,
+                comp_type,
+                ..
+            } => {
+                // Comparisons do not associate
+                self.expr(expr_lhs, PREC_ADD);
+                self.write(match comp_type {
+                    CompType::LT => " < ",
+                    CompType::LEq => " <= ",
+                });
+                self.expr(expr_rhs, PREC_ADD);
+            }
+            Expression::Eq {
+                expr_lhs, expr_rhs, ..
+            } => {
+                self.expr(expr_lhs, PREC_ADD);
+                self.write(" = ");
+                self.expr(expr_rhs, PREC_ADD);
+            }
+            Expression::Not { expr, .. } => {
+                self.write("not ");
+                self.expr(expr, PREC_NOT);
+            }
+            Expression::IntConst { val, .. } => self.write(&val.to_string()),
+            Expression::BoolConst { val, .. } => self.write(&val.to_string()),
+            Expression::StringConst { val, .. } => self.write(&escape_cool_str(val)),
+            Expression::New { type_name, .. } => self.write(&format!("new {}", type_name)),
+            Expression::IsVoid { expr, .. } => {
+                self.write("isvoid ");
+                self.expr(expr, PREC_ISVOID);
+            }
+            Expression::NoExpr => {}
+            Expression::VarByName { name, .. } => self.write(&name.to_string()),
+        }
+    }
+
+    // Write an expression on the following lines one level deeper
+    // It comes after the keyword at keyword_pos (e.g. then), and next follows it.
+    fn indented(&mut self, expr: &Expression, keyword_pos: u32, next: u32) {
+        self.end_line_at(keyword_pos, expr.span().lo);
+        self.indent += 1;
+        self.statement(expr);
+        self.end_line(expr.span(), next);
+        self.indent -= 1;
+    }
+
+    fn branch(&mut self, branch: &Branch, width: usize, next: u32) {
+        self.comments_before(branch.span.lo);
+
+        let decl = format!("{} : {}", branch.name, branch.type_decl);
+
+        self.write(&format!("{:width$} => ", decl, width = width));
+        self.expr(&branch.expr, PREC_LET);
+        self.write(";");
+        self.end_line(branch.span, next);
+    }
+
+    // let a : A, b : B in e is parsed as let a : A in let b : B in e
+    // The nested lets that were written as one are written as one again.
+    fn let_expr(&mut self, expr: &Expression, at_statement: bool) {
+        let mut bindings: Vec<(Span, &ObjectID, &TypeID, &Expression)> = Vec::new();
+        let one_line = !at_statement && self.fits_on_line(expr);
+        let mut expr = expr;
+
+        while let Expression::Let {
+            span,
+            identifier,
+            type_decl,
+            init,
+            body,
+        } = expr
+        {
+            bindings.push((*span, identifier, type_decl, init));
+            expr = body;
+
+            // A let written in the source starts with the keyword rather than a name
+            match &**body {
+                Expression::Let { span, .. } if !self.starts_with_let(*span) => {}
+                _ => break,
+            }
+        }
+
+        // Where a binding ends in the source
+        let binding_end = |(span, _, _, init): (Span, &ObjectID, &TypeID, &Expression)| match init {
+            Expression::NoExpr => span.lo,
+            init => init.span().hi.saturating_sub(1),
+        };
+
+        self.write("let ");
+
+        for (i, &(span, identifier, type_decl, init)) in bindings.iter().enumerate() {
+            if i > 0 {
+                self.write(",");
+
+                if one_line {
+                    self.write(" ");
+                } else {
+                    self.end_line_at(binding_end(bindings[i - 1]), span.lo);
+                    self.comments_before(span.lo);
+                    self.write(INDENT);
+                }
+            }
+
+            self.write(&format!("{} : {}", identifier, type_decl));
+
+            if !matches!(init, Expression::NoExpr) {
+                self.write(" <- ");
+                self.expr(init, PREC_ASSIGN);
+            }
+        }
+
+        if one_line {
+            self.write(" in ");
+            self.expr(expr, PREC_LET);
+        } else {
+            let last_end = binding_end(bindings[bindings.len() - 1]);
+            let in_pos = self.keyword_pos(last_end, expr.span().lo, "in");
+
+            self.end_line_at(last_end, in_pos);
+            self.comments_before(in_pos);
+            self.write("in");
+            self.end_line_at(in_pos, expr.span().lo);
+            self.indent += 1;
+            self.statement(expr);
+            self.indent -= 1;
+        }
+    }
+
+    fn starts_with_let(&self, span: Span) -> bool {
+        let text = self.source(span).as_bytes();
+
+        text.len() > 3
+            && text[..3].eq_ignore_ascii_case(b"let")
+            && !(text[3].is_ascii_alphanumeric() || text[3] == b'_')
+    }
+}
+
+// The AST of a formatted file without line numbers
+// Formatting must not change it.
+fn program_shape(classes: &IndexMap<TypeID, Class>) -> String {
+    let mut out: Vec<u8> = Vec::new();
+
+    dump_ast(&mut out, classes, false).unwrap();
+
+    String::from_utf8(out)
+        .unwrap()
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
+
+// Where the statements of an expression start: the expressions of blocks, the bodies of
+// loops and lets, and the branches of conditions and cases
+fn statement_starts(expr: &Expression, starts: &mut Vec<u32>) {
+    match expr {
+        Expression::Cond {
+            then_expr,
+            else_expr,
+            ..
+        } => starts.extend([then_expr.span().lo, else_expr.span().lo]),
+        Expression::Loop { body, .. } | Expression::Let { body, .. } => starts.push(body.span().lo),
+        Expression::TypeCase { branches, .. } => {
+            starts.extend(branches.iter().map(|branch| branch.span.lo))
+        }
+        Expression::Block { body, .. } => starts.extend(body.iter().map(|expr| expr.span().lo)),
+        _ => {}
+    }
+
+    for child in expr.children() {
+        statement_starts(child, starts);
+    }
+}
+
+// Each comment with the number of classes, features and statements that start before it
+// Formatting must not move a comment past any of them.
+fn comment_places<'a>(
+    classes: &IndexMap<TypeID, Class>,
+    comments: &'a [Comment],
+) -> Vec<(usize, &'a str)> {
+    let mut starts: Vec<u32> = Vec::new();
+
+    for class in classes.values().filter(|class| !class.basic) {
+        starts.push(class.span.lo);
+
+        for attr in class.attrs.iter() {
+            starts.push(attr.span.lo);
+            statement_starts(&attr.init, &mut starts);
+        }
+
+        for method in class.methods.values() {
+            starts.extend([method.span.lo, method.expr.span().lo]);
+            statement_starts(&method.expr, &mut starts);
+        }
+    }
+
+    starts.sort_unstable();
+
+    comments
+        .iter()
+        .map(|comment| {
+            (
+                starts.partition_point(|&start| start < comment.span.lo),
+                comment.text.as_str(),
+            )
+        })
+        .collect()
+}
+
+fn parse_with_comments(
+    source: &Source,
+    diagnostics: &mut Diagnostics,
+) -> (IndexMap<TypeID, Class>, Vec<Comment>) {
+    let mut classes = initialize_classes();
+    let (tokens, comments) = tokenize_with_comments(source.text, source.name, diagnostics);
+
+    parse(tokens, source.name, 1, &mut classes, diagnostics);
+
+    (classes, comments)
+}
+
+// Format a COOL source file
+// Only a file without syntax errors can be formatted. Otherwise, None is returned
+// and the errors are recorded in diagnostics.
+pub fn format_source(
+    source: &Source,
+    diagnostics: &mut Diagnostics,
+) -> Result<Option<String>, String> {
+    let (classes, comments) = parse_with_comments(source, diagnostics);
+
+    if diagnostics.has_errors() {
+        return Ok(None);
+    }
+
+    let mut program: Vec<(&TypeID, &Class)> =
+        classes.iter().filter(|(_, class)| !class.basic).collect();
+    program.sort_by_key(|(_, class)| class.span.lo);
+
+    let mut printer = Printer::new(source.text, &comments);
+
+    for (i, (class_name, class)) in program.iter().enumerate() {
+        let next = program
+            .get(i + 1)
+            .map_or(u32::MAX, |(_, next)| next.span.lo);
+
+        if i > 0 {
+            printer.blank_line();
+        }
+
+        printer.class(class_name, class, next);
+    }
+
+    printer.comments_before(u32::MAX);
+
+    let formatted = printer.out;
+
+    // Check that nothing was lost
+    let mut check_diagnostics = Diagnostics::new(0);
+    let formatted_source = Source {
+        name: source.name,
+        text: &formatted,
+    };
+    let (formatted_classes, formatted_comments) =
+        parse_with_comments(&formatted_source, &mut check_diagnostics);
+
+    if check_diagnostics.has_errors()
+        || program_shape(&formatted_classes) != program_shape(&classes)
+        || comment_places(&formatted_classes, &formatted_comments)
+            != comment_places(&classes, &comments)
+    {
+        return Err(format!(
+            "Cannot format {}: the formatted program differs from the original.",
+            source.name
+        ));
+    }
+
+    Ok(Some(formatted))
+}
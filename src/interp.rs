@@ -0,0 +1,812 @@
+// A reference interpreter that evaluates the typed classes directly
+// It follows the operational semantics of the COOL manual and writes exactly what the
+// program compiled by cgen writes under mips::run, so the two can be compared.
+//
+// The classes must have passed semantic analysis (dispatch tables, attribute
+// offsets and case branch order are set by semant).
+
+use indexmap::IndexMap;
+use std::cell::RefCell;
+use std::io::{BufRead, Write};
+use std::rc::Rc;
+use std::thread;
+
+use crate::ast::{ArithOpType, Branch, Class, CompType, Expression, Method, ObjectID, TypeID};
+use crate::mips::TERM_MSG;
+use crate::semant::ATTR_OFFSET_START;
+use crate::span::Span;
+use crate::token::escape_str;
+
+const STACK_SIZE: usize = 1 << 30; // Bytes (for the thread that runs the program)
+const STACK_GUARD_SIZE: usize = 1 << 20; // Bytes (recursion stops when less than this is left)
+
+#[derive(Clone)]
+enum Value {
+    Void,
+    Int(i32),
+    Bool(bool),
+    Str(Rc<Vec<u8>>),
+    Object(Rc<Object>),
+}
+
+// An object of a class other than Int, Bool and String
+struct Object {
+    class_index: usize, // Index in the classes map
+    attrs: RefCell<Vec<Value>>,
+}
+
+// Why evaluation stopped early
+enum Stop {
+    Halt,          // A runtime error (the message has been written)
+    Error(String), // An error that the compiled program reports as an exception
+}
+
+type Eval = Result<Value, Stop>;
+
+// The state of a method call
+struct Frame<'a> {
+    self_val: Value,
+    class: &'a Class,                     // The class the code is in
+    attrs: &'a IndexMap<ObjectID, usize>, // Attribute positions of self
+    locals: Vec<(ObjectID, Value)>,       // Formals, let variables and case branch variables
+}
+
+impl<'a> Frame<'a> {
+    fn lookup(&self, name: &ObjectID) -> Value {
+        if name.is_self() {
+            return self.self_val.clone();
+        }
+
+        if let Some((_, val)) = self.locals.iter().rev().find(|(local, _)| local == name) {
+            return val.clone();
+        }
+
+        match (&self.self_val, self.attrs.get(name)) {
+            (Value::Object(obj), Some(i)) => obj.attrs.borrow()[*i].clone(),
+            _ => Value::Void,
+        }
+    }
+
+    fn assign(&mut self, name: &ObjectID, val: Value) {
+        if let Some((_, local)) = self
+            .locals
+            .iter_mut()
+            .rev()
+            .find(|(local, _)| local == name)
+        {
+            *local = val;
+            return;
+        }
+
+        if let (Value::Object(obj), Some(i)) = (&self.self_val, self.attrs.get(name)) {
+            obj.attrs.borrow_mut()[*i] = val;
+        }
+    }
+}
+
+struct Interpreter<'a> {
+    classes: &'a IndexMap<TypeID, Class>,
+    layouts: &'a [IndexMap<ObjectID, usize>], // Attribute positions of each class (by class index)
+    input: &'a mut dyn BufRead,
+    output: &'a mut dyn Write,
+    stack_base: usize, // The address of the stack when the program started
+}
+
+// Run a program
+// Returns false if the program was stopped by a runtime error (e.g. a dispatch to void).
+pub fn run(
+    classes: &IndexMap<TypeID, Class>,
+    input: &mut (dyn BufRead + Send),
+    output: &mut (dyn Write + Send),
+) -> Result<bool, String> {
+    with_stack(|| run_main(classes, input, output))?
+}
+
+// Run a function on a thread with enough stack for deeply recursive programs
+// Programs must be interpreted inside such a function.
+pub fn with_stack<T: Send>(f: impl FnOnce() -> T + Send) -> Result<T, String> {
+    thread::scope(|scope| {
+        let handle = thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn_scoped(scope, f);
+
+        match handle {
+            Ok(handle) => match handle.join() {
+                Ok(result) => Ok(result),
+                Err(_) => Err("The interpreter failed.".to_string()),
+            },
+            Err(why) => Err(format!("Cannot start the interpreter: {}", why)),
+        }
+    })
+}
+
+fn run_main(
+    classes: &IndexMap<TypeID, Class>,
+    input: &mut dyn BufRead,
+    output: &mut dyn Write,
+) -> Result<bool, String> {
+    let layouts = attr_layouts(classes);
+    let mut interp = Interpreter::new(classes, &layouts, input, output);
+
+    let result = match interp.new_object(&TypeID::new_main()) {
+        Ok(main) => interp.dispatch(main, &TypeID::new_main(), &ObjectID::new_main(), Vec::new()),
+        Err(stop) => Err(stop),
+    };
+
+    let result = match result {
+        Ok(_) => interp.write(TERM_MSG.as_bytes()).map(|_| true),
+        Err(Stop::Halt) => Ok(false),
+        Err(Stop::Error(msg)) => Err(msg),
+    };
+
+    // Output written before an error is kept
+    if let Err(why) = interp.output.flush() {
+        return Err(format!("Cannot write output: {}", why));
+    }
+
+    result
+}
+
+// The Main object that expressions are evaluated with (see eval_method)
+// It is created the first time it is needed.
+#[derive(Default)]
+pub struct Session {
+    main: Option<Value>,
+}
+
+impl Session {
+    pub fn new() -> Session {
+        Session { main: None }
+    }
+
+    // Forget the Main object (e.g. because its class has changed)
+    pub fn reset(&mut self) {
+        self.main = None;
+    }
+}
+
+// Call a method that takes no arguments with the Main object of a session as self
+// The method may be defined in a class that inherits from Main, as long as that class
+// adds no attributes. Returns a description of the result (e.g. 42 or "abc"), or None
+// if the program was stopped by a runtime error.
+// Must be called inside with_stack.
+pub fn eval_method(
+    classes: &IndexMap<TypeID, Class>,
+    session: &mut Session,
+    class_name: &TypeID,
+    method_name: &ObjectID,
+    input: &mut dyn BufRead,
+    output: &mut dyn Write,
+) -> Result<Option<String>, String> {
+    let layouts = attr_layouts(classes);
+    let mut interp = Interpreter::new(classes, &layouts, input, output);
+
+    let result = match interp.eval_in_main(session, class_name, method_name) {
+        Ok(val) => Ok(Some(interp.describe(&val))),
+        Err(Stop::Halt) => Ok(None),
+        Err(Stop::Error(msg)) => Err(msg),
+    };
+
+    if let Err(why) = interp.output.flush() {
+        return Err(format!("Cannot write output: {}", why));
+    }
+
+    result
+}
+
+// An address in the current stack frame
+fn stack_address() -> usize {
+    let marker = 0u8;
+
+    &marker as *const u8 as usize
+}
+
+// The attribute positions of every class (in the order of the classes map)
+fn attr_layouts(classes: &IndexMap<TypeID, Class>) -> Vec<IndexMap<ObjectID, usize>> {
+    classes
+        .values()
+        .map(|class| attr_layout(classes, class))
+        .collect()
+}
+
+// The positions of the attributes of a class (including inherited attributes)
+fn attr_layout(classes: &IndexMap<TypeID, Class>, class: &Class) -> IndexMap<ObjectID, usize> {
+    let mut layout = match class
+        .parent_name
+        .as_ref()
+        .and_then(|name| classes.get(name))
+    {
+        Some(parent) => attr_layout(classes, parent),
+        None => IndexMap::new(),
+    };
+
+    for attr in class.attrs.iter() {
+        layout.insert(
+            attr.name.clone(),
+            (attr.self_offset - ATTR_OFFSET_START) as usize,
+        );
+    }
+
+    layout
+}
+
+// The initial value of a variable of a type
+fn default_value(type_name: &TypeID) -> Value {
+    if type_name.is_int() {
+        Value::Int(0)
+    } else if type_name.is_bool() {
+        Value::Bool(false)
+    } else if type_name.is_string() {
+        Value::Str(Rc::new(Vec::new()))
+    } else {
+        Value::Void
+    }
+}
+
+impl<'a> Interpreter<'a> {
+    fn new(
+        classes: &'a IndexMap<TypeID, Class>,
+        layouts: &'a [IndexMap<ObjectID, usize>],
+        input: &'a mut dyn BufRead,
+        output: &'a mut dyn Write,
+    ) -> Interpreter<'a> {
+        Interpreter {
+            classes,
+            layouts,
+            input,
+            output,
+            stack_base: stack_address(),
+        }
+    }
+
+    fn eval_in_main(
+        &mut self,
+        session: &mut Session,
+        class_name: &TypeID,
+        method_name: &ObjectID,
+    ) -> Eval {
+        let main = match &session.main {
+            Some(main) => main.clone(),
+            None => {
+                let main = self.new_object(&TypeID::new_main())?;
+                session.main = Some(main.clone());
+                main
+            }
+        };
+
+        self.invoke(main, class_name, method_name, Vec::new())
+    }
+
+    // How a value is shown to the user
+    fn describe(&self, val: &Value) -> String {
+        match val {
+            Value::Void => "void".to_string(),
+            Value::Int(val) => val.to_string(),
+            Value::Bool(val) => val.to_string(),
+            Value::Str(val) => escape_str(&String::from_utf8_lossy(val)),
+            Value::Object(_) => format!("<{} object>", self.class_name(val)),
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.output
+            .write_all(bytes)
+            .map_err(|why| format!("Cannot write output: {}", why))
+    }
+
+    // Write the message of a runtime error and stop
+    fn halt(&mut self, msg: &[u8]) -> Eval {
+        self.write(msg).map_err(Stop::Error)?;
+
+        Err(Stop::Halt)
+    }
+
+    fn read_line(&mut self) -> Result<Vec<u8>, Stop> {
+        // Prompts must appear before the program waits for input
+        if let Err(why) = self.output.flush() {
+            return Err(Stop::Error(format!("Cannot write output: {}", why)));
+        }
+
+        let mut line = Vec::new();
+
+        if let Err(why) = self.input.read_until(b'\n', &mut line) {
+            return Err(Stop::Error(format!("Cannot read input: {}", why)));
+        }
+
+        if line.last() == Some(&b'\n') {
+            line.pop();
+        }
+
+        Ok(line)
+    }
+
+    // The name of the class of a value
+    fn class_name(&self, val: &Value) -> &'a TypeID {
+        let index = match val {
+            Value::Object(obj) => obj.class_index,
+            Value::Int(_) => self.classes.get_index_of(&TypeID::new_int()).unwrap(),
+            Value::Bool(_) => self.classes.get_index_of(&TypeID::new_bool()).unwrap(),
+            Value::Str(_) => self.classes.get_index_of(&TypeID::new_string()).unwrap(),
+            Value::Void => panic!("Void has no class"),
+        };
+
+        self.classes.get_index(index).unwrap().0
+    }
+
+    // Create an object of a class and initialize its attributes
+    fn new_object(&mut self, class_name: &TypeID) -> Eval {
+        if class_name.is_int() || class_name.is_bool() || class_name.is_string() {
+            return Ok(default_value(class_name));
+        }
+
+        let (class_index, _, _) = self.classes.get_full(class_name).unwrap();
+
+        let mut attrs = vec![Value::Void; self.layouts[class_index].len()];
+
+        // Attributes start with the default value of their type
+        for class in self.ancestors(class_name) {
+            for attr in class.attrs.iter() {
+                attrs[(attr.self_offset - ATTR_OFFSET_START) as usize] =
+                    default_value(&attr.type_decl);
+            }
+        }
+
+        let obj = Value::Object(Rc::new(Object {
+            class_index,
+            attrs: RefCell::new(attrs),
+        }));
+
+        // Initializers are run from the root of the inheritance tree down
+        let layouts = self.layouts;
+        let layout = &layouts[class_index];
+
+        for class in self.ancestors(class_name).into_iter().rev() {
+            let mut frame = Frame {
+                self_val: obj.clone(),
+                class,
+                attrs: layout,
+                locals: Vec::new(),
+            };
+
+            for attr in class.attrs.iter() {
+                if !matches!(attr.init, Expression::NoExpr) {
+                    let val = self.eval(&attr.init, &mut frame)?;
+                    frame.assign(&attr.name, val);
+                }
+            }
+        }
+
+        Ok(obj)
+    }
+
+    // A class and its ancestors (the class first)
+    fn ancestors(&self, class_name: &TypeID) -> Vec<&'a Class> {
+        let mut result = Vec::new();
+        let mut class_name = Some(class_name);
+
+        while let Some(class) = class_name.and_then(|name| self.classes.get(name)) {
+            result.push(class);
+            class_name = class.parent_name.as_ref();
+        }
+
+        result
+    }
+
+    // Call the method of a class (found in its dispatch table) on a receiver
+    fn dispatch(
+        &mut self,
+        receiver: Value,
+        class_name: &TypeID,
+        method_name: &ObjectID,
+        args: Vec<Value>,
+    ) -> Eval {
+        let class = &self.classes[class_name];
+        let pos = class.method_name_to_pos[method_name];
+        let (impl_class_name, _) = &class.dispatch_table[pos as usize];
+
+        self.invoke(receiver, impl_class_name, method_name, args)
+    }
+
+    // Call the method defined in a class
+    fn invoke(
+        &mut self,
+        receiver: Value,
+        class_name: &TypeID,
+        method_name: &ObjectID,
+        args: Vec<Value>,
+    ) -> Eval {
+        let classes = self.classes;
+        let layouts = self.layouts;
+
+        let (class_index, _, class) = classes.get_full(class_name).unwrap();
+
+        // Methods of the basic classes are built in
+        if class.basic {
+            return self.builtin(receiver, class_name, method_name, args);
+        }
+
+        let method: &Method = &class.methods[method_name];
+
+        let layout = match &receiver {
+            Value::Object(obj) => &layouts[obj.class_index],
+            _ => &layouts[class_index],
+        };
+
+        let mut frame = Frame {
+            self_val: receiver,
+            class,
+            attrs: layout,
+            locals: method
+                .formals
+                .iter()
+                .map(|formal| formal.name.clone())
+                .zip(args)
+                .collect(),
+        };
+
+        self.eval(&method.expr, &mut frame)
+    }
+
+    fn builtin(
+        &mut self,
+        receiver: Value,
+        class_name: &TypeID,
+        method_name: &ObjectID,
+        args: Vec<Value>,
+    ) -> Eval {
+        match (
+            class_name.to_string().as_str(),
+            method_name.to_string().as_str(),
+        ) {
+            ("Object", "abort") => {
+                let mut msg = b"Abort called from class ".to_vec();
+                msg.extend(self.class_name(&receiver).to_string().bytes());
+                msg.push(b'\n');
+
+                self.halt(&msg)
+            }
+            ("Object", "type_name") => Ok(Value::Str(Rc::new(
+                self.class_name(&receiver).to_string().into_bytes(),
+            ))),
+            ("Object", "copy") => Ok(match receiver {
+                Value::Object(obj) => Value::Object(Rc::new(Object {
+                    class_index: obj.class_index,
+                    attrs: RefCell::new(obj.attrs.borrow().clone()),
+                })),
+                val => val,
+            }),
+            ("IO", "out_string") => {
+                if let Some(Value::Str(val)) = args.first() {
+                    self.write(val).map_err(Stop::Error)?;
+                }
+
+                Ok(receiver)
+            }
+            ("IO", "out_int") => {
+                if let Some(Value::Int(val)) = args.first() {
+                    self.write(val.to_string().as_bytes())
+                        .map_err(Stop::Error)?;
+                }
+
+                Ok(receiver)
+            }
+            ("IO", "in_string") => {
+                let line = self.read_line()?;
+
+                // A string with a null character is read as the empty string
+                if line.contains(&0) {
+                    Ok(Value::Str(Rc::new(Vec::new())))
+                } else {
+                    Ok(Value::Str(Rc::new(line)))
+                }
+            }
+            ("IO", "in_int") => {
+                let line = self.read_line()?;
+                let line = String::from_utf8_lossy(&line);
+                let line = line.trim_start();
+
+                // Like atoi, leading digits are read and the rest is ignored
+                let end = line
+                    .char_indices()
+                    .find(|(i, c)| !(c.is_ascii_digit() || (*i == 0 && (*c == '-' || *c == '+'))))
+                    .map_or(line.len(), |(i, _)| i);
+
+                Ok(Value::Int(line[..end].parse::<i64>().unwrap_or(0) as i32))
+            }
+            ("String", "length") => match receiver {
+                Value::Str(val) => Ok(Value::Int(val.len() as i32)),
+                _ => panic!("length of a value that is not a String"),
+            },
+            ("String", "concat") => match (receiver, args.first()) {
+                (Value::Str(val), Some(Value::Str(arg))) => {
+                    let mut val = val.to_vec();
+                    val.extend(arg.iter());
+
+                    Ok(Value::Str(Rc::new(val)))
+                }
+                _ => panic!("concat of a value that is not a String"),
+            },
+            ("String", "substr") => match (receiver, args.first(), args.get(1)) {
+                (Value::Str(val), Some(Value::Int(index)), Some(Value::Int(len))) => {
+                    let (index, len) = (*index, *len);
+
+                    let msg: &[u8] = if index < 0 {
+                        b"Index to substr is negative\n"
+                    } else if index as usize > val.len() {
+                        b"Index to substr is too big\n"
+                    } else if len < 0 {
+                        b"Length to substr is negative\n"
+                    } else if (index as usize + len as usize) > val.len() {
+                        b"Length to substr too long\n"
+                    } else {
+                        let start = index as usize;
+
+                        return Ok(Value::Str(Rc::new(
+                            val[start..start + len as usize].to_vec(),
+                        )));
+                    };
+
+                    let mut msg = msg.to_vec();
+                    msg.extend(b"Execution aborted.\n");
+
+                    self.halt(&msg)
+                }
+                _ => panic!("substr of a value that is not a String"),
+            },
+            _ => panic!("Unknown method {}.{}", class_name, method_name),
+        }
+    }
+
+    fn eval_int(&mut self, expr: &Expression, frame: &mut Frame<'a>) -> Result<i32, Stop> {
+        match self.eval(expr, frame)? {
+            Value::Int(val) => Ok(val),
+            _ => panic!("Expected an Int"),
+        }
+    }
+
+    fn eval_bool(&mut self, expr: &Expression, frame: &mut Frame<'a>) -> Result<bool, Stop> {
+        match self.eval(expr, frame)? {
+            Value::Bool(val) => Ok(val),
+            _ => panic!("Expected a Bool"),
+        }
+    }
+
+    fn eval_dispatch(
+        &mut self,
+        span: Span,
+        expr: &Expression,
+        type_name: Option<&TypeID>,
+        name: &ObjectID,
+        args: &[Expression],
+        frame: &mut Frame<'a>,
+    ) -> Eval {
+        // The arguments are evaluated before the receiver
+        let mut arg_vals = Vec::with_capacity(args.len());
+
+        for arg in args.iter() {
+            arg_vals.push(self.eval(arg, frame)?);
+        }
+
+        let receiver = self.eval(expr, frame)?;
+
+        if let Value::Void = receiver {
+            let msg = format!(
+                "{}:{}: Dispatch to void.\n",
+                frame.class.file_name, span.line_no
+            );
+
+            return self.halt(msg.as_bytes());
+        }
+
+        let class_name = match type_name {
+            Some(type_name) => type_name,
+            None => self.class_name(&receiver),
+        };
+
+        self.dispatch(receiver, class_name, name, arg_vals)
+    }
+
+    fn eval_case(
+        &mut self,
+        span: Span,
+        expr: &Expression,
+        branches: &[Branch],
+        frame: &mut Frame<'a>,
+    ) -> Eval {
+        let val = self.eval(expr, frame)?;
+
+        if let Value::Void = val {
+            let msg = format!(
+                "{}:{}: Match on void in case statement.\n",
+                frame.class.file_name, span.line_no
+            );
+
+            return self.halt(msg.as_bytes());
+        }
+
+        // The branches are sorted so that the first match is the closest ancestor
+        let tag = self.classes[self.class_name(&val)].tag();
+        let branch: Option<&Branch> = branches.iter().find(|branch| branch.family.contains(&tag));
+
+        match branch {
+            Some(branch) => {
+                frame.locals.push((branch.name.clone(), val));
+                let result = self.eval(&branch.expr, frame);
+                frame.locals.pop();
+
+                result
+            }
+            None => {
+                let msg = format!(
+                    "No match in case statement for Class {}\n",
+                    self.class_name(&val)
+                );
+
+                self.halt(msg.as_bytes())
+            }
+        }
+    }
+
+    fn eval_arith(
+        &mut self,
+        expr_lhs: &Expression,
+        expr_rhs: &Expression,
+        arith_op_type: &ArithOpType,
+        frame: &mut Frame<'a>,
+    ) -> Eval {
+        let lhs = self.eval_int(expr_lhs, frame)?;
+        let rhs = self.eval_int(expr_rhs, frame)?;
+
+        // The same as the MIPS instructions used by cgen
+        let val = match arith_op_type {
+            ArithOpType::Add => lhs.checked_add(rhs),
+            ArithOpType::Sub => lhs.checked_sub(rhs),
+            ArithOpType::Mul => Some(lhs.wrapping_mul(rhs)),
+            ArithOpType::Div => {
+                if rhs == 0 {
+                    return Err(Stop::Error("Division by zero".to_string()));
+                }
+
+                Some(lhs.wrapping_div(rhs))
+            }
+        };
+
+        match val {
+            Some(val) => Ok(Value::Int(val)),
+            None => Err(Stop::Error("Arithmetic overflow".to_string())),
+        }
+    }
+
+    fn eval(&mut self, expr: &Expression, frame: &mut Frame<'a>) -> Eval {
+        // Deep recursion is stopped before the thread runs out of stack
+        if self.stack_base.abs_diff(stack_address()) > STACK_SIZE - STACK_GUARD_SIZE {
+            return Err(Stop::Error("Stack overflow".to_string()));
+        }
+
+        match expr {
+            Expression::Assign { name, expr, .. } => {
+                let val = self.eval(expr, frame)?;
+                frame.assign(name, val.clone());
+
+                Ok(val)
+            }
+            Expression::Dispatch {
+                span,
+                expr,
+                type_name,
+                name,
+                args,
+                ..
+            } => self.eval_dispatch(*span, expr, type_name.as_ref(), name, args, frame),
+            Expression::Cond {
+                pred,
+                then_expr,
+                else_expr,
+                ..
+            } => {
+                if self.eval_bool(pred, frame)? {
+                    self.eval(then_expr, frame)
+                } else {
+                    self.eval(else_expr, frame)
+                }
+            }
+            Expression::Loop { pred, body, .. } => {
+                while self.eval_bool(pred, frame)? {
+                    self.eval(body, frame)?;
+                }
+
+                Ok(Value::Void)
+            }
+            Expression::TypeCase {
+                span,
+                expr,
+                branches,
+                ..
+            } => self.eval_case(*span, expr, branches, frame),
+            Expression::Block { body, .. } => {
+                let mut val = Value::Void;
+
+                for expr in body.iter() {
+                    val = self.eval(expr, frame)?;
+                }
+
+                Ok(val)
+            }
+            Expression::Let {
+                identifier,
+                type_decl,
+                init,
+                body,
+                ..
+            } => {
+                let val = match **init {
+                    Expression::NoExpr => default_value(type_decl),
+                    _ => self.eval(init, frame)?,
+                };
+
+                frame.locals.push((identifier.clone(), val));
+                let result = self.eval(body, frame);
+                frame.locals.pop();
+
+                result
+            }
+            Expression::ArithOp {
+                expr_lhs,
+                expr_rhs,
+                arith_op_type,
+                ..
+            } => self.eval_arith(expr_lhs, expr_rhs, arith_op_type, frame),
+            Expression::Neg { expr, .. } => match self.eval_int(expr, frame)?.checked_neg() {
+                Some(val) => Ok(Value::Int(val)),
+                None => Err(Stop::Error("Arithmetic overflow".to_string())),
+            },
+            Expression::Comp {
+                expr_lhs,
+                expr_rhs,
+                comp_type,
+                ..
+            } => {
+                let lhs = self.eval_int(expr_lhs, frame)?;
+                let rhs = self.eval_int(expr_rhs, frame)?;
+
+                Ok(Value::Bool(match comp_type {
+                    CompType::LT => lhs < rhs,
+                    CompType::LEq => lhs <= rhs,
+                }))
+            }
+            Expression::Eq {
+                expr_lhs, expr_rhs, ..
+            } => {
+                let lhs = self.eval(expr_lhs, frame)?;
+                let rhs = self.eval(expr_rhs, frame)?;
+
+                // Basic values are compared by value and other objects by identity
+                Ok(Value::Bool(match (lhs, rhs) {
+                    (Value::Void, Value::Void) => true,
+                    (Value::Int(lhs), Value::Int(rhs)) => lhs == rhs,
+                    (Value::Bool(lhs), Value::Bool(rhs)) => lhs == rhs,
+                    (Value::Str(lhs), Value::Str(rhs)) => lhs == rhs,
+                    (Value::Object(lhs), Value::Object(rhs)) => Rc::ptr_eq(&lhs, &rhs),
+                    _ => false,
+                }))
+            }
+            Expression::Not { expr, .. } => Ok(Value::Bool(!self.eval_bool(expr, frame)?)),
+            Expression::IntConst { val, .. } => Ok(Value::Int(*val as i32)),
+            Expression::BoolConst { val, .. } => Ok(Value::Bool(*val)),
+            Expression::StringConst { val, .. } => Ok(Value::Str(Rc::new(val.as_bytes().to_vec()))),
+            Expression::New { type_name, .. } => {
+                if type_name.is_self_type() {
+                    let class_name = self.class_name(&frame.self_val);
+                    self.new_object(class_name)
+                } else {
+                    self.new_object(type_name)
+                }
+            }
+            Expression::IsVoid { expr, .. } => {
+                Ok(Value::Bool(matches!(self.eval(expr, frame)?, Value::Void)))
+            }
+            Expression::NoExpr => Ok(Value::Void),
+            Expression::VarByName { name, .. } => Ok(frame.lookup(name)),
+        }
+    }
+}
@@ -0,0 +1,295 @@
+// A minimal JSON value used for machine-readable output and by the language server
+// Only integers are supported. Numbers with a fraction or exponent are truncated.
+
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(i64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>), // Members are kept in insertion order
+}
+
+impl Json {
+    // Build an object from (key, value) pairs
+    pub fn object(members: Vec<(&str, Json)>) -> Json {
+        Json::Object(
+            members
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    // Get a member of an object
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members
+                .iter()
+                .find(|(member_key, _)| member_key == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Json::Number(val) => Some(*val),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(vals) => Some(vals),
+            _ => None,
+        }
+    }
+
+    // Parse a complete JSON text
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let mut chars = text.chars().peekable();
+
+        let value = parse_value(&mut chars)?;
+
+        skip_white_space(&mut chars);
+
+        match chars.next() {
+            None => Ok(value),
+            Some(c) => Err(format!("Unexpected character '{}' after JSON value", c)),
+        }
+    }
+}
+
+fn skip_white_space(chars: &mut Peekable<Chars>) {
+    while let Some(' ' | '\t' | '\n' | '\r') = chars.peek() {
+        chars.next();
+    }
+}
+
+fn expect_word(chars: &mut Peekable<Chars>, word: &str) -> Result<(), String> {
+    for expected in word.chars() {
+        if chars.next() != Some(expected) {
+            return Err(format!("Expected {}", word));
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_value(chars: &mut Peekable<Chars>) -> Result<Json, String> {
+    skip_white_space(chars);
+
+    match chars.peek() {
+        Some('n') => expect_word(chars, "null").map(|_| Json::Null),
+        Some('t') => expect_word(chars, "true").map(|_| Json::Bool(true)),
+        Some('f') => expect_word(chars, "false").map(|_| Json::Bool(false)),
+        Some('"') => parse_str(chars).map(Json::String),
+        Some('[') => {
+            chars.next();
+
+            let mut vals = Vec::new();
+
+            skip_white_space(chars);
+
+            if chars.peek() == Some(&']') {
+                chars.next();
+                return Ok(Json::Array(vals));
+            }
+
+            loop {
+                vals.push(parse_value(chars)?);
+
+                skip_white_space(chars);
+
+                match chars.next() {
+                    Some(',') => {}
+                    Some(']') => return Ok(Json::Array(vals)),
+                    _ => return Err("Expected , or ] in array".to_string()),
+                }
+            }
+        }
+        Some('{') => {
+            chars.next();
+
+            let mut members = Vec::new();
+
+            skip_white_space(chars);
+
+            if chars.peek() == Some(&'}') {
+                chars.next();
+                return Ok(Json::Object(members));
+            }
+
+            loop {
+                skip_white_space(chars);
+
+                if chars.peek() != Some(&'"') {
+                    return Err("Expected a string as an object key".to_string());
+                }
+
+                let key = parse_str(chars)?;
+
+                skip_white_space(chars);
+
+                if chars.next() != Some(':') {
+                    return Err("Expected : in object".to_string());
+                }
+
+                members.push((key, parse_value(chars)?));
+
+                skip_white_space(chars);
+
+                match chars.next() {
+                    Some(',') => {}
+                    Some('}') => return Ok(Json::Object(members)),
+                    _ => return Err("Expected , or } in object".to_string()),
+                }
+            }
+        }
+        Some('-' | '0'..='9') => parse_number(chars),
+        Some(c) => Err(format!("Unexpected character '{}' in JSON", c)),
+        None => Err("Unexpected end of JSON".to_string()),
+    }
+}
+
+fn parse_number(chars: &mut Peekable<Chars>) -> Result<Json, String> {
+    let mut digits = String::new();
+
+    if chars.peek() == Some(&'-') {
+        digits.push('-');
+        chars.next();
+    }
+
+    while let Some(c @ '0'..='9') = chars.peek() {
+        digits.push(*c);
+        chars.next();
+    }
+
+    // The fraction and exponent are dropped
+    while let Some('.' | 'e' | 'E' | '+' | '-' | '0'..='9') = chars.peek() {
+        chars.next();
+    }
+
+    match digits.parse::<i64>() {
+        Ok(val) => Ok(Json::Number(val)),
+        Err(_) => Err(format!("Bad number {}", digits)),
+    }
+}
+
+fn parse_hex4(chars: &mut Peekable<Chars>) -> Result<u32, String> {
+    let digits: String = chars.by_ref().take(4).collect();
+
+    match u32::from_str_radix(&digits, 16) {
+        Ok(val) if digits.len() == 4 => Ok(val),
+        _ => Err(format!("Bad unicode escape {}", digits)),
+    }
+}
+
+fn parse_str(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    chars.next(); // Opening quote
+
+    let mut s = String::new();
+
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(s),
+            Some('\\') => match chars.next() {
+                Some('"') => s.push('"'),
+                Some('\\') => s.push('\\'),
+                Some('/') => s.push('/'),
+                Some('b') => s.push('\x08'),
+                Some('f') => s.push('\x0c'),
+                Some('n') => s.push('\n'),
+                Some('r') => s.push('\r'),
+                Some('t') => s.push('\t'),
+                Some('u') => {
+                    let mut code = parse_hex4(chars)?;
+
+                    // Characters outside the BMP are written as surrogate pairs
+                    if (0xd800..0xdc00).contains(&code) {
+                        expect_word(chars, "\\u")?;
+                        let low = parse_hex4(chars)?;
+                        code =
+                            0x10000 + ((code - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff);
+                    }
+
+                    s.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                }
+                _ => return Err("Bad escape in string".to_string()),
+            },
+            Some(c) => s.push(c),
+            None => return Err("Unterminated string".to_string()),
+        }
+    }
+}
+
+// Write a string with the escapes required by JSON
+fn write_str(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+
+    write!(f, "\"")
+}
+
+// Serialize on a single line
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(val) => write!(f, "{}", val),
+            Json::Number(val) => write!(f, "{}", val),
+            Json::String(val) => write_str(f, val),
+            Json::Array(vals) => {
+                write!(f, "[")?;
+
+                for (i, val) in vals.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+
+                    write!(f, "{}", val)?;
+                }
+
+                write!(f, "]")
+            }
+            Json::Object(members) => {
+                write!(f, "{{")?;
+
+                for (i, (key, val)) in members.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+
+                    write_str(f, key)?;
+                    write!(f, ":{}", val)?;
+                }
+
+                write!(f, "}}")
+            }
+        }
+    }
+}
@@ -3,14 +3,15 @@ mod process;
 
 use std::cmp::{Ord, Ordering};
 use std::collections::VecDeque;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{self, BufRead};
 
 use regex::Regex;
 
 use self::patterns::{comment_pats, initial_pats, quote_pats, PatName, State};
 use self::process::process;
-use crate::token::Token;
+use crate::diagnostics::{Diagnostics, ErrorCode};
+use crate::span::Span;
+use crate::token::{Comment, ErrorToken, Token};
 
 // Represents a match of a regular expression and the length
 #[derive(Clone, Copy)]
@@ -65,78 +66,238 @@ fn search_pats(pats: &[(Regex, PatName)], remain: &str) -> Match {
     *v.iter().min().unwrap()
 }
 
-// Transform a file to a queue of tokens
-pub fn tokenize(in_file: File, in_file_name: &str) -> Result<VecDeque<Token>, String> {
+// Transform the text of a source file to a queue of tokens
+// Errors in the source are recorded in diagnostics and the offending characters are skipped.
+pub fn tokenize(
+    in_file_text: &str,
+    in_file_name: &str,
+    diagnostics: &mut Diagnostics,
+) -> VecDeque<Token> {
+    // Reading from a string cannot fail
+    tokenize_reader(in_file_text.as_bytes(), in_file_name, diagnostics).unwrap()
+}
+
+// Transform the text of a source file to a queue of tokens and also return its comments
+// The comments are in source order. The comments before a token are those between the
+// end of the previous token and its start, so they are found by comparing spans.
+pub fn tokenize_with_comments(
+    in_file_text: &str,
+    in_file_name: &str,
+    diagnostics: &mut Diagnostics,
+) -> (VecDeque<Token>, Vec<Comment>) {
+    let mut comments: Vec<Comment> = Vec::new();
+
+    // Reading from a string cannot fail
+    let tokens = lex(
+        in_file_text.as_bytes(),
+        in_file_name,
+        &mut comments,
+        diagnostics,
+    )
+    .unwrap();
+
+    (tokens, comments)
+}
+
+// Transform a source read from in_file to a queue of tokens
+// Only a failure to read the source is returned as an Err.
+pub fn tokenize_reader<R: BufRead>(
+    in_file: R,
+    in_file_name: &str,
+    diagnostics: &mut Diagnostics,
+) -> Result<VecDeque<Token>, String> {
+    lex(in_file, in_file_name, &mut Vec::new(), diagnostics)
+}
+
+// Comments are added to comments in the order they appear
+fn lex<R: BufRead>(
+    mut in_file: R,
+    in_file_name: &str,
+    comments: &mut Vec<Comment>,
+    diagnostics: &mut Diagnostics,
+) -> Result<VecDeque<Token>, String> {
     let mut tokens: VecDeque<Token> = VecDeque::new();
 
     let mut state_stack: Vec<State> = vec![State::Normal]; // Top state determines lexer behaviour
     let mut working_str: String = String::new(); // used to store string constant
+    let mut working_span: Span = Span::default(); // Where the string constant began
 
     // Sets of patterns for each state
     let initial_pats = initial_pats();
     let comment_pats = comment_pats();
     let quote_pats = quote_pats();
 
-    let mut line_no: i16 = 0; // Tracks the current line number being processed
-
-    let mut buff_reader = BufReader::new(in_file);
+    let mut line_no: u32 = 0; // Tracks the current line number being processed
+    let mut line_lo: u32 = 0; // Byte offset of the start of the current line
+    let mut last_line_len: u32 = 0; // Length of the last line without its new line
+    let mut ends_with_new_line = true; // An empty file is treated as ending with a new line
 
     loop {
         let mut line = String::new();
         let mut start = 0; // the location in the current input string
 
+        let line_len = match in_file.read_line(&mut line) {
+            Ok(0) => break, // No more lines
+            Ok(line_len) => line_len,
+            Err(why) => return Err(format!("Cannot read {}: {}", in_file_name, why)),
+        };
+
         line_no += 1;
+        ends_with_new_line = line.ends_with('\n');
+        last_line_len = line.trim_end_matches('\n').len() as u32;
 
-        match buff_reader.read_line(&mut line) {
-            Ok(0) => break, // No more lines
-            Ok(_) => {
-                // Only ASCII characters are valid in source
-                for b in line.as_bytes().iter() {
-                    if b & 0x80 != 0x00 {
-                        return Err(format!(
-                            "{} : {} - Non-ASCII character in source",
-                            in_file_name, line_no
-                        ));
+        // Only ASCII characters are valid in source
+        // Report the line once and blank out the offending characters.
+        // Each byte becomes a space so that the offsets of the other tokens are kept.
+        if let Some(pos) = line.find(|c: char| !c.is_ascii()) {
+            let char_len = line[pos..].chars().next().unwrap().len_utf8() as u32;
+            let lo = line_lo + pos as u32;
+
+            diagnostics.error(
+                ErrorCode::NonAsciiChar,
+                in_file_name,
+                Span::new(lo, lo + char_len, line_no, pos as u32 + 1),
+                "Non-ASCII character in source".to_string(),
+            );
+
+            line = line
+                .chars()
+                .map(|c| {
+                    if c.is_ascii() {
+                        c.to_string()
+                    } else {
+                        " ".repeat(c.len_utf8())
                     }
-                }
-
-                // While there is remaining data in the input line
-                while line.len() > start {
-                    // get the next match
-                    let Match { pat_name, len } = match state_stack.last().unwrap() {
-                        State::Normal => search_pats(&initial_pats, &line[start..]),
-                        State::Comment => search_pats(&comment_pats, &line[start..]),
-                        State::Quote => search_pats(&quote_pats, &line[start..]),
-                    };
-
-                    process(
-                        in_file_name,
-                        line_no,
-                        pat_name,
-                        &line[start..start + len],
-                        &mut state_stack,
-                        &mut tokens,
-                        &mut working_str,
-                    )?;
-
-                    start += len;
-                }
-            }
-            Err(s) => return Err(format!("{} : {} - {}", in_file_name, line_no, s)),
+                })
+                .collect();
+        }
+
+        // While there is remaining data in the input line
+        while line.len() > start {
+            // get the next match
+            let Match { pat_name, len } = match state_stack.last().unwrap() {
+                State::Normal => search_pats(&initial_pats, &line[start..]),
+                State::Comment => search_pats(&comment_pats, &line[start..]),
+                State::Quote => search_pats(&quote_pats, &line[start..]),
+            };
+
+            let lo = line_lo + start as u32;
+            let span = Span::new(lo, lo + len as u32, line_no, start as u32 + 1);
+
+            process(
+                in_file_name,
+                span,
+                pat_name,
+                &line[start..start + len],
+                &mut state_stack,
+                &mut tokens,
+                &mut working_str,
+                &mut working_span,
+                comments,
+                diagnostics,
+            );
+
+            start += len;
         }
+
+        line_lo += line_len as u32;
     }
 
+    // The end of the file is on a line of its own only if the last line ends with a new line
+    let end_col = if ends_with_new_line {
+        line_no += 1;
+        1
+    } else {
+        last_line_len + 1
+    };
+
+    let end_span = Span::new(line_lo, line_lo, line_no, end_col); // The end of the file
+
     // Ensure that the file ended in Normal state
     match state_stack.last().unwrap() {
-        State::Normal => tokens.push_back(Token::End { line_no }), // Needed for the parser
-        State::Comment => return Err(format!("{} : {} - EOF in comment", in_file_name, line_no)),
-        State::Quote => {
-            return Err(format!(
-                "{} : {} - EOF in string constant",
-                in_file_name, line_no
-            ))
-        }
+        State::Normal => {}
+        State::Comment => diagnostics.error(
+            ErrorCode::EofInComment,
+            in_file_name,
+            end_span,
+            "EOF in comment".to_string(),
+        ),
+        State::Quote => diagnostics.error(
+            ErrorCode::EofInString,
+            in_file_name,
+            end_span,
+            "EOF in string constant".to_string(),
+        ),
     };
 
+    tokens.push_back(Token::End { span: end_span }); // Needed for the parser
+
     Ok(tokens)
 }
+
+// Write the tokens of a file in the format of the reference lexer:
+//
+// #name "test.cl"
+// #1 CLASS
+// #1 TYPEID Main
+//
+// The errors found while tokenizing the file are written in place as ERROR tokens.
+pub fn dump_tokens(
+    out_file: &mut dyn io::Write,
+    in_file_text: &str,
+    in_file_name: &str,
+    tokens: &VecDeque<Token>,
+    diagnostics: &Diagnostics,
+) -> Result<(), io::Error> {
+    // (position, line, text) of each line of output
+    let mut lines: Vec<(u32, u32, String)> = tokens
+        .iter()
+        .filter(|token| !matches!(token, Token::End { .. }))
+        .map(|token| {
+            let span = token.get_span();
+
+            // Integers are written as they appear in the source (e.g. with leading zeros)
+            let text = match token {
+                Token::IntConst { .. } => format!(
+                    "INT_CONST {}",
+                    in_file_text
+                        .get(span.lo as usize..span.hi as usize)
+                        .unwrap_or_default()
+                ),
+                _ => token.to_string(),
+            };
+
+            (span.lo, span.line_no, text)
+        })
+        .collect();
+
+    for diagnostic in diagnostics.diagnostics() {
+        let span = match &diagnostic.loc {
+            Some(loc) => loc.span,
+            None => continue,
+        };
+
+        // The reference lexer gives the offending character rather than a message,
+        // and it keeps integers of any size
+        let msg = match diagnostic.code {
+            ErrorCode::IntOverflow => continue,
+            ErrorCode::UnexpectedChar => in_file_text
+                .get(span.lo as usize..span.hi as usize)
+                .unwrap_or_default(),
+            _ => &diagnostic.msg,
+        };
+
+        lines.push((span.lo, span.line_no, ErrorToken(msg).to_string()));
+    }
+
+    // The sort is stable so that an error follows the tokens at the same position
+    lines.sort_by_key(|(lo, _, _)| *lo);
+
+    writeln!(out_file, "#name \"{}\"", in_file_name)?;
+
+    for (_, line_no, text) in lines.iter() {
+        writeln!(out_file, "#{} {}", line_no, text)?;
+    }
+
+    Ok(())
+}
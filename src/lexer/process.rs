@@ -2,122 +2,155 @@ use std::collections::VecDeque;
 
 use super::patterns::PatName;
 use super::State;
-use crate::token::Token;
+use crate::diagnostics::{Diagnostics, ErrorCode};
+use crate::span::Span;
+use crate::token::{Comment, Token};
 
 // When passes the information about pattern match,
 // Update tokens, working_str, and state_stack as appropriate
+// Errors are recorded in diagnostics and lexing continues after the offending lexeme.
 
+#[allow(clippy::too_many_arguments)]
 pub fn process(
     in_file_name: &str,
-    line_no: i16,
+    span: Span,
     pat_name: PatName,
     lexeme: &str,
     state_stack: &mut Vec<State>,
     tokens: &mut VecDeque<Token>,
     working_str: &mut String,
-) -> Result<(), String> {
+    working_span: &mut Span,
+    comments: &mut Vec<Comment>,
+    diagnostics: &mut Diagnostics,
+) {
     match pat_name {
         // In normal state, add a based on the pattern match token
-        PatName::NormalAdd => tokens.push_back(Token::Add { line_no }),
-        PatName::NormalDiv => tokens.push_back(Token::Div { line_no }),
-        PatName::NormalSub => tokens.push_back(Token::Sub { line_no }),
-        PatName::NormalMul => tokens.push_back(Token::Mul { line_no }),
-        PatName::NormalEq => tokens.push_back(Token::Eq { line_no }),
-        PatName::NormalLT => tokens.push_back(Token::LT { line_no }),
-        PatName::NormalDot => tokens.push_back(Token::Dot { line_no }),
-        PatName::NormalNeg => tokens.push_back(Token::Neg { line_no }),
-        PatName::NormalComma => tokens.push_back(Token::Comma { line_no }),
-        PatName::NormalSemiColon => tokens.push_back(Token::SemiColon { line_no }),
-        PatName::NormalColon => tokens.push_back(Token::Colon { line_no }),
-        PatName::NormalOpenParen => tokens.push_back(Token::OpenParen { line_no }),
-        PatName::NormalCloseParen => tokens.push_back(Token::CloseParen { line_no }),
-        PatName::NormalAt => tokens.push_back(Token::At { line_no }),
-        PatName::NormalOpenBrace => tokens.push_back(Token::OpenBrace { line_no }),
-        PatName::NormalCloseBrace => tokens.push_back(Token::CloseBrace { line_no }),
+        PatName::NormalAdd => tokens.push_back(Token::Add { span }),
+        PatName::NormalDiv => tokens.push_back(Token::Div { span }),
+        PatName::NormalSub => tokens.push_back(Token::Sub { span }),
+        PatName::NormalMul => tokens.push_back(Token::Mul { span }),
+        PatName::NormalEq => tokens.push_back(Token::Eq { span }),
+        PatName::NormalLT => tokens.push_back(Token::LT { span }),
+        PatName::NormalDot => tokens.push_back(Token::Dot { span }),
+        PatName::NormalNeg => tokens.push_back(Token::Neg { span }),
+        PatName::NormalComma => tokens.push_back(Token::Comma { span }),
+        PatName::NormalSemiColon => tokens.push_back(Token::SemiColon { span }),
+        PatName::NormalColon => tokens.push_back(Token::Colon { span }),
+        PatName::NormalOpenParen => tokens.push_back(Token::OpenParen { span }),
+        PatName::NormalCloseParen => tokens.push_back(Token::CloseParen { span }),
+        PatName::NormalAt => tokens.push_back(Token::At { span }),
+        PatName::NormalOpenBrace => tokens.push_back(Token::OpenBrace { span }),
+        PatName::NormalCloseBrace => tokens.push_back(Token::CloseBrace { span }),
         PatName::NormalWhiteSpace => {} // Take no action for a white space
-        PatName::NormalClass => tokens.push_back(Token::Class { line_no }),
-        PatName::NormalElse => tokens.push_back(Token::Else { line_no }),
-        PatName::NormalFi => tokens.push_back(Token::Fi { line_no }),
-        PatName::NormalIf => tokens.push_back(Token::If { line_no }),
-        PatName::NormalIn => tokens.push_back(Token::In { line_no }),
-        PatName::NormalInherits => tokens.push_back(Token::Inherits { line_no }),
-        PatName::NormalLet => tokens.push_back(Token::Let { line_no }),
-        PatName::NormalLoop => tokens.push_back(Token::Loop { line_no }),
-        PatName::NormalPool => tokens.push_back(Token::Pool { line_no }),
-        PatName::NormalThen => tokens.push_back(Token::Then { line_no }),
-        PatName::NormalWhile => tokens.push_back(Token::While { line_no }),
-        PatName::NormalCase => tokens.push_back(Token::Case { line_no }),
-        PatName::NormalEsac => tokens.push_back(Token::Esac { line_no }),
-        PatName::NormalOf => tokens.push_back(Token::Of { line_no }),
-        PatName::NormalNew => tokens.push_back(Token::New { line_no }),
-        PatName::NormalIsVoid => tokens.push_back(Token::IsVoid { line_no }),
-        PatName::NormalNot => tokens.push_back(Token::Not { line_no }),
-        PatName::NormalDArrow => tokens.push_back(Token::DArrow { line_no }),
-        PatName::NormalLE => tokens.push_back(Token::LEq { line_no }),
-        PatName::NormalAssign => tokens.push_back(Token::Assign { line_no }),
-        PatName::NormalTrue => tokens.push_back(Token::BoolConst { line_no, val: true }),
-        PatName::NormalFalse => tokens.push_back(Token::BoolConst {
-            line_no,
-            val: false,
-        }),
+        PatName::NormalClass => tokens.push_back(Token::Class { span }),
+        PatName::NormalElse => tokens.push_back(Token::Else { span }),
+        PatName::NormalFi => tokens.push_back(Token::Fi { span }),
+        PatName::NormalIf => tokens.push_back(Token::If { span }),
+        PatName::NormalIn => tokens.push_back(Token::In { span }),
+        PatName::NormalInherits => tokens.push_back(Token::Inherits { span }),
+        PatName::NormalLet => tokens.push_back(Token::Let { span }),
+        PatName::NormalLoop => tokens.push_back(Token::Loop { span }),
+        PatName::NormalPool => tokens.push_back(Token::Pool { span }),
+        PatName::NormalThen => tokens.push_back(Token::Then { span }),
+        PatName::NormalWhile => tokens.push_back(Token::While { span }),
+        PatName::NormalCase => tokens.push_back(Token::Case { span }),
+        PatName::NormalEsac => tokens.push_back(Token::Esac { span }),
+        PatName::NormalOf => tokens.push_back(Token::Of { span }),
+        PatName::NormalNew => tokens.push_back(Token::New { span }),
+        PatName::NormalIsVoid => tokens.push_back(Token::IsVoid { span }),
+        PatName::NormalNot => tokens.push_back(Token::Not { span }),
+        PatName::NormalDArrow => tokens.push_back(Token::DArrow { span }),
+        PatName::NormalLE => tokens.push_back(Token::LEq { span }),
+        PatName::NormalAssign => tokens.push_back(Token::Assign { span }),
+        PatName::NormalTrue => tokens.push_back(Token::BoolConst { span, val: true }),
+        PatName::NormalFalse => tokens.push_back(Token::BoolConst { span, val: false }),
         PatName::NormalTypeID => tokens.push_back(Token::TypeID {
-            line_no,
+            span,
             type_name: lexeme.to_string(),
         }),
         PatName::NormalObjectID => tokens.push_back(Token::ObjectID {
-            line_no,
+            span,
             obj_name: lexeme.to_string(),
         }),
         PatName::NormalIntConst => match lexeme.parse::<u32>() {
-            Ok(val) => tokens.push_back(Token::IntConst { line_no, val }),
+            Ok(val) => tokens.push_back(Token::IntConst { span, val }),
             Err(_) => {
-                return Err(
-                    format!( // If the string of digits cannot be represented by a u32
-                    "{} : {} - {} exceedes 32 bits",
-                    in_file_name, line_no, lexeme
-                ),
+                // If the string of digits cannot be represented by a u32
+                diagnostics.error(
+                    ErrorCode::IntOverflow,
+                    in_file_name,
+                    span,
+                    format!("{} exceedes 32 bits", lexeme),
                 );
+
+                // Keep the token so that the parser is not thrown off
+                tokens.push_back(Token::IntConst { span, val: 0 });
             }
         },
-        PatName::NormalDashComment => {} // Ignore comment
-        PatName::NormalOpenComment => state_stack.push(State::Comment), // Enter Comment state
+        PatName::NormalDashComment => comments.push(Comment {
+            span,
+            text: lexeme.trim_end().to_string(),
+        }),
+        PatName::NormalOpenComment => {
+            state_stack.push(State::Comment); // Enter Comment state
+            comments.push(Comment {
+                span,
+                text: lexeme.to_string(),
+            });
+        }
         PatName::NormalCloseComment => {
             // Error if there is a close comment without an open comment
-            return Err(format!("{} : {} - Unmatched *)", in_file_name, line_no));
+            diagnostics.error(
+                ErrorCode::UnmatchedComment,
+                in_file_name,
+                span,
+                "Unmatched *)".to_string(),
+            );
         }
         PatName::NormalQuote => {
             // Begin a string
             state_stack.push(State::Quote); // Enter Quote state
             working_str.clear();
+            *working_span = span;
         }
         PatName::NormalBadChar => {
             // Any other unrecognized character is an error
-            return Err(format!(
-                "{} : {} -  Unexpected character : '{}'",
-                in_file_name, line_no, lexeme
-            ));
+            // The character is skipped
+            diagnostics.error(
+                ErrorCode::UnexpectedChar,
+                in_file_name,
+                span,
+                format!("Unexpected character : '{}'", lexeme),
+            );
         }
 
-        PatName::CommentOpenComment => state_stack.push(State::Comment), // (* .. *) comments can be nested
+        PatName::CommentOpenComment => {
+            state_stack.push(State::Comment); // (* .. *) comments can be nested
+            extend_comment(comments, span, lexeme);
+        }
         PatName::CommentCloseComment => {
             // Exit the current state if the comment ends
             state_stack.pop();
+            extend_comment(comments, span, lexeme);
         }
-        PatName::CommentChar => {} // Ignore all other characters
+        PatName::CommentChar => extend_comment(comments, span, lexeme),
 
         PatName::QuoteNull | PatName::QuoteEscNull => {
             // NUL characters are not allowed in quotes
-            return Err(format!(
-                "{} : {} - String contains null character.",
-                in_file_name, line_no
-            ));
+            // The character is left out of the string
+            diagnostics.error(
+                ErrorCode::NullInString,
+                in_file_name,
+                span,
+                "String contains null character.".to_string(),
+            );
         }
         PatName::QuoteQuote => {
             // When the quote ends,
             state_stack.pop(); // exit the quote state
             tokens.push_back(Token::StrConst {
                 // Add a string constant
-                line_no,
+                span: working_span.to(span),
                 val: working_str.clone(),
             })
         }
@@ -129,13 +162,23 @@ pub fn process(
         PatName::QuoteEscChar => working_str.push_str(&lexeme[1..2]), // Any other escaped character
         PatName::QuoteNewLine => {
             // In case of an unescaped new line - raise an error
+            // Lexing resumes on the next line
             state_stack.pop();
-            return Err(format!(
-                "{} : {} - Unterminated string constant",
-                in_file_name, line_no
-            ));
+            diagnostics.error(
+                ErrorCode::UnterminatedString,
+                in_file_name,
+                working_span.to(span),
+                "Unterminated string constant".to_string(),
+            );
         }
     };
+}
 
-    Ok(())
+// Add to the (* .. *) comment that is being read
+// It is always the last comment.
+fn extend_comment(comments: &mut [Comment], span: Span, lexeme: &str) {
+    if let Some(comment) = comments.last_mut() {
+        comment.span = comment.span.to(span);
+        comment.text.push_str(lexeme);
+    }
 }
@@ -0,0 +1,355 @@
+// The COOL compiler as a library
+// Each stage of the pipeline is public so that tools can run only the stages they need.
+// compile() runs the whole pipeline on in-memory sources.
+
+pub mod ast;
+pub mod ast_dump;
+pub mod ast_read;
+pub mod builtins;
+pub mod bytecode;
+pub mod cgen;
+pub mod cgen_c;
+pub mod cgen_llvm;
+pub mod cgen_wasm;
+pub mod cgen_x86_64;
+pub mod diagnostics;
+pub mod formatter;
+pub mod interp;
+pub mod json;
+pub mod lexer;
+pub mod lsp;
+pub mod mips;
+pub mod parser;
+pub mod render;
+pub mod repl;
+pub mod riscv;
+mod scoped_collections;
+pub mod semant;
+pub mod span;
+pub mod token;
+
+use indexmap::{IndexMap, IndexSet};
+use std::str::FromStr;
+
+use crate::ast::{Class, TypeID};
+use crate::ast_read::read_ast;
+use crate::builtins::initialize_classes;
+use crate::cgen::{cgen, GarbageCollector, Isa};
+use crate::diagnostics::Diagnostics;
+use crate::lexer::tokenize;
+use crate::parser::parse;
+use crate::semant::semant;
+
+pub const DEFAULT_MAX_ERRORS: usize = 100;
+
+// A source file held in memory
+// The name is used in error messages and in runtime error messages.
+pub struct Source<'a> {
+    pub name: &'a str,
+    pub text: &'a str,
+}
+
+// The language the sources are written in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputFormat {
+    Cool, // COOL source
+    Ast,  // The output of the reference parser or semantic analyzer
+}
+
+impl FromStr for InputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<InputFormat, String> {
+        match s {
+            "cool" => Ok(InputFormat::Cool),
+            "ast" => Ok(InputFormat::Ast),
+            _ => Err(format!("Unknown input format {}", s)),
+        }
+    }
+}
+
+// The machine (or language) that the generated code is for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+    Mips,     // SPIM with trap.handler (or coolc run)
+    Riscv32,  // RV32IM Linux, linked with runtime/riscv32.s (or coolc run)
+    X86_64,   // Linux, linked with runtime/x86_64.c
+    C,        // C source that includes its runtime (runtime/c.c)
+    Bytecode, // A .coolbc file for the virtual machine in bytecode (or coolc run)
+    Wasm,     // A WebAssembly text module that includes its runtime (runtime/wasm.wat)
+}
+
+impl Target {
+    // The extension of the file that the output is written to by default
+    pub fn extension(self) -> &'static str {
+        match self {
+            Target::C => "c",
+            Target::Bytecode => bytecode::FILE_EXTENSION,
+            Target::Wasm => "wat",
+            _ => "s",
+        }
+    }
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Target, String> {
+        match s {
+            "mips" => Ok(Target::Mips),
+            "riscv32" => Ok(Target::Riscv32),
+            "x86-64" => Ok(Target::X86_64),
+            "c" => Ok(Target::C),
+            "bytecode" => Ok(Target::Bytecode),
+            "wasm" => Ok(Target::Wasm),
+            _ => Err(format!("Unknown target {}", s)),
+        }
+    }
+}
+
+// The form of the generated code
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Emit {
+    Asm,    // The output of the target (assembly, C, bytecode or WebAssembly)
+    LlvmIr, // LLVM IR for a 64-bit machine, linked with runtime/x86_64.c (the target is ignored)
+}
+
+impl FromStr for Emit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Emit, String> {
+        match s {
+            "asm" => Ok(Emit::Asm),
+            "llvm-ir" => Ok(Emit::LlvmIr),
+            _ => Err(format!("Unknown output form {}", s)),
+        }
+    }
+}
+
+// The last stage of the pipeline that compile() runs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    Parse,   // Check syntax only
+    Check,   // Check syntax and semantics
+    Codegen, // Generate assembly
+}
+
+pub struct CompileOptions {
+    pub max_errors: usize, // Stop after this many errors (0 for no limit)
+    pub input_format: InputFormat,
+    pub stop_after: Stage,
+    pub target: Target,
+    pub emit: Emit,
+    pub gc: GarbageCollector, // Only for MIPS
+    pub gc_test: bool,        // Collect garbage at every allocation
+}
+
+impl Default for CompileOptions {
+    fn default() -> CompileOptions {
+        CompileOptions {
+            max_errors: DEFAULT_MAX_ERRORS,
+            input_format: InputFormat::Cool,
+            stop_after: Stage::Codegen,
+            target: Target::Mips,
+            emit: Emit::Asm,
+            gc: GarbageCollector::None,
+            gc_test: false,
+        }
+    }
+}
+
+pub struct Compilation {
+    pub assembly: Option<String>, // None if there were errors or code generation was not requested
+    pub bytecode: Option<Vec<u8>>, // Instead of assembly for the bytecode target
+    pub diagnostics: Diagnostics,
+}
+
+// Tokenize and parse COOL sources into classes
+// The result also contains the basic classes (Object, IO, Int, Bool, String).
+pub fn parse_sources(sources: &[Source], diagnostics: &mut Diagnostics) -> IndexMap<TypeID, Class> {
+    // classes is a map to store the Class objects.
+    let mut classes: IndexMap<TypeID, Class> = initialize_classes();
+
+    // File numbers are stored starting at 1.
+    for (source, file_no) in sources.iter().zip(1..) {
+        // Tokenize the file
+        let tokens = tokenize(source.text, source.name, diagnostics);
+
+        // Parse the tokens into class definitions.
+        // The classes are added to the classes map.
+        parse(tokens, source.name, file_no, &mut classes, diagnostics);
+
+        if diagnostics.limit_reached() {
+            break;
+        }
+    }
+
+    classes
+}
+
+// Read sources in the reference AST format into classes
+// Also returns the names of the COOL source files named in the AST (in file number order).
+pub fn read_ast_sources(
+    sources: &[Source],
+    diagnostics: &mut Diagnostics,
+) -> (IndexMap<TypeID, Class>, Vec<String>) {
+    let mut classes: IndexMap<TypeID, Class> = initialize_classes();
+    let mut file_nos: IndexMap<String, u32> = IndexMap::new();
+
+    for source in sources.iter() {
+        read_ast(
+            source.text,
+            source.name,
+            &mut classes,
+            &mut file_nos,
+            diagnostics,
+        );
+
+        if diagnostics.limit_reached() {
+            break;
+        }
+    }
+
+    (classes, file_nos.into_keys().collect())
+}
+
+// Read sources with the front end for their format
+// Also returns the names of the source files (in file number order).
+pub fn read_sources(
+    sources: &[Source],
+    input_format: InputFormat,
+    diagnostics: &mut Diagnostics,
+) -> (IndexMap<TypeID, Class>, Vec<String>) {
+    match input_format {
+        InputFormat::Cool => {
+            let classes = parse_sources(sources, diagnostics);
+            let in_file_names = sources
+                .iter()
+                .map(|source| source.name.to_string())
+                .collect();
+
+            (classes, in_file_names)
+        }
+        InputFormat::Ast => read_ast_sources(sources, diagnostics),
+    }
+}
+
+// Compile COOL sources for options.target (or to LLVM IR with Emit::LlvmIr)
+// The output is assembly or text in Compilation::assembly, except bytecode, which is in
+// Compilation::bytecode.
+// Errors in the program are reported in the diagnostics of the result.
+pub fn compile(sources: &[Source], options: &CompileOptions) -> Result<Compilation, String> {
+    // Errors from all stages are collected here
+    let mut diagnostics = Diagnostics::new(options.max_errors);
+
+    let (classes, in_file_names) = read_sources(sources, options.input_format, &mut diagnostics);
+
+    // Semantic analysis is not attempted on a program with syntax errors
+    if diagnostics.has_errors() || options.stop_after == Stage::Parse {
+        return Ok(Compilation {
+            assembly: None,
+            bytecode: None,
+            diagnostics,
+        });
+    }
+
+    // Add semantic information to classes (see semant/mod.rs for more detail)
+    // int_table : set of integer constants specified in the source
+    // str_table : vector of string constants specified in the source
+    let (classes, mut int_table, str_table): (IndexMap<TypeID, Class>, IndexSet<u32>, Vec<String>) =
+        match semant(classes, &mut diagnostics) {
+            Some(result) => result,
+            None => {
+                return Ok(Compilation {
+                    assembly: None,
+                    bytecode: None,
+                    diagnostics,
+                })
+            }
+        };
+
+    if options.stop_after == Stage::Check {
+        return Ok(Compilation {
+            assembly: None,
+            bytecode: None,
+            diagnostics,
+        });
+    }
+
+    // add lengths of source files to int_table
+    for in_file_name in in_file_names.iter() {
+        int_table.insert(in_file_name.len() as u32);
+    }
+
+    // add lengths of class names to int_table
+    for class_name in classes.keys() {
+        int_table.insert(class_name.len() as u32);
+    }
+
+    // convert int_table into sorted vector
+    let mut int_table: Vec<u32> = int_table.into_iter().collect();
+    int_table.sort_unstable();
+
+    // Code generation - write out valid assembly for the target
+    let mut out: Vec<u8> = Vec::new();
+
+    match options.target {
+        _ if options.emit == Emit::LlvmIr => {
+            if options.gc != GarbageCollector::None || options.gc_test {
+                return Err("Garbage collection is only available for MIPS".to_string());
+            }
+
+            cgen_llvm::cgen(&mut out, &in_file_names, &classes, &int_table, &str_table)?
+        }
+        Target::Mips => cgen(
+            &mut out,
+            &in_file_names,
+            &classes,
+            &int_table,
+            &str_table,
+            Isa::Mips,
+            options.gc,
+            options.gc_test,
+        )?,
+        _ if options.gc != GarbageCollector::None || options.gc_test => {
+            return Err("Garbage collection is only available for MIPS".to_string());
+        }
+        Target::Riscv32 => cgen(
+            &mut out,
+            &in_file_names,
+            &classes,
+            &int_table,
+            &str_table,
+            Isa::Riscv32,
+            GarbageCollector::None,
+            false,
+        )?,
+        Target::X86_64 => {
+            cgen_x86_64::cgen(&mut out, &in_file_names, &classes, &int_table, &str_table)?
+        }
+        Target::C => cgen_c::cgen(&mut out, &in_file_names, &classes, &int_table, &str_table)?,
+        Target::Wasm => {
+            cgen_wasm::cgen(&mut out, &in_file_names, &classes, &int_table, &str_table)?
+        }
+        Target::Bytecode => {
+            let program = bytecode::generate(&classes, &in_file_names, &str_table);
+
+            return Ok(Compilation {
+                assembly: None,
+                bytecode: Some(program.to_bytes()),
+                diagnostics,
+            });
+        }
+    }
+
+    let assembly = match String::from_utf8(out) {
+        Ok(assembly) => assembly,
+        Err(_) => return Err("Generated assembly is not valid UTF-8.".to_string()),
+    };
+
+    Ok(Compilation {
+        assembly: Some(assembly),
+        bytecode: None,
+        diagnostics,
+    })
+}
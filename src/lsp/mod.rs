@@ -0,0 +1,518 @@
+// A language server for COOL
+// It speaks the Language Server Protocol (JSON-RPC) over a pair of streams (normally stdin and stdout).
+//
+// All open documents are analyzed together as one program after every change.
+// The URI of each document is used as its file name, so diagnostics and
+// definitions can be mapped back to documents.
+
+mod query;
+mod transport;
+
+use indexmap::IndexMap;
+use std::io::{BufRead, Write};
+
+use crate::ast::{Class, Expression, TypeID};
+use crate::diagnostics::{Diagnostic, Diagnostics, Location};
+use crate::json::Json;
+use crate::semant::analyze;
+use crate::span::Span;
+use crate::{parse_sources, Source, DEFAULT_MAX_ERRORS};
+
+use self::query::Completion;
+use self::transport::{parse_message, read_message, write_message};
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+
+// LSP constants
+const TEXT_DOCUMENT_SYNC_FULL: i64 = 1;
+const DIAGNOSTIC_SEVERITY_ERROR: i64 = 1;
+const COMPLETION_ITEM_KIND_METHOD: i64 = 2;
+const COMPLETION_ITEM_KIND_CLASS: i64 = 7;
+
+struct Document {
+    text: String,
+    line_starts: Vec<usize>, // Byte offset of the start of each line
+}
+
+impl Document {
+    fn new(text: String) -> Document {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        Document { text, line_starts }
+    }
+
+    // LSP positions count UTF-16 code units from the start of the line
+    fn offset(&self, line: usize, character: usize) -> u32 {
+        let start = match self.line_starts.get(line) {
+            Some(start) => *start,
+            None => return self.text.len() as u32,
+        };
+
+        let mut units = 0;
+
+        for (i, c) in self.text[start..].char_indices() {
+            if units >= character || c == '\n' {
+                return (start + i) as u32;
+            }
+
+            units += c.len_utf16();
+        }
+
+        self.text.len() as u32
+    }
+
+    fn position(&self, offset: u32) -> Json {
+        let offset = (offset as usize).min(self.text.len());
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(line) => line - 1,
+        };
+        let start = self.line_starts[line];
+
+        let character: usize = self
+            .text
+            .get(start..offset)
+            .unwrap_or("")
+            .chars()
+            .map(char::len_utf16)
+            .sum();
+
+        Json::object(vec![
+            ("line", Json::Number(line as i64)),
+            ("character", Json::Number(character as i64)),
+        ])
+    }
+
+    fn range(&self, span: Span) -> Json {
+        Json::object(vec![
+            ("start", self.position(span.lo)),
+            ("end", self.position(span.hi)),
+        ])
+    }
+
+    // The identifier at (or just before) an offset
+    fn word_at(&self, offset: u32) -> &str {
+        let is_id_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+        let offset = offset as usize;
+
+        let lo = self.text[..offset]
+            .rfind(|c: char| !is_id_char(c))
+            .map_or(0, |i| i + 1);
+        let hi = self.text[offset..]
+            .find(|c: char| !is_id_char(c))
+            .map_or(self.text.len(), |i| offset + i);
+
+        &self.text[lo..hi]
+    }
+}
+
+struct Server {
+    documents: IndexMap<String, Document>, // Open documents indexed by URI
+    // The classes of the current text if it could not be fully analyzed
+    untyped: Option<IndexMap<TypeID, Class>>,
+    // The classes of the last text that was analyzed (used for completion while typing)
+    typed: IndexMap<TypeID, Class>,
+    shutdown: bool, // Has a shutdown request been received?
+}
+
+impl Server {
+    fn new() -> Server {
+        Server {
+            documents: IndexMap::new(),
+            untyped: None,
+            typed: IndexMap::new(),
+            shutdown: false,
+        }
+    }
+
+    // The classes of the current text
+    fn classes(&self) -> &IndexMap<TypeID, Class> {
+        self.untyped.as_ref().unwrap_or(&self.typed)
+    }
+
+    // Analyze the open documents and publish their diagnostics
+    fn analyze<W: Write>(&mut self, output: &mut W) -> Result<(), String> {
+        let sources: Vec<Source> = self
+            .documents
+            .iter()
+            .map(|(uri, document)| Source {
+                name: uri,
+                text: &document.text,
+            })
+            .collect();
+
+        let mut diagnostics = Diagnostics::new(DEFAULT_MAX_ERRORS);
+
+        let classes = parse_sources(&sources, &mut diagnostics);
+
+        // Semantic analysis is not attempted on a program with syntax errors
+        self.untyped = if diagnostics.has_errors() {
+            Some(classes)
+        } else {
+            match analyze(classes, &mut diagnostics) {
+                Some((classes, _, _)) => {
+                    self.typed = classes;
+                    None
+                }
+                // The inheritance tree is invalid, so only the parsed classes are available
+                None => Some(parse_sources(&sources, &mut Diagnostics::new(0))),
+            }
+        };
+
+        let first_uri = self.documents.keys().next().cloned();
+
+        for uri in self.documents.keys() {
+            let document = &self.documents[uri];
+
+            let diagnostics: Vec<Json> = diagnostics
+                .diagnostics()
+                .iter()
+                .filter(|diagnostic| match &diagnostic.loc {
+                    Some(loc) => &loc.file_name == uri,
+                    // Errors that concern the whole program are shown in the first document
+                    None => Some(uri) == first_uri.as_ref(),
+                })
+                .map(|diagnostic| self.diagnostic_json(document, diagnostic))
+                .collect();
+
+            publish_diagnostics(output, uri, diagnostics)?;
+        }
+
+        Ok(())
+    }
+
+    fn location_json(&self, loc: &Location) -> Option<Json> {
+        let document = self.documents.get(&loc.file_name)?;
+
+        Some(Json::object(vec![
+            ("uri", Json::String(loc.file_name.clone())),
+            ("range", document.range(loc.span)),
+        ]))
+    }
+
+    fn diagnostic_json(&self, document: &Document, diagnostic: &Diagnostic) -> Json {
+        let range = match &diagnostic.loc {
+            Some(loc) => document.range(loc.span),
+            None => document.range(Span::default()),
+        };
+
+        let related_information: Vec<Json> = diagnostic
+            .notes
+            .iter()
+            .filter_map(|note| {
+                let location = self.location_json(note.loc.as_ref()?)?;
+
+                Some(Json::object(vec![
+                    ("location", location),
+                    ("message", Json::String(note.msg.clone())),
+                ]))
+            })
+            .collect();
+
+        Json::object(vec![
+            ("range", range),
+            ("severity", Json::Number(DIAGNOSTIC_SEVERITY_ERROR)),
+            ("code", Json::String(diagnostic.code.to_string())),
+            ("source", Json::String("coolc".to_string())),
+            ("message", Json::String(diagnostic.msg.clone())),
+            ("relatedInformation", Json::Array(related_information)),
+        ])
+    }
+
+    // Find the document and offset of the textDocument and position parameters
+    fn document_offset<'a>(&'a self, params: &Json) -> Option<(&'a str, &'a Document, u32)> {
+        let uri = params.get("textDocument")?.get("uri")?.as_str()?;
+        let position = params.get("position")?;
+        let line = position.get("line")?.as_i64()?;
+        let character = position.get("character")?.as_i64()?;
+
+        let (uri, document) = self.documents.get_key_value(uri)?;
+        let offset = document.offset(line.max(0) as usize, character.max(0) as usize);
+
+        Some((uri, document, offset))
+    }
+
+    fn hover(&self, params: &Json) -> Option<Json> {
+        let (uri, document, offset) = self.document_offset(params)?;
+
+        let expr = query::expr_at(self.classes(), &document.text, uri, offset)?;
+        let static_type = expr.static_type();
+
+        // Before semantic analysis there are no types to show
+        if static_type == TypeID::new_no_type() {
+            return None;
+        }
+
+        let value = match expr {
+            Expression::VarByName { name, .. } => format!("{} : {}", name, static_type),
+            _ => static_type.to_string(),
+        };
+
+        Some(Json::object(vec![
+            (
+                "contents",
+                Json::object(vec![
+                    ("kind", Json::String("plaintext".to_string())),
+                    ("value", Json::String(value)),
+                ]),
+            ),
+            ("range", document.range(expr.span())),
+        ]))
+    }
+
+    fn definition(&self, params: &Json) -> Option<Json> {
+        let (uri, document, offset) = self.document_offset(params)?;
+
+        let word = document.word_at(offset);
+
+        if word.is_empty() {
+            return None;
+        }
+
+        let definition = query::definition(self.classes(), &document.text, uri, offset, word)?;
+
+        self.location_json(&Location::new(definition.file_name, definition.span))
+    }
+
+    fn completion(&self, params: &Json) -> Option<Json> {
+        let (uri, document, offset) = self.document_offset(params)?;
+
+        let (completion, prefix) = query::completion_at(&self.typed, &document.text, uri, offset)?;
+
+        let items: Vec<Json> = match completion {
+            Completion::Methods(class_name) => query::method_signatures(&self.typed, &class_name)
+                .into_iter()
+                .filter(|(label, _)| label.starts_with(&prefix))
+                .map(|(label, detail)| {
+                    Json::object(vec![
+                        ("label", Json::String(label)),
+                        ("kind", Json::Number(COMPLETION_ITEM_KIND_METHOD)),
+                        ("detail", Json::String(detail)),
+                    ])
+                })
+                .collect(),
+            Completion::Classes => self
+                .typed
+                .keys()
+                .map(|class_name| class_name.to_string())
+                .filter(|label| label.starts_with(&prefix))
+                .map(|label| {
+                    Json::object(vec![
+                        ("label", Json::String(label)),
+                        ("kind", Json::Number(COMPLETION_ITEM_KIND_CLASS)),
+                    ])
+                })
+                .collect(),
+        };
+
+        Some(Json::Array(items))
+    }
+
+    // Handle a request
+    // Returns the result or an error code and message.
+    fn request(&mut self, method: &str, params: &Json) -> Result<Json, (i64, String)> {
+        if self.shutdown {
+            return Err((INVALID_REQUEST, "The server is shutting down.".to_string()));
+        }
+
+        match method {
+            "initialize" => Ok(initialize_result()),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Json::Null)
+            }
+            "textDocument/hover" => Ok(self.hover(params).unwrap_or(Json::Null)),
+            "textDocument/definition" => Ok(self.definition(params).unwrap_or(Json::Null)),
+            "textDocument/completion" => Ok(self.completion(params).unwrap_or(Json::Null)),
+            _ => Err((METHOD_NOT_FOUND, format!("Unknown method {}", method))),
+        }
+    }
+
+    // Handle a notification
+    // The documents are analyzed again whenever one of them changes.
+    fn notification<W: Write>(
+        &mut self,
+        method: &str,
+        params: &Json,
+        output: &mut W,
+    ) -> Result<(), String> {
+        let uri = params
+            .get("textDocument")
+            .and_then(|document| document.get("uri"))
+            .and_then(Json::as_str);
+
+        match (method, uri) {
+            ("textDocument/didOpen", Some(uri)) => {
+                let text = params
+                    .get("textDocument")
+                    .and_then(|document| document.get("text"))
+                    .and_then(Json::as_str)
+                    .unwrap_or("");
+
+                self.documents
+                    .insert(uri.to_string(), Document::new(text.to_string()));
+
+                self.analyze(output)
+            }
+            ("textDocument/didChange", Some(uri)) => {
+                // With full synchronization the last change has the whole text
+                let text = params
+                    .get("contentChanges")
+                    .and_then(Json::as_array)
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change.get("text"))
+                    .and_then(Json::as_str);
+
+                match (text, self.documents.get_mut(uri)) {
+                    (Some(text), Some(document)) => {
+                        *document = Document::new(text.to_string());
+                        self.analyze(output)
+                    }
+                    _ => Ok(()),
+                }
+            }
+            ("textDocument/didClose", Some(uri)) => {
+                self.documents.shift_remove(uri);
+
+                // Clear the diagnostics of the closed document
+                publish_diagnostics(output, uri, Vec::new())?;
+
+                self.analyze(output)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn initialize_result() -> Json {
+    Json::object(vec![
+        (
+            "capabilities",
+            Json::object(vec![
+                ("textDocumentSync", Json::Number(TEXT_DOCUMENT_SYNC_FULL)),
+                ("hoverProvider", Json::Bool(true)),
+                ("definitionProvider", Json::Bool(true)),
+                (
+                    "completionProvider",
+                    Json::object(vec![(
+                        "triggerCharacters",
+                        Json::Array(vec![
+                            Json::String(".".to_string()),
+                            Json::String("@".to_string()),
+                        ]),
+                    )]),
+                ),
+            ]),
+        ),
+        (
+            "serverInfo",
+            Json::object(vec![
+                ("name", Json::String("coolc-lsp".to_string())),
+                (
+                    "version",
+                    Json::String(env!("CARGO_PKG_VERSION").to_string()),
+                ),
+            ]),
+        ),
+    ])
+}
+
+fn publish_diagnostics<W: Write>(
+    output: &mut W,
+    uri: &str,
+    diagnostics: Vec<Json>,
+) -> Result<(), String> {
+    write_message(
+        output,
+        &Json::object(vec![
+            ("jsonrpc", Json::String("2.0".to_string())),
+            (
+                "method",
+                Json::String("textDocument/publishDiagnostics".to_string()),
+            ),
+            (
+                "params",
+                Json::object(vec![
+                    ("uri", Json::String(uri.to_string())),
+                    ("diagnostics", Json::Array(diagnostics)),
+                ]),
+            ),
+        ]),
+    )
+}
+
+fn error_response(id: Json, code: i64, msg: String) -> Json {
+    Json::object(vec![
+        ("jsonrpc", Json::String("2.0".to_string())),
+        ("id", id),
+        (
+            "error",
+            Json::object(vec![
+                ("code", Json::Number(code)),
+                ("message", Json::String(msg)),
+            ]),
+        ),
+    ])
+}
+
+// Serve requests until the client sends exit (or closes the input)
+// It is an error to exit without a shutdown request.
+pub fn serve<R: BufRead, W: Write>(mut input: R, mut output: W) -> Result<(), String> {
+    let mut server = Server::new();
+
+    while let Some(content) = read_message(&mut input)? {
+        // The id of a message that cannot be parsed is not known
+        let message = match parse_message(&content) {
+            Ok(message) => message,
+            Err(msg) => {
+                write_message(&mut output, &error_response(Json::Null, PARSE_ERROR, msg))?;
+                continue;
+            }
+        };
+
+        let method = message.get("method").and_then(Json::as_str);
+        let params = message.get("params").unwrap_or(&Json::Null);
+
+        match (method, message.get("id")) {
+            (Some("exit"), _) => break,
+            // A request
+            (Some(method), Some(id)) => {
+                let response = match server.request(method, params) {
+                    Ok(result) => Json::object(vec![
+                        ("jsonrpc", Json::String("2.0".to_string())),
+                        ("id", id.clone()),
+                        ("result", result),
+                    ]),
+                    Err((code, msg)) => error_response(id.clone(), code, msg),
+                };
+
+                write_message(&mut output, &response)?;
+            }
+            // A notification
+            (Some(method), None) => {
+                server.notification(method, params, &mut output)?;
+            }
+            // A message with an id that is neither a request nor a response
+            (None, Some(id))
+                if message.get("result").is_none() && message.get("error").is_none() =>
+            {
+                write_message(
+                    &mut output,
+                    &error_response(id.clone(), INVALID_REQUEST, "Missing method".to_string()),
+                )?;
+            }
+            _ => {}
+        }
+    }
+
+    if server.shutdown {
+        Ok(())
+    } else {
+        Err("The client exited without a shutdown request.".to_string())
+    }
+}
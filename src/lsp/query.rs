@@ -0,0 +1,438 @@
+// Lookups in the classes of the program by source position
+// Offsets are byte offsets into the file that contains the class.
+
+use indexmap::IndexMap;
+
+use crate::ast::{Attr, Class, Expression, Formal, Method, ObjectID, TypeID};
+use crate::diagnostics::Diagnostics;
+use crate::lexer::tokenize;
+use crate::span::Span;
+use crate::token::Token;
+
+// A place where a name is defined
+pub struct Definition<'a> {
+    pub file_name: &'a str,
+    pub span: Span,
+}
+
+// A variable in scope
+struct Binding<'a> {
+    file_name: &'a str,
+    span: Span,
+    type_decl: &'a TypeID,
+}
+
+// What can be completed at a position
+pub enum Completion {
+    Methods(TypeID), // Methods of a class (after '.' or as a dispatch to self)
+    Classes,         // Class names (after '@')
+}
+
+// The end is included so that a position just after a name still refers to it
+fn contains(span: Span, offset: u32) -> bool {
+    span.lo <= offset && offset <= span.hi
+}
+
+// The receiver of a dispatch to self without 'self.' has the span of the method name
+fn is_implicit_self(expr: &Expression, text: &str) -> bool {
+    match expr {
+        Expression::VarByName { span, name, .. } => {
+            name.is_self() && text.get(span.lo as usize..span.hi as usize) != Some("self")
+        }
+        _ => false,
+    }
+}
+
+// The chain of expressions that contain the offset (outermost first)
+fn expr_path<'a>(expr: &'a Expression, text: &str, offset: u32) -> Vec<&'a Expression> {
+    let mut path = Vec::new();
+    let mut expr = expr;
+
+    loop {
+        if matches!(expr, Expression::NoExpr) || !contains(expr.span(), offset) {
+            return path;
+        }
+
+        path.push(expr);
+
+        // Spans of adjacent expressions can share an end point, so the last match wins
+        match expr
+            .children()
+            .into_iter()
+            .rev()
+            .filter(|child| !matches!(child, Expression::NoExpr) && !is_implicit_self(child, text))
+            .find(|child| contains(child.span(), offset))
+        {
+            Some(child) => expr = child,
+            None => return path,
+        }
+    }
+}
+
+// The class of a file that contains the offset
+fn class_at<'a>(
+    classes: &'a IndexMap<TypeID, Class>,
+    file_name: &str,
+    offset: u32,
+) -> Option<(&'a TypeID, &'a Class)> {
+    classes
+        .iter()
+        .find(|(_, class)| class.file_name == file_name && contains(class.span, offset))
+}
+
+fn attr_at(class: &Class, offset: u32) -> Option<&Attr> {
+    class.attrs.iter().find(|attr| contains(attr.span, offset))
+}
+
+fn method_at(class: &Class, offset: u32) -> Option<&Method> {
+    class
+        .methods
+        .values()
+        .find(|method| contains(method.span, offset))
+}
+
+// The expressions that contain the offset in a class
+// The method is returned too for its formals.
+fn class_expr_path<'a>(
+    class: &'a Class,
+    text: &str,
+    offset: u32,
+) -> (Option<&'a Method>, Vec<&'a Expression>) {
+    if let Some(attr) = attr_at(class, offset) {
+        return (None, expr_path(&attr.init, text, offset));
+    }
+
+    match method_at(class, offset) {
+        Some(method) => (Some(method), expr_path(&method.expr, text, offset)),
+        None => (None, Vec::new()),
+    }
+}
+
+// The innermost expression at an offset
+pub fn expr_at<'a>(
+    classes: &'a IndexMap<TypeID, Class>,
+    text: &str,
+    file_name: &str,
+    offset: u32,
+) -> Option<&'a Expression> {
+    let (_, class) = class_at(classes, file_name, offset)?;
+    let (_, path) = class_expr_path(class, text, offset);
+
+    path.last().copied()
+}
+
+// A class and its ancestors
+// A limit guards against inheritance cycles in a program that has not been checked.
+fn ancestors<'a>(
+    classes: &'a IndexMap<TypeID, Class>,
+    class_name: &TypeID,
+) -> Vec<(&'a TypeID, &'a Class)> {
+    let mut result = Vec::new();
+    let mut class_name = Some(class_name);
+
+    while let Some((name, class)) = class_name.and_then(|name| classes.get_key_value(name)) {
+        if result.len() > classes.len() {
+            break;
+        }
+
+        result.push((name, class));
+        class_name = class.parent_name.as_ref();
+    }
+
+    result
+}
+
+fn find_method<'a>(
+    classes: &'a IndexMap<TypeID, Class>,
+    class_name: &TypeID,
+    method_name: &ObjectID,
+) -> Option<Definition<'a>> {
+    ancestors(classes, class_name)
+        .into_iter()
+        .filter(|(_, class)| !class.basic)
+        .find_map(|(_, class)| {
+            class.methods.get(method_name).map(|method| Definition {
+                file_name: &class.file_name,
+                span: method.span,
+            })
+        })
+}
+
+// Find the variable that a name refers to at an offset in a class
+fn find_binding<'a>(
+    classes: &'a IndexMap<TypeID, Class>,
+    text: &str,
+    class_name: &TypeID,
+    offset: u32,
+    name: &ObjectID,
+) -> Option<Binding<'a>> {
+    let class = classes.get(class_name)?;
+    let (method, path) = class_expr_path(class, text, offset);
+
+    // The innermost let or case branch is searched first
+    for expr in path.iter().rev() {
+        match expr {
+            Expression::Let {
+                span,
+                identifier,
+                type_decl,
+                init,
+                ..
+            } if identifier == name => {
+                // The variable is not in scope in its own initialization
+                let in_init =
+                    !matches!(**init, Expression::NoExpr) && contains(init.span(), offset);
+
+                if !in_init {
+                    return Some(Binding {
+                        file_name: &class.file_name,
+                        span: *span,
+                        type_decl,
+                    });
+                }
+            }
+            Expression::TypeCase { branches, .. } => {
+                if let Some(branch) = branches
+                    .iter()
+                    .find(|branch| &branch.name == name && contains(branch.span, offset))
+                {
+                    return Some(Binding {
+                        file_name: &class.file_name,
+                        span: branch.span,
+                        type_decl: &branch.type_decl,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let formal: Option<&Formal> =
+        method.and_then(|method| method.formals.iter().find(|formal| &formal.name == name));
+
+    if let Some(formal) = formal {
+        return Some(Binding {
+            file_name: &class.file_name,
+            span: formal.span,
+            type_decl: &formal.type_decl,
+        });
+    }
+
+    ancestors(classes, class_name)
+        .into_iter()
+        .find_map(|(_, class)| {
+            class
+                .attrs
+                .iter()
+                .find(|attr| &attr.name == name)
+                .map(|attr| Binding {
+                    file_name: &class.file_name,
+                    span: attr.span,
+                    type_decl: &attr.type_decl,
+                })
+        })
+}
+
+// SELF_TYPE stands for the class the code is in
+fn resolve_self_type(type_name: TypeID, class_name: &TypeID) -> TypeID {
+    if type_name == TypeID::new_self_type() {
+        class_name.clone()
+    } else {
+        type_name
+    }
+}
+
+// Find where the class, attribute, method or variable named by word is defined
+pub fn definition<'a>(
+    classes: &'a IndexMap<TypeID, Class>,
+    text: &str,
+    file_name: &str,
+    offset: u32,
+    word: &str,
+) -> Option<Definition<'a>> {
+    let (class_name, class) = class_at(classes, file_name, offset)?;
+
+    // Type names start with an upper case letter
+    if word.starts_with(|c: char| c.is_ascii_uppercase()) {
+        let type_name = resolve_self_type(TypeID::new(word.to_string()), class_name);
+
+        return classes
+            .get(&type_name)
+            .filter(|class| !class.basic)
+            .map(|class| Definition {
+                file_name: &class.file_name,
+                span: class.span,
+            });
+    }
+
+    let name = ObjectID::new(word.to_string());
+
+    if name.is_self() {
+        return None;
+    }
+
+    // A dispatch is searched in the class of its receiver
+    let (_, path) = class_expr_path(class, text, offset);
+
+    if let Some(Expression::Dispatch {
+        expr,
+        type_name,
+        name: method_name,
+        ..
+    }) = path.last()
+    {
+        if method_name == &name {
+            let receiver_type = match (type_name, &**expr) {
+                (Some(type_name), _) => type_name.clone(),
+                (None, expr) if is_implicit_self(expr, text) => class_name.clone(),
+                (None, expr) => resolve_self_type(expr.static_type(), class_name),
+            };
+
+            return find_method(classes, &receiver_type, &name);
+        }
+    }
+
+    if let Some(binding) = find_binding(classes, text, class_name, offset, &name) {
+        return Some(Definition {
+            file_name: binding.file_name,
+            span: binding.span,
+        });
+    }
+
+    find_method(classes, class_name, &name)
+}
+
+// Work out what can be completed at an offset of a file
+// The classes may be from an earlier version of the text (the text being typed
+// usually does not parse), so the position is found from the tokens of the current text.
+// Offsets before the position are assumed not to have changed.
+pub fn completion_at(
+    classes: &IndexMap<TypeID, Class>,
+    text: &str,
+    file_name: &str,
+    offset: u32,
+) -> Option<(Completion, String)> {
+    let tokens = tokenize(text, file_name, &mut Diagnostics::new(0));
+
+    let tokens: Vec<&Token> = tokens
+        .iter()
+        .filter(|token| token.get_span().hi <= offset && !matches!(token, Token::End { .. }))
+        .collect();
+
+    // The class being written
+    let class_name = tokens.windows(2).rev().find_map(|pair| match pair {
+        [Token::Class { .. }, Token::TypeID { type_name, .. }] => {
+            Some(TypeID::new(type_name.clone()))
+        }
+        _ => None,
+    })?;
+
+    // The part of the name already typed
+    let (prefix, tokens) = match tokens.split_last() {
+        Some((Token::ObjectID { span, obj_name }, rest)) if span.hi == offset => {
+            (obj_name.clone(), rest)
+        }
+        Some((Token::TypeID { span, type_name }, rest)) if span.hi == offset => {
+            (type_name.clone(), rest)
+        }
+        _ => (String::new(), &tokens[..]),
+    };
+
+    let completion = match tokens {
+        [.., Token::At { .. }] => Completion::Classes,
+        [.., Token::At { .. }, Token::TypeID { type_name, .. }, Token::Dot { .. }] => {
+            Completion::Methods(TypeID::new(type_name.clone()))
+        }
+        [.., receiver, Token::Dot { span }] => Completion::Methods(receiver_type(
+            classes,
+            text,
+            &class_name,
+            receiver,
+            span.lo,
+        )?),
+        _ if !prefix.starts_with(|c: char| c.is_ascii_uppercase()) => {
+            Completion::Methods(class_name)
+        }
+        _ => return None,
+    };
+
+    Some((completion, prefix))
+}
+
+// The type of the expression before a '.'
+fn receiver_type(
+    classes: &IndexMap<TypeID, Class>,
+    text: &str,
+    class_name: &TypeID,
+    receiver: &Token,
+    dot_offset: u32,
+) -> Option<TypeID> {
+    if let Token::ObjectID { obj_name, .. } = receiver {
+        if obj_name == "self" {
+            return Some(class_name.clone());
+        }
+    }
+
+    // An expression of the earlier text that ends at the '.'
+    let class = classes.get(class_name)?;
+    let (_, path) = class_expr_path(class, text, dot_offset);
+
+    let static_type = path
+        .iter()
+        .rev()
+        .find(|expr| expr.span().hi == dot_offset)
+        .map(|expr| expr.static_type())
+        .filter(|static_type| static_type != &TypeID::new_no_type());
+
+    if let Some(static_type) = static_type {
+        return Some(resolve_self_type(static_type, class_name));
+    }
+
+    // Otherwise a variable of the declared type
+    match receiver {
+        Token::ObjectID { obj_name, .. } => {
+            let name = ObjectID::new(obj_name.clone());
+            let binding = find_binding(classes, text, class_name, dot_offset, &name)?;
+
+            Some(resolve_self_type(binding.type_decl.clone(), class_name))
+        }
+        _ => None,
+    }
+}
+
+// The methods that can be called on a class with their signatures
+// e.g. ("substr", "substr(i : Int, l : Int) : String")
+pub fn method_signatures(
+    classes: &IndexMap<TypeID, Class>,
+    class_name: &TypeID,
+) -> Vec<(String, String)> {
+    let class = match classes.get(class_name) {
+        Some(class) => class,
+        None => return Vec::new(),
+    };
+
+    class
+        .method_name_to_pos
+        .iter()
+        .filter_map(|(method_name, pos)| {
+            let (impl_class_name, _) = class.dispatch_table.get(*pos as usize)?;
+            let method = classes.get(impl_class_name)?.methods.get(method_name)?;
+
+            let formals: Vec<String> = method
+                .formals
+                .iter()
+                .map(|formal| format!("{} : {}", formal.name, formal.type_decl))
+                .collect();
+
+            Some((
+                method_name.to_string(),
+                format!(
+                    "{}({}) : {}",
+                    method_name,
+                    formals.join(", "),
+                    method.return_type
+                ),
+            ))
+        })
+        .collect()
+}
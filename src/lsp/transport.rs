@@ -0,0 +1,76 @@
+// The base protocol of LSP: each JSON-RPC message is preceded by headers
+//
+// Content-Length: 52\r\n
+// \r\n
+// {"jsonrpc":"2.0","id":1,"method":"shutdown"}
+
+use std::io::{BufRead, Write};
+
+use crate::json::Json;
+
+// Read the content of the next message
+// Returns None at the end of the input. Only a message whose headers cannot be read is an Err,
+// since the next message cannot be found after it.
+pub fn read_message<R: BufRead>(input: &mut R) -> Result<Option<Vec<u8>>, String> {
+    let mut content_length: Option<usize> = None;
+
+    loop {
+        let mut line = String::new();
+
+        match input.read_line(&mut line) {
+            Ok(0) => return Ok(None),
+            Ok(_) => {}
+            Err(err) => return Err(format!("Cannot read message: {}", err)),
+        }
+
+        let line = line.trim_end_matches(['\r', '\n']);
+
+        // A blank line ends the headers
+        if line.is_empty() {
+            if content_length.is_some() {
+                break;
+            }
+
+            continue;
+        }
+
+        // Other headers (i.e. Content-Type) are ignored
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("Content-Length") {
+                match value.trim().parse::<usize>() {
+                    Ok(len) => content_length = Some(len),
+                    Err(_) => return Err(format!("Bad Content-Length header: {}", line)),
+                }
+            }
+        }
+    }
+
+    let mut content = vec![0u8; content_length.unwrap_or(0)];
+
+    if let Err(err) = input.read_exact(&mut content) {
+        return Err(format!("Cannot read message: {}", err));
+    }
+
+    Ok(Some(content))
+}
+
+// Parse the content of a message
+pub fn parse_message(content: &[u8]) -> Result<Json, String> {
+    match std::str::from_utf8(content) {
+        Ok(content) => Json::parse(content),
+        Err(_) => Err("Message is not valid UTF-8.".to_string()),
+    }
+}
+
+pub fn write_message<W: Write>(output: &mut W, message: &Json) -> Result<(), String> {
+    let content = message.to_string();
+
+    write!(
+        output,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )
+    .and_then(|_| output.flush())
+    .map_err(|err| format!("Cannot write message: {}", err))
+}
@@ -1,61 +1,613 @@
-mod ast;
-mod builtins;
-mod cgen;
-mod lexer;
-mod parser;
-mod scoped_collections;
-mod semant;
-mod token;
-
-use std::fs::File;
-use std::io::Write;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, IsTerminal, Read, Write};
 use std::path::Path;
 use std::process::exit;
 
-use argparse::{ArgumentParser, Collect, StoreOption};
-use indexmap::{IndexMap, IndexSet};
+use argparse::{ArgumentParser, Collect, Store, StoreConst, StoreOption, StoreTrue};
 
-use crate::ast::{Class, TypeID};
-use crate::builtins::initialize_classes;
-use crate::cgen::cgen;
-use crate::lexer::tokenize;
-use crate::parser::parse;
-use crate::semant::semant;
+use coolc::ast_dump::dump_ast;
+use coolc::bytecode::{self, Program};
+use coolc::cgen::GarbageCollector;
+use coolc::diagnostics::Diagnostics;
+use coolc::formatter::format_source;
+use coolc::interp;
+use coolc::lexer::{dump_tokens, tokenize};
+use coolc::mips;
+use coolc::render::{render, render_json, ColorChoice, ErrorFormat, SourceMap};
+use coolc::repl::repl;
+use coolc::riscv;
+use coolc::semant::semant;
+use coolc::{
+    compile, read_sources, CompileOptions, Emit, InputFormat, Source, Stage, Target,
+    DEFAULT_MAX_ERRORS,
+};
 
-fn get_name(in_file_name: &String, file_no: u32) -> Result<&str, String> {
-    match Path::new(in_file_name).file_name() {
-        Some(in_file_name) => match in_file_name.to_str() {
-            Some(in_file_name) => return Ok(&in_file_name),
+const STDIN_FILE_NAME: &str = "-"; // Source file name that reads from standard input
+const STDIN_NAME: &str = "<stdin>"; // How standard input is named in error messages
+const STDIN_OUT_FILE_STEM: &str = "stdin"; // Default output (with the extension) if the first source is standard input
+const STDOUT_FILE_NAME: &str = "-"; // Output file name that writes to standard output
+
+// Print all errors collected so far and give up
+fn halt(
+    diagnostics: &Diagnostics,
+    sources: &SourceMap,
+    error_format: ErrorFormat,
+    color: bool,
+) -> Result<(), String> {
+    for diagnostic in diagnostics.diagnostics() {
+        match error_format {
+            ErrorFormat::Human => eprintln!("{}", render(diagnostic, sources, color)),
+            ErrorFormat::Json => eprintln!("{}", render_json(diagnostic)),
+        }
+    }
+
+    if error_format == ErrorFormat::Json {
+        // Only JSON is written in this mode
+        Err(String::new())
+    } else if diagnostics.limit_reached() {
+        Err("Too many errors. Compilation halted.".to_string())
+    } else {
+        Err("Compilation halted due to errors.".to_string())
+    }
+}
+
+// Read source files (- reads standard input)
+// Returns the name used in messages (the path as given) and the text of each file.
+fn read_in_files(in_file_names: &[String]) -> Result<Vec<(&str, String)>, String> {
+    let mut in_files: Vec<(&str, String)> = Vec::new();
+
+    if in_file_names
+        .iter()
+        .filter(|in_file_name| *in_file_name == STDIN_FILE_NAME)
+        .count()
+        > 1
+    {
+        return Err("Standard input can only be read once".to_string());
+    }
+
+    for in_file_name in in_file_names.iter() {
+        if in_file_name == STDIN_FILE_NAME {
+            let mut in_file_text = String::new();
+
+            if let Err(why) = io::stdin().read_to_string(&mut in_file_text) {
+                return Err(format!("Cannot read standard input: {}", why));
+            }
+
+            in_files.push((STDIN_NAME, in_file_text));
+            continue;
+        }
+
+        let in_file_text = match fs::read_to_string(in_file_name) {
+            Err(why) => {
+                return Err(format!("Cannot open {} as input: {}", in_file_name, why));
+            }
+            Ok(in_file_text) => in_file_text,
+        };
+
+        in_files.push((in_file_name, in_file_text));
+    }
+
+    Ok(in_files)
+}
+
+// The sources in the files that were read
+fn to_sources<'a>(in_files: &'a [(&'a str, String)]) -> Vec<Source<'a>> {
+    in_files
+        .iter()
+        .map(|(name, text)| Source { name, text })
+        .collect()
+}
+
+fn use_color(color_choice: ColorChoice) -> bool {
+    match color_choice {
+        ColorChoice::Auto => io::stderr().is_terminal(),
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+    }
+}
+
+// Options for reading sources and reporting their errors
+// They are shared by the subcommands that compile or check a program.
+struct SourceOptions {
+    max_errors: usize,         // Stop after this many errors
+    color_choice: ColorChoice, // Whether to color the error output
+    error_format: ErrorFormat, // How errors are written
+    input_format: InputFormat, // The language of the sources
+}
+
+impl SourceOptions {
+    fn new() -> SourceOptions {
+        SourceOptions {
+            max_errors: DEFAULT_MAX_ERRORS,
+            color_choice: ColorChoice::Auto,
+            error_format: ErrorFormat::Human,
+            input_format: InputFormat::Cool,
+        }
+    }
+
+    // Add --max-errors, --color, --error-format and --input-format to a parser
+    fn refer<'a>(&'a mut self, ap: &mut ArgumentParser<'a>) {
+        ap.refer(&mut self.max_errors).add_option(
+            &["--max-errors"],
+            Store,
+            "Maximum number of errors to report (0 for no limit)",
+        );
+
+        refer_color(ap, &mut self.color_choice);
+        refer_error_format(ap, &mut self.error_format);
+
+        ap.refer(&mut self.input_format).add_option(
+            &["--input-format"],
+            Store,
+            "Format of the sources (cool, or ast for the output of the reference parser)",
+        );
+    }
+}
+
+fn refer_color<'a>(ap: &mut ArgumentParser<'a>, color_choice: &'a mut ColorChoice) {
+    ap.refer(color_choice).add_option(
+        &["--color"],
+        Store,
+        "Color the error output (auto, always or never)",
+    );
+}
+
+fn refer_error_format<'a>(ap: &mut ArgumentParser<'a>, error_format: &'a mut ErrorFormat) {
+    ap.refer(error_format).add_option(
+        &["--error-format"],
+        Store,
+        "Format of the error output (human or json)",
+    );
+}
+
+// coolc fmt [--check] FILE...
+// Rewrites each file in place (or writes standard input to standard output)
+fn coolc_fmt(args: Vec<String>) -> Result<(), String> {
+    let mut in_file_names: Vec<String> = Vec::new(); // Stores the paths of the source files
+    let mut check = false; // Only report the files that are not formatted
+    let mut color_choice = ColorChoice::Auto; // Whether to color the error output
+    let mut error_format = ErrorFormat::Human; // How errors are written
+
+    {
+        let mut ap = ArgumentParser::new();
+        ap.set_description("Format COOL source");
+
+        ap.refer(&mut check).add_option(
+            &["--check"],
+            StoreTrue,
+            "Do not write the files, only fail if any file is not formatted",
+        );
+
+        refer_color(&mut ap, &mut color_choice);
+        refer_error_format(&mut ap, &mut error_format);
+
+        ap.refer(&mut in_file_names).add_argument(
+            "Source Files",
+            Collect,
+            "COOL source (- for standard input)",
+        );
+
+        // The subcommand is shown as part of the program name
+        let mut args = args;
+        args.remove(1);
+        args[0] = format!("{} fmt", args[0]);
+
+        if let Err(code) = ap.parse(args, &mut io::stdout(), &mut io::stderr()) {
+            exit(code);
+        }
+    }
+
+    if in_file_names.is_empty() {
+        return Err("Must specify at least one source file".to_string());
+    }
+
+    let color = use_color(color_choice);
+    let in_files = read_in_files(&in_file_names)?;
+    let mut unformatted: Vec<&str> = Vec::new();
+
+    for (in_file_name, (name, text)) in in_file_names.iter().zip(in_files.iter()) {
+        let source = Source { name, text };
+        let mut diagnostics = Diagnostics::new(DEFAULT_MAX_ERRORS);
+
+        let formatted = match format_source(&source, &mut diagnostics)? {
+            Some(formatted) => formatted,
             None => {
-                return Err(format!(
-                    "Cannot process input file name number {}.",
-                    file_no
-                ))
+                let sources = SourceMap::from_sources(&[source]);
+
+                return halt(&diagnostics, &sources, error_format, color);
+            }
+        };
+
+        if check {
+            if formatted != *text {
+                unformatted.push(name);
+            }
+        } else if in_file_name == STDIN_FILE_NAME {
+            if let Err(why) = io::stdout().lock().write_all(formatted.as_bytes()) {
+                return Err(format!("Cannot write standard output: {}", why));
+            }
+        } else if formatted != *text {
+            if let Err(why) = fs::write(in_file_name, formatted) {
+                return Err(format!("Cannot write {}: {}", in_file_name, why));
+            }
+        }
+    }
+
+    if unformatted.is_empty() {
+        Ok(())
+    } else {
+        Err(unformatted
+            .iter()
+            .map(|name| format!("{} is not formatted.", name))
+            .collect::<Vec<String>>()
+            .join("\n"))
+    }
+}
+
+// coolc run [OPTIONS] FILE...
+// Compiles the sources and runs the program without SPIM
+// A single file ending in .s is run as assembly and one ending in .coolbc as bytecode.
+fn coolc_run(args: Vec<String>) -> Result<(), String> {
+    let mut in_file_names: Vec<String> = Vec::new(); // Stores the paths of the source files
+    let mut options = SourceOptions::new(); // How sources are read and errors reported
+    let mut target = Target::Mips; // The machine to run the program on
+
+    {
+        let mut ap = ArgumentParser::new();
+        ap.set_description(
+            "Compile and run a COOL program (a single .s file is run as assembly, a .coolbc file as bytecode)",
+        );
+
+        options.refer(&mut ap);
+
+        ap.refer(&mut target).add_option(
+            &["--target"],
+            Store,
+            "Machine to run the program on (mips, riscv32 or bytecode)",
+        );
+
+        ap.refer(&mut in_file_names).add_argument(
+            "Source Files",
+            Collect,
+            "COOL source (- for standard input)",
+        );
+
+        // The subcommand is shown as part of the program name
+        let mut args = args;
+        args.remove(1);
+        args[0] = format!("{} run", args[0]);
+
+        if let Err(code) = ap.parse(args, &mut io::stdout(), &mut io::stderr()) {
+            exit(code);
+        }
+    }
+
+    if in_file_names.is_empty() {
+        return Err("Must specify at least one source file".to_string());
+    }
+
+    let SourceOptions {
+        max_errors,
+        color_choice,
+        error_format,
+        input_format,
+    } = options;
+
+    match target {
+        Target::X86_64 => return Err("x86-64 programs cannot be run with coolc run".to_string()),
+        Target::C => return Err("C programs cannot be run with coolc run".to_string()),
+        Target::Wasm => return Err("WebAssembly programs cannot be run with coolc run".to_string()),
+        _ => {}
+    }
+
+    // A bytecode file is run without compiling
+    if let [in_file_name] = in_file_names.as_slice() {
+        if in_file_name.ends_with(&format!(".{}", bytecode::FILE_EXTENSION)) {
+            let bytes = match fs::read(in_file_name) {
+                Ok(bytes) => bytes,
+                Err(why) => return Err(format!("Cannot read {}: {}", in_file_name, why)),
+            };
+
+            return run_bytecode(&bytes);
+        }
+    }
+
+    let in_files = read_in_files(&in_file_names)?;
+
+    let assembly = match in_file_names.as_slice() {
+        [in_file_name] if in_file_name.ends_with(".s") => in_files[0].1.clone(),
+        _ => {
+            let in_files = to_sources(&in_files);
+
+            let options = CompileOptions {
+                max_errors,
+                input_format,
+                stop_after: Stage::Codegen,
+                target,
+                ..CompileOptions::default() // The built-in runtime never collects garbage
+            };
+
+            let compilation = compile(&in_files, &options)?;
+
+            if let Some(bytecode) = compilation.bytecode {
+                return run_bytecode(&bytecode);
+            }
+
+            match compilation.assembly {
+                Some(assembly) => assembly,
+                None => {
+                    let sources = SourceMap::from_sources(&in_files);
+
+                    return halt(
+                        &compilation.diagnostics,
+                        &sources,
+                        error_format,
+                        use_color(color_choice),
+                    );
+                }
             }
-        },
-        None => {
-            return Err(format!(
-                "Cannot process input file name number {}.",
-                file_no
-            ))
         }
     };
+
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let mut output = BufWriter::new(stdout.lock());
+
+    // A runtime error has already been reported by the program
+    let succeeded = match target {
+        Target::Riscv32 => {
+            riscv::run(&assembly, &mut stdin.lock(), &mut output, &mut io::stderr())?
+        }
+        _ => mips::run(&assembly, &mut stdin.lock(), &mut output)?,
+    };
+
+    if succeeded {
+        Ok(())
+    } else {
+        Err(String::new())
+    }
+}
+
+// Run the contents of a .coolbc file with the virtual machine
+fn run_bytecode(bytes: &[u8]) -> Result<(), String> {
+    let program = Program::from_bytes(bytes)?;
+
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let mut output = BufWriter::new(stdout.lock());
+
+    // A runtime error has already been reported by the program
+    if bytecode::run(&program, &mut stdin.lock(), &mut output)? {
+        Ok(())
+    } else {
+        Err(String::new())
+    }
+}
+
+// coolc interp [OPTIONS] FILE...
+// Checks the sources and runs the program with the tree-walking interpreter
+// The output is the same as that of coolc run, so the two can be compared.
+fn coolc_interp(args: Vec<String>) -> Result<(), String> {
+    let mut in_file_names: Vec<String> = Vec::new(); // Stores the paths of the source files
+    let mut options = SourceOptions::new(); // How sources are read and errors reported
+
+    {
+        let mut ap = ArgumentParser::new();
+        ap.set_description("Check a COOL program and run it without compiling it");
+
+        options.refer(&mut ap);
+
+        ap.refer(&mut in_file_names).add_argument(
+            "Source Files",
+            Collect,
+            "COOL source (- for standard input)",
+        );
+
+        // The subcommand is shown as part of the program name
+        let mut args = args;
+        args.remove(1);
+        args[0] = format!("{} interp", args[0]);
+
+        if let Err(code) = ap.parse(args, &mut io::stdout(), &mut io::stderr()) {
+            exit(code);
+        }
+    }
+
+    if in_file_names.is_empty() {
+        return Err("Must specify at least one source file".to_string());
+    }
+
+    let SourceOptions {
+        max_errors,
+        color_choice,
+        error_format,
+        input_format,
+    } = options;
+
+    let in_files = read_in_files(&in_file_names)?;
+
+    let in_files = to_sources(&in_files);
+
+    let mut diagnostics = Diagnostics::new(max_errors);
+    let (classes, _) = read_sources(&in_files, input_format, &mut diagnostics);
+
+    // The interpreter relies on the information added by semantic analysis
+    let classes = if diagnostics.has_errors() {
+        None
+    } else {
+        semant(classes, &mut diagnostics).map(|(classes, _, _)| classes)
+    };
+
+    let classes = match classes {
+        Some(classes) if !diagnostics.has_errors() => classes,
+        _ => {
+            let sources = SourceMap::from_sources(&in_files);
+
+            return halt(
+                &diagnostics,
+                &sources,
+                error_format,
+                use_color(color_choice),
+            );
+        }
+    };
+
+    let mut input = BufReader::new(io::stdin());
+    let mut output = BufWriter::new(io::stdout());
+
+    // A runtime error has already been reported by the program
+    if interp::run(&classes, &mut input, &mut output)? {
+        Ok(())
+    } else {
+        Err(String::new())
+    }
+}
+
+// coolc repl [OPTIONS] [FILE...]
+// Starts an interactive session with the classes of the files already defined
+fn coolc_repl(args: Vec<String>) -> Result<(), String> {
+    let mut in_file_names: Vec<String> = Vec::new(); // Stores the paths of the source files
+    let mut color_choice = ColorChoice::Auto; // Whether to color the error output
+
+    {
+        let mut ap = ArgumentParser::new();
+        ap.set_description("Evaluate COOL expressions and class definitions interactively");
+
+        refer_color(&mut ap, &mut color_choice);
+
+        ap.refer(&mut in_file_names).add_argument(
+            "Source Files",
+            Collect,
+            "COOL source to load first",
+        );
+
+        // The subcommand is shown as part of the program name
+        let mut args = args;
+        args.remove(1);
+        args[0] = format!("{} repl", args[0]);
+
+        if let Err(code) = ap.parse(args, &mut io::stdout(), &mut io::stderr()) {
+            exit(code);
+        }
+    }
+
+    // The session reads standard input, so the sources cannot
+    if in_file_names.iter().any(|name| name == STDIN_FILE_NAME) {
+        return Err("Sources cannot be read from standard input in a session".to_string());
+    }
+
+    let in_files = read_in_files(&in_file_names)?;
+
+    let in_files = to_sources(&in_files);
+
+    // Errors are written with the rest of the session to standard output
+    let color = match color_choice {
+        ColorChoice::Auto => io::stdout().is_terminal(),
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+    };
+
+    let prompt = io::stdin().is_terminal();
+    let mut input = BufReader::new(io::stdin());
+    let mut output = BufWriter::new(io::stdout());
+
+    repl(&in_files, &mut input, &mut output, prompt, color)
 }
 
 fn coolc() -> Result<(), String> {
     let mut out_file_name: Option<String> = None; // Stores the output path or None if not specified
     let mut in_file_names: Vec<String> = Vec::new(); // Stores the paths of the source files
+    let mut options = SourceOptions::new(); // How sources are read and errors reported
+    let mut stop_after = Stage::Codegen; // The last stage to run
+    let mut target = Target::Mips; // The machine the assembly is for
+    let mut emit = Emit::Asm; // The form of the output
+    let mut gc = GarbageCollector::None; // The collector of the runtime
+    let mut gc_test = false; // Collect garbage at every allocation
+    let mut dump_tokens_only = false; // Print the tokens instead of compiling
+    let mut dump_ast_only = false; // Print the parsed program instead of compiling
+    let mut dump_typed_ast_only = false; // Print the program with static types instead of compiling
 
     {
         // Get arguments from the command line
         let mut ap = ArgumentParser::new();
-        ap.set_description("Compile COOL to MIPS assembly");
+        ap.set_description(
+            "Compile COOL to MIPS assembly, or to the output chosen with --target and --emit (see also: coolc fmt --help, coolc run --help, coolc interp --help, coolc repl --help)",
+        );
+
+        ap.refer(&mut out_file_name).add_option(
+            &["-o"],
+            StoreOption,
+            "Output file (- for standard output)",
+        );
+
+        options.refer(&mut ap);
+
+        ap.refer(&mut stop_after)
+            .add_option(
+                &["--parse-only"],
+                StoreConst(Stage::Parse),
+                "Only check syntax (no output file is written)",
+            )
+            .add_option(
+                &["--check-only"],
+                StoreConst(Stage::Check),
+                "Only check syntax and semantics (no output file is written)",
+            );
 
-        ap.refer(&mut out_file_name)
-            .add_option(&["-o"], StoreOption, "Output file");
+        ap.refer(&mut target).add_option(
+            &["--target"],
+            Store,
+            "Machine to generate assembly for (mips, riscv32 or x86-64), c for C source, bytecode or wasm for WebAssembly text",
+        );
 
-        ap.refer(&mut in_file_names)
-            .add_argument("Source Files", Collect, "COOL source");
+        ap.refer(&mut emit).add_option(
+            &["--emit"],
+            Store,
+            "Form of the output (asm for the target, or llvm-ir for LLVM IR)",
+        );
+
+        ap.refer(&mut gc)
+            .add_option(
+                &["--gc"],
+                Store,
+                "Garbage collector (none, generational or stop-and-copy)",
+            )
+            .add_option(
+                &["-g"],
+                StoreConst(GarbageCollector::Generational),
+                "Use the generational garbage collector",
+            );
+
+        ap.refer(&mut gc_test).add_option(
+            &["--gc-test"],
+            StoreTrue,
+            "Collect garbage at every allocation (to test the collector)",
+        );
+
+        ap.refer(&mut dump_tokens_only).add_option(
+            &["--dump-tokens"],
+            StoreTrue,
+            "Print the tokens of each source in the format of the reference lexer and exit",
+        );
+
+        ap.refer(&mut dump_ast_only).add_option(
+            &["--dump-ast"],
+            StoreTrue,
+            "Print the parsed program in the format of the reference parser and exit",
+        );
+
+        ap.refer(&mut dump_typed_ast_only).add_option(
+            &["--dump-typed-ast"],
+            StoreTrue,
+            "Print the program with static types in the format of the reference semantic analyzer and exit",
+        );
+
+        ap.refer(&mut in_file_names).add_argument(
+            "Source Files",
+            Collect,
+            "COOL source (- for standard input)",
+        );
 
         ap.parse_args_or_exit();
     }
@@ -64,89 +616,143 @@ fn coolc() -> Result<(), String> {
         return Err("Must specify at least one source file".to_string());
     }
 
-    // classes is a map to store the Class objects.
-    // This function creates a map that stores the basic classes (Object, IO, Int, Bool, String)
-    let mut classes: IndexMap<TypeID, Class> = initialize_classes();
+    let SourceOptions {
+        max_errors,
+        color_choice,
+        error_format,
+        input_format,
+    } = options;
 
-    // Loop over the source files from the command line
-    // File numbers are stored starting at 1.
-    for (in_file_name, file_no) in in_file_names.iter().zip(1..) {
-        // try to open input file
-        let in_file = match File::open(&in_file_name) {
-            Err(why) => {
-                return Err(format!("Cannot open {} as input: {}", in_file_name, why));
+    let color = use_color(color_choice);
+
+    // Read the source files from the command line
+    let in_files = read_in_files(&in_file_names)?;
+
+    let in_files = to_sources(&in_files);
+
+    if dump_tokens_only {
+        if input_format != InputFormat::Cool {
+            return Err("Only COOL sources can be tokenized".to_string());
+        }
+
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+
+        for source in in_files.iter() {
+            // Lexical errors are written as ERROR tokens, so there is no limit
+            let mut diagnostics = Diagnostics::new(0);
+            let tokens = tokenize(source.text, source.name, &mut diagnostics);
+
+            if let Err(why) = dump_tokens(&mut out, source.text, source.name, &tokens, &diagnostics)
+            {
+                return Err(format!("Cannot write standard output: {}", why));
             }
-            Ok(in_file) => in_file,
+        }
+
+        return Ok(());
+    }
+
+    if dump_ast_only || dump_typed_ast_only {
+        let mut diagnostics = Diagnostics::new(max_errors);
+        let (classes, _) = read_sources(&in_files, input_format, &mut diagnostics);
+
+        // Static types are only set if the program passes semantic analysis
+        let classes = if dump_typed_ast_only && !diagnostics.has_errors() {
+            semant(classes, &mut diagnostics).map(|(classes, _, _)| classes)
+        } else {
+            Some(classes)
         };
 
-        // Tokenize the file
-        let tokens = tokenize(in_file, in_file_name)?;
+        let classes = match classes {
+            Some(classes) if !diagnostics.has_errors() => classes,
+            _ => {
+                let sources = SourceMap::from_sources(&in_files);
 
-        let in_file_name = get_name(in_file_name, file_no)?;
+                return halt(&diagnostics, &sources, error_format, color);
+            }
+        };
 
-        // Parse the tokens into class definitions.
-        // The classes are added to the classes map.
-        parse(tokens, in_file_name, file_no, &mut classes)?;
+        if let Err(why) = dump_ast(&mut io::stdout().lock(), &classes, dump_typed_ast_only) {
+            return Err(format!("Cannot write standard output: {}", why));
+        }
+
+        return Ok(());
     }
 
-    // Add semantic information to classes (see semant/mod.rs for more detail)
-    // int_table : set of integer constants specified in the source
-    // str_table : vector of string constants specified in the source
-    let (classes, mut int_table, str_table): (IndexMap<TypeID, Class>, IndexSet<u32>, Vec<String>) =
-        semant(classes)?;
+    let options = CompileOptions {
+        max_errors,
+        input_format,
+        stop_after,
+        target,
+        emit,
+        gc,
+        gc_test,
+    };
 
-    // add lengths of source files to int_table
-    for in_file_name in in_file_names.iter() {
-        int_table.insert(in_file_name.len() as u32);
-    }
+    // The extension of the output file
+    let extension = match emit {
+        Emit::LlvmIr => "ll",
+        Emit::Asm => target.extension(),
+    };
 
-    // add lengths of class names to int_table
-    for class_name in classes.keys() {
-        int_table.insert(class_name.len() as u32);
-    }
+    // Run the compiler pipeline (see lib.rs)
+    let compilation = compile(&in_files, &options)?;
 
-    // convert int_table into sorted vector
-    let mut int_table: Vec<u32> = int_table.into_iter().collect();
-    int_table.sort_unstable();
+    let output = match (compilation.assembly, compilation.bytecode) {
+        (Some(assembly), _) => assembly.into_bytes(),
+        (None, Some(bytecode)) => bytecode,
+        (None, None) if !compilation.diagnostics.has_errors() => return Ok(()), // Stopped before cgen
+        (None, None) => {
+            // The source text is used to show the lines that contain errors
+            let sources = SourceMap::from_sources(&in_files);
+
+            return halt(&compilation.diagnostics, &sources, error_format, color);
+        }
+    };
 
     // determine output file name
     let out_file_name = match out_file_name {
         Some(out_file_name) => out_file_name, // If one is specified on the command line, use it
+        None if in_file_names.first().unwrap() == STDIN_FILE_NAME => {
+            format!("{}.{}", STDIN_OUT_FILE_STEM, extension)
+        }
         None =>
-        // If not, use the same name as the first source file but with .s
+        // If not, use the same name as the first source file but with .s (or .c, .coolbc, .wat or .ll)
         {
-            match Path::new(in_file_names.get(0).unwrap())
-                .with_extension("s")
+            match Path::new(in_file_names.first().unwrap())
+                .with_extension(extension)
                 .file_name()
             {
                 Some(out_file_name) => match out_file_name.to_str() {
                     Some(out_file_name) => out_file_name.to_string(),
-                    None => return Err(format!("Cannot process output file name.")),
+                    None => return Err("Cannot process output file name.".to_string()),
                 },
 
-                None => return Err(format!("Cannot process output file name.")),
+                None => return Err("Cannot process output file name.".to_string()),
             }
         }
     };
 
-    let mut out_file: Box<dyn Write> = {
-        match File::create(&out_file_name) {
-            // Open output file for writing.
-            Err(why) => {
-                return Err(format!("Cannot open {} as output: {}", out_file_name, why));
-            }
-            Ok(file) => Box::new(file),
+    if out_file_name == STDOUT_FILE_NAME {
+        // Nothing else is written to standard output so that it can be piped
+        if let Err(why) = io::stdout().lock().write_all(&output) {
+            return Err(format!("Cannot write standard output: {}", why));
+        }
+
+        return Ok(());
+    }
+
+    let mut out_file = match File::create(&out_file_name) {
+        // Open output file for writing.
+        Err(why) => {
+            return Err(format!("Cannot open {} as output: {}", out_file_name, why));
         }
+        Ok(file) => file,
     };
 
-    // Code generation - write out valid MIPS assembly
-    cgen(
-        &mut out_file,
-        &in_file_names,
-        &classes,
-        &int_table,
-        &str_table,
-    )?;
+    if let Err(why) = out_file.write_all(&output) {
+        return Err(format!("Cannot write {}: {}", out_file_name, why));
+    }
 
     eprintln!("Successfully wrote {}.", out_file_name);
 
@@ -154,8 +760,23 @@ fn coolc() -> Result<(), String> {
 }
 
 fn main() {
-    if let Err(s) = coolc() {
-        eprintln!("{}", s);
+    let args: Vec<String> = env::args().collect();
+
+    // A subcommand must be the first argument
+    let result = match args.get(1).map(|arg| arg.as_str()) {
+        Some("fmt") => coolc_fmt(args),
+        Some("run") => coolc_run(args),
+        Some("interp") => coolc_interp(args),
+        Some("repl") => coolc_repl(args),
+        _ => coolc(),
+    };
+
+    if let Err(s) = result {
+        // The message may be empty if the errors have already been written
+        if !s.is_empty() {
+            eprintln!("{}", s);
+        }
+
         exit(1);
     }
 }
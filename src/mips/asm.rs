@@ -0,0 +1,399 @@
+// Assembles the output of cgen into instructions and an initial data segment
+//
+// Only the directives and instructions that cgen writes are understood
+// (see cgen/emit.rs). Pseudo-instructions such as li, la and blt are kept
+// as single instructions rather than being expanded.
+
+use std::collections::HashMap;
+
+use super::runtime::RUNTIME_ROUTINES;
+use super::{DATA_BASE, RUNTIME_BASE, TEXT_BASE, WORD_SIZE};
+
+// Register numbers
+pub const ZERO: usize = 0;
+pub const A0: usize = 4;
+pub const A1: usize = 5;
+pub const T1: usize = 9;
+pub const T2: usize = 10;
+pub const SP: usize = 29;
+pub const RA: usize = 31;
+
+const REGISTER_NAMES: [&str; 32] = [
+    "zero", "at", "v0", "v1", "a0", "a1", "a2", "a3", "t0", "t1", "t2", "t3", "t4", "t5", "t6",
+    "t7", "s0", "s1", "s2", "s3", "s4", "s5", "s6", "s7", "t8", "t9", "k0", "k1", "gp", "sp", "fp",
+    "ra",
+];
+
+#[derive(Clone, Copy, Debug)]
+pub enum Instr {
+    Lw { rt: usize, offset: i32, base: usize },
+    Sw { rt: usize, offset: i32, base: usize },
+    Li { rd: usize, imm: u32 },
+    La { rd: usize, addr: u32 },
+    Move { rd: usize, rs: usize },
+    Neg { rd: usize, rs: usize },
+    Add { rd: usize, rs: usize, rt: usize }, // Traps on overflow
+    Addu { rd: usize, rs: usize, rt: usize },
+    Addiu { rt: usize, rs: usize, imm: i32 },
+    Sub { rd: usize, rs: usize, rt: usize }, // Traps on overflow
+    Mul { rd: usize, rs: usize, rt: usize },
+    Div { rd: usize, rs: usize, rt: usize }, // Traps on division by zero
+    Sll { rd: usize, rt: usize, shift: u32 },
+    B { target: u32 },
+    Beqz { rs: usize, target: u32 },
+    Bnez { rs: usize, target: u32 },
+    Beq { rs: usize, rt: usize, target: u32 },
+    Ble { rs: usize, rt: usize, target: u32 },
+    Blt { rs: usize, rt: usize, target: u32 },
+    Blti { rs: usize, imm: i32, target: u32 },
+    Bgei { rs: usize, imm: i32, target: u32 },
+    Jal { target: u32 },
+    Jalr { rs: usize },
+    Jr { rs: usize },
+}
+
+pub struct Program {
+    pub text: Vec<Instr>, // Instruction i is at TEXT_BASE + 4 * i
+    pub data: Vec<u8>,    // Starts at DATA_BASE
+    pub labels: HashMap<String, u32>,
+}
+
+impl Program {
+    pub fn label(&self, name: &str) -> Result<u32, String> {
+        match self.labels.get(name) {
+            Some(addr) => Ok(*addr),
+            None => Err(format!("Undefined label {}", name)),
+        }
+    }
+}
+
+#[derive(PartialEq)]
+enum Segment {
+    Data,
+    Text,
+}
+
+// Split a line into its label (if any) and the words of the statement
+fn split_line(line: &str) -> (Option<&str>, Vec<&str>) {
+    // Comments run to the end of the line
+    let line = match line.find('#') {
+        Some(i) => &line[..i],
+        None => line,
+    };
+
+    let (label, rest) = match line.find(':') {
+        Some(i) => (Some(line[..i].trim()), &line[i + 1..]),
+        None => (None, line),
+    };
+
+    let words = rest
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|word| !word.is_empty())
+        .collect();
+
+    (label, words)
+}
+
+fn parse_int(s: &str) -> Option<i64> {
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, s),
+    };
+
+    let val = match digits.strip_prefix("0x") {
+        Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+        None => digits.parse::<i64>().ok()?,
+    };
+
+    Some(if negative { -val } else { val })
+}
+
+fn align(offset: usize, lg_size: u32) -> usize {
+    let size = 1 << lg_size;
+
+    offset.div_ceil(size) * size
+}
+
+struct Assembler<'a> {
+    labels: HashMap<String, u32>,
+    line_no: usize,
+    line: &'a str,
+}
+
+impl<'a> Assembler<'a> {
+    fn error(&self, msg: &str) -> String {
+        format!(
+            "Cannot assemble line {} ({}): {}",
+            self.line_no,
+            self.line.trim(),
+            msg
+        )
+    }
+
+    fn register(&self, word: Option<&&str>) -> Result<usize, String> {
+        let name = match word.and_then(|word| word.strip_prefix('$')) {
+            Some(name) => name,
+            None => return Err(self.error("expected a register")),
+        };
+
+        if let Some(reg) = REGISTER_NAMES.iter().position(|reg| *reg == name) {
+            return Ok(reg);
+        }
+
+        match name.parse::<usize>() {
+            Ok(reg) if reg < REGISTER_NAMES.len() => Ok(reg),
+            _ => Err(self.error(&format!("unknown register ${}", name))),
+        }
+    }
+
+    fn int(&self, word: Option<&&str>) -> Result<i64, String> {
+        match word.and_then(|word| parse_int(word)) {
+            Some(val) => Ok(val),
+            None => Err(self.error("expected an integer")),
+        }
+    }
+
+    fn address(&self, word: Option<&&str>) -> Result<u32, String> {
+        let word = match word {
+            Some(word) => *word,
+            None => return Err(self.error("expected a label")),
+        };
+
+        if let Some(val) = parse_int(word) {
+            return Ok(val as u32);
+        }
+
+        match self.labels.get(word) {
+            Some(addr) => Ok(*addr),
+            None => Err(self.error(&format!("undefined label {}", word))),
+        }
+    }
+
+    // An offset and base register (i.e. 12($s0))
+    fn mem(&self, word: Option<&&str>) -> Result<(i32, usize), String> {
+        let word = match word {
+            Some(word) => *word,
+            None => return Err(self.error("expected a memory location")),
+        };
+
+        match word.split_once('(') {
+            Some((offset, base)) => {
+                let offset = if offset.is_empty() {
+                    0
+                } else {
+                    self.int(Some(&offset))? as i32
+                };
+                let base = self.register(Some(&base.trim_end_matches(')')))?;
+
+                Ok((offset, base))
+            }
+            None => Err(self.error("expected a memory location")),
+        }
+    }
+
+    fn instr(&self, words: &[&str]) -> Result<Instr, String> {
+        let op = words[0];
+        let args = &words[1..];
+
+        let instr = match op {
+            "lw" | "sw" => {
+                let rt = self.register(args.first())?;
+                let (offset, base) = self.mem(args.get(1))?;
+
+                if op == "lw" {
+                    Instr::Lw { rt, offset, base }
+                } else {
+                    Instr::Sw { rt, offset, base }
+                }
+            }
+            "li" => Instr::Li {
+                rd: self.register(args.first())?,
+                imm: self.int(args.get(1))? as u32,
+            },
+            "la" => Instr::La {
+                rd: self.register(args.first())?,
+                addr: self.address(args.get(1))?,
+            },
+            "move" | "neg" => {
+                let rd = self.register(args.first())?;
+                let rs = self.register(args.get(1))?;
+
+                if op == "move" {
+                    Instr::Move { rd, rs }
+                } else {
+                    Instr::Neg { rd, rs }
+                }
+            }
+            "add" | "addu" | "sub" | "mul" | "div" => {
+                let rd = self.register(args.first())?;
+                let rs = self.register(args.get(1))?;
+                let rt = self.register(args.get(2))?;
+
+                match op {
+                    "add" => Instr::Add { rd, rs, rt },
+                    "addu" => Instr::Addu { rd, rs, rt },
+                    "sub" => Instr::Sub { rd, rs, rt },
+                    "mul" => Instr::Mul { rd, rs, rt },
+                    _ => Instr::Div { rd, rs, rt },
+                }
+            }
+            "addiu" => Instr::Addiu {
+                rt: self.register(args.first())?,
+                rs: self.register(args.get(1))?,
+                imm: self.int(args.get(2))? as i32,
+            },
+            "sll" => Instr::Sll {
+                rd: self.register(args.first())?,
+                rt: self.register(args.get(1))?,
+                shift: self.int(args.get(2))? as u32 & 31,
+            },
+            "b" => Instr::B {
+                target: self.address(args.first())?,
+            },
+            "beqz" | "bnez" => {
+                let rs = self.register(args.first())?;
+                let target = self.address(args.get(1))?;
+
+                if op == "beqz" {
+                    Instr::Beqz { rs, target }
+                } else {
+                    Instr::Bnez { rs, target }
+                }
+            }
+            "beq" | "ble" | "blt" | "bge" => {
+                let rs = self.register(args.first())?;
+                let target = self.address(args.get(2))?;
+
+                // The second operand may be a register or an immediate
+                match (op, args.get(1).map(|arg| arg.starts_with('$'))) {
+                    ("beq", _) => Instr::Beq {
+                        rs,
+                        rt: self.register(args.get(1))?,
+                        target,
+                    },
+                    ("ble", _) => Instr::Ble {
+                        rs,
+                        rt: self.register(args.get(1))?,
+                        target,
+                    },
+                    ("blt", Some(true)) => Instr::Blt {
+                        rs,
+                        rt: self.register(args.get(1))?,
+                        target,
+                    },
+                    ("blt", _) => Instr::Blti {
+                        rs,
+                        imm: self.int(args.get(1))? as i32,
+                        target,
+                    },
+                    (_, _) => Instr::Bgei {
+                        rs,
+                        imm: self.int(args.get(1))? as i32,
+                        target,
+                    },
+                }
+            }
+            "jal" => Instr::Jal {
+                target: self.address(args.first())?,
+            },
+            "jalr" => Instr::Jalr {
+                rs: self.register(args.first())?,
+            },
+            "jr" => Instr::Jr {
+                rs: self.register(args.first())?,
+            },
+            _ => return Err(self.error(&format!("unknown instruction {}", op))),
+        };
+
+        Ok(instr)
+    }
+}
+
+pub fn assemble(assembly: &str) -> Result<Program, String> {
+    let mut asm = Assembler {
+        labels: HashMap::new(),
+        line_no: 0,
+        line: "",
+    };
+
+    // The runtime routines are at addresses of their own
+    for (name, i) in RUNTIME_ROUTINES.iter().map(|(name, _)| name).zip(0..) {
+        asm.labels
+            .insert(name.to_string(), RUNTIME_BASE + i * WORD_SIZE);
+    }
+
+    // First pass: find the address of every label
+    let mut segment = Segment::Text;
+    let mut data_size: usize = 0;
+    let mut text_size: u32 = 0;
+
+    for (line, line_no) in assembly.lines().zip(1..) {
+        asm.line_no = line_no;
+        asm.line = line;
+
+        let (label, words) = split_line(line);
+
+        if let Some(label) = label {
+            let addr = match segment {
+                Segment::Data => DATA_BASE + data_size as u32,
+                Segment::Text => TEXT_BASE + text_size * WORD_SIZE,
+            };
+
+            asm.labels.insert(label.to_string(), addr);
+        }
+
+        match words.first() {
+            None => {}
+            Some(&".data") => segment = Segment::Data,
+            Some(&".text") => segment = Segment::Text,
+            Some(&".globl") => {}
+            Some(&".align") => data_size = align(data_size, asm.int(words.get(1))? as u32),
+            Some(&".word") => data_size += (words.len() - 1) * WORD_SIZE as usize,
+            Some(&".byte") => data_size += words.len() - 1,
+            Some(directive) if directive.starts_with('.') => {
+                return Err(asm.error(&format!("unknown directive {}", directive)));
+            }
+            Some(_) if segment == Segment::Data => {
+                return Err(asm.error("instruction in the data segment"));
+            }
+            Some(_) => text_size += 1,
+        }
+    }
+
+    // Second pass: fill in the data and decode the instructions
+    let mut data: Vec<u8> = Vec::with_capacity(data_size);
+    let mut text: Vec<Instr> = Vec::with_capacity(text_size as usize);
+
+    for (line, line_no) in assembly.lines().zip(1..) {
+        asm.line_no = line_no;
+        asm.line = line;
+
+        let (_, words) = split_line(line);
+
+        match words.first() {
+            None | Some(&".data") | Some(&".text") | Some(&".globl") => {}
+            Some(&".align") => {
+                let size = align(data.len(), asm.int(words.get(1))? as u32);
+                data.resize(size, 0);
+            }
+            Some(&".word") => {
+                for word in words[1..].iter() {
+                    let val = asm.address(Some(word))?;
+                    data.extend_from_slice(&val.to_le_bytes());
+                }
+            }
+            Some(&".byte") => {
+                for word in words[1..].iter() {
+                    data.push(asm.int(Some(word))? as u8);
+                }
+            }
+            Some(_) => text.push(asm.instr(&words)?),
+        }
+    }
+
+    Ok(Program {
+        text,
+        data,
+        labels: asm.labels,
+    })
+}
@@ -0,0 +1,334 @@
+// Executes an assembled program
+//
+// Memory has three regions: the data segment (followed by the heap) at DATA_BASE,
+// the stack (which grows down from STACK_TOP) and the instructions, which are not
+// addressable as data.
+
+use std::io::{BufRead, Write};
+
+use super::asm::{Instr, Program, RA, SP, ZERO};
+use super::runtime::RUNTIME_ROUTINES;
+use super::{DATA_BASE, EXIT_ADDR, RUNTIME_BASE, TEXT_BASE, WORD_SIZE};
+
+pub const STACK_TOP: u32 = 0x8000_0000; // Just above the highest stack address
+const MAX_STACK_SIZE: usize = 64 << 20; // Bytes
+const STACK_GUARD_SIZE: u32 = 1 << 20; // Bytes
+const MAX_HEAP_SIZE: usize = 1 << 30; // Bytes
+
+// How a call into the program ended
+#[derive(PartialEq, Eq)]
+pub enum Flow {
+    Return, // Returned to the caller
+    Halt,   // A runtime error stopped the program (the message has been written)
+}
+
+pub struct Memory {
+    data: Vec<u8>,  // From DATA_BASE
+    stack: Vec<u8>, // Up to STACK_TOP
+}
+
+impl Memory {
+    pub fn new(data: Vec<u8>) -> Memory {
+        Memory {
+            data,
+            stack: Vec::new(),
+        }
+    }
+
+    // The bytes at an address
+    fn bytes(&mut self, addr: u32, len: u32) -> Result<&mut [u8], String> {
+        let bad_address = || format!("Bad address {:#010x}", addr);
+
+        if addr >= DATA_BASE {
+            let start = (addr - DATA_BASE) as usize;
+            let end = start + len as usize;
+
+            if end <= self.data.len() {
+                return Ok(&mut self.data[start..end]);
+            }
+        }
+
+        if addr < STACK_TOP && STACK_TOP - addr <= MAX_STACK_SIZE as u32 {
+            let depth = (STACK_TOP - addr) as usize;
+
+            if depth < len as usize {
+                return Err(bad_address());
+            }
+
+            // The stack is grown on demand
+            if depth > self.stack.len() {
+                let new_len = (self.stack.len() * 2).max(depth).min(MAX_STACK_SIZE);
+                let mut stack = vec![0; new_len - self.stack.len()];
+                stack.append(&mut self.stack);
+                self.stack = stack;
+            }
+
+            let start = self.stack.len() - depth;
+
+            return Ok(&mut self.stack[start..start + len as usize]);
+        }
+
+        // Accesses just beyond the limit are from a stack that has grown too large
+        let stack_limit = STACK_TOP - MAX_STACK_SIZE as u32;
+
+        if addr < stack_limit && addr >= stack_limit - STACK_GUARD_SIZE {
+            Err("Stack overflow".to_string())
+        } else {
+            Err(bad_address())
+        }
+    }
+
+    pub fn load_word(&mut self, addr: u32) -> Result<u32, String> {
+        if !addr.is_multiple_of(WORD_SIZE) {
+            return Err(format!("Unaligned address {:#010x}", addr));
+        }
+
+        let bytes = self.bytes(addr, WORD_SIZE)?;
+
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    pub fn store_word(&mut self, addr: u32, val: u32) -> Result<(), String> {
+        if !addr.is_multiple_of(WORD_SIZE) {
+            return Err(format!("Unaligned address {:#010x}", addr));
+        }
+
+        self.bytes(addr, WORD_SIZE)?
+            .copy_from_slice(&val.to_le_bytes());
+
+        Ok(())
+    }
+
+    pub fn load_byte(&mut self, addr: u32) -> Result<u8, String> {
+        Ok(self.bytes(addr, 1)?[0])
+    }
+
+    pub fn store_byte(&mut self, addr: u32, val: u8) -> Result<(), String> {
+        self.bytes(addr, 1)?[0] = val;
+
+        Ok(())
+    }
+
+    pub fn load_bytes(&mut self, addr: u32, len: u32) -> Result<Vec<u8>, String> {
+        Ok(self.bytes(addr, len)?.to_vec())
+    }
+
+    pub fn store_bytes(&mut self, addr: u32, bytes: &[u8]) -> Result<(), String> {
+        self.bytes(addr, bytes.len() as u32)?.copy_from_slice(bytes);
+
+        Ok(())
+    }
+
+    // Copy bytes from one address to another
+    pub fn copy_bytes(&mut self, src: u32, dest: u32, len: u32) -> Result<(), String> {
+        // Objects are copied within the data segment and heap, so this is done in place
+        if src >= DATA_BASE && dest >= DATA_BASE {
+            let src = (src - DATA_BASE) as usize;
+            let dest = (dest - DATA_BASE) as usize;
+            let len = len as usize;
+
+            if src + len <= self.data.len() && dest + len <= self.data.len() {
+                self.data.copy_within(src..src + len, dest);
+                return Ok(());
+            }
+        }
+
+        let bytes = self.load_bytes(src, len)?;
+        self.store_bytes(dest, &bytes)
+    }
+
+    // The address just past the heap
+    pub fn end(&self) -> u32 {
+        DATA_BASE + self.data.len() as u32
+    }
+
+    // Allocate zeroed words on the heap
+    pub fn alloc(&mut self, words: u32) -> Result<u32, String> {
+        let addr = DATA_BASE + self.data.len() as u32;
+        let size = self.data.len() + (words * WORD_SIZE) as usize;
+
+        if size > MAX_HEAP_SIZE {
+            return Err("Out of memory".to_string());
+        }
+
+        self.data.resize(size, 0);
+
+        Ok(addr)
+    }
+}
+
+pub struct Machine<'a> {
+    pub program: &'a Program,
+    pub regs: [u32; 32],
+    pub memory: Memory,
+    pub input: &'a mut dyn BufRead,
+    pub output: &'a mut dyn Write,
+    pc: u32,
+}
+
+impl<'a> Machine<'a> {
+    pub fn new(
+        program: &'a Program,
+        input: &'a mut dyn BufRead,
+        output: &'a mut dyn Write,
+    ) -> Machine<'a> {
+        let mut regs = [0; 32];
+        regs[SP] = STACK_TOP - WORD_SIZE;
+
+        // The data segment is word aligned so that the heap is too
+        let mut data = program.data.clone();
+        data.resize(
+            data.len().div_ceil(WORD_SIZE as usize) * WORD_SIZE as usize,
+            0,
+        );
+
+        Machine {
+            program,
+            regs,
+            memory: Memory::new(data),
+            input,
+            output,
+            pc: 0,
+        }
+    }
+
+    // Call code at an address with the current registers
+    // Runs until the code returns or the program is halted.
+    pub fn call(&mut self, addr: u32) -> Result<Flow, String> {
+        self.regs[RA] = EXIT_ADDR;
+        self.pc = addr;
+
+        loop {
+            let pc = self.pc;
+
+            if pc == EXIT_ADDR {
+                return Ok(Flow::Return);
+            }
+
+            if pc >= TEXT_BASE {
+                let instr = match self
+                    .program
+                    .text
+                    .get(((pc - TEXT_BASE) / WORD_SIZE) as usize)
+                {
+                    Some(instr) => *instr,
+                    None => return Err(format!("Jump to bad address {:#010x}", pc)),
+                };
+
+                self.pc = pc + WORD_SIZE;
+                self.execute(instr)?;
+                continue;
+            }
+
+            // A runtime routine returns to RA unless it halts the program
+            match RUNTIME_ROUTINES.get((pc.wrapping_sub(RUNTIME_BASE) / WORD_SIZE) as usize) {
+                Some((_, routine)) if pc >= RUNTIME_BASE => {
+                    if routine(self)? == Flow::Halt {
+                        return Ok(Flow::Halt);
+                    }
+
+                    self.pc = self.regs[RA];
+                }
+                _ => return Err(format!("Jump to bad address {:#010x}", pc)),
+            }
+        }
+    }
+
+    fn set(&mut self, reg: usize, val: u32) {
+        if reg != ZERO {
+            self.regs[reg] = val;
+        }
+    }
+
+    fn execute(&mut self, instr: Instr) -> Result<(), String> {
+        let regs = self.regs;
+
+        match instr {
+            Instr::Lw { rt, offset, base } => {
+                let val = self
+                    .memory
+                    .load_word(regs[base].wrapping_add(offset as u32))?;
+                self.set(rt, val);
+            }
+            Instr::Sw { rt, offset, base } => {
+                self.memory
+                    .store_word(regs[base].wrapping_add(offset as u32), regs[rt])?;
+            }
+            Instr::Li { rd, imm } => self.set(rd, imm),
+            Instr::La { rd, addr } => self.set(rd, addr),
+            Instr::Move { rd, rs } => self.set(rd, regs[rs]),
+            Instr::Neg { rd, rs } => match 0i32.checked_sub(regs[rs] as i32) {
+                Some(val) => self.set(rd, val as u32),
+                None => return Err("Arithmetic overflow".to_string()),
+            },
+            Instr::Add { rd, rs, rt } => match (regs[rs] as i32).checked_add(regs[rt] as i32) {
+                Some(val) => self.set(rd, val as u32),
+                None => return Err("Arithmetic overflow".to_string()),
+            },
+            Instr::Addu { rd, rs, rt } => self.set(rd, regs[rs].wrapping_add(regs[rt])),
+            Instr::Addiu { rt, rs, imm } => self.set(rt, regs[rs].wrapping_add(imm as u32)),
+            Instr::Sub { rd, rs, rt } => match (regs[rs] as i32).checked_sub(regs[rt] as i32) {
+                Some(val) => self.set(rd, val as u32),
+                None => return Err("Arithmetic overflow".to_string()),
+            },
+            Instr::Mul { rd, rs, rt } => {
+                self.set(rd, (regs[rs] as i32).wrapping_mul(regs[rt] as i32) as u32)
+            }
+            Instr::Div { rd, rs, rt } => {
+                if regs[rt] == 0 {
+                    return Err("Division by zero".to_string());
+                }
+
+                self.set(rd, (regs[rs] as i32).wrapping_div(regs[rt] as i32) as u32);
+            }
+            Instr::Sll { rd, rt, shift } => self.set(rd, regs[rt] << shift),
+            Instr::B { target } => self.pc = target,
+            Instr::Beqz { rs, target } => {
+                if regs[rs] == 0 {
+                    self.pc = target;
+                }
+            }
+            Instr::Bnez { rs, target } => {
+                if regs[rs] != 0 {
+                    self.pc = target;
+                }
+            }
+            Instr::Beq { rs, rt, target } => {
+                if regs[rs] == regs[rt] {
+                    self.pc = target;
+                }
+            }
+            Instr::Ble { rs, rt, target } => {
+                if (regs[rs] as i32) <= (regs[rt] as i32) {
+                    self.pc = target;
+                }
+            }
+            Instr::Blt { rs, rt, target } => {
+                if (regs[rs] as i32) < (regs[rt] as i32) {
+                    self.pc = target;
+                }
+            }
+            Instr::Blti { rs, imm, target } => {
+                if (regs[rs] as i32) < imm {
+                    self.pc = target;
+                }
+            }
+            Instr::Bgei { rs, imm, target } => {
+                if (regs[rs] as i32) >= imm {
+                    self.pc = target;
+                }
+            }
+            Instr::Jal { target } => {
+                self.regs[RA] = self.pc;
+                self.pc = target;
+            }
+            Instr::Jalr { rs } => {
+                self.regs[RA] = self.pc;
+                self.pc = regs[rs];
+            }
+            Instr::Jr { rs } => self.pc = regs[rs],
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+// An interpreter for the MIPS assembly written by cgen
+// Programs can be run without SPIM or trap.handler: the instructions that cgen
+// writes are interpreted directly and the runtime routines of trap.handler are
+// implemented in Rust (see runtime.rs).
+
+mod asm;
+mod machine;
+mod runtime;
+
+use std::io::{BufRead, Write};
+
+use self::asm::{assemble, A0};
+use self::machine::{Flow, Machine};
+
+// The RISC-V interpreter has the same memory layout
+pub(crate) use self::machine::{Memory, STACK_TOP};
+
+const WORD_SIZE: u32 = 4;
+
+// Where each part of the program is placed in memory
+const EXIT_ADDR: u32 = 0x0020_0000; // Returning here ends a call from run()
+const RUNTIME_BASE: u32 = 0x0030_0000; // The runtime routines
+const TEXT_BASE: u32 = 0x0040_0000; // The instructions
+pub(crate) const DATA_BASE: u32 = 0x1000_0000; // The data segment, followed by the heap
+
+// Printed when a program finishes (as trap.handler does)
+pub(crate) const TERM_MSG: &str = "\nCOOL program successfully executed\n";
+
+// Run a program
+// Returns false if the program was stopped by a runtime error (e.g. a dispatch to void).
+// Errors are for programs that cannot be run (or that go wrong in a way that
+// COOL programs cannot, such as accessing a bad address).
+pub fn run(
+    assembly: &str,
+    input: &mut dyn BufRead,
+    output: &mut dyn Write,
+) -> Result<bool, String> {
+    let program = assemble(assembly)?;
+    let mut machine = Machine::new(&program, input, output);
+
+    let result = start(&mut machine);
+
+    // Output written before an error is kept
+    if let Err(why) = machine.output.flush() {
+        return Err(format!("Cannot write output: {}", why));
+    }
+
+    result
+}
+
+// Create a Main object and call its main method
+fn start(machine: &mut Machine) -> Result<bool, String> {
+    let main_proto = machine.program.label("Main_protObj")?;
+    let main_init = machine.program.label("Main_init")?;
+    let main_main = machine.program.label("Main.main")?;
+    let copy = machine.program.label("Object.copy")?;
+
+    machine.regs[A0] = main_proto;
+
+    // Each call leaves the new object in $a0 for the next (Main_init returns self)
+    for addr in [copy, main_init, main_main] {
+        if machine.call(addr)? == Flow::Halt {
+            return Ok(false);
+        }
+    }
+
+    if let Err(why) = machine.output.write_all(TERM_MSG.as_bytes()) {
+        return Err(format!("Cannot write output: {}", why));
+    }
+
+    Ok(true)
+}
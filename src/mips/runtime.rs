@@ -0,0 +1,351 @@
+// The runtime routines that SPIM loads from trap.handler
+// These are the methods of the basic classes and the routines that cgen jumps to
+// when a program fails. Messages match those of trap.handler.
+//
+// Arguments are passed on the stack (the last argument is at 4($sp)) and are
+// removed by the routine. Self and the result are in $a0.
+
+use super::asm::{A0, A1, SP, T1, T2};
+use super::machine::{Flow, Machine};
+use super::WORD_SIZE;
+
+pub type Routine = fn(&mut Machine) -> Result<Flow, String>;
+
+// Offsets of the fields of an object (in bytes)
+const TAG_OFFSET: u32 = 0;
+const SIZE_OFFSET: u32 = 4;
+const DISP_TAB_OFFSET: u32 = 8;
+const VAL_OFFSET: u32 = 12; // The value of an Int or Bool or the length of a String
+const STR_OFFSET: u32 = 16; // The characters of a String
+
+const HEADER_WORDS: u32 = 4; // The words of a String before its characters
+const EYE_CATCHER: u32 = 0xffff_ffff; // Precedes every object (for the garbage collector)
+
+pub const RUNTIME_ROUTINES: [(&str, Routine); 21] = [
+    ("Object.abort", object_abort),
+    ("Object.type_name", object_type_name),
+    ("Object.copy", object_copy),
+    ("IO.out_string", io_out_string),
+    ("IO.out_int", io_out_int),
+    ("IO.in_string", io_in_string),
+    ("IO.in_int", io_in_int),
+    ("String.length", string_length),
+    ("String.concat", string_concat),
+    ("String.substr", string_substr),
+    ("equality_test", equality_test),
+    ("_dispatch_abort", dispatch_abort),
+    ("_case_abort", case_abort),
+    ("_case_abort2", case_abort2),
+    ("_NoGC_Init", no_gc),
+    ("_NoGC_Collect", no_gc),
+    ("_GenGC_Init", no_gc),
+    ("_GenGC_Collect", no_gc),
+    ("_GenGC_Assign", no_gc),
+    ("_SCC_Init", no_gc),
+    ("_SCC_Collect", no_gc),
+];
+
+impl<'a> Machine<'a> {
+    // An argument of the routine (1 is the last)
+    fn arg(&mut self, n: u32) -> Result<u32, String> {
+        self.memory
+            .load_word(self.regs[SP].wrapping_add(n * WORD_SIZE))
+    }
+
+    fn pop_args(&mut self, count: u32) {
+        self.regs[SP] = self.regs[SP].wrapping_add(count * WORD_SIZE);
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.output
+            .write_all(bytes)
+            .map_err(|why| format!("Cannot write output: {}", why))
+    }
+
+    fn not_void(&self, obj: u32, routine: &str) -> Result<u32, String> {
+        if obj == 0 {
+            Err(format!("{} called on void", routine))
+        } else {
+            Ok(obj)
+        }
+    }
+
+    fn copy(&mut self, obj: u32) -> Result<u32, String> {
+        let obj = self.not_void(obj, "Object.copy")?;
+        let size = self.memory.load_word(obj + SIZE_OFFSET)?;
+
+        let new_obj = self.memory.alloc(size + 1)? + WORD_SIZE;
+        self.memory.store_word(new_obj - WORD_SIZE, EYE_CATCHER)?;
+        self.memory.copy_bytes(obj, new_obj, size * WORD_SIZE)?;
+
+        Ok(new_obj)
+    }
+
+    fn new_int(&mut self, val: i32) -> Result<u32, String> {
+        let proto = self.program.label("Int_protObj")?;
+        let obj = self.copy(proto)?;
+        self.memory.store_word(obj + VAL_OFFSET, val as u32)?;
+
+        Ok(obj)
+    }
+
+    fn int_val(&mut self, obj: u32) -> Result<i32, String> {
+        let obj = self.not_void(obj, "Int")?;
+
+        Ok(self.memory.load_word(obj + VAL_OFFSET)? as i32)
+    }
+
+    fn new_string(&mut self, val: &[u8]) -> Result<u32, String> {
+        let proto = self.program.label("String_protObj")?;
+        let tag = self.memory.load_word(proto + TAG_OFFSET)?;
+        let disp_tab = self.memory.load_word(proto + DISP_TAB_OFFSET)?;
+        let len = self.new_int(val.len() as i32)?;
+
+        // The characters are followed by a null
+        let size = HEADER_WORDS + val.len() as u32 / WORD_SIZE + 1;
+
+        let obj = self.memory.alloc(size + 1)? + WORD_SIZE;
+        self.memory.store_word(obj - WORD_SIZE, EYE_CATCHER)?;
+        self.memory.store_word(obj + TAG_OFFSET, tag)?;
+        self.memory.store_word(obj + SIZE_OFFSET, size)?;
+        self.memory.store_word(obj + DISP_TAB_OFFSET, disp_tab)?;
+        self.memory.store_word(obj + VAL_OFFSET, len)?;
+        self.memory.store_bytes(obj + STR_OFFSET, val)?;
+
+        Ok(obj)
+    }
+
+    fn string_val(&mut self, obj: u32) -> Result<Vec<u8>, String> {
+        let obj = self.not_void(obj, "String")?;
+        let len = self.memory.load_word(obj + VAL_OFFSET)?;
+        let len = self.int_val(len)?;
+
+        self.memory.load_bytes(obj + STR_OFFSET, len as u32)
+    }
+
+    // The name of the class of an object
+    fn class_name(&mut self, obj: u32) -> Result<Vec<u8>, String> {
+        let tag = self.memory.load_word(obj + TAG_OFFSET)?;
+        let class_name_tab = self.program.label("class_nameTab")?;
+        let name = self.memory.load_word(class_name_tab + tag * WORD_SIZE)?;
+
+        self.string_val(name)
+    }
+
+    fn read_line(&mut self) -> Result<Vec<u8>, String> {
+        // Prompts must appear before the program waits for input
+        self.output
+            .flush()
+            .map_err(|why| format!("Cannot write output: {}", why))?;
+
+        let mut line = Vec::new();
+
+        if let Err(why) = self.input.read_until(b'\n', &mut line) {
+            return Err(format!("Cannot read input: {}", why));
+        }
+
+        if line.last() == Some(&b'\n') {
+            line.pop();
+        }
+
+        Ok(line)
+    }
+}
+
+fn object_abort(machine: &mut Machine) -> Result<Flow, String> {
+    let class_name = machine.class_name(machine.regs[A0])?;
+
+    machine.write(b"Abort called from class ")?;
+    machine.write(&class_name)?;
+    machine.write(b"\n")?;
+
+    Ok(Flow::Halt)
+}
+
+fn object_type_name(machine: &mut Machine) -> Result<Flow, String> {
+    let tag = machine.memory.load_word(machine.regs[A0] + TAG_OFFSET)?;
+    let class_name_tab = machine.program.label("class_nameTab")?;
+
+    machine.regs[A0] = machine.memory.load_word(class_name_tab + tag * WORD_SIZE)?;
+
+    Ok(Flow::Return)
+}
+
+fn object_copy(machine: &mut Machine) -> Result<Flow, String> {
+    machine.regs[A0] = machine.copy(machine.regs[A0])?;
+
+    Ok(Flow::Return)
+}
+
+fn io_out_string(machine: &mut Machine) -> Result<Flow, String> {
+    let arg = machine.arg(1)?;
+    let val = machine.string_val(arg)?;
+    machine.pop_args(1);
+
+    machine.write(&val)?;
+
+    Ok(Flow::Return)
+}
+
+fn io_out_int(machine: &mut Machine) -> Result<Flow, String> {
+    let arg = machine.arg(1)?;
+    let val = machine.int_val(arg)?;
+    machine.pop_args(1);
+
+    machine.write(val.to_string().as_bytes())?;
+
+    Ok(Flow::Return)
+}
+
+fn io_in_string(machine: &mut Machine) -> Result<Flow, String> {
+    let line = machine.read_line()?;
+
+    // A string with a null character is read as the empty string
+    let val: &[u8] = if line.contains(&0) { b"" } else { &line };
+
+    machine.regs[A0] = machine.new_string(val)?;
+
+    Ok(Flow::Return)
+}
+
+fn io_in_int(machine: &mut Machine) -> Result<Flow, String> {
+    let line = machine.read_line()?;
+    let line = String::from_utf8_lossy(&line);
+    let line = line.trim_start();
+
+    // Like atoi, leading digits are read and the rest is ignored
+    let end = line
+        .char_indices()
+        .find(|(i, c)| !(c.is_ascii_digit() || (*i == 0 && (*c == '-' || *c == '+'))))
+        .map_or(line.len(), |(i, _)| i);
+
+    let val = line[..end].parse::<i64>().unwrap_or(0) as i32;
+
+    machine.regs[A0] = machine.new_int(val)?;
+
+    Ok(Flow::Return)
+}
+
+fn string_length(machine: &mut Machine) -> Result<Flow, String> {
+    let val = machine.string_val(machine.regs[A0])?;
+
+    machine.regs[A0] = machine.new_int(val.len() as i32)?;
+
+    Ok(Flow::Return)
+}
+
+fn string_concat(machine: &mut Machine) -> Result<Flow, String> {
+    let arg = machine.arg(1)?;
+    let mut val = machine.string_val(machine.regs[A0])?;
+    val.extend(machine.string_val(arg)?);
+    machine.pop_args(1);
+
+    machine.regs[A0] = machine.new_string(&val)?;
+
+    Ok(Flow::Return)
+}
+
+fn string_substr(machine: &mut Machine) -> Result<Flow, String> {
+    let len = machine.arg(1)?;
+    let len = machine.int_val(len)?;
+    let index = machine.arg(2)?;
+    let index = machine.int_val(index)?;
+    let val = machine.string_val(machine.regs[A0])?;
+    machine.pop_args(2);
+
+    let msg: &[u8] = if index < 0 {
+        b"Index to substr is negative\n"
+    } else if index as usize > val.len() {
+        b"Index to substr is too big\n"
+    } else if len < 0 {
+        b"Length to substr is negative\n"
+    } else if (index + len) as usize > val.len() {
+        b"Length to substr too long\n"
+    } else {
+        let start = index as usize;
+
+        machine.regs[A0] = machine.new_string(&val[start..start + len as usize])?;
+
+        return Ok(Flow::Return);
+    };
+
+    machine.write(msg)?;
+    machine.write(b"Execution aborted.\n")?;
+
+    Ok(Flow::Halt)
+}
+
+// Compare the objects in $t1 and $t2 (which are known to be different objects)
+// Returns $a0 if they are equal and $a1 if they are not.
+fn equality_test(machine: &mut Machine) -> Result<Flow, String> {
+    let lhs = machine.regs[T1];
+    let rhs = machine.regs[T2];
+
+    let equal = if lhs == 0 || rhs == 0 {
+        false
+    } else {
+        let lhs_tag = machine.memory.load_word(lhs + TAG_OFFSET)?;
+        let rhs_tag = machine.memory.load_word(rhs + TAG_OFFSET)?;
+
+        let int_tag = machine.program.label("_int_tag")?;
+        let int_tag = machine.memory.load_word(int_tag)?;
+        let bool_tag = machine.program.label("_bool_tag")?;
+        let bool_tag = machine.memory.load_word(bool_tag)?;
+        let string_tag = machine.program.label("_string_tag")?;
+        let string_tag = machine.memory.load_word(string_tag)?;
+
+        if lhs_tag != rhs_tag {
+            false
+        } else if lhs_tag == int_tag || lhs_tag == bool_tag {
+            machine.int_val(lhs)? == machine.int_val(rhs)?
+        } else if lhs_tag == string_tag {
+            machine.string_val(lhs)? == machine.string_val(rhs)?
+        } else {
+            false
+        }
+    };
+
+    if !equal {
+        machine.regs[A0] = machine.regs[A1];
+    }
+
+    Ok(Flow::Return)
+}
+
+// $a0 is the name of the file and $t1 is the line number
+fn dispatch_abort(machine: &mut Machine) -> Result<Flow, String> {
+    let file_name = machine.string_val(machine.regs[A0])?;
+    let line_no = machine.regs[T1];
+
+    machine.write(&file_name)?;
+    machine.write(format!(":{}: Dispatch to void.\n", line_no).as_bytes())?;
+
+    Ok(Flow::Halt)
+}
+
+// $a0 is the object that did not match
+fn case_abort(machine: &mut Machine) -> Result<Flow, String> {
+    let class_name = machine.class_name(machine.regs[A0])?;
+
+    machine.write(b"No match in case statement for Class ")?;
+    machine.write(&class_name)?;
+    machine.write(b"\n")?;
+
+    Ok(Flow::Halt)
+}
+
+// $a0 is the name of the file and $t1 is the line number
+fn case_abort2(machine: &mut Machine) -> Result<Flow, String> {
+    let file_name = machine.string_val(machine.regs[A0])?;
+    let line_no = machine.regs[T1];
+
+    machine.write(&file_name)?;
+    machine.write(format!(":{}: Match on void in case statement.\n", line_no).as_bytes())?;
+
+    Ok(Flow::Halt)
+}
+
+// Memory is never reclaimed (whichever collector the program asks for)
+fn no_gc(_machine: &mut Machine) -> Result<Flow, String> {
+    Ok(Flow::Return)
+}
@@ -1,4 +1,5 @@
 use crate::ast::{Attr, Class, Expression, Method, ObjectID, TypeID};
+use crate::diagnostics::{Diagnostic, Diagnostics, ErrorCode, Location};
 use crate::token::Token;
 
 use self::node::ParseStackNode;
@@ -58,51 +59,47 @@ impl Parser {
         let token = self.tokens.pop_front().unwrap();
 
         let node = match token {
-            Token::Add { line_no } => ParseStackNode::TermAdd { line_no },
-            Token::Div { line_no } => ParseStackNode::TermDiv { line_no },
-            Token::Sub { line_no } => ParseStackNode::TermSub { line_no },
-            Token::Mul { line_no } => ParseStackNode::TermMul { line_no },
-            Token::Eq { line_no } => ParseStackNode::TermEq { line_no },
-            Token::LT { line_no } => ParseStackNode::TermLT { line_no },
-            Token::Dot { line_no } => ParseStackNode::TermDot { line_no },
-            Token::Neg { line_no } => ParseStackNode::TermNeg { line_no },
-            Token::Comma { line_no } => ParseStackNode::TermComma { line_no },
-            Token::SemiColon { line_no } => ParseStackNode::TermSemiColon { line_no },
-            Token::Colon { line_no } => ParseStackNode::TermColon { line_no },
-            Token::OpenParen { line_no } => ParseStackNode::TermOpenParen { line_no },
-            Token::CloseParen { line_no } => ParseStackNode::TermCloseParen { line_no },
-            Token::At { line_no } => ParseStackNode::TermAt { line_no },
-            Token::OpenBrace { line_no } => ParseStackNode::TermOpenBrace { line_no },
-            Token::CloseBrace { line_no } => ParseStackNode::TermCloseBrace { line_no },
-            Token::Class { line_no } => ParseStackNode::TermClass { line_no },
-            Token::Else { line_no } => ParseStackNode::TermElse { line_no },
-            Token::Fi { line_no } => ParseStackNode::TermFi { line_no },
-            Token::If { line_no } => ParseStackNode::TermIf { line_no },
-            Token::In { line_no } => ParseStackNode::TermIn { line_no },
-            Token::Inherits { line_no } => ParseStackNode::TermInherits { line_no },
-            Token::Let { line_no } => ParseStackNode::TermLet { line_no },
-            Token::Loop { line_no } => ParseStackNode::TermLoop { line_no },
-            Token::Pool { line_no } => ParseStackNode::TermPool { line_no },
-            Token::Then { line_no } => ParseStackNode::TermThen { line_no },
-            Token::While { line_no } => ParseStackNode::TermWhile { line_no },
-            Token::Case { line_no } => ParseStackNode::TermCase { line_no },
-            Token::Esac { line_no } => ParseStackNode::TermEsac { line_no },
-            Token::Of { line_no } => ParseStackNode::TermOf { line_no },
-            Token::New { line_no } => ParseStackNode::TermNew { line_no },
-            Token::IsVoid { line_no } => ParseStackNode::TermIsVoid { line_no },
-            Token::Not { line_no } => ParseStackNode::TermNot { line_no },
-            Token::DArrow { line_no } => ParseStackNode::TermDArrow { line_no },
-            Token::LEq { line_no } => ParseStackNode::TermLEq { line_no },
-            Token::Assign { line_no } => ParseStackNode::TermAssign { line_no },
-            Token::BoolConst { line_no, val } => ParseStackNode::TermBoolConst { line_no, val },
-            Token::TypeID { line_no, type_name } => {
-                ParseStackNode::TermTypeID { line_no, type_name }
-            }
-            Token::ObjectID { line_no, obj_name } => {
-                ParseStackNode::TermObjectID { line_no, obj_name }
-            }
-            Token::IntConst { line_no, val } => ParseStackNode::TermIntConst { line_no, val },
-            Token::StrConst { line_no, val } => ParseStackNode::TermStrConst { line_no, val },
+            Token::Add { span } => ParseStackNode::TermAdd { span },
+            Token::Div { span } => ParseStackNode::TermDiv { span },
+            Token::Sub { span } => ParseStackNode::TermSub { span },
+            Token::Mul { span } => ParseStackNode::TermMul { span },
+            Token::Eq { span } => ParseStackNode::TermEq { span },
+            Token::LT { span } => ParseStackNode::TermLT { span },
+            Token::Dot { span } => ParseStackNode::TermDot { span },
+            Token::Neg { span } => ParseStackNode::TermNeg { span },
+            Token::Comma { span } => ParseStackNode::TermComma { span },
+            Token::SemiColon { span } => ParseStackNode::TermSemiColon { span },
+            Token::Colon { span } => ParseStackNode::TermColon { span },
+            Token::OpenParen { span } => ParseStackNode::TermOpenParen { span },
+            Token::CloseParen { span } => ParseStackNode::TermCloseParen { span },
+            Token::At { span } => ParseStackNode::TermAt { span },
+            Token::OpenBrace { span } => ParseStackNode::TermOpenBrace { span },
+            Token::CloseBrace { span } => ParseStackNode::TermCloseBrace { span },
+            Token::Class { span } => ParseStackNode::TermClass { span },
+            Token::Else { span } => ParseStackNode::TermElse { span },
+            Token::Fi { span } => ParseStackNode::TermFi { span },
+            Token::If { span } => ParseStackNode::TermIf { span },
+            Token::In { span } => ParseStackNode::TermIn { span },
+            Token::Inherits { span } => ParseStackNode::TermInherits { span },
+            Token::Let { span } => ParseStackNode::TermLet { span },
+            Token::Loop { span } => ParseStackNode::TermLoop { span },
+            Token::Pool { span } => ParseStackNode::TermPool { span },
+            Token::Then { span } => ParseStackNode::TermThen { span },
+            Token::While { span } => ParseStackNode::TermWhile { span },
+            Token::Case { span } => ParseStackNode::TermCase { span },
+            Token::Esac { span } => ParseStackNode::TermEsac { span },
+            Token::Of { span } => ParseStackNode::TermOf { span },
+            Token::New { span } => ParseStackNode::TermNew { span },
+            Token::IsVoid { span } => ParseStackNode::TermIsVoid { span },
+            Token::Not { span } => ParseStackNode::TermNot { span },
+            Token::DArrow { span } => ParseStackNode::TermDArrow { span },
+            Token::LEq { span } => ParseStackNode::TermLEq { span },
+            Token::Assign { span } => ParseStackNode::TermAssign { span },
+            Token::BoolConst { span, val } => ParseStackNode::TermBoolConst { span, val },
+            Token::TypeID { span, type_name } => ParseStackNode::TermTypeID { span, type_name },
+            Token::ObjectID { span, obj_name } => ParseStackNode::TermObjectID { span, obj_name },
+            Token::IntConst { span, val } => ParseStackNode::TermIntConst { span, val },
+            Token::StrConst { span, val } => ParseStackNode::TermStrConst { span, val },
             Token::End { .. } => panic!("Cannot shift End token"),
         };
 
@@ -116,7 +113,8 @@ impl Parser {
         classes: &mut IndexMap<TypeID, Class>,
         in_file_name: &str,
         is_empty: bool,
-    ) -> Result<(), String> {
+        diagnostics: &mut Diagnostics,
+    ) {
         // class_list:            class ';'
         // class_list: class_list class ';'
 
@@ -129,36 +127,20 @@ impl Parser {
             self.pop()
         };
 
-        let line_no = class.line_no;
-
-        if let Some(prev_case) = classes.insert(name.clone(), class) {
-            if prev_case.basic {
-                return Err(format!(
-                    "{} : {} - Redefinition of basic class {}.",
-                    in_file_name, line_no, name
-                ));
-            } else {
-                return Err(format!(
-                    "{} : {} - Class {} was previously defined.",
-                    in_file_name, line_no, name
-                ));
-            }
-        }
+        add_class(classes, name, class, in_file_name, diagnostics);
 
         let new_node = class_list;
 
         let new_state = get_reduce_new_state_class_list(self.top_state());
 
         self.push(new_node, new_state);
-
-        Ok(())
     }
 
     fn reduce_class(&mut self, in_file_name: &str, file_no: u32, has_parent: bool) {
         // class: CLASS TYPEID                 '{' feature_list '}'
         // class: CLASS TYPEID INHERITS TYPEID '{' feature_list '}'
 
-        self.pop(); // CloseBrace
+        let hi = self.pop().span(); // CloseBrace
         let features = self.pop();
         self.pop(); // OpenBrace
 
@@ -171,14 +153,14 @@ impl Parser {
         });
 
         let name = self.pop().into_type_id();
-        let line_no = self.pop().line_no(); // CLASS
+        let span = self.pop().span().to(hi); // CLASS
 
         let (attrs, methods) = features.extract_features();
 
         let new_node = ParseStackNode::Class {
             file_name: in_file_name.to_string(),
             file_no,
-            line_no,
+            span,
             name,
             parent_name,
             attrs,
@@ -190,7 +172,12 @@ impl Parser {
         self.push(new_node, new_state);
     }
 
-    fn reduce_feature_list(&mut self, in_file_name: &str, is_empty: bool) -> Result<(), String> {
+    fn reduce_feature_list(
+        &mut self,
+        in_file_name: &str,
+        is_empty: bool,
+        diagnostics: &mut Diagnostics,
+    ) {
         // feature_list: /* empty */
         // feature_list: feature_list feature ';'
 
@@ -207,15 +194,10 @@ impl Parser {
                 ParseStackNode::Attr { .. } => {
                     attrs.push(feature.into_attr());
                 }
-                ParseStackNode::Method { line_no, .. } => {
+                ParseStackNode::Method { .. } => {
                     let (name, method) = feature.into_method();
 
-                    if methods.insert(name.clone(), method).is_some() {
-                        return Err(format!(
-                            "{} : {} - Method {} is multiply defined.",
-                            in_file_name, line_no, name
-                        ));
-                    }
+                    add_method(&mut methods, name, method, in_file_name, diagnostics);
                 }
                 _ => {
                     panic!("Bad ParseStackNode type");
@@ -230,14 +212,12 @@ impl Parser {
         let new_state = get_reduce_new_state_feature_list(self.top_state());
 
         self.push(new_node, new_state);
-
-        Ok(())
     }
 
     fn reduce_method(&mut self) {
         // feature: OBJECTID '(' formal_list ')' ':' TYPEID '{' expression '}'
 
-        self.pop(); // CloseBrace
+        let hi = self.pop().span(); // CloseBrace
         let expr = self.pop().into_expression();
         self.pop(); // OpenBrace
         let return_type = self.pop().into_type_id();
@@ -245,12 +225,12 @@ impl Parser {
         self.pop(); // CloseParen
         let formal_list = self.pop();
         self.pop(); // OpenParen
-        let (line_no, name) = self.pop().get_line_no_object_id();
+        let (span, name) = self.pop().get_span_object_id();
 
         let formals = formal_list.extract_formals();
 
         let new_node = ParseStackNode::Method {
-            line_no,
+            span: span.to(hi),
             name,
             formals,
             return_type,
@@ -274,12 +254,18 @@ impl Parser {
             Expression::NoExpr
         };
 
-        let type_decl = self.pop().into_type_id();
+        let (hi, type_decl) = self.pop().get_span_type_id();
         self.pop(); // Colon
-        let (line_no, name) = self.pop().get_line_no_object_id();
+        let (span, name) = self.pop().get_span_object_id();
+
+        // The initialization is part of the attribute
+        let hi = match &init {
+            Expression::NoExpr => hi,
+            init => init.span(),
+        };
 
         let new_node = ParseStackNode::Attr {
-            line_no,
+            span: span.to(hi),
             name,
             type_decl,
             init,
@@ -313,9 +299,9 @@ impl Parser {
     }
 
     fn reduce_formal_list(&mut self, is_empty: bool) {
-    	// formal_list : /* empty /*
-    	// formal_list : formal_list_ne
-    	
+        // formal_list : /* empty /*
+        // formal_list : formal_list_ne
+
         let formals = if is_empty {
             Vec::new()
         } else {
@@ -333,12 +319,12 @@ impl Parser {
     fn reduce_formal(&mut self) {
         // formal: OBJECTID ':' TYPEID
 
-        let type_decl = self.pop().into_type_id();
+        let (hi, type_decl) = self.pop().get_span_type_id();
         self.pop(); // Colon
-        let (line_no, name) = self.pop().get_line_no_object_id();
+        let (span, name) = self.pop().get_span_object_id();
 
         let new_node = ParseStackNode::Formal {
-            line_no,
+            span: span.to(hi),
             name,
             type_decl,
         };
@@ -378,10 +364,10 @@ impl Parser {
         self.pop(); // DARROW
         let type_decl = self.pop().into_type_id();
         self.pop(); // Colon
-        let (line_no, name) = self.pop().get_line_no_object_id();
+        let (span, name) = self.pop().get_span_object_id();
 
         let new_node = ParseStackNode::Branch {
-            line_no,
+            span: span.to(expr.span()),
             name,
             type_decl,
             expr,
@@ -474,10 +460,10 @@ impl Parser {
 
         let type_decl = self.pop().into_type_id();
         self.pop(); // Colon
-        let (line_no, identifier) = self.pop().get_line_no_object_id();
+        let (span, identifier) = self.pop().get_span_object_id();
 
         let new_node = ParseStackNode::ExpressionLet {
-            line_no,
+            span: span.to(body.span()),
             identifier,
             type_decl,
             init,
@@ -494,10 +480,10 @@ impl Parser {
 
         let expr = Box::new(self.pop().into_expression());
         self.pop(); // ASSIGN
-        let (line_no, name) = self.pop().get_line_no_object_id();
+        let (span, name) = self.pop().get_span_object_id();
 
         let new_node = ParseStackNode::Assign {
-            line_no,
+            span: span.to(expr.span()),
             name,
             expr,
         };
@@ -512,29 +498,29 @@ impl Parser {
         // expression: expression            '.' OBJECTID '(' expression_list_C ')'
         // expression: expression '@' TYPEID '.' OBJECTID '(' expression_list_C ')'
 
-        self.pop(); // CloseParen
+        let hi = self.pop().span(); // CloseParen
         let expression_list_c = self.pop();
         self.pop(); // OpenParen
-        let (line_no, name) = self.pop().get_line_no_object_id();
+        let (span, name) = self.pop().get_span_object_id();
         let type_name = None;
 
-        let (line_no, expr, type_name, name) = match dispatch_type {
+        let (span, expr, type_name, name) = match dispatch_type {
             DispatchType::OnSelf => {
                 let expr = Box::new(Expression::VarByName {
-                    line_no,
+                    span,
                     name: ObjectID::new_self(),
                     static_type: TypeID::new_no_type(),
                 });
 
-                (line_no, expr, type_name, name)
+                (span, expr, type_name, name)
             }
             DispatchType::OnExpr => {
                 self.pop(); // Dot
 
                 let expr = Box::new(self.pop().into_expression());
-                let line_no = expr.line_no();
+                let span = expr.span();
 
-                (line_no, expr, type_name, name)
+                (span, expr, type_name, name)
             }
             DispatchType::Static => {
                 self.pop(); // Dot
@@ -542,16 +528,16 @@ impl Parser {
                 self.pop(); // At
                 let expr = Box::new(self.pop().into_expression());
 
-                let line_no = expr.line_no();
+                let span = expr.span();
 
-                (line_no, expr, type_name, name)
+                (span, expr, type_name, name)
             }
         };
 
         let args = expression_list_c.extract_expressions();
 
         let new_node = ParseStackNode::Dispatch {
-            line_no,
+            span: span.to(hi),
             expr,
             type_name,
             name,
@@ -566,16 +552,16 @@ impl Parser {
     fn reduce_cond(&mut self) {
         // expression: IF expression THEN expression ELSE expression FI
 
-        self.pop(); // FI
+        let hi = self.pop().span(); // FI
         let else_expr = Box::new(self.pop().into_expression());
         self.pop(); // ELSE
         let then_expr = Box::new(self.pop().into_expression());
         self.pop(); // THEN
         let pred = Box::new(self.pop().into_expression());
-        let line_no = self.pop().line_no(); // IF
+        let span = self.pop().span().to(hi); // IF
 
         let new_node = ParseStackNode::Cond {
-            line_no,
+            span,
             pred,
             then_expr,
             else_expr,
@@ -589,17 +575,13 @@ impl Parser {
     fn reduce_while(&mut self) {
         // expression: WHILE expression LOOP expression POOL
 
-        self.pop(); // POOL
+        let hi = self.pop().span(); // POOL
         let body = Box::new(self.pop().into_expression());
         self.pop(); // LOOP
         let pred = Box::new(self.pop().into_expression());
-        let line_no = self.pop().line_no(); // WHILE
+        let span = self.pop().span().to(hi); // WHILE
 
-        let new_node = ParseStackNode::Loop {
-            line_no,
-            pred,
-            body,
-        };
+        let new_node = ParseStackNode::Loop { span, pred, body };
 
         let new_state = get_reduce_new_state_expression(self.top_state());
 
@@ -609,13 +591,13 @@ impl Parser {
     fn reduce_block(&mut self) {
         // expression: '{' expression_list_SC '}'
 
-        self.pop(); // CloseBrace
+        let hi = self.pop().span(); // CloseBrace
         let expression_list_sc = self.pop();
-        let line_no = self.pop().line_no(); // OpenBrace
+        let span = self.pop().span().to(hi); // OpenBrace
 
         let body = expression_list_sc.extract_expressions();
 
-        let new_node = ParseStackNode::Block { line_no, body };
+        let new_node = ParseStackNode::Block { span, body };
 
         let new_state = get_reduce_new_state_expression(self.top_state());
 
@@ -626,10 +608,10 @@ impl Parser {
         // expression: LET expression_let
 
         let expr_let = self.pop();
-        self.pop(); // LET
+        let lo = self.pop().span(); // LET
 
         let new_node = if let ParseStackNode::ExpressionLet {
-            line_no,
+            span,
             identifier,
             type_decl,
             init,
@@ -637,7 +619,7 @@ impl Parser {
         } = expr_let
         {
             ParseStackNode::Let {
-                line_no,
+                span: lo.to(span),
                 identifier,
                 type_decl,
                 init,
@@ -655,16 +637,16 @@ impl Parser {
     fn reduce_type_case(&mut self) {
         // expression: CASE expression OF branch_list ESAC
 
-        self.pop(); // ESAC
+        let hi = self.pop().span(); // ESAC
         let branch_list = self.pop();
         self.pop(); // OF
         let expr = Box::new(self.pop().into_expression());
-        let line_no = self.pop().line_no(); // CASE
+        let span = self.pop().span().to(hi); // CASE
 
         let branches = branch_list.extract_branches();
 
         let new_node = ParseStackNode::TypeCase {
-            line_no,
+            span,
             expr,
             branches,
         };
@@ -677,10 +659,10 @@ impl Parser {
     fn reduce_new(&mut self) {
         // expression: NEW TYPEID
 
-        let type_name = self.pop().into_type_id();
-        let line_no = self.pop().line_no(); // NEW
+        let (hi, type_name) = self.pop().get_span_type_id();
+        let span = self.pop().span().to(hi); // NEW
 
-        let new_node = ParseStackNode::New { line_no, type_name };
+        let new_node = ParseStackNode::New { span, type_name };
 
         let new_state = get_reduce_new_state_expression(self.top_state());
 
@@ -691,9 +673,9 @@ impl Parser {
         // expression: ISVOID expression
 
         let expr = Box::new(self.pop().into_expression());
-        let line_no = self.pop().line_no(); // ISVOID
+        let span = self.pop().span().to(expr.span()); // ISVOID
 
-        let new_node = ParseStackNode::IsVoid { line_no, expr };
+        let new_node = ParseStackNode::IsVoid { span, expr };
 
         let new_state = get_reduce_new_state_expression(self.top_state());
 
@@ -708,7 +690,7 @@ impl Parser {
         let expr_lhs = Box::new(self.pop().into_expression());
 
         let new_node = ParseStackNode::ArithOp {
-            line_no: expr_lhs.line_no(),
+            span: expr_lhs.span().to(expr_rhs.span()),
             expr_lhs,
             expr_rhs,
             arith_op_type,
@@ -723,10 +705,10 @@ impl Parser {
         // expression: '~' expression
 
         let expr = Box::new(self.pop().into_expression());
-        self.pop(); // Neg
+        let lo = self.pop().span(); // Neg
 
         let new_node = ParseStackNode::Neg {
-            line_no: expr.line_no(),
+            span: lo.to(expr.span()),
             expr,
         };
 
@@ -743,7 +725,7 @@ impl Parser {
         let expr_lhs = Box::new(self.pop().into_expression());
 
         let new_node = ParseStackNode::Comp {
-            line_no: expr_lhs.line_no(),
+            span: expr_lhs.span().to(expr_rhs.span()),
             expr_lhs,
             expr_rhs,
             comp_type,
@@ -762,7 +744,7 @@ impl Parser {
         let expr_lhs = Box::new(self.pop().into_expression());
 
         let new_node = ParseStackNode::Eq {
-            line_no: expr_lhs.line_no(),
+            span: expr_lhs.span().to(expr_rhs.span()),
             expr_lhs,
             expr_rhs,
         };
@@ -776,10 +758,10 @@ impl Parser {
         // expression: NOT expression
 
         let expr = Box::new(self.pop().into_expression());
-        self.pop(); // NOT
+        let lo = self.pop().span(); // NOT
 
         let new_node = ParseStackNode::Not {
-            line_no: expr.line_no(),
+            span: lo.to(expr.span()),
             expr,
         };
 
@@ -803,9 +785,9 @@ impl Parser {
     fn reduce_var_by_name(&mut self) {
         // expression: OBJECTID
 
-        let (line_no, name) = self.pop().get_line_no_object_id();
+        let (span, name) = self.pop().get_span_object_id();
 
-        let new_node = ParseStackNode::VarByName { line_no, name };
+        let new_node = ParseStackNode::VarByName { span, name };
 
         let new_state = get_reduce_new_state_expression(self.top_state());
 
@@ -817,8 +799,8 @@ impl Parser {
 
         let e = self.pop();
 
-        let new_node = if let ParseStackNode::TermIntConst { line_no, val } = e {
-            ParseStackNode::IntConst { line_no, val }
+        let new_node = if let ParseStackNode::TermIntConst { span, val } = e {
+            ParseStackNode::IntConst { span, val }
         } else {
             panic!("Bad ParseStackNode type")
         };
@@ -833,8 +815,8 @@ impl Parser {
 
         let e = self.pop();
 
-        let new_node = if let ParseStackNode::TermStrConst { line_no, val } = e {
-            ParseStackNode::StringConst { line_no, val }
+        let new_node = if let ParseStackNode::TermStrConst { span, val } = e {
+            ParseStackNode::StringConst { span, val }
         } else {
             panic!("Bad ParseStackNode type")
         };
@@ -849,8 +831,8 @@ impl Parser {
 
         let e = self.pop();
 
-        let new_node = if let ParseStackNode::TermBoolConst { line_no, val } = e {
-            ParseStackNode::BoolConst { line_no, val }
+        let new_node = if let ParseStackNode::TermBoolConst { span, val } = e {
+            ParseStackNode::BoolConst { span, val }
         } else {
             panic!("Bad ParseStackNode type")
         };
@@ -868,14 +850,15 @@ impl Parser {
         in_file_name: &str,
         file_no: u32,
         classes: &mut IndexMap<TypeID, Class>,
-    ) -> Result<(), String> {
+        diagnostics: &mut Diagnostics,
+    ) {
         match action {
             Action::Shift { new_state } => {
                 self.shift(new_state);
             }
 
             Action::ReduceClassList { is_empty } => {
-                self.reduce_class_list(classes, in_file_name, is_empty)?;
+                self.reduce_class_list(classes, in_file_name, is_empty, diagnostics);
             }
 
             Action::ReduceClass { has_parent } => {
@@ -883,7 +866,7 @@ impl Parser {
             }
 
             Action::ReduceFeatureList { is_empty } => {
-                self.reduce_feature_list(in_file_name, is_empty)?;
+                self.reduce_feature_list(in_file_name, is_empty, diagnostics);
             }
 
             Action::ReduceMethod => {
@@ -1010,31 +993,179 @@ impl Parser {
                 panic!("Cannot execute");
             }
         };
+    }
+
+    // Panic-mode error recovery
+    // Discard nodes from the stack until the parser is in a state that expects a
+    // feature, an expression within a block, or a class. Then discard tokens until
+    // the end of the broken construct (a ';' or '}' at the same nesting level, or
+    // the next 'class').
+    // Only braces count for nesting: a parenthesis left open by the error is never closed.
+    // Returns false if there is nothing left to parse.
+    fn recover(&mut self) -> bool {
+        if let Token::End { .. } = self.tokens.front().unwrap() {
+            return false;
+        }
 
-        Ok(())
+        // Braces opened by the discarded nodes
+        let mut depth: u32 = 0;
+
+        // Braces closed by the discarded nodes (they match braces further down the stack)
+        let mut closed: u32 = 0;
+
+        let top_state = loop {
+            match self.top_state() {
+                // Expecting a class, a feature, or an expression within a block
+                State::StateInitial
+                | State::State002
+                | State::State011
+                | State::State016
+                | State::State045
+                | State::State060 => break self.top_state(),
+
+                _ => match self.pop() {
+                    ParseStackNode::TermOpenBrace { .. } if closed > 0 => closed -= 1,
+                    ParseStackNode::TermOpenBrace { .. } => depth += 1,
+                    ParseStackNode::TermCloseBrace { .. } => closed += 1,
+                    _ => {}
+                },
+            }
+        };
+
+        let at_class_level = matches!(top_state, State::StateInitial | State::State002);
+
+        loop {
+            match self.tokens.front().unwrap() {
+                Token::End { .. } => break,
+                Token::Class { .. } if at_class_level => break,
+                Token::SemiColon { .. } if !at_class_level && depth == 0 => {
+                    self.tokens.pop_front();
+                    break;
+                }
+                Token::CloseBrace { .. } if !at_class_level && depth == 0 => break, // End of class or block
+                Token::OpenBrace { .. } => depth += 1,
+                Token::CloseBrace { .. } => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+
+            self.tokens.pop_front();
+        }
+
+        // If the first expression of a block was discarded, continue as if it had been parsed
+        if let State::State045 = top_state {
+            self.push(
+                ParseStackNode::ExpressionListSemiColon { exprs: Vec::new() },
+                get_reduce_new_state_expression_list_sc(top_state),
+            );
+        }
+
+        true
+    }
+}
+
+// Add a class to the program
+// The first definition of a class is kept and later ones are reported.
+pub fn add_class(
+    classes: &mut IndexMap<TypeID, Class>,
+    name: TypeID,
+    class: Class,
+    in_file_name: &str,
+    diagnostics: &mut Diagnostics,
+) {
+    let span = class.span;
+
+    match classes.get(&name) {
+        Some(prev_class) if prev_class.basic => diagnostics.error(
+            ErrorCode::ClassRedefined,
+            in_file_name,
+            span,
+            format!("Redefinition of basic class {}.", name),
+        ),
+        Some(prev_class) => diagnostics.report(
+            Diagnostic::error(
+                ErrorCode::ClassRedefined,
+                Some(Location::new(in_file_name, span)),
+                format!("Class {} was previously defined.", name),
+            )
+            .with_note(
+                Some(Location::new(&prev_class.file_name, prev_class.span)),
+                format!("Class {} previously defined here.", name),
+            ),
+        ),
+        None => {
+            classes.insert(name, class);
+        }
+    }
+}
+
+// Add a method to a class
+// The first definition of a method is kept and later ones are reported.
+pub fn add_method(
+    methods: &mut IndexMap<ObjectID, Method>,
+    name: ObjectID,
+    method: Method,
+    in_file_name: &str,
+    diagnostics: &mut Diagnostics,
+) {
+    if let Some(prev_method) = methods.get(&name) {
+        diagnostics.report(
+            Diagnostic::error(
+                ErrorCode::MethodRedefined,
+                Some(Location::new(in_file_name, method.span)),
+                format!("Method {} is multiply defined.", name),
+            )
+            .with_note(
+                Some(Location::new(in_file_name, prev_method.span)),
+                format!("Method {} previously defined here.", name),
+            ),
+        );
+    } else {
+        methods.insert(name, method);
     }
 }
 
 // Parse a queue of tokens into an AST
+// Syntax errors are recorded in diagnostics. The parser recovers and continues
+// so that later errors in the same file are also found.
 pub fn parse(
     tokens: VecDeque<Token>,
     in_file_name: &str,
     file_no: u32,
     classes: &mut IndexMap<TypeID, Class>,
-) -> Result<(), String> {
+    diagnostics: &mut Diagnostics,
+) {
     let mut parser = Parser::new(tokens);
 
-    if let Token::End { .. } = parser.tokens.front().unwrap() {
-        return Err(format!("{} - Empty file", in_file_name));
+    if let Token::End { span } = parser.tokens.front().unwrap() {
+        diagnostics.error(
+            ErrorCode::EmptyFile,
+            in_file_name,
+            *span,
+            "Empty file".to_string(),
+        );
+        return;
     }
 
     loop {
         match get_action(parser.top_state(), parser.tokens.front().unwrap()) {
             Ok(Action::Accept) => {
-                break Ok(());
+                break;
+            }
+            Ok(action) => {
+                parser.execute_action(action, in_file_name, file_no, classes, diagnostics)
+            }
+            Err(span) => {
+                diagnostics.error(
+                    ErrorCode::SyntaxError,
+                    in_file_name,
+                    span,
+                    "Syntax error".to_string(),
+                );
+
+                if diagnostics.limit_reached() || !parser.recover() {
+                    break;
+                }
             }
-            Ok(action) => parser.execute_action(action, in_file_name, file_no, classes)?,
-            Err(line_no) => return Err(format!("{} : {} - Syntax error", in_file_name, line_no)),
         }
     }
 }
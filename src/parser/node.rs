@@ -2,137 +2,139 @@ use crate::ast::{
     ArithOpType, Attr, Branch, Class, CompType, Expression, Formal, Method, ObjectID, TypeID,
 };
 
+use crate::span::Span;
+
 use indexmap::IndexMap;
 
 // The types of nodes to be placed on the parse stack
 pub enum ParseStackNode {
     // Terminals
     TermAdd {
-        line_no: i16,
+        span: Span,
     },
     TermDiv {
-        line_no: i16,
+        span: Span,
     },
     TermSub {
-        line_no: i16,
+        span: Span,
     },
     TermMul {
-        line_no: i16,
+        span: Span,
     },
     TermEq {
-        line_no: i16,
+        span: Span,
     },
     TermLT {
-        line_no: i16,
+        span: Span,
     },
     TermDot {
-        line_no: i16,
+        span: Span,
     },
     TermNeg {
-        line_no: i16,
+        span: Span,
     },
     TermComma {
-        line_no: i16,
+        span: Span,
     },
     TermSemiColon {
-        line_no: i16,
+        span: Span,
     },
     TermColon {
-        line_no: i16,
+        span: Span,
     },
     TermOpenParen {
-        line_no: i16,
+        span: Span,
     },
     TermCloseParen {
-        line_no: i16,
+        span: Span,
     },
     TermAt {
-        line_no: i16,
+        span: Span,
     },
     TermOpenBrace {
-        line_no: i16,
+        span: Span,
     },
     TermCloseBrace {
-        line_no: i16,
+        span: Span,
     },
     TermClass {
-        line_no: i16,
+        span: Span,
     },
     TermElse {
-        line_no: i16,
+        span: Span,
     },
     TermFi {
-        line_no: i16,
+        span: Span,
     },
     TermIf {
-        line_no: i16,
+        span: Span,
     },
     TermIn {
-        line_no: i16,
+        span: Span,
     },
     TermInherits {
-        line_no: i16,
+        span: Span,
     },
     TermLet {
-        line_no: i16,
+        span: Span,
     },
     TermLoop {
-        line_no: i16,
+        span: Span,
     },
     TermPool {
-        line_no: i16,
+        span: Span,
     },
     TermThen {
-        line_no: i16,
+        span: Span,
     },
     TermWhile {
-        line_no: i16,
+        span: Span,
     },
     TermCase {
-        line_no: i16,
+        span: Span,
     },
     TermEsac {
-        line_no: i16,
+        span: Span,
     },
     TermOf {
-        line_no: i16,
+        span: Span,
     },
     TermNew {
-        line_no: i16,
+        span: Span,
     },
     TermIsVoid {
-        line_no: i16,
+        span: Span,
     },
     TermNot {
-        line_no: i16,
+        span: Span,
     },
     TermDArrow {
-        line_no: i16,
+        span: Span,
     },
     TermLEq {
-        line_no: i16,
+        span: Span,
     },
     TermAssign {
-        line_no: i16,
+        span: Span,
     },
     TermBoolConst {
-        line_no: i16,
+        span: Span,
         val: bool,
     },
     TermIntConst {
-        line_no: i16,
+        span: Span,
         val: u32,
     },
     TermStrConst {
-        line_no: i16,
+        span: Span,
         val: String,
     },
     TermTypeID {
-        line_no: i16,
+        span: Span,
         type_name: String,
     },
     TermObjectID {
-        line_no: i16,
+        span: Span,
         obj_name: String,
     },
     // Special
@@ -142,7 +144,7 @@ pub enum ParseStackNode {
     Class {
         file_name: String,
         file_no: u32,
-        line_no: i16,
+        span: Span,
         name: TypeID,
         parent_name: Option<TypeID>,
         attrs: Vec<Attr>,
@@ -154,7 +156,7 @@ pub enum ParseStackNode {
     },
     Method {
         // Feature type
-        line_no: i16,
+        span: Span,
         name: ObjectID,
         formals: Vec<Formal>,
         return_type: TypeID,
@@ -162,7 +164,7 @@ pub enum ParseStackNode {
     },
     Attr {
         // Feature type
-        line_no: i16,
+        span: Span,
         name: ObjectID,
         type_decl: TypeID,
         init: Expression,
@@ -174,7 +176,7 @@ pub enum ParseStackNode {
         formals: Vec<Formal>,
     },
     Formal {
-        line_no: i16,
+        span: Span,
         name: ObjectID,
         type_decl: TypeID,
     },
@@ -182,7 +184,7 @@ pub enum ParseStackNode {
         branches: Vec<Branch>,
     },
     Branch {
-        line_no: i16,
+        span: Span,
         name: ObjectID,
         type_decl: TypeID,
         expr: Expression,
@@ -198,7 +200,7 @@ pub enum ParseStackNode {
     },
     ExpressionLet {
         // ExpressionLet type
-        line_no: i16,
+        span: Span,
         identifier: ObjectID,
         type_decl: TypeID,
         init: Box<Expression>,
@@ -206,13 +208,13 @@ pub enum ParseStackNode {
     },
     Assign {
         // Expr type
-        line_no: i16,
+        span: Span,
         name: ObjectID,
         expr: Box<Expression>,
     },
     Dispatch {
         // Expr type
-        line_no: i16,
+        span: Span,
         expr: Box<Expression>,
         type_name: Option<TypeID>,
         name: ObjectID,
@@ -220,31 +222,31 @@ pub enum ParseStackNode {
     },
     Cond {
         // Expr type
-        line_no: i16,
+        span: Span,
         pred: Box<Expression>,
         then_expr: Box<Expression>,
         else_expr: Box<Expression>,
     },
     Loop {
         // Expr type
-        line_no: i16,
+        span: Span,
         pred: Box<Expression>,
         body: Box<Expression>,
     },
     TypeCase {
         // Expr type
-        line_no: i16,
+        span: Span,
         expr: Box<Expression>,
         branches: Vec<Branch>,
     },
     Block {
         // Expr type
-        line_no: i16,
+        span: Span,
         body: Vec<Expression>,
     },
     Let {
         // Expr type
-        line_no: i16,
+        span: Span,
         identifier: ObjectID,
         type_decl: TypeID,
         init: Box<Expression>,
@@ -252,135 +254,135 @@ pub enum ParseStackNode {
     },
     ArithOp {
         // Expr type
-        line_no: i16,
+        span: Span,
         expr_lhs: Box<Expression>,
         expr_rhs: Box<Expression>,
         arith_op_type: ArithOpType,
     },
     Neg {
         // Expr type
-        line_no: i16,
+        span: Span,
         expr: Box<Expression>,
     },
     Comp {
         // Expr type
-        line_no: i16,
+        span: Span,
         expr_lhs: Box<Expression>,
         expr_rhs: Box<Expression>,
         comp_type: CompType,
     },
     Eq {
         // Expr type
-        line_no: i16,
+        span: Span,
         expr_lhs: Box<Expression>,
         expr_rhs: Box<Expression>,
     },
     Not {
         // Expr type
-        line_no: i16,
+        span: Span,
         expr: Box<Expression>,
     },
     IntConst {
         // Expr type
-        line_no: i16,
+        span: Span,
         val: u32,
     },
     BoolConst {
         // Expr type
-        line_no: i16,
+        span: Span,
         val: bool,
     },
     StringConst {
         // Expr type
-        line_no: i16,
+        span: Span,
         val: String,
     },
     New {
         // Expr type
-        line_no: i16,
+        span: Span,
         type_name: TypeID,
     },
     IsVoid {
         // Expr type
-        line_no: i16,
+        span: Span,
         expr: Box<Expression>,
     },
     VarByName {
         // Expr type
-        line_no: i16,
+        span: Span,
         name: ObjectID,
     },
 }
 
 impl ParseStackNode {
-    pub fn line_no(&self) -> i16 {
+    pub fn span(&self) -> Span {
         match self {
-            ParseStackNode::TermAdd { line_no }
-            | ParseStackNode::TermDiv { line_no }
-            | ParseStackNode::TermSub { line_no }
-            | ParseStackNode::TermMul { line_no }
-            | ParseStackNode::TermEq { line_no }
-            | ParseStackNode::TermLT { line_no }
-            | ParseStackNode::TermDot { line_no }
-            | ParseStackNode::TermNeg { line_no }
-            | ParseStackNode::TermComma { line_no }
-            | ParseStackNode::TermSemiColon { line_no }
-            | ParseStackNode::TermColon { line_no }
-            | ParseStackNode::TermOpenParen { line_no }
-            | ParseStackNode::TermCloseParen { line_no }
-            | ParseStackNode::TermAt { line_no }
-            | ParseStackNode::TermOpenBrace { line_no }
-            | ParseStackNode::TermCloseBrace { line_no }
-            | ParseStackNode::TermClass { line_no }
-            | ParseStackNode::TermElse { line_no }
-            | ParseStackNode::TermFi { line_no }
-            | ParseStackNode::TermIf { line_no }
-            | ParseStackNode::TermIn { line_no }
-            | ParseStackNode::TermInherits { line_no }
-            | ParseStackNode::TermLet { line_no }
-            | ParseStackNode::TermLoop { line_no }
-            | ParseStackNode::TermPool { line_no }
-            | ParseStackNode::TermThen { line_no }
-            | ParseStackNode::TermWhile { line_no }
-            | ParseStackNode::TermCase { line_no }
-            | ParseStackNode::TermEsac { line_no }
-            | ParseStackNode::TermOf { line_no }
-            | ParseStackNode::TermNew { line_no }
-            | ParseStackNode::TermIsVoid { line_no }
-            | ParseStackNode::TermNot { line_no }
-            | ParseStackNode::TermDArrow { line_no }
-            | ParseStackNode::TermLEq { line_no }
-            | ParseStackNode::TermAssign { line_no }
-            | ParseStackNode::TermBoolConst { line_no, .. }
-            | ParseStackNode::TermTypeID { line_no, .. }
-            | ParseStackNode::TermObjectID { line_no, .. }
-            | ParseStackNode::TermIntConst { line_no, .. }
-            | ParseStackNode::TermStrConst { line_no, .. }
-            | ParseStackNode::Class { line_no, .. }
-            | ParseStackNode::Method { line_no, .. }
-            | ParseStackNode::Attr { line_no, .. }
-            | ParseStackNode::Formal { line_no, .. }
-            | ParseStackNode::Branch { line_no, .. }
-            | ParseStackNode::ExpressionLet { line_no, .. }
-            | ParseStackNode::Assign { line_no, .. }
-            | ParseStackNode::Dispatch { line_no, .. }
-            | ParseStackNode::Cond { line_no, .. }
-            | ParseStackNode::Loop { line_no, .. }
-            | ParseStackNode::TypeCase { line_no, .. }
-            | ParseStackNode::Block { line_no, .. }
-            | ParseStackNode::Let { line_no, .. }
-            | ParseStackNode::ArithOp { line_no, .. }
-            | ParseStackNode::Neg { line_no, .. }
-            | ParseStackNode::Comp { line_no, .. }
-            | ParseStackNode::Eq { line_no, .. }
-            | ParseStackNode::Not { line_no, .. }
-            | ParseStackNode::IntConst { line_no, .. }
-            | ParseStackNode::BoolConst { line_no, .. }
-            | ParseStackNode::StringConst { line_no, .. }
-            | ParseStackNode::New { line_no, .. }
-            | ParseStackNode::IsVoid { line_no, .. }
-            | ParseStackNode::VarByName { line_no, .. } => *line_no,
-            _ => panic!("No line_no for this node type"),
+            ParseStackNode::TermAdd { span }
+            | ParseStackNode::TermDiv { span }
+            | ParseStackNode::TermSub { span }
+            | ParseStackNode::TermMul { span }
+            | ParseStackNode::TermEq { span }
+            | ParseStackNode::TermLT { span }
+            | ParseStackNode::TermDot { span }
+            | ParseStackNode::TermNeg { span }
+            | ParseStackNode::TermComma { span }
+            | ParseStackNode::TermSemiColon { span }
+            | ParseStackNode::TermColon { span }
+            | ParseStackNode::TermOpenParen { span }
+            | ParseStackNode::TermCloseParen { span }
+            | ParseStackNode::TermAt { span }
+            | ParseStackNode::TermOpenBrace { span }
+            | ParseStackNode::TermCloseBrace { span }
+            | ParseStackNode::TermClass { span }
+            | ParseStackNode::TermElse { span }
+            | ParseStackNode::TermFi { span }
+            | ParseStackNode::TermIf { span }
+            | ParseStackNode::TermIn { span }
+            | ParseStackNode::TermInherits { span }
+            | ParseStackNode::TermLet { span }
+            | ParseStackNode::TermLoop { span }
+            | ParseStackNode::TermPool { span }
+            | ParseStackNode::TermThen { span }
+            | ParseStackNode::TermWhile { span }
+            | ParseStackNode::TermCase { span }
+            | ParseStackNode::TermEsac { span }
+            | ParseStackNode::TermOf { span }
+            | ParseStackNode::TermNew { span }
+            | ParseStackNode::TermIsVoid { span }
+            | ParseStackNode::TermNot { span }
+            | ParseStackNode::TermDArrow { span }
+            | ParseStackNode::TermLEq { span }
+            | ParseStackNode::TermAssign { span }
+            | ParseStackNode::TermBoolConst { span, .. }
+            | ParseStackNode::TermTypeID { span, .. }
+            | ParseStackNode::TermObjectID { span, .. }
+            | ParseStackNode::TermIntConst { span, .. }
+            | ParseStackNode::TermStrConst { span, .. }
+            | ParseStackNode::Class { span, .. }
+            | ParseStackNode::Method { span, .. }
+            | ParseStackNode::Attr { span, .. }
+            | ParseStackNode::Formal { span, .. }
+            | ParseStackNode::Branch { span, .. }
+            | ParseStackNode::ExpressionLet { span, .. }
+            | ParseStackNode::Assign { span, .. }
+            | ParseStackNode::Dispatch { span, .. }
+            | ParseStackNode::Cond { span, .. }
+            | ParseStackNode::Loop { span, .. }
+            | ParseStackNode::TypeCase { span, .. }
+            | ParseStackNode::Block { span, .. }
+            | ParseStackNode::Let { span, .. }
+            | ParseStackNode::ArithOp { span, .. }
+            | ParseStackNode::Neg { span, .. }
+            | ParseStackNode::Comp { span, .. }
+            | ParseStackNode::Eq { span, .. }
+            | ParseStackNode::Not { span, .. }
+            | ParseStackNode::IntConst { span, .. }
+            | ParseStackNode::BoolConst { span, .. }
+            | ParseStackNode::StringConst { span, .. }
+            | ParseStackNode::New { span, .. }
+            | ParseStackNode::IsVoid { span, .. }
+            | ParseStackNode::VarByName { span, .. } => *span,
+            _ => panic!("No span for this node type"),
         }
     }
 
@@ -424,9 +426,17 @@ impl ParseStackNode {
         }
     }
 
-    pub fn get_line_no_object_id(self) -> (i16, ObjectID) {
-        if let ParseStackNode::TermObjectID { line_no, obj_name } = self {
-            (line_no, ObjectID::new(obj_name))
+    pub fn get_span_object_id(self) -> (Span, ObjectID) {
+        if let ParseStackNode::TermObjectID { span, obj_name } = self {
+            (span, ObjectID::new(obj_name))
+        } else {
+            panic!("Bad node type")
+        }
+    }
+
+    pub fn get_span_type_id(self) -> (Span, TypeID) {
+        if let ParseStackNode::TermTypeID { span, type_name } = self {
+            (span, TypeID::new(type_name))
         } else {
             panic!("Bad node type")
         }
@@ -438,7 +448,7 @@ impl ParseStackNode {
         if let ParseStackNode::Class {
             file_name,
             file_no,
-            line_no,
+            span,
             name,
             parent_name,
             attrs,
@@ -451,7 +461,7 @@ impl ParseStackNode {
                     file_name,
                     file_no,
                     basic: false,
-                    line_no,
+                    span,
                     parent_name,
                     attrs,
                     methods,
@@ -468,14 +478,14 @@ impl ParseStackNode {
 
     pub fn into_attr(self) -> Attr {
         if let ParseStackNode::Attr {
-            line_no,
+            span,
             name,
             type_decl,
             init,
         } = self
         {
             Attr {
-                line_no,
+                span,
                 name,
                 type_decl,
                 init,
@@ -488,7 +498,7 @@ impl ParseStackNode {
 
     pub fn into_method(self) -> (ObjectID, Method) {
         if let ParseStackNode::Method {
-            line_no,
+            span,
             name,
             formals,
             return_type,
@@ -498,7 +508,7 @@ impl ParseStackNode {
             (
                 name,
                 Method {
-                    line_no,
+                    span,
                     formals,
                     return_type,
                     expr,
@@ -511,13 +521,13 @@ impl ParseStackNode {
 
     pub fn into_formal(self) -> Formal {
         if let ParseStackNode::Formal {
-            line_no,
+            span,
             name,
             type_decl,
         } = self
         {
             Formal {
-                line_no,
+                span,
                 name,
                 type_decl,
             }
@@ -528,14 +538,14 @@ impl ParseStackNode {
 
     pub fn into_branch(self) -> Branch {
         if let ParseStackNode::Branch {
-            line_no,
+            span,
             name,
             type_decl,
             expr,
         } = self
         {
             Branch {
-                line_no,
+                span,
                 name,
                 type_decl,
                 expr,
@@ -551,35 +561,27 @@ impl ParseStackNode {
 
         match self {
             ParseStackNode::ExpressionLet {
-                line_no,
+                span,
                 identifier,
                 type_decl,
                 init,
                 body,
             } => Expression::Let {
-                line_no,
+                span,
                 identifier,
                 type_decl,
                 init,
                 body,
             },
-            ParseStackNode::Assign {
-                line_no,
-                name,
-                expr,
-            } => Expression::Assign {
-                line_no,
-                name,
-                expr,
-            },
+            ParseStackNode::Assign { span, name, expr } => Expression::Assign { span, name, expr },
             ParseStackNode::Dispatch {
-                line_no,
+                span,
                 expr,
                 type_name,
                 name,
                 args,
             } => Expression::Dispatch {
-                line_no,
+                span,
                 expr,
                 type_name,
                 name,
@@ -587,94 +589,86 @@ impl ParseStackNode {
                 static_type,
             },
             ParseStackNode::Cond {
-                line_no,
+                span,
                 pred,
                 then_expr,
                 else_expr,
             } => Expression::Cond {
-                line_no,
+                span,
                 pred,
                 then_expr,
                 else_expr,
                 static_type,
             },
-            ParseStackNode::Loop {
-                line_no,
-                pred,
-                body,
-            } => Expression::Loop {
-                line_no,
-                pred,
-                body,
-            },
+            ParseStackNode::Loop { span, pred, body } => Expression::Loop { span, pred, body },
             ParseStackNode::TypeCase {
-                line_no,
+                span,
                 expr,
                 branches,
             } => Expression::TypeCase {
-                line_no,
+                span,
                 expr,
                 branches,
                 static_type,
             },
-            ParseStackNode::Block { line_no, body } => Expression::Block { line_no, body },
+            ParseStackNode::Block { span, body } => Expression::Block { span, body },
             ParseStackNode::Let {
-                line_no,
+                span,
                 identifier,
                 type_decl,
                 init,
                 body,
             } => Expression::Let {
-                line_no,
+                span,
                 identifier,
                 type_decl,
                 init,
                 body,
             },
             ParseStackNode::ArithOp {
-                line_no,
+                span,
                 expr_lhs,
                 expr_rhs,
                 arith_op_type,
             } => Expression::ArithOp {
-                line_no,
+                span,
                 expr_lhs,
                 expr_rhs,
                 arith_op_type,
             },
-            ParseStackNode::Neg { line_no, expr } => Expression::Neg { line_no, expr },
+            ParseStackNode::Neg { span, expr } => Expression::Neg { span, expr },
             ParseStackNode::Comp {
-                line_no,
+                span,
                 expr_lhs,
                 expr_rhs,
                 comp_type,
             } => Expression::Comp {
-                line_no,
+                span,
                 expr_lhs,
                 expr_rhs,
                 comp_type,
             },
             ParseStackNode::Eq {
-                line_no,
+                span,
                 expr_lhs,
                 expr_rhs,
             } => Expression::Eq {
-                line_no,
+                span,
                 expr_lhs,
                 expr_rhs,
             },
-            ParseStackNode::Not { line_no, expr } => Expression::Not { line_no, expr },
-            ParseStackNode::IntConst { line_no, val } => Expression::IntConst { line_no, val },
-            ParseStackNode::BoolConst { line_no, val } => Expression::BoolConst { line_no, val },
-            ParseStackNode::StringConst { line_no, val } => Expression::StringConst {
-                line_no,
+            ParseStackNode::Not { span, expr } => Expression::Not { span, expr },
+            ParseStackNode::IntConst { span, val } => Expression::IntConst { span, val },
+            ParseStackNode::BoolConst { span, val } => Expression::BoolConst { span, val },
+            ParseStackNode::StringConst { span, val } => Expression::StringConst {
+                span,
                 val,
                 val_id: 0, // This is set later
             },
-            ParseStackNode::New { line_no, type_name } => Expression::New { line_no, type_name },
-            ParseStackNode::IsVoid { line_no, expr } => Expression::IsVoid { line_no, expr },
-            ParseStackNode::VarByName { line_no, name } => Expression::VarByName {
-                line_no,
+            ParseStackNode::New { span, type_name } => Expression::New { span, type_name },
+            ParseStackNode::IsVoid { span, expr } => Expression::IsVoid { span, expr },
+            ParseStackNode::VarByName { span, name } => Expression::VarByName {
+                span,
                 name,
                 static_type,
             },
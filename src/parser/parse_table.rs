@@ -1,8 +1,10 @@
+use crate::span::Span;
 use crate::token::Token;
 
 // This module contains information about the behaviour of the shift-reduce parse used to parse the token stream into an AST.
 
 // The state component of the stack
+#[allow(clippy::enum_variant_names)]
 #[derive(Copy, Clone)]
 pub enum State {
     StateInitial, // Initial State matched with the Bottom Node
@@ -189,8 +191,8 @@ pub enum Action {
 }
 
 // What to do (shift, reduce, or accept based on the next token and the top parse state
-pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
-    let next_line_no = next_token.get_line_no();
+pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, Span> {
+    let next_span = next_token.get_span();
 
     Ok(match top_state {
         State::StateInitial => match next_token {
@@ -198,7 +200,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State001,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -207,7 +209,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State004,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -217,7 +219,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
             },
             Token::End { .. } => Action::Accept,
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -226,7 +228,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State006,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -238,7 +240,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State008,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -247,7 +249,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State009,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -258,7 +260,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State010,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -266,7 +268,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
             Token::ObjectID { .. } => Action::ReduceFeatureList { is_empty: true },
             Token::CloseBrace { .. } => Action::ReduceFeatureList { is_empty: true },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -277,7 +279,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State012,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -289,7 +291,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State014,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -297,7 +299,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
             Token::ObjectID { .. } => Action::ReduceFeatureList { is_empty: true },
             Token::CloseBrace { .. } => Action::ReduceFeatureList { is_empty: true },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -309,7 +311,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State018,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -320,7 +322,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State019,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -332,7 +334,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State020,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -348,7 +350,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State025,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -361,7 +363,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State026,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -377,7 +379,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State028,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -395,7 +397,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State030,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -404,7 +406,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State021,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -413,7 +415,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State032,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -461,7 +463,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State046,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -474,7 +476,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State048,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -522,7 +524,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State046,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -531,7 +533,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State050,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -579,7 +581,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State046,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -627,7 +629,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State046,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -636,7 +638,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State054,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -684,7 +686,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State046,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -748,7 +750,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State046,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -796,7 +798,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State046,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -844,7 +846,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State046,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -892,7 +894,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State046,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -932,7 +934,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State072,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -968,7 +970,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State071,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -977,7 +979,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State074,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -1015,7 +1017,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State071,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -1051,7 +1053,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State071,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -1111,7 +1113,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State046,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -1249,7 +1251,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State046,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -1285,7 +1287,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State083,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -1321,7 +1323,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State084,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -1369,7 +1371,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State046,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -1417,7 +1419,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State046,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -1465,7 +1467,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State046,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -1513,7 +1515,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State046,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -1561,7 +1563,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State046,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -1609,7 +1611,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State046,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -1657,7 +1659,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State046,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -1666,7 +1668,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State092,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -1675,7 +1677,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State093,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -1723,7 +1725,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State046,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -1771,7 +1773,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State046,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -1780,7 +1782,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State096,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -1828,7 +1830,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State046,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -1837,7 +1839,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State098,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -1884,7 +1886,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State102,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -1953,7 +1955,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State103,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -2084,7 +2086,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State104,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -2093,7 +2095,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State105,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -2129,7 +2131,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State106,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -2165,7 +2167,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State071,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -2180,7 +2182,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State110,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -2216,7 +2218,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State071,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -2225,7 +2227,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State112,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -2237,7 +2239,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State098,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -2246,7 +2248,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State115,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -2294,7 +2296,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State046,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -2309,7 +2311,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State117,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -2405,7 +2407,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State046,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -2453,7 +2455,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State046,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -2501,7 +2503,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State046,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -2510,7 +2512,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State050,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -2521,7 +2523,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State123,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -2532,7 +2534,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State124,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -2574,7 +2576,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State125,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -2583,7 +2585,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State126,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -2619,7 +2621,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State071,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -2689,7 +2691,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State129,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -2700,7 +2702,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State130,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -2802,7 +2804,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State046,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -2811,7 +2813,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State050,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -2859,7 +2861,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State046,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
@@ -2868,7 +2870,7 @@ pub fn get_action(top_state: State, next_token: &Token) -> Result<Action, i16> {
                 new_state: State::State135,
             },
             _ => {
-                return Err(next_line_no);
+                return Err(next_span);
             }
         },
 
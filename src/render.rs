@@ -0,0 +1,310 @@
+// Renders diagnostics either as JSON or in the style of rustc:
+//
+// error[E0102]: 'new' used with undefined class Foo.
+//  --> test.cl:11:9
+//    |
+// 11 |         new Foo;
+//    |         ^^^^^^^
+
+use std::fmt::Write;
+use std::str::FromStr;
+
+use crate::diagnostics::{Diagnostic, Location, Severity};
+use crate::json::Json;
+use crate::Source;
+
+const TAB_WIDTH: usize = 4;
+
+// ANSI escape sequences
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const GREEN: &str = "\x1b[1;32m";
+const BLUE: &str = "\x1b[1;34m";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ColorChoice, String> {
+        match s {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(format!("Unknown color choice {}", s)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ErrorFormat {
+    Human, // Source snippets for people
+    Json,  // One JSON object per line for tools
+}
+
+impl FromStr for ErrorFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ErrorFormat, String> {
+        match s {
+            "human" => Ok(ErrorFormat::Human),
+            "json" => Ok(ErrorFormat::Json),
+            _ => Err(format!("Unknown error format {}", s)),
+        }
+    }
+}
+
+// The text of each source file, used to show the offending lines
+#[derive(Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+struct SourceFile {
+    name: String,
+    text: String,
+    line_starts: Vec<usize>, // Byte offset of the start of each line
+}
+
+impl SourceMap {
+    pub fn new() -> SourceMap {
+        SourceMap { files: Vec::new() }
+    }
+
+    pub fn from_sources(sources: &[Source]) -> SourceMap {
+        let mut source_map = SourceMap::new();
+
+        for source in sources.iter() {
+            source_map.add(source.name, source.text.to_string());
+        }
+
+        source_map
+    }
+
+    pub fn add(&mut self, name: &str, text: String) {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        self.files.push(SourceFile {
+            name: name.to_string(),
+            text,
+            line_starts,
+        });
+    }
+
+    // Get the text of a line (without the line ending)
+    fn line(&self, file_name: &str, line_no: u32) -> Option<&str> {
+        let file = self.files.iter().find(|file| file.name == file_name)?;
+
+        if line_no == 0 {
+            return None;
+        }
+
+        let index = line_no as usize - 1;
+        let start = *file.line_starts.get(index)?;
+        let end = match file.line_starts.get(index + 1) {
+            Some(end) => *end,
+            None => file.text.len(),
+        };
+
+        Some(file.text[start..end].trim_end_matches(['\n', '\r']))
+    }
+}
+
+// Wraps text in an escape sequence if color is enabled
+struct Painter {
+    color: bool,
+}
+
+impl Painter {
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", style, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+// Width of text when displayed with tabs expanded
+fn display_width(text: &str) -> usize {
+    text.chars()
+        .map(|c| if c == '\t' { TAB_WIDTH } else { 1 })
+        .sum()
+}
+
+// Write the location and the source line with the span underlined
+fn render_snippet(
+    out: &mut String,
+    loc: &Location,
+    sources: &SourceMap,
+    painter: &Painter,
+    underline_style: &str,
+    gutter_width: usize,
+) {
+    let span = &loc.span;
+    let pad = " ".repeat(gutter_width);
+
+    writeln!(
+        out,
+        "{}{} {}:{}:{}",
+        pad,
+        painter.paint(BLUE, "-->"),
+        loc.file_name,
+        span.line_no,
+        span.col
+    )
+    .unwrap();
+
+    let line = match sources.line(&loc.file_name, span.line_no) {
+        Some(line) => line,
+        None => return, // There is nothing to show (e.g. a basic class)
+    };
+
+    // Clamp the span to the first line it covers
+    let start = (span.col as usize).saturating_sub(1).min(line.len());
+    let end = start + (span.hi.saturating_sub(span.lo) as usize).min(line.len() - start);
+
+    let prefix_width = display_width(line.get(..start).unwrap_or_default());
+    let underline_width = display_width(line.get(start..end).unwrap_or_default()).max(1);
+
+    let bar = painter.paint(BLUE, "|");
+
+    writeln!(out, "{} {}", pad, bar).unwrap();
+    writeln!(
+        out,
+        "{} {} {}",
+        painter.paint(BLUE, &format!("{:>1$}", span.line_no, gutter_width)),
+        bar,
+        line.replace('\t', &" ".repeat(TAB_WIDTH))
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "{} {} {}{}",
+        pad,
+        bar,
+        " ".repeat(prefix_width),
+        painter.paint(underline_style, &"^".repeat(underline_width))
+    )
+    .unwrap();
+}
+
+pub fn render(diagnostic: &Diagnostic, sources: &SourceMap, color: bool) -> String {
+    let painter = Painter { color };
+    let mut out = String::new();
+
+    let severity_style = match diagnostic.severity {
+        Severity::Error => RED,
+        Severity::Warning => YELLOW,
+    };
+
+    // The gutter must fit the largest line number shown
+    let gutter_width = diagnostic
+        .loc
+        .iter()
+        .chain(diagnostic.notes.iter().filter_map(|note| note.loc.as_ref()))
+        .map(|loc| loc.span.line_no.to_string().len())
+        .max()
+        .unwrap_or(0);
+
+    writeln!(
+        out,
+        "{}{}",
+        painter.paint(
+            severity_style,
+            &format!("{}[{}]", diagnostic.severity, diagnostic.code)
+        ),
+        painter.paint(BOLD, &format!(": {}", diagnostic.msg))
+    )
+    .unwrap();
+
+    if let Some(loc) = &diagnostic.loc {
+        render_snippet(
+            &mut out,
+            loc,
+            sources,
+            &painter,
+            severity_style,
+            gutter_width,
+        );
+    }
+
+    for note in diagnostic.notes.iter() {
+        match &note.loc {
+            Some(loc) => {
+                writeln!(
+                    out,
+                    "{}{}",
+                    painter.paint(GREEN, "note"),
+                    painter.paint(BOLD, &format!(": {}", note.msg))
+                )
+                .unwrap();
+
+                render_snippet(&mut out, loc, sources, &painter, GREEN, gutter_width);
+            }
+            None => {
+                writeln!(
+                    out,
+                    "{} {} {}: {}",
+                    " ".repeat(gutter_width),
+                    painter.paint(BLUE, "="),
+                    painter.paint(BOLD, "note"),
+                    note.msg
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    out
+}
+
+// The position of a diagnostic or note as JSON members
+fn loc_json(loc: &Option<Location>) -> Vec<(&'static str, Json)> {
+    match loc {
+        Some(loc) => vec![
+            ("file", Json::String(loc.file_name.clone())),
+            ("line", Json::Number(loc.span.line_no as i64)),
+            ("column", Json::Number(loc.span.col as i64)),
+            ("byte_start", Json::Number(loc.span.lo as i64)),
+            ("byte_end", Json::Number(loc.span.hi as i64)),
+        ],
+        None => vec![
+            ("file", Json::Null),
+            ("line", Json::Null),
+            ("column", Json::Null),
+            ("byte_start", Json::Null),
+            ("byte_end", Json::Null),
+        ],
+    }
+}
+
+// A single line of JSON
+pub fn render_json(diagnostic: &Diagnostic) -> String {
+    let notes = diagnostic
+        .notes
+        .iter()
+        .map(|note| {
+            let mut members = loc_json(&note.loc);
+            members.push(("message", Json::String(note.msg.clone())));
+            Json::object(members)
+        })
+        .collect();
+
+    let mut members = loc_json(&diagnostic.loc);
+    members.push(("severity", Json::String(diagnostic.severity.to_string())));
+    members.push(("code", Json::String(diagnostic.code.to_string())));
+    members.push(("message", Json::String(diagnostic.msg.clone())));
+    members.push(("notes", Json::Array(notes)));
+
+    Json::object(members).to_string()
+}
@@ -0,0 +1,543 @@
+// An interactive session for COOL (coolc repl)
+// Class definitions are kept as sources and the whole program is checked again after
+// every entry. An expression is parsed as the body of a method of a class that inherits
+// from Main and is run by the interpreter with one Main object as self, so attributes of
+// Main keep their values between expressions. The value and static type are printed.
+
+use indexmap::IndexMap;
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+
+use crate::ast::{Class, ObjectID, TypeID};
+use crate::builtins::initialize_classes;
+use crate::diagnostics::Diagnostics;
+use crate::interp::{eval_method, with_stack, Session};
+use crate::lexer::tokenize;
+use crate::parser::parse;
+use crate::render::{render, SourceMap};
+use crate::semant::semant;
+use crate::span::Span;
+use crate::token::Token;
+use crate::{Source, DEFAULT_MAX_ERRORS};
+
+const PROMPT: &str = "cool> ";
+const CONTINUATION_PROMPT: &str = "  ... ";
+
+// The class and method that hold an expression (neither is a valid COOL name, so they
+// cannot clash with the program)
+const EXPR_CLASS: &str = "_Repl";
+const EXPR_METHOD: &str = "_eval";
+
+// Used if no definition includes Main
+const DEFAULT_MAIN_NAME: &str = "<main>";
+const DEFAULT_MAIN: &str = "class Main inherits IO { main() : Object { self }; };";
+
+const HELP: &str = "\
+Enter a class definition or an expression (a blank line ends an incomplete entry).
+Expressions are evaluated with a Main object as self. A class cannot be defined
+twice, but :reset forgets the classes entered so far.
+  :type EXPR  Show the static type of an expression without evaluating it
+  :reset      Forget the classes entered and the Main object
+  :help       Show this message
+  :quit       Leave (as does end of input)
+";
+
+// How an entry is handled
+enum Entry {
+    Definition(String),
+    Expression(String),
+    Type(String), // :type
+    Reset,
+    Help,
+    Quit,
+    Unknown(String),
+}
+
+impl Entry {
+    fn new(text: String) -> Entry {
+        let trimmed = text.trim();
+
+        if let Some(command) = trimmed.strip_prefix(':') {
+            let (name, arg) = match command.split_once(char::is_whitespace) {
+                Some((name, arg)) => (name, arg.trim()),
+                None => (command, ""),
+            };
+
+            return match name {
+                "type" | "t" => Entry::Type(arg.to_string()),
+                "reset" => Entry::Reset,
+                "help" | "h" | "?" => Entry::Help,
+                "quit" | "q" => Entry::Quit,
+                _ => Entry::Unknown(name.to_string()),
+            };
+        }
+
+        if is_definition(&text) {
+            Entry::Definition(text)
+        } else {
+            Entry::Expression(text)
+        }
+    }
+}
+
+fn is_definition(text: &str) -> bool {
+    let tokens = tokenize(text, "", &mut Diagnostics::new(0));
+
+    matches!(tokens.front(), Some(Token::Class { .. }))
+}
+
+// Whether an entry can be handled or more lines are needed
+fn is_complete(text: &str) -> bool {
+    let mut diagnostics = Diagnostics::new(0);
+    let mut tokens = tokenize(text, "", &mut diagnostics);
+
+    // The error is reported when the entry is handled
+    if diagnostics.has_errors() {
+        return true;
+    }
+
+    tokens.pop_back(); // End
+
+    let mut depth = 0;
+
+    for token in tokens.iter() {
+        match token {
+            Token::OpenBrace { .. }
+            | Token::OpenParen { .. }
+            | Token::If { .. }
+            | Token::Case { .. }
+            | Token::Loop { .. } => depth += 1,
+            Token::CloseBrace { .. }
+            | Token::CloseParen { .. }
+            | Token::Fi { .. }
+            | Token::Esac { .. }
+            | Token::Pool { .. } => depth -= 1,
+            _ => {}
+        }
+    }
+
+    if depth > 0 {
+        return false;
+    }
+
+    match (tokens.front(), tokens.back()) {
+        // A definition ends with a semicolon
+        (Some(Token::Class { .. }), Some(last)) => matches!(last, Token::SemiColon { .. }),
+        // An expression cannot end with a token that needs something after it
+        (_, Some(last)) => !matches!(
+            last,
+            Token::Add { .. }
+                | Token::Div { .. }
+                | Token::Sub { .. }
+                | Token::Mul { .. }
+                | Token::Eq { .. }
+                | Token::LT { .. }
+                | Token::LEq { .. }
+                | Token::Dot { .. }
+                | Token::Neg { .. }
+                | Token::Comma { .. }
+                | Token::Colon { .. }
+                | Token::At { .. }
+                | Token::Else { .. }
+                | Token::In { .. }
+                | Token::Let { .. }
+                | Token::Then { .. }
+                | Token::While { .. }
+                | Token::Of { .. }
+                | Token::New { .. }
+                | Token::IsVoid { .. }
+                | Token::Not { .. }
+                | Token::DArrow { .. }
+                | Token::Assign { .. }
+        ),
+        (_, None) => true,
+    }
+}
+
+// Wrap the tokens of an expression in a method of a class that inherits from Main:
+//   class _Repl inherits Main { _eval() : Object { EXPR }; };
+fn wrap_expression(mut tokens: VecDeque<Token>) -> VecDeque<Token> {
+    let end = match tokens.pop_back() {
+        Some(end) => end.get_span(),
+        None => Span::default(),
+    };
+
+    // Errors at the added tokens are shown at the start or the end of the expression
+    let start = match tokens.front() {
+        Some(token) => token.get_span(),
+        None => end,
+    };
+
+    let type_id = |type_name: &str| Token::TypeID {
+        span: start,
+        type_name: type_name.to_string(),
+    };
+
+    let mut wrapped: VecDeque<Token> = VecDeque::from(vec![
+        Token::Class { span: start },
+        type_id(EXPR_CLASS),
+        Token::Inherits { span: start },
+        type_id("Main"),
+        Token::OpenBrace { span: start },
+        Token::ObjectID {
+            span: start,
+            obj_name: EXPR_METHOD.to_string(),
+        },
+        Token::OpenParen { span: start },
+        Token::CloseParen { span: start },
+        Token::Colon { span: start },
+        type_id("Object"),
+        Token::OpenBrace { span: start },
+    ]);
+
+    wrapped.append(&mut tokens);
+
+    wrapped.extend(vec![
+        Token::CloseBrace { span: end },
+        Token::SemiColon { span: end },
+        Token::CloseBrace { span: end },
+        Token::SemiColon { span: end },
+        Token::End { span: end },
+    ]);
+
+    wrapped
+}
+
+// Remembers whether the output is at the start of a line
+struct LineTracker<'a> {
+    output: &'a mut dyn Write,
+    at_line_start: bool,
+}
+
+impl Write for LineTracker<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.output.write(buf)?;
+
+        if len > 0 {
+            self.at_line_start = buf[len - 1] == b'\n';
+        }
+
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.output.flush()
+    }
+}
+
+fn write_str(output: &mut dyn Write, s: &str) -> Result<(), String> {
+    output
+        .write_all(s.as_bytes())
+        .and_then(|_| output.flush())
+        .map_err(|why| format!("Cannot write output: {}", why))
+}
+
+struct Repl {
+    definitions: Vec<(String, String)>, // The name and text of each accepted definition
+    loaded: usize,                      // The number of definitions read from files
+    session: Session,
+    entry_no: u32, // Entries are named <1>, <2>, ...
+    color: bool,
+}
+
+impl Repl {
+    // Parse and check the definitions along with an extra definition and an expression
+    // Returns the classes if there were no errors.
+    fn check(
+        &self,
+        definition: Option<(&str, &str)>,
+        expression: Option<(&str, &str)>,
+        diagnostics: &mut Diagnostics,
+    ) -> Option<IndexMap<TypeID, Class>> {
+        let mut classes = initialize_classes();
+        let mut file_no = 1;
+
+        for (name, text) in self
+            .definitions
+            .iter()
+            .map(|(name, text)| (name.as_str(), text.as_str()))
+            .chain(definition)
+        {
+            let tokens = tokenize(text, name, diagnostics);
+            parse(tokens, name, file_no, &mut classes, diagnostics);
+            file_no += 1;
+        }
+
+        if !classes.contains_key(&TypeID::new_main()) {
+            let tokens = tokenize(DEFAULT_MAIN, DEFAULT_MAIN_NAME, diagnostics);
+            parse(
+                tokens,
+                DEFAULT_MAIN_NAME,
+                file_no,
+                &mut classes,
+                diagnostics,
+            );
+            file_no += 1;
+        }
+
+        if let Some((name, text)) = expression {
+            let tokens = wrap_expression(tokenize(text, name, diagnostics));
+            parse(tokens, name, file_no, &mut classes, diagnostics);
+        }
+
+        // Semantic analysis is not attempted on a program with syntax errors
+        if diagnostics.has_errors() {
+            return None;
+        }
+
+        semant(classes, diagnostics).map(|(classes, _, _)| classes)
+    }
+
+    fn report(
+        &self,
+        diagnostics: &Diagnostics,
+        entry: Option<(&str, &str)>,
+        output: &mut dyn Write,
+    ) -> Result<(), String> {
+        let mut sources = SourceMap::new();
+
+        for (name, text) in self.definitions.iter() {
+            sources.add(name, text.clone());
+        }
+
+        if let Some((name, text)) = entry {
+            sources.add(name, text.to_string());
+        }
+
+        for diagnostic in diagnostics.diagnostics() {
+            write_str(output, &render(diagnostic, &sources, self.color))?;
+            write_str(output, "\n")?;
+        }
+
+        Ok(())
+    }
+
+    // Add class definitions
+    // Returns false (after reporting the errors) if they are not accepted.
+    fn define(&mut self, name: &str, text: &str, output: &mut dyn Write) -> Result<bool, String> {
+        let mut diagnostics = Diagnostics::new(DEFAULT_MAX_ERRORS);
+
+        let classes = match self.check(Some((name, text)), None, &mut diagnostics) {
+            Some(classes) => classes,
+            None => {
+                self.report(&diagnostics, Some((name, text)), output)?;
+                return Ok(false);
+            }
+        };
+
+        self.definitions.push((name.to_string(), text.to_string()));
+
+        // Main may have changed
+        self.session.reset();
+
+        for (class_name, class) in classes.iter() {
+            if class.file_name == name {
+                write_str(output, &format!("Defined class {}.\n", class_name))?;
+            }
+        }
+
+        Ok(true)
+    }
+
+    // Check an expression and return the classes, including the one that holds it
+    fn check_expression(
+        &self,
+        name: &str,
+        text: &str,
+        output: &mut dyn Write,
+    ) -> Result<Option<IndexMap<TypeID, Class>>, String> {
+        let mut diagnostics = Diagnostics::new(DEFAULT_MAX_ERRORS);
+
+        match self.check(None, Some((name, text)), &mut diagnostics) {
+            Some(classes) => Ok(Some(classes)),
+            None => {
+                self.report(&diagnostics, Some((name, text)), output)?;
+                Ok(None)
+            }
+        }
+    }
+
+    fn static_type(classes: &IndexMap<TypeID, Class>) -> TypeID {
+        let class_name = TypeID::new(EXPR_CLASS.to_string());
+        let method_name = ObjectID::new(EXPR_METHOD.to_string());
+
+        classes[&class_name].methods[&method_name]
+            .expr
+            .static_type()
+    }
+
+    fn evaluate(
+        &mut self,
+        name: &str,
+        text: &str,
+        input: &mut dyn BufRead,
+        output: &mut dyn Write,
+    ) -> Result<(), String> {
+        let classes = match self.check_expression(name, text, output)? {
+            Some(classes) => classes,
+            None => return Ok(()),
+        };
+
+        let mut tracker = LineTracker {
+            output,
+            at_line_start: true,
+        };
+
+        let result = eval_method(
+            &classes,
+            &mut self.session,
+            &TypeID::new(EXPR_CLASS.to_string()),
+            &ObjectID::new(EXPR_METHOD.to_string()),
+            input,
+            &mut tracker,
+        );
+
+        // The result starts on a line of its own
+        if !tracker.at_line_start {
+            write_str(output, "\n")?;
+        }
+
+        match result {
+            Ok(Some(val)) => write_str(
+                output,
+                &format!("{} : {}\n", val, Repl::static_type(&classes)),
+            ),
+            Ok(None) => Ok(()), // A runtime error (already reported)
+            Err(msg) => write_str(output, &format!("{}\n", msg)),
+        }
+    }
+
+    // Handle one entry
+    // Returns false if the session is over.
+    fn handle(
+        &mut self,
+        text: String,
+        input: &mut dyn BufRead,
+        output: &mut dyn Write,
+    ) -> Result<bool, String> {
+        self.entry_no += 1;
+        let name = format!("<{}>", self.entry_no);
+
+        match Entry::new(text) {
+            Entry::Definition(text) => {
+                self.define(&name, &text, output)?;
+            }
+            Entry::Expression(text) => self.evaluate(&name, &text, input, output)?,
+            Entry::Type(text) => {
+                if let Some(classes) = self.check_expression(&name, &text, output)? {
+                    write_str(output, &format!("{}\n", Repl::static_type(&classes)))?;
+                }
+            }
+            Entry::Reset => {
+                self.definitions.truncate(self.loaded);
+                self.session.reset();
+            }
+            Entry::Help => write_str(output, HELP)?,
+            Entry::Quit => return Ok(false),
+            Entry::Unknown(command) => write_str(
+                output,
+                &format!("Unknown command :{} (:help lists the commands)\n", command),
+            )?,
+        }
+
+        Ok(true)
+    }
+}
+
+// Read lines until they form a complete entry
+// Returns None at the end of the input.
+fn read_entry(
+    input: &mut dyn BufRead,
+    output: &mut dyn Write,
+    prompt: bool,
+) -> Result<Option<String>, String> {
+    let mut text = String::new();
+
+    loop {
+        if prompt {
+            let prompt = if text.is_empty() {
+                PROMPT
+            } else {
+                CONTINUATION_PROMPT
+            };
+
+            write_str(output, prompt)?;
+        }
+
+        let mut line = String::new();
+
+        match input.read_line(&mut line) {
+            Ok(0) => {
+                if prompt {
+                    write_str(output, "\n")?;
+                }
+
+                return Ok(if text.is_empty() { None } else { Some(text) });
+            }
+            Ok(_) => {}
+            Err(why) => return Err(format!("Cannot read input: {}", why)),
+        }
+
+        // A blank line ends an incomplete entry
+        if line.trim().is_empty() {
+            if text.is_empty() {
+                continue;
+            }
+
+            return Ok(Some(text));
+        }
+
+        text.push_str(&line);
+
+        if text.trim_start().starts_with(':') || is_complete(&text) {
+            return Ok(Some(text));
+        }
+    }
+}
+
+// Run a session
+// The sources are loaded as definitions first. Prompts are only written if prompt is set
+// (i.e. the input is a terminal).
+pub fn repl(
+    sources: &[Source],
+    input: &mut (dyn BufRead + Send),
+    output: &mut (dyn Write + Send),
+    prompt: bool,
+    color: bool,
+) -> Result<(), String> {
+    with_stack(|| {
+        let mut repl = Repl {
+            definitions: Vec::new(),
+            loaded: 0,
+            session: Session::new(),
+            entry_no: 0,
+            color,
+        };
+
+        // The sources may refer to each other, so they are checked together
+        repl.definitions = sources
+            .iter()
+            .map(|source| (source.name.to_string(), source.text.to_string()))
+            .collect();
+        repl.loaded = repl.definitions.len();
+
+        let mut diagnostics = Diagnostics::new(DEFAULT_MAX_ERRORS);
+
+        if repl.check(None, None, &mut diagnostics).is_none() {
+            repl.report(&diagnostics, None, output)?;
+            return Err(String::new()); // The errors have been reported
+        }
+
+        if prompt {
+            write_str(output, "COOL interactive session (:help for help)\n")?;
+        }
+
+        while let Some(text) = read_entry(input, output, prompt)? {
+            if !repl.handle(text, input, output)? {
+                break;
+            }
+        }
+
+        Ok(())
+    })?
+}
@@ -0,0 +1,611 @@
+// Assembles the output of cgen (and the runtime) into instructions and an initial data segment
+//
+// Only the directives and instructions that cgen and runtime/riscv32.s use are
+// understood. Pseudo-instructions such as li, la, call and ble are kept as single
+// instructions rather than being expanded, so every instruction takes one word.
+
+use std::collections::HashMap;
+
+use super::{TEXT_BASE, WORD_SIZE};
+use crate::mips::DATA_BASE;
+
+// Register numbers
+pub const ZERO: usize = 0;
+pub const RA: usize = 1;
+pub const SP: usize = 2;
+pub const A0: usize = 10;
+pub const A1: usize = 11;
+pub const A2: usize = 12;
+pub const A7: usize = 17;
+
+const REGISTER_NAMES: [&str; 32] = [
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4",
+    "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
+    "t5", "t6",
+];
+
+// Operations on two registers (or a register and an immediate)
+#[derive(Clone, Copy, Debug)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Mulhu,
+    Div,
+    Divu,
+    Rem,
+    Remu,
+    Slt,
+    Sltu,
+    And,
+    Or,
+    Xor,
+    Sll,
+    Srl,
+    Sra,
+}
+
+// Conditions of branches
+#[derive(Clone, Copy, Debug)]
+pub enum Cond {
+    Eq,
+    Ne,
+    Lt,
+    Ge,
+    Ltu,
+    Geu,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Instr {
+    Lw {
+        rd: usize,
+        offset: i32,
+        base: usize,
+    },
+    Sw {
+        rs: usize,
+        offset: i32,
+        base: usize,
+    },
+    Lbu {
+        rd: usize,
+        offset: i32,
+        base: usize,
+    },
+    Sb {
+        rs: usize,
+        offset: i32,
+        base: usize,
+    },
+    // Also la
+    Li {
+        rd: usize,
+        imm: u32,
+    },
+    Op {
+        op: Op,
+        rd: usize,
+        rs1: usize,
+        rs2: usize,
+    },
+    OpImm {
+        op: Op,
+        rd: usize,
+        rs1: usize,
+        imm: i32,
+    },
+    Branch {
+        cond: Cond,
+        rs1: usize,
+        rs2: usize,
+        target: u32,
+    },
+    Jal {
+        rd: usize,
+        target: u32,
+    },
+    Jalr {
+        rd: usize,
+        rs1: usize,
+        offset: i32,
+    },
+    Ecall,
+}
+
+pub struct Program {
+    pub text: Vec<Instr>, // Instruction i is at TEXT_BASE + 4 * i
+    pub data: Vec<u8>,    // Starts at DATA_BASE
+    pub labels: HashMap<String, u32>,
+}
+
+impl Program {
+    pub fn label(&self, name: &str) -> Result<u32, String> {
+        match self.labels.get(name) {
+            Some(addr) => Ok(*addr),
+            None => Err(format!("Undefined label {}", name)),
+        }
+    }
+}
+
+#[derive(PartialEq)]
+enum Segment {
+    Data,
+    Text,
+}
+
+// Split a line into its label (if any) and the rest of the statement
+fn split_label(line: &str) -> (Option<&str>, &str) {
+    // Comments run to the end of the line (but may not start in a string)
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut end = line.len();
+
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => in_string = !in_string,
+            '#' if !in_string => {
+                end = i;
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    let line = line[..end].trim();
+
+    // A label is a symbol followed by a colon
+    let symbol_len = line
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '$'))
+        .unwrap_or(line.len());
+
+    match line[symbol_len..].strip_prefix(':') {
+        Some(rest) if symbol_len > 0 => (Some(&line[..symbol_len]), rest.trim()),
+        _ => (None, line),
+    }
+}
+
+// The words of a statement
+fn split_words(statement: &str) -> Vec<&str> {
+    statement
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+fn parse_int(s: &str) -> Option<i64> {
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, s),
+    };
+
+    let val = match digits.strip_prefix("0x") {
+        Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+        None => digits.parse::<i64>().ok()?,
+    };
+
+    Some(if negative { -val } else { val })
+}
+
+fn align(offset: usize, lg_size: u32) -> usize {
+    let size = 1 << lg_size;
+
+    offset.div_ceil(size) * size
+}
+
+struct Assembler<'a> {
+    labels: HashMap<String, u32>,
+    line_no: usize,
+    line: &'a str,
+}
+
+impl<'a> Assembler<'a> {
+    fn error(&self, msg: &str) -> String {
+        format!(
+            "Cannot assemble line {} ({}): {}",
+            self.line_no,
+            self.line.trim(),
+            msg
+        )
+    }
+
+    fn register(&self, word: Option<&&str>) -> Result<usize, String> {
+        let name = match word {
+            Some(name) => *name,
+            None => return Err(self.error("expected a register")),
+        };
+
+        if let Some(reg) = REGISTER_NAMES.iter().position(|reg| *reg == name) {
+            return Ok(reg);
+        }
+
+        if name == "fp" {
+            return Ok(8); // Another name for s0
+        }
+
+        match name.strip_prefix('x').map(|reg| reg.parse::<usize>()) {
+            Some(Ok(reg)) if reg < REGISTER_NAMES.len() => Ok(reg),
+            _ => Err(self.error(&format!("unknown register {}", name))),
+        }
+    }
+
+    fn int(&self, word: Option<&&str>) -> Result<i64, String> {
+        match word.and_then(|word| parse_int(word)) {
+            Some(val) => Ok(val),
+            None => Err(self.error("expected an integer")),
+        }
+    }
+
+    // An immediate of an instruction (12 bits, sign-extended)
+    fn imm(&self, word: Option<&&str>) -> Result<i32, String> {
+        match self.int(word)? {
+            imm if (-2048..2048).contains(&imm) => Ok(imm as i32),
+            _ => Err(self.error("immediate out of range")),
+        }
+    }
+
+    fn shift(&self, word: Option<&&str>) -> Result<i32, String> {
+        match self.int(word)? {
+            shift if (0..32).contains(&shift) => Ok(shift as i32),
+            _ => Err(self.error("shift out of range")),
+        }
+    }
+
+    fn address(&self, word: Option<&&str>) -> Result<u32, String> {
+        let word = match word {
+            Some(word) => *word,
+            None => return Err(self.error("expected a label")),
+        };
+
+        if let Some(val) = parse_int(word) {
+            return Ok(val as u32);
+        }
+
+        match self.labels.get(word) {
+            Some(addr) => Ok(*addr),
+            None => Err(self.error(&format!("undefined label {}", word))),
+        }
+    }
+
+    // An offset and base register (i.e. 12(s1))
+    fn mem(&self, word: Option<&&str>) -> Result<(i32, usize), String> {
+        let word = match word {
+            Some(word) => *word,
+            None => return Err(self.error("expected a memory location")),
+        };
+
+        match word.split_once('(') {
+            Some((offset, base)) => {
+                let offset = if offset.is_empty() {
+                    0
+                } else {
+                    self.imm(Some(&offset))?
+                };
+                let base = self.register(Some(&base.trim_end_matches(')')))?;
+
+                Ok((offset, base))
+            }
+            None => Err(self.error("expected a memory location")),
+        }
+    }
+
+    // The characters of a string (i.e. "Abort\n")
+    fn string(&self, statement: &str) -> Result<Vec<u8>, String> {
+        let quoted = statement
+            .find('"')
+            .map(|start| statement[start..].trim_end())
+            .filter(|quoted| quoted.len() >= 2 && quoted.ends_with('"'));
+
+        let quoted = match quoted {
+            Some(quoted) => &quoted[1..quoted.len() - 1],
+            None => return Err(self.error("expected a string")),
+        };
+
+        let mut bytes = Vec::new();
+        let mut chars = quoted.chars();
+
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                let mut buf = [0; 4];
+                bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                continue;
+            }
+
+            match chars.next() {
+                Some('n') => bytes.push(b'\n'),
+                Some('t') => bytes.push(b'\t'),
+                Some('0') => bytes.push(0),
+                Some('\\') => bytes.push(b'\\'),
+                Some('"') => bytes.push(b'"'),
+                _ => return Err(self.error("unknown escape sequence")),
+            }
+        }
+
+        Ok(bytes)
+    }
+
+    fn instr(&self, words: &[&str]) -> Result<Instr, String> {
+        let op = words[0];
+        let args = &words[1..];
+
+        let instr = match op {
+            "lw" | "lbu" => {
+                let rd = self.register(args.first())?;
+                let (offset, base) = self.mem(args.get(1))?;
+
+                if op == "lw" {
+                    Instr::Lw { rd, offset, base }
+                } else {
+                    Instr::Lbu { rd, offset, base }
+                }
+            }
+            "sw" | "sb" => {
+                let rs = self.register(args.first())?;
+                let (offset, base) = self.mem(args.get(1))?;
+
+                if op == "sw" {
+                    Instr::Sw { rs, offset, base }
+                } else {
+                    Instr::Sb { rs, offset, base }
+                }
+            }
+            "li" => Instr::Li {
+                rd: self.register(args.first())?,
+                imm: self.int(args.get(1))? as u32,
+            },
+            "la" => Instr::Li {
+                rd: self.register(args.first())?,
+                imm: self.address(args.get(1))?,
+            },
+            "mv" => Instr::OpImm {
+                op: Op::Add,
+                rd: self.register(args.first())?,
+                rs1: self.register(args.get(1))?,
+                imm: 0,
+            },
+            "neg" => Instr::Op {
+                op: Op::Sub,
+                rd: self.register(args.first())?,
+                rs1: ZERO,
+                rs2: self.register(args.get(1))?,
+            },
+            "add" | "sub" | "mul" | "mulhu" | "div" | "divu" | "rem" | "remu" | "slt" | "sltu"
+            | "and" | "or" | "xor" | "sll" | "srl" | "sra" => {
+                let op = match op {
+                    "add" => Op::Add,
+                    "sub" => Op::Sub,
+                    "mul" => Op::Mul,
+                    "mulhu" => Op::Mulhu,
+                    "div" => Op::Div,
+                    "divu" => Op::Divu,
+                    "rem" => Op::Rem,
+                    "remu" => Op::Remu,
+                    "slt" => Op::Slt,
+                    "sltu" => Op::Sltu,
+                    "and" => Op::And,
+                    "or" => Op::Or,
+                    "xor" => Op::Xor,
+                    "sll" => Op::Sll,
+                    "srl" => Op::Srl,
+                    _ => Op::Sra,
+                };
+
+                Instr::Op {
+                    op,
+                    rd: self.register(args.first())?,
+                    rs1: self.register(args.get(1))?,
+                    rs2: self.register(args.get(2))?,
+                }
+            }
+            "addi" | "slti" | "sltiu" | "andi" | "ori" | "xori" => {
+                let op = match op {
+                    "addi" => Op::Add,
+                    "slti" => Op::Slt,
+                    "sltiu" => Op::Sltu,
+                    "andi" => Op::And,
+                    "ori" => Op::Or,
+                    _ => Op::Xor,
+                };
+
+                Instr::OpImm {
+                    op,
+                    rd: self.register(args.first())?,
+                    rs1: self.register(args.get(1))?,
+                    imm: self.imm(args.get(2))?,
+                }
+            }
+            "slli" | "srli" | "srai" => {
+                let op = match op {
+                    "slli" => Op::Sll,
+                    "srli" => Op::Srl,
+                    _ => Op::Sra,
+                };
+
+                Instr::OpImm {
+                    op,
+                    rd: self.register(args.first())?,
+                    rs1: self.register(args.get(1))?,
+                    imm: self.shift(args.get(2))?,
+                }
+            }
+            "beq" | "bne" | "blt" | "bge" | "bltu" | "bgeu" | "ble" | "bgt" | "bleu" | "bgtu" => {
+                let rs1 = self.register(args.first())?;
+                let rs2 = self.register(args.get(1))?;
+                let target = self.address(args.get(2))?;
+
+                // The others compare the registers the other way around
+                let (cond, rs1, rs2) = match op {
+                    "beq" => (Cond::Eq, rs1, rs2),
+                    "bne" => (Cond::Ne, rs1, rs2),
+                    "blt" => (Cond::Lt, rs1, rs2),
+                    "bge" => (Cond::Ge, rs1, rs2),
+                    "bltu" => (Cond::Ltu, rs1, rs2),
+                    "bgeu" => (Cond::Geu, rs1, rs2),
+                    "ble" => (Cond::Ge, rs2, rs1),
+                    "bgt" => (Cond::Lt, rs2, rs1),
+                    "bleu" => (Cond::Geu, rs2, rs1),
+                    _ => (Cond::Ltu, rs2, rs1),
+                };
+
+                Instr::Branch {
+                    cond,
+                    rs1,
+                    rs2,
+                    target,
+                }
+            }
+            "beqz" | "bnez" | "bltz" | "bgez" | "blez" | "bgtz" => {
+                let rs = self.register(args.first())?;
+                let target = self.address(args.get(1))?;
+
+                // Comparisons with zero
+                let (cond, rs1, rs2) = match op {
+                    "beqz" => (Cond::Eq, rs, ZERO),
+                    "bnez" => (Cond::Ne, rs, ZERO),
+                    "bltz" => (Cond::Lt, rs, ZERO),
+                    "bgez" => (Cond::Ge, rs, ZERO),
+                    "blez" => (Cond::Ge, ZERO, rs),
+                    _ => (Cond::Lt, ZERO, rs),
+                };
+
+                Instr::Branch {
+                    cond,
+                    rs1,
+                    rs2,
+                    target,
+                }
+            }
+            "j" => Instr::Jal {
+                rd: ZERO,
+                target: self.address(args.first())?,
+            },
+            "jal" | "call" if args.len() == 1 => Instr::Jal {
+                rd: RA,
+                target: self.address(args.first())?,
+            },
+            "jal" => Instr::Jal {
+                rd: self.register(args.first())?,
+                target: self.address(args.get(1))?,
+            },
+            "jr" => Instr::Jalr {
+                rd: ZERO,
+                rs1: self.register(args.first())?,
+                offset: 0,
+            },
+            "jalr" if args.len() == 1 => Instr::Jalr {
+                rd: RA,
+                rs1: self.register(args.first())?,
+                offset: 0,
+            },
+            "jalr" => {
+                let rd = self.register(args.first())?;
+                let (offset, rs1) = self.mem(args.get(1))?;
+
+                Instr::Jalr { rd, rs1, offset }
+            }
+            "ret" => Instr::Jalr {
+                rd: ZERO,
+                rs1: RA,
+                offset: 0,
+            },
+            "ecall" => Instr::Ecall,
+            _ => return Err(self.error(&format!("unknown instruction {}", op))),
+        };
+
+        Ok(instr)
+    }
+}
+
+pub fn assemble(assembly: &str) -> Result<Program, String> {
+    let mut asm = Assembler {
+        labels: HashMap::new(),
+        line_no: 0,
+        line: "",
+    };
+
+    // First pass: find the address of every label
+    let mut segment = Segment::Text;
+    let mut data_size: usize = 0;
+    let mut text_size: u32 = 0;
+
+    for (line, line_no) in assembly.lines().zip(1..) {
+        asm.line_no = line_no;
+        asm.line = line;
+
+        let (label, statement) = split_label(line);
+        let words = split_words(statement);
+
+        if let Some(label) = label {
+            let addr = match segment {
+                Segment::Data => DATA_BASE + data_size as u32,
+                Segment::Text => TEXT_BASE + text_size * WORD_SIZE,
+            };
+
+            asm.labels.insert(label.to_string(), addr);
+        }
+
+        match words.first() {
+            None => {}
+            Some(&".data") => segment = Segment::Data,
+            Some(&".text") => segment = Segment::Text,
+            Some(&".globl") => {}
+            Some(&".align") => data_size = align(data_size, asm.int(words.get(1))? as u32),
+            Some(&".word") => data_size += (words.len() - 1) * WORD_SIZE as usize,
+            Some(&".byte") => data_size += words.len() - 1,
+            Some(&".ascii") => data_size += asm.string(statement)?.len(),
+            Some(&".asciz") => data_size += asm.string(statement)?.len() + 1,
+            Some(directive) if directive.starts_with('.') => {
+                return Err(asm.error(&format!("unknown directive {}", directive)));
+            }
+            Some(_) if segment == Segment::Data => {
+                return Err(asm.error("instruction in the data segment"));
+            }
+            Some(_) => text_size += 1,
+        }
+    }
+
+    // Second pass: fill in the data and decode the instructions
+    let mut data: Vec<u8> = Vec::with_capacity(data_size);
+    let mut text: Vec<Instr> = Vec::with_capacity(text_size as usize);
+
+    for (line, line_no) in assembly.lines().zip(1..) {
+        asm.line_no = line_no;
+        asm.line = line;
+
+        let (_, statement) = split_label(line);
+        let words = split_words(statement);
+
+        match words.first() {
+            None | Some(&".data") | Some(&".text") | Some(&".globl") => {}
+            Some(&".align") => {
+                let size = align(data.len(), asm.int(words.get(1))? as u32);
+                data.resize(size, 0);
+            }
+            Some(&".word") => {
+                for word in words[1..].iter() {
+                    let val = asm.address(Some(word))?;
+                    data.extend_from_slice(&val.to_le_bytes());
+                }
+            }
+            Some(&".byte") => {
+                for word in words[1..].iter() {
+                    data.push(asm.int(Some(word))? as u8);
+                }
+            }
+            Some(&".ascii") => data.extend(asm.string(statement)?),
+            Some(&".asciz") => {
+                data.extend(asm.string(statement)?);
+                data.push(0);
+            }
+            Some(_) => text.push(asm.instr(&words)?),
+        }
+    }
+
+    Ok(Program {
+        text,
+        data,
+        labels: asm.labels,
+    })
+}
@@ -0,0 +1,249 @@
+// Executes an assembled program
+//
+// Memory is laid out as it is for the MIPS interpreter. The program talks to the
+// outside world only through the Linux system calls that the runtime makes.
+
+use std::io::{BufRead, Write};
+
+use super::asm::{Cond, Instr, Op, Program, A0, A1, A2, A7, SP, ZERO};
+use super::{TEXT_BASE, WORD_SIZE};
+use crate::mips::{Memory, STACK_TOP};
+
+// Linux system call numbers
+const SYS_READ: u32 = 63;
+const SYS_WRITE: u32 = 64;
+const SYS_EXIT: u32 = 93;
+const SYS_EXIT_GROUP: u32 = 94;
+const SYS_BRK: u32 = 214;
+
+const EBADF: i32 = 9; // Returned (negated) for a file descriptor other than 0, 1 or 2
+
+pub struct Machine<'a> {
+    program: &'a Program,
+    regs: [u32; 32],
+    memory: Memory,
+    input: &'a mut dyn BufRead,
+    pub output: &'a mut dyn Write,
+    error_output: &'a mut dyn Write,
+    pc: u32,
+}
+
+impl<'a> Machine<'a> {
+    pub fn new(
+        program: &'a Program,
+        input: &'a mut dyn BufRead,
+        output: &'a mut dyn Write,
+        error_output: &'a mut dyn Write,
+    ) -> Machine<'a> {
+        let mut regs = [0; 32];
+        regs[SP] = STACK_TOP - 4 * WORD_SIZE; // Aligned to 16 bytes as Linux does
+
+        // The data segment is word aligned so that the heap is too
+        let mut data = program.data.clone();
+        data.resize(
+            data.len().div_ceil(WORD_SIZE as usize) * WORD_SIZE as usize,
+            0,
+        );
+
+        Machine {
+            program,
+            regs,
+            memory: Memory::new(data),
+            input,
+            output,
+            error_output,
+            pc: 0,
+        }
+    }
+
+    // Run the program from an address until it exits
+    // Returns the exit status.
+    pub fn run(&mut self, addr: u32) -> Result<u32, String> {
+        self.pc = addr;
+
+        loop {
+            let pc = self.pc;
+
+            let instr = match pc
+                .checked_sub(TEXT_BASE)
+                .and_then(|offset| self.program.text.get((offset / WORD_SIZE) as usize))
+            {
+                Some(instr) if pc.is_multiple_of(WORD_SIZE) => *instr,
+                _ => return Err(format!("Jump to bad address {:#010x}", pc)),
+            };
+
+            self.pc = pc + WORD_SIZE;
+
+            if let Some(status) = self.execute(instr)? {
+                return Ok(status);
+            }
+        }
+    }
+
+    fn set(&mut self, reg: usize, val: u32) {
+        if reg != ZERO {
+            self.regs[reg] = val;
+        }
+    }
+
+    // Returns the exit status if the program exits
+    fn execute(&mut self, instr: Instr) -> Result<Option<u32>, String> {
+        let regs = self.regs;
+
+        match instr {
+            Instr::Lw { rd, offset, base } => {
+                let val = self
+                    .memory
+                    .load_word(regs[base].wrapping_add(offset as u32))?;
+                self.set(rd, val);
+            }
+            Instr::Sw { rs, offset, base } => {
+                self.memory
+                    .store_word(regs[base].wrapping_add(offset as u32), regs[rs])?;
+            }
+            Instr::Lbu { rd, offset, base } => {
+                let val = self
+                    .memory
+                    .load_byte(regs[base].wrapping_add(offset as u32))?;
+                self.set(rd, val as u32);
+            }
+            Instr::Sb { rs, offset, base } => {
+                self.memory
+                    .store_byte(regs[base].wrapping_add(offset as u32), regs[rs] as u8)?;
+            }
+            Instr::Li { rd, imm } => self.set(rd, imm),
+            Instr::Op { op, rd, rs1, rs2 } => self.set(rd, operate(op, regs[rs1], regs[rs2])),
+            Instr::OpImm { op, rd, rs1, imm } => self.set(rd, operate(op, regs[rs1], imm as u32)),
+            Instr::Branch {
+                cond,
+                rs1,
+                rs2,
+                target,
+            } => {
+                let (a, b) = (regs[rs1], regs[rs2]);
+
+                let taken = match cond {
+                    Cond::Eq => a == b,
+                    Cond::Ne => a != b,
+                    Cond::Lt => (a as i32) < (b as i32),
+                    Cond::Ge => (a as i32) >= (b as i32),
+                    Cond::Ltu => a < b,
+                    Cond::Geu => a >= b,
+                };
+
+                if taken {
+                    self.pc = target;
+                }
+            }
+            Instr::Jal { rd, target } => {
+                self.set(rd, self.pc);
+                self.pc = target;
+            }
+            Instr::Jalr { rd, rs1, offset } => {
+                self.set(rd, self.pc);
+                self.pc = regs[rs1].wrapping_add(offset as u32);
+            }
+            Instr::Ecall => return self.system_call(),
+        }
+
+        Ok(None)
+    }
+
+    // The number is in a7 and the arguments are in a0-a2
+    // The result is returned in a0 (a negated error number if the call fails).
+    fn system_call(&mut self) -> Result<Option<u32>, String> {
+        let (arg0, arg1, arg2) = (self.regs[A0], self.regs[A1], self.regs[A2]);
+
+        let result = match self.regs[A7] {
+            SYS_READ => self.read(arg0, arg1, arg2)?,
+            SYS_WRITE => self.write(arg0, arg1, arg2)?,
+            SYS_EXIT | SYS_EXIT_GROUP => return Ok(Some(arg0)),
+            SYS_BRK => {
+                // The break only moves up (and stays put when there is no more memory)
+                let end = self.memory.end();
+
+                if arg0 > end {
+                    let _ = self.memory.alloc((arg0 - end).div_ceil(WORD_SIZE));
+                }
+
+                self.memory.end()
+            }
+            number => return Err(format!("Unknown system call {}", number)),
+        };
+
+        self.regs[A0] = result;
+
+        Ok(None)
+    }
+
+    fn read(&mut self, fd: u32, addr: u32, len: u32) -> Result<u32, String> {
+        if fd != 0 {
+            return Ok(-EBADF as u32);
+        }
+
+        // Prompts must appear before the program waits for input
+        self.output
+            .flush()
+            .map_err(|why| format!("Cannot write output: {}", why))?;
+
+        let mut buf = vec![0; len as usize];
+
+        let count = match self.input.read(&mut buf) {
+            Ok(count) => count,
+            Err(why) => return Err(format!("Cannot read input: {}", why)),
+        };
+
+        self.memory.store_bytes(addr, &buf[..count])?;
+
+        Ok(count as u32)
+    }
+
+    fn write(&mut self, fd: u32, addr: u32, len: u32) -> Result<u32, String> {
+        let bytes = self.memory.load_bytes(addr, len)?;
+
+        let result = match fd {
+            1 => self.output.write_all(&bytes),
+            2 => {
+                // Errors appear after the output that came before them
+                self.output
+                    .flush()
+                    .and_then(|()| self.error_output.write_all(&bytes))
+            }
+            _ => return Ok(-EBADF as u32),
+        };
+
+        match result {
+            Ok(()) => Ok(len),
+            Err(why) => Err(format!("Cannot write output: {}", why)),
+        }
+    }
+}
+
+// The result of an operation (as RV32IM defines it)
+// Division by zero and overflow do not trap.
+fn operate(op: Op, a: u32, b: u32) -> u32 {
+    let (signed_a, signed_b) = (a as i32, b as i32);
+
+    match op {
+        Op::Add => a.wrapping_add(b),
+        Op::Sub => a.wrapping_sub(b),
+        Op::Mul => a.wrapping_mul(b),
+        Op::Mulhu => ((a as u64 * b as u64) >> 32) as u32,
+        Op::Div if b == 0 => u32::MAX,
+        Op::Div => signed_a.wrapping_div(signed_b) as u32,
+        Op::Divu if b == 0 => u32::MAX,
+        Op::Divu => a / b,
+        Op::Rem if b == 0 => a,
+        Op::Rem => signed_a.wrapping_rem(signed_b) as u32,
+        Op::Remu if b == 0 => a,
+        Op::Remu => a % b,
+        Op::Slt => (signed_a < signed_b) as u32,
+        Op::Sltu => (a < b) as u32,
+        Op::And => a & b,
+        Op::Or => a | b,
+        Op::Xor => a ^ b,
+        Op::Sll => a << (b & 31),
+        Op::Srl => a >> (b & 31),
+        Op::Sra => (signed_a >> (b & 31)) as u32,
+    }
+}
@@ -0,0 +1,42 @@
+// An interpreter for the RISC-V (RV32IM) assembly written by cgen
+// The program is assembled together with its runtime (runtime/riscv32.s) and run
+// from _start. The runtime makes Linux system calls, which are carried out here,
+// so neither a RISC-V machine nor an emulator such as qemu-riscv32 is needed.
+
+mod asm;
+mod machine;
+
+use std::io::{BufRead, Write};
+
+use self::asm::assemble;
+use self::machine::Machine;
+
+const WORD_SIZE: u32 = 4;
+
+const TEXT_BASE: u32 = 0x0040_0000; // The instructions
+
+const RUNTIME: &str = include_str!("../../runtime/riscv32.s");
+
+// Run a program
+// Returns false if the program exits with an error (e.g. after a dispatch to void).
+// Errors are for programs that cannot be run (or that go wrong in a way that
+// COOL programs cannot, such as accessing a bad address).
+pub fn run(
+    assembly: &str,
+    input: &mut dyn BufRead,
+    output: &mut dyn Write,
+    error_output: &mut dyn Write,
+) -> Result<bool, String> {
+    let program = assemble(&format!("{}\n{}", assembly, RUNTIME))?;
+    let start = program.label("_start")?;
+    let mut machine = Machine::new(&program, input, output, error_output);
+
+    let result = machine.run(start);
+
+    // Output written before an error is kept
+    if let Err(why) = machine.output.flush() {
+        return Err(format!("Cannot write output: {}", why));
+    }
+
+    Ok(result? == 0)
+}
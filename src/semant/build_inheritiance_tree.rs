@@ -1,15 +1,18 @@
 use std::ops::Range;
 
 use crate::ast::{Class, TypeID};
+use crate::diagnostics::{Diagnostics, ErrorCode};
 
 use indexmap::IndexMap;
 
 // Set the children field of the Class structs
 // Ensure that all inheritance is valid
+// Returns None if the classes do not form a valid tree
 
 pub fn build_inheritiance_tree(
     mut classes: IndexMap<TypeID, Class>,
-) -> Result<IndexMap<TypeID, Class>, String> {
+    diagnostics: &mut Diagnostics,
+) -> Option<IndexMap<TypeID, Class>> {
     let mut ret_classes: IndexMap<TypeID, Class> = IndexMap::new(); // Returned value
 
     // map to store children fields of types
@@ -18,49 +21,61 @@ pub fn build_inheritiance_tree(
 
     for (class_name, class) in classes.drain(..) {
         let file_name = class.file_name.clone();
-        let line_no = class.line_no;
+        let span = class.span;
 
         children_by_parent.insert(class_name.clone(), Vec::new());
 
         if class.parent_name == Some(TypeID::new_bool()) {
-            return Err(format!(
-                "{} : {} - Class {} cannot inherit class Bool.",
-                file_name, line_no, class_name
-            ));
+            diagnostics.error(
+                ErrorCode::BadInheritance,
+                &file_name,
+                span,
+                format!("Class {} cannot inherit class Bool.", class_name),
+            );
         }
 
         if class.parent_name == Some(TypeID::new_string()) {
-            return Err(format!(
-                "{} : {} - Class {} cannot inherit class String.",
-                file_name, line_no, class_name
-            ));
+            diagnostics.error(
+                ErrorCode::BadInheritance,
+                &file_name,
+                span,
+                format!("Class {} cannot inherit class String.", class_name),
+            );
         }
 
         if class.parent_name == Some(TypeID::new_int()) {
-            return Err(format!(
-                "{} : {} - Class {} cannot inherit class Int.",
-                file_name, line_no, class_name
-            ));
+            diagnostics.error(
+                ErrorCode::BadInheritance,
+                &file_name,
+                span,
+                format!("Class {} cannot inherit class Int.", class_name),
+            );
         }
 
         if class.parent_name == Some(TypeID::new_self_type()) {
-            return Err(format!(
-                "{} : {} - Class {} cannot inherit class SELF_TYPE.",
-                file_name, line_no, class_name
-            ));
+            diagnostics.error(
+                ErrorCode::BadInheritance,
+                &file_name,
+                span,
+                format!("Class {} cannot inherit class SELF_TYPE.", class_name),
+            );
         }
 
         if class_name.is_self_type() {
-            return Err(format!(
-                "{} : {} - Redefinition of basic class SELF_TYPE.",
-                file_name, line_no
-            ));
+            diagnostics.error(
+                ErrorCode::ClassRedefined,
+                &file_name,
+                span,
+                "Redefinition of basic class SELF_TYPE.".to_string(),
+            );
         }
 
         // Move data from classes to the ret_clasees
         ret_classes.insert(class_name, class);
     }
 
+    let mut has_orphans = false; // Are there classes whose parent does not exist?
+
     for (child_name, child) in ret_classes.iter() {
         if let Some(parent_name) = &child.parent_name {
             // If the class has a parent (Only Object does not.)
@@ -69,15 +84,30 @@ pub fn build_inheritiance_tree(
                     child_names.push(child_name.clone());
                 }
                 None => {
-                    return Err(format!(
-                        "{} : {} - Class {} inherits from an undefined class {}.",
-                        child.file_name, child.line_no, child_name, parent_name
-                    ))
+                    // Inheriting SELF_TYPE has already been reported
+                    if !parent_name.is_self_type() {
+                        diagnostics.error(
+                            ErrorCode::UndefinedClass,
+                            &child.file_name,
+                            child.span,
+                            format!(
+                                "Class {} inherits from an undefined class {}.",
+                                child_name, parent_name
+                            ),
+                        );
+                    }
+
+                    has_orphans = true;
                 }
             }
         }
     }
 
+    // The tree cannot be built with missing parents
+    if has_orphans {
+        return None;
+    }
+
     for (parent_name, child_names) in children_by_parent.iter() {
         // Set child_names in the ret_classes
         ret_classes.get_mut(parent_name).unwrap().child_names = child_names.clone();
@@ -93,19 +123,28 @@ pub fn build_inheritiance_tree(
     // Look for classes that weren't found in the DFS
     for (class_name, class) in ret_classes.iter() {
         if families.get(class_name).is_none() {
-            return Err(format!(
-                "{} : {} - Class {}, or an ancestor of {}, is involved in an inheritance cycle.",
-                class.file_name, class.line_no, class_name, class_name
-            ));
+            diagnostics.error(
+                ErrorCode::InheritanceCycle,
+                &class.file_name,
+                class.span,
+                format!(
+                    "Class {}, or an ancestor of {}, is involved in an inheritance cycle.",
+                    class_name, class_name
+                ),
+            );
         }
     }
 
+    if diagnostics.has_errors() {
+        return None;
+    }
+
     for (class_name, family) in families.drain(..) {
         // Set family in ret_classes
         ret_classes.get_mut(&class_name).unwrap().family = family;
     }
 
-    Ok(ret_classes)
+    Some(ret_classes)
 }
 
 // Determine families of all classes via a DFS
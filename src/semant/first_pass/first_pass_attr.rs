@@ -2,13 +2,15 @@ use indexmap::{IndexMap, IndexSet};
 
 use super::first_pass_expr::first_pass_expr;
 use crate::ast::{Attr, Class, ObjectID, TypeID};
+use crate::diagnostics::{Diagnostics, ErrorCode};
 use crate::scoped_collections::ScopedIndexSet;
 
 use std::ops::Range;
 
+#[allow(clippy::too_many_arguments)]
 pub fn first_pass_attr(
     Attr {
-        line_no,
+        span,
         name,
         type_decl,
         init,
@@ -23,26 +25,31 @@ pub fn first_pass_attr(
     str_consts: &mut IndexMap<String, u32>,
     str_table: &mut Vec<String>,
     inherited_attrs: &mut ScopedIndexSet<ObjectID>,
-) -> Result<Attr, String> {
+    diagnostics: &mut Diagnostics,
+) -> Attr {
     if name.is_self() {
-        return Err(format!(
-            "{} : {} - 'self' cannot be the name of an attribute.",
-            file_name, line_no
-        ));
+        diagnostics.error(
+            ErrorCode::BadSelf,
+            file_name,
+            span,
+            "'self' cannot be the name of an attribute.".to_string(),
+        );
     }
 
     if inherited_attrs.contains_top_scope(&name) {
-        return Err(format!(
-            "{} : {} - Attribute {} is multiply defined in class.",
-            file_name, line_no, name
-        ));
-    }
-
-    if inherited_attrs.contains(&name) {
-        return Err(format!(
-            "{} : {} - Attribute {} is an attribute of an inherited class.",
-            file_name, line_no, name
-        ));
+        diagnostics.error(
+            ErrorCode::AttrRedefined,
+            file_name,
+            span,
+            format!("Attribute {} is multiply defined in class.", name),
+        );
+    } else if inherited_attrs.contains(&name) {
+        diagnostics.error(
+            ErrorCode::AttrRedefined,
+            file_name,
+            span,
+            format!("Attribute {} is an attribute of an inherited class.", name),
+        );
     }
 
     inherited_attrs.insert(name.clone());
@@ -52,10 +59,12 @@ pub fn first_pass_attr(
         && !type_decl.is_system_type()
         && !type_decl.is_self_type()
     {
-        return Err(format!(
-            "{} : {} - Class {} of attribute {} is undefined.",
-            file_name, line_no, type_decl, name
-        ));
+        diagnostics.error(
+            ErrorCode::UndefinedClass,
+            file_name,
+            span,
+            format!("Class {} of attribute {} is undefined.", type_decl, name),
+        );
     }
 
     let init = first_pass_expr(
@@ -67,13 +76,14 @@ pub fn first_pass_attr(
         int_table,
         str_consts,
         str_table,
-    )?;
+        diagnostics,
+    );
 
-    Ok(Attr {
-        line_no,
+    Attr {
+        span,
         name,
         type_decl,
         init,
         self_offset,
-    })
+    }
 }
@@ -2,12 +2,14 @@ use indexmap::{IndexMap, IndexSet};
 
 use super::first_pass_expr::first_pass_expr;
 use crate::ast::{Branch, Class, TypeID};
+use crate::diagnostics::{Diagnostics, ErrorCode};
 
 use std::ops::Range;
 
+#[allow(clippy::too_many_arguments)]
 pub fn first_pass_branch(
     Branch {
-        line_no,
+        span,
         name,
         type_decl,
         expr,
@@ -20,7 +22,8 @@ pub fn first_pass_branch(
     int_table: &mut IndexSet<u32>,
     str_consts: &mut IndexMap<String, u32>,
     str_table: &mut Vec<String>,
-) -> Result<Branch, String> {
+    diagnostics: &mut Diagnostics,
+) -> Branch {
     let expr = first_pass_expr(
         expr,
         classes,
@@ -30,13 +33,16 @@ pub fn first_pass_branch(
         int_table,
         str_consts,
         str_table,
-    )?;
+        diagnostics,
+    );
 
     if name.is_self() {
-        return Err(format!(
-            "{} : {} - 'self' bound in 'case'.",
-            file_name, line_no
-        ));
+        diagnostics.error(
+            ErrorCode::BadSelf,
+            file_name,
+            span,
+            "'self' bound in 'case'.".to_string(),
+        );
     }
 
     let family: Range<u32> = if type_decl == *current_class_name {
@@ -45,19 +51,23 @@ pub fn first_pass_branch(
         match classes.get(&type_decl) {
             Some(class) => class.family.clone(),
             None => {
-                return Err(format!(
-                    "{} : {} : Class {} of case branch is undefined.",
-                    file_name, line_no, type_decl
-                ))
+                diagnostics.error(
+                    ErrorCode::UndefinedClass,
+                    file_name,
+                    span,
+                    format!("Class {} of case branch is undefined.", type_decl),
+                );
+
+                0..0 // Matches nothing
             }
         }
     };
 
-    Ok(Branch {
-        line_no,
+    Branch {
+        span,
         name,
         type_decl,
         expr,
         family,
-    })
+    }
 }
@@ -2,9 +2,11 @@ use indexmap::{IndexMap, IndexSet};
 
 use super::first_pass_branch::first_pass_branch;
 use crate::ast::{Branch, Class, Expression, TypeID};
+use crate::diagnostics::{Diagnostics, ErrorCode};
 
 use std::ops::Range;
 
+#[allow(clippy::too_many_arguments)]
 pub fn first_pass_expr(
     expr: Expression,
     classes: &IndexMap<TypeID, Class>,
@@ -14,13 +16,10 @@ pub fn first_pass_expr(
     int_table: &mut IndexSet<u32>,
     str_consts: &mut IndexMap<String, u32>,
     str_table: &mut Vec<String>,
-) -> Result<Expression, String> {
-    Ok(match expr {
-        Expression::Assign {
-            line_no,
-            name,
-            expr,
-        } => {
+    diagnostics: &mut Diagnostics,
+) -> Expression {
+    match expr {
+        Expression::Assign { span, name, expr } => {
             let expr = Box::new(first_pass_expr(
                 *expr,
                 classes,
@@ -30,23 +29,22 @@ pub fn first_pass_expr(
                 int_table,
                 str_consts,
                 str_table,
-            )?);
+                diagnostics,
+            ));
 
             if name.is_self() {
-                return Err(format!(
-                    "{} : {} - Cannot assign to 'self'.",
-                    file_name, line_no
-                ));
+                diagnostics.error(
+                    ErrorCode::BadSelf,
+                    file_name,
+                    span,
+                    "Cannot assign to 'self'.".to_string(),
+                );
             }
 
-            Expression::Assign {
-                line_no,
-                name,
-                expr,
-            }
+            Expression::Assign { span, name, expr }
         }
         Expression::Dispatch {
-            line_no,
+            span,
             expr,
             type_name,
             name,
@@ -62,7 +60,8 @@ pub fn first_pass_expr(
                 int_table,
                 str_consts,
                 str_table,
-            )?);
+                diagnostics,
+            ));
 
             let args = {
                 let mut new_args: Vec<Expression> = Vec::new();
@@ -76,7 +75,8 @@ pub fn first_pass_expr(
                         int_table,
                         str_consts,
                         str_table,
-                    )?);
+                        diagnostics,
+                    ));
                 }
 
                 new_args
@@ -86,17 +86,20 @@ pub fn first_pass_expr(
                 Some(type_name) => {
                     // static dispatch
                     if type_name.is_self_type() {
-                        return Err(format!(
-                            "{} : {} - Static dispatch to SELF_TYPE.",
-                            file_name, line_no
-                        ));
-                    }
-
-                    if type_name != *current_class_name && !classes.contains_key(&type_name) {
-                        return Err(format!(
-                            "{} : {} - Static dispatch to undefined class {}.",
-                            file_name, line_no, type_name
-                        ));
+                        diagnostics.error(
+                            ErrorCode::BadSelfType,
+                            file_name,
+                            span,
+                            "Static dispatch to SELF_TYPE.".to_string(),
+                        );
+                    } else if type_name != *current_class_name && !classes.contains_key(&type_name)
+                    {
+                        diagnostics.error(
+                            ErrorCode::UndefinedClass,
+                            file_name,
+                            span,
+                            format!("Static dispatch to undefined class {}.", type_name),
+                        );
                     }
 
                     Some(type_name)
@@ -105,7 +108,7 @@ pub fn first_pass_expr(
             };
 
             Expression::Dispatch {
-                line_no,
+                span,
                 expr,
                 type_name,
                 name,
@@ -114,7 +117,7 @@ pub fn first_pass_expr(
             }
         }
         Expression::Cond {
-            line_no,
+            span,
             pred,
             then_expr,
             else_expr,
@@ -129,7 +132,8 @@ pub fn first_pass_expr(
                 int_table,
                 str_consts,
                 str_table,
-            )?);
+                diagnostics,
+            ));
 
             let then_expr = Box::new(first_pass_expr(
                 *then_expr,
@@ -140,7 +144,8 @@ pub fn first_pass_expr(
                 int_table,
                 str_consts,
                 str_table,
-            )?);
+                diagnostics,
+            ));
 
             let else_expr = Box::new(first_pass_expr(
                 *else_expr,
@@ -151,21 +156,18 @@ pub fn first_pass_expr(
                 int_table,
                 str_consts,
                 str_table,
-            )?);
+                diagnostics,
+            ));
 
             Expression::Cond {
-                line_no,
+                span,
                 pred,
                 then_expr,
                 else_expr,
                 static_type,
             }
         }
-        Expression::Loop {
-            line_no,
-            pred,
-            body,
-        } => {
+        Expression::Loop { span, pred, body } => {
             let pred = Box::new(first_pass_expr(
                 *pred,
                 classes,
@@ -175,7 +177,8 @@ pub fn first_pass_expr(
                 int_table,
                 str_consts,
                 str_table,
-            )?);
+                diagnostics,
+            ));
 
             let body = Box::new(first_pass_expr(
                 *body,
@@ -186,16 +189,13 @@ pub fn first_pass_expr(
                 int_table,
                 str_consts,
                 str_table,
-            )?);
+                diagnostics,
+            ));
 
-            Expression::Loop {
-                line_no,
-                pred,
-                body,
-            }
+            Expression::Loop { span, pred, body }
         }
         Expression::TypeCase {
-            line_no,
+            span,
             expr,
             mut branches,
             static_type,
@@ -211,7 +211,8 @@ pub fn first_pass_expr(
                 int_table,
                 str_consts,
                 str_table,
-            )?);
+                diagnostics,
+            ));
 
             let branches = {
                 let mut new_branches: Vec<Branch> = Vec::new();
@@ -225,13 +226,16 @@ pub fn first_pass_expr(
                         int_table,
                         str_consts,
                         str_table,
-                    )?;
+                        diagnostics,
+                    );
 
                     if !branch_types.insert(branch.type_decl.clone()) {
-                        return Err(format!(
-                            "{} : {} - Duplicate branch {} in case statement.",
-                            file_name, branch.line_no, branch.type_decl
-                        ));
+                        diagnostics.error(
+                            ErrorCode::DuplicateBranch,
+                            file_name,
+                            branch.span,
+                            format!("Duplicate branch {} in case statement.", branch.type_decl),
+                        );
                     }
 
                     new_branches.push(branch);
@@ -243,13 +247,13 @@ pub fn first_pass_expr(
             };
 
             Expression::TypeCase {
-                line_no,
+                span,
                 expr,
                 branches,
                 static_type,
             }
         }
-        Expression::Block { line_no, mut body } => {
+        Expression::Block { span, mut body } => {
             let body = {
                 let mut new_body: Vec<Expression> = Vec::new();
                 for expr in body.drain(..) {
@@ -262,27 +266,30 @@ pub fn first_pass_expr(
                         int_table,
                         str_consts,
                         str_table,
-                    )?);
+                        diagnostics,
+                    ));
                 }
 
                 new_body
             };
 
-            Expression::Block { line_no, body }
+            Expression::Block { span, body }
         }
 
         Expression::Let {
-            line_no,
+            span,
             identifier,
             type_decl,
             init,
             body,
         } => {
             if identifier.is_self() {
-                return Err(format!(
-                    "{} : {} - 'self' cannot be bound in a 'let' expression.",
-                    file_name, line_no
-                ));
+                diagnostics.error(
+                    ErrorCode::BadSelf,
+                    file_name,
+                    span,
+                    "'self' cannot be bound in a 'let' expression.".to_string(),
+                );
             }
 
             let init = Box::new(first_pass_expr(
@@ -294,7 +301,8 @@ pub fn first_pass_expr(
                 int_table,
                 str_consts,
                 str_table,
-            )?);
+                diagnostics,
+            ));
 
             let body = Box::new(first_pass_expr(
                 *body,
@@ -305,20 +313,26 @@ pub fn first_pass_expr(
                 int_table,
                 str_consts,
                 str_table,
-            )?);
+                diagnostics,
+            ));
 
             if !type_decl.is_self_type()
                 && type_decl != *current_class_name
                 && !classes.contains_key(&type_decl)
             {
-                return Err(format!(
-                    "{} : {} - Class {} of let-bound identifier {} is undefined.",
-                    file_name, line_no, type_decl, identifier
-                ));
+                diagnostics.error(
+                    ErrorCode::UndefinedClass,
+                    file_name,
+                    span,
+                    format!(
+                        "Class {} of let-bound identifier {} is undefined.",
+                        type_decl, identifier
+                    ),
+                );
             }
 
             Expression::Let {
-                line_no,
+                span,
                 identifier,
                 type_decl,
                 init,
@@ -326,7 +340,7 @@ pub fn first_pass_expr(
             }
         }
         Expression::ArithOp {
-            line_no,
+            span,
             expr_lhs,
             expr_rhs,
             arith_op_type,
@@ -340,7 +354,8 @@ pub fn first_pass_expr(
                 int_table,
                 str_consts,
                 str_table,
-            )?);
+                diagnostics,
+            ));
 
             let expr_rhs = Box::new(first_pass_expr(
                 *expr_rhs,
@@ -351,16 +366,17 @@ pub fn first_pass_expr(
                 int_table,
                 str_consts,
                 str_table,
-            )?);
+                diagnostics,
+            ));
 
             Expression::ArithOp {
-                line_no,
+                span,
                 expr_lhs,
                 expr_rhs,
                 arith_op_type,
             }
         }
-        Expression::Neg { line_no, expr } => {
+        Expression::Neg { span, expr } => {
             let expr = Box::new(first_pass_expr(
                 *expr,
                 classes,
@@ -370,12 +386,13 @@ pub fn first_pass_expr(
                 int_table,
                 str_consts,
                 str_table,
-            )?);
+                diagnostics,
+            ));
 
-            Expression::Neg { line_no, expr }
+            Expression::Neg { span, expr }
         }
         Expression::Comp {
-            line_no,
+            span,
             expr_lhs,
             expr_rhs,
             comp_type,
@@ -389,7 +406,8 @@ pub fn first_pass_expr(
                 int_table,
                 str_consts,
                 str_table,
-            )?);
+                diagnostics,
+            ));
 
             let expr_rhs = Box::new(first_pass_expr(
                 *expr_rhs,
@@ -400,17 +418,18 @@ pub fn first_pass_expr(
                 int_table,
                 str_consts,
                 str_table,
-            )?);
+                diagnostics,
+            ));
 
             Expression::Comp {
-                line_no,
+                span,
                 expr_lhs,
                 expr_rhs,
                 comp_type,
             }
         }
         Expression::Eq {
-            line_no,
+            span,
             expr_lhs,
             expr_rhs,
         } => {
@@ -423,7 +442,8 @@ pub fn first_pass_expr(
                 int_table,
                 str_consts,
                 str_table,
-            )?);
+                diagnostics,
+            ));
 
             let expr_rhs = Box::new(first_pass_expr(
                 *expr_rhs,
@@ -434,15 +454,16 @@ pub fn first_pass_expr(
                 int_table,
                 str_consts,
                 str_table,
-            )?);
+                diagnostics,
+            ));
 
             Expression::Eq {
-                line_no,
+                span,
                 expr_lhs,
                 expr_rhs,
             }
         }
-        Expression::Not { line_no, expr } => {
+        Expression::Not { span, expr } => {
             let expr = Box::new(first_pass_expr(
                 *expr,
                 classes,
@@ -452,9 +473,10 @@ pub fn first_pass_expr(
                 int_table,
                 str_consts,
                 str_table,
-            )?);
+                diagnostics,
+            ));
 
-            Expression::Not { line_no, expr }
+            Expression::Not { span, expr }
         }
         Expression::IntConst { val, .. } => {
             int_table.insert(val);
@@ -462,7 +484,7 @@ pub fn first_pass_expr(
             expr
         }
         Expression::BoolConst { .. } => expr,
-        Expression::StringConst { line_no, val, .. } => {
+        Expression::StringConst { span, val, .. } => {
             let val_id = match str_consts.get(&val) {
                 Some(val_id) => *val_id, // If this string is already in the table
                 None => {
@@ -474,26 +496,24 @@ pub fn first_pass_expr(
                 }
             };
 
-            Expression::StringConst {
-                line_no,
-                val,
-                val_id,
-            }
+            Expression::StringConst { span, val, val_id }
         }
-        Expression::New { line_no, type_name } => {
+        Expression::New { span, type_name } => {
             if !type_name.is_self_type()
                 && type_name != *current_class_name
                 && !classes.contains_key(&type_name)
             {
-                return Err(format!(
-                    "{} : {} - 'new' used with undefined class {}.",
-                    file_name, line_no, type_name
-                ));
+                diagnostics.error(
+                    ErrorCode::UndefinedClass,
+                    file_name,
+                    span,
+                    format!("'new' used with undefined class {}.", type_name),
+                );
             }
 
-            Expression::New { line_no, type_name }
+            Expression::New { span, type_name }
         }
-        Expression::IsVoid { line_no, expr } => {
+        Expression::IsVoid { span, expr } => {
             let expr = Box::new(first_pass_expr(
                 *expr,
                 classes,
@@ -503,11 +523,12 @@ pub fn first_pass_expr(
                 int_table,
                 str_consts,
                 str_table,
-            )?);
+                diagnostics,
+            ));
 
-            Expression::IsVoid { line_no, expr }
+            Expression::IsVoid { span, expr }
         }
         Expression::NoExpr => expr,
         Expression::VarByName { .. } => expr,
-    })
+    }
 }
@@ -2,13 +2,16 @@ use indexmap::{IndexMap, IndexSet};
 
 use super::first_pass_expr::first_pass_expr;
 use crate::ast::{Class, Formal, Method, ObjectID, TypeID};
+use crate::diagnostics::{Diagnostics, ErrorCode};
 use crate::scoped_collections::ScopedIndexMap;
+use crate::span::Span;
 
 use std::ops::Range;
 
+#[allow(clippy::too_many_arguments)]
 pub fn first_pass_method(
     Method {
-        line_no,
+        span,
         formals,
         return_type,
         expr,
@@ -24,10 +27,11 @@ pub fn first_pass_method(
     method_count: i16,
     method_name_to_pos: &mut ScopedIndexMap<ObjectID, i16>,
     dispatch_table: &mut ScopedIndexMap<i16, (TypeID, ObjectID)>,
-) -> Result<(Method, bool), String> {
+    diagnostics: &mut Diagnostics,
+) -> (Method, bool) {
     let is_new: bool; // Is this method not overriding a previously declared method?
 
-    match method_name_to_pos.get(&method_name) {
+    match method_name_to_pos.get(method_name) {
         Some(method_pos) => {
             is_new = false;
 
@@ -46,12 +50,13 @@ pub fn first_pass_method(
 
             method_compare(
                 old_method,
-                line_no,
-                &method_name,
+                span,
+                method_name,
                 &formals,
                 &return_type,
                 file_name,
-            )?;
+                diagnostics,
+            );
         }
         None => {
             is_new = true;
@@ -68,31 +73,45 @@ pub fn first_pass_method(
 
     for formal in formals.iter() {
         if formal.type_decl.is_self_type() {
-            return Err(format!(
-                "{} : {} - Formal parameter {} cannot have type SELF_TYPE.",
-                file_name, line_no, formal.name
-            ));
+            diagnostics.error(
+                ErrorCode::BadSelfType,
+                file_name,
+                formal.span,
+                format!(
+                    "Formal parameter {} cannot have type SELF_TYPE.",
+                    formal.name
+                ),
+            );
+        } else if formal.type_decl != *current_class_name
+            && !classes.contains_key(&formal.type_decl)
+        {
+            diagnostics.error(
+                ErrorCode::UndefinedClass,
+                file_name,
+                formal.span,
+                format!(
+                    "Class {} of formal parameter {} is undefined.",
+                    formal.type_decl, formal.name
+                ),
+            );
         }
 
         if formal.name.is_self() {
-            return Err(format!(
-                "{} : {} - 'self' cannot be the name of a formal parameter.",
-                file_name, line_no
-            ));
-        }
-
-        if formal.type_decl != *current_class_name && !classes.contains_key(&formal.type_decl) {
-            return Err(format!(
-                "{} : {} - Class {} of formal parameter {} is undefined.",
-                file_name, line_no, formal.type_decl, formal.name
-            ));
+            diagnostics.error(
+                ErrorCode::BadSelf,
+                file_name,
+                formal.span,
+                "'self' cannot be the name of a formal parameter.".to_string(),
+            );
         }
 
         if !formal_names.insert(formal.name.clone()) {
-            return Err(format!(
-                "{} : {} - Formal parameter {} is multiply defined.",
-                file_name, line_no, formal.name
-            ));
+            diagnostics.error(
+                ErrorCode::FormalRedefined,
+                file_name,
+                formal.span,
+                format!("Formal parameter {} is multiply defined.", formal.name),
+            );
         }
     }
 
@@ -100,10 +119,15 @@ pub fn first_pass_method(
         && return_type != *current_class_name
         && !classes.contains_key(&return_type)
     {
-        return Err(format!(
-            "{} : {} - Undefined return type {} in method {}.",
-            file_name, line_no, return_type, method_name
-        ));
+        diagnostics.error(
+            ErrorCode::UndefinedClass,
+            file_name,
+            span,
+            format!(
+                "Undefined return type {} in method {}.",
+                return_type, method_name
+            ),
+        );
     }
 
     let expr = first_pass_expr(
@@ -115,50 +139,66 @@ pub fn first_pass_method(
         int_table,
         str_consts,
         str_table,
-    )?;
+        diagnostics,
+    );
 
-    Ok((
+    (
         Method {
-            line_no,
+            span,
             formals,
             return_type,
             expr,
         },
         is_new,
-    ))
+    )
 }
 
 // Ensure that a redefined method has the same signature of its predecessor
 fn method_compare(
     old_method: &Method,
-    line_no: i16,
+    span: Span,
     method_name: &ObjectID,
     formals: &[Formal],
     return_type: &TypeID,
     file_name: &str,
-) -> Result<(), String> {
+    diagnostics: &mut Diagnostics,
+) {
     if old_method.return_type != *return_type {
-        return Err(format!(
-            "{} : {} - In redefined method {}, return type {} is different from original return type {}.",
-            file_name, line_no, method_name, return_type, old_method.return_type
-        ));
+        diagnostics.error(
+            ErrorCode::BadOverride,
+            file_name,
+            span,
+            format!(
+                "In redefined method {}, return type {} is different from original return type {}.",
+                method_name, return_type, old_method.return_type
+            ),
+        );
     }
 
     if old_method.formals.len() != formals.len() {
-        return Err(format!(
-            "{} : {} - Incompatible number of formal parameters in redefined method {}.",
-            file_name, line_no, method_name
-        ));
+        diagnostics.error(
+            ErrorCode::BadOverride,
+            file_name,
+            span,
+            format!(
+                "Incompatible number of formal parameters in redefined method {}.",
+                method_name
+            ),
+        );
+        return;
     }
 
     for (old_formal, new_formal) in old_method.formals.iter().zip(formals.iter()) {
         if old_formal.type_decl != new_formal.type_decl {
-            return Err(format!(
-                "{} : {} - In redefined method {}, parameter type {} is different from original type {}.",
-                file_name, line_no, method_name, new_formal.type_decl, old_formal.type_decl
-            ));
+            diagnostics.error(
+                ErrorCode::BadOverride,
+                file_name,
+                span,
+                format!(
+                    "In redefined method {}, parameter type {} is different from original type {}.",
+                    method_name, new_formal.type_decl, old_formal.type_decl
+                ),
+            );
         }
     }
-
-    Ok(())
 }
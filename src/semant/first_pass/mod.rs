@@ -8,8 +8,10 @@ use indexmap::{IndexMap, IndexSet};
 use self::first_pass_attr::first_pass_attr;
 use self::first_pass_method::first_pass_method;
 use crate::ast::{Attr, Class, Method, ObjectID, TypeID};
+use crate::diagnostics::Diagnostics;
 use crate::scoped_collections::{ScopedIndexMap, ScopedIndexSet};
 
+#[allow(clippy::too_many_arguments)]
 pub fn first_pass(
     mut classes: IndexMap<TypeID, Class>,
     class_name: &TypeID,
@@ -21,12 +23,13 @@ pub fn first_pass(
     inherited_attrs: &mut ScopedIndexSet<ObjectID>,
     method_name_to_pos: &mut ScopedIndexMap<ObjectID, i16>,
     dispatch_table: &mut ScopedIndexMap<i16, (TypeID, ObjectID)>,
-) -> Result<IndexMap<TypeID, Class>, String> {
+    diagnostics: &mut Diagnostics,
+) -> IndexMap<TypeID, Class> {
     let Class {
         file_name,
         file_no,
         basic,
-        line_no,
+        span,
         parent_name,
         mut attrs,
         mut methods,
@@ -54,7 +57,8 @@ pub fn first_pass(
                 str_consts,
                 str_table,
                 inherited_attrs,
-            )?);
+                diagnostics,
+            ));
 
             self_offset += 1;
         }
@@ -79,7 +83,8 @@ pub fn first_pass(
                 method_count,
                 method_name_to_pos,
                 dispatch_table,
-            )?;
+                diagnostics,
+            );
 
             new_methods.insert(method_name, method);
 
@@ -99,7 +104,7 @@ pub fn first_pass(
             file_name,
             file_no,
             basic,
-            line_no,
+            span,
             parent_name,
             attrs,
             methods,
@@ -125,12 +130,13 @@ pub fn first_pass(
             inherited_attrs,
             method_name_to_pos,
             dispatch_table,
-        )?;
+            diagnostics,
+        );
     }
 
     inherited_attrs.exit_scope();
     method_name_to_pos.exit_scope();
     dispatch_table.exit_scope();
 
-    Ok(classes)
+    classes
 }
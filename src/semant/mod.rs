@@ -11,13 +11,36 @@ use self::first_pass::first_pass;
 use self::second_pass::second_pass;
 use self::verify_main::verify_main;
 use crate::ast::{Class, Method, ObjectID, TypeID};
+use crate::diagnostics::Diagnostics;
 use crate::scoped_collections::{ScopedIndexMap, ScopedIndexSet};
 
-const ATTR_OFFSET_START: i16 = 3;
+pub(crate) const ATTR_OFFSET_START: i16 = 3; // Attribute offsets start after the object header
 
+// Errors are recorded in diagnostics.
+// None is returned if any errors were found.
+#[allow(clippy::type_complexity)]
 pub fn semant(
     classes: IndexMap<TypeID, Class>,
-) -> Result<(IndexMap<TypeID, Class>, IndexSet<u32>, Vec<String>), String> {
+    diagnostics: &mut Diagnostics,
+) -> Option<(IndexMap<TypeID, Class>, IndexSet<u32>, Vec<String>)> {
+    let result = analyze(classes, diagnostics)?;
+
+    if diagnostics.has_errors() {
+        return None;
+    }
+
+    Some(result)
+}
+
+// Like semant, but the classes are returned even if they contain errors
+// (expressions that fail to type check have the type _error).
+// None is only returned if the inheritance tree is not valid.
+// This is meant for tools (e.g. the language server) rather than for code generation.
+#[allow(clippy::type_complexity)]
+pub fn analyze(
+    classes: IndexMap<TypeID, Class>,
+    diagnostics: &mut Diagnostics,
+) -> Option<(IndexMap<TypeID, Class>, IndexSet<u32>, Vec<String>)> {
     let mut int_table: IndexSet<u32> = IndexSet::new(); // All integer constants
     let mut str_consts: IndexMap<String, u32> = IndexMap::new(); // All string constants mapped to unique index
     let mut str_table: Vec<String> = Vec::new(); // Inverse of str_conts
@@ -29,10 +52,11 @@ pub fn semant(
 
     // Ensure that the classes are valid and that there is no inheritance cycle
     // Set the children field of the Class structs
-    let classes: IndexMap<TypeID, Class> = build_inheritiance_tree(classes)?;
+    // The remaining checks depend on a valid inheritance tree.
+    let classes: IndexMap<TypeID, Class> = build_inheritiance_tree(classes, diagnostics)?;
 
     // Ensure that there is a Main.main() method
-    verify_main(&classes)?;
+    verify_main(&classes, diagnostics);
 
     // Keep track of inherited attributes
     let mut inherited_attrs: ScopedIndexSet<ObjectID> = ScopedIndexSet::new();
@@ -58,7 +82,8 @@ pub fn semant(
         &mut inherited_attrs,
         &mut method_name_to_pos,
         &mut dispatch_table,
-    )?;
+        diagnostics,
+    );
 
     // Map objects to their static type
     let mut object_types: ScopedIndexMap<ObjectID, TypeID> = ScopedIndexMap::new();
@@ -103,7 +128,8 @@ pub fn semant(
         &method_sigs,
         &parent_names,
         &families,
-    )?;
+        diagnostics,
+    );
 
-    Ok((classes, int_table, str_table))
+    Some((classes, int_table, str_table))
 }
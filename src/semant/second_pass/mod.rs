@@ -8,6 +8,7 @@ use std::ops::Range;
 use self::second_pass_attr::second_pass_attr;
 use self::second_pass_method::second_pass_method;
 use crate::ast::{Attr, Class, Method, ObjectID, TypeID};
+use crate::diagnostics::Diagnostics;
 use crate::scoped_collections::ScopedIndexMap;
 
 use indexmap::IndexMap;
@@ -19,12 +20,13 @@ pub fn second_pass(
     method_sigs: &IndexMap<(TypeID, ObjectID), Method>,
     parent_names: &IndexMap<TypeID, TypeID>,
     families: &IndexMap<TypeID, Range<u32>>,
-) -> Result<IndexMap<TypeID, Class>, String> {
+    diagnostics: &mut Diagnostics,
+) -> IndexMap<TypeID, Class> {
     let Class {
         file_name,
         file_no,
         basic,
-        line_no,
+        span,
         parent_name,
         mut attrs,
         mut methods,
@@ -53,7 +55,8 @@ pub fn second_pass(
                 method_sigs,
                 parent_names,
                 families,
-            )?);
+                diagnostics,
+            ));
         }
 
         new_attrs
@@ -72,7 +75,8 @@ pub fn second_pass(
                 method_sigs,
                 parent_names,
                 families,
-            )?;
+                diagnostics,
+            );
             new_methods.insert(method_name, method);
         }
 
@@ -85,7 +89,7 @@ pub fn second_pass(
             file_name,
             file_no,
             basic,
-            line_no,
+            span,
             parent_name,
             attrs,
             methods,
@@ -105,23 +109,25 @@ pub fn second_pass(
             method_sigs,
             parent_names,
             families,
-        )?;
+            diagnostics,
+        );
     }
 
     object_types.exit_scope();
 
-    Ok(classes)
+    classes
 }
 
 // Does class #1 conform to class #2 ?
 // Is class #1 a descendant of class #2 ?
+// Types that are already in error (including undefined classes) conform to everything.
 pub fn conforms(
     class_name1: &TypeID,
     class_name2: &TypeID,
     current_class_name: &TypeID,
     families: &IndexMap<TypeID, Range<u32>>,
 ) -> bool {
-    if class_name1.is_no_type() {
+    if class_name1.is_no_type() || class_name1.is_error_type() || class_name2.is_error_type() {
         return true;
     }
 
@@ -130,24 +136,22 @@ pub fn conforms(
     }
 
     if class_name1.is_self_type() {
-        return conforms(current_class_name, class_name2, current_class_name, families);
+        return conforms(
+            current_class_name,
+            class_name2,
+            current_class_name,
+            families,
+        );
     }
 
     if class_name2.is_self_type() {
         return false;
     }
 
-    let family = families.get(class_name2).unwrap();
-
-    let tag = if class_name1.is_self_type() {
-        families.get(current_class_name)
-    } else {
-        families.get(class_name1)
+    match (families.get(class_name1), families.get(class_name2)) {
+        (Some(family1), Some(family2)) => family2.contains(&family1.start),
+        _ => true, // Undefined classes have already been reported
     }
-    .unwrap()
-    .start;
-
-    family.contains(&tag)
 }
 
 // Return the name of the least class that both class_name1 and class_name2 conform to
@@ -158,6 +162,10 @@ pub fn least_upper_bound(
     parent_names: &IndexMap<TypeID, TypeID>,
     families: &IndexMap<TypeID, Range<u32>>,
 ) -> TypeID {
+    if !is_valid_type(class_name1, families) || !is_valid_type(class_name2, families) {
+        return TypeID::new_error_type();
+    }
+
     if class_name1.is_self_type() && class_name2.is_self_type() {
         return TypeID::new_self_type();
     }
@@ -183,11 +191,11 @@ pub fn least_upper_bound(
     }
 
     if conforms(class_name1, class_name2, current_class_name, families) {
-        return class_name1.clone();
+        return class_name2.clone();
     }
 
     if conforms(class_name2, class_name1, current_class_name, families) {
-        return class_name2.clone();
+        return class_name1.clone();
     }
 
     least_upper_bound(
@@ -198,3 +206,9 @@ pub fn least_upper_bound(
         families,
     )
 }
+
+// Is this a type that can be reasoned about?
+// False for the error type and for undefined classes (which have already been reported)
+fn is_valid_type(class_name: &TypeID, families: &IndexMap<TypeID, Range<u32>>) -> bool {
+    class_name.is_self_type() || families.contains_key(class_name)
+}
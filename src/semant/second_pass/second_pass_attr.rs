@@ -3,13 +3,15 @@ use std::ops::Range;
 use super::conforms;
 use super::second_pass_expr::second_pass_expr;
 use crate::ast::{Attr, Class, Method, ObjectID, TypeID};
+use crate::diagnostics::{Diagnostics, ErrorCode};
 use crate::scoped_collections::ScopedIndexMap;
 
 use indexmap::IndexMap;
 
+#[allow(clippy::too_many_arguments)]
 pub fn second_pass_attr(
     Attr {
-        line_no,
+        span,
         name,
         type_decl,
         init,
@@ -22,7 +24,8 @@ pub fn second_pass_attr(
     method_sigs: &IndexMap<(TypeID, ObjectID), Method>,
     parent_names: &IndexMap<TypeID, TypeID>,
     families: &IndexMap<TypeID, Range<u32>>,
-) -> Result<Attr, String> {
+    diagnostics: &mut Diagnostics,
+) -> Attr {
     let init = second_pass_expr(
         init,
         classes,
@@ -32,7 +35,8 @@ pub fn second_pass_attr(
         method_sigs,
         parent_names,
         families,
-    )?;
+        diagnostics,
+    );
 
     if !conforms(
         &init.static_type(),
@@ -40,21 +44,24 @@ pub fn second_pass_attr(
         current_class_name,
         families,
     ) {
-        return Err(format!(
-            "{} : {} - Type {} of assigned expression does not conform to declared type {} of identifier {}.",
+        diagnostics.error(
+            ErrorCode::TypeMismatch,
             file_name,
-            line_no,
-            init.static_type(),
-            type_decl,
-            name
-        ));
+            span,
+            format!(
+                "Type {} of assigned expression does not conform to declared type {} of identifier {}.",
+                init.static_type(),
+                type_decl,
+                name
+            ),
+        );
     }
 
-    Ok(Attr {
-        line_no,
+    Attr {
+        span,
         name,
         type_decl,
         init,
         self_offset,
-    })
+    }
 }
@@ -2,13 +2,15 @@ use std::ops::Range;
 
 use super::second_pass_expr::second_pass_expr;
 use crate::ast::{Branch, Class, Method, ObjectID, TypeID};
+use crate::diagnostics::Diagnostics;
 use crate::scoped_collections::ScopedIndexMap;
 
 use indexmap::IndexMap;
 
+#[allow(clippy::too_many_arguments)]
 pub fn second_pass_branch(
     Branch {
-        line_no,
+        span,
         name,
         type_decl,
         expr,
@@ -21,7 +23,8 @@ pub fn second_pass_branch(
     method_sigs: &IndexMap<(TypeID, ObjectID), Method>,
     parent_names: &IndexMap<TypeID, TypeID>,
     families: &IndexMap<TypeID, Range<u32>>,
-) -> Result<Branch, String> {
+    diagnostics: &mut Diagnostics,
+) -> Branch {
     object_types.enter_scope();
 
     object_types.insert(name.clone(), type_decl.clone());
@@ -35,15 +38,16 @@ pub fn second_pass_branch(
         method_sigs,
         parent_names,
         families,
-    )?;
+        diagnostics,
+    );
 
     object_types.exit_scope();
 
-    Ok(Branch {
-        line_no,
+    Branch {
+        span,
         name,
         type_decl,
         expr,
         family,
-    })
+    }
 }
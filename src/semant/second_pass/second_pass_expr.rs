@@ -3,10 +3,12 @@ use std::ops::Range;
 use super::second_pass_branch::second_pass_branch;
 use super::{conforms, least_upper_bound};
 use crate::ast::{Branch, Class, Expression, Method, ObjectID, TypeID};
+use crate::diagnostics::{Diagnostics, ErrorCode};
 use crate::scoped_collections::ScopedIndexMap;
 
 use indexmap::IndexMap;
 
+#[allow(clippy::too_many_arguments)]
 pub fn second_pass_expr(
     expr: Expression,
     classes: &IndexMap<TypeID, Class>,
@@ -16,13 +18,10 @@ pub fn second_pass_expr(
     method_sigs: &IndexMap<(TypeID, ObjectID), Method>,
     parent_names: &IndexMap<TypeID, TypeID>,
     families: &IndexMap<TypeID, Range<u32>>,
-) -> Result<Expression, String> {
-    Ok(match expr {
-        Expression::Assign {
-            line_no,
-            name,
-            expr,
-        } => {
+    diagnostics: &mut Diagnostics,
+) -> Expression {
+    match expr {
+        Expression::Assign { span, name, expr } => {
             let expr = Box::new(second_pass_expr(
                 *expr,
                 classes,
@@ -32,38 +31,38 @@ pub fn second_pass_expr(
                 method_sigs,
                 parent_names,
                 families,
-            )?);
+                diagnostics,
+            ));
 
             match object_types.get(&name) {
                 Some(t) => {
                     if !conforms(&expr.static_type(), t, current_class_name, families) {
-                        return Err(format!(
-                                "{} : {} - Type {} of assigned expression does not conform to declared type {} of identifier {}.",
-                                file_name,
-                                line_no,
+                        diagnostics.error(
+                            ErrorCode::TypeMismatch,
+                            file_name,
+                            expr.span(),
+                            format!(
+                                "Type {} of assigned expression does not conform to declared type {} of identifier {}.",
                                 &expr.static_type(),
                                 t,
                                 name
-                            ));
+                            ),
+                        );
                     }
                 }
-                None => {
-                    return Err(format!(
-                        "{} : {} - Assignment to undeclared variable {}.",
-                        file_name, line_no, name
-                    ))
-                }
+                None => diagnostics.error(
+                    ErrorCode::UndeclaredIdentifier,
+                    file_name,
+                    span,
+                    format!("Assignment to undeclared variable {}.", name),
+                ),
             }
 
-            Expression::Assign {
-                line_no,
-                name,
-                expr,
-            }
+            Expression::Assign { span, name, expr }
         }
 
         Expression::Dispatch {
-            line_no,
+            span,
             expr,
             type_name,
             name,
@@ -79,7 +78,8 @@ pub fn second_pass_expr(
                 method_sigs,
                 parent_names,
                 families,
-            )?);
+                diagnostics,
+            ));
 
             let type_name = match type_name {
                 Some(type_name) => {
@@ -89,13 +89,16 @@ pub fn second_pass_expr(
                         current_class_name,
                         families,
                     ) {
-                        return Err(format!(
-                            "{} : {} - Expression type {} does not conform to declared static dispatch type {}.",
+                        diagnostics.error(
+                            ErrorCode::TypeMismatch,
                             file_name,
-                            line_no,
-                            expr.static_type(),
-                            type_name
-                        ));
+                            expr.span(),
+                            format!(
+                                "Expression type {} does not conform to declared static dispatch type {}.",
+                                expr.static_type(),
+                                type_name
+                            ),
+                        );
                     }
 
                     Some(type_name)
@@ -115,68 +118,78 @@ pub fn second_pass_expr(
                         method_sigs,
                         parent_names,
                         families,
-                    )?);
+                        diagnostics,
+                    ));
                 }
 
                 new_args
             };
 
-            let (type_name, method_id) = match type_name {
-                Some(type_name) => (Some(type_name.clone()), (type_name, name.clone())),
-                None => {
-                    let mut class_name = expr.static_type();
-                    if class_name.is_self_type() {
-                        class_name = current_class_name.clone();
-                    }
-
-                    (None, (class_name, name.clone()))
-                }
-            };
-
-            let method_sig = match method_sigs.get(&method_id) {
-                Some(method_sig) => method_sig,
-                None => {
-                    return Err(format!(
-                        "{} : {} - Dispatch to undefined method {}.",
-                        file_name, line_no, name
-                    ));
-                }
+            // The class in which the method is looked up
+            let class_name = match &type_name {
+                Some(type_name) => type_name.clone(),
+                None if expr.static_type().is_self_type() => current_class_name.clone(),
+                None => expr.static_type(),
             };
 
-            let mut t = method_sig.return_type.clone();
-            if t.is_self_type() && !expr.static_type().is_self_type() {
-                t = expr.static_type();
-            }
-
-            let static_type = t;
-
-            if args.len() != method_sig.formals.len() {
-                return Err(format!(
-                    "{} : {} - Method {} called with wrong number of arguments.",
-                    file_name, line_no, name
-                ));
-            }
+            // A class in error has already been reported
+            let static_type = if !families.contains_key(&class_name) {
+                TypeID::new_error_type()
+            } else {
+                match method_sigs.get(&(class_name, name.clone())) {
+                    Some(method_sig) => {
+                        if args.len() != method_sig.formals.len() {
+                            diagnostics.error(
+                                ErrorCode::WrongArgCount,
+                                file_name,
+                                span,
+                                format!("Method {} called with wrong number of arguments.", name),
+                            );
+                        }
+
+                        for (arg, formal) in args.iter().zip(method_sig.formals.iter()) {
+                            if !conforms(
+                                &arg.static_type(),
+                                &formal.type_decl,
+                                current_class_name,
+                                families,
+                            ) {
+                                diagnostics.error(
+                                    ErrorCode::TypeMismatch,
+                                    file_name,
+                                    arg.span(),
+                                    format!(
+                                        "In call of method {}, type {} of parameter a does not conform to declared type {}.",
+                                        name,
+                                        arg.static_type(),
+                                        formal.type_decl
+                                    ),
+                                );
+                            }
+                        }
+
+                        let mut t = method_sig.return_type.clone();
+                        if t.is_self_type() && !expr.static_type().is_self_type() {
+                            t = expr.static_type();
+                        }
+
+                        t
+                    }
+                    None => {
+                        diagnostics.error(
+                            ErrorCode::UndefinedMethod,
+                            file_name,
+                            span,
+                            format!("Dispatch to undefined method {}.", name),
+                        );
 
-            for (arg, formal) in args.iter().zip(method_sig.formals.iter()) {
-                if !conforms(
-                    &arg.static_type(),
-                    &formal.type_decl,
-                    current_class_name,
-                    families,
-                ) {
-                    return Err(format!(
-                        "{} : {} - In call of method {}, type {} of parameter a does not conform to declared type {}.",
-                        file_name,
-                        line_no,
-                        name,
-                        arg.static_type(),
-                        formal.type_decl
-                    ));
+                        TypeID::new_error_type()
+                    }
                 }
-            }
+            };
 
             Expression::Dispatch {
-                line_no,
+                span,
                 expr,
                 type_name,
                 name,
@@ -186,7 +199,7 @@ pub fn second_pass_expr(
         }
 
         Expression::Cond {
-            line_no,
+            span,
             pred,
             then_expr,
             else_expr,
@@ -201,13 +214,16 @@ pub fn second_pass_expr(
                 method_sigs,
                 parent_names,
                 families,
-            )?);
+                diagnostics,
+            ));
 
-            if !pred.static_type().is_bool() {
-                return Err(format!(
-                    "{} : {} - Predicate of 'if' does not have type Bool.",
-                    file_name, line_no
-                ));
+            if !pred.static_type().is_bool() && !pred.static_type().is_error_type() {
+                diagnostics.error(
+                    ErrorCode::NonBoolOperand,
+                    file_name,
+                    pred.span(),
+                    "Predicate of 'if' does not have type Bool.".to_string(),
+                );
             }
 
             let then_expr = Box::new(second_pass_expr(
@@ -219,7 +235,8 @@ pub fn second_pass_expr(
                 method_sigs,
                 parent_names,
                 families,
-            )?);
+                diagnostics,
+            ));
 
             let else_expr = Box::new(second_pass_expr(
                 *else_expr,
@@ -230,7 +247,8 @@ pub fn second_pass_expr(
                 method_sigs,
                 parent_names,
                 families,
-            )?);
+                diagnostics,
+            ));
 
             let static_type = least_upper_bound(
                 &then_expr.static_type(),
@@ -241,18 +259,14 @@ pub fn second_pass_expr(
             );
 
             Expression::Cond {
-                line_no,
+                span,
                 pred,
                 then_expr,
                 else_expr,
                 static_type,
             }
         }
-        Expression::Loop {
-            line_no,
-            pred,
-            body,
-        } => {
+        Expression::Loop { span, pred, body } => {
             let pred = Box::new(second_pass_expr(
                 *pred,
                 classes,
@@ -262,7 +276,8 @@ pub fn second_pass_expr(
                 method_sigs,
                 parent_names,
                 families,
-            )?);
+                diagnostics,
+            ));
 
             let body = Box::new(second_pass_expr(
                 *body,
@@ -273,23 +288,22 @@ pub fn second_pass_expr(
                 method_sigs,
                 parent_names,
                 families,
-            )?);
+                diagnostics,
+            ));
 
-            if !pred.static_type().is_bool() {
-                return Err(format!(
-                    "{} : {} - Loop condition does not have type Bool.",
-                    file_name, line_no
-                ));
+            if !pred.static_type().is_bool() && !pred.static_type().is_error_type() {
+                diagnostics.error(
+                    ErrorCode::NonBoolOperand,
+                    file_name,
+                    pred.span(),
+                    "Loop condition does not have type Bool.".to_string(),
+                );
             }
 
-            Expression::Loop {
-                line_no,
-                pred,
-                body,
-            }
+            Expression::Loop { span, pred, body }
         }
         Expression::TypeCase {
-            line_no,
+            span,
             expr,
             mut branches,
             ..
@@ -303,7 +317,8 @@ pub fn second_pass_expr(
                 method_sigs,
                 parent_names,
                 families,
-            )?);
+                diagnostics,
+            ));
 
             let branches = {
                 let mut new_branches: Vec<Branch> = Vec::new();
@@ -317,7 +332,8 @@ pub fn second_pass_expr(
                         method_sigs,
                         parent_names,
                         families,
-                    )?;
+                        diagnostics,
+                    );
 
                     new_branches.push(branch);
                 }
@@ -338,15 +354,13 @@ pub fn second_pass_expr(
             }
 
             Expression::TypeCase {
-                line_no,
+                span,
                 expr,
                 branches,
                 static_type,
             }
         }
-        Expression::Block {
-            line_no, mut body, ..
-        } => {
+        Expression::Block { span, mut body, .. } => {
             let body = {
                 let mut new_body: Vec<Expression> = Vec::new();
                 for expr in body.drain(..) {
@@ -359,16 +373,17 @@ pub fn second_pass_expr(
                         method_sigs,
                         parent_names,
                         families,
-                    )?);
+                        diagnostics,
+                    ));
                 }
 
                 new_body
             };
 
-            Expression::Block { line_no, body }
+            Expression::Block { span, body }
         }
         Expression::Let {
-            line_no,
+            span,
             identifier,
             type_decl,
             init,
@@ -384,7 +399,8 @@ pub fn second_pass_expr(
                 method_sigs,
                 parent_names,
                 families,
-            )?);
+                diagnostics,
+            ));
 
             if !conforms(
                 &init.static_type(),
@@ -392,14 +408,17 @@ pub fn second_pass_expr(
                 current_class_name,
                 families,
             ) {
-                return Err(format!(
-                    "{} : {} - Inferred type {} of initialization of {} does not conform to identifier's declared type {}.",
+                diagnostics.error(
+                    ErrorCode::TypeMismatch,
                     file_name,
-                    line_no,
-                    init.static_type(),
-                    identifier,
-                    type_decl
-                ));
+                    init.span(),
+                    format!(
+                        "Inferred type {} of initialization of {} does not conform to identifier's declared type {}.",
+                        init.static_type(),
+                        identifier,
+                        type_decl
+                    ),
+                );
             }
 
             object_types.enter_scope();
@@ -415,12 +434,13 @@ pub fn second_pass_expr(
                 method_sigs,
                 parent_names,
                 families,
-            )?);
+                diagnostics,
+            ));
 
             object_types.exit_scope();
 
             Expression::Let {
-                line_no,
+                span,
                 identifier,
                 type_decl,
                 init,
@@ -428,7 +448,7 @@ pub fn second_pass_expr(
             }
         }
         Expression::ArithOp {
-            line_no,
+            span,
             expr_lhs,
             expr_rhs,
             arith_op_type,
@@ -442,7 +462,8 @@ pub fn second_pass_expr(
                 method_sigs,
                 parent_names,
                 families,
-            )?);
+                diagnostics,
+            ));
 
             let expr_rhs = Box::new(second_pass_expr(
                 *expr_rhs,
@@ -453,27 +474,33 @@ pub fn second_pass_expr(
                 method_sigs,
                 parent_names,
                 families,
-            )?);
-
-            if !expr_lhs.static_type().is_int() || !expr_lhs.static_type().is_int() {
-                return Err(format!(
-                    "{} : {} - non-Int arguments: {} {} {}",
+                diagnostics,
+            ));
+
+            if !is_int_or_error(&expr_lhs.static_type())
+                || !is_int_or_error(&expr_rhs.static_type())
+            {
+                diagnostics.error(
+                    ErrorCode::NonIntOperand,
                     file_name,
-                    line_no,
-                    expr_lhs.static_type(),
-                    arith_op_type,
-                    expr_rhs.static_type()
-                ));
+                    span,
+                    format!(
+                        "non-Int arguments: {} {} {}",
+                        expr_lhs.static_type(),
+                        arith_op_type,
+                        expr_rhs.static_type()
+                    ),
+                );
             }
 
             Expression::ArithOp {
-                line_no,
+                span,
                 expr_lhs,
                 expr_rhs,
                 arith_op_type,
             }
         }
-        Expression::Neg { line_no, expr } => {
+        Expression::Neg { span, expr } => {
             let expr = Box::new(second_pass_expr(
                 *expr,
                 classes,
@@ -483,21 +510,25 @@ pub fn second_pass_expr(
                 method_sigs,
                 parent_names,
                 families,
-            )?);
+                diagnostics,
+            ));
 
-            if !expr.static_type().is_int() {
-                return Err(format!(
-                    "{} : {} - Argument of '~' has type {} instead of Int",
+            if !is_int_or_error(&expr.static_type()) {
+                diagnostics.error(
+                    ErrorCode::NonIntOperand,
                     file_name,
-                    line_no,
-                    expr.static_type()
-                ));
+                    span,
+                    format!(
+                        "Argument of '~' has type {} instead of Int",
+                        expr.static_type()
+                    ),
+                );
             }
 
-            Expression::Neg { line_no, expr }
+            Expression::Neg { span, expr }
         }
         Expression::Comp {
-            line_no,
+            span,
             expr_lhs,
             expr_rhs,
             comp_type,
@@ -511,7 +542,8 @@ pub fn second_pass_expr(
                 method_sigs,
                 parent_names,
                 families,
-            )?);
+                diagnostics,
+            ));
 
             let expr_rhs = Box::new(second_pass_expr(
                 *expr_rhs,
@@ -522,28 +554,34 @@ pub fn second_pass_expr(
                 method_sigs,
                 parent_names,
                 families,
-            )?);
-
-            if !expr_lhs.static_type().is_int() || !expr_lhs.static_type().is_int() {
-                return Err(format!(
-                    "{} : {} - non-Int arguments: {} {} {}",
+                diagnostics,
+            ));
+
+            if !is_int_or_error(&expr_lhs.static_type())
+                || !is_int_or_error(&expr_rhs.static_type())
+            {
+                diagnostics.error(
+                    ErrorCode::NonIntOperand,
                     file_name,
-                    line_no,
-                    expr_lhs.static_type(),
-                    comp_type,
-                    expr_rhs.static_type()
-                ));
+                    span,
+                    format!(
+                        "non-Int arguments: {} {} {}",
+                        expr_lhs.static_type(),
+                        comp_type,
+                        expr_rhs.static_type()
+                    ),
+                );
             }
 
             Expression::Comp {
-                line_no,
+                span,
                 expr_lhs,
                 expr_rhs,
                 comp_type,
             }
         }
         Expression::Eq {
-            line_no,
+            span,
             expr_lhs,
             expr_rhs,
         } => {
@@ -556,7 +594,8 @@ pub fn second_pass_expr(
                 method_sigs,
                 parent_names,
                 families,
-            )?);
+                diagnostics,
+            ));
 
             let expr_rhs = Box::new(second_pass_expr(
                 *expr_rhs,
@@ -567,54 +606,34 @@ pub fn second_pass_expr(
                 method_sigs,
                 parent_names,
                 families,
-            )?);
-
-            if expr_lhs.static_type().is_int() && !expr_rhs.static_type().is_int() {
-                return Err(format!(
-                    "{} : {} - Illegal comparison with a basic type.",
-                    file_name, line_no
-                ));
-            }
-            if !expr_lhs.static_type().is_int() && expr_rhs.static_type().is_int() {
-                return Err(format!(
-                    "{} : {} - Illegal comparison with a basic type.",
-                    file_name, line_no
-                ));
-            }
-
-            if expr_lhs.static_type().is_string() && !expr_rhs.static_type().is_string() {
-                return Err(format!(
-                    "{} : {} - Illegal comparison with a basic type.",
-                    file_name, line_no
-                ));
-            }
-            if !expr_lhs.static_type().is_string() && expr_rhs.static_type().is_string() {
-                return Err(format!(
-                    "{} : {} - Illegal comparison with a basic type.",
-                    file_name, line_no
-                ));
-            }
-
-            if expr_lhs.static_type().is_bool() && !expr_rhs.static_type().is_bool() {
-                return Err(format!(
-                    "{} : {} - Illegal comparison with a basic type.",
-                    file_name, line_no
-                ));
-            }
-            if !expr_lhs.static_type().is_bool() && expr_rhs.static_type().is_bool() {
-                return Err(format!(
-                    "{} : {} - Illegal comparison with a basic type.",
-                    file_name, line_no
-                ));
+                diagnostics,
+            ));
+
+            let lhs_type = expr_lhs.static_type();
+            let rhs_type = expr_rhs.static_type();
+
+            // An Int, String, or Bool can only be compared to another of the same type
+            if !lhs_type.is_error_type()
+                && !rhs_type.is_error_type()
+                && (lhs_type.is_int() != rhs_type.is_int()
+                    || lhs_type.is_string() != rhs_type.is_string()
+                    || lhs_type.is_bool() != rhs_type.is_bool())
+            {
+                diagnostics.error(
+                    ErrorCode::IllegalComparison,
+                    file_name,
+                    span,
+                    "Illegal comparison with a basic type.".to_string(),
+                );
             }
 
             Expression::Eq {
-                line_no,
+                span,
                 expr_lhs,
                 expr_rhs,
             }
         }
-        Expression::Not { line_no, expr } => {
+        Expression::Not { span, expr } => {
             let expr = Box::new(second_pass_expr(
                 *expr,
                 classes,
@@ -624,24 +643,28 @@ pub fn second_pass_expr(
                 method_sigs,
                 parent_names,
                 families,
-            )?);
+                diagnostics,
+            ));
 
-            if !expr.static_type().is_bool() {
-                return Err(format!(
-                    "{} : {} - Argument of 'not' has type {} instead of Bool.",
+            if !expr.static_type().is_bool() && !expr.static_type().is_error_type() {
+                diagnostics.error(
+                    ErrorCode::NonBoolOperand,
                     file_name,
-                    line_no,
-                    expr.static_type()
-                ));
+                    span,
+                    format!(
+                        "Argument of 'not' has type {} instead of Bool.",
+                        expr.static_type()
+                    ),
+                );
             }
 
-            Expression::Not { line_no, expr }
+            Expression::Not { span, expr }
         }
         Expression::IntConst { .. } => expr,
         Expression::BoolConst { .. } => expr,
         Expression::StringConst { .. } => expr,
         Expression::New { .. } => expr,
-        Expression::IsVoid { line_no, expr } => {
+        Expression::IsVoid { span, expr } => {
             let expr = Box::new(second_pass_expr(
                 *expr,
                 classes,
@@ -651,31 +674,42 @@ pub fn second_pass_expr(
                 method_sigs,
                 parent_names,
                 families,
-            )?);
+                diagnostics,
+            ));
 
-            Expression::IsVoid { line_no, expr }
+            Expression::IsVoid { span, expr }
         }
         Expression::NoExpr => expr,
-        Expression::VarByName { line_no, name, .. } => {
+        Expression::VarByName { span, name, .. } => {
             let static_type = if name.is_self() {
                 TypeID::new_self_type()
             } else {
                 match object_types.get(&name) {
                     Some(t) => t.clone(),
                     None => {
-                        return Err(format!(
-                            "{} : {} - Undeclared identifier {}.",
-                            file_name, line_no, name
-                        ))
+                        diagnostics.error(
+                            ErrorCode::UndeclaredIdentifier,
+                            file_name,
+                            span,
+                            format!("Undeclared identifier {}.", name),
+                        );
+
+                        TypeID::new_error_type()
                     }
                 }
             };
 
             Expression::VarByName {
-                line_no,
+                span,
                 name,
                 static_type,
             }
         }
-    })
+    }
+}
+
+// Arithmetic operands should be Int
+// Operands in error have already been reported
+fn is_int_or_error(type_name: &TypeID) -> bool {
+    type_name.is_int() || type_name.is_error_type()
 }
@@ -3,13 +3,15 @@ use std::ops::Range;
 use super::conforms;
 use super::second_pass_expr::second_pass_expr;
 use crate::ast::{Class, Method, ObjectID, TypeID};
+use crate::diagnostics::{Diagnostics, ErrorCode};
 use crate::scoped_collections::ScopedIndexMap;
 
 use indexmap::IndexMap;
 
+#[allow(clippy::too_many_arguments)]
 pub fn second_pass_method(
     Method {
-        line_no,
+        span,
         formals,
         return_type,
         expr,
@@ -22,7 +24,8 @@ pub fn second_pass_method(
     method_sigs: &IndexMap<(TypeID, ObjectID), Method>,
     parent_names: &IndexMap<TypeID, TypeID>,
     families: &IndexMap<TypeID, Range<u32>>,
-) -> Result<Method, String> {
+    diagnostics: &mut Diagnostics,
+) -> Method {
     // Scope for the formals of the method
     object_types.enter_scope();
 
@@ -39,7 +42,8 @@ pub fn second_pass_method(
         method_sigs,
         parent_names,
         families,
-    )?;
+        diagnostics,
+    );
 
     // Does the expr's static type conform to the declared return type?
     if !conforms(
@@ -48,22 +52,25 @@ pub fn second_pass_method(
         current_class_name,
         families,
     ) {
-        return Err(format!(
-            "{} : {} - Inferred return type {} of method {} does not conform to declared return type {}.",
+        diagnostics.error(
+            ErrorCode::TypeMismatch,
             file_name,
-            line_no,
-            expr.static_type(),
-            method_name,
-            return_type
-        ));
+            span,
+            format!(
+                "Inferred return type {} of method {} does not conform to declared return type {}.",
+                expr.static_type(),
+                method_name,
+                return_type
+            ),
+        );
     }
 
     object_types.exit_scope();
 
-    Ok(Method {
-        line_no,
+    Method {
+        span,
         formals,
         return_type,
         expr,
-    })
+    }
 }
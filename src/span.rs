@@ -0,0 +1,36 @@
+// Identifies a region of a source file
+// lo and hi are byte offsets into the file (hi is exclusive).
+// line_no and col are the position of lo (both start at 1).
+
+use std::cmp::{max, min};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Span {
+    pub lo: u32,
+    pub hi: u32,
+    pub line_no: u32,
+    pub col: u32,
+}
+
+impl Span {
+    pub fn new(lo: u32, hi: u32, line_no: u32, col: u32) -> Span {
+        Span {
+            lo,
+            hi,
+            line_no,
+            col,
+        }
+    }
+
+    // The smallest span that covers both spans
+    pub fn to(self, other: Span) -> Span {
+        let start = if other.lo < self.lo { other } else { self };
+
+        Span {
+            lo: min(self.lo, other.lo),
+            hi: max(self.hi, other.hi),
+            line_no: start.line_no,
+            col: start.col,
+        }
+    }
+}
@@ -1,109 +1,203 @@
 // All of the tokens that are produced by the lexer
 
-// line_no is the line number where the token appears
+// span is the region of the source where the token appears
+
+use std::fmt;
+
+use crate::span::Span;
 
 pub enum Token {
     // Characters
-    Add { line_no: i16 },
-    Div { line_no: i16 },
-    Sub { line_no: i16 },
-    Mul { line_no: i16 },
-    Eq { line_no: i16 },
-    LT { line_no: i16 },
-    Dot { line_no: i16 },
-    Neg { line_no: i16 },
-    Comma { line_no: i16 },
-    SemiColon { line_no: i16 },
-    Colon { line_no: i16 },
-    OpenParen { line_no: i16 },
-    CloseParen { line_no: i16 },
-    At { line_no: i16 },
-    OpenBrace { line_no: i16 },
-    CloseBrace { line_no: i16 },
+    Add { span: Span },
+    Div { span: Span },
+    Sub { span: Span },
+    Mul { span: Span },
+    Eq { span: Span },
+    LT { span: Span },
+    Dot { span: Span },
+    Neg { span: Span },
+    Comma { span: Span },
+    SemiColon { span: Span },
+    Colon { span: Span },
+    OpenParen { span: Span },
+    CloseParen { span: Span },
+    At { span: Span },
+    OpenBrace { span: Span },
+    CloseBrace { span: Span },
 
     // Key words
-    Class { line_no: i16 },
-    Else { line_no: i16 },
-    Fi { line_no: i16 },
-    If { line_no: i16 },
-    In { line_no: i16 },
-    Inherits { line_no: i16 },
-    Let { line_no: i16 },
-    Loop { line_no: i16 },
-    Pool { line_no: i16 },
-    Then { line_no: i16 },
-    While { line_no: i16 },
-    Case { line_no: i16 },
-    Esac { line_no: i16 },
-    Of { line_no: i16 },
-    New { line_no: i16 },
-    IsVoid { line_no: i16 },
-    Not { line_no: i16 },
+    Class { span: Span },
+    Else { span: Span },
+    Fi { span: Span },
+    If { span: Span },
+    In { span: Span },
+    Inherits { span: Span },
+    Let { span: Span },
+    Loop { span: Span },
+    Pool { span: Span },
+    Then { span: Span },
+    While { span: Span },
+    Case { span: Span },
+    Esac { span: Span },
+    Of { span: Span },
+    New { span: Span },
+    IsVoid { span: Span },
+    Not { span: Span },
 
     // Character sets
-    DArrow { line_no: i16 }, // =>
-    LEq { line_no: i16 },    // <=
-    Assign { line_no: i16 }, // <-
+    DArrow { span: Span }, // =>
+    LEq { span: Span },    // <=
+    Assign { span: Span }, // <-
 
     // Constants
-    BoolConst { line_no: i16, val: bool },
-    IntConst { line_no: i16, val: u32 },
-    StrConst { line_no: i16, val: String },
+    BoolConst { span: Span, val: bool },
+    IntConst { span: Span, val: u32 },
+    StrConst { span: Span, val: String },
 
     // IDs
-    TypeID { line_no: i16, type_name: String },
-    ObjectID { line_no: i16, obj_name: String },
+    TypeID { span: Span, type_name: String },
+    ObjectID { span: Span, obj_name: String },
 
     // Placed at the end of all file outputs
     // Needed for the parser
-    End { line_no: i16 },
+    End { span: Span },
+}
+
+// A comment in the source
+// Comments are not passed to the parser, but they are kept for tools that
+// rewrite the source (e.g. the formatter). They are a separate list rather than
+// trivia on the tokens since the AST does not keep tokens, only their spans.
+pub struct Comment {
+    pub span: Span,
+    pub text: String, // Including the delimiters
 }
 
 impl Token {
-    pub fn get_line_no(&self) -> i16 {
+    pub fn get_span(&self) -> Span {
         match self {
-            Token::Add { line_no }
-            | Token::Div { line_no }
-            | Token::Sub { line_no }
-            | Token::Mul { line_no }
-            | Token::Eq { line_no }
-            | Token::LT { line_no }
-            | Token::Dot { line_no }
-            | Token::Neg { line_no }
-            | Token::Comma { line_no }
-            | Token::SemiColon { line_no }
-            | Token::Colon { line_no }
-            | Token::OpenParen { line_no }
-            | Token::CloseParen { line_no }
-            | Token::At { line_no }
-            | Token::OpenBrace { line_no }
-            | Token::CloseBrace { line_no }
-            | Token::Class { line_no }
-            | Token::Else { line_no }
-            | Token::Fi { line_no }
-            | Token::If { line_no }
-            | Token::In { line_no }
-            | Token::Inherits { line_no }
-            | Token::Let { line_no }
-            | Token::Loop { line_no }
-            | Token::Pool { line_no }
-            | Token::Then { line_no }
-            | Token::While { line_no }
-            | Token::Case { line_no }
-            | Token::Esac { line_no }
-            | Token::Of { line_no }
-            | Token::New { line_no }
-            | Token::IsVoid { line_no }
-            | Token::Not { line_no }
-            | Token::DArrow { line_no }
-            | Token::LEq { line_no }
-            | Token::Assign { line_no }
-            | Token::BoolConst { line_no, .. }
-            | Token::TypeID { line_no, .. }
-            | Token::ObjectID { line_no, .. }
-            | Token::IntConst { line_no, .. }
-            | Token::StrConst { line_no, .. }
-            | Token::End { line_no } => *line_no,
+            Token::Add { span }
+            | Token::Div { span }
+            | Token::Sub { span }
+            | Token::Mul { span }
+            | Token::Eq { span }
+            | Token::LT { span }
+            | Token::Dot { span }
+            | Token::Neg { span }
+            | Token::Comma { span }
+            | Token::SemiColon { span }
+            | Token::Colon { span }
+            | Token::OpenParen { span }
+            | Token::CloseParen { span }
+            | Token::At { span }
+            | Token::OpenBrace { span }
+            | Token::CloseBrace { span }
+            | Token::Class { span }
+            | Token::Else { span }
+            | Token::Fi { span }
+            | Token::If { span }
+            | Token::In { span }
+            | Token::Inherits { span }
+            | Token::Let { span }
+            | Token::Loop { span }
+            | Token::Pool { span }
+            | Token::Then { span }
+            | Token::While { span }
+            | Token::Case { span }
+            | Token::Esac { span }
+            | Token::Of { span }
+            | Token::New { span }
+            | Token::IsVoid { span }
+            | Token::Not { span }
+            | Token::DArrow { span }
+            | Token::LEq { span }
+            | Token::Assign { span }
+            | Token::BoolConst { span, .. }
+            | Token::TypeID { span, .. }
+            | Token::ObjectID { span, .. }
+            | Token::IntConst { span, .. }
+            | Token::StrConst { span, .. }
+            | Token::End { span } => *span,
+        }
+    }
+}
+
+// Quote a string constant the way the reference tools do
+// Unprintable characters are written as octal escapes.
+pub fn escape_str(s: &str) -> String {
+    let mut escaped = String::from("\"");
+
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\x08' => escaped.push_str("\\b"),
+            '\x0c' => escaped.push_str("\\f"),
+            c if c.is_ascii_graphic() || c == ' ' => escaped.push(c),
+            c => escaped.push_str(&format!("\\{:03o}", c as u32)),
         }
     }
+
+    escaped.push('"');
+    escaped
+}
+
+// The token name and value in the format of the reference lexer (e.g. TYPEID Main)
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Add { .. } => write!(f, "'+'"),
+            Token::Div { .. } => write!(f, "'/'"),
+            Token::Sub { .. } => write!(f, "'-'"),
+            Token::Mul { .. } => write!(f, "'*'"),
+            Token::Eq { .. } => write!(f, "'='"),
+            Token::LT { .. } => write!(f, "'<'"),
+            Token::Dot { .. } => write!(f, "'.'"),
+            Token::Neg { .. } => write!(f, "'~'"),
+            Token::Comma { .. } => write!(f, "','"),
+            Token::SemiColon { .. } => write!(f, "';'"),
+            Token::Colon { .. } => write!(f, "':'"),
+            Token::OpenParen { .. } => write!(f, "'('"),
+            Token::CloseParen { .. } => write!(f, "')'"),
+            Token::At { .. } => write!(f, "'@'"),
+            Token::OpenBrace { .. } => write!(f, "'{{'"),
+            Token::CloseBrace { .. } => write!(f, "'}}'"),
+            Token::Class { .. } => write!(f, "CLASS"),
+            Token::Else { .. } => write!(f, "ELSE"),
+            Token::Fi { .. } => write!(f, "FI"),
+            Token::If { .. } => write!(f, "IF"),
+            Token::In { .. } => write!(f, "IN"),
+            Token::Inherits { .. } => write!(f, "INHERITS"),
+            Token::Let { .. } => write!(f, "LET"),
+            Token::Loop { .. } => write!(f, "LOOP"),
+            Token::Pool { .. } => write!(f, "POOL"),
+            Token::Then { .. } => write!(f, "THEN"),
+            Token::While { .. } => write!(f, "WHILE"),
+            Token::Case { .. } => write!(f, "CASE"),
+            Token::Esac { .. } => write!(f, "ESAC"),
+            Token::Of { .. } => write!(f, "OF"),
+            Token::New { .. } => write!(f, "NEW"),
+            Token::IsVoid { .. } => write!(f, "ISVOID"),
+            Token::Not { .. } => write!(f, "NOT"),
+            Token::DArrow { .. } => write!(f, "DARROW"),
+            Token::LEq { .. } => write!(f, "LE"),
+            Token::Assign { .. } => write!(f, "ASSIGN"),
+            Token::BoolConst { val, .. } => write!(f, "BOOL_CONST {}", val),
+            Token::IntConst { val, .. } => write!(f, "INT_CONST {}", val),
+            Token::StrConst { val, .. } => write!(f, "STR_CONST {}", escape_str(val)),
+            Token::TypeID { type_name, .. } => write!(f, "TYPEID {}", type_name),
+            Token::ObjectID { obj_name, .. } => write!(f, "OBJECTID {}", obj_name),
+            Token::End { .. } => write!(f, "EOF"),
+        }
+    }
+}
+
+// An error in the format of the reference lexer (e.g. ERROR "Unmatched *)")
+pub struct ErrorToken<'a>(pub &'a str);
+
+impl fmt::Display for ErrorToken<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ERROR {}", escape_str(self.0))
+    }
 }
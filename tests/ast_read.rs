@@ -0,0 +1,76 @@
+mod common;
+
+use common::{coolc, stderr, stdout};
+
+const PROGRAM: &str = r#"class Main inherits IO {
+  x : Int <- 3;
+  f(n : Int) : SELF_TYPE { { x <- x + n; self; } };
+  main() : Object {
+    case self@IO.out_string("a\n") of
+      o : Main => o.f(1).out_int(x);
+      o : Object => abort();
+    esac
+  };
+};
+"#;
+
+// The assembly for a program read in the given format
+fn assembly(args: &[&str], input: &str) -> String {
+    let output = coolc(&[args, &["-o", "-", "-"]].concat(), input);
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    stdout(&output)
+}
+
+#[test]
+fn dumped_ast_compiles_to_the_same_assembly() {
+    let ast = stdout(&coolc(&["--dump-ast", "-"], PROGRAM));
+
+    assert_eq!(
+        assembly(&["--input-format=ast"], &ast),
+        assembly(&[], PROGRAM)
+    );
+}
+
+// semant sets the types again
+#[test]
+fn static_types_are_ignored() {
+    let typed_ast = stdout(&coolc(&["--dump-typed-ast", "-"], PROGRAM));
+    let wrong_types: String = typed_ast
+        .lines()
+        .map(|line| {
+            if line.trim_start().starts_with(": ") {
+                "  : Bool\n".to_string()
+            } else {
+                format!("{}\n", line)
+            }
+        })
+        .collect();
+
+    assert!(typed_ast.contains(": SELF_TYPE"));
+    assert_eq!(
+        assembly(&["--input-format=ast"], &wrong_types),
+        assembly(&[], PROGRAM)
+    );
+}
+
+#[test]
+fn malformed_ast_is_reported_at_its_line() {
+    let output = coolc(
+        &["--color=never", "--input-format=ast", "-"],
+        "#1\n_program\n  #1\n  _class\n    Main\n",
+    );
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stderr(&output),
+        "error[E0052]: Malformed AST: expected a type
+ --> <stdin>:5:9
+  |
+5 |     Main
+  |         ^
+
+Compilation halted due to errors.
+"
+    );
+}
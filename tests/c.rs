@@ -0,0 +1,108 @@
+mod common;
+
+use std::fs;
+use std::thread;
+
+use common::{cc_run, coolc_in, has_cc, stderr, stdout, temp_dir};
+
+const NAME: &str = "test.cl"; // The name of the source in runtime errors
+
+// The program builds without warnings, and it writes what coolc run writes
+// expected is the end of the output and errors.
+fn check(text: &str, input: &str, expected: &str) {
+    if !has_cc() {
+        eprintln!("Skipped: there is no C compiler");
+        return;
+    }
+
+    let dir = temp_dir(&format!("c_{}", thread::current().name().unwrap()));
+    fs::write(dir.join(NAME), text).unwrap();
+
+    let compiled = coolc_in(&dir, &["--target=c", NAME], "");
+    assert!(compiled.status.success(), "{}", stderr(&compiled));
+
+    let native = cc_run(
+        &dir,
+        &[
+            "-Wall",
+            "-Wextra",
+            "-std=c99",
+            "-pedantic",
+            "-Werror",
+            "test.c",
+        ],
+        input,
+    );
+    let mips = coolc_in(&dir, &["run", NAME], input);
+    let (output, errors) = (stdout(&native), stderr(&native));
+
+    assert_eq!(output, stdout(&mips));
+    assert_eq!(errors, stderr(&mips));
+    assert_eq!(native.status.code(), mips.status.code());
+    assert!(
+        format!("{}{}", output, errors).ends_with(expected),
+        "{}{}",
+        output,
+        errors
+    );
+}
+
+#[test]
+fn program_reads_input_and_writes_output() {
+    check(
+        "class Main inherits IO {
+  main() : Object {
+    let name : String <- in_string(), n : Int <- in_int() in
+      out_string(\"Hello, \".concat(name).concat(\"\\n\")).out_int(n * 2).out_string(\"\\n\")
+  };
+};
+",
+        "COOL\n21\n",
+        "Hello, COOL\n42\n\nCOOL program successfully executed\n",
+    );
+}
+
+#[test]
+fn dispatch_to_void() {
+    check(
+        "class Main {\n  x : Main;\n  main() : Object { x.main() };\n};\n",
+        "",
+        "test.cl:3: Dispatch to void.\n",
+    );
+}
+
+#[test]
+fn case_without_matching_branch() {
+    check(
+        "class Main {\n  main() : Object { case 1 of s : String => s; esac };\n};\n",
+        "",
+        "No match in case statement for Class Int\n",
+    );
+}
+
+#[test]
+fn division_by_zero() {
+    check(
+        "class Main {\n  main() : Object { 1 / 0 };\n};\n",
+        "",
+        "Division by zero\n",
+    );
+}
+
+#[test]
+fn substr_out_of_range() {
+    check(
+        "class Main {\n  main() : Object { \"abc\".substr(2, 5) };\n};\n",
+        "",
+        "Length to substr too long\nExecution aborted.\n",
+    );
+}
+
+#[test]
+fn stack_overflow() {
+    check(
+        "class Main {\n  f(n : Int) : Int { f(n + 1) };\n  main() : Object { f(0) };\n};\n",
+        "",
+        "Stack overflow\n",
+    );
+}
@@ -0,0 +1,28 @@
+mod common;
+
+use common::{coolc, stdout};
+
+// new SELF_TYPE in an inherited method creates an object of the class of self,
+// even when the last value computed (in $a0) is an object of another class
+#[test]
+fn new_self_type_uses_class_of_self() {
+    let source = "
+class A inherits IO {
+  make(o : Object) : SELF_TYPE { { o; new SELF_TYPE; } };
+  name() : String { \"A\" };
+};
+class B inherits A {
+  name() : String { \"B\" };
+};
+class Main inherits IO {
+  main() : Object { out_string((new B).make(new A).name().concat(\"\\n\")) };
+};
+";
+
+    for target in ["mips", "riscv32"] {
+        let output = coolc(&["run", &format!("--target={}", target), "-"], source);
+
+        assert!(output.status.success());
+        assert!(stdout(&output).starts_with("B\n"), "{}", target);
+    }
+}
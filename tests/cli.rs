@@ -0,0 +1,126 @@
+mod common;
+
+use std::fs;
+
+use common::{coolc, coolc_in, stderr, stdout, temp_dir};
+
+const TYPE_ERRORS: &str = "class Main inherits IO {
+  main() : Object { out_strin(\"x\") };
+  x : Int <- \"a\";
+  y : Foo;
+  z : Int <- 1 + true;
+};
+";
+
+#[test]
+fn every_error_is_reported() {
+    let output = coolc(&["--color=never", "-"], TYPE_ERRORS);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stderr(&output).matches("error[").count(), 4);
+    assert!(stderr(&output).ends_with("Compilation halted due to errors.\n"));
+}
+
+#[test]
+fn max_errors_limits_the_errors_reported() {
+    let output = coolc(&["--color=never", "--max-errors=2", "-"], TYPE_ERRORS);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stderr(&output).matches("error[").count(), 2);
+    assert!(stderr(&output).ends_with("Too many errors. Compilation halted.\n"));
+}
+
+const PROGRAM: &str = "class Main { main() : Object { 0 }; };\n";
+
+// -o - writes only the output to standard output, and no file
+#[test]
+fn dash_output_writes_to_standard_output() {
+    let dir = temp_dir("dash_output_writes_to_standard_output");
+    let output = coolc_in(&dir, &["-o", "-", "-"], PROGRAM);
+
+    assert!(output.status.success());
+    assert!(stdout(&output).contains("Main.main:"));
+    assert_eq!(stderr(&output), "");
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+}
+
+// Binary output is written unchanged
+#[test]
+fn dash_output_writes_bytecode_unchanged() {
+    let dir = temp_dir("dash_output_writes_bytecode_unchanged");
+    let output = coolc_in(&dir, &["--target=bytecode", "-o", "-", "-"], PROGRAM);
+    let written = coolc_in(
+        &dir,
+        &["--target=bytecode", "-o", "main.coolbc", "-"],
+        PROGRAM,
+    );
+
+    assert!(output.status.success() && written.status.success());
+    assert_eq!(output.stdout, fs::read(dir.join("main.coolbc")).unwrap());
+}
+
+// Runtime errors name the source by the path given on the command line
+#[test]
+fn runtime_errors_use_the_path_given() {
+    let dir = temp_dir("runtime_errors_use_the_path_given");
+    fs::create_dir(dir.join("sub")).unwrap();
+    fs::write(
+        dir.join("sub/e1.cl"),
+        "class Main {\n  main() : Object { (let x : Main in x).main() };\n};\n",
+    )
+    .unwrap();
+    let output = coolc_in(&dir, &["run", "sub/e1.cl"], "");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).ends_with("sub/e1.cl:2: Dispatch to void.\n"));
+}
+
+// --parse-only and --check-only write nothing and print nothing unless there are errors
+#[test]
+fn stopping_early_writes_no_output() {
+    let dir = temp_dir("stopping_early_writes_no_output");
+    fs::write(dir.join("main.cl"), PROGRAM).unwrap();
+    fs::write(
+        dir.join("syntax.cl"),
+        "class Main { main() : Object { ( }; };\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("types.cl"),
+        "class Main { main() : Object { x }; };\n",
+    )
+    .unwrap();
+
+    for option in ["--parse-only", "--check-only"] {
+        let output = coolc_in(&dir, &[option, "main.cl"], "");
+
+        assert!(output.status.success(), "{}", option);
+        assert_eq!(
+            (stdout(&output), stderr(&output)),
+            (String::new(), String::new())
+        );
+
+        let output = coolc_in(&dir, &["--color=never", option, "syntax.cl"], "");
+
+        assert_eq!(output.status.code(), Some(1), "{}", option);
+        assert!(stderr(&output).starts_with("error[E0050]: Syntax error\n"));
+    }
+
+    // Types are only checked by --check-only
+    assert!(coolc_in(&dir, &["--parse-only", "types.cl"], "")
+        .status
+        .success());
+
+    let output = coolc_in(&dir, &["--color=never", "--check-only", "types.cl"], "");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).starts_with("error[E0301]: Undeclared identifier x.\n"));
+
+    let mut files: Vec<String> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    files.sort();
+
+    assert_eq!(files, ["main.cl", "syntax.cl", "types.cl"]);
+}
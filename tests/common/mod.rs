@@ -0,0 +1,110 @@
+// Helpers for the tests that run coolc
+#![allow(dead_code)] // Each test crate uses only some of them
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::thread;
+
+// Run coolc with the arguments and the input on its standard input
+pub fn coolc(args: &[&str], input: &str) -> Output {
+    coolc_in(Path::new("."), args, input)
+}
+
+// Run coolc in the directory dir (e.g. to check the files it writes)
+pub fn coolc_in(dir: &Path, args: &[&str], input: &str) -> Output {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_coolc"));
+
+    command.current_dir(dir).args(args);
+    run(command, input)
+}
+
+// Run a command with the input on its standard input
+pub fn run(mut command: Command, input: &str) -> Output {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // The input is written by another thread so that a large input cannot block the output
+    let mut stdin = child.stdin.take().unwrap();
+    let input = input.to_string();
+    let writer = thread::spawn(move || {
+        // The command may exit without reading all of it
+        let _ = stdin.write_all(input.as_bytes());
+    });
+
+    let output = child.wait_with_output().unwrap();
+    writer.join().unwrap();
+
+    output
+}
+
+// Whether there is a C compiler (cc) to build the output of the native targets
+// The tests that need one are skipped otherwise.
+pub fn has_cc() -> bool {
+    Command::new("cc")
+        .arg("--version")
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+// The path of a file in runtime/ (e.g. to link with the output of --target=x86-64)
+pub fn runtime(name: &str) -> String {
+    format!("{}/runtime/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+// Build a program in dir with cc and the arguments, and run it with the input
+pub fn cc_run(dir: &Path, cc_args: &[&str], input: &str) -> Output {
+    let built = Command::new("cc")
+        .current_dir(dir)
+        .args(cc_args)
+        .args(["-o", "program"])
+        .output()
+        .unwrap();
+
+    assert!(built.status.success(), "{}", stderr(&built));
+
+    let mut command = Command::new(dir.join("program"));
+
+    command.current_dir(dir);
+    run(command, input)
+}
+
+pub fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+pub fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+// The static type of the first node of a kind (e.g. "_cond") in the output of --dump-typed-ast
+// The type follows the children of the node at the same indentation.
+pub fn static_type<'a>(dump: &'a str, kind: &str) -> &'a str {
+    let lines: Vec<&str> = dump.lines().collect();
+    let pos = lines
+        .iter()
+        .position(|line| line.trim() == kind)
+        .unwrap_or_else(|| panic!("No {} in\n{}", kind, dump));
+    let indent = lines[pos].len() - lines[pos].trim_start().len();
+
+    lines[pos + 1..]
+        .iter()
+        .find(|line| line.len() - line.trim_start().len() == indent && line.trim().starts_with(':'))
+        .map(|line| line.trim().trim_start_matches(": "))
+        .unwrap()
+}
+
+// An empty directory for the files written by a test
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    dir
+}
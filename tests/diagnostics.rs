@@ -0,0 +1,240 @@
+use coolc::diagnostics::{Diagnostics, ErrorCode};
+use coolc::{compile, CompileOptions, Source, Stage};
+
+// Check a program (without generating code) and return the errors
+fn check(text: &str, max_errors: usize) -> Diagnostics {
+    let options = CompileOptions {
+        max_errors,
+        stop_after: Stage::Check,
+        ..CompileOptions::default()
+    };
+
+    compile(
+        &[Source {
+            name: "test.cl",
+            text,
+        }],
+        &options,
+    )
+    .unwrap()
+    .diagnostics
+}
+
+// The code and line of each error
+fn errors(diagnostics: &Diagnostics) -> Vec<(ErrorCode, u32)> {
+    diagnostics
+        .diagnostics()
+        .iter()
+        .map(|diagnostic| {
+            (
+                diagnostic.code,
+                diagnostic.loc.as_ref().unwrap().span.line_no,
+            )
+        })
+        .collect()
+}
+
+// A parenthesis left open does not make recovery skip the end of the class
+#[test]
+fn syntax_errors_after_unclosed_paren_are_each_reported_once() {
+    let diagnostics = check(
+        "class A { f() : Int { 1 + }; g() : Int { 2 }; };
+class B { h() : Int { ( }; };
+class Main { main() : Object { 0 }; x : Int <- ; };
+",
+        0,
+    );
+
+    assert_eq!(
+        errors(&diagnostics),
+        vec![
+            (ErrorCode::SyntaxError, 1),
+            (ErrorCode::SyntaxError, 2),
+            (ErrorCode::SyntaxError, 3)
+        ]
+    );
+}
+
+// The lexer skips a bad character and the rest of the file is still parsed
+#[test]
+fn lexer_reports_every_bad_character() {
+    let diagnostics = check(
+        "class Main { main() : Object { 1 # + 2 }; x : Int <- 3 $; };\n",
+        0,
+    );
+
+    assert_eq!(
+        errors(&diagnostics),
+        vec![
+            (ErrorCode::UnexpectedChar, 1),
+            (ErrorCode::UnexpectedChar, 1)
+        ]
+    );
+}
+
+// The parser recovers at ';' within a block
+#[test]
+fn parser_recovers_within_a_block() {
+    let diagnostics = check(
+        "class Main {
+  main() : Object { { 1 + ; 2 * ; 3; } };
+};
+",
+        0,
+    );
+
+    assert_eq!(
+        errors(&diagnostics),
+        vec![(ErrorCode::SyntaxError, 2), (ErrorCode::SyntaxError, 2)]
+    );
+}
+
+// Type checking continues after an error
+#[test]
+fn semant_reports_every_type_error() {
+    let diagnostics = check(
+        "class Main inherits IO {
+  main() : Object { out_strin(\"x\") };
+  x : Int <- \"a\";
+  y : Foo;
+  z : Int <- 1 + true;
+};
+",
+        0,
+    );
+
+    let mut errors = errors(&diagnostics);
+    errors.sort_by_key(|(_, line_no)| *line_no);
+
+    assert_eq!(
+        errors,
+        vec![
+            (ErrorCode::UndefinedMethod, 2),
+            (ErrorCode::TypeMismatch, 3),
+            (ErrorCode::UndefinedClass, 4),
+            (ErrorCode::NonIntOperand, 5)
+        ]
+    );
+}
+
+// An expression with an error does not cause more errors in the expressions around it
+#[test]
+fn semant_errors_do_not_cascade() {
+    let diagnostics = check(
+        "class Main {
+  main() : Object { 0 };
+  z : Int <- undefined.bar() + 2;
+};
+",
+        0,
+    );
+
+    assert_eq!(
+        errors(&diagnostics),
+        vec![(ErrorCode::UndeclaredIdentifier, 3)]
+    );
+}
+
+// No more errors than the limit are recorded
+#[test]
+fn errors_stop_at_the_limit() {
+    let text = "class Main inherits IO {
+  main() : Object { out_strin(\"x\") };
+  x : Int <- \"a\";
+  y : Foo;
+  z : Int <- 1 + true;
+};
+";
+
+    let diagnostics = check(text, 2);
+    assert_eq!(diagnostics.error_count(), 2);
+    assert!(diagnostics.limit_reached());
+
+    let diagnostics = check(text, 0);
+    assert_eq!(diagnostics.error_count(), 4);
+    assert!(!diagnostics.limit_reached());
+}
+
+// Each kind of error has its own code
+#[test]
+fn errors_have_stable_codes() {
+    let main = "class Main { main() : Object { 0 }; };\n";
+    let cases = [
+        ("class A { x : Int <- 4294967296; };", "E0001"),
+        ("class A { x : Int; }; *)", "E0002"),
+        ("class A { x : Int; }; #", "E0003"),
+        ("class A { x : String <- \"a\0b\"; };", "E0004"),
+        ("class A { }; \"a\n", "E0005"),
+        ("class A { }; \u{e9}", "E0006"),
+        ("class A { x : Int; }; (* ", "E0007"),
+        ("class A { }; \"a", "E0008"),
+        ("class A { x : Int <- ; };", "E0050"),
+        ("class A { }; class A { };", "E0100"),
+        ("class A inherits Int { };", "E0101"),
+        ("class A inherits B { };", "E0102"),
+        ("class A inherits B { }; class B inherits A { };", "E0103"),
+        ("class A { f() : Int { 0 }; f() : Int { 1 }; };", "E0200"),
+        ("class A { x : Int; x : Int; };", "E0201"),
+        ("class A { f(a : Int, a : Int) : Int { 0 }; };", "E0202"),
+        (
+            "class A { f() : Int { 0 }; }; class B inherits A { f() : Bool { true }; };",
+            "E0203",
+        ),
+        ("class A { f(a : SELF_TYPE) : Int { 0 }; };", "E0204"),
+        ("class A { self : Int; };", "E0205"),
+        ("class A { x : Int <- \"a\"; };", "E0300"),
+        ("class A { x : Int <- y; };", "E0301"),
+        ("class A { x : Int <- f(); };", "E0302"),
+        ("class A { f(a : Int) : Int { f() }; };", "E0303"),
+        ("class A { x : Object <- while 1 loop 0 pool; };", "E0304"),
+        ("class A { x : Int <- 1 + \"a\"; };", "E0305"),
+        ("class A { x : Bool <- 1 = \"a\"; };", "E0306"),
+        (
+            "class A { x : Int <- case 0 of a : Int => 0; b : Int => 1; esac; };",
+            "E0307",
+        ),
+    ];
+
+    for (text, code) in cases.iter() {
+        let diagnostics = check(&format!("{}{}", main, text), 0);
+        let mut codes: Vec<&str> = diagnostics
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| diagnostic.code.as_str())
+            .collect();
+        codes.dedup(); // Every class in a cycle is reported
+
+        assert_eq!(codes, vec![*code], "{}", text);
+    }
+
+    let diagnostics = check("class A { };\n", 0);
+    assert_eq!(diagnostics.diagnostics()[0].code.as_str(), "E0104");
+    assert!(diagnostics.diagnostics()[0].loc.is_none());
+
+    let diagnostics = check("class Main { main(x : Int) : Object { 0 }; };\n", 0);
+    assert_eq!(diagnostics.diagnostics()[0].code.as_str(), "E0105");
+}
+
+// A class defined twice has a note that points at the first definition
+#[test]
+fn redefinition_has_a_note() {
+    let diagnostics = check(
+        "class Main { main() : Object { 0 }; };
+class A { };
+class A { };
+",
+        0,
+    );
+
+    let diagnostic = &diagnostics.diagnostics()[0];
+    assert_eq!(diagnostic.code, ErrorCode::ClassRedefined);
+    assert_eq!(diagnostic.loc.as_ref().unwrap().span.line_no, 3);
+    assert_eq!(diagnostic.notes.len(), 1);
+    assert_eq!(diagnostic.notes[0].loc.as_ref().unwrap().span.line_no, 2);
+
+    assert_eq!(
+        diagnostic.to_string(),
+        "test.cl : 3 - error[E0100]: Class A was previously defined.
+test.cl : 2 - note: Class A previously defined here."
+    );
+}
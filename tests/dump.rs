@@ -0,0 +1,270 @@
+mod common;
+
+use std::fs;
+
+use common::{coolc, coolc_in, stdout, temp_dir};
+
+// No initializers, a static dispatch and a case
+const AST_TEXT: &str = r#"class Main inherits IO {
+  x : Int;
+  main() : Object {
+    let y : Int in
+      case self@IO.out_string("a\n") of o : Object => x <- y + 1; esac
+  };
+};
+"#;
+
+// String escapes are written back, and errors are ERROR tokens
+#[test]
+fn dump_tokens_matches_the_reference_lexer() {
+    let output = coolc(
+        &["--dump-tokens", "-"],
+        r#"class Main {
+  s : String <- "tab\there\nnew \"q\" \\";
+  i : Int <- 007 + x;
+  b : Bool <- true;
+  (* c *) f() : Object { if not isvoid self then 1 <= 2 else ~3 fi };
+  e : Int <- 1 # 2;
+};
+"unterminated
+x
+"#,
+    );
+
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        r##"#name "<stdin>"
+#1 CLASS
+#1 TYPEID Main
+#1 '{'
+#2 OBJECTID s
+#2 ':'
+#2 TYPEID String
+#2 ASSIGN
+#2 STR_CONST "tab\there\nnew \"q\" \\"
+#2 ';'
+#3 OBJECTID i
+#3 ':'
+#3 TYPEID Int
+#3 ASSIGN
+#3 INT_CONST 007
+#3 '+'
+#3 OBJECTID x
+#3 ';'
+#4 OBJECTID b
+#4 ':'
+#4 TYPEID Bool
+#4 ASSIGN
+#4 BOOL_CONST true
+#4 ';'
+#5 OBJECTID f
+#5 '('
+#5 ')'
+#5 ':'
+#5 TYPEID Object
+#5 '{'
+#5 IF
+#5 NOT
+#5 ISVOID
+#5 OBJECTID self
+#5 THEN
+#5 INT_CONST 1
+#5 LE
+#5 INT_CONST 2
+#5 ELSE
+#5 '~'
+#5 INT_CONST 3
+#5 FI
+#5 '}'
+#5 ';'
+#6 OBJECTID e
+#6 ':'
+#6 TYPEID Int
+#6 ASSIGN
+#6 INT_CONST 1
+#6 ERROR "#"
+#6 INT_CONST 2
+#6 ';'
+#7 '}'
+#7 ';'
+#8 ERROR "Unterminated string constant"
+#9 OBJECTID x
+"##
+    );
+}
+
+// Missing expressions are _no_expr, and nothing has a type before checking
+#[test]
+fn dump_ast_matches_the_reference_parser() {
+    let output = coolc(&["--dump-ast", "-"], AST_TEXT);
+
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        r#"#1
+_program
+  #1
+  _class
+    Main
+    IO
+    "<stdin>"
+    (
+    #2
+    _attr
+      x
+      Int
+      #2
+      _no_expr
+      : _no_type
+    #3
+    _method
+      main
+      Object
+      #4
+      _let
+        y
+        Int
+        #4
+        _no_expr
+        : _no_type
+        #5
+        _typcase
+          #5
+          _static_dispatch
+            #5
+            _object
+              self
+            : _no_type
+            IO
+            out_string
+            (
+            #5
+            _string
+              "a\n"
+            : _no_type
+            )
+          : _no_type
+          #5
+          _branch
+            o
+            Object
+            #5
+            _assign
+              x
+              #5
+              _plus
+                #5
+                _object
+                  y
+                : _no_type
+                #5
+                _int
+                  1
+                : _no_type
+              : _no_type
+            : _no_type
+        : _no_type
+      : _no_type
+    )
+"#
+    );
+}
+
+#[test]
+fn dump_typed_ast_matches_the_reference_checker() {
+    let output = coolc(&["--dump-typed-ast", "-"], AST_TEXT);
+
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        r#"#1
+_program
+  #1
+  _class
+    Main
+    IO
+    "<stdin>"
+    (
+    #2
+    _attr
+      x
+      Int
+      #2
+      _no_expr
+      : _no_type
+    #3
+    _method
+      main
+      Object
+      #4
+      _let
+        y
+        Int
+        #4
+        _no_expr
+        : _no_type
+        #5
+        _typcase
+          #5
+          _static_dispatch
+            #5
+            _object
+              self
+            : SELF_TYPE
+            IO
+            out_string
+            (
+            #5
+            _string
+              "a\n"
+            : String
+            )
+          : SELF_TYPE
+          #5
+          _branch
+            o
+            Object
+            #5
+            _assign
+              x
+              #5
+              _plus
+                #5
+                _object
+                  y
+                : Int
+                #5
+                _int
+                  1
+                : Int
+              : Int
+            : Int
+        : Int
+      : Int
+    )
+"#
+    );
+}
+
+// Files are named by the path given, and integers of any size are tokens
+#[test]
+fn dumps_name_files_by_the_path_given() {
+    let dir = temp_dir("dumps_name_files_by_the_path_given");
+    fs::create_dir(dir.join("sub")).unwrap();
+    fs::write(dir.join("sub/big.cl"), "x <- 99999999999\n").unwrap();
+    fs::write(dir.join("sub/main.cl"), AST_TEXT).unwrap();
+
+    let tokens = coolc_in(&dir, &["--dump-tokens", "sub/big.cl"], "");
+
+    assert!(tokens.status.success());
+    assert_eq!(
+        stdout(&tokens),
+        "#name \"sub/big.cl\"\n#1 OBJECTID x\n#1 ASSIGN\n#1 INT_CONST 99999999999\n"
+    );
+
+    let ast = coolc_in(&dir, &["--dump-ast", "sub/main.cl"], "");
+
+    assert!(ast.status.success());
+    assert!(stdout(&ast).contains("    IO\n    \"sub/main.cl\"\n"));
+}
@@ -0,0 +1,140 @@
+mod common;
+
+use common::{coolc, stderr, stdout};
+use coolc::diagnostics::Diagnostics;
+use coolc::lexer::tokenize_with_comments;
+
+fn format(text: &str) -> String {
+    let output = coolc(&["fmt", "-"], text);
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    stdout(&output)
+}
+
+// A comment after code on the same line stays after that code
+#[test]
+fn trailing_comment_is_not_moved_to_an_earlier_line() {
+    assert_eq!(
+        format(
+            "class Main inherits IO {
+  main():Object{ let x : Int <- 1 in if x = 1 then out_int(x) else -- inner
+    abort() fi };
+};
+"
+        ),
+        "class Main inherits IO {
+    main() : Object {
+        let x : Int <- 1
+        in
+            if x = 1 then
+                out_int(x)
+            else -- inner
+                abort()
+            fi
+    };
+};
+"
+    );
+}
+
+// A comment before a closing keyword stays inside the expression
+#[test]
+fn comment_before_closing_keyword_stays_before_it() {
+    assert_eq!(
+        format(
+            "class Main {
+  main() : Object { if true then 1 else 2
+  (* before end *) fi };
+};
+"
+        ),
+        "class Main {
+    main() : Object {
+        if true then
+            1
+        else
+            2
+        (* before end *)
+        fi
+    };
+};
+"
+    );
+}
+
+// A comment at the end of a line only follows the code before it
+#[test]
+fn trailing_comment_stays_between_features_on_one_line() {
+    assert_eq!(
+        format(
+            "class Main {
+  main() : Object { 0 }; (* after main *) f() : Object { 1 }; (* after f *)
+};
+"
+        ),
+        "class Main {
+    main() : Object {
+        0
+    }; (* after main *)
+
+    f() : Object {
+        1
+    }; (* after f *)
+};
+"
+    );
+}
+
+const COMMENTS: &str = r#"-- file header
+class A inherits Object { (* after brace *)
+  x : Int <- 1; -- after x
+  y : Int; z : Int; -- after z
+  f(a : Int) : Int { -- header
+    {
+      a <- 1 (* mid *) + 2; -- after stmt
+      (* own line *)
+      while a < 3 loop -- after loop
+        a <- a + 1
+        -- before pool
+      pool;
+      case a of -- after of
+        i : Int => i; -- first branch
+        -- between branches
+        o : Object => 0;
+        -- before esac
+      esac;
+      let b : Int <- 1, -- after b
+          (* before c *) c : Int
+          -- before in
+      in b + c; -- after let
+      x <- if a = 1 then 2 -- then trailing
+           else 3 fi;
+      if true then 1 else 2 fi; if false then 3 else 4 fi; -- after second if
+    } -- end of block
+    -- before method close
+  };
+  g() : Object { self }; (* after g *) h() : Object { self };
+  -- before class close
+}; -- after class
+class Main { main() : Object { new A }; }; -- trailing end
+-- end of file
+"#;
+
+#[test]
+fn formatting_is_idempotent() {
+    let formatted = format(COMMENTS);
+
+    assert_eq!(format(&formatted), formatted);
+}
+
+#[test]
+fn every_comment_is_kept_in_order() {
+    let comments = |text: &str| -> Vec<String> {
+        let mut diagnostics = Diagnostics::new(0);
+        let (_, comments) = tokenize_with_comments(text, "test.cl", &mut diagnostics);
+
+        comments.into_iter().map(|comment| comment.text).collect()
+    };
+
+    assert_eq!(comments(&format(COMMENTS)), comments(COMMENTS));
+}
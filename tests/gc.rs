@@ -0,0 +1,112 @@
+mod common;
+
+use common::{coolc, stdout};
+
+// An attribute written in _init and in main, and a local written in main
+const PROGRAM: &str = "class Main {
+  x : Object <- new Object;
+  main() : Object { let y : Object in { y <- x; x <- y; } };
+};
+";
+
+// The MIPS code for PROGRAM with the arguments
+fn assembly(args: &[&str]) -> String {
+    let output = coolc(&[args, &["-o", "-", "-"]].concat(), PROGRAM);
+
+    assert!(output.status.success());
+    stdout(&output)
+}
+
+// The lines of a routine (from its label to the next label)
+fn routine<'a>(assembly: &'a str, label: &str) -> Vec<&'a str> {
+    assembly
+        .lines()
+        .skip_while(|line| *line != format!("{}:", label))
+        .skip(1)
+        .take_while(|line| line.starts_with('\t'))
+        .map(str::trim)
+        .collect()
+}
+
+// The word after a label in the data segment
+fn word<'a>(assembly: &'a str, label: &str) -> &'a str {
+    routine(assembly, label)[0]
+}
+
+#[test]
+fn generational_records_attribute_writes() {
+    let assembly = assembly(&["--gc=generational"]);
+
+    // x is the first attribute (at offset 12)
+    for label in ["Main_init", "Main.main"] {
+        let code = routine(&assembly, label);
+        let store = code
+            .iter()
+            .position(|line| *line == "sw $a0 12($s0)")
+            .unwrap();
+
+        assert_eq!(
+            code[store + 1..store + 3],
+            ["addiu $a1 $s0 12", "jal _GenGC_Assign"],
+            "{}",
+            label
+        );
+    }
+
+    // Writes to locals are not recorded
+    assert_eq!(assembly.matches("jal _GenGC_Assign").count(), 2);
+}
+
+#[test]
+fn other_collectors_do_not_record_writes() {
+    for gc in ["none", "stop-and-copy"] {
+        let assembly = assembly(&[&format!("--gc={}", gc)]);
+
+        assert!(!assembly.contains("_GenGC_Assign"), "{}", gc);
+    }
+}
+
+#[test]
+fn memory_manager_words() {
+    for (args, initializer, collector, test) in [
+        (&[][..], "_NoGC_Init", "_NoGC_Collect", "0x00000000"),
+        (&["--gc=none"], "_NoGC_Init", "_NoGC_Collect", "0x00000000"),
+        (
+            &["--gc=generational"],
+            "_GenGC_Init",
+            "_GenGC_Collect",
+            "0x00000000",
+        ),
+        (&["-g"], "_GenGC_Init", "_GenGC_Collect", "0x00000000"),
+        (
+            &["--gc=stop-and-copy"],
+            "_SCC_Init",
+            "_SCC_Collect",
+            "0x00000000",
+        ),
+        (&["--gc-test"], "_NoGC_Init", "_NoGC_Collect", "0x00000001"),
+        (
+            &["--gc=generational", "--gc-test"],
+            "_GenGC_Init",
+            "_GenGC_Collect",
+            "0x00000001",
+        ),
+    ] {
+        let assembly = assembly(args);
+
+        assert_eq!(
+            [
+                word(&assembly, "_MemMgr_INITIALIZER"),
+                word(&assembly, "_MemMgr_COLLECTOR"),
+                word(&assembly, "_MemMgr_TEST"),
+            ],
+            [
+                format!(".word {}", initializer),
+                format!(".word {}", collector),
+                format!(".word {}", test),
+            ],
+            "{:?}",
+            args
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use coolc::builtins::initialize_classes;
+use coolc::diagnostics::{Diagnostics, ErrorCode};
+use coolc::lexer::tokenize;
+use coolc::parser::parse;
+use coolc::semant::semant;
+use coolc::{compile, CompileOptions, Source, Stage, Target};
+
+const HELLO: &str = "class Main inherits IO {
+  main() : Object { out_string(greeting()) };
+};
+";
+
+const GREETING: &str = "class Main2 { };
+class IO2 inherits IO { };
+";
+
+fn sources<'a>(texts: &[(&'a str, &'a str)]) -> Vec<Source<'a>> {
+    texts
+        .iter()
+        .map(|(name, text)| Source { name, text })
+        .collect()
+}
+
+#[test]
+fn compile_returns_assembly() {
+    let compilation = compile(
+        &sources(&[("hello.cl", "class Main { main() : Object { 0 }; };\n")]),
+        &CompileOptions::default(),
+    )
+    .unwrap();
+
+    assert!(!compilation.diagnostics.has_errors());
+
+    let assembly = compilation.assembly.unwrap();
+    assert!(assembly.contains("Main.main:"));
+    assert!(assembly.contains("file_name_00000001:"));
+}
+
+// Errors in each source are reported with the name of the source
+#[test]
+fn compile_reports_errors_by_source_name() {
+    let compilation = compile(
+        &sources(&[("hello.cl", HELLO), ("greeting.cl", GREETING)]),
+        &CompileOptions::default(),
+    )
+    .unwrap();
+
+    assert!(compilation.assembly.is_none());
+
+    let diagnostics = compilation.diagnostics.diagnostics();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, ErrorCode::UndefinedMethod);
+    assert_eq!(diagnostics[0].loc.as_ref().unwrap().file_name, "hello.cl");
+}
+
+#[test]
+fn compile_can_stop_early() {
+    for stop_after in [Stage::Parse, Stage::Check] {
+        let options = CompileOptions {
+            stop_after,
+            ..CompileOptions::default()
+        };
+        let compilation = compile(&sources(&[("hello.cl", HELLO)]), &options).unwrap();
+
+        assert!(compilation.assembly.is_none());
+        assert_eq!(
+            compilation.diagnostics.has_errors(),
+            stop_after == Stage::Check
+        );
+    }
+}
+
+#[test]
+fn compile_for_bytecode_returns_bytes() {
+    let options = CompileOptions {
+        target: Target::Bytecode,
+        ..CompileOptions::default()
+    };
+    let compilation = compile(
+        &sources(&[("hello.cl", "class Main { main() : Object { 0 }; };\n")]),
+        &options,
+    )
+    .unwrap();
+
+    assert!(compilation.assembly.is_none());
+    assert!(!compilation.bytecode.unwrap().is_empty());
+}
+
+// The stages can also be run one at a time
+#[test]
+fn stages_run_separately() {
+    let mut diagnostics = Diagnostics::new(0);
+    let mut classes = initialize_classes();
+
+    let tokens = tokenize(HELLO, "hello.cl", &mut diagnostics);
+    parse(tokens, "hello.cl", 1, &mut classes, &mut diagnostics);
+    assert!(!diagnostics.has_errors());
+
+    let tokens = tokenize(
+        "class Main2 { }; class Main inherits IO { greeting() : String { \"hi\" }; };",
+        "greeting.cl",
+        &mut diagnostics,
+    );
+    parse(tokens, "greeting.cl", 2, &mut classes, &mut diagnostics);
+
+    // Main is defined twice
+    assert_eq!(diagnostics.diagnostics()[0].code, ErrorCode::ClassRedefined);
+
+    let mut diagnostics = Diagnostics::new(0);
+    let mut classes = initialize_classes();
+    let tokens = tokenize(HELLO, "hello.cl", &mut diagnostics);
+    parse(tokens, "hello.cl", 1, &mut classes, &mut diagnostics);
+
+    // greeting is not defined
+    assert!(semant(classes, &mut diagnostics).is_none());
+    assert_eq!(
+        diagnostics.diagnostics()[0].code,
+        ErrorCode::UndefinedMethod
+    );
+}
@@ -0,0 +1,52 @@
+mod common;
+
+use common::{coolc, stderr, stdout};
+
+// A program whose last expressions are on line 70002 (beyond 16 bits)
+// Blank lines keep the test fast.
+fn long_program(expr: &str) -> String {
+    format!(
+        "class Main {{\n  x : Main;\n  main() : Object {{{}\n    {}\n  }};\n}};\n",
+        "\n".repeat(69998),
+        expr
+    )
+}
+
+#[test]
+fn runtime_errors_report_lines_beyond_16_bits() {
+    let source = long_program("x.main()");
+
+    for target in ["mips", "riscv32", "bytecode"] {
+        let output = coolc(&["run", &format!("--target={}", target), "-"], &source);
+
+        assert_eq!(output.status.code(), Some(1), "{}", target);
+        assert_eq!(
+            stdout(&output),
+            "<stdin>:70002: Dispatch to void.\n",
+            "{}",
+            target
+        );
+    }
+
+    let output = coolc(&["interp", "-"], &source);
+    assert_eq!(stdout(&output), "<stdin>:70002: Dispatch to void.\n");
+}
+
+// The line number is loaded with all 32 bits
+#[test]
+fn mips_loads_line_numbers_with_32_bit_li() {
+    let output = coolc(&["-o", "-", "-"], &long_program("x.main()"));
+
+    assert!(output.status.success());
+    assert!(stdout(&output).contains("\tli $t1 0x00011172\n\tjal _dispatch_abort\n"));
+}
+
+#[test]
+fn compile_errors_report_lines_beyond_16_bits() {
+    let output = coolc(
+        &["--error-format=json", "-"],
+        &long_program("x.undefined()"),
+    );
+
+    assert!(stderr(&output).starts_with(r#"{"file":"<stdin>","line":70002,"column":5,"#));
+}
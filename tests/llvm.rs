@@ -0,0 +1,115 @@
+mod common;
+
+use std::fs;
+use std::process::Command;
+
+use regex::Regex;
+
+use common::{cc_run, coolc, coolc_in, has_cc, runtime, stderr, stdout, temp_dir};
+
+const PROGRAM: &str = "class A inherits IO {
+  f(n : Int) : Int { if n = 0 then 0 else n + f(n - 1) fi };
+};
+class B inherits A { };
+class Main inherits IO {
+  x : A <- new B;
+  main() : Object {
+    case x of
+      b : B => out_int(b.f(in_int())).out_string(\"\\n\");
+      a : A => out_string(\"A\\n\");
+      o : Object => abort();
+    esac
+  };
+};
+";
+
+fn llvm_ir() -> String {
+    let output = coolc(&["--emit=llvm-ir", "-o", "-", "-"], PROGRAM);
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    stdout(&output)
+}
+
+// The arguments llc needs for the IR, or None if there is no llc
+// LLVM 14 only reads opaque pointers when asked to.
+fn llc_args() -> Option<Vec<&'static str>> {
+    let output = Command::new("llc").arg("--version").output().ok()?;
+    let version = stdout(&output);
+
+    if version.contains("LLVM version 14.") {
+        Some(vec!["-opaque-pointers", "-relocation-model=pic"])
+    } else {
+        Some(vec!["-relocation-model=pic"])
+    }
+}
+
+#[test]
+fn case_is_a_switch_on_the_tag() {
+    let ir = llvm_ir();
+    let switch =
+        Regex::new(r"(?m)^  (%t\d+) = load i64, ptr %t\d+\n  switch i64 (%t\d+),").unwrap();
+    let captures = switch.captures(&ir).unwrap();
+
+    assert_eq!(captures[1], captures[2]);
+}
+
+#[test]
+fn every_class_has_a_dispatch_table() {
+    let ir = llvm_ir();
+
+    for class_name in ["Object", "IO", "Int", "Bool", "String", "A", "B", "Main"] {
+        assert!(
+            ir.contains(&format!("\n@{}_dispTab = constant [", class_name)),
+            "{}",
+            class_name
+        );
+    }
+}
+
+#[test]
+fn pointers_are_opaque() {
+    let typed_pointer = Regex::new(r"(i\d+|ptr|%[\w.]+|\]|\)|>)\*").unwrap();
+
+    assert!(!typed_pointer.is_match(&llvm_ir()));
+}
+
+// The program compiled by llc writes what coolc run writes
+#[test]
+fn compiled_program_runs() {
+    let llc_args = match llc_args() {
+        Some(llc_args) if has_cc() => llc_args,
+        _ => {
+            eprintln!("Skipped: there is no llc or no C compiler");
+            return;
+        }
+    };
+
+    let dir = temp_dir("llvm_compiled_program_runs");
+    fs::write(dir.join("test.cl"), PROGRAM).unwrap();
+
+    let compiled = coolc_in(&dir, &["--emit=llvm-ir", "test.cl"], "");
+    assert!(compiled.status.success(), "{}", stderr(&compiled));
+
+    let llc = Command::new("llc")
+        .current_dir(&dir)
+        .args(&llc_args)
+        .args(["test.ll", "-o", "test.s"])
+        .output()
+        .unwrap();
+    assert!(llc.status.success(), "{}", stderr(&llc));
+
+    for input in ["10\n", ""] {
+        let native = cc_run(&dir, &["test.s", &runtime("x86_64.c")], input);
+        let mips = coolc_in(&dir, &["run", "test.cl"], input);
+
+        assert_eq!(stdout(&native), stdout(&mips));
+        assert_eq!(stderr(&native), stderr(&mips));
+        assert_eq!(native.status.code(), mips.status.code());
+    }
+
+    let output = cc_run(&dir, &["test.s", &runtime("x86_64.c")], "10\n");
+    assert_eq!(
+        stdout(&output),
+        "55\n\nCOOL program successfully executed\n"
+    );
+}
@@ -0,0 +1,214 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use coolc::json::Json;
+
+// A client that talks to coolc-lsp one message at a time
+struct Client {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Client {
+    fn new() -> Client {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_coolc-lsp"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+
+        Client {
+            child,
+            stdin,
+            stdout,
+        }
+    }
+
+    fn send(&mut self, content: &str) {
+        write!(
+            self.stdin,
+            "Content-Length: {}\r\n\r\n{}",
+            content.len(),
+            content
+        )
+        .unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> Json {
+        let mut content_length = 0;
+
+        loop {
+            let mut line = String::new();
+            self.stdout.read_line(&mut line).unwrap();
+
+            match line.trim_end().split_once(": ") {
+                Some(("Content-Length", len)) => content_length = len.parse().unwrap(),
+                _ if line.trim_end().is_empty() => break,
+                _ => {}
+            }
+        }
+
+        let mut content = vec![0u8; content_length];
+        self.stdout.read_exact(&mut content).unwrap();
+
+        Json::parse(&String::from_utf8(content).unwrap()).unwrap()
+    }
+
+    // Send a request and return its result
+    fn request(&mut self, id: i64, method: &str, params: &str) -> Json {
+        self.send(&format!(
+            r#"{{"jsonrpc":"2.0","id":{},"method":"{}","params":{}}}"#,
+            id, method, params
+        ));
+
+        let response = self.receive();
+        assert_eq!(response.get("id").and_then(Json::as_i64), Some(id));
+
+        response.get("result").cloned().unwrap()
+    }
+
+    fn notify(&mut self, method: &str, params: &str) {
+        self.send(&format!(
+            r#"{{"jsonrpc":"2.0","method":"{}","params":{}}}"#,
+            method, params
+        ));
+    }
+}
+
+const URI: &str = "file:///main.cl";
+
+const TEXT: &str =
+    r#"class Main inherits IO {\n  x : Int <- 1;\n  main() : Object { out_int(x) };\n};\n"#;
+
+fn position(line: i64, character: i64) -> String {
+    format!(
+        r#"{{"textDocument":{{"uri":"{}"}},"position":{{"line":{},"character":{}}}}}"#,
+        URI, line, character
+    )
+}
+
+fn open(client: &mut Client) {
+    client.notify(
+        "textDocument/didOpen",
+        &format!(
+            r#"{{"textDocument":{{"uri":"{}","languageId":"cool","version":1,"text":"{}"}}}}"#,
+            URI, TEXT
+        ),
+    );
+}
+
+fn shutdown(mut client: Client) {
+    assert_eq!(client.request(99, "shutdown", "null"), Json::Null);
+    client.notify("exit", "null");
+
+    assert!(client.child.wait().unwrap().success());
+}
+
+#[test]
+fn session() {
+    let mut client = Client::new();
+
+    let result = client.request(1, "initialize", r#"{"capabilities":{}}"#);
+    assert!(result.get("capabilities").is_some());
+    client.notify("initialized", "{}");
+
+    // Opening a document publishes its diagnostics
+    open(&mut client);
+    let diagnostics = client.receive();
+    assert_eq!(
+        diagnostics.get("method").and_then(Json::as_str),
+        Some("textDocument/publishDiagnostics")
+    );
+    assert_eq!(
+        diagnostics.get("params").unwrap().get("diagnostics"),
+        Some(&Json::Array(Vec::new()))
+    );
+
+    // x in out_int(x)
+    let hover = client.request(2, "textDocument/hover", &position(2, 28));
+    assert_eq!(
+        hover.get("contents").unwrap().get("value"),
+        Some(&Json::String("x : Int".to_string()))
+    );
+
+    let definition = client.request(3, "textDocument/definition", &position(2, 28));
+    assert_eq!(
+        definition.get("range").unwrap().to_string(),
+        r#"{"start":{"line":1,"character":2},"end":{"line":1,"character":14}}"#
+    );
+
+    let completion = client.request(4, "textDocument/completion", &position(2, 20));
+    let labels: Vec<&str> = completion
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|item| item.get("label").and_then(Json::as_str))
+        .collect();
+    assert!(labels.contains(&"out_int") && labels.contains(&"main"));
+
+    // A change is analyzed again
+    client.notify(
+        "textDocument/didChange",
+        &format!(
+            r#"{{"textDocument":{{"uri":"{}","version":2}},"contentChanges":[{{"text":"{}"}}]}}"#,
+            URI,
+            TEXT.replace("out_int(x)", "out_int(y)")
+        ),
+    );
+    let diagnostics = client.receive();
+    assert_eq!(
+        diagnostics
+            .get("params")
+            .unwrap()
+            .get("diagnostics")
+            .unwrap()
+            .to_string(),
+        r#"[{"range":{"start":{"line":2,"character":28},"end":{"line":2,"character":29}},"severity":1,"code":"E0301","source":"coolc","message":"Undeclared identifier y.","relatedInformation":[]}]"#
+    );
+
+    shutdown(client);
+}
+
+// A message that is not JSON is answered with an error, and the next message is served
+#[test]
+fn malformed_message_is_a_parse_error() {
+    let mut client = Client::new();
+
+    client.request(1, "initialize", r#"{"capabilities":{}}"#);
+    client.send("{\"jsonrpc\":\"2.0\",\"id\":2,");
+
+    let response = client.receive();
+    assert_eq!(response.get("id"), Some(&Json::Null));
+    assert_eq!(
+        response.get("error").unwrap().get("code"),
+        Some(&Json::Number(-32700))
+    );
+
+    open(&mut client);
+    client.receive();
+    let hover = client.request(3, "textDocument/hover", &position(2, 28));
+    assert!(hover.get("contents").is_some());
+
+    shutdown(client);
+}
+
+// After headers that cannot be read, the next message cannot be found
+#[test]
+fn bad_header_ends_the_session() {
+    let mut client = Client::new();
+
+    write!(client.stdin, "Content-Length: many\r\n\r\n{{}}").unwrap();
+    client.stdin.flush().unwrap();
+
+    let output = client.child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "Bad Content-Length header: Content-Length: many\n"
+    );
+}
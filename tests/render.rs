@@ -0,0 +1,144 @@
+mod common;
+
+use common::{coolc, stderr};
+
+// The offending line is shown with the span underlined, and tabs are expanded
+#[test]
+fn error_shows_line_and_carets() {
+    let output = coolc(
+        &["--color=never", "-"],
+        "class Main {\n\tmain() : Object { 0 };\n\tx : Int <- 1 + \"a\";\n};\n",
+    );
+
+    assert_eq!(
+        stderr(&output),
+        "error[E0305]: non-Int arguments: Int + String
+ --> <stdin>:3:13
+  |
+3 |     x : Int <- 1 + \"a\";
+  |                ^^^^^^^
+
+Compilation halted due to errors.
+"
+    );
+}
+
+// A note has its own snippet, and the gutter fits the widest line number
+#[test]
+fn note_shows_its_own_snippet() {
+    let output = coolc(
+        &["--color=never", "-"],
+        "class Main { main() : Object { 0 }; };\nclass A { };\n\n\n\n\n\n\n\nclass A { };\n",
+    );
+
+    assert_eq!(
+        stderr(&output),
+        "error[E0100]: Class A was previously defined.
+  --> <stdin>:10:1
+   |
+10 | class A { };
+   | ^^^^^^^^^^^
+note: Class A previously defined here.
+  --> <stdin>:2:1
+   |
+ 2 | class A { };
+   | ^^^^^^^^^^^
+
+Compilation halted due to errors.
+"
+    );
+}
+
+// An error about the whole program has no snippet
+#[test]
+fn error_without_location_has_no_snippet() {
+    let output = coolc(&["--color=never", "-"], "class A { };\n");
+
+    assert_eq!(
+        stderr(&output),
+        "error[E0104]: Class Main is not defined.
+
+Compilation halted due to errors.
+"
+    );
+}
+
+#[test]
+fn color_is_only_used_when_asked_for_or_on_a_terminal() {
+    let source = "class A { };\n";
+
+    let output = coolc(&["--color=always", "-"], source);
+    assert!(stderr(&output)
+        .starts_with("\x1b[1;31merror[E0104]\x1b[0m\x1b[1m: Class Main is not defined.\x1b[0m\n"));
+
+    let output = coolc(&["--color=never", "-"], source);
+    assert!(!stderr(&output).contains('\x1b'));
+
+    // Standard error is a pipe here
+    let output = coolc(&["--color=auto", "-"], source);
+    assert!(!stderr(&output).contains('\x1b'));
+}
+
+// Each error is one line of JSON and nothing else is written
+#[test]
+fn json_has_one_line_per_error() {
+    let output = coolc(
+        &["--error-format=json", "-"],
+        "class Main inherits IO {
+  main() : Object { out_strin(\"x\") };
+  x : Int <- \"a\";
+  y : Foo;
+  z : Int <- 1 + true;
+};
+",
+    );
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stderr(&output),
+        r#"{"file":"<stdin>","line":4,"column":3,"byte_start":83,"byte_end":90,"severity":"error","code":"E0102","message":"Class Foo of attribute y is undefined.","notes":[]}
+{"file":"<stdin>","line":3,"column":3,"byte_start":65,"byte_end":79,"severity":"error","code":"E0300","message":"Type String of assigned expression does not conform to declared type Int of identifier x.","notes":[]}
+{"file":"<stdin>","line":5,"column":14,"byte_start":105,"byte_end":113,"severity":"error","code":"E0305","message":"non-Int arguments: Int + Bool","notes":[]}
+{"file":"<stdin>","line":2,"column":21,"byte_start":45,"byte_end":59,"severity":"error","code":"E0302","message":"Dispatch to undefined method out_strin.","notes":[]}
+"#
+    );
+}
+
+#[test]
+fn json_notes_are_nested() {
+    let output = coolc(
+        &["--error-format=json", "-"],
+        "class Main { main() : Object { 0 }; };\nclass A { };\nclass A { };\n",
+    );
+
+    assert_eq!(
+        stderr(&output),
+        r#"{"file":"<stdin>","line":3,"column":1,"byte_start":52,"byte_end":63,"severity":"error","code":"E0100","message":"Class A was previously defined.","notes":[{"file":"<stdin>","line":2,"column":1,"byte_start":39,"byte_end":50,"message":"Class A previously defined here."}]}
+"#
+    );
+}
+
+#[test]
+fn json_error_without_location_has_nulls() {
+    let output = coolc(&["--error-format=json", "-"], "class A { };\n");
+
+    assert_eq!(
+        stderr(&output),
+        r#"{"file":null,"line":null,"column":null,"byte_start":null,"byte_end":null,"severity":"error","code":"E0104","message":"Class Main is not defined.","notes":[]}
+"#
+    );
+}
+
+#[test]
+fn json_strings_are_escaped() {
+    let output = coolc(
+        &["--error-format=json", "-"],
+        "class Main { main() : Object { 0 }; x : Int; }; \\\n",
+    );
+
+    assert_eq!(
+        stderr(&output),
+        r#"{"file":"<stdin>","line":1,"column":49,"byte_start":48,"byte_end":49,"severity":"error","code":"E0003","message":"Unexpected character : '\\'","notes":[]}
+"#
+    );
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use common::{coolc, stdout};
+
+// A session read from standard input (without prompts)
+// A bad entry is reported and the session goes on. Nothing after :quit is read.
+#[test]
+fn session() {
+    let output = coolc(
+        &["repl", "--color=never"],
+        "class A { f() : Int { 41 + 1 }; };
+(new A).f()
+:type \"ab\".concat(\"c\")
+new B
+1 + 2
+:reset
+(new A).f()
+:quit
+1
+",
+    );
+
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "Defined class A.
+42 : Int
+String
+error[E0102]: 'new' used with undefined class B.
+ --> <4>:1:1
+  |
+1 | new B
+  | ^^^^^
+
+3 : Int
+error[E0102]: 'new' used with undefined class A.
+ --> <7>:1:2
+  |
+1 | (new A).f()
+  |  ^^^^^
+
+"
+    );
+}
@@ -0,0 +1,51 @@
+mod common;
+
+use common::{coolc, static_type, stdout};
+
+const RELATED_CLASSES: &str = "
+class A { };
+class B inherits A { };
+class C inherits B { };
+";
+
+// The type of a conditional is the least upper bound of its branches
+#[test]
+fn cond_of_related_classes_has_parent_type() {
+    for (then_expr, else_expr) in [("new B", "new A"), ("new A", "new B"), ("new C", "new A")] {
+        let source = format!(
+            "{}class Main {{ main() : Object {{ if true then {} else {} fi }}; }};",
+            RELATED_CLASSES, then_expr, else_expr
+        );
+        let output = coolc(&["--dump-typed-ast", "-"], &source);
+
+        assert!(output.status.success());
+        assert_eq!(static_type(&stdout(&output), "_cond"), "A");
+    }
+}
+
+// The type of a case is the least upper bound of its branches
+#[test]
+fn case_of_related_classes_has_parent_type() {
+    let source = format!(
+        "{}class Main {{ main() : Object {{ case 0 of c : C => new C; a : A => new A; b : B => new B; esac }}; }};",
+        RELATED_CLASSES
+    );
+    let output = coolc(&["--dump-typed-ast", "-"], &source);
+
+    assert!(output.status.success());
+    assert_eq!(static_type(&stdout(&output), "_typcase"), "A");
+}
+
+// Unrelated classes meet at a common ancestor
+#[test]
+fn cond_of_sibling_classes_has_common_ancestor_type() {
+    let source = format!(
+        "{}class D inherits A {{ }};
+class Main {{ main() : Object {{ if true then new C else new D fi }}; }};",
+        RELATED_CLASSES
+    );
+    let output = coolc(&["--dump-typed-ast", "-"], &source);
+
+    assert!(output.status.success());
+    assert_eq!(static_type(&stdout(&output), "_cond"), "A");
+}
@@ -0,0 +1,109 @@
+use coolc::ast::{Expression, TypeID};
+use coolc::diagnostics::{Diagnostics, ErrorCode};
+use coolc::lexer::tokenize;
+use coolc::span::Span;
+use coolc::{compile, parse_sources, CompileOptions, Source, Stage};
+
+// A comment before the class and a string that continues on the next line
+const TEXT: &str = "(* a
+ b *) class Main {
+  x : Int <- 1 + \"a\\
+b\".length();
+  f(a : Int) : Int { case a of i : Int => i; esac };
+};
+";
+
+#[test]
+fn tokens_have_byte_offsets_lines_and_columns() {
+    let mut diagnostics = Diagnostics::new(0);
+    let spans: Vec<Span> = tokenize(TEXT, "test.cl", &mut diagnostics)
+        .iter()
+        .map(|token| token.get_span())
+        .collect();
+
+    assert!(!diagnostics.has_errors());
+
+    // class Main {
+    assert_eq!(spans[0], Span::new(11, 16, 2, 7));
+    assert_eq!(spans[1], Span::new(17, 21, 2, 13));
+    assert_eq!(spans[2], Span::new(22, 23, 2, 18));
+
+    // The string starts on line 3 and the '.' after it is on line 4
+    assert_eq!(spans[9], Span::new(41, 47, 3, 18));
+    assert_eq!(spans[10], Span::new(47, 48, 4, 3));
+
+    // The end of the file is on a line of its own
+    assert_eq!(*spans.last().unwrap(), Span::new(114, 114, 7, 1));
+}
+
+#[test]
+fn ast_nodes_have_spans() {
+    let mut diagnostics = Diagnostics::new(0);
+    let classes = parse_sources(
+        &[Source {
+            name: "test.cl",
+            text: TEXT,
+        }],
+        &mut diagnostics,
+    );
+
+    assert!(!diagnostics.has_errors());
+
+    let class = classes.get(&TypeID::new("Main".to_string())).unwrap();
+    assert_eq!(class.span, Span::new(11, 112, 2, 7));
+
+    // x : Int <- 1 + "a\ b".length()
+    let attr = &class.attrs[0];
+    assert_eq!(attr.span, Span::new(26, 56, 3, 3));
+    assert_eq!(attr.init.span(), Span::new(37, 56, 3, 14));
+
+    match &attr.init {
+        Expression::ArithOp { expr_rhs, .. } => {
+            assert_eq!(expr_rhs.span(), Span::new(41, 56, 3, 18))
+        }
+        _ => panic!("Expected an arithmetic expression"),
+    }
+
+    // f(a : Int) : Int { case a of i : Int => i; esac }
+    let method = class.methods.values().next().unwrap();
+    assert_eq!(method.span, Span::new(60, 109, 5, 3));
+    assert_eq!(method.formals[0].span, Span::new(62, 69, 5, 5));
+    assert_eq!(method.expr.span(), Span::new(79, 107, 5, 22));
+
+    match &method.expr {
+        Expression::TypeCase { branches, .. } => {
+            assert_eq!(branches[0].span, Span::new(89, 101, 5, 32));
+            assert_eq!(branches[0].expr.span(), Span::new(100, 101, 5, 43));
+        }
+        _ => panic!("Expected a case expression"),
+    }
+}
+
+// A type error points at the sub-expression that is wrong, not at the whole attribute
+#[test]
+fn semantic_errors_point_at_the_sub_expression() {
+    let text = "class Main {
+  main() : Object { 0 };
+  x : Int <- 1 + (2 + \"a\");
+};
+";
+    let options = CompileOptions {
+        stop_after: Stage::Check,
+        ..CompileOptions::default()
+    };
+    let compilation = compile(
+        &[Source {
+            name: "test.cl",
+            text,
+        }],
+        &options,
+    )
+    .unwrap();
+
+    let diagnostic = &compilation.diagnostics.diagnostics()[0];
+    assert_eq!(diagnostic.code, ErrorCode::NonIntOperand);
+    assert_eq!(
+        diagnostic.loc.as_ref().unwrap().span,
+        Span::new(56, 63, 3, 19)
+    );
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use std::collections::VecDeque;
+use std::fs;
+use std::io::BufReader;
+
+use common::{coolc, coolc_in, stderr, stdout, temp_dir};
+use coolc::diagnostics::Diagnostics;
+use coolc::lexer::{tokenize, tokenize_reader};
+use coolc::token::Token;
+
+const TEXT: &str = "class Main inherits IO {
+  (* a comment
+     over two lines *)
+  main() : Object { out_string(\"a long \\
+string\\n\") };
+};
+";
+
+// Each token with its span
+fn describe(tokens: &VecDeque<Token>) -> Vec<String> {
+    tokens
+        .iter()
+        .map(|token| format!("{:?} {}", token.get_span(), token))
+        .collect()
+}
+
+// A reader with a small buffer gives the same tokens as the whole string
+#[test]
+fn tokenize_reader_matches_tokenize() {
+    let mut diagnostics = Diagnostics::new(0);
+    let expected = describe(&tokenize(TEXT, "test.cl", &mut diagnostics));
+
+    let reader = BufReader::with_capacity(4, TEXT.as_bytes());
+    let tokens = tokenize_reader(reader, "test.cl", &mut diagnostics).unwrap();
+
+    assert!(!diagnostics.has_errors());
+    assert_eq!(describe(&tokens), expected);
+}
+
+#[test]
+fn dash_reads_standard_input() {
+    let output = coolc(&["run", "-"], TEXT);
+
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "a long \nstring\n\nCOOL program successfully executed\n"
+    );
+}
+
+#[test]
+fn standard_input_can_only_be_read_once() {
+    let output = coolc(&["run", "-", "-"], TEXT);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("Standard input can only be read once"));
+}
+
+// Errors in standard input are reported as <stdin>
+#[test]
+fn errors_in_standard_input_are_named_stdin() {
+    let output = coolc(
+        &["--color=never", "-"],
+        "class Main { main() : Object { x }; };\n",
+    );
+
+    assert!(stderr(&output).contains(" --> <stdin>:1:32\n"));
+}
+
+// The output is named after standard input if it is the first source
+#[test]
+fn output_from_standard_input_is_stdin_s() {
+    let dir = temp_dir("output_from_standard_input_is_stdin_s");
+
+    let output = coolc_in(&dir, &["-"], TEXT);
+
+    assert!(output.status.success());
+    assert_eq!(stderr(&output), "Successfully wrote stdin.s.\n");
+    assert!(fs::read_to_string(dir.join("stdin.s"))
+        .unwrap()
+        .contains("Main.main:"));
+}
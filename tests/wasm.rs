@@ -0,0 +1,233 @@
+mod common;
+
+use std::fs;
+use std::thread;
+
+use coolc::{compile, CompileOptions, Source, Target};
+use wasmi::core::TrapCode;
+use wasmi::{Caller, Engine, Error, Extern, Linker, Module, Store};
+
+use common::{coolc_in, stderr, stdout, temp_dir};
+
+const NAME: &str = "test.cl"; // The name of the source in runtime errors
+
+// What the host of the program keeps (see runtime/wasm.mjs)
+struct Host {
+    input: Vec<u8>,
+    input_pos: usize,
+    line: Vec<u8>, // The line that was read last
+    output: Vec<u8>,
+    errors: String,
+}
+
+fn memory<'a>(caller: &'a Caller<Host>) -> &'a [u8] {
+    caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .unwrap()
+        .data(caller)
+}
+
+// Compile a program with --target=wasm and convert it to binary
+fn assemble(text: &str) -> Vec<u8> {
+    let options = CompileOptions {
+        target: Target::Wasm,
+        ..CompileOptions::default()
+    };
+    let compilation = compile(&[Source { name: NAME, text }], &options).unwrap();
+
+    wat::parse_str(compilation.assembly.unwrap()).unwrap()
+}
+
+// Run a module with the imports of runtime/wasm.mjs
+// Returns the output, the errors and whether the program finished.
+fn run(wasm: &[u8], input: &str) -> (String, String, bool) {
+    let engine = Engine::default();
+    let module = Module::new(&engine, wasm).unwrap();
+    let mut store = Store::new(
+        &engine,
+        Host {
+            input: input.as_bytes().to_vec(),
+            input_pos: 0,
+            line: Vec::new(),
+            output: Vec::new(),
+            errors: String::new(),
+        },
+    );
+    let mut linker = <Linker<Host>>::new(&engine);
+
+    linker
+        .func_wrap(
+            "cool",
+            "out",
+            |mut caller: Caller<Host>, ptr: i32, len: i32| {
+                let bytes = memory(&caller)[ptr as usize..(ptr + len) as usize].to_vec();
+                caller.data_mut().output.extend(bytes);
+            },
+        )
+        .unwrap()
+        .func_wrap(
+            "cool",
+            "fail",
+            |mut caller: Caller<Host>, ptr: i32, len: i32| -> Result<(), Error> {
+                let msg =
+                    String::from_utf8_lossy(&memory(&caller)[ptr as usize..(ptr + len) as usize])
+                        .into_owned();
+                caller.data_mut().errors += &format!("{}\n", msg);
+                Err(Error::i32_exit(1))
+            },
+        )
+        .unwrap()
+        .func_wrap("cool", "halt", || -> Result<(), Error> {
+            Err(Error::i32_exit(1))
+        })
+        .unwrap()
+        .func_wrap("cool", "read_line", |mut caller: Caller<Host>| -> i32 {
+            let host = caller.data_mut();
+            let rest = &host.input[host.input_pos..];
+            let end = rest.iter().position(|&c| c == b'\n').unwrap_or(rest.len());
+
+            host.line = rest[..end].to_vec();
+            host.input_pos = (host.input_pos + end + 1).min(host.input.len());
+            host.line.len() as i32
+        })
+        .unwrap()
+        .func_wrap("cool", "copy_line", |mut caller: Caller<Host>, ptr: i32| {
+            let memory = caller
+                .get_export("memory")
+                .and_then(Extern::into_memory)
+                .unwrap();
+            let (data, host) = memory.data_and_store_mut(&mut caller);
+
+            data[ptr as usize..ptr as usize + host.line.len()].copy_from_slice(&host.line);
+        })
+        .unwrap();
+
+    let instance = linker
+        .instantiate(&mut store, &module)
+        .unwrap()
+        .start(&mut store)
+        .unwrap();
+    let main = instance.get_typed_func::<(), ()>(&store, "main").unwrap();
+
+    let finished = match main.call(&mut store, ()) {
+        Ok(()) => true,
+        Err(err) if err.i32_exit_status() == Some(1) => false,
+        Err(err) if err.as_trap_code() == Some(TrapCode::StackOverflow) => {
+            store.data_mut().errors += "Stack overflow\n";
+            false
+        }
+        Err(err) => panic!("{}", err),
+    };
+
+    let host = store.data();
+
+    (
+        String::from_utf8_lossy(&host.output).into_owned(),
+        host.errors.clone(),
+        finished,
+    )
+}
+
+// The module is valid, and it writes what coolc run writes
+// expected is the end of the output and errors.
+fn check(text: &str, input: &str, expected: &str) {
+    let wasm = assemble(text);
+
+    wasmparser::Validator::new().validate_all(&wasm).unwrap();
+
+    let (output, errors, finished) = run(&wasm, input);
+
+    // The program is read from a file since standard input is the input of the program
+    let dir = temp_dir(&format!("wasm_{}", thread::current().name().unwrap()));
+    fs::write(dir.join(NAME), text).unwrap();
+    let mips = coolc_in(&dir, &["run", NAME], input);
+
+    assert_eq!(output, stdout(&mips));
+    assert_eq!(errors, stderr(&mips));
+    assert_eq!(finished, mips.status.success());
+    assert!(
+        format!("{}{}", output, errors).ends_with(expected),
+        "{}{}",
+        output,
+        errors
+    );
+}
+
+#[test]
+fn program_reads_input_and_writes_output() {
+    check(
+        "class Main inherits IO {
+  main() : Object {
+    let name : String <- in_string(), n : Int <- in_int() in
+      out_string(\"Hello, \".concat(name).concat(\"\\n\")).out_int(n * 2).out_string(\"\\n\")
+  };
+};
+",
+        "COOL\n21\n",
+        "Hello, COOL\n42\n\nCOOL program successfully executed\n",
+    );
+}
+
+#[test]
+fn dispatch_to_void() {
+    check(
+        "class Main {\n  x : Main;\n  main() : Object { x.main() };\n};\n",
+        "",
+        "test.cl:3: Dispatch to void.\n",
+    );
+}
+
+#[test]
+fn case_on_void() {
+    check(
+        "class Main {\n  x : Main;\n  main() : Object { case x of o : Object => o; esac };\n};\n",
+        "",
+        "test.cl:3: Match on void in case statement.\n",
+    );
+}
+
+#[test]
+fn case_without_matching_branch() {
+    check(
+        "class Main {\n  main() : Object { case 1 of s : String => s; esac };\n};\n",
+        "",
+        "No match in case statement for Class Int\n",
+    );
+}
+
+#[test]
+fn abort() {
+    check(
+        "class Main inherits IO {\n  main() : Object { { out_string(\"before\\n\"); abort(); } };\n};\n",
+        "",
+        "before\nAbort called from class Main\n",
+    );
+}
+
+#[test]
+fn substr_out_of_range() {
+    check(
+        "class Main {\n  main() : Object { \"abc\".substr(2, 5) };\n};\n",
+        "",
+        "Length to substr too long\nExecution aborted.\n",
+    );
+}
+
+#[test]
+fn division_by_zero() {
+    check(
+        "class Main {\n  main() : Object { 1 / 0 };\n};\n",
+        "",
+        "Division by zero\n",
+    );
+}
+
+#[test]
+fn arithmetic_overflow() {
+    check(
+        "class Main {\n  main() : Object { 2147483647 + 1 };\n};\n",
+        "",
+        "Arithmetic overflow\n",
+    );
+}